@@ -0,0 +1,206 @@
+// Clock widget with calendar and world clock popover
+//
+// Clicking the clock opens a popover above the app bar showing the current
+// month and the time in each time zone listed under [app_bar.clock]. While
+// open, the calendar takes keyboard focus: arrow keys move the selected day,
+// Home/End jump within the month, Tab/Shift+Tab change the month and Escape
+// closes the popover. Colors follow the theme.
+
+use compositor_utils::prelude::*;
+use config::{ClockConfig, SurfaceClass, ThemeConfig};
+use std::time::{SystemTime, UNIX_EPOCH};
+use ui_framework::calendar::{CalendarPopover, Date, WeekStart};
+use ui_framework::focus::{FocusAction, NavigationKey};
+use ui_framework::styling::{FocusIndicatorStyle, PopoverStyle};
+use ui_framework::timezone::{LocalTime, TimeZone};
+
+/// Additional time zone listed in the popover
+#[derive(Debug, Clone)]
+pub struct WorldClock {
+    pub label: String,
+    pub zone: TimeZone,
+}
+
+/// One world clock line as shown in the popover
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorldClockLine {
+    pub label: String,
+    /// e.g. "14:05"
+    pub time: String,
+    /// e.g. "EDT"
+    pub abbreviation: String,
+    /// Days ahead of (or behind, when negative) the local date
+    pub day_offset: i64,
+}
+
+/// Clock shown in the app bar and its popover
+#[derive(Debug)]
+pub struct ClockWidget {
+    week_start: WeekStart,
+    local: TimeZone,
+    world_clocks: Vec<WorldClock>,
+    popover: Option<CalendarPopover>,
+    style: PopoverStyle,
+    /// Date, hour and minute last shown in the app bar
+    shown: Option<(Date, u32, u32)>,
+}
+
+impl ClockWidget {
+    /// Create a clock for the system time zone
+    pub fn new(config: &ClockConfig, theme: &ThemeConfig) -> Self {
+        let mut widget = Self {
+            week_start: WeekStart::default(),
+            local: TimeZone::local(),
+            world_clocks: Vec::new(),
+            popover: None,
+            style: PopoverStyle::default(),
+            shown: None,
+        };
+        widget.set_config(config);
+        widget.set_theme(theme);
+        widget
+    }
+
+    /// Apply new clock settings, loading the world clock time zones
+    ///
+    /// Zones missing from the tz database are skipped with a warning.
+    pub fn set_config(&mut self, config: &ClockConfig) {
+        self.week_start = match config.week_start {
+            config::WeekStart::Monday => WeekStart::Monday,
+            config::WeekStart::Sunday => WeekStart::Sunday,
+        };
+        self.world_clocks = config
+            .world_clocks
+            .iter()
+            .filter_map(|clock| match TimeZone::load(&clock.time_zone) {
+                Ok(zone) => Some(WorldClock { label: clock.display_label(), zone }),
+                Err(e) => {
+                    warn!("Skipping world clock: {}", e);
+                    None
+                }
+            })
+            .collect();
+        if let Some(popover) = &mut self.popover {
+            *popover = CalendarPopover::new(popover.today(), self.week_start);
+        }
+    }
+
+    /// Style the popover after the theme
+    pub fn set_theme(&mut self, theme: &ThemeConfig) {
+        let focus = FocusIndicatorStyle {
+            color: theme.focus_ring.color,
+            width: theme.focus_ring.width,
+            offset: theme.focus_ring.offset,
+        };
+        let corner_radius = theme.surface_style(SurfaceClass::Popup).corner_radius;
+        self.style = PopoverStyle::from_palette(&theme.palette(), corner_radius, focus);
+    }
+
+    /// Re-read the system time zone, e.g. after it was changed
+    pub fn reload_local_zone(&mut self) {
+        self.local = TimeZone::local();
+    }
+
+    /// Local time shown in the app bar
+    pub fn local_time(&self, now: SystemTime) -> LocalTime {
+        self.local.local_time(unix_time(now))
+    }
+
+    /// Open or close the popover on a click; returns whether it is now open
+    pub fn toggle_popover(&mut self, now: SystemTime) -> bool {
+        if self.popover.take().is_none() {
+            let today = self.local_time(now).date;
+            self.popover = Some(CalendarPopover::new(today, self.week_start));
+        }
+        self.popover.is_some()
+    }
+
+    /// Close the popover, e.g. when it loses focus
+    pub fn close_popover(&mut self) {
+        self.popover = None;
+    }
+
+    /// Calendar shown in the open popover
+    pub fn popover(&self) -> Option<&CalendarPopover> {
+        self.popover.as_ref()
+    }
+
+    /// Handle a key while the popover is open; returns whether it was consumed
+    pub fn handle_key(&mut self, key: NavigationKey) -> bool {
+        let Some(popover) = &mut self.popover else {
+            return false;
+        };
+        match popover.navigate(key) {
+            FocusAction::Moved(_) | FocusAction::Activate(_) => true,
+            FocusAction::Dismiss => {
+                self.popover = None;
+                true
+            }
+            FocusAction::Ignored => false,
+        }
+    }
+
+    /// Keep today's marker current across midnight; returns whether it moved
+    pub fn tick(&mut self, now: SystemTime) -> bool {
+        let today = self.local_time(now).date;
+        let Some(popover) = &mut self.popover else {
+            return false;
+        };
+        if popover.today() == today {
+            return false;
+        }
+        popover.set_today(today);
+        true
+    }
+
+    /// Note the time shown in the app bar; returns whether it changed since
+    /// the last call and the clock needs a redraw
+    pub fn time_changed(&mut self, now: SystemTime) -> bool {
+        let time = self.local_time(now);
+        let shown = Some((time.date, time.hour, time.minute));
+        std::mem::replace(&mut self.shown, shown) != shown
+    }
+
+    /// Time in each world clock zone
+    pub fn world_clock_lines(&self, now: SystemTime) -> Vec<WorldClockLine> {
+        let timestamp = unix_time(now);
+        let local_date = self.local.local_time(timestamp).date;
+        self.world_clocks
+            .iter()
+            .map(|clock| {
+                let time_type = clock.zone.local_time_type(timestamp);
+                let time = LocalTime::from_unix(timestamp, time_type.utc_offset);
+                WorldClockLine {
+                    label: clock.label.clone(),
+                    time: format!("{:02}:{:02}", time.hour, time.minute),
+                    abbreviation: time_type.abbreviation,
+                    day_offset: time.date.to_days() - local_date.to_days(),
+                }
+            })
+            .collect()
+    }
+
+    /// Colors and shape of the popover
+    pub fn style(&self) -> PopoverStyle {
+        self.style
+    }
+
+    /// Date selected in the open popover
+    pub fn selected_date(&self) -> Option<Date> {
+        self.popover.as_ref().map(CalendarPopover::selected)
+    }
+}
+
+impl Default for ClockWidget {
+    fn default() -> Self {
+        Self::new(&ClockConfig::default(), &ThemeConfig::default())
+    }
+}
+
+/// Seconds since the Unix epoch, negative before it
+fn unix_time(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+    }
+}
@@ -0,0 +1,55 @@
+// Dock icon bouncing while applications start
+//
+// The compositor reports the app IDs of launched applications that have not
+// shown a window yet. Their dock icons bounce until the app shows a window or
+// the compositor gives up on it.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Duration of one bounce, up and back down
+pub const BOUNCE_PERIOD: Duration = Duration::from_millis(700);
+
+/// Dock icons bouncing for starting applications
+#[derive(Debug, Default)]
+pub struct LaunchBounce {
+    /// When each bouncing app started bouncing
+    started: HashMap<String, Instant>,
+}
+
+impl LaunchBounce {
+    /// Create with no bouncing icons
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bounce the icons of exactly these apps; returns whether any icon
+    /// started or stopped bouncing
+    pub fn set_launching(&mut self, app_ids: &[String], now: Instant) -> bool {
+        let count = self.started.len();
+        self.started.retain(|app_id, _| app_ids.contains(app_id));
+        let mut changed = self.started.len() != count;
+        for app_id in app_ids {
+            if !self.started.contains_key(app_id) {
+                self.started.insert(app_id.clone(), now);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Height of an app's icon above its rest position, from 0.0 to 1.0 of
+    /// the bounce height
+    pub fn offset(&self, app_id: &str, now: Instant) -> f32 {
+        let Some(started) = self.started.get(app_id) else {
+            return 0.0;
+        };
+        let phase = now.duration_since(*started).as_secs_f32() / BOUNCE_PERIOD.as_secs_f32();
+        (phase.fract() * std::f32::consts::PI).sin()
+    }
+
+    /// Whether any icon is bouncing, so the dock keeps redrawing
+    pub fn is_animating(&self) -> bool {
+        !self.started.is_empty()
+    }
+}
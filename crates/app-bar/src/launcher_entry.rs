@@ -0,0 +1,299 @@
+// Badges and progress bars on app bar icons
+//
+// Applications report unread counts, progress and urgency through the
+// com.canonical.Unity.LauncherEntry D-Bus interface: they emit an Update
+// signal carrying their desktop file URI and the changed properties. The app
+// bar keeps the latest state per application and draws it over the
+// application's icon in the theme accent color. An application's state is
+// dropped when it leaves the bus.
+
+use compositor_utils::dbus::{Connection, Message, Value, BUS_INTERFACE, BUS_NAME};
+use compositor_utils::prelude::*;
+use config::{ColorToken, Palette};
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+/// D-Bus interface of the Update signal
+pub const LAUNCHER_ENTRY_INTERFACE: &str = "com.canonical.Unity.LauncherEntry";
+
+/// Largest count shown; higher counts are shown as "99+"
+pub const MAX_BADGE_COUNT: i64 = 99;
+
+/// Value of one property in an Update signal
+#[derive(Debug, Clone, PartialEq)]
+pub enum LauncherProperty {
+    /// `count` (x)
+    Count(i64),
+    /// `count-visible` (b)
+    CountVisible(bool),
+    /// `progress` (d), from 0.0 to 1.0
+    Progress(f64),
+    /// `progress-visible` (b)
+    ProgressVisible(bool),
+    /// `urgent` (b)
+    Urgent(bool),
+}
+
+impl LauncherProperty {
+    /// Property from its name in the signal's a{sv} dictionary
+    ///
+    /// Returns `None` for properties the app bar does not show, such as
+    /// quicklists.
+    pub fn from_entry(name: &str, value: LauncherValue) -> Option<Self> {
+        match (name, value) {
+            ("count", LauncherValue::Int(count)) => Some(Self::Count(count)),
+            ("count-visible", LauncherValue::Bool(visible)) => Some(Self::CountVisible(visible)),
+            ("progress", LauncherValue::Double(progress)) => Some(Self::Progress(progress)),
+            ("progress-visible", LauncherValue::Bool(visible)) => Some(Self::ProgressVisible(visible)),
+            ("urgent", LauncherValue::Bool(urgent)) => Some(Self::Urgent(urgent)),
+            _ => None,
+        }
+    }
+}
+
+/// Variant value of a signal property, as far as the app bar needs it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LauncherValue {
+    Int(i64),
+    Double(f64),
+    Bool(bool),
+}
+
+/// Update signal from an application
+#[derive(Debug, Clone, PartialEq)]
+pub struct LauncherEntryUpdate {
+    /// Unique bus name of the sender, e.g. ":1.42"
+    pub sender: String,
+    /// Desktop file URI, e.g. "application://org.mozilla.firefox.desktop"
+    pub app_uri: String,
+    pub properties: Vec<LauncherProperty>,
+}
+
+impl LauncherEntryUpdate {
+    /// Update from a received Update(s app_uri, a{sv} properties) signal
+    pub fn from_message(message: &Message) -> Option<Self> {
+        if !message.is_signal(LAUNCHER_ENTRY_INTERFACE, "Update") {
+            return None;
+        }
+        let properties = message
+            .body
+            .get(1)?
+            .as_array()?
+            .iter()
+            .filter_map(|entry| match entry {
+                Value::DictEntry(name, value) => {
+                    let value = match value.unwrap_variant() {
+                        Value::Bool(b) => LauncherValue::Bool(*b),
+                        Value::Double(d) => LauncherValue::Double(*d),
+                        value => LauncherValue::Int(value.as_i64()?),
+                    };
+                    LauncherProperty::from_entry(name.as_str()?, value)
+                }
+                _ => None,
+            })
+            .collect();
+        Some(Self {
+            sender: message.sender.clone()?,
+            app_uri: message.body.first()?.as_str()?.to_string(),
+            properties,
+        })
+    }
+}
+
+/// Badge and progress state of one application
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LauncherEntry {
+    pub count: i64,
+    pub count_visible: bool,
+    pub progress: f64,
+    pub progress_visible: bool,
+    pub urgent: bool,
+    /// Bus name of the application that set this state
+    sender: String,
+}
+
+impl LauncherEntry {
+    /// Badge text, if a count is shown
+    pub fn badge_label(&self) -> Option<String> {
+        if !self.count_visible || self.count <= 0 {
+            return None;
+        }
+        Some(if self.count > MAX_BADGE_COUNT {
+            format!("{}+", MAX_BADGE_COUNT)
+        } else {
+            self.count.to_string()
+        })
+    }
+
+    /// Fraction of the progress bar to fill, if a progress bar is shown
+    pub fn progress_fraction(&self) -> Option<f32> {
+        self.progress_visible.then(|| self.progress.clamp(0.0, 1.0) as f32)
+    }
+
+    /// Whether nothing is drawn over the icon
+    pub fn is_empty(&self) -> bool {
+        self.badge_label().is_none() && self.progress_fraction().is_none() && !self.urgent
+    }
+
+    fn apply(&mut self, property: &LauncherProperty) {
+        match *property {
+            LauncherProperty::Count(count) => self.count = count,
+            LauncherProperty::CountVisible(visible) => self.count_visible = visible,
+            LauncherProperty::Progress(progress) => self.progress = progress,
+            LauncherProperty::ProgressVisible(visible) => self.progress_visible = visible,
+            LauncherProperty::Urgent(urgent) => self.urgent = urgent,
+        }
+    }
+}
+
+/// Badge and progress state of every application, by app ID
+#[derive(Debug, Default)]
+pub struct LauncherEntries {
+    entries: HashMap<String, LauncherEntry>,
+}
+
+impl LauncherEntries {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply an Update signal; returns whether the app bar needs a redraw
+    pub fn apply(&mut self, update: &LauncherEntryUpdate) -> bool {
+        let Some(app_id) = app_id_from_uri(&update.app_uri) else {
+            debug!("Ignoring launcher entry update for {:?}", update.app_uri);
+            return false;
+        };
+        let entry = self.entries.entry(app_id.to_string()).or_default();
+        let before = entry.clone();
+        entry.sender.clone_from(&update.sender);
+        for property in &update.properties {
+            entry.apply(property);
+        }
+        let changed = *entry != before;
+        if entry.is_empty() {
+            self.entries.remove(app_id);
+        }
+        changed
+    }
+
+    /// Drop the state of an application that left the bus
+    pub fn sender_vanished(&mut self, sender: &str) -> bool {
+        let count = self.entries.len();
+        self.entries.retain(|_, entry| entry.sender != sender);
+        self.entries.len() != count
+    }
+
+    /// State to draw over the icon of `app_id`
+    pub fn get(&self, app_id: &str) -> Option<&LauncherEntry> {
+        self.entries.get(app_id)
+    }
+}
+
+/// App ID from a launcher entry URI
+///
+/// "application://org.mozilla.firefox.desktop" becomes "org.mozilla.firefox".
+pub fn app_id_from_uri(uri: &str) -> Option<&str> {
+    let desktop_file = uri.strip_prefix("application://")?;
+    let app_id = desktop_file.strip_suffix(".desktop").unwrap_or(desktop_file);
+    (!app_id.is_empty()).then_some(app_id)
+}
+
+/// Colors of badges and progress bars, derived from the theme
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BadgeStyle {
+    /// Badge background and progress fill
+    pub accent: [f32; 4],
+    /// Badge text
+    pub label: [f32; 4],
+    /// Unfilled part of the progress bar
+    pub track: [f32; 4],
+    /// Badge background of urgent applications
+    pub urgent: [f32; 4],
+    /// Badge corner radius in pixels
+    pub corner_radius: f32,
+}
+
+impl BadgeStyle {
+    /// Style matching the theme's semantic colors and corner radius
+    pub fn from_palette(palette: &Palette, corner_radius: f32) -> Self {
+        let accent = palette.color(ColorToken::Accent);
+        Self {
+            accent,
+            label: palette.color(ColorToken::OnAccent),
+            track: palette.states(ColorToken::Accent).disabled,
+            urgent: palette.color(ColorToken::Error),
+            corner_radius: corner_radius.min(8.0),
+        }
+    }
+}
+
+impl Default for BadgeStyle {
+    fn default() -> Self {
+        Self::from_palette(&Palette::default(), 12.0)
+    }
+}
+
+/// Receives LauncherEntry signals from the session bus
+pub struct LauncherEntryBridge {
+    updates: mpsc::UnboundedSender<LauncherEntryUpdate>,
+    vanished: mpsc::UnboundedSender<String>,
+}
+
+impl LauncherEntryBridge {
+    /// Create a bridge forwarding updates and vanished senders to the app bar
+    pub fn new(updates: mpsc::UnboundedSender<LauncherEntryUpdate>, vanished: mpsc::UnboundedSender<String>) -> Self {
+        Self { updates, vanished }
+    }
+
+    /// Forward an Update signal
+    pub fn update_received(&self, update: LauncherEntryUpdate) {
+        let _ = self.updates.send(update);
+    }
+
+    /// Forward a NameOwnerChanged signal whose new owner is empty
+    pub fn name_vanished(&self, name: &str) {
+        let _ = self.vanished.send(name.to_string());
+    }
+
+    /// Listen for signals until the app bar goes away
+    pub async fn run(self) {
+        info!("Launcher entry bridge started");
+        if let Err(e) = self.listen().await {
+            warn!("Launcher entries unavailable: {}", e);
+            self.updates.closed().await;
+        }
+        info!("Launcher entry bridge stopped");
+    }
+
+    /// Forward signals from the session bus until the app bar goes away or
+    /// the bus connection closes
+    async fn listen(&self) -> Result<()> {
+        let (connection, mut incoming) = Connection::session().await?;
+        connection
+            .add_match(&format!("type='signal',interface='{}',member='Update'", LAUNCHER_ENTRY_INTERFACE))
+            .await?;
+        connection
+            .add_match(&format!("type='signal',sender='{}',interface='{}',member='NameOwnerChanged'", BUS_NAME, BUS_INTERFACE))
+            .await?;
+
+        loop {
+            tokio::select! {
+                message = incoming.recv() => {
+                    let Some(message) = message else { break };
+                    if let Some(update) = LauncherEntryUpdate::from_message(&message) {
+                        self.update_received(update);
+                    } else if message.is_signal(BUS_INTERFACE, "NameOwnerChanged") {
+                        // NameOwnerChanged(s name, s old_owner, s new_owner)
+                        let arg = |index: usize| message.body.get(index).and_then(Value::as_str);
+                        if let (Some(name), Some("")) = (arg(0), arg(2)) {
+                            self.name_vanished(name);
+                        }
+                    }
+                }
+                _ = self.updates.closed() => return Ok(()),
+            }
+        }
+        Err(CompositorError::ipc("Session bus connection closed"))
+    }
+}
@@ -367,12 +367,370 @@ pub enum KeyCode {
 }
 */
 
+pub mod clock;
+pub mod launch_bounce;
+pub mod launcher_entry;
+pub mod media;
+pub mod quick_settings;
+
+use clock::{ClockWidget, WorldClockLine};
+use launch_bounce::LaunchBounce;
+use launcher_entry::{BadgeStyle, LauncherEntries, LauncherEntry, LauncherEntryBridge, LauncherEntryUpdate};
+use media::{MediaCommand, MediaPlayers, MediaWidget, MprisBridge, PlayerEvent, PlayerState};
+use quick_settings::{
+    QuickSettingsBridge, QuickSettingsCommand, QuickSettingsControl, QuickSettingsEvent, QuickSettingsPanel,
+    QuickSettingsState,
+};
+use compositor_utils::animation_tick::AnimationTicker;
+use compositor_utils::math::Rect;
+use std::time::{Instant, SystemTime};
+use ui_framework::calendar::CalendarPopover;
+use ui_framework::damage::{DamageTracker, SurfaceDamage};
+use ui_framework::focus::NavigationKey;
+use ui_framework::styling::PopoverStyle;
+use tokio::sync::mpsc;
+
+/// Widgets drawn on the bar, each re-rendered on its own when it changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BarWidget {
+    Dock,
+    Media,
+    Clock,
+    QuickSettings,
+}
+
 // Minimal placeholder implementation to satisfy the crate structure
 // This will be replaced when we implement the full app-bar functionality
-pub struct AppBar;
+pub struct AppBar {
+    /// Unread badges and progress of applications
+    launcher_entries: LauncherEntries,
+    badge_style: BadgeStyle,
+    /// Icons of starting applications bounce until they show a window
+    launch_bounce: LaunchBounce,
+    /// Apps with a window asking for attention, as reported by the compositor
+    urgent_apps: Vec<String>,
+    updates: mpsc::UnboundedReceiver<LauncherEntryUpdate>,
+    vanished: mpsc::UnboundedReceiver<String>,
+    /// MPRIS media players and the media controls widget
+    media_players: MediaPlayers,
+    media_widget: MediaWidget,
+    player_events: mpsc::UnboundedReceiver<PlayerEvent>,
+    media_commands: Option<mpsc::UnboundedSender<(String, MediaCommand)>>,
+    /// When widget animations step
+    ui_ticker: AnimationTicker,
+    /// Clock with its calendar and world clock popover
+    clock: ClockWidget,
+    /// Wi-Fi, Bluetooth, volume and brightness controls
+    quick_settings: QuickSettingsState,
+    quick_settings_panel: QuickSettingsPanel,
+    quick_settings_events: mpsc::UnboundedReceiver<QuickSettingsEvent>,
+    quick_settings_commands: Option<mpsc::UnboundedSender<QuickSettingsCommand>>,
+    /// Parts of the bar's cached texture to re-render
+    damage: DamageTracker<BarWidget>,
+}
 
 impl AppBar {
     pub fn new() -> Self {
-        Self
+        Self {
+            launcher_entries: LauncherEntries::new(),
+            badge_style: BadgeStyle::default(),
+            launch_bounce: LaunchBounce::new(),
+            urgent_apps: Vec::new(),
+            updates: mpsc::unbounded_channel().1,
+            vanished: mpsc::unbounded_channel().1,
+            media_players: MediaPlayers::new(),
+            media_widget: MediaWidget::default(),
+            player_events: mpsc::unbounded_channel().1,
+            media_commands: None,
+            ui_ticker: AnimationTicker::default(),
+            clock: ClockWidget::default(),
+            quick_settings: QuickSettingsState::default(),
+            quick_settings_panel: QuickSettingsPanel::new(),
+            quick_settings_events: mpsc::unbounded_channel().1,
+            quick_settings_commands: None,
+            damage: DamageTracker::new(0.0, 0.0),
+        }
+    }
+    
+    /// Resize the bar surface, re-rendering all of it
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.damage.resize(width, height);
+    }
+    
+    /// Set where a widget is drawn on the bar, in surface pixels
+    pub fn set_widget_bounds(&mut self, widget: BarWidget, bounds: Rect) {
+        self.damage.set_widget_bounds(widget, bounds);
+    }
+    
+    /// Hide a widget, e.g. the media widget without players
+    pub fn remove_widget(&mut self, widget: BarWidget) {
+        self.damage.remove_widget(&widget);
+    }
+    
+    /// Parts of the bar to re-render into its cached texture; `None` when
+    /// nothing changed
+    pub fn take_damage(&mut self) -> Option<SurfaceDamage> {
+        // TODO: Re-render the damaged regions once the app bar has its own surfaces
+        self.damage.take()
+    }
+    
+    /// Bridge feeding LauncherEntry signals to this app bar; spawn its `run`
+    pub fn launcher_entry_bridge(&mut self) -> LauncherEntryBridge {
+        let (updates, updates_receiver) = mpsc::unbounded_channel();
+        let (vanished, vanished_receiver) = mpsc::unbounded_channel();
+        self.updates = updates_receiver;
+        self.vanished = vanished_receiver;
+        LauncherEntryBridge::new(updates, vanished)
+    }
+    
+    /// Apply received LauncherEntry signals; returns whether icons need a redraw
+    pub fn process_launcher_entries(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok(update) = self.updates.try_recv() {
+            changed |= self.launcher_entries.apply(&update);
+        }
+        while let Ok(sender) = self.vanished.try_recv() {
+            changed |= self.launcher_entries.sender_vanished(&sender);
+        }
+        if changed {
+            self.damage.damage_widget(&BarWidget::Dock);
+        }
+        changed
+    }
+    
+    /// Badge and progress to draw over an application's icon
+    pub fn launcher_entry(&self, app_id: &str) -> Option<&LauncherEntry> {
+        // TODO: Draw badges and progress bars in the dock once icons are rendered
+        self.launcher_entries.get(app_id)
+    }
+    
+    /// Bounce the dock icons of these starting applications, as reported by
+    /// the compositor; returns whether icons need a redraw
+    pub fn set_launching_apps(&mut self, app_ids: &[String], now: Instant) -> bool {
+        let changed = self.launch_bounce.set_launching(app_ids, now);
+        if changed {
+            self.damage.damage_widget(&BarWidget::Dock);
+        }
+        changed
+    }
+    
+    /// Bounce height of an application's icon, from 0.0 to 1.0
+    pub fn launch_bounce(&self, app_id: &str, now: Instant) -> f32 {
+        // TODO: Offset dock icons by their bounce once icons are rendered
+        self.launch_bounce.offset(app_id, now)
+    }
+    
+    /// Highlight the dock entries of these applications, whose windows the
+    /// compositor reports as urgent; returns whether icons need a redraw
+    pub fn set_urgent_apps(&mut self, app_ids: &[String]) -> bool {
+        if self.urgent_apps == app_ids {
+            return false;
+        }
+        self.urgent_apps = app_ids.to_vec();
+        self.damage.damage_widget(&BarWidget::Dock);
+        true
+    }
+    
+    /// Whether an application's dock entry is highlighted, because one of its
+    /// windows asks for attention or it set the LauncherEntry urgent hint
+    pub fn is_urgent(&self, app_id: &str) -> bool {
+        // TODO: Highlight urgent dock entries in badge_style().urgent once icons are rendered
+        self.urgent_apps.iter().any(|urgent| urgent == app_id)
+            || self.launcher_entries.get(app_id).is_some_and(|entry| entry.urgent)
+    }
+    
+    /// Whether dock icons are bouncing and need redrawing every frame
+    pub fn is_bouncing(&self) -> bool {
+        self.launch_bounce.is_animating()
+    }
+    
+    /// Style badges after the theme's semantic colors and corner radius
+    pub fn set_theme(&mut self, palette: &config::Palette, corner_radius: f32) {
+        self.badge_style = BadgeStyle::from_palette(palette, corner_radius);
+        self.damage.damage_full();
+    }
+    
+    /// Colors and shape of badges and progress bars
+    pub fn badge_style(&self) -> BadgeStyle {
+        self.badge_style
+    }
+    
+    /// Bridge following MPRIS players for the media widget; spawn its `run`
+    pub fn mpris_bridge(&mut self) -> MprisBridge {
+        let (events, events_receiver) = mpsc::unbounded_channel();
+        let (commands, commands_receiver) = mpsc::unbounded_channel();
+        self.player_events = events_receiver;
+        self.media_commands = Some(commands);
+        MprisBridge::new(events, commands_receiver)
+    }
+    
+    /// Apply media player changes and advance the widget animation, which
+    /// steps at the UI animation rate
+    ///
+    /// Returns whether the media widget needs a redraw.
+    pub fn process_media_events(&mut self, now: Instant) -> bool {
+        let mut changed = false;
+        while let Ok(event) = self.player_events.try_recv() {
+            changed |= self.media_players.apply(event);
+        }
+        let animating = if self.ui_ticker.tick(now) {
+            self.media_widget.advance(now)
+        } else {
+            self.media_widget.is_animating()
+        };
+        let changed = animating || changed;
+        if changed {
+            self.damage.damage_widget(&BarWidget::Media);
+        }
+        changed
+    }
+    
+    /// Player to show in the media widget, if the widget is visible
+    pub fn media_player(&self) -> Option<&PlayerState> {
+        // TODO: Draw track, artwork and controls in the dock once widgets are rendered
+        let active = self.media_players.active();
+        self.media_widget.is_visible(active).then_some(active).flatten()
+    }
+    
+    /// Send play/pause, next or previous to the shown player
+    pub fn media_command(&self, command: MediaCommand) {
+        let (Some(player), Some(commands)) = (self.media_player(), &self.media_commands) else {
+            return;
+        };
+        let _ = commands.send((player.bus_name.clone(), command));
+    }
+    
+    /// Expand or collapse the media widget as the pointer enters or leaves it
+    pub fn set_media_hovered(&mut self, hovered: bool, now: Instant) {
+        self.media_widget.set_hovered(hovered, now);
+    }
+    
+    /// Media widget expansion from 0.0 (collapsed) to 1.0 (expanded) for a
+    /// frame drawn at `now`
+    pub fn media_expansion(&self, now: Instant) -> f32 {
+        self.media_widget.expansion(self.ui_ticker.alpha(now))
+    }
+    
+    /// Set how often widget animations step per second; 0 steps on every frame
+    pub fn set_animation_rate(&mut self, rate: u32) {
+        self.ui_ticker.set_rate(rate);
+    }
+    
+    /// Apply media widget visibility and hover settings
+    pub fn set_media_config(&mut self, config: config::MediaWidgetConfig, now: Instant) {
+        self.media_widget.set_config(config, now);
+    }
+    
+    /// Open or close the clock popover when the clock is clicked
+    ///
+    /// Returns whether the popover is now open and should take keyboard focus.
+    pub fn clock_clicked(&mut self, now: SystemTime) -> bool {
+        // TODO: Show the popover on an overlay surface anchored to the clock once
+        // the app bar has its own surfaces
+        self.clock.toggle_popover(now)
+    }
+    
+    /// Handle a key while the clock popover is open; returns whether it was consumed
+    pub fn clock_key(&mut self, key: NavigationKey) -> bool {
+        self.clock.handle_key(key)
+    }
+    
+    /// Close the clock popover, e.g. on a click outside it
+    pub fn close_clock_popover(&mut self) {
+        self.clock.close_popover();
+    }
+    
+    /// Calendar of the open clock popover
+    pub fn clock_popover(&self) -> Option<&CalendarPopover> {
+        self.clock.popover()
+    }
+    
+    /// World clock times listed below the calendar
+    pub fn world_clocks(&self, now: SystemTime) -> Vec<WorldClockLine> {
+        self.clock.world_clock_lines(now)
+    }
+    
+    /// Update the clock, damaging it on the bar when the minute changes;
+    /// returns whether the popover needs a redraw
+    pub fn tick_clock(&mut self, now: SystemTime) -> bool {
+        if self.clock.time_changed(now) {
+            self.damage.damage_widget(&BarWidget::Clock);
+        }
+        self.clock.tick(now)
+    }
+    
+    /// Colors and shape of the clock popover
+    pub fn clock_popover_style(&self) -> PopoverStyle {
+        self.clock.style()
+    }
+    
+    /// Apply clock settings and the theme to the clock popover
+    pub fn set_clock_config(&mut self, config: &config::ClockConfig, theme: &config::ThemeConfig) {
+        self.clock.set_config(config);
+        self.clock.set_theme(theme);
+    }
+    
+    /// Bridge connecting the quick settings panel to its backends; spawn its `run`
+    pub fn quick_settings_bridge(&mut self) -> QuickSettingsBridge {
+        let (events, events_receiver) = mpsc::unbounded_channel();
+        let (commands, commands_receiver) = mpsc::unbounded_channel();
+        self.quick_settings_events = events_receiver;
+        self.quick_settings_commands = Some(commands);
+        QuickSettingsBridge::new(events, commands_receiver)
+    }
+    
+    /// Apply backend state changes; returns whether the panel needs a redraw
+    pub fn process_quick_settings(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok(event) = self.quick_settings_events.try_recv() {
+            changed |= self.quick_settings.apply(event);
+        }
+        changed && self.quick_settings_panel.is_open()
+    }
+    
+    /// Open or close the quick settings panel from its app bar button
+    ///
+    /// Returns whether the panel is now open and should take keyboard focus.
+    pub fn toggle_quick_settings(&mut self) -> bool {
+        // TODO: Show the panel on an overlay surface styled like the clock popover
+        // once the app bar has its own surfaces; the compositor shows it for now
+        self.quick_settings_panel.toggle(&self.quick_settings)
+    }
+    
+    /// Handle a key while the quick settings panel is open; returns whether it was consumed
+    pub fn quick_settings_key(&mut self, key: NavigationKey) -> bool {
+        let (consumed, command) = self.quick_settings_panel.handle_key(key, &self.quick_settings);
+        if let (Some(command), Some(commands)) = (command, &self.quick_settings_commands) {
+            let _ = commands.send(command);
+        }
+        consumed
+    }
+    
+    /// Send a change made with the pointer, e.g. dragging the volume slider
+    pub fn quick_settings_command(&self, command: QuickSettingsCommand) {
+        if let Some(commands) = &self.quick_settings_commands {
+            let _ = commands.send(command);
+        }
+    }
+    
+    /// Close the quick settings panel, e.g. on a click outside it
+    pub fn close_quick_settings(&mut self) {
+        self.quick_settings_panel.close();
+    }
+    
+    /// Backend state shown in the quick settings panel
+    pub fn quick_settings(&self) -> &QuickSettingsState {
+        &self.quick_settings
+    }
+    
+    /// Control with keyboard focus in the open quick settings panel
+    pub fn quick_settings_focus(&self) -> Option<QuickSettingsControl> {
+        self.quick_settings_panel.focused()
+    }
+}
+
+impl Default for AppBar {
+    fn default() -> Self {
+        Self::new()
     }
 }
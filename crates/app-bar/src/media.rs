@@ -0,0 +1,520 @@
+// Media controls widget
+//
+// Media players publish their playback state and current track over MPRIS2,
+// owning an org.mpris.MediaPlayer2.* name on the session bus. The app bar
+// follows every player, shows the one that most recently started playing with
+// its track, artwork and play/pause/next controls, and sends control requests
+// back to it. The widget expands to show the artwork and track details while
+// hovered; the expansion steps at the UI animation rate.
+
+use compositor_utils::animation_tick::Interpolated;
+use compositor_utils::dbus::{Connection, Message, Value, BUS_INTERFACE, BUS_NAME, BUS_PATH, PROPERTIES_INTERFACE};
+use compositor_utils::prelude::*;
+use config::{MediaWidgetConfig, MediaWidgetVisibility};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Bus name prefix of MPRIS players
+pub const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+/// Object path of every MPRIS player
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const MPRIS_PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// Duration of the hover expansion animation
+pub const EXPAND_DURATION: Duration = Duration::from_millis(200);
+
+/// PlaybackStatus property of a player
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+impl PlaybackStatus {
+    /// Status from its MPRIS string; unknown values count as stopped
+    pub fn from_mpris(value: &str) -> Self {
+        match value {
+            "Playing" => Self::Playing,
+            "Paused" => Self::Paused,
+            _ => Self::Stopped,
+        }
+    }
+}
+
+/// Current track from the Metadata property
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackMetadata {
+    /// xesam:title
+    pub title: Option<String>,
+    /// xesam:artist
+    pub artists: Vec<String>,
+    /// xesam:album
+    pub album: Option<String>,
+    /// mpris:artUrl
+    pub art_url: Option<String>,
+    /// mpris:length
+    pub length: Option<Duration>,
+}
+
+impl TrackMetadata {
+    /// Track from a Metadata property, an a{sv} dictionary
+    pub fn from_mpris(metadata: &Value) -> Self {
+        let string = |key: &str| metadata.get(key).and_then(Value::as_str).map(str::to_string);
+        Self {
+            title: string("xesam:title"),
+            artists: metadata
+                .get("xesam:artist")
+                .and_then(Value::as_array)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|artist| artist.as_str().map(str::to_string))
+                .collect(),
+            album: string("xesam:album"),
+            art_url: string("mpris:artUrl"),
+            // Microseconds
+            length: metadata
+                .get("mpris:length")
+                .and_then(Value::as_i64)
+                .and_then(|length| u64::try_from(length).ok())
+                .filter(|&length| length > 0)
+                .map(Duration::from_micros),
+        }
+    }
+
+    /// Artists joined for display, e.g. "Artist A, Artist B"
+    pub fn artist_line(&self) -> Option<String> {
+        (!self.artists.is_empty()).then(|| self.artists.join(", "))
+    }
+
+    /// Local artwork file; remote artwork is not fetched
+    pub fn artwork_path(&self) -> Option<PathBuf> {
+        let path = self.art_url.as_deref()?.strip_prefix("file://")?;
+        Some(PathBuf::from(percent_decode(path)))
+    }
+}
+
+/// State of one MPRIS player
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerState {
+    /// Bus name, e.g. "org.mpris.MediaPlayer2.spotify"
+    pub bus_name: String,
+    pub status: PlaybackStatus,
+    pub metadata: TrackMetadata,
+    pub can_play: bool,
+    pub can_pause: bool,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    /// When the player last started playing, for picking the active player
+    started: u64,
+}
+
+impl PlayerState {
+    /// Player name for display, taken from the bus name
+    ///
+    /// Instance suffixes such as ".instance1234" are left out.
+    pub fn name(&self) -> &str {
+        let name = self.bus_name.strip_prefix(MPRIS_PREFIX).unwrap_or(&self.bus_name);
+        name.split(".instance").next().unwrap_or(name)
+    }
+
+    /// Whether the play/pause control does anything
+    pub fn can_play_pause(&self) -> bool {
+        match self.status {
+            PlaybackStatus::Playing => self.can_pause,
+            PlaybackStatus::Paused | PlaybackStatus::Stopped => self.can_play,
+        }
+    }
+}
+
+/// Change reported by the MPRIS bridge
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerEvent {
+    /// A player name appeared on the bus
+    Appeared { bus_name: String },
+    /// A player left the bus
+    Vanished { bus_name: String },
+    /// PlaybackStatus changed
+    Status { bus_name: String, status: PlaybackStatus },
+    /// Metadata changed
+    Metadata { bus_name: String, metadata: TrackMetadata },
+    /// CanPlay, CanPause, CanGoNext or CanGoPrevious changed
+    Capabilities {
+        bus_name: String,
+        can_play: bool,
+        can_pause: bool,
+        can_go_next: bool,
+        can_go_previous: bool,
+    },
+}
+
+/// Control request sent to the active player
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaCommand {
+    PlayPause,
+    Next,
+    Previous,
+}
+
+impl MediaCommand {
+    /// Method of org.mpris.MediaPlayer2.Player to call
+    pub fn method(self) -> &'static str {
+        match self {
+            Self::PlayPause => "PlayPause",
+            Self::Next => "Next",
+            Self::Previous => "Previous",
+        }
+    }
+}
+
+/// All MPRIS players on the bus
+#[derive(Debug, Default)]
+pub struct MediaPlayers {
+    players: Vec<PlayerState>,
+    /// Incremented whenever a player starts playing
+    sequence: u64,
+}
+
+impl MediaPlayers {
+    /// Create an empty player list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a player event; returns whether the widget needs a redraw
+    pub fn apply(&mut self, event: PlayerEvent) -> bool {
+        match event {
+            PlayerEvent::Appeared { bus_name } => {
+                if self.player_mut(&bus_name).is_some() {
+                    return false;
+                }
+                debug!("Media player {} appeared", bus_name);
+                self.players.push(PlayerState { bus_name, ..Default::default() });
+                true
+            }
+            PlayerEvent::Vanished { bus_name } => {
+                let count = self.players.len();
+                self.players.retain(|player| player.bus_name != bus_name);
+                self.players.len() != count
+            }
+            PlayerEvent::Status { bus_name, status } => {
+                self.sequence += 1;
+                let sequence = self.sequence;
+                let Some(player) = self.player_mut(&bus_name) else { return false };
+                if status == PlaybackStatus::Playing && player.status != PlaybackStatus::Playing {
+                    player.started = sequence;
+                }
+                std::mem::replace(&mut player.status, status) != status
+            }
+            PlayerEvent::Metadata { bus_name, metadata } => {
+                let Some(player) = self.player_mut(&bus_name) else { return false };
+                std::mem::replace(&mut player.metadata, metadata.clone()) != metadata
+            }
+            PlayerEvent::Capabilities { bus_name, can_play, can_pause, can_go_next, can_go_previous } => {
+                let Some(player) = self.player_mut(&bus_name) else { return false };
+                let before = (player.can_play, player.can_pause, player.can_go_next, player.can_go_previous);
+                player.can_play = can_play;
+                player.can_pause = can_pause;
+                player.can_go_next = can_go_next;
+                player.can_go_previous = can_go_previous;
+                before != (can_play, can_pause, can_go_next, can_go_previous)
+            }
+        }
+    }
+
+    /// Player shown in the widget
+    ///
+    /// The player that most recently started playing wins; paused players
+    /// are preferred over stopped ones so the widget stays on a paused track.
+    pub fn active(&self) -> Option<&PlayerState> {
+        let rank = |player: &PlayerState| {
+            let status = match player.status {
+                PlaybackStatus::Playing => 2,
+                PlaybackStatus::Paused => 1,
+                PlaybackStatus::Stopped => 0,
+            };
+            (status, player.started)
+        };
+        self.players.iter().max_by_key(|player| rank(player))
+    }
+
+    fn player_mut(&mut self, bus_name: &str) -> Option<&mut PlayerState> {
+        self.players.iter_mut().find(|player| player.bus_name == bus_name)
+    }
+}
+
+/// Visibility and hover expansion of the media widget
+#[derive(Debug)]
+pub struct MediaWidget {
+    config: MediaWidgetConfig,
+    hovered: bool,
+    /// 0.0 = collapsed controls, 1.0 = expanded with artwork and details
+    expansion: f32,
+    /// Expansion at the start of the current animation and where it is heading
+    animation: Option<(Instant, f32, f32)>,
+    /// Expansion of the last two animation steps, interpolated when drawn
+    steps: Interpolated<f32>,
+}
+
+impl MediaWidget {
+    /// Create a collapsed widget
+    pub fn new(config: MediaWidgetConfig) -> Self {
+        Self {
+            config,
+            hovered: false,
+            expansion: 0.0,
+            animation: None,
+            steps: Interpolated::new(0.0),
+        }
+    }
+
+    /// Apply new widget settings
+    pub fn set_config(&mut self, config: MediaWidgetConfig, now: Instant) {
+        self.config = config;
+        if !self.config.expand_on_hover {
+            self.animate_to(0.0, now);
+        }
+    }
+
+    /// Whether the widget is shown for the active player
+    pub fn is_visible(&self, active: Option<&PlayerState>) -> bool {
+        match (self.config.visibility, active) {
+            (MediaWidgetVisibility::Never, _) | (_, None) => false,
+            (MediaWidgetVisibility::Always, Some(_)) => true,
+            (MediaWidgetVisibility::WhenActive, Some(player)) => player.status != PlaybackStatus::Stopped,
+        }
+    }
+
+    /// Expand while the pointer is over the widget
+    pub fn set_hovered(&mut self, hovered: bool, now: Instant) {
+        if hovered == self.hovered {
+            return;
+        }
+        self.hovered = hovered;
+        let target = if hovered && self.config.expand_on_hover { 1.0 } else { 0.0 };
+        self.animate_to(target, now);
+    }
+
+    /// Step the expansion animation; returns whether it is still running
+    pub fn advance(&mut self, now: Instant) -> bool {
+        if let Some((start, from, to)) = self.animation {
+            let t = (now.duration_since(start).as_secs_f32() / EXPAND_DURATION.as_secs_f32()).min(1.0);
+            let eased = 1.0 - (1.0 - t).powi(3);
+            self.expansion = from + (to - from) * eased;
+            if t >= 1.0 {
+                self.animation = None;
+            }
+        }
+        self.steps.step(self.expansion);
+        self.is_animating()
+    }
+
+    /// Whether the expansion is changing, including frames still
+    /// interpolating towards the last step
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some() || !self.steps.is_settled()
+    }
+
+    /// Expansion from 0.0 (collapsed) to 1.0 (expanded), `alpha` of the way
+    /// from the previous animation step to the last
+    pub fn expansion(&self, alpha: f32) -> f32 {
+        self.steps.value(alpha)
+    }
+
+    fn animate_to(&mut self, target: f32, now: Instant) {
+        if self.expansion != target {
+            self.animation = Some((now, self.expansion, target));
+        }
+    }
+}
+
+impl Default for MediaWidget {
+    fn default() -> Self {
+        Self::new(MediaWidgetConfig::default())
+    }
+}
+
+/// Follows MPRIS players on the session bus and forwards control requests
+pub struct MprisBridge {
+    events: mpsc::UnboundedSender<PlayerEvent>,
+    commands: mpsc::UnboundedReceiver<(String, MediaCommand)>,
+    /// Followed players by unique bus name, which their signals come from
+    players: HashMap<String, FollowedPlayer>,
+}
+
+/// Player followed by the bridge
+struct FollowedPlayer {
+    bus_name: String,
+    /// CanPlay, CanPause, CanGoNext and CanGoPrevious, which change separately
+    capabilities: [bool; 4],
+}
+
+impl MprisBridge {
+    /// Create a bridge reporting to and taking commands from the app bar
+    pub fn new(
+        events: mpsc::UnboundedSender<PlayerEvent>,
+        commands: mpsc::UnboundedReceiver<(String, MediaCommand)>,
+    ) -> Self {
+        Self { events, commands, players: HashMap::new() }
+    }
+
+    /// Report a player event
+    pub fn player_event(&self, event: PlayerEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Follow players until the app bar goes away
+    pub async fn run(mut self) {
+        info!("MPRIS bridge started");
+        if let Err(e) = self.follow().await {
+            warn!("Media players unavailable: {}", e);
+            while self.commands.recv().await.is_some() {}
+        }
+        info!("MPRIS bridge stopped");
+    }
+
+    /// Follow players and send them commands until the app bar goes away or
+    /// the bus connection closes
+    async fn follow(&mut self) -> Result<()> {
+        let (connection, mut incoming) = Connection::session().await?;
+        connection
+            .add_match(&format!(
+                "type='signal',sender='{}',interface='{}',member='NameOwnerChanged',arg0namespace='{}'",
+                BUS_NAME,
+                BUS_INTERFACE,
+                MPRIS_PREFIX.trim_end_matches('.')
+            ))
+            .await?;
+        connection
+            .add_match(&format!(
+                "type='signal',path='{}',interface='{}',member='PropertiesChanged'",
+                MPRIS_PATH, PROPERTIES_INTERFACE
+            ))
+            .await?;
+
+        for bus_name in connection.list_names().await? {
+            if !bus_name.starts_with(MPRIS_PREFIX) {
+                continue;
+            }
+            let owner = connection
+                .call_method(BUS_NAME, BUS_PATH, BUS_INTERFACE, "GetNameOwner", vec![Value::from(bus_name.as_str())])
+                .await;
+            match owner {
+                Ok(owner) => match owner.first().and_then(Value::as_str) {
+                    Some(owner) => self.player_appeared(&connection, owner, bus_name).await,
+                    None => warn!("No owner for media player {}", bus_name),
+                },
+                Err(e) => debug!("Media player {} left: {}", bus_name, e),
+            }
+        }
+
+        loop {
+            tokio::select! {
+                message = incoming.recv() => {
+                    let Some(message) = message else { break };
+                    self.signal_received(&connection, message).await;
+                }
+                command = self.commands.recv() => {
+                    let Some((bus_name, command)) = command else { return Ok(()) };
+                    debug!("Media command {} for {}", command.method(), bus_name);
+                    if let Err(e) = connection
+                        .call_method(&bus_name, MPRIS_PATH, MPRIS_PLAYER_INTERFACE, command.method(), Vec::new())
+                        .await
+                    {
+                        warn!("Media command {} for {} failed: {}", command.method(), bus_name, e);
+                    }
+                }
+            }
+        }
+        Err(CompositorError::ipc("Session bus connection closed"))
+    }
+
+    /// Handle NameOwnerChanged and PropertiesChanged signals
+    async fn signal_received(&mut self, connection: &Connection, message: Message) {
+        let arg = |index: usize| message.body.get(index).and_then(Value::as_str);
+        if message.is_signal(BUS_INTERFACE, "NameOwnerChanged") {
+            // NameOwnerChanged(s name, s old_owner, s new_owner)
+            let (Some(bus_name), Some(old_owner), Some(new_owner)) = (arg(0), arg(1), arg(2)) else { return };
+            if !bus_name.starts_with(MPRIS_PREFIX) {
+                return;
+            }
+            if self.players.remove(old_owner).is_some() {
+                self.player_event(PlayerEvent::Vanished { bus_name: bus_name.to_string() });
+            }
+            if !new_owner.is_empty() {
+                self.player_appeared(connection, new_owner, bus_name.to_string()).await;
+            }
+        } else if message.is_signal(PROPERTIES_INTERFACE, "PropertiesChanged") && arg(0) == Some(MPRIS_PLAYER_INTERFACE) {
+            // PropertiesChanged(s interface, a{sv} changed, as invalidated)
+            let Some(sender) = message.sender.as_deref() else { return };
+            if let Some(changed) = message.body.get(1) {
+                self.properties_changed(sender, changed);
+            }
+        }
+    }
+
+    /// Follow a player that owns `bus_name` as `owner`, reading its state
+    async fn player_appeared(&mut self, connection: &Connection, owner: &str, bus_name: String) {
+        self.player_event(PlayerEvent::Appeared { bus_name: bus_name.clone() });
+        self.players.insert(owner.to_string(), FollowedPlayer { bus_name: bus_name.clone(), capabilities: [false; 4] });
+        match connection.get_all_properties(&bus_name, MPRIS_PATH, MPRIS_PLAYER_INTERFACE).await {
+            Ok(properties) => self.properties_changed(owner, &properties),
+            Err(e) => debug!("Cannot read the state of media player {}: {}", bus_name, e),
+        }
+    }
+
+    /// Report changed Player properties of the player owned by `owner`
+    fn properties_changed(&mut self, owner: &str, properties: &Value) {
+        let Some(player) = self.players.get_mut(owner) else { return };
+        let bus_name = player.bus_name.clone();
+        let mut events = Vec::new();
+        if let Some(status) = properties.get("PlaybackStatus").and_then(Value::as_str) {
+            events.push(PlayerEvent::Status { bus_name: bus_name.clone(), status: PlaybackStatus::from_mpris(status) });
+        }
+        if let Some(metadata) = properties.get("Metadata") {
+            events.push(PlayerEvent::Metadata { bus_name: bus_name.clone(), metadata: TrackMetadata::from_mpris(metadata) });
+        }
+        let mut capabilities_changed = false;
+        for (capability, name) in player.capabilities.iter_mut().zip(["CanPlay", "CanPause", "CanGoNext", "CanGoPrevious"]) {
+            if let Some(value) = properties.get(name).and_then(Value::as_bool) {
+                *capability = value;
+                capabilities_changed = true;
+            }
+        }
+        if capabilities_changed {
+            let [can_play, can_pause, can_go_next, can_go_previous] = player.capabilities;
+            events.push(PlayerEvent::Capabilities { bus_name, can_play, can_pause, can_go_next, can_go_previous });
+        }
+        for event in events {
+            self.player_event(event);
+        }
+    }
+}
+
+/// Decode %XX escapes of a file URL path
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
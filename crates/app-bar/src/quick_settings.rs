@@ -0,0 +1,578 @@
+// Quick settings panel
+//
+// A popover opened from the app bar (or a keybinding) gathering the toggles
+// and sliders users reach for most: Wi-Fi through NetworkManager, Bluetooth
+// through BlueZ, output volume through PipeWire and screen brightness through
+// the kernel backlight. Backends report their state to the panel and the
+// panel sends commands back; controls of unavailable backends are hidden.
+// NetworkManager and BlueZ are followed on the system bus, brightness is set
+// through logind, and volume through wpctl, PipeWire having no bus API.
+// The panel is keyboard navigable: Up/Down move between controls, Left/Right
+// adjust sliders, Enter toggles and Escape closes it.
+
+use compositor_utils::dbus::{Connection, Message, Value, PROPERTIES_INTERFACE};
+use compositor_utils::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use ui_framework::focus::NavigationKey;
+
+/// Step of the volume and brightness sliders on Left/Right
+pub const SLIDER_STEP: f32 = 0.05;
+
+/// Lowest brightness the slider allows, so the screen never goes fully dark
+pub const MIN_BRIGHTNESS: f32 = 0.05;
+
+/// Wi-Fi state from NetworkManager
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WifiState {
+    /// WirelessEnabled
+    pub enabled: bool,
+    /// SSID of the active wireless connection
+    pub network: Option<String>,
+    /// Signal strength in percent
+    pub strength: Option<u8>,
+}
+
+/// Bluetooth state from BlueZ
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BluetoothState {
+    /// Powered property of the default adapter
+    pub powered: bool,
+    /// Names of connected devices
+    pub connected_devices: Vec<String>,
+}
+
+/// Volume of the default PipeWire sink
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioState {
+    /// 0.0 - 1.0
+    pub volume: f32,
+    pub muted: bool,
+}
+
+/// Brightness of a backlight device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BacklightState {
+    /// Device name under /sys/class/backlight, e.g. "intel_backlight"
+    pub device: String,
+    pub brightness: u32,
+    pub max_brightness: u32,
+}
+
+impl BacklightState {
+    /// Brightness from 0.0 to 1.0
+    pub fn fraction(&self) -> f32 {
+        if self.max_brightness == 0 {
+            return 0.0;
+        }
+        self.brightness as f32 / self.max_brightness as f32
+    }
+
+    /// Raw brightness value for a fraction
+    pub fn value_for(&self, fraction: f32) -> u32 {
+        (fraction.clamp(0.0, 1.0) * self.max_brightness as f32).round() as u32
+    }
+}
+
+/// State change reported by a backend; `None` means the backend is unavailable
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuickSettingsEvent {
+    Wifi(Option<WifiState>),
+    Bluetooth(Option<BluetoothState>),
+    Audio(Option<AudioState>),
+    Backlight(Option<BacklightState>),
+}
+
+/// Change requested from the panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuickSettingsCommand {
+    SetWifiEnabled(bool),
+    SetBluetoothPowered(bool),
+    SetVolume(f32),
+    SetMuted(bool),
+    /// Brightness from 0.0 to 1.0
+    SetBrightness(f32),
+}
+
+/// Control in the panel, in display order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuickSettingsControl {
+    Wifi,
+    Bluetooth,
+    Volume,
+    Brightness,
+}
+
+impl QuickSettingsControl {
+    const ALL: [Self; 4] = [Self::Wifi, Self::Bluetooth, Self::Volume, Self::Brightness];
+
+    /// Label shown next to the control
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Wifi => "Wi-Fi",
+            Self::Bluetooth => "Bluetooth",
+            Self::Volume => "Volume",
+            Self::Brightness => "Brightness",
+        }
+    }
+}
+
+/// State of every backend, as shown in the panel
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuickSettingsState {
+    pub wifi: Option<WifiState>,
+    pub bluetooth: Option<BluetoothState>,
+    pub audio: Option<AudioState>,
+    pub backlight: Option<BacklightState>,
+}
+
+impl QuickSettingsState {
+    /// Apply a backend event; returns whether the panel needs a redraw
+    pub fn apply(&mut self, event: QuickSettingsEvent) -> bool {
+        match event {
+            QuickSettingsEvent::Wifi(wifi) => std::mem::replace(&mut self.wifi, wifi.clone()) != wifi,
+            QuickSettingsEvent::Bluetooth(bluetooth) => {
+                std::mem::replace(&mut self.bluetooth, bluetooth.clone()) != bluetooth
+            }
+            QuickSettingsEvent::Audio(audio) => std::mem::replace(&mut self.audio, audio) != audio,
+            QuickSettingsEvent::Backlight(backlight) => {
+                std::mem::replace(&mut self.backlight, backlight.clone()) != backlight
+            }
+        }
+    }
+
+    /// Controls whose backend is available
+    pub fn controls(&self) -> Vec<QuickSettingsControl> {
+        QuickSettingsControl::ALL
+            .into_iter()
+            .filter(|control| match control {
+                QuickSettingsControl::Wifi => self.wifi.is_some(),
+                QuickSettingsControl::Bluetooth => self.bluetooth.is_some(),
+                QuickSettingsControl::Volume => self.audio.is_some(),
+                QuickSettingsControl::Brightness => self.backlight.is_some(),
+            })
+            .collect()
+    }
+
+    /// Command toggling a control: Wi-Fi, Bluetooth or mute
+    pub fn toggle(&self, control: QuickSettingsControl) -> Option<QuickSettingsCommand> {
+        match control {
+            QuickSettingsControl::Wifi => Some(QuickSettingsCommand::SetWifiEnabled(!self.wifi.as_ref()?.enabled)),
+            QuickSettingsControl::Bluetooth => {
+                Some(QuickSettingsCommand::SetBluetoothPowered(!self.bluetooth.as_ref()?.powered))
+            }
+            QuickSettingsControl::Volume => Some(QuickSettingsCommand::SetMuted(!self.audio?.muted)),
+            QuickSettingsControl::Brightness => None,
+        }
+    }
+
+    /// Command moving a slider by `delta`
+    fn adjust(&self, control: QuickSettingsControl, delta: f32) -> Option<QuickSettingsCommand> {
+        match control {
+            QuickSettingsControl::Volume => {
+                Some(QuickSettingsCommand::SetVolume((self.audio?.volume + delta).clamp(0.0, 1.0)))
+            }
+            QuickSettingsControl::Brightness => {
+                let brightness = self.backlight.as_ref()?.fraction() + delta;
+                Some(QuickSettingsCommand::SetBrightness(brightness.clamp(MIN_BRIGHTNESS, 1.0)))
+            }
+            QuickSettingsControl::Wifi | QuickSettingsControl::Bluetooth => None,
+        }
+    }
+}
+
+/// Open state and keyboard focus of the panel
+#[derive(Debug, Default)]
+pub struct QuickSettingsPanel {
+    open: bool,
+    focused: Option<QuickSettingsControl>,
+}
+
+impl QuickSettingsPanel {
+    /// Create a closed panel
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open or close the panel; returns whether it is now open
+    pub fn toggle(&mut self, state: &QuickSettingsState) -> bool {
+        self.open = !self.open;
+        self.focused = if self.open { state.controls().first().copied() } else { None };
+        self.open
+    }
+
+    /// Close the panel, e.g. on a click outside it
+    pub fn close(&mut self) {
+        self.open = false;
+        self.focused = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Control with keyboard focus
+    pub fn focused(&self) -> Option<QuickSettingsControl> {
+        self.focused
+    }
+
+    /// Handle a key while the panel is open
+    ///
+    /// Returns whether the key was consumed and the command it triggered.
+    pub fn handle_key(&mut self, key: NavigationKey, state: &QuickSettingsState) -> (bool, Option<QuickSettingsCommand>) {
+        if !self.open {
+            return (false, None);
+        }
+        let controls = state.controls();
+        // Keep focus valid when a backend went away while the panel was open
+        if !self.focused.is_some_and(|control| controls.contains(&control)) {
+            self.focused = controls.first().copied();
+        }
+        let Some(focused) = self.focused else {
+            let consumed = key == NavigationKey::Cancel;
+            if consumed {
+                self.close();
+            }
+            return (consumed, None);
+        };
+        let index = controls.iter().position(|&control| control == focused).unwrap_or(0);
+
+        match key {
+            NavigationKey::Up | NavigationKey::BackTab => {
+                self.focused = Some(controls[(index + controls.len() - 1) % controls.len()]);
+                (true, None)
+            }
+            NavigationKey::Down | NavigationKey::Tab => {
+                self.focused = Some(controls[(index + 1) % controls.len()]);
+                (true, None)
+            }
+            NavigationKey::Home => {
+                self.focused = controls.first().copied();
+                (true, None)
+            }
+            NavigationKey::End => {
+                self.focused = controls.last().copied();
+                (true, None)
+            }
+            NavigationKey::Left => (true, state.adjust(focused, -SLIDER_STEP)),
+            NavigationKey::Right => (true, state.adjust(focused, SLIDER_STEP)),
+            NavigationKey::Activate => (true, state.toggle(focused)),
+            NavigationKey::Cancel => {
+                self.close();
+                (true, None)
+            }
+        }
+    }
+}
+
+/// Directory of kernel backlight devices
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+/// How often volume and brightness are re-read
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// wpctl's name for the default PipeWire sink
+const DEFAULT_SINK: &str = "@DEFAULT_AUDIO_SINK@";
+
+const NM_NAME: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const NM_INTERFACE: &str = "org.freedesktop.NetworkManager";
+const NM_DEVICE_INTERFACE: &str = "org.freedesktop.NetworkManager.Device";
+const NM_WIRELESS_INTERFACE: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+const NM_ACCESS_POINT_INTERFACE: &str = "org.freedesktop.NetworkManager.AccessPoint";
+/// DeviceType of Wi-Fi devices
+const NM_DEVICE_TYPE_WIFI: i64 = 2;
+
+const BLUEZ_NAME: &str = "org.bluez";
+const BLUEZ_PATH: &str = "/org/bluez";
+const BLUEZ_ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+const BLUEZ_DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const OBJECT_MANAGER_INTERFACE: &str = "org.freedesktop.DBus.ObjectManager";
+
+const LOGIND_NAME: &str = "org.freedesktop.login1";
+/// Session of the compositor
+const LOGIND_SESSION_PATH: &str = "/org/freedesktop/login1/session/auto";
+const LOGIND_SESSION_INTERFACE: &str = "org.freedesktop.login1.Session";
+
+/// Read the first backlight device, preferring firmware and platform
+/// interfaces over raw ones as they map brightness more evenly
+pub fn read_backlight() -> Option<BacklightState> {
+    let mut devices: Vec<(u8, PathBuf)> = std::fs::read_dir(BACKLIGHT_DIR)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .map(|path| {
+            let kind = std::fs::read_to_string(path.join("type")).unwrap_or_default();
+            let rank = match kind.trim() {
+                "firmware" => 0,
+                "platform" => 1,
+                _ => 2,
+            };
+            (rank, path)
+        })
+        .collect();
+    devices.sort();
+    devices.into_iter().find_map(|(_, path)| read_backlight_device(&path))
+}
+
+fn read_backlight_device(path: &Path) -> Option<BacklightState> {
+    let read = |file: &str| std::fs::read_to_string(path.join(file)).ok()?.trim().parse::<u32>().ok();
+    let max_brightness = read("max_brightness").filter(|&max| max > 0)?;
+    Some(BacklightState {
+        device: path.file_name()?.to_string_lossy().into_owned(),
+        brightness: read("brightness")?.min(max_brightness),
+        max_brightness,
+    })
+}
+
+/// Connects the panel to NetworkManager, BlueZ, PipeWire and the backlight
+pub struct QuickSettingsBridge {
+    events: mpsc::UnboundedSender<QuickSettingsEvent>,
+    commands: mpsc::UnboundedReceiver<QuickSettingsCommand>,
+    /// Object path of the Bluetooth adapter shown, e.g. "/org/bluez/hci0"
+    bluetooth_adapter: Option<String>,
+}
+
+impl QuickSettingsBridge {
+    /// Create a bridge reporting to and taking commands from the app bar
+    pub fn new(
+        events: mpsc::UnboundedSender<QuickSettingsEvent>,
+        commands: mpsc::UnboundedReceiver<QuickSettingsCommand>,
+    ) -> Self {
+        Self { events, commands, bluetooth_adapter: None }
+    }
+
+    /// Report a backend state change
+    pub fn backend_changed(&self, event: QuickSettingsEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Serve the panel until the app bar goes away
+    pub async fn run(mut self) {
+        info!("Quick settings bridge started");
+        self.backend_changed(QuickSettingsEvent::Backlight(read_backlight()));
+        self.backend_changed(QuickSettingsEvent::Audio(read_volume().await));
+
+        let (system, mut incoming) = match Connection::system().await {
+            Ok((connection, incoming)) => (Some(connection), incoming),
+            Err(e) => {
+                warn!("No system bus, Wi-Fi, Bluetooth and brightness controls are unavailable: {}", e);
+                (None, mpsc::unbounded_channel().1)
+            }
+        };
+        let mut bus_open = system.is_some();
+        if let Some(system) = &system {
+            for rule in [
+                format!("type='signal',sender='{}',interface='{}',member='PropertiesChanged'", NM_NAME, PROPERTIES_INTERFACE),
+                format!("type='signal',sender='{}',interface='{}',member='PropertiesChanged'", BLUEZ_NAME, PROPERTIES_INTERFACE),
+                format!("type='signal',sender='{}',interface='{}'", BLUEZ_NAME, OBJECT_MANAGER_INTERFACE),
+            ] {
+                if let Err(e) = system.add_match(&rule).await {
+                    warn!("Cannot follow quick settings backends: {}", e);
+                }
+            }
+            self.refresh_wifi(system).await;
+            self.refresh_bluetooth(system).await;
+        }
+
+        // Volume and brightness also change through media keys and other
+        // clients, which send no signals on the bus
+        let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+        loop {
+            tokio::select! {
+                message = incoming.recv(), if bus_open => {
+                    let (Some(message), Some(system)) = (message, &system) else {
+                        warn!("System bus connection closed");
+                        bus_open = false;
+                        continue;
+                    };
+                    self.signal_received(system, &message).await;
+                }
+                command = self.commands.recv() => {
+                    let Some(command) = command else { break };
+                    self.run_command(system.as_ref(), command).await;
+                }
+                _ = refresh.tick() => {
+                    self.backend_changed(QuickSettingsEvent::Audio(read_volume().await));
+                    self.backend_changed(QuickSettingsEvent::Backlight(read_backlight()));
+                }
+            }
+        }
+        info!("Quick settings bridge stopped");
+    }
+
+    /// Re-read the backend a PropertiesChanged or ObjectManager signal is from
+    async fn signal_received(&mut self, system: &Connection, message: &Message) {
+        let path = message.path.as_deref().unwrap_or_default();
+        if message.interface.as_deref() == Some(OBJECT_MANAGER_INTERFACE) || path.starts_with(BLUEZ_PATH) {
+            self.refresh_bluetooth(system).await;
+        } else if message.is_signal(PROPERTIES_INTERFACE, "PropertiesChanged") && path.starts_with(NM_PATH) {
+            self.refresh_wifi(system).await;
+        }
+    }
+
+    /// Carry out a change requested from the panel
+    async fn run_command(&mut self, system: Option<&Connection>, command: QuickSettingsCommand) {
+        debug!("Quick settings command {:?}", command);
+        let result = match command {
+            QuickSettingsCommand::SetVolume(volume) => {
+                let result = wpctl(&["set-volume", DEFAULT_SINK, &format!("{:.2}", volume.clamp(0.0, 1.0))]).await;
+                self.backend_changed(QuickSettingsEvent::Audio(read_volume().await));
+                result
+            }
+            QuickSettingsCommand::SetMuted(muted) => {
+                let result = wpctl(&["set-mute", DEFAULT_SINK, if muted { "1" } else { "0" }]).await;
+                self.backend_changed(QuickSettingsEvent::Audio(read_volume().await));
+                result
+            }
+            _ => match system {
+                Some(system) => self.run_system_command(system, command).await,
+                None => Err(CompositorError::ipc("No system bus")),
+            },
+        };
+        if let Err(e) = result {
+            warn!("Quick settings command {:?} failed: {}", command, e);
+        }
+    }
+
+    /// Carry out a change through NetworkManager, BlueZ or logind
+    async fn run_system_command(&mut self, system: &Connection, command: QuickSettingsCommand) -> Result<()> {
+        match command {
+            QuickSettingsCommand::SetWifiEnabled(enabled) => {
+                system.set_property(NM_NAME, NM_PATH, NM_INTERFACE, "WirelessEnabled", Value::Bool(enabled)).await
+            }
+            QuickSettingsCommand::SetBluetoothPowered(powered) => {
+                let adapter = self.bluetooth_adapter.as_deref().ok_or_else(|| CompositorError::ipc("No Bluetooth adapter"))?;
+                system.set_property(BLUEZ_NAME, adapter, BLUEZ_ADAPTER_INTERFACE, "Powered", Value::Bool(powered)).await
+            }
+            QuickSettingsCommand::SetBrightness(fraction) => {
+                let backlight = read_backlight().ok_or_else(|| CompositorError::ipc("No backlight"))?;
+                // logind writes sysfs for the session, which the compositor may not
+                system
+                    .call_method(
+                        LOGIND_NAME,
+                        LOGIND_SESSION_PATH,
+                        LOGIND_SESSION_INTERFACE,
+                        "SetBrightness",
+                        vec![
+                            Value::from("backlight"),
+                            Value::from(backlight.device.as_str()),
+                            Value::UInt32(backlight.value_for(fraction)),
+                        ],
+                    )
+                    .await?;
+                self.backend_changed(QuickSettingsEvent::Backlight(read_backlight()));
+                Ok(())
+            }
+            QuickSettingsCommand::SetVolume(_) | QuickSettingsCommand::SetMuted(_) => Ok(()),
+        }
+    }
+
+    /// Report the Wi-Fi state; unavailable without NetworkManager or a
+    /// wireless device
+    async fn refresh_wifi(&self, system: &Connection) {
+        self.backend_changed(QuickSettingsEvent::Wifi(read_wifi(system).await));
+    }
+
+    /// Report the state of the first Bluetooth adapter; unavailable without
+    /// BlueZ or an adapter
+    async fn refresh_bluetooth(&mut self, system: &Connection) {
+        let bluetooth = read_bluetooth(system).await;
+        self.bluetooth_adapter = bluetooth.as_ref().map(|(adapter, _)| adapter.clone());
+        self.backend_changed(QuickSettingsEvent::Bluetooth(bluetooth.map(|(_, state)| state)));
+    }
+}
+
+/// Wi-Fi state from NetworkManager, with the network of the first wireless
+/// device that has an active access point
+async fn read_wifi(system: &Connection) -> Option<WifiState> {
+    let enabled = system.get_property(NM_NAME, NM_PATH, NM_INTERFACE, "WirelessEnabled").await.ok()?.as_bool()?;
+    let devices = system.get_property(NM_NAME, NM_PATH, NM_INTERFACE, "Devices").await.ok()?;
+    let mut wifi = None;
+    for device in devices.as_array().unwrap_or_default().iter().filter_map(Value::as_str) {
+        let device_type = system.get_property(NM_NAME, device, NM_DEVICE_INTERFACE, "DeviceType").await;
+        if device_type.ok().and_then(|device_type| device_type.as_i64()) != Some(NM_DEVICE_TYPE_WIFI) {
+            continue;
+        }
+        let state = wifi.get_or_insert(WifiState { enabled, network: None, strength: None });
+        let access_point = system.get_property(NM_NAME, device, NM_WIRELESS_INTERFACE, "ActiveAccessPoint").await;
+        let Some(access_point) = access_point.ok().and_then(|path| path.as_str().map(str::to_string)).filter(|path| path != "/") else {
+            continue;
+        };
+        let Ok(properties) = system.get_all_properties(NM_NAME, &access_point, NM_ACCESS_POINT_INTERFACE).await else {
+            continue;
+        };
+        // The SSID is a byte array
+        let ssid: Vec<u8> = properties
+            .get("Ssid")
+            .and_then(Value::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|byte| byte.as_i64().and_then(|byte| u8::try_from(byte).ok()))
+            .collect();
+        state.network = (!ssid.is_empty()).then(|| String::from_utf8_lossy(&ssid).into_owned());
+        state.strength = properties.get("Strength").and_then(Value::as_i64).and_then(|strength| u8::try_from(strength).ok());
+        break;
+    }
+    wifi
+}
+
+/// Object path and state of the first Bluetooth adapter, with the names of
+/// its connected devices
+async fn read_bluetooth(system: &Connection) -> Option<(String, BluetoothState)> {
+    let objects = system.call_method(BLUEZ_NAME, "/", OBJECT_MANAGER_INTERFACE, "GetManagedObjects", Vec::new()).await.ok()?;
+    // a{oa{sa{sv}}}, object paths to their interfaces and properties
+    let objects: Vec<(&str, &Value)> = objects
+        .first()?
+        .as_array()?
+        .iter()
+        .filter_map(|entry| match entry {
+            Value::DictEntry(path, interfaces) => Some((path.as_str()?, interfaces.as_ref())),
+            _ => None,
+        })
+        .collect();
+    let (adapter, properties) = objects
+        .iter()
+        .filter_map(|(path, interfaces)| Some((*path, interfaces.get(BLUEZ_ADAPTER_INTERFACE)?)))
+        .min_by_key(|(path, _)| *path)?;
+    let connected_devices = objects
+        .iter()
+        .filter(|(path, _)| path.starts_with(&format!("{}/", adapter)))
+        .filter_map(|(_, interfaces)| interfaces.get(BLUEZ_DEVICE_INTERFACE))
+        .filter(|device| device.get("Connected").and_then(Value::as_bool).unwrap_or(false))
+        .filter_map(|device| device.get("Alias").or_else(|| device.get("Name")).and_then(Value::as_str).map(str::to_string))
+        .collect();
+    let state = BluetoothState {
+        powered: properties.get("Powered").and_then(Value::as_bool).unwrap_or(false),
+        connected_devices,
+    };
+    Some((adapter.to_string(), state))
+}
+
+/// Volume of the default PipeWire sink; unavailable without wpctl
+async fn read_volume() -> Option<AudioState> {
+    let output = tokio::process::Command::new("wpctl").args(["get-volume", DEFAULT_SINK]).output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_wpctl_volume(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse wpctl get-volume output, e.g. "Volume: 0.40 [MUTED]"
+fn parse_wpctl_volume(output: &str) -> Option<AudioState> {
+    let mut words = output.strip_prefix("Volume:")?.split_whitespace();
+    let volume: f32 = words.next()?.parse().ok()?;
+    Some(AudioState {
+        volume: volume.clamp(0.0, 1.0),
+        muted: words.any(|word| word == "[MUTED]"),
+    })
+}
+
+/// Run a wpctl command
+async fn wpctl(args: &[&str]) -> Result<()> {
+    let status = tokio::process::Command::new("wpctl").args(args).status().await?;
+    if !status.success() {
+        return Err(CompositorError::ipc(format!("wpctl {} failed: {}", args.join(" "), status)));
+    }
+    Ok(())
+}
@@ -0,0 +1,384 @@
+// Screen reader support
+//
+// Client windows are mirrored into the shared accessibility tree as frames
+// named after their titles, with focus following keyboard focus. The AT-SPI
+// bridge embeds the tree in the AT-SPI registry on the accessibility bus,
+// answers screen reader queries about its nodes and turns tree changes into
+// AT-SPI event signals so screen readers such as Orca can announce window
+// and compositor UI interactions.
+
+use compositor_utils::accessibility::{
+    AccessibilityEvent, AccessibilityTree, AccessibleId, AccessibleNode, AccessibleRole, AccessibleState, Politeness,
+};
+use compositor_utils::dbus::{Connection, Message, MessageType, Value, PROPERTIES_INTERFACE, UNKNOWN_METHOD_ERROR};
+use compositor_utils::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use wayland_server::backend::ObjectId;
+
+/// Object path prefix of accessible objects on the accessibility bus
+pub const ATSPI_PATH_PREFIX: &str = "/org/a11y/atspi/accessible";
+
+const ATSPI_REGISTRY_NAME: &str = "org.a11y.atspi.Registry";
+const ATSPI_SOCKET_INTERFACE: &str = "org.a11y.atspi.Socket";
+const ATSPI_ACCESSIBLE_INTERFACE: &str = "org.a11y.atspi.Accessible";
+const ATSPI_APPLICATION_INTERFACE: &str = "org.a11y.atspi.Application";
+const UNKNOWN_PROPERTY_ERROR: &str = "org.freedesktop.DBus.Error.UnknownProperty";
+
+/// Mirrors client windows into the accessibility tree
+pub struct WindowAccessibility {
+    tree: Arc<AccessibilityTree>,
+    windows: HashMap<ObjectId, AccessibleId>,
+}
+
+impl WindowAccessibility {
+    /// Track windows in `tree`
+    pub fn new(tree: Arc<AccessibilityTree>) -> Self {
+        Self {
+            tree,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Shared tree, for compositor UI and the AT-SPI bridge
+    pub fn tree(&self) -> Arc<AccessibilityTree> {
+        self.tree.clone()
+    }
+
+    /// Register a new window
+    pub fn add_window(&mut self, window: ObjectId, title: &str) {
+        let id = self.tree.add(AccessibleId::ROOT, AccessibleRole::Frame, title);
+        self.windows.insert(window, id);
+    }
+
+    /// Update a window's title; unchanged titles are not reported
+    pub fn set_title(&mut self, window: &ObjectId, title: &str) {
+        if let Some(&id) = self.windows.get(window) {
+            self.tree.set_name(id, title);
+        }
+    }
+
+    /// Forget a destroyed window
+    pub fn remove_window(&mut self, window: &ObjectId) {
+        if let Some(id) = self.windows.remove(window) {
+            self.tree.remove(id);
+        }
+    }
+
+    /// Follow keyboard focus
+    ///
+    /// Focus on surfaces that are not tracked windows (layer surfaces,
+    /// popups) clears window focus.
+    pub fn focus_window(&mut self, window: Option<&ObjectId>) {
+        let id = window.and_then(|window| self.windows.get(window).copied());
+        self.tree.focus(id);
+    }
+}
+
+/// AT-SPI event signal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtspiSignal {
+    /// Object path of the source accessible
+    pub path: String,
+    pub interface: &'static str,
+    pub member: &'static str,
+    pub detail: String,
+    pub detail1: i32,
+    pub detail2: i32,
+    /// String payload (names, announcement text, child paths)
+    pub value: String,
+}
+
+/// Object path of an accessible
+pub fn object_path(id: AccessibleId) -> String {
+    if id == AccessibleId::ROOT {
+        format!("{}/root", ATSPI_PATH_PREFIX)
+    } else {
+        format!("{}/{}", ATSPI_PATH_PREFIX, id.as_u64())
+    }
+}
+
+/// Translate a tree change into the AT-SPI signal describing it
+pub fn atspi_signal(event: &AccessibilityEvent) -> AtspiSignal {
+    const OBJECT: &str = "org.a11y.atspi.Event.Object";
+    const FOCUS: &str = "org.a11y.atspi.Event.Focus";
+
+    let signal = |id: AccessibleId, interface, member, detail: &str, detail1, value: String| AtspiSignal {
+        path: object_path(id),
+        interface,
+        member,
+        detail: detail.to_string(),
+        detail1,
+        detail2: 0,
+        value,
+    };
+
+    match event {
+        AccessibilityEvent::NodeAdded { id, parent } => {
+            signal(*parent, OBJECT, "ChildrenChanged", "add", -1, object_path(*id))
+        }
+        AccessibilityEvent::NodeRemoved { id, parent } => {
+            signal(*parent, OBJECT, "ChildrenChanged", "remove", -1, object_path(*id))
+        }
+        AccessibilityEvent::NameChanged { id, name } => {
+            signal(*id, OBJECT, "PropertyChange", "accessible-name", 0, name.clone())
+        }
+        AccessibilityEvent::StateChanged { id, state, enabled } => {
+            signal(*id, OBJECT, "StateChanged", state.atspi_name(), *enabled as i32, String::new())
+        }
+        // Older screen readers still listen for the legacy focus event
+        AccessibilityEvent::FocusChanged { id } => {
+            signal(*id, FOCUS, "Focus", "", 0, String::new())
+        }
+        AccessibilityEvent::Announcement { text, politeness } => {
+            let politeness = match politeness {
+                Politeness::Polite => 1,
+                Politeness::Assertive => 2,
+            };
+            signal(AccessibleId::ROOT, OBJECT, "Announcement", "", politeness, text.clone())
+        }
+    }
+}
+
+/// Forwards accessibility tree changes to AT-SPI
+pub struct AtspiBridge {
+    tree: Arc<AccessibilityTree>,
+    events: broadcast::Receiver<AccessibilityEvent>,
+}
+
+impl AtspiBridge {
+    /// Create a bridge for a tree
+    pub fn new(tree: Arc<AccessibilityTree>) -> Self {
+        let events = tree.subscribe();
+        Self { tree, events }
+    }
+
+    /// Forward events until the tree is dropped
+    pub async fn run(mut self) {
+        info!("AT-SPI bridge started");
+        if let Err(e) = self.serve().await {
+            warn!("Screen readers cannot follow the compositor: {}", e);
+        }
+        info!("AT-SPI bridge stopped");
+    }
+
+    /// Embed the tree in the AT-SPI registry, answer screen reader queries
+    /// about it and emit its events, until the tree is dropped or the
+    /// accessibility bus goes away
+    async fn serve(&mut self) -> Result<()> {
+        let address = accessibility_bus_address().await?;
+        let (connection, mut incoming) = Connection::connect(&address).await?;
+        let root = Value::Struct(vec![Value::from(connection.unique_name()), Value::ObjectPath(object_path(AccessibleId::ROOT))]);
+        let mut reply = connection
+            .call_method(ATSPI_REGISTRY_NAME, &object_path(AccessibleId::ROOT), ATSPI_SOCKET_INTERFACE, "Embed", vec![root])
+            .await?;
+        let registry = reply.pop().ok_or_else(|| CompositorError::ipc("No registry root from Embed"))?;
+        info!("Registered with the AT-SPI registry");
+
+        loop {
+            tokio::select! {
+                message = incoming.recv() => {
+                    let Some(message) = message else { break };
+                    if message.kind == MessageType::MethodCall {
+                        if let Err(e) = self.answer(&connection, &registry, &message).await {
+                            debug!("Failed to answer AT-SPI call {:?}: {}", message.member, e);
+                        }
+                    }
+                }
+                event = self.events.recv() => match event {
+                    Ok(event) => {
+                        let signal = atspi_signal(&event);
+                        trace!("AT-SPI {}.{} {} on {}", signal.interface, signal.member, signal.detail, signal.path);
+                        let body = signal.body(connection.unique_name());
+                        if let Err(e) = connection.emit_signal(&signal.path, signal.interface, signal.member, body).await {
+                            warn!("Failed to emit AT-SPI {}: {}", signal.member, e);
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("AT-SPI bridge dropped {} accessibility events", skipped);
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                }
+            }
+        }
+        Err(CompositorError::ipc("Accessibility bus connection closed"))
+    }
+
+    /// Answer a screen reader's call on an accessible object
+    async fn answer(&self, connection: &Connection, registry: &Value, message: &Message) -> Result<()> {
+        let node = message.path.as_deref().and_then(accessible_id).and_then(|id| self.tree.node(id));
+        let Some(node) = node else {
+            return connection
+                .reply_error(message, "org.freedesktop.DBus.Error.UnknownObject", "No such accessible")
+                .await;
+        };
+        let reference = |id: AccessibleId| {
+            Value::Struct(vec![Value::from(connection.unique_name()), Value::ObjectPath(object_path(id))])
+        };
+        let parent = || node.parent.map_or_else(|| registry.clone(), reference);
+        let arg = |index: usize| message.body.get(index);
+
+        let body = match message.member.as_deref().unwrap_or_default() {
+            "Get" if message.interface.as_deref() == Some(PROPERTIES_INTERFACE) => {
+                let interface = arg(0).and_then(Value::as_str).unwrap_or_default();
+                let name = arg(1).and_then(Value::as_str).unwrap_or_default();
+                match accessible_properties(&node, interface, parent()).into_iter().find(|(key, _)| key == name) {
+                    Some((_, value)) => vec![Value::Variant(Box::new(value))],
+                    None => {
+                        let text = format!("No property {}.{}", interface, name);
+                        return connection.reply_error(message, UNKNOWN_PROPERTY_ERROR, &text).await;
+                    }
+                }
+            }
+            "GetAll" if message.interface.as_deref() == Some(PROPERTIES_INTERFACE) => {
+                let interface = arg(0).and_then(Value::as_str).unwrap_or_default();
+                vec![Value::dict(accessible_properties(&node, interface, parent()))]
+            }
+            // Orca sets the application ID, which is only read back by itself
+            "Set" if message.interface.as_deref() == Some(PROPERTIES_INTERFACE) => Vec::new(),
+            "GetChildren" => vec![Value::Array("(so)".to_string(), node.children.iter().copied().map(reference).collect())],
+            "GetChildAtIndex" => {
+                let child = arg(0)
+                    .and_then(Value::as_i64)
+                    .and_then(|index| usize::try_from(index).ok())
+                    .and_then(|index| node.children.get(index));
+                // Out of range children are the null reference
+                vec![child.map_or_else(
+                    || Value::Struct(vec![Value::from(""), Value::ObjectPath("/org/a11y/atspi/null".to_string())]),
+                    |&child| reference(child),
+                )]
+            }
+            "GetIndexInParent" => {
+                let index = node
+                    .parent
+                    .and_then(|parent| self.tree.node(parent))
+                    .and_then(|parent| parent.children.iter().position(|&child| child == node.id))
+                    .map_or(-1, |index| i32::try_from(index).unwrap_or(i32::MAX));
+                vec![Value::Int32(index)]
+            }
+            "GetRole" => vec![Value::UInt32(node.role.atspi_role())],
+            "GetRoleName" | "GetLocalizedRoleName" => vec![Value::from(role_name(node.role))],
+            "GetState" => vec![Value::Array("u".to_string(), state_set(&node).into_iter().map(Value::UInt32).collect())],
+            "GetAttributes" => vec![Value::Array("{ss}".to_string(), Vec::new())],
+            "GetRelationSet" => vec![Value::Array("(ua(so))".to_string(), Vec::new())],
+            "GetApplication" => vec![reference(AccessibleId::ROOT)],
+            "GetInterfaces" => {
+                let mut interfaces = vec![Value::from(ATSPI_ACCESSIBLE_INTERFACE)];
+                if node.id == AccessibleId::ROOT {
+                    interfaces.push(Value::from(ATSPI_APPLICATION_INTERFACE));
+                }
+                vec![Value::Array("s".to_string(), interfaces)]
+            }
+            member => {
+                let text = format!("No method {}", member);
+                return connection.reply_error(message, UNKNOWN_METHOD_ERROR, &text).await;
+            }
+        };
+        connection.reply(message, body).await
+    }
+}
+
+impl AtspiSignal {
+    /// Event arguments (siiva{sv}): detail, detail1, detail2, any_data and
+    /// properties, with children referenced on the bus name `bus_name`
+    pub fn body(&self, bus_name: &str) -> Vec<Value> {
+        let any_data = match self.member {
+            "ChildrenChanged" => Value::Struct(vec![Value::from(bus_name), Value::ObjectPath(self.value.clone())]),
+            "PropertyChange" | "Announcement" => Value::from(self.value.as_str()),
+            _ => Value::Int32(0),
+        };
+        vec![
+            Value::from(self.detail.as_str()),
+            Value::Int32(self.detail1),
+            Value::Int32(self.detail2),
+            Value::Variant(Box::new(any_data)),
+            Value::Array("{sv}".to_string(), Vec::new()),
+        ]
+    }
+}
+
+/// Address of the accessibility bus, from the environment or the session
+/// bus's org.a11y.Bus service
+async fn accessibility_bus_address() -> Result<String> {
+    if let Ok(address) = std::env::var("AT_SPI_BUS_ADDRESS") {
+        return Ok(address);
+    }
+    let (session, _) = Connection::session().await?;
+    let reply = session.call_method("org.a11y.Bus", "/org/a11y/bus", "org.a11y.Bus", "GetAddress", Vec::new()).await?;
+    reply
+        .first()
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| CompositorError::ipc("No accessibility bus address"))
+}
+
+/// Accessible from its object path
+fn accessible_id(path: &str) -> Option<AccessibleId> {
+    match path.strip_prefix(ATSPI_PATH_PREFIX)?.strip_prefix('/')? {
+        "root" => Some(AccessibleId::ROOT),
+        id => {
+            let id = id.parse().ok()?;
+            (id != AccessibleId::ROOT.as_u64()).then(|| AccessibleId::from_u64(id))
+        }
+    }
+}
+
+/// Properties of an interface of an accessible
+fn accessible_properties(node: &AccessibleNode, interface: &str, parent: Value) -> Vec<(String, Value)> {
+    let property = |name: &str, value: Value| (name.to_string(), value);
+    match interface {
+        ATSPI_ACCESSIBLE_INTERFACE => vec![
+            property("Name", Value::from(node.name.as_str())),
+            property("Description", Value::from(node.description.as_str())),
+            property("Parent", parent),
+            property("ChildCount", Value::Int32(i32::try_from(node.children.len()).unwrap_or(i32::MAX))),
+            property("Locale", Value::from("")),
+            property("AccessibleId", Value::from(node.id.as_u64().to_string())),
+        ],
+        ATSPI_APPLICATION_INTERFACE if node.id == AccessibleId::ROOT => vec![
+            property("ToolkitName", Value::from(env!("CARGO_PKG_NAME"))),
+            property("Version", Value::from(env!("CARGO_PKG_VERSION"))),
+            property("AtspiVersion", Value::from("2.1")),
+            property("Id", Value::Int32(0)),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+/// AT-SPI role name of a role
+fn role_name(role: AccessibleRole) -> &'static str {
+    match role {
+        AccessibleRole::Application => "application",
+        AccessibleRole::Frame => "frame",
+        AccessibleRole::Panel => "panel",
+        AccessibleRole::ToolBar => "tool bar",
+        AccessibleRole::PushButton => "push button",
+        AccessibleRole::ToggleButton => "toggle button",
+        AccessibleRole::List => "list",
+        AccessibleRole::ListItem => "list item",
+        AccessibleRole::Label => "label",
+        AccessibleRole::Slider => "slider",
+    }
+}
+
+/// AT-SPI state set of an accessible, as two 32-bit words of AtspiStateType bits
+fn state_set(node: &AccessibleNode) -> [u32; 2] {
+    let mut bits = 0u64;
+    for state in &node.states {
+        let bit = match state {
+            AccessibleState::Focusable => 11,
+            AccessibleState::Focused => 12,
+            AccessibleState::Pressed => 20,
+            AccessibleState::Selected => 23,
+            AccessibleState::Sensitive => 24,
+            // Showing nodes are also visible
+            AccessibleState::Showing => {
+                bits |= 1 << 30;
+                25
+            }
+        };
+        bits |= 1 << bit;
+    }
+    [bits as u32, (bits >> 32) as u32]
+}
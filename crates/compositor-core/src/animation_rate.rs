@@ -0,0 +1,66 @@
+// Animation step rates
+//
+// Compositor animations step at a configured rate per class of effect rather
+// than on every display refresh; frames in between interpolate. The app
+// bar's widgets follow the UI rate on their own.
+
+use compositor_utils::animation_tick::AnimationTicker;
+use config::AnimationRatesConfig;
+use std::time::Instant;
+
+/// Class of effect sharing a step rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationClass {
+    /// Windows moving, e.g. for show desktop
+    Windows,
+    /// Workspace wallpaper and accent cross-fades
+    Workspaces,
+    /// Outputs fading for power saving
+    Outputs,
+}
+
+/// When each class of compositor animation steps
+#[derive(Debug, Default)]
+pub struct AnimationRates {
+    windows: AnimationTicker,
+    workspaces: AnimationTicker,
+    outputs: AnimationTicker,
+}
+
+impl AnimationRates {
+    pub fn new(config: &AnimationRatesConfig) -> Self {
+        let mut rates = Self::default();
+        rates.set_config(config);
+        rates
+    }
+
+    /// Apply new rates
+    pub fn set_config(&mut self, config: &AnimationRatesConfig) {
+        self.windows.set_rate(config.windows);
+        self.workspaces.set_rate(config.workspaces);
+        self.outputs.set_rate(config.outputs);
+    }
+
+    /// Whether animations of a class step on a frame drawn at `now`
+    pub fn tick(&mut self, class: AnimationClass, now: Instant) -> bool {
+        self.ticker_mut(class).tick(now)
+    }
+
+    /// How far a frame drawn at `now` is between the last two steps of a
+    /// class, for interpolating
+    pub fn alpha(&self, class: AnimationClass, now: Instant) -> f32 {
+        match class {
+            AnimationClass::Windows => self.windows.alpha(now),
+            AnimationClass::Workspaces => self.workspaces.alpha(now),
+            AnimationClass::Outputs => self.outputs.alpha(now),
+        }
+    }
+
+    fn ticker_mut(&mut self, class: AnimationClass) -> &mut AnimationTicker {
+        match class {
+            AnimationClass::Windows => &mut self.windows,
+            AnimationClass::Workspaces => &mut self.workspaces,
+            AnimationClass::Outputs => &mut self.outputs,
+        }
+    }
+}
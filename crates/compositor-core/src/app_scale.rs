@@ -0,0 +1,117 @@
+// Per-application scale overrides
+//
+// Some toolkits misrender under fractional scaling, with blurry text or
+// off-by-one input regions. A window rule with `scale` tells the matching app
+// to render at that scale whatever its output's, e.g. 1.0 for legacy apps,
+// and one with `fractional_scaling = false` rounds the output scale up to a
+// whole number for it. The renderer then scales the app's buffers to the
+// output, sampling them with trilinear filtering when they are scaled down.
+// The override covers the window's surface and its subsurfaces.
+
+use config::WindowRule;
+use std::collections::HashMap;
+use wayland_server::backend::ObjectId;
+
+/// Scale an app renders at instead of its output's
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScaleOverride {
+    /// This scale on every output
+    Fixed(f64),
+    /// The output scale rounded up to a whole number
+    Integer,
+}
+
+impl ScaleOverride {
+    /// Scale to tell the app on an output with `output_scale`
+    pub fn apply(self, output_scale: f64) -> f64 {
+        match self {
+            Self::Fixed(scale) => scale,
+            Self::Integer => output_scale.ceil().max(1.0),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct WindowScale {
+    app_id: String,
+    /// `None` when no rule overrides the app's scale
+    scale: Option<ScaleOverride>,
+}
+
+/// Scale overrides of all windows
+#[derive(Debug, Default)]
+pub struct AppScales {
+    rules: Vec<WindowRule>,
+    /// Windows keyed by their root surface
+    windows: HashMap<ObjectId, WindowScale>,
+}
+
+impl AppScales {
+    /// Create a tracker with no rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the window rules, returning the windows whose override changed
+    pub fn set_rules(&mut self, rules: Vec<WindowRule>) -> Vec<ObjectId> {
+        self.rules = rules;
+        let mut changed = Vec::new();
+        for (window, scale) in &mut self.windows {
+            let new = scale_override(&self.rules, &scale.app_id);
+            if new != scale.scale {
+                scale.scale = new;
+                changed.push(window.clone());
+            }
+        }
+        changed
+    }
+
+    /// Apply window rules when a window's app ID becomes known or changes,
+    /// returning whether its override changed
+    pub fn apply_rules(&mut self, window: &ObjectId, app_id: &str) -> bool {
+        let previous = match self.windows.get(window) {
+            Some(scale) if scale.app_id == app_id => return false,
+            Some(scale) => scale.scale,
+            None => None,
+        };
+        let scale = scale_override(&self.rules, app_id);
+        self.windows.insert(window.clone(), WindowScale { app_id: app_id.to_string(), scale });
+        scale != previous
+    }
+
+    /// Forget a destroyed window
+    pub fn remove_window(&mut self, window: &ObjectId) {
+        self.windows.remove(window);
+    }
+
+    /// Scale to tell `window` on an output with `output_scale`
+    pub fn preferred_scale(&self, window: &ObjectId, output_scale: f64) -> f64 {
+        self.windows
+            .get(window)
+            .and_then(|scale| scale.scale)
+            .map_or(output_scale, |scale| scale.apply(output_scale))
+    }
+
+    /// Protocol IDs of the root surfaces of windows with an override, for
+    /// the renderer to filter
+    pub fn surface_ids(&self) -> Vec<u32> {
+        self.windows
+            .iter()
+            .filter(|(_, scale)| scale.scale.is_some())
+            .map(|(window, _)| window.protocol_id())
+            .collect()
+    }
+}
+
+/// Scale override for an app; later matching rules take precedence
+fn scale_override(rules: &[WindowRule], app_id: &str) -> Option<ScaleOverride> {
+    rules
+        .iter()
+        .rev()
+        .filter(|rule| rule.matches(app_id))
+        .find_map(|rule| match (rule.scale, rule.fractional_scaling) {
+            (Some(scale), _) => Some(ScaleOverride::Fixed(scale)),
+            (None, Some(false)) => Some(ScaleOverride::Integer),
+            _ => None,
+        })
+}
@@ -0,0 +1,49 @@
+// Automation requests from IPC
+//
+// Accessibility tools and test harnesses can warp the pointer, move keyboard
+// focus and, with the input injection permission, synthesize clicks and key
+// presses over IPC. The IPC handler validates and forwards requests through
+// this queue; the compositor applies them on its own thread so they go
+// through the same seat as real input.
+
+use compositor_utils::prelude::*;
+use ipc::protocol::AutomationRequest;
+use tokio::sync::mpsc;
+
+/// Queue of automation requests waiting to be applied
+#[derive(Debug)]
+pub struct AutomationQueue {
+    sender: mpsc::UnboundedSender<AutomationRequest>,
+    receiver: mpsc::UnboundedReceiver<AutomationRequest>,
+}
+
+impl AutomationQueue {
+    /// Create an empty automation queue
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self { sender, receiver }
+    }
+
+    /// Channel for IPC to submit requests
+    pub fn sender(&self) -> mpsc::UnboundedSender<AutomationRequest> {
+        self.sender.clone()
+    }
+
+    /// Take all pending requests in submission order
+    pub fn drain(&mut self) -> Vec<AutomationRequest> {
+        let mut requests = Vec::new();
+        while let Ok(request) = self.receiver.try_recv() {
+            requests.push(request);
+        }
+        if !requests.is_empty() {
+            debug!("Applying {} automation requests", requests.len());
+        }
+        requests
+    }
+}
+
+impl Default for AutomationQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
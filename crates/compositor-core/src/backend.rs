@@ -1,5 +1,5 @@
 use compositor_utils::prelude::*;
-use crate::session::SessionManager;
+use crate::session::{DeviceAccess, SessionManager};
 
 /// Backend type selection
 #[derive(Debug, Clone)]
@@ -140,11 +140,6 @@ impl Backend {
         // Process session events to maintain DRM access
         if let Some(ref mut session_manager) = self.session_manager {
             session_manager.dispatch_events(Some(1))?; // Non-blocking check
-            
-            if !session_manager.is_active() {
-                warn!("Session deactivated - compositor paused");
-                // In a real implementation, we'd pause rendering and wait for reactivation
-            }
         }
         
         // TODO: Process DRM and libinput events
@@ -162,6 +157,17 @@ impl Backend {
         self.session_manager.as_ref()?.get_drm_fd().ok()
     }
     
+    /// Handle for opening devices through the session, if there is one
+    pub fn device_access(&self) -> Option<DeviceAccess> {
+        self.session_manager.as_ref().map(SessionManager::device_access)
+    }
+    
+    /// Whether the session lost access to devices, e.g. while switched away
+    /// or suspended; backends without a session are never paused
+    pub fn is_paused(&self) -> bool {
+        self.session_manager.as_ref().is_some_and(|sm| !sm.is_active())
+    }
+    
     /// Check if session is active
     pub fn is_session_active(&self) -> bool {
         self.session_manager.as_ref()
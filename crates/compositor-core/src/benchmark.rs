@@ -0,0 +1,261 @@
+// Benchmark mode
+//
+// `--benchmark <scenario.ron>` replaces client windows with synthetic ones
+// whose movement, resizes and content updates are scripted as functions of
+// time, runs them for the scenario's duration and prints a RON report of frame
+// times, so performance can be compared between builds on the same machine.
+
+use compositor_utils::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use vulkan_renderer::VulkanRenderer;
+
+/// Surface IDs of synthetic windows start here to stay clear of client surfaces
+const SYNTHETIC_SURFACE_BASE: u32 = 0x4000_0000;
+
+/// Scripted movement of a synthetic window
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum WindowMotion {
+    /// Stay at the initial position
+    #[default]
+    Static,
+    /// Circle around the initial position
+    Orbit { radius: f32, period_secs: f32 },
+    /// Slide back and forth by an offset
+    Slide { dx: f32, dy: f32, period_secs: f32 },
+}
+
+/// Scripted resizing between the initial size and a target size
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResizeCycle {
+    pub width: u32,
+    pub height: u32,
+    pub period_secs: f32,
+}
+
+/// A synthetic window in a benchmark scenario
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyntheticWindow {
+    pub x: f32,
+    pub y: f32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub motion: WindowMotion,
+    #[serde(default)]
+    pub resize: Option<ResizeCycle>,
+    /// Content updates per second; 0.0 keeps the first frame
+    #[serde(default)]
+    pub content_fps: f32,
+}
+
+impl SyntheticWindow {
+    /// Position and size at `t` seconds into the scenario
+    pub fn geometry_at(&self, t: f32) -> (Vec2, (u32, u32)) {
+        let origin = Vec2::new(self.x, self.y);
+        let position = match self.motion {
+            WindowMotion::Static => origin,
+            WindowMotion::Orbit { radius, period_secs } => {
+                let angle = std::f32::consts::TAU * phase(t, period_secs);
+                origin + Vec2::new(angle.cos(), angle.sin()) * radius
+            }
+            WindowMotion::Slide { dx, dy, period_secs } => {
+                origin + Vec2::new(dx, dy) * triangle(phase(t, period_secs))
+            }
+        };
+
+        let size = match &self.resize {
+            Some(resize) => {
+                let blend = triangle(phase(t, resize.period_secs));
+                let lerp = |from: u32, to: u32| (from as f32 + (to as f32 - from as f32) * blend).round().max(1.0) as u32;
+                (lerp(self.width, resize.width), lerp(self.height, resize.height))
+            }
+            None => (self.width, self.height),
+        };
+        (position, size)
+    }
+
+    /// Index of the content frame shown at `t` seconds
+    pub fn content_frame_at(&self, t: f32) -> u64 {
+        if self.content_fps > 0.0 { (t * self.content_fps) as u64 } else { 0 }
+    }
+}
+
+/// Fraction of the current period elapsed at `t`
+fn phase(t: f32, period_secs: f32) -> f32 {
+    if period_secs > 0.0 { (t / period_secs).fract() } else { 0.0 }
+}
+
+/// Ramp 0 -> 1 -> 0 over one period
+fn triangle(phase: f32) -> f32 {
+    1.0 - (2.0 * phase - 1.0).abs()
+}
+
+/// A scripted benchmark run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkScenario {
+    pub name: String,
+    pub duration_secs: f32,
+    pub windows: Vec<SyntheticWindow>,
+}
+
+impl BenchmarkScenario {
+    /// Load a scenario from a RON file
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| CompositorError::configuration(format!("Failed to read benchmark scenario: {}", e)))?;
+
+        let scenario: BenchmarkScenario = ron::from_str(&content)
+            .map_err(|e| CompositorError::configuration(format!("Failed to parse benchmark scenario: {}", e)))?;
+
+        if !scenario.duration_secs.is_finite() || scenario.duration_secs <= 0.0 {
+            return Err(CompositorError::configuration("Benchmark duration must be positive"));
+        }
+        Ok(scenario)
+    }
+}
+
+/// Machine-readable benchmark results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub scenario: String,
+    pub version: String,
+    pub duration_secs: f32,
+    pub frames: u64,
+    pub average_fps: f32,
+    pub frame_time_average_ms: f32,
+    pub frame_time_p50_ms: f32,
+    pub frame_time_p95_ms: f32,
+    pub frame_time_p99_ms: f32,
+    pub frame_time_max_ms: f32,
+    /// Texture uploads for synthetic window content and resizes
+    pub surface_updates: u64,
+    pub uploaded_megabytes: f32,
+    /// Peak device-local GPU memory use, when the driver reports it
+    pub peak_gpu_memory_megabytes: Option<f32>,
+}
+
+impl BenchmarkReport {
+    /// Serialize the report as pretty-printed RON
+    pub fn to_ron(&self) -> Result<String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| CompositorError::runtime(format!("Failed to serialize benchmark report: {}", e)))
+    }
+}
+
+/// Drive a scenario on the renderer and measure frame times
+pub fn run_benchmark(renderer: &mut VulkanRenderer, scenario: &BenchmarkScenario) -> Result<BenchmarkReport> {
+    info!("Running benchmark '{}' with {} windows for {:.1}s", scenario.name, scenario.windows.len(), scenario.duration_secs);
+
+    let duration = Duration::from_secs_f32(scenario.duration_secs);
+    let mut shown: Vec<Option<(u64, (u32, u32))>> = vec![None; scenario.windows.len()];
+    let mut frame_times = Vec::new();
+    let mut surface_updates = 0u64;
+    let mut uploaded_bytes = 0u64;
+    let mut peak_gpu_memory: Option<u64> = None;
+    let mut has_output = true;
+
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        let frame_start = Instant::now();
+        let t = frame_start.duration_since(start).as_secs_f32();
+
+        for (index, window) in scenario.windows.iter().enumerate() {
+            // TODO: Place windows at their scripted position once the renderer
+            // positions surfaces; until then only size and content changes cost work
+            let (_position, size) = window.geometry_at(t);
+            let content_frame = window.content_frame_at(t);
+            if shown[index] == Some((content_frame, size)) {
+                continue;
+            }
+
+            let pixels = synthetic_content(size, content_frame);
+            renderer.update_surface_texture(
+                SYNTHETIC_SURFACE_BASE + index as u32,
+                &pixels,
+                size.0,
+                size.1,
+                ash::vk::Format::B8G8R8A8_UNORM,
+            )?;
+            shown[index] = Some((content_frame, size));
+            surface_updates += 1;
+            uploaded_bytes += pixels.len() as u64;
+        }
+
+        if has_output {
+            match renderer.begin_frame() {
+                Ok(_) => {
+                    renderer.end_frame()?;
+                    renderer.wait_for_present(Duration::from_millis(100))?;
+                }
+                Err(e) => {
+                    warn!("Benchmark has no output to present to ({}); measuring surface updates only", e);
+                    has_output = false;
+                }
+            }
+        }
+
+        if !has_output {
+            // Uploads otherwise wait for the next frame
+            renderer.flush_surface_uploads()?;
+        }
+
+        if let Some(usage) = renderer.memory_usage() {
+            let used = usage.device_local_usage();
+            peak_gpu_memory = Some(peak_gpu_memory.map_or(used, |peak| peak.max(used)));
+        }
+        frame_times.push(frame_start.elapsed());
+    }
+
+    for index in 0..scenario.windows.len() {
+        renderer.remove_surface(SYNTHETIC_SURFACE_BASE + index as u32)?;
+    }
+
+    let elapsed = start.elapsed().as_secs_f32();
+    let report = build_report(scenario, elapsed, frame_times, surface_updates, uploaded_bytes, peak_gpu_memory);
+    info!("Benchmark '{}' finished: {} frames, {:.1} FPS", scenario.name, report.frames, report.average_fps);
+    Ok(report)
+}
+
+/// Solid color that changes with every content frame, so each update is a real upload
+fn synthetic_content((width, height): (u32, u32), content_frame: u64) -> Vec<u8> {
+    let shade = (content_frame.wrapping_mul(37) % 256) as u8;
+    [shade, 255 - shade, 128, 255].repeat(width as usize * height as usize)
+}
+
+fn build_report(
+    scenario: &BenchmarkScenario,
+    elapsed: f32,
+    mut frame_times: Vec<Duration>,
+    surface_updates: u64,
+    uploaded_bytes: u64,
+    peak_gpu_memory: Option<u64>,
+) -> BenchmarkReport {
+    const MEGABYTE: f32 = 1024.0 * 1024.0;
+
+    frame_times.sort_unstable();
+    let millis = |duration: Duration| duration.as_secs_f32() * 1000.0;
+    let percentile = |fraction: f32| {
+        let index = ((frame_times.len() as f32 * fraction).ceil() as usize).saturating_sub(1);
+        frame_times.get(index).copied().map_or(0.0, millis)
+    };
+    let frames = frame_times.len() as u64;
+    let total: Duration = frame_times.iter().sum();
+
+    BenchmarkReport {
+        scenario: scenario.name.clone(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        duration_secs: elapsed,
+        frames,
+        average_fps: if elapsed > 0.0 { frames as f32 / elapsed } else { 0.0 },
+        frame_time_average_ms: if frames > 0 { millis(total) / frames as f32 } else { 0.0 },
+        frame_time_p50_ms: percentile(0.50),
+        frame_time_p95_ms: percentile(0.95),
+        frame_time_p99_ms: percentile(0.99),
+        frame_time_max_ms: frame_times.last().copied().map_or(0.0, millis),
+        surface_updates,
+        uploaded_megabytes: uploaded_bytes as f32 / MEGABYTE,
+        peak_gpu_memory_megabytes: peak_gpu_memory.map(|bytes| bytes as f32 / MEGABYTE),
+    }
+}
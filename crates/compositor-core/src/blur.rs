@@ -0,0 +1,125 @@
+// Rule-based background blur behind client surfaces
+//
+// Surfaces matched by a blur rule (by toplevel app ID or layer-shell
+// namespace) get the content behind them blurred, similar to KDE's blur
+// protocol. Only the transparent part of the surface is blurred: the region
+// the client declares opaque is masked out, since nothing behind it shows.
+
+use compositor_utils::prelude::*;
+use config::BlurConfig;
+use smithay::utils::{Logical, Point, Rectangle, Size};
+use smithay::wayland::compositor::{RectangleKind, RegionAttributes};
+use std::collections::HashMap;
+use vulkan_renderer::SurfaceBlurRequest;
+use wayland_server::backend::ObjectId;
+
+/// Blur request for a single surface
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceBlur {
+    /// Radius from the matching rule; the default radius, tunable over IPC, when unset
+    pub radius: Option<f32>,
+    /// Opaque region from the last commit, in surface-local coordinates
+    opaque: Vec<Rectangle<i32, Logical>>,
+}
+
+impl SurfaceBlur {
+    /// Regions to blur behind a surface of the given size
+    pub fn regions(&self, size: Size<i32, Logical>) -> Vec<Rectangle<i32, Logical>> {
+        Rectangle::from_size(size).subtract_rects(self.opaque.iter().copied())
+    }
+
+    /// Request for the renderer for a surface of the given size, with
+    /// regions relative to `origin`, e.g. where its window geometry starts
+    pub fn request(&self, size: Size<i32, Logical>, origin: Point<i32, Logical>) -> SurfaceBlurRequest {
+        SurfaceBlurRequest {
+            radius: self.radius,
+            regions: self
+                .regions(size)
+                .into_iter()
+                .map(|region| [region.loc.x - origin.x, region.loc.y - origin.y, region.size.w, region.size.h])
+                .collect(),
+        }
+    }
+}
+
+/// Tracks which surfaces request background blur
+#[derive(Debug, Default)]
+pub struct BlurManager {
+    config: BlurConfig,
+    surfaces: HashMap<ObjectId, SurfaceBlur>,
+}
+
+impl BlurManager {
+    /// Create a blur manager from configuration
+    pub fn new(config: BlurConfig) -> Self {
+        Self {
+            config,
+            surfaces: HashMap::new(),
+        }
+    }
+
+    /// Apply new configuration; existing assignments are re-evaluated on the next commit
+    pub fn set_config(&mut self, config: BlurConfig) {
+        self.config = config;
+        self.surfaces.clear();
+    }
+
+    /// Apply rules to a layer surface by its namespace
+    pub fn assign_layer_surface(&mut self, surface: ObjectId, namespace: &str) {
+        let rule = self.config.rule_for(None, Some(namespace)).map(|rule| rule.radius);
+        self.assign(surface, rule);
+    }
+
+    /// Apply rules to a toplevel surface by its app ID
+    pub fn assign_toplevel(&mut self, surface: ObjectId, app_id: Option<&str>) {
+        let rule = self.config.rule_for(app_id, None).map(|rule| rule.radius);
+        self.assign(surface, rule);
+    }
+
+    /// Update the opaque region of a blurred surface after a commit
+    pub fn update_opaque_region(&mut self, surface: &ObjectId, opaque_region: Option<&RegionAttributes>) {
+        if let Some(blur) = self.surfaces.get_mut(surface) {
+            blur.opaque = opaque_rects(opaque_region);
+        }
+    }
+
+    /// Blur request for a surface, if it has one
+    pub fn get(&self, surface: &ObjectId) -> Option<&SurfaceBlur> {
+        self.surfaces.get(surface)
+    }
+
+    /// Forget a destroyed surface
+    pub fn remove_surface(&mut self, surface: &ObjectId) {
+        self.surfaces.remove(surface);
+    }
+
+    /// Enable blur with the radius of the matching rule, if any rule matched
+    fn assign(&mut self, surface: ObjectId, rule: Option<Option<f32>>) {
+        match rule {
+            Some(radius) => {
+                let blur = self.surfaces.entry(surface.clone()).or_insert_with(|| {
+                    debug!("Enabling background blur for surface {:?}", surface);
+                    SurfaceBlur { radius, opaque: Vec::new() }
+                });
+                blur.radius = radius;
+            }
+            None => {
+                self.surfaces.remove(&surface);
+            }
+        }
+    }
+}
+
+/// Flatten a region into the list of rectangles it covers
+fn opaque_rects(region: Option<&RegionAttributes>) -> Vec<Rectangle<i32, Logical>> {
+    let mut rects: Vec<Rectangle<i32, Logical>> = Vec::new();
+    for (kind, rect) in region.map(|region| region.rects.as_slice()).unwrap_or_default() {
+        match kind {
+            RectangleKind::Add => rects.push(*rect),
+            RectangleKind::Subtract => {
+                rects = rects.into_iter().flat_map(|existing| existing.subtract_rect(*rect)).collect();
+            }
+        }
+    }
+    rects
+}
@@ -0,0 +1,182 @@
+// Buffer format statistics and scanout preference
+//
+// Counts the pixel formats and modifiers of the buffers clients attach, over
+// wl_shm or linux-dmabuf, and publishes them for IPC once per second together
+// with whether the display planes accept each format. When enabled, dmabuf
+// feedback gains a scanout tranche listing the plane-compatible formats, most
+// used first, so clients allocate buffers that can go straight to a plane
+// instead of being composited.
+//
+// Plane modifier lists are not read, so only linear dmabuf buffers count as
+// scanout capable.
+
+use ipc::protocol::{BufferFormatUsage, BufferKind};
+use smithay::backend::allocator::{Format, Fourcc, Modifier};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use wayland_server::backend::ObjectId;
+
+/// Interval statistics are published at
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Format and modifier of an attached buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferFormat {
+    pub kind: BufferKind,
+    pub fourcc: u32,
+    /// DRM format modifier of dmabuf buffers
+    pub modifier: Option<u64>,
+}
+
+impl BufferFormat {
+    /// Format of a dmabuf buffer
+    pub fn dmabuf(format: Format) -> Self {
+        Self {
+            kind: BufferKind::Dmabuf,
+            fourcc: format.code as u32,
+            modifier: Some(format.modifier.into()),
+        }
+    }
+
+    /// Format of a shared memory buffer
+    pub fn shm(fourcc: Fourcc) -> Self {
+        Self { kind: BufferKind::Shm, fourcc: fourcc as u32, modifier: None }
+    }
+}
+
+/// Buffer formats in use and the formats display planes accept
+#[derive(Debug)]
+pub struct BufferFormatStats {
+    /// Format of the buffer attached to each surface
+    surfaces: HashMap<ObjectId, BufferFormat>,
+    /// Commits per format since startup
+    commits: HashMap<BufferFormat, u64>,
+    /// Fourcc codes accepted by the display planes
+    scanout_formats: HashSet<u32>,
+    prefer_scanout: bool,
+    /// Formats last sent in the scanout tranche
+    advertised: Vec<Format>,
+    last_published: Option<Instant>,
+    publisher: watch::Sender<Vec<BufferFormatUsage>>,
+}
+
+impl BufferFormatStats {
+    /// Create empty statistics
+    pub fn new() -> Self {
+        Self {
+            surfaces: HashMap::new(),
+            commits: HashMap::new(),
+            scanout_formats: HashSet::new(),
+            prefer_scanout: false,
+            advertised: Vec::new(),
+            last_published: None,
+            publisher: watch::channel(Vec::new()).0,
+        }
+    }
+
+    /// Channel for IPC to read published statistics
+    pub fn subscribe(&self) -> watch::Receiver<Vec<BufferFormatUsage>> {
+        self.publisher.subscribe()
+    }
+
+    /// Enable or disable the scanout tranche in dmabuf feedback
+    pub fn set_prefer_scanout(&mut self, prefer: bool) {
+        self.prefer_scanout = prefer;
+    }
+
+    /// Set the fourcc codes the display planes accept
+    pub fn set_scanout_formats(&mut self, formats: impl IntoIterator<Item = u32>) {
+        self.scanout_formats = formats.into_iter().collect();
+    }
+
+    /// Whether buffers of a format can be scanned out without compositing
+    pub fn is_scanout_capable(&self, format: &BufferFormat) -> bool {
+        format.kind == BufferKind::Dmabuf
+            && format.modifier == Some(Modifier::Linear.into())
+            && self.scanout_formats.contains(&format.fourcc)
+    }
+
+    /// Record a commit; `None` when the surface's buffer was detached
+    pub fn surface_committed(&mut self, surface: ObjectId, format: Option<BufferFormat>) {
+        match format {
+            Some(format) => {
+                *self.commits.entry(format).or_default() += 1;
+                self.surfaces.insert(surface, format);
+            }
+            None => {
+                self.surfaces.remove(&surface);
+            }
+        }
+    }
+
+    /// Forget a destroyed surface
+    pub fn surface_destroyed(&mut self, surface: &ObjectId) {
+        self.surfaces.remove(surface);
+    }
+
+    /// Publish statistics if the interval has passed; returns whether they were
+    pub fn publish(&mut self, now: Instant) -> bool {
+        if self.last_published.is_some_and(|last| now.duration_since(last) < PUBLISH_INTERVAL) {
+            return false;
+        }
+        self.last_published = Some(now);
+
+        let mut usage: Vec<BufferFormatUsage> = self
+            .commits
+            .iter()
+            .map(|(format, &commits)| BufferFormatUsage {
+                kind: format.kind,
+                fourcc: format.fourcc,
+                format: format_name(format.fourcc),
+                modifier: format.modifier,
+                surfaces: self.surfaces.values().filter(|attached| *attached == format).count() as u32,
+                commits,
+                scanout_capable: self.is_scanout_capable(format),
+            })
+            .collect();
+        usage.sort_by(|a, b| b.commits.cmp(&a.commits).then(a.fourcc.cmp(&b.fourcc)));
+        self.publisher.send_replace(usage);
+        true
+    }
+
+    /// Formats for the scanout tranche of dmabuf feedback, most used first
+    ///
+    /// Empty unless scanout preference is enabled.
+    pub fn preferred_formats(&self, supported: &[Format]) -> Vec<Format> {
+        if !self.prefer_scanout {
+            return Vec::new();
+        }
+        let mut preferred: Vec<Format> = supported
+            .iter()
+            .copied()
+            .filter(|&format| self.is_scanout_capable(&BufferFormat::dmabuf(format)))
+            .collect();
+        let commits = |format: &Format| self.commits.get(&BufferFormat::dmabuf(*format)).copied().unwrap_or(0);
+        preferred.sort_by_key(|format| std::cmp::Reverse(commits(format)));
+        preferred
+    }
+
+    /// Formats to send in the scanout tranche now, and whether they differ
+    /// from the ones sent last
+    pub fn advertise(&mut self, supported: &[Format]) -> (Vec<Format>, bool) {
+        let preferred = self.preferred_formats(supported);
+        let changed = preferred != self.advertised;
+        self.advertised.clone_from(&preferred);
+        (preferred, changed)
+    }
+}
+
+impl Default for BufferFormatStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Readable name of a fourcc code, e.g. "Argb8888"
+fn format_name(fourcc: u32) -> String {
+    match Fourcc::try_from(fourcc) {
+        Ok(fourcc) => format!("{:?}", fourcc),
+        Err(_) => format!("{:#010x}", fourcc),
+    }
+}
@@ -0,0 +1,258 @@
+// Pointer click assistance
+//
+// Fed from the pointer motion and button paths. Dwell clicking clicks once the
+// pointer has come to rest after moving; simulated secondary click turns a
+// long press of the primary button into a secondary click. Both report their
+// progress so a countdown can be drawn around the cursor.
+
+use compositor_utils::prelude::*;
+use config::PointerAccessibilityConfig;
+use std::time::{Duration, Instant};
+use vulkan_renderer::UiPrimitive;
+
+/// Radius of the countdown ring around the cursor in logical pixels
+const RING_RADIUS: f32 = 18.0;
+/// Thickness of the countdown ring in logical pixels
+const RING_THICKNESS: f32 = 4.0;
+/// Track the countdown fills, so the ring's extent shows from the start
+const RING_TRACK_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.35];
+
+/// Linux input event code of the primary (left) button
+pub const BTN_LEFT: u32 = 0x110;
+/// Linux input event code of the secondary (right) button
+pub const BTN_RIGHT: u32 = 0x111;
+
+/// Kind of click produced by click assistance
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AssistedClick {
+    #[default]
+    Primary,
+    Secondary,
+}
+
+impl AssistedClick {
+    /// Button event code to emit
+    pub fn button(self) -> u32 {
+        match self {
+            Self::Primary => BTN_LEFT,
+            Self::Secondary => BTN_RIGHT,
+        }
+    }
+}
+
+/// Synthetic pointer input to send through the seat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssistAction {
+    /// Press and release a button
+    Click(u32),
+    /// Press a button that was held back; its release is forwarded normally
+    Press(u32),
+}
+
+/// What the input path should do with a real button event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonDisposition {
+    /// Deliver the event unchanged
+    Forward,
+    /// Drop the event
+    Suppress,
+    /// Drop the event and send this instead
+    Replace(AssistAction),
+}
+
+/// Countdown to draw around the cursor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClickFeedback {
+    pub position: Vec2,
+    /// Elapsed fraction of the countdown (0.0-1.0)
+    pub progress: f32,
+    pub click: AssistedClick,
+}
+
+impl ClickFeedback {
+    /// Countdown ring filling clockwise in `color`, for the renderer to draw
+    pub fn primitives(&self, color: [f32; 4]) -> Vec<UiPrimitive> {
+        let ring = |progress, color| UiPrimitive::Ring {
+            center: self.position,
+            radius: RING_RADIUS,
+            thickness: RING_THICKNESS,
+            progress,
+            color,
+        };
+        vec![ring(1.0, RING_TRACK_COLOR), ring(self.progress, color)]
+    }
+}
+
+/// Primary press held back while deciding whether it becomes a secondary click
+#[derive(Debug, Clone, Copy)]
+struct HeldPress {
+    position: Vec2,
+    since: Instant,
+}
+
+/// Decides when dwell and simulated secondary clicks fire
+#[derive(Debug)]
+pub struct ClickAssist {
+    config: PointerAccessibilityConfig,
+    position: Vec2,
+    /// Where the pointer came to rest and since when
+    dwell: Option<(Vec2, Instant)>,
+    /// The current dwell already clicked; the pointer must move before re-arming
+    dwell_fired: bool,
+    /// Click the next dwell produces; reverts to primary after firing
+    dwell_click: AssistedClick,
+    held: Option<HeldPress>,
+    /// The held press already turned into a secondary click
+    held_fired: bool,
+    buttons_down: u32,
+}
+
+impl ClickAssist {
+    /// Create click assistance with the given configuration
+    pub fn new(config: PointerAccessibilityConfig) -> Self {
+        Self {
+            config,
+            position: Vec2::ZERO,
+            dwell: None,
+            dwell_fired: false,
+            dwell_click: AssistedClick::Primary,
+            held: None,
+            held_fired: false,
+            buttons_down: 0,
+        }
+    }
+
+    /// Apply new configuration
+    pub fn set_config(&mut self, config: PointerAccessibilityConfig) {
+        self.config = config;
+        self.dwell = None;
+        self.held = None;
+        self.held_fired = false;
+    }
+
+    /// Choose the click the next dwell produces, e.g. from a dwell click type selector
+    pub fn set_dwell_click(&mut self, click: AssistedClick) {
+        self.dwell_click = click;
+    }
+
+    /// Handle pointer motion; may release a held press so dragging still works
+    pub fn pointer_motion(&mut self, position: Vec2, now: Instant) -> Option<AssistAction> {
+        self.position = position;
+        let threshold = self.config.dwell_threshold as f32;
+
+        if self.config.dwell_click {
+            let moved = self
+                .dwell
+                .is_none_or(|(anchor, _)| anchor.distance(position) > threshold);
+            if moved {
+                self.dwell = Some((position, now));
+                self.dwell_fired = false;
+            }
+        }
+
+        let held = self.held?;
+        if self.held_fired || held.position.distance(position) <= threshold {
+            return None;
+        }
+        self.held = None;
+        Some(AssistAction::Press(BTN_LEFT))
+    }
+
+    /// Handle a real button event
+    pub fn button(&mut self, button: u32, pressed: bool, now: Instant) -> ButtonDisposition {
+        // A manual click stands in for the dwell click until the pointer moves again
+        self.dwell_fired = true;
+        if pressed {
+            self.buttons_down += 1;
+        } else {
+            self.buttons_down = self.buttons_down.saturating_sub(1);
+        }
+
+        if button != BTN_LEFT || !self.config.simulated_secondary_click {
+            return ButtonDisposition::Forward;
+        }
+
+        if pressed {
+            if self.buttons_down == 1 {
+                self.held = Some(HeldPress { position: self.position, since: now });
+                self.held_fired = false;
+                return ButtonDisposition::Suppress;
+            }
+            return ButtonDisposition::Forward;
+        }
+
+        match (self.held.take(), std::mem::take(&mut self.held_fired)) {
+            (Some(_), true) => ButtonDisposition::Suppress,
+            (Some(_), false) => ButtonDisposition::Replace(AssistAction::Click(BTN_LEFT)),
+            // The press was released to the client when the pointer moved
+            (None, _) => ButtonDisposition::Forward,
+        }
+    }
+
+    /// Check whether a countdown has completed
+    ///
+    /// Call from a timer when `next_deadline` returns a time, so clicks fire
+    /// without further input.
+    pub fn poll(&mut self, now: Instant) -> Option<AssistAction> {
+        if let Some(held) = self.held.filter(|_| !self.held_fired) {
+            if now.duration_since(held.since) >= self.secondary_click_delay() {
+                self.held_fired = true;
+                debug!("Simulated secondary click at {:?}", held.position);
+                return Some(AssistAction::Click(BTN_RIGHT));
+            }
+        }
+
+        let (position, since) = self.dwell.filter(|_| self.dwell_armed())?;
+        if now.duration_since(since) < self.dwell_time() {
+            return None;
+        }
+
+        self.dwell_fired = true;
+        let click = std::mem::take(&mut self.dwell_click);
+        debug!("Dwell {:?} click at {:?}", click, position);
+        Some(AssistAction::Click(click.button()))
+    }
+
+    /// Time at which a pending countdown completes, if any
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if let Some(held) = self.held.filter(|_| !self.held_fired) {
+            return Some(held.since + self.secondary_click_delay());
+        }
+        let (_, since) = self.dwell.filter(|_| self.dwell_armed())?;
+        Some(since + self.dwell_time())
+    }
+
+    /// Countdown to show around the cursor, if one is running
+    pub fn feedback(&self, now: Instant) -> Option<ClickFeedback> {
+        let progress = |since: Instant, duration: Duration| {
+            (now.duration_since(since).as_secs_f32() / duration.as_secs_f32()).min(1.0)
+        };
+
+        if let Some(held) = self.held.filter(|_| !self.held_fired) {
+            return Some(ClickFeedback {
+                position: held.position,
+                progress: progress(held.since, self.secondary_click_delay()),
+                click: AssistedClick::Secondary,
+            });
+        }
+        let (position, since) = self.dwell.filter(|_| self.dwell_armed())?;
+        Some(ClickFeedback {
+            position,
+            progress: progress(since, self.dwell_time()),
+            click: self.dwell_click,
+        })
+    }
+
+    /// Dwell clicks are paused while a button is held, e.g. during a drag
+    fn dwell_armed(&self) -> bool {
+        self.config.dwell_click && !self.dwell_fired && self.buttons_down == 0
+    }
+
+    fn dwell_time(&self) -> Duration {
+        Duration::from_millis(self.config.dwell_time)
+    }
+
+    fn secondary_click_delay(&self) -> Duration {
+        Duration::from_millis(self.config.secondary_click_delay)
+    }
+}
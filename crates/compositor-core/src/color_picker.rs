@@ -0,0 +1,215 @@
+// Color picker
+//
+// A keybinding starts the picker and the cursor becomes a crosshair. A loupe
+// follows the pointer showing the pixels around it magnified, read back from
+// the composed frame. Clicking copies the color of the pixel under the pointer
+// to the clipboard, as hex or rgb() text, and announces it.
+
+use compositor_utils::math::Rect;
+use compositor_utils::prelude::*;
+use config::{ColorFormat, ColorPickerConfig};
+use smithay::input::pointer::CursorIcon;
+use std::sync::Arc;
+use vulkan_renderer::{ReadbackPixels, ReadbackRegion, UiFilter, UiImage, UiPrimitive};
+
+/// Gap between the pixels the loupe reads back and the loupe, in logical
+/// pixels, so the loupe never shows itself
+const LOUPE_GAP: f32 = 16.0;
+
+/// Color copied by the picker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickedColor {
+    pub rgba: [u8; 4],
+    /// The color in the configured format
+    pub text: String,
+}
+
+/// Color picker state
+#[derive(Debug)]
+pub struct ColorPicker {
+    config: ColorPickerConfig,
+    active: bool,
+    /// Pixel under the pointer, in pixels of the frame
+    pointer: Option<(i32, i32)>,
+    /// Pixels around the pointer from the latest frame read back
+    pixels: Option<ReadbackPixels>,
+    /// The loupe's pixels, updated when the pointer moves or they change
+    loupe_image: Option<Arc<UiImage>>,
+}
+
+impl ColorPicker {
+    /// Create an inactive picker with the given configuration
+    pub fn new(config: ColorPickerConfig) -> Self {
+        Self {
+            config,
+            active: false,
+            pointer: None,
+            pixels: None,
+            loupe_image: None,
+        }
+    }
+
+    /// Apply new configuration
+    pub fn set_config(&mut self, config: ColorPickerConfig) {
+        self.config = config;
+    }
+
+    pub fn config(&self) -> &ColorPickerConfig {
+        &self.config
+    }
+
+    /// Whether pointer clicks should pick a color instead of reaching clients
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Start picking
+    pub fn start(&mut self) {
+        self.active = true;
+    }
+
+    /// Stop picking without copying a color
+    pub fn cancel(&mut self) {
+        self.active = false;
+        self.pointer = None;
+        self.pixels = None;
+        self.loupe_image = None;
+    }
+
+    /// Follow the pointer, at a pixel of the frame
+    pub fn pointer_moved(&mut self, x: i32, y: i32) {
+        if self.active && self.pointer != Some((x, y)) {
+            self.pointer = Some((x, y));
+            self.update_loupe_image();
+        }
+    }
+
+    /// Region of the frame the loupe shows, to read back with the next frame
+    pub fn readback_region(&self) -> Option<ReadbackRegion> {
+        let (x, y) = self.pointer.filter(|_| self.active)?;
+        let radius = self.config.loupe_radius as i32;
+        let side = self.config.loupe_radius * 2 + 1;
+        Some(ReadbackRegion { x: x - radius, y: y - radius, width: side, height: side })
+    }
+
+    /// Take pixels read back for the loupe
+    pub fn frame_read(&mut self, pixels: ReadbackPixels) {
+        // Frames redrawn without a change around the pointer keep the image,
+        // so showing it does not cause another redraw
+        if self.active && self.pixels.as_ref() != Some(&pixels) {
+            self.pixels = Some(pixels);
+            self.update_loupe_image();
+        }
+    }
+
+    /// Stop picking when the loupe's region could not be read back, as no
+    /// color can be picked then
+    pub fn readback_failed(&mut self, region: ReadbackRegion) {
+        if self.readback_region() == Some(region) {
+            warn!("Color picker stopped, the frame cannot be read back");
+            self.cancel();
+        }
+    }
+
+    /// Pixels shown in the loupe, row by row with the pointer's in the
+    /// middle; `None` outside the frame or before the first readback
+    pub fn loupe(&self) -> Vec<Option<[u8; 4]>> {
+        let Some((x, y)) = self.pointer.filter(|_| self.active) else {
+            return Vec::new();
+        };
+        let radius = self.config.loupe_radius as i32;
+        (-radius..=radius)
+            .flat_map(|row| (-radius..=radius).map(move |column| (x + column, y + row)))
+            .map(|(x, y)| self.pixels.as_ref().and_then(|pixels| pixels.pixel(x, y)))
+            .collect()
+    }
+
+    /// Side of the loupe in logical pixels
+    pub fn loupe_size(&self) -> u32 {
+        (self.config.loupe_radius * 2 + 1) * self.config.loupe_zoom
+    }
+
+    /// Primitives drawing the loupe below and right of the pointer at
+    /// `pointer`, or on the other side where it would leave `bounds`, with
+    /// the pixel under the pointer outlined in `accent`
+    pub fn loupe_primitives(&self, pointer: Vec2, bounds: Rect, accent: [f32; 4]) -> Vec<UiPrimitive> {
+        let Some(image) = self.loupe_image.clone().filter(|_| self.active) else {
+            return Vec::new();
+        };
+        let size = self.loupe_size() as f32;
+        let offset = self.config.loupe_radius as f32 + LOUPE_GAP;
+        let x = match pointer.x + offset + size > bounds.x + bounds.width {
+            true => pointer.x - offset - size,
+            false => pointer.x + offset,
+        };
+        let y = match pointer.y + offset + size > bounds.y + bounds.height {
+            true => pointer.y - offset - size,
+            false => pointer.y + offset,
+        };
+        let rect = Rect::new(x.round(), y.round(), size, size);
+        let zoom = self.config.loupe_zoom as f32;
+        let center = self.config.loupe_radius as f32 * zoom;
+        vec![
+            UiPrimitive::drop_shadow(rect, 0.0, 0.4),
+            // Pixels outside the frame show as black
+            UiPrimitive::Rect { rect, color: [0.0, 0.0, 0.0, 1.0], corner_radius: 0.0 },
+            UiPrimitive::Image { rect, image, tint: [1.0; 4] },
+            UiPrimitive::Outline {
+                rect: Rect::new(rect.x + center - 1.0, rect.y + center - 1.0, zoom + 2.0, zoom + 2.0),
+                width: 2.0,
+                color: accent,
+                corner_radius: 0.0,
+            },
+            UiPrimitive::Outline { rect, width: 2.0, color: accent, corner_radius: 0.0 },
+        ]
+    }
+
+    fn update_loupe_image(&mut self) {
+        let side = self.config.loupe_radius * 2 + 1;
+        // The alpha of composed frames is not meaningful
+        let pixels = self
+            .loupe()
+            .into_iter()
+            .flat_map(|pixel| pixel.map_or([0; 4], |[r, g, b, _]| [r, g, b, 255]))
+            .collect();
+        self.loupe_image = UiImage::new(side, side, pixels, UiFilter::Nearest)
+            .map_err(|e| warn!("Cannot show the color picker's loupe: {}", e))
+            .ok();
+    }
+
+    /// Color of the pixel under the pointer
+    pub fn color(&self) -> Option<[u8; 4]> {
+        let (x, y) = self.pointer.filter(|_| self.active)?;
+        self.pixels.as_ref()?.pixel(x, y)
+    }
+
+    /// Pick the color under the pointer and stop picking
+    ///
+    /// Returns `None`, and keeps picking, until the pixel under the pointer
+    /// has been read back.
+    pub fn pick(&mut self) -> Option<PickedColor> {
+        let rgba = self.color()?;
+        self.cancel();
+        Some(PickedColor { rgba, text: format_color(rgba, self.config.format) })
+    }
+
+    /// Cursor to show while picking
+    pub fn cursor_icon(&self) -> Option<CursorIcon> {
+        self.active.then_some(CursorIcon::Crosshair)
+    }
+}
+
+impl Default for ColorPicker {
+    fn default() -> Self {
+        Self::new(ColorPickerConfig::default())
+    }
+}
+
+/// Text for a color; the alpha of composed frames is not meaningful and left
+/// out
+pub fn format_color([r, g, b, _]: [u8; 4], format: ColorFormat) -> String {
+    match format {
+        ColorFormat::Hex => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        ColorFormat::Rgb => format!("rgb({}, {}, {})", r, g, b),
+    }
+}
@@ -0,0 +1,202 @@
+// Client process identification
+//
+// Every client connection is tagged with the peer credentials of its socket
+// (SO_PEERCRED) and the executable behind the PID when it connects. The
+// security policy matches privileged protocols against the executable, and
+// IPC window queries and disconnect diagnostics report which process a
+// client is.
+
+use compositor_utils::prelude::*;
+use config::{KeyboardGrabPolicy, ProtocolExposure};
+use ipc::protocol::ClientProcessInfo;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::os::fd::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+/// Process credentials of a connected client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCredentials {
+    pub pid: i32,
+    pub uid: u32,
+    pub gid: u32,
+    /// Executable at connect time; `None` if /proc is not readable
+    pub executable: Option<PathBuf>,
+}
+
+impl ClientCredentials {
+    /// Read the peer credentials of a client socket
+    pub fn from_stream(stream: &UnixStream) -> Option<Self> {
+        let mut ucred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+        let mut length = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        let result = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                &mut ucred as *mut libc::ucred as *mut libc::c_void,
+                &mut length,
+            )
+        };
+        if result != 0 {
+            warn!("Failed to read client credentials: {}", std::io::Error::last_os_error());
+            return None;
+        }
+
+        Some(Self {
+            pid: ucred.pid,
+            uid: ucred.uid,
+            gid: ucred.gid,
+            executable: std::fs::read_link(format!("/proc/{}/exe", ucred.pid)).ok(),
+        })
+    }
+
+    /// File name of the executable, e.g. "firefox"
+    pub fn executable_name(&self) -> Option<&str> {
+        self.executable.as_ref()?.file_name()?.to_str()
+    }
+
+    /// Whether the executable matches a policy entry, a file name or an absolute path
+    pub fn matches(&self, entry: &str) -> bool {
+        if entry.starts_with('/') {
+            self.executable.as_deref() == Some(std::path::Path::new(entry))
+        } else {
+            self.executable_name() == Some(entry)
+        }
+    }
+
+    /// Credentials in the form reported over IPC
+    pub fn process_info(&self) -> ClientProcessInfo {
+        ClientProcessInfo {
+            pid: self.pid,
+            uid: self.uid,
+            executable: self.executable.as_ref().map(|path| path.display().to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for ClientCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.executable {
+            Some(executable) => write!(f, "{} (pid {}, uid {})", executable.display(), self.pid, self.uid),
+            None => write!(f, "pid {} (uid {})", self.pid, self.uid),
+        }
+    }
+}
+
+/// Which clients may connect and which globals they see
+///
+/// Privileged protocols (virtual keyboard, input method, session lock) can
+/// read or inject input for the whole session. Which globals are advertised
+/// to which class of client is configurable per interface; clients of
+/// restricted sockets and security contexts never count as privileged.
+/// Keyboard grabs have their own policy, which defaults to the privileged
+/// client list. In kiosk mode only whitelisted clients may connect at all.
+#[derive(Debug, Default)]
+pub struct SecurityPolicy {
+    /// Executable names or absolute paths; empty allows every client
+    privileged_clients: RwLock<Vec<String>>,
+    keyboard_grab: RwLock<KeyboardGrabPolicy>,
+    /// Executable names or absolute paths; `None` allows every client
+    connection_whitelist: RwLock<Option<Vec<String>>>,
+    /// Configured exposure by interface name, overriding the defaults
+    protocol_exposure: RwLock<HashMap<String, ProtocolExposure>>,
+}
+
+impl SecurityPolicy {
+    /// Create a policy that allows every client
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the privileged client list
+    pub fn set_privileged_clients(&self, clients: Vec<String>) {
+        *self.privileged_clients.write() = clients;
+    }
+
+    /// Replace the keyboard grab policy
+    pub fn set_keyboard_grab_policy(&self, policy: KeyboardGrabPolicy) {
+        *self.keyboard_grab.write() = policy;
+    }
+
+    /// Restrict new connections to the given executables, or allow all with `None`
+    pub fn set_connection_whitelist(&self, clients: Option<Vec<String>>) {
+        *self.connection_whitelist.write() = clients;
+    }
+
+    /// Whether a client may connect
+    pub fn allows_connection(&self, credentials: Option<&ClientCredentials>) -> bool {
+        let connection_whitelist = self.connection_whitelist.read();
+        let Some(clients) = connection_whitelist.as_ref() else {
+            return true;
+        };
+        credentials.is_some_and(|credentials| clients.iter().any(|entry| credentials.matches(entry)))
+    }
+
+    /// Replace the configured protocol exposure
+    ///
+    /// Globals already advertised to a client stay advertised; binding them
+    /// is checked against the new policy.
+    pub fn set_protocol_exposure(&self, exposure: HashMap<String, ProtocolExposure>) {
+        *self.protocol_exposure.write() = exposure;
+    }
+
+    /// Who a global is advertised to, from the configuration or the built-in default
+    pub fn protocol_exposure(&self, interface: &str) -> ProtocolExposure {
+        self.protocol_exposure
+            .read()
+            .get(interface)
+            .copied()
+            .unwrap_or_else(|| default_protocol_exposure(interface))
+    }
+
+    /// Whether a global is advertised to a client
+    pub fn allows_global(&self, interface: &str, credentials: Option<&ClientCredentials>, restricted: bool) -> bool {
+        match self.protocol_exposure(interface) {
+            ProtocolExposure::Everyone => true,
+            ProtocolExposure::Unrestricted => !restricted,
+            ProtocolExposure::Privileged => !restricted && self.allows_privileged(credentials),
+            ProtocolExposure::Nobody => false,
+        }
+    }
+
+    /// Whether a client may grab the keyboard exclusively
+    pub fn allows_keyboard_grab(&self, credentials: Option<&ClientCredentials>) -> bool {
+        match *self.keyboard_grab.read() {
+            KeyboardGrabPolicy::Deny => false,
+            KeyboardGrabPolicy::Privileged => self.allows_privileged(credentials),
+            KeyboardGrabPolicy::Any => true,
+        }
+    }
+
+    /// Whether a client may bind privileged protocols
+    pub fn allows_privileged(&self, credentials: Option<&ClientCredentials>) -> bool {
+        let privileged_clients = self.privileged_clients.read();
+        if privileged_clients.is_empty() {
+            return true;
+        }
+        let Some(credentials) = credentials else {
+            return false;
+        };
+        privileged_clients.iter().any(|entry| credentials.matches(entry))
+    }
+}
+
+/// Exposure of globals not configured explicitly
+fn default_protocol_exposure(interface: &str) -> ProtocolExposure {
+    match interface {
+        // Input injection, session lock and screen or window capture and control
+        "zwp_virtual_keyboard_manager_v1"
+        | "zwp_input_method_manager_v2"
+        | "ext_session_lock_manager_v1"
+        | "zwlr_screencopy_manager_v1"
+        | "ext_image_copy_capture_manager_v1"
+        | "zwlr_foreign_toplevel_manager_v1" => ProtocolExposure::Privileged,
+        // Desktop shell components, window lists and sandbox management
+        "zwlr_layer_shell_v1" | "ext_foreign_toplevel_list_v1" | "wp_security_context_manager_v1" => {
+            ProtocolExposure::Unrestricted
+        }
+        _ => ProtocolExposure::Everyone,
+    }
+}
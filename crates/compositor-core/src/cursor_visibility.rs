@@ -0,0 +1,112 @@
+// Automatic cursor hiding
+//
+// Fed from the pointer motion and key paths. The cursor hides while the user
+// types and after a period without pointer motion, and comes back on the next
+// motion. The output the pointer is on is tracked so only that output needs
+// redrawing when the cursor hides or shows. While a client holds a pointer
+// lock or confinement, e.g. a game, the cursor is left as the client wants it.
+
+use compositor_utils::prelude::*;
+use config::CursorConfig;
+use std::time::{Duration, Instant};
+
+/// Why the cursor is hidden
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HideReason {
+    Typing,
+    Inactivity,
+}
+
+/// Decides when the cursor is hidden
+#[derive(Debug)]
+pub struct CursorVisibility {
+    config: CursorConfig,
+    last_motion: Instant,
+    hidden: Option<HideReason>,
+    /// Output the pointer is on
+    output: Option<String>,
+}
+
+impl CursorVisibility {
+    /// Create cursor hiding with the given configuration
+    pub fn new(config: CursorConfig) -> Self {
+        Self {
+            config,
+            last_motion: Instant::now(),
+            hidden: None,
+            output: None,
+        }
+    }
+
+    /// Apply new configuration
+    pub fn set_config(&mut self, config: CursorConfig) {
+        self.config = config;
+        self.hidden = None;
+        self.last_motion = Instant::now();
+    }
+
+    /// Handle pointer motion; returns whether the cursor was shown again
+    pub fn pointer_motion(&mut self, output: Option<&str>, now: Instant) -> bool {
+        self.last_motion = now;
+        if self.output.as_deref() != output {
+            self.output = output.map(str::to_string);
+        }
+        self.hidden.take().is_some()
+    }
+
+    /// Handle a key press; returns whether the cursor was hidden
+    ///
+    /// Modifier presses should not be passed in, so modifier clicks keep the
+    /// cursor visible.
+    pub fn key_pressed(&mut self, constrained: bool) -> bool {
+        if !self.config.hide_while_typing || constrained || self.hidden.is_some() {
+            return false;
+        }
+        debug!("Hiding cursor while typing");
+        self.hidden = Some(HideReason::Typing);
+        true
+    }
+
+    /// Hide the cursor once the inactivity timeout passed; returns whether it was hidden
+    ///
+    /// Call from a timer when `next_deadline` returns a time.
+    pub fn poll(&mut self, constrained: bool, now: Instant) -> bool {
+        let Some(deadline) = self.next_deadline() else {
+            return false;
+        };
+        if now < deadline {
+            return false;
+        }
+        if constrained {
+            // Check again after another timeout instead of on every tick
+            self.last_motion = now;
+            return false;
+        }
+        debug!("Hiding cursor after {} seconds of inactivity", self.config.hide_after);
+        self.hidden = Some(HideReason::Inactivity);
+        true
+    }
+
+    /// Time at which the cursor hides for inactivity, if it is visible
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if self.hidden.is_some() || self.config.hide_after == 0 {
+            return None;
+        }
+        Some(self.last_motion + Duration::from_secs(self.config.hide_after))
+    }
+
+    /// Why the cursor is hidden, if it is
+    pub fn hidden(&self) -> Option<HideReason> {
+        self.hidden
+    }
+
+    /// Whether the cursor is drawn on an output
+    pub fn is_visible_on(&self, output: &str) -> bool {
+        self.hidden.is_none() && self.output.as_deref() == Some(output)
+    }
+
+    /// Output the pointer is on, which needs redrawing when visibility changes
+    pub fn output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+}
@@ -0,0 +1,237 @@
+// Compositor dialogs
+//
+// Questions the compositor asks itself, such as whether to keep waiting for
+// an application that stopped responding or to force quit a window picked in
+// kill mode, are shown as a card with a message and a row of buttons over the
+// window they are about. The UI pass draws them; labels are rasterized once
+// when a dialog opens, in white, and tinted with the theme colors. Buttons
+// are pressed with the pointer, or with the keyboard: Tab moves the focus,
+// Enter presses the focused button and Escape the last one, which cancels
+// and has the focus when the dialog opens.
+
+use compositor_utils::math::Rect;
+use compositor_utils::prelude::*;
+use config::{ColorToken, ThemeConfig};
+use std::path::Path;
+use std::sync::Arc;
+use ui_framework::font::Font;
+use ui_framework::label::Label;
+use vulkan_renderer::UiPrimitive;
+
+/// Fonts tried for labels when the theme does not name one
+const DEFAULT_FONTS: [&str; 5] = [
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+];
+
+/// Space around the message and the buttons in logical pixels
+const PADDING: f32 = 20.0;
+/// Narrowest a dialog gets in logical pixels
+const MIN_WIDTH: f32 = 320.0;
+const MESSAGE_SIZE: f32 = 16.0;
+const BUTTON_LABEL_SIZE: f32 = 14.0;
+const BUTTON_HEIGHT: f32 = 32.0;
+const MIN_BUTTON_WIDTH: f32 = 88.0;
+const BUTTON_GAP: f32 = 8.0;
+
+/// Colors, corner radius and font dialogs are drawn with
+#[derive(Debug, Clone)]
+pub struct DialogStyle {
+    font: Option<Arc<Font>>,
+    card: [f32; 4],
+    text: [f32; 4],
+    button: [f32; 4],
+    accent: [f32; 4],
+    on_accent: [f32; 4],
+    corner_radius: f32,
+}
+
+impl DialogStyle {
+    /// Style dialogs after a theme, loading its label font
+    pub fn new(theme: &ThemeConfig) -> Self {
+        let font = match &theme.font {
+            Some(path) => Font::load(path).map_err(|e| warn!("Cannot load font {}: {}", path.display(), e)).ok(),
+            None => DEFAULT_FONTS.iter().find_map(|path| Font::load(Path::new(path)).ok()),
+        };
+        if font.is_none() {
+            warn!("No font for compositor dialogs; their labels are left out");
+        }
+        Self {
+            font: font.map(Arc::new),
+            card: theme.color(ColorToken::Surface),
+            text: theme.color(ColorToken::OnSurface),
+            button: theme.color(ColorToken::SurfaceVariant),
+            accent: theme.color(ColorToken::Accent),
+            on_accent: theme.color(ColorToken::OnAccent),
+            corner_radius: theme.corner_radius,
+        }
+    }
+
+    /// Font labels are drawn with, also used by other compositor overlays
+    pub fn font(&self) -> Option<&Arc<Font>> {
+        self.font.as_ref()
+    }
+}
+
+impl Default for DialogStyle {
+    fn default() -> Self {
+        Self::new(&ThemeConfig::default())
+    }
+}
+
+/// Where a click on a dialog landed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogHit<T> {
+    Button(T),
+    /// On the card between the buttons
+    Card,
+    Outside,
+}
+
+/// Key pressed while a dialog is open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogKey {
+    /// Move the focus to the next button
+    Next,
+    /// Press the focused button
+    Press,
+    /// Press the last button
+    Cancel,
+}
+
+#[derive(Debug, Clone)]
+struct DialogButton<T> {
+    label: Option<Label>,
+    action: T,
+}
+
+/// A message with buttons, each answering with an action
+#[derive(Debug, Clone)]
+pub struct Dialog<T> {
+    message: Option<Label>,
+    buttons: Vec<DialogButton<T>>,
+    focused: usize,
+    style: DialogStyle,
+}
+
+/// Rectangles of a dialog centered on an anchor
+struct DialogLayout {
+    card: Rect,
+    message: Rect,
+    buttons: Vec<Rect>,
+}
+
+impl<T: Copy> Dialog<T> {
+    /// Open a dialog with buttons from left to right; the last cancels and
+    /// is focused
+    pub fn new(message: &str, buttons: &[(&str, T)], style: &DialogStyle) -> Self {
+        let white = [1.0; 4];
+        let label = |text: &str, size| style.font.as_ref().and_then(|font| Label::new(text, font, size, white));
+        Self {
+            message: label(message, MESSAGE_SIZE),
+            buttons: buttons
+                .iter()
+                .map(|&(text, action)| DialogButton { label: label(text, BUTTON_LABEL_SIZE), action })
+                .collect(),
+            focused: buttons.len().saturating_sub(1),
+            style: style.clone(),
+        }
+    }
+
+    /// Answer a key press; returns the action of a pressed button
+    pub fn key(&mut self, key: DialogKey) -> Option<T> {
+        match key {
+            DialogKey::Next => {
+                self.focused = (self.focused + 1) % self.buttons.len().max(1);
+                None
+            }
+            DialogKey::Press => self.buttons.get(self.focused).map(|button| button.action),
+            DialogKey::Cancel => self.buttons.last().map(|button| button.action),
+        }
+    }
+
+    /// What is at `point` of the dialog shown over `anchor`
+    pub fn hit(&self, anchor: Rect, point: Vec2) -> DialogHit<T> {
+        let layout = self.layout(anchor);
+        let contains = |rect: &Rect| rect.contains(point);
+        match layout.buttons.iter().position(contains) {
+            Some(index) => DialogHit::Button(self.buttons[index].action),
+            None if contains(&layout.card) => DialogHit::Card,
+            None => DialogHit::Outside,
+        }
+    }
+
+    /// Primitives drawing the dialog centered over `anchor`, back to front
+    pub fn primitives(&self, anchor: Rect) -> Vec<UiPrimitive> {
+        let style = &self.style;
+        let layout = self.layout(anchor);
+        let mut primitives = vec![
+            UiPrimitive::drop_shadow(layout.card, style.corner_radius, 0.4),
+            UiPrimitive::Rect { rect: layout.card, color: style.card, corner_radius: style.corner_radius },
+        ];
+        if let Some(label) = &self.message {
+            primitives.push(label_primitive(label, layout.message, style.text));
+        }
+        for (index, (button, rect)) in self.buttons.iter().zip(layout.buttons).enumerate() {
+            let (color, text) = match index == self.focused {
+                true => (style.accent, style.on_accent),
+                false => (style.button, style.text),
+            };
+            primitives.push(UiPrimitive::Rect { rect, color, corner_radius: style.corner_radius.min(BUTTON_HEIGHT / 2.0) });
+            if let Some(label) = &button.label {
+                primitives.push(label_primitive(label, rect, text));
+            }
+        }
+        primitives
+    }
+
+    fn layout(&self, anchor: Rect) -> DialogLayout {
+        let (message_width, message_height) = self.message.as_ref().map_or((0.0, MESSAGE_SIZE * 1.2), Label::size);
+        let button_widths: Vec<f32> = self
+            .buttons
+            .iter()
+            .map(|button| button.label.as_ref().map_or(0.0, |label| label.size().0 + PADDING * 1.6).max(MIN_BUTTON_WIDTH))
+            .collect();
+        let buttons_width = button_widths.iter().sum::<f32>() + BUTTON_GAP * button_widths.len().saturating_sub(1) as f32;
+        let width = (message_width.max(buttons_width) + PADDING * 2.0).max(MIN_WIDTH);
+        let height = PADDING * 3.0 + message_height + BUTTON_HEIGHT;
+        let card = Rect::new(
+            (anchor.x + (anchor.width - width) / 2.0).round(),
+            (anchor.y + (anchor.height - height) / 2.0).round(),
+            width,
+            height,
+        );
+        let message = Rect::new(card.x + PADDING, card.y + PADDING, card.width - PADDING * 2.0, message_height);
+
+        // Buttons line up at the bottom right
+        let top = card.y + card.height - PADDING - BUTTON_HEIGHT;
+        let mut x = card.x + card.width - PADDING - buttons_width;
+        let buttons = button_widths
+            .into_iter()
+            .map(|width| {
+                let rect = Rect::new(x, top, width, BUTTON_HEIGHT);
+                x += width + BUTTON_GAP;
+                rect
+            })
+            .collect();
+        DialogLayout { card, message, buttons }
+    }
+}
+
+/// A white label tinted with `color`, centered in `rect` at whole pixels
+fn label_primitive(label: &Label, rect: Rect, color: [f32; 4]) -> UiPrimitive {
+    let (width, height) = label.size();
+    UiPrimitive::Image {
+        rect: Rect::new(
+            (rect.x + (rect.width - width) / 2.0).round(),
+            (rect.y + (rect.height - height) / 2.0).round(),
+            width,
+            height,
+        ),
+        image: label.image.clone(),
+        tint: color,
+    }
+}
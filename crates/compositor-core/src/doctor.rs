@@ -0,0 +1,276 @@
+// System prerequisite checks
+//
+// Run by the `doctor` subcommand to explain why the compositor cannot start
+// or runs degraded: missing DRM or render node access, Vulkan without the
+// dmabuf import extensions, no seat manager, or no runtime directory. Every
+// failed check carries a remediation step. The permission checks also run at
+// startup, where their results are only logged.
+
+use ash::vk;
+use nix::unistd::{access, AccessFlags};
+use std::ffi::CStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use vulkan_renderer::VulkanInstance;
+
+/// Device extensions needed to import client dmabufs without copies
+const DMABUF_EXTENSIONS: [&CStr; 3] = [
+    vk::KhrExternalMemoryFdFn::name(),
+    vk::ExtExternalMemoryDmaBufFn::name(),
+    vk::ExtImageDrmFormatModifierFn::name(),
+];
+
+/// Sockets of the seat managers libseat can use
+const SEAT_SOCKETS: [&str; 2] = ["/run/systemd/seats", "/run/seatd.sock"];
+
+/// Outcome of a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    /// Works, but degraded or only some devices usable
+    Warn,
+    /// The compositor cannot run like this
+    Fail,
+}
+
+/// Result of one prerequisite check
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a warning or failure
+    pub remediation: Option<String>,
+}
+
+impl Diagnostic {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            remediation: None,
+        }
+    }
+
+    fn problem(name: &'static str, status: CheckStatus, detail: impl Into<String>, remediation: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            remediation: Some(remediation.into()),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self.status {
+            CheckStatus::Pass => "ok",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "FAIL",
+        };
+        write!(f, "[{:>4}] {}: {}", status, self.name, self.detail)?;
+        if let Some(remediation) = &self.remediation {
+            write!(f, "\n       -> {}", remediation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run every check, including Vulkan device creation prerequisites
+pub fn run_all() -> Vec<Diagnostic> {
+    let mut diagnostics = run_permission_checks();
+    diagnostics.push(check_vulkan());
+    diagnostics
+}
+
+/// Checks that need no GPU initialization, cheap enough for every startup
+pub fn run_permission_checks() -> Vec<Diagnostic> {
+    vec![
+        check_runtime_dir(),
+        check_seat(),
+        check_drm_devices(),
+        check_render_nodes(),
+        check_input_devices(),
+    ]
+}
+
+/// Whether any check failed
+pub fn has_failures(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|diagnostic| diagnostic.status == CheckStatus::Fail)
+}
+
+fn check_runtime_dir() -> Diagnostic {
+    const NAME: &str = "XDG runtime directory";
+    let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from) else {
+        return Diagnostic::problem(
+            NAME,
+            CheckStatus::Fail,
+            "XDG_RUNTIME_DIR is not set",
+            "Log in through a session manager (systemd-logind, elogind) or configure wayland.sockets with a directory",
+        );
+    };
+    if !dir.is_dir() {
+        return Diagnostic::problem(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} does not exist", dir.display()),
+            "Create it owned by your user with mode 0700, or log in again so the session manager creates it",
+        );
+    }
+    if access(&dir, AccessFlags::W_OK).is_err() {
+        return Diagnostic::problem(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} is not writable", dir.display()),
+            format!("chown $USER {} && chmod 0700 {}", dir.display(), dir.display()),
+        );
+    }
+    Diagnostic::pass(NAME, dir.display().to_string())
+}
+
+fn check_seat() -> Diagnostic {
+    const NAME: &str = "Seat management";
+    match SEAT_SOCKETS.iter().find(|path| Path::new(path).exists()) {
+        Some(path) => Diagnostic::pass(NAME, format!("found {}", path)),
+        None => Diagnostic::problem(
+            NAME,
+            CheckStatus::Fail,
+            "neither systemd-logind nor seatd is running",
+            "Start systemd-logind, or install seatd, enable seatd.service and add your user to its group",
+        ),
+    }
+}
+
+fn check_drm_devices() -> Diagnostic {
+    check_device_nodes(
+        "DRM devices",
+        "card",
+        "Run from a logind session on an active VT, or add your user to the video group",
+    )
+}
+
+fn check_render_nodes() -> Diagnostic {
+    check_device_nodes(
+        "Render nodes",
+        "renderD",
+        "Add your user to the render group (usermod -aG render $USER) and log in again",
+    )
+}
+
+/// Check read-write access to the /dev/dri nodes starting with `prefix`
+fn check_device_nodes(name: &'static str, prefix: &str, remediation: &str) -> Diagnostic {
+    let mut nodes: Vec<PathBuf> = std::fs::read_dir("/dev/dri")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with(prefix)))
+        .collect();
+    nodes.sort();
+    if nodes.is_empty() {
+        return Diagnostic::problem(
+            name,
+            CheckStatus::Fail,
+            format!("no /dev/dri/{}* nodes found", prefix),
+            "Load the kernel driver for your GPU with modesetting enabled",
+        );
+    }
+
+    let (accessible, denied): (Vec<&PathBuf>, Vec<&PathBuf>) = nodes
+        .iter()
+        .partition(|path| access(path.as_path(), AccessFlags::R_OK | AccessFlags::W_OK).is_ok());
+    let list = |paths: &[&PathBuf]| paths.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ");
+    match (accessible.is_empty(), denied.is_empty()) {
+        (_, true) => Diagnostic::pass(name, list(&accessible)),
+        (false, false) => Diagnostic::problem(
+            name,
+            CheckStatus::Warn,
+            format!("no access to {}", list(&denied)),
+            remediation,
+        ),
+        // DRM masters are handed out by the seat manager
+        (true, false) if prefix == "card" => Diagnostic::problem(
+            name,
+            CheckStatus::Warn,
+            format!("no direct access to {}; the seat manager must open them", list(&denied)),
+            remediation,
+        ),
+        (true, false) => Diagnostic::problem(
+            name,
+            CheckStatus::Fail,
+            format!("no access to {}", list(&denied)),
+            remediation,
+        ),
+    }
+}
+
+fn check_input_devices() -> Diagnostic {
+    const NAME: &str = "Input devices";
+    match std::fs::read_dir("/dev/input") {
+        Ok(entries) => Diagnostic::pass(NAME, format!("{} devices found", entries.count())),
+        Err(e) => Diagnostic::problem(
+            NAME,
+            CheckStatus::Warn,
+            format!("cannot list /dev/input: {}", e),
+            "Make sure udev is running; input devices are opened through the seat manager",
+        ),
+    }
+}
+
+fn check_vulkan() -> Diagnostic {
+    const NAME: &str = "Vulkan";
+    let instance = match VulkanInstance::new() {
+        Ok(instance) => instance,
+        Err(e) => {
+            return Diagnostic::problem(
+                NAME,
+                CheckStatus::Fail,
+                format!("cannot create a Vulkan instance: {}", e),
+                "Install the Vulkan loader and your GPU's ICD (e.g. mesa-vulkan-drivers)",
+            );
+        }
+    };
+    let devices = match instance.enumerate_physical_devices() {
+        Ok(devices) if !devices.is_empty() => devices,
+        _ => {
+            return Diagnostic::problem(
+                NAME,
+                CheckStatus::Fail,
+                "no Vulkan devices found",
+                "Install the Vulkan driver for your GPU and check vulkaninfo --summary",
+            );
+        }
+    };
+
+    // One capable device is enough
+    let mut missing_by_device = Vec::new();
+    for device in devices {
+        let properties = instance.get_physical_device_properties(device);
+        let device_name = unsafe { CStr::from_ptr(properties.device_name.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+        let supported = unsafe { instance.handle().enumerate_device_extension_properties(device) }
+            .unwrap_or_default();
+        let missing: Vec<String> = DMABUF_EXTENSIONS
+            .iter()
+            .filter(|&&required| {
+                !supported
+                    .iter()
+                    .any(|extension| unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) } == required)
+            })
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+        if missing.is_empty() {
+            return Diagnostic::pass(NAME, format!("{} supports dmabuf import with modifiers", device_name));
+        }
+        missing_by_device.push(format!("{} lacks {}", device_name, missing.join(", ")));
+    }
+    Diagnostic::problem(
+        NAME,
+        CheckStatus::Warn,
+        missing_by_device.join("; "),
+        "Update Mesa or your GPU driver; without these extensions client buffers are copied",
+    )
+}
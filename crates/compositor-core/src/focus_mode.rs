@@ -0,0 +1,128 @@
+// Do-not-disturb / focus mode
+//
+// Decides whether focus mode is active from the configured schedule, the
+// focused application and fullscreen state, and any manual override sent
+// over IPC. While active, notification layer surfaces are suppressed,
+// unfocused windows can be dimmed and app bar badges hidden.
+
+use compositor_utils::prelude::*;
+use config::FocusModeConfig;
+use ipc::protocol::FocusModeOverride;
+use tokio::sync::watch;
+
+/// What the rest of the compositor should do while focus mode is active
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FocusPolicy {
+    pub suppress_notifications: bool,
+    /// Dimming strength for unfocused windows, if dimming is enabled
+    pub dim_unfocused: Option<f32>,
+    pub hide_badges: bool,
+}
+
+/// Focus mode state
+#[derive(Debug)]
+pub struct FocusModeState {
+    config: FocusModeConfig,
+    override_sender: watch::Sender<FocusModeOverride>,
+    override_receiver: watch::Receiver<FocusModeOverride>,
+    active: bool,
+}
+
+impl FocusModeState {
+    /// Create focus mode state from configuration, starting in automatic mode
+    pub fn new(config: FocusModeConfig) -> Self {
+        let (override_sender, override_receiver) = watch::channel(FocusModeOverride::Auto);
+        Self {
+            config,
+            override_sender,
+            override_receiver,
+            active: false,
+        }
+    }
+
+    /// Apply new configuration
+    pub fn set_config(&mut self, config: FocusModeConfig) {
+        self.config = config;
+    }
+
+    /// Channel for IPC to set the manual override
+    pub fn override_sender(&self) -> watch::Sender<FocusModeOverride> {
+        self.override_sender.clone()
+    }
+
+    /// Whether focus mode is currently active
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Re-evaluate focus mode
+    ///
+    /// `minute_of_day` is local time in minutes since midnight. Returns whether
+    /// focus mode is active afterwards.
+    pub fn update(&mut self, minute_of_day: u32, focused_app_id: Option<&str>, fullscreen: bool) -> bool {
+        let manual = *self.override_receiver.borrow_and_update();
+        let active = match manual {
+            FocusModeOverride::On => true,
+            FocusModeOverride::Off => false,
+            FocusModeOverride::Auto => {
+                let scheduled = self.config.schedule.iter().any(|window| window.contains(minute_of_day));
+                let app_triggered = focused_app_id.is_some_and(|app_id| {
+                    self.config.trigger_apps.iter().any(|trigger| trigger == app_id)
+                });
+                let fullscreen_triggered = fullscreen && self.config.activate_on_fullscreen;
+                scheduled || app_triggered || fullscreen_triggered
+            }
+        };
+
+        if active != self.active {
+            info!("Focus mode {} ({:?})", if active { "activated" } else { "deactivated" }, manual);
+            self.active = active;
+        }
+        active
+    }
+
+    /// Current policy; everything is allowed while focus mode is inactive
+    pub fn policy(&self) -> FocusPolicy {
+        if !self.active {
+            return FocusPolicy::default();
+        }
+
+        FocusPolicy {
+            suppress_notifications: self.config.suppress_notifications,
+            dim_unfocused: self.config.dim_unfocused.then_some(self.config.dim_strength),
+            hide_badges: self.config.hide_badges,
+        }
+    }
+
+    /// Whether a layer surface with the given namespace should be shown
+    pub fn allows_layer_surface(&self, namespace: &str) -> bool {
+        !(self.policy().suppress_notifications && self.is_notification_namespace(namespace))
+    }
+
+    /// Whether a layer-shell namespace belongs to a notification daemon
+    pub fn is_notification_namespace(&self, namespace: &str) -> bool {
+        self.config
+            .notification_namespaces
+            .iter()
+            .any(|candidate| candidate.eq_ignore_ascii_case(namespace))
+    }
+}
+
+impl Default for FocusModeState {
+    fn default() -> Self {
+        Self::new(FocusModeConfig::default())
+    }
+}
+
+/// Current local time in minutes since midnight
+pub fn local_minute_of_day() -> u32 {
+    // SAFETY: `time` and `localtime_r` only write to the provided out-pointers
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return 0;
+        }
+        (tm.tm_hour * 60 + tm.tm_min) as u32
+    }
+}
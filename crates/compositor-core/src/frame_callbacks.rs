@@ -0,0 +1,194 @@
+// Frame callback pacing
+//
+// Clients draw their next frame when their frame callbacks fire. Firing them
+// right on commit lets clients draw as fast as they can, whether or not their
+// frames are ever shown, so callbacks are held until a frame that includes
+// the commit has been presented on an output showing the surface. The render
+// thread reports each presented frame, which also times the surface's
+// presentation-time feedback. Surfaces no presented output shows, e.g. on
+// another workspace, get their callbacks once they waited the configured
+// max latency, so they keep making progress at a low rate.
+//
+// Presentation-time feedback carries the refresh counter of the frame.
+// Without one from the display hardware it is counted from the presentation
+// times, so frames that missed a refresh skip a number as they should.
+
+use config::FrameCallbackConfig;
+use smithay::reexports::wayland_server::backend::ObjectId;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::Resource;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// A frame that reached an output, reported by the render thread
+#[derive(Debug, Clone)]
+pub struct FramePresented {
+    /// Name of the output, e.g. "DP-1"
+    pub output: String,
+    /// When rendering of the frame started; commits after it are not in it
+    pub submitted: Instant,
+    /// When the frame became visible, or the refresh it is expected on when
+    /// the driver does not report presentation
+    pub presented: Instant,
+    /// Refresh interval of the output
+    pub refresh: Duration,
+    /// Whether `presented` is the actual time the frame reached the display
+    pub vsync: bool,
+}
+
+/// A surface with frame callbacks waiting for a presented frame
+#[derive(Debug)]
+struct Waiting {
+    surface: WlSurface,
+    /// Oldest commit whose callbacks have not fired yet
+    first_commit: Instant,
+    /// Most recent commit, which a frame must include
+    last_commit: Instant,
+}
+
+/// Refresh cycles of an output counted from its first presented frame
+#[derive(Debug)]
+struct RefreshCounter {
+    /// Presentation time the count is based on
+    base: Instant,
+    base_sequence: u64,
+    last_sequence: u64,
+}
+
+impl RefreshCounter {
+    fn new(base: Instant) -> Self {
+        Self { base, base_sequence: 0, last_sequence: 0 }
+    }
+
+    /// Count for a frame presented at `presented`, always above the last
+    fn sequence(&mut self, presented: Instant, refresh: Duration) -> u64 {
+        let elapsed = presented.saturating_duration_since(self.base).as_secs_f64();
+        let cycles = (elapsed / refresh.as_secs_f64().max(f64::EPSILON)).round() as u64;
+        let sequence = (self.base_sequence + cycles).max(self.last_sequence + 1);
+        // Rebase after a refresh rate change or drift, keeping the count going
+        if sequence != self.base_sequence + cycles {
+            self.base = presented;
+            self.base_sequence = sequence;
+        }
+        self.last_sequence = sequence;
+        sequence
+    }
+}
+
+/// Surfaces waiting for their frame callbacks
+#[derive(Debug)]
+pub struct FrameCallbackQueue {
+    waiting: HashMap<ObjectId, Waiting>,
+    max_latency: Duration,
+    /// Refresh counter of each output, for presentation feedback
+    sequences: HashMap<String, RefreshCounter>,
+    sender: mpsc::UnboundedSender<FramePresented>,
+    receiver: mpsc::UnboundedReceiver<FramePresented>,
+}
+
+impl FrameCallbackQueue {
+    pub fn new(config: &FrameCallbackConfig) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            waiting: HashMap::new(),
+            max_latency: max_latency(config),
+            sequences: HashMap::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// Apply a new config
+    pub fn set_config(&mut self, config: &FrameCallbackConfig) {
+        self.max_latency = max_latency(config);
+    }
+
+    /// Channel for the render thread to report presented frames
+    pub fn sender(&self) -> mpsc::UnboundedSender<FramePresented> {
+        self.sender.clone()
+    }
+
+    /// Hold a surface's callbacks after a commit at `now` until a frame with
+    /// it is presented
+    pub fn queue(&mut self, surface: &WlSurface, now: Instant) {
+        self.waiting
+            .entry(surface.id())
+            .and_modify(|waiting| waiting.last_commit = now)
+            .or_insert_with(|| Waiting {
+                surface: surface.clone(),
+                first_commit: now,
+                last_commit: now,
+            });
+    }
+
+    /// Forget a destroyed surface
+    pub fn remove(&mut self, surface: &ObjectId) {
+        self.waiting.remove(surface);
+    }
+
+    /// Frames presented since the last call
+    pub fn presentations(&mut self) -> Vec<FramePresented> {
+        std::iter::from_fn(|| self.receiver.try_recv().ok()).collect()
+    }
+
+    /// Refresh counter value of a presented frame
+    pub fn sequence(&mut self, frame: &FramePresented) -> u64 {
+        self.sequences
+            .entry(frame.output.clone())
+            .or_insert_with(|| RefreshCounter::new(frame.presented))
+            .sequence(frame.presented, frame.refresh)
+    }
+
+    /// Take the surfaces whose callbacks fire for a presented frame; `shown`
+    /// tells whether the frame's output shows a surface
+    pub fn take_presented(&mut self, frame: &FramePresented, shown: impl Fn(&WlSurface) -> bool) -> Vec<WlSurface> {
+        self.take(|waiting| waiting.last_commit <= frame.submitted && shown(&waiting.surface))
+    }
+
+    /// Take the surfaces that waited the max latency for a presented frame
+    pub fn take_overdue(&mut self, now: Instant) -> Vec<WlSurface> {
+        let max_latency = self.max_latency;
+        self.take(|waiting| now.saturating_duration_since(waiting.first_commit) >= max_latency)
+    }
+
+    /// When the longest waiting surface reaches the max latency
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.waiting
+            .values()
+            .map(|waiting| waiting.first_commit + self.max_latency)
+            .min()
+    }
+
+    /// Number of surfaces waiting
+    pub fn len(&self) -> usize {
+        self.waiting.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+    }
+
+    fn take(&mut self, due: impl Fn(&Waiting) -> bool) -> Vec<WlSurface> {
+        let ids: Vec<ObjectId> = self
+            .waiting
+            .iter()
+            .filter(|(_, waiting)| due(waiting))
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.iter()
+            .filter_map(|id| self.waiting.remove(id))
+            .map(|waiting| waiting.surface)
+            .collect()
+    }
+}
+
+impl Default for FrameCallbackQueue {
+    fn default() -> Self {
+        Self::new(&FrameCallbackConfig::default())
+    }
+}
+
+fn max_latency(config: &FrameCallbackConfig) -> Duration {
+    Duration::from_millis(u64::from(config.max_latency_ms.max(1)))
+}
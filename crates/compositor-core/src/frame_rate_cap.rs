@@ -0,0 +1,99 @@
+// Per-window frame rate caps
+//
+// A window rule with `max_fps` caps how often the matching app is sent frame
+// callbacks, e.g. holding a busy Electron app to 30Hz. Clients that draw in
+// response to frame callbacks then render no faster than the cap, which saves
+// power without slowing down other clients. The cap covers the window's
+// surface and its subsurfaces.
+
+use config::WindowRule;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use wayland_server::backend::ObjectId;
+
+/// Callbacks due within this much of the cap interval are sent early, so a
+/// cap dividing the refresh rate is met exactly despite frame timing jitter
+const CAP_SLACK: Duration = Duration::from_millis(2);
+
+#[derive(Debug)]
+struct WindowCap {
+    app_id: String,
+    /// Minimum time between frame callbacks; `None` when no rule caps the app
+    interval: Option<Duration>,
+    /// When callbacks were last sent to each surface of the window
+    last_sent: HashMap<ObjectId, Instant>,
+}
+
+/// Frame rate caps of all windows
+#[derive(Debug, Default)]
+pub struct FrameRateCaps {
+    rules: Vec<WindowRule>,
+    /// Windows keyed by their root surface
+    windows: HashMap<ObjectId, WindowCap>,
+}
+
+impl FrameRateCaps {
+    /// Create a tracker with no rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the window rules, re-evaluating the cap of every window
+    pub fn set_rules(&mut self, rules: Vec<WindowRule>) {
+        self.rules = rules;
+        for window in self.windows.values_mut() {
+            window.interval = cap_interval(&self.rules, &window.app_id);
+        }
+    }
+
+    /// Apply window rules when a window's app ID becomes known or changes
+    pub fn apply_rules(&mut self, window: &ObjectId, app_id: &str) {
+        if self.windows.get(window).is_some_and(|cap| cap.app_id == app_id) {
+            return;
+        }
+        self.windows.insert(
+            window.clone(),
+            WindowCap {
+                app_id: app_id.to_string(),
+                interval: cap_interval(&self.rules, app_id),
+                last_sent: HashMap::new(),
+            },
+        );
+    }
+
+    /// Forget a destroyed window
+    pub fn remove_window(&mut self, window: &ObjectId) {
+        self.windows.remove(window);
+    }
+
+    /// Whether frame callbacks of a surface in `window` may be sent now
+    pub fn callbacks_due(&self, window: &ObjectId, surface: &ObjectId, now: Instant) -> bool {
+        let Some(cap) = self.windows.get(window) else {
+            return true;
+        };
+        let Some(interval) = cap.interval else {
+            return true;
+        };
+        cap.last_sent
+            .get(surface)
+            .is_none_or(|last| now.duration_since(*last) + CAP_SLACK >= interval)
+    }
+
+    /// Record that frame callbacks of a surface in `window` were sent
+    pub fn callbacks_sent(&mut self, window: &ObjectId, surface: &ObjectId, now: Instant) {
+        if let Some(cap) = self.windows.get_mut(window).filter(|cap| cap.interval.is_some()) {
+            cap.last_sent.insert(surface.clone(), now);
+        }
+    }
+}
+
+/// Minimum time between frame callbacks for an app; later matching rules
+/// take precedence
+fn cap_interval(rules: &[WindowRule], app_id: &str) -> Option<Duration> {
+    let max_fps = rules
+        .iter()
+        .rev()
+        .filter(|rule| rule.matches(app_id))
+        .find_map(|rule| rule.max_fps)?;
+    Some(Duration::from_secs(1) / max_fps.max(1))
+}
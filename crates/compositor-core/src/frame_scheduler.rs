@@ -0,0 +1,283 @@
+// Per-output frame scheduling
+//
+// Each output gets its own scheduler driven by its refresh rate and, where the
+// backend reports them, its vblank/page-flip events. This lets mixed setups
+// (e.g. a 60Hz and a 144Hz monitor) render at their native rates instead of
+// sharing one global frame timer, and lets damage be routed only to the
+// outputs it actually touches.
+
+use compositor_utils::math::Rect;
+use compositor_utils::prelude::*;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Identifier for an output known to the frame scheduler
+pub type OutputId = u32;
+
+/// Refresh rate assumed when an output does not report one (60Hz)
+pub const DEFAULT_REFRESH_MHZ: u32 = 60_000;
+
+/// Frame timing state for a single output
+#[derive(Debug, Clone)]
+pub struct OutputFrameScheduler {
+    name: String,
+    geometry: Rect,
+    refresh_interval: Duration,
+    /// Whether frame completion is signalled by vblank events from the backend
+    vblank_driven: bool,
+    /// Earliest time the next frame may start rendering
+    next_frame: Instant,
+    last_vblank: Option<Instant>,
+    /// A frame was submitted and we are waiting for its vblank
+    frame_pending: bool,
+    needs_redraw: bool,
+}
+
+impl OutputFrameScheduler {
+    /// Create a scheduler for an output with the given refresh rate in millihertz
+    pub fn new(name: impl Into<String>, geometry: Rect, refresh_mhz: u32, vblank_driven: bool) -> Self {
+        Self {
+            name: name.into(),
+            geometry,
+            refresh_interval: refresh_interval(refresh_mhz),
+            vblank_driven,
+            next_frame: Instant::now(),
+            last_vblank: None,
+            frame_pending: false,
+            needs_redraw: true,
+        }
+    }
+
+    /// Output name (e.g. "DP-1")
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Output geometry in global compositor space
+    pub fn geometry(&self) -> Rect {
+        self.geometry
+    }
+
+    /// Update the output geometry, e.g. after a mode change or rearrangement
+    pub fn set_geometry(&mut self, geometry: Rect) {
+        self.geometry = geometry;
+        self.needs_redraw = true;
+    }
+
+    /// Time between two refresh cycles of this output
+    pub fn refresh_interval(&self) -> Duration {
+        self.refresh_interval
+    }
+
+    /// Update the refresh rate in millihertz
+    pub fn set_refresh_rate(&mut self, refresh_mhz: u32) {
+        self.refresh_interval = refresh_interval(refresh_mhz);
+    }
+
+    /// Timestamp of the most recent vblank reported for this output
+    pub fn last_vblank(&self) -> Option<Instant> {
+        self.last_vblank
+    }
+
+    /// Whether a rectangle in global space is visible on this output
+    pub fn intersects(&self, rect: &Rect) -> bool {
+        self.geometry.intersects(rect)
+    }
+
+    /// Best guess of the first vblank at or after `time`, from the last
+    /// reported vblank and the refresh interval; `time` itself without one
+    pub fn predicted_vblank(&self, time: Instant) -> Instant {
+        let Some(last) = self.last_vblank else {
+            return time;
+        };
+        let since = time.saturating_duration_since(last);
+        let cycles = since.as_nanos().div_ceil(self.refresh_interval.as_nanos().max(1));
+        last + self.refresh_interval * u32::try_from(cycles).unwrap_or(u32::MAX)
+    }
+
+    /// Request that this output renders a new frame
+    pub fn schedule_redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+
+    /// Whether the output should render a frame at `now`
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.needs_redraw && !self.frame_pending && now >= self.next_frame
+    }
+
+    /// Time at which this output next wants to render, if it has anything to draw
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if self.needs_redraw && !self.frame_pending {
+            Some(self.next_frame)
+        } else {
+            None
+        }
+    }
+
+    /// Record that a frame was submitted for this output at `now`
+    pub fn frame_submitted(&mut self, now: Instant) {
+        self.needs_redraw = false;
+        self.next_frame = now + self.refresh_interval;
+        self.frame_pending = self.vblank_driven;
+    }
+
+    /// Record a vblank (page flip completion) for this output
+    pub fn on_vblank(&mut self, timestamp: Instant) {
+        self.frame_pending = false;
+        self.last_vblank = Some(timestamp);
+        // The next frame can start right away; it will be displayed on the
+        // following refresh cycle.
+        self.next_frame = timestamp;
+    }
+
+    /// Forget frame timing and redraw in full at `now`, e.g. after resume,
+    /// when vblanks of frames in flight never arrive
+    pub fn reset(&mut self, now: Instant) {
+        self.next_frame = now;
+        self.last_vblank = None;
+        self.frame_pending = false;
+        self.needs_redraw = true;
+    }
+}
+
+/// Frame scheduler managing independent refresh loops for all outputs
+#[derive(Debug)]
+pub struct FrameScheduler {
+    outputs: HashMap<OutputId, OutputFrameScheduler>,
+    next_output_id: OutputId,
+}
+
+impl FrameScheduler {
+    /// Create a scheduler with no outputs
+    pub fn new() -> Self {
+        Self {
+            outputs: HashMap::new(),
+            next_output_id: 1,
+        }
+    }
+
+    /// Register an output and return its identifier
+    pub fn add_output(&mut self, output: OutputFrameScheduler) -> OutputId {
+        let output_id = self.next_output_id;
+        self.next_output_id = output_id + 1;
+
+        info!(
+            "Added output {} ({}) at {:.2}Hz",
+            output_id,
+            output.name(),
+            1.0 / output.refresh_interval().as_secs_f64()
+        );
+        self.outputs.insert(output_id, output);
+        output_id
+    }
+
+    /// Remove an output
+    pub fn remove_output(&mut self, output_id: OutputId) -> Option<OutputFrameScheduler> {
+        let removed = self.outputs.remove(&output_id);
+        if let Some(output) = &removed {
+            info!("Removed output {} ({})", output_id, output.name());
+        }
+        removed
+    }
+
+    /// Get an output's scheduler
+    pub fn output(&self, output_id: OutputId) -> Option<&OutputFrameScheduler> {
+        self.outputs.get(&output_id)
+    }
+
+    /// Get an output's scheduler mutably
+    pub fn output_mut(&mut self, output_id: OutputId) -> Option<&mut OutputFrameScheduler> {
+        self.outputs.get_mut(&output_id)
+    }
+
+    /// Identifier of the output with the given name
+    pub fn find_output(&self, name: &str) -> Option<OutputId> {
+        self.outputs
+            .iter()
+            .find(|(_, output)| output.name() == name)
+            .map(|(&output_id, _)| output_id)
+    }
+
+    /// Identifiers of all registered outputs
+    pub fn output_ids(&self) -> Vec<OutputId> {
+        self.outputs.keys().copied().collect()
+    }
+
+    /// Number of registered outputs
+    pub fn output_count(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Identifiers of all outputs that a rectangle in global space is visible on
+    pub fn outputs_intersecting(&self, rect: &Rect) -> Vec<OutputId> {
+        self.outputs
+            .iter()
+            .filter(|(_, output)| output.intersects(rect))
+            .map(|(&output_id, _)| output_id)
+            .collect()
+    }
+
+    /// Schedule a redraw on every output
+    pub fn schedule_redraw_all(&mut self) {
+        for output in self.outputs.values_mut() {
+            output.schedule_redraw();
+        }
+    }
+
+    /// Forget the frame timing of every output and redraw them all
+    pub fn reset_all(&mut self, now: Instant) {
+        for output in self.outputs.values_mut() {
+            output.reset(now);
+        }
+    }
+
+    /// Schedule a redraw only on outputs that the damaged region intersects
+    pub fn schedule_redraw_region(&mut self, damage: &Rect) {
+        for output in self.outputs.values_mut() {
+            if output.intersects(damage) {
+                output.schedule_redraw();
+            }
+        }
+    }
+
+    /// Outputs that should render a frame at `now`
+    pub fn due_outputs(&self, now: Instant) -> Vec<OutputId> {
+        self.outputs
+            .iter()
+            .filter(|(_, output)| output.is_due(now))
+            .map(|(&output_id, _)| output_id)
+            .collect()
+    }
+
+    /// Earliest time any output next wants to render
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.outputs.values().filter_map(|output| output.next_deadline()).min()
+    }
+
+    /// Record that a frame was submitted for an output
+    pub fn frame_submitted(&mut self, output_id: OutputId, now: Instant) {
+        if let Some(output) = self.outputs.get_mut(&output_id) {
+            output.frame_submitted(now);
+        }
+    }
+
+    /// Record a vblank for an output
+    pub fn on_vblank(&mut self, output_id: OutputId, timestamp: Instant) {
+        match self.outputs.get_mut(&output_id) {
+            Some(output) => output.on_vblank(timestamp),
+            None => warn!("Vblank for unknown output {}", output_id),
+        }
+    }
+}
+
+impl Default for FrameScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Convert a refresh rate in millihertz to a frame interval
+fn refresh_interval(refresh_mhz: u32) -> Duration {
+    let refresh_mhz = if refresh_mhz == 0 { DEFAULT_REFRESH_MHZ } else { refresh_mhz };
+    Duration::from_nanos(1_000_000_000_000 / refresh_mhz as u64)
+}
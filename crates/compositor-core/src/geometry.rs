@@ -0,0 +1,109 @@
+// Conversions between smithay and compositor-utils geometry
+//
+// Window management gets its geometry from smithay in integer or f64
+// coordinates, while the renderer and UI code take the f32 types of
+// compositor_utils::geometry, which convert to glam. The coordinate space
+// carries over in both directions, so a logical smithay rectangle becomes a
+// logical compositor-utils rectangle and back.
+
+use compositor_utils::geometry::{self, Point, Rect, Size};
+use smithay::utils::{self as smithay_utils, Coordinate, Rectangle};
+
+/// Coordinate space of smithay matching a compositor-utils space
+pub trait SmithaySpace {
+    type Kind;
+}
+
+impl SmithaySpace for geometry::Logical {
+    type Kind = smithay_utils::Logical;
+}
+
+impl SmithaySpace for geometry::Physical {
+    type Kind = smithay_utils::Physical;
+}
+
+impl SmithaySpace for geometry::Buffer {
+    type Kind = smithay_utils::Buffer;
+}
+
+/// Coordinate space of compositor-utils matching a smithay space
+pub trait GeometrySpace {
+    type Space;
+}
+
+impl GeometrySpace for smithay_utils::Logical {
+    type Space = geometry::Logical;
+}
+
+impl GeometrySpace for smithay_utils::Physical {
+    type Space = geometry::Physical;
+}
+
+impl GeometrySpace for smithay_utils::Buffer {
+    type Space = geometry::Buffer;
+}
+
+/// Convert smithay geometry to compositor-utils geometry
+pub trait ToGeometry {
+    type Output;
+
+    fn to_geometry(self) -> Self::Output;
+}
+
+impl<N: Coordinate, Kind: GeometrySpace> ToGeometry for smithay_utils::Point<N, Kind> {
+    type Output = Point<Kind::Space>;
+
+    fn to_geometry(self) -> Self::Output {
+        let point = self.to_f64();
+        Point::new(point.x as f32, point.y as f32)
+    }
+}
+
+impl<N: Coordinate, Kind: GeometrySpace> ToGeometry for smithay_utils::Size<N, Kind> {
+    type Output = Size<Kind::Space>;
+
+    fn to_geometry(self) -> Self::Output {
+        let size = self.to_f64();
+        Size::new(size.w as f32, size.h as f32)
+    }
+}
+
+impl<N: Coordinate, Kind: GeometrySpace> ToGeometry for Rectangle<N, Kind> {
+    type Output = Rect<Kind::Space>;
+
+    fn to_geometry(self) -> Self::Output {
+        Rect::from_loc_and_size(self.loc.to_geometry(), self.size.to_geometry())
+    }
+}
+
+/// Convert compositor-utils geometry to smithay geometry in f64
+/// coordinates; round with e.g. `to_i32_round` where integers are needed
+pub trait ToSmithay {
+    type Output;
+
+    fn to_smithay(self) -> Self::Output;
+}
+
+impl<S: SmithaySpace> ToSmithay for Point<S> {
+    type Output = smithay_utils::Point<f64, S::Kind>;
+
+    fn to_smithay(self) -> Self::Output {
+        (f64::from(self.x), f64::from(self.y)).into()
+    }
+}
+
+impl<S: SmithaySpace> ToSmithay for Size<S> {
+    type Output = smithay_utils::Size<f64, S::Kind>;
+
+    fn to_smithay(self) -> Self::Output {
+        (f64::from(self.w), f64::from(self.h)).into()
+    }
+}
+
+impl<S: SmithaySpace> ToSmithay for Rect<S> {
+    type Output = Rectangle<f64, S::Kind>;
+
+    fn to_smithay(self) -> Self::Output {
+        Rectangle::new(self.loc.to_smithay(), self.size.to_smithay())
+    }
+}
@@ -0,0 +1,346 @@
+// Hot corners and screen edge actions
+//
+// Fed from the pointer motion and touch paths. A corner or edge fires its
+// configured action once the pointer has rested there for the dwell time, or
+// when a touch swipes inward from a screen edge. A cooldown prevents repeated
+// triggers while the pointer lingers or jitters around the trigger area.
+
+use compositor_utils::prelude::*;
+use config::{HotCornerAction, HotCornersConfig};
+use std::time::{Duration, Instant};
+
+/// Screen corner or edge that can trigger an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HotZone {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    TopEdge,
+    BottomEdge,
+    LeftEdge,
+    RightEdge,
+}
+
+/// Touch that started on a screen edge and may turn into a swipe
+#[derive(Debug, Clone, Copy)]
+struct EdgeTouch {
+    id: i32,
+    zone: HotZone,
+    start: Vec2,
+}
+
+/// Tracks pointer and touch positions and decides when hot zones fire
+#[derive(Debug)]
+pub struct HotCornerTracker {
+    config: HotCornersConfig,
+    output_size: Vec2,
+    /// Zone the pointer is currently in and since when
+    dwell: Option<(HotZone, Instant)>,
+    /// The current dwell already fired; the pointer must leave before re-arming
+    dwell_fired: bool,
+    last_trigger: Option<Instant>,
+    edge_touch: Option<EdgeTouch>,
+}
+
+impl HotCornerTracker {
+    /// Create a tracker for an output of the given logical size
+    pub fn new(config: HotCornersConfig, output_size: Vec2) -> Self {
+        Self {
+            config,
+            output_size,
+            dwell: None,
+            dwell_fired: false,
+            last_trigger: None,
+            edge_touch: None,
+        }
+    }
+
+    /// Apply new configuration
+    pub fn set_config(&mut self, config: HotCornersConfig) {
+        self.config = config;
+        self.reset();
+    }
+
+    /// Update the logical output size the zones are computed from
+    pub fn set_output_size(&mut self, output_size: Vec2) {
+        self.output_size = output_size;
+        self.reset();
+    }
+
+    /// Logical size of the output the zones are computed from
+    pub fn output_size(&self) -> Vec2 {
+        self.output_size
+    }
+
+    /// Handle pointer motion; returns an action if a zone fired immediately
+    pub fn pointer_motion(&mut self, position: Vec2, now: Instant) -> Option<HotCornerAction> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let zone = self.zone_at(position).filter(|&zone| self.action_for(zone).is_some());
+        match (zone, self.dwell) {
+            (Some(zone), Some((current, _))) if zone == current => {}
+            (Some(zone), _) => {
+                self.dwell = Some((zone, now));
+                self.dwell_fired = false;
+            }
+            (None, _) => {
+                self.dwell = None;
+                self.dwell_fired = false;
+            }
+        }
+
+        self.poll(now)
+    }
+
+    /// Check whether the pointer has dwelled long enough to fire
+    ///
+    /// Call from a timer when `next_deadline` returns a time, so a pointer
+    /// resting in a corner fires without further motion.
+    pub fn poll(&mut self, now: Instant) -> Option<HotCornerAction> {
+        let (zone, since) = self.dwell?;
+        if self.dwell_fired || now.duration_since(since) < self.dwell_time() {
+            return None;
+        }
+
+        self.dwell_fired = true;
+        self.trigger(zone, now)
+    }
+
+    /// Time at which a pending dwell will fire, if any
+    pub fn next_deadline(&self) -> Option<Instant> {
+        match self.dwell {
+            Some((_, since)) if !self.dwell_fired => Some(since + self.dwell_time()),
+            _ => None,
+        }
+    }
+
+    /// Handle a touch point going down
+    pub fn touch_down(&mut self, id: i32, position: Vec2) {
+        if !self.config.enabled || self.edge_touch.is_some() {
+            return;
+        }
+
+        let zone = self
+            .edge_at(position, self.config.corner_size.max(1) as f32 * 4.0)
+            .filter(|&zone| self.action_for(zone).is_some());
+        if let Some(zone) = zone {
+            self.edge_touch = Some(EdgeTouch { id, zone, start: position });
+        }
+    }
+
+    /// Handle touch motion; returns an action when an edge swipe completes
+    pub fn touch_motion(&mut self, id: i32, position: Vec2, now: Instant) -> Option<HotCornerAction> {
+        let touch = self.edge_touch.filter(|touch| touch.id == id)?;
+
+        let delta = position - touch.start;
+        let inward = match touch.zone {
+            HotZone::TopEdge => delta.y,
+            HotZone::BottomEdge => -delta.y,
+            HotZone::LeftEdge => delta.x,
+            HotZone::RightEdge => -delta.x,
+            _ => 0.0,
+        };
+        if inward < self.config.edge_swipe_distance as f32 {
+            return None;
+        }
+
+        self.edge_touch = None;
+        self.trigger(touch.zone, now)
+    }
+
+    /// Handle a touch point going up
+    pub fn touch_up(&mut self, id: i32) {
+        if self.edge_touch.is_some_and(|touch| touch.id == id) {
+            self.edge_touch = None;
+        }
+    }
+
+    /// Forget touches in progress, e.g. when the touch sequence is cancelled
+    pub fn touch_cancel(&mut self) {
+        self.edge_touch = None;
+    }
+
+    /// Fire a zone's action unless still cooling down
+    fn trigger(&mut self, zone: HotZone, now: Instant) -> Option<HotCornerAction> {
+        let cooldown = Duration::from_millis(self.config.cooldown);
+        if self.last_trigger.is_some_and(|last| now.duration_since(last) < cooldown) {
+            debug!("Hot zone {:?} ignored during cooldown", zone);
+            return None;
+        }
+
+        let action = self.action_for(zone)?.clone();
+        self.last_trigger = Some(now);
+        info!("Hot zone {:?} triggered {:?}", zone, action);
+        Some(action)
+    }
+
+    /// Configured action for a zone, or `None` if it does nothing
+    fn action_for(&self, zone: HotZone) -> Option<&HotCornerAction> {
+        let action = match zone {
+            HotZone::TopLeft => &self.config.top_left,
+            HotZone::TopRight => &self.config.top_right,
+            HotZone::BottomLeft => &self.config.bottom_left,
+            HotZone::BottomRight => &self.config.bottom_right,
+            HotZone::TopEdge => &self.config.top_edge,
+            HotZone::BottomEdge => &self.config.bottom_edge,
+            HotZone::LeftEdge => &self.config.left_edge,
+            HotZone::RightEdge => &self.config.right_edge,
+        };
+        (*action != HotCornerAction::None).then_some(action)
+    }
+
+    /// Corner or edge (for pointer dwell) at a position, corners taking priority
+    fn zone_at(&self, position: Vec2) -> Option<HotZone> {
+        let size = self.config.corner_size as f32;
+        let left = position.x < size;
+        let right = position.x >= self.output_size.x - size;
+        let top = position.y < size;
+        let bottom = position.y >= self.output_size.y - size;
+
+        match (left, right, top, bottom) {
+            (true, _, true, _) => Some(HotZone::TopLeft),
+            (_, true, true, _) => Some(HotZone::TopRight),
+            (true, _, _, true) => Some(HotZone::BottomLeft),
+            (_, true, _, true) => Some(HotZone::BottomRight),
+            // Edges require the pointer to be pressed against the very last pixel
+            _ => self.edge_at(position, 1.0),
+        }
+    }
+
+    /// Screen edge within `margin` pixels of a position
+    fn edge_at(&self, position: Vec2, margin: f32) -> Option<HotZone> {
+        if position.y < margin {
+            Some(HotZone::TopEdge)
+        } else if position.y >= self.output_size.y - margin {
+            Some(HotZone::BottomEdge)
+        } else if position.x < margin {
+            Some(HotZone::LeftEdge)
+        } else if position.x >= self.output_size.x - margin {
+            Some(HotZone::RightEdge)
+        } else {
+            None
+        }
+    }
+
+    fn dwell_time(&self) -> Duration {
+        Duration::from_millis(self.config.dwell_time)
+    }
+
+    fn reset(&mut self) {
+        self.dwell = None;
+        self.dwell_fired = false;
+        self.edge_touch = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: Vec2 = Vec2::new(1920.0, 1080.0);
+
+    fn tracker() -> HotCornerTracker {
+        HotCornerTracker::new(HotCornersConfig::default(), OUTPUT)
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_corner_hit() {
+        let tracker = tracker();
+        assert_eq!(tracker.zone_at(Vec2::new(0.0, 0.0)), Some(HotZone::TopLeft));
+        assert_eq!(tracker.zone_at(Vec2::new(3.0, 3.0)), Some(HotZone::TopLeft));
+        assert_eq!(tracker.zone_at(Vec2::new(4.0, 4.0)), None);
+        assert_eq!(tracker.zone_at(Vec2::new(1919.0, 0.0)), Some(HotZone::TopRight));
+        assert_eq!(tracker.zone_at(Vec2::new(0.0, 1079.0)), Some(HotZone::BottomLeft));
+        assert_eq!(tracker.zone_at(Vec2::new(1916.0, 1076.0)), Some(HotZone::BottomRight));
+        // Edges only count against the last pixel
+        assert_eq!(tracker.zone_at(Vec2::new(0.0, 500.0)), Some(HotZone::LeftEdge));
+        assert_eq!(tracker.zone_at(Vec2::new(1.0, 500.0)), None);
+        assert_eq!(tracker.zone_at(Vec2::new(960.0, 1079.0)), Some(HotZone::BottomEdge));
+    }
+
+    #[test]
+    fn test_dwell_fires_once_after_dwell_time() {
+        let mut tracker = tracker();
+        let start = Instant::now();
+
+        assert_eq!(tracker.pointer_motion(Vec2::new(1.0, 1.0), start), None);
+        assert_eq!(tracker.next_deadline(), Some(start + ms(150)));
+        assert_eq!(tracker.poll(start + ms(149)), None);
+        assert_eq!(tracker.poll(start + ms(150)), Some(HotCornerAction::Overview));
+        assert_eq!(tracker.next_deadline(), None);
+        // Moving within the corner does not re-arm it
+        assert_eq!(tracker.pointer_motion(Vec2::new(2.0, 2.0), start + ms(2000)), None);
+        assert_eq!(tracker.poll(start + ms(2200)), None);
+    }
+
+    #[test]
+    fn test_leaving_zone_cancels_dwell() {
+        let mut tracker = tracker();
+        let start = Instant::now();
+
+        tracker.pointer_motion(Vec2::new(1.0, 1.0), start);
+        tracker.pointer_motion(Vec2::new(500.0, 500.0), start + ms(100));
+        assert_eq!(tracker.next_deadline(), None);
+        assert_eq!(tracker.poll(start + ms(200)), None);
+    }
+
+    #[test]
+    fn test_zone_without_action_does_not_arm() {
+        let mut tracker = tracker();
+        let start = Instant::now();
+
+        tracker.pointer_motion(Vec2::new(1919.0, 0.0), start);
+        assert_eq!(tracker.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_cooldown_suppresses_retrigger() {
+        let mut tracker = tracker();
+        let start = Instant::now();
+
+        tracker.pointer_motion(Vec2::new(1.0, 1.0), start);
+        assert!(tracker.poll(start + ms(150)).is_some());
+        tracker.pointer_motion(Vec2::new(500.0, 500.0), start + ms(200));
+        tracker.pointer_motion(Vec2::new(1919.0, 1079.0), start + ms(300));
+        assert_eq!(tracker.poll(start + ms(450)), None);
+
+        tracker.pointer_motion(Vec2::new(500.0, 500.0), start + ms(1200));
+        tracker.pointer_motion(Vec2::new(1919.0, 1079.0), start + ms(1300));
+        assert_eq!(tracker.poll(start + ms(1450)), Some(HotCornerAction::ShowDesktop));
+    }
+
+    #[test]
+    fn test_edge_swipe_threshold() {
+        let mut tracker = tracker();
+        let now = Instant::now();
+
+        // Starts within four corner sizes of the left edge
+        tracker.touch_down(0, Vec2::new(10.0, 500.0));
+        assert_eq!(tracker.touch_motion(0, Vec2::new(69.0, 500.0), now), None);
+        assert_eq!(tracker.touch_motion(1, Vec2::new(200.0, 500.0), now), None);
+        assert_eq!(tracker.touch_motion(0, Vec2::new(70.0, 520.0), now), Some(HotCornerAction::Launcher));
+        // The swipe is used up
+        assert_eq!(tracker.touch_motion(0, Vec2::new(200.0, 500.0), now), None);
+    }
+
+    #[test]
+    fn test_edge_swipe_must_start_at_edge_and_move_inward() {
+        let mut tracker = tracker();
+        let now = Instant::now();
+
+        tracker.touch_down(0, Vec2::new(16.0, 500.0));
+        assert_eq!(tracker.touch_motion(0, Vec2::new(200.0, 500.0), now), None);
+
+        tracker.touch_down(1, Vec2::new(10.0, 500.0));
+        assert_eq!(tracker.touch_motion(1, Vec2::new(10.0, 700.0), now), None);
+        tracker.touch_up(1);
+        assert_eq!(tracker.touch_motion(1, Vec2::new(200.0, 500.0), now), None);
+    }
+}
@@ -1,2 +1,85 @@
-// Input handling placeholder
+// Input handling
+//
+// Keybindings are matched on key presses before the keys reach clients.
+// Combinations match the key's unshifted Latin keysym, so `Super+Shift+1`
+// matches although Shift turns the key into `!`, and `Super+Q` works with
+// non-Latin layouts too. A key that triggered a binding also has its release
+// kept from the focused client.
 pub use crate::window::input::*;
+
+use compositor_utils::prelude::*;
+use config::{BindingAction, BindingsConfig, KeyModifiers};
+use smithay::input::keyboard::{xkb, Keycode, Keysym, ModifiersState};
+use std::collections::{HashMap, HashSet};
+
+/// Configured keybindings, resolved to keysyms
+#[derive(Debug, Default)]
+pub struct KeyBindings {
+    bindings: HashMap<(KeyModifiers, Keysym), BindingAction>,
+    /// Keys held down that triggered a binding
+    pressed: HashSet<Keycode>,
+}
+
+impl KeyBindings {
+    /// Resolve configured bindings; bindings of unknown keys are skipped
+    pub fn new(config: &BindingsConfig) -> Self {
+        let mut bindings = Self::default();
+        bindings.set_config(config);
+        bindings
+    }
+
+    /// Apply new configuration
+    ///
+    /// The configuration is expected to be validated; invalid bindings are
+    /// skipped.
+    pub fn set_config(&mut self, config: &BindingsConfig) {
+        let parsed = match config.parse() {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Ignoring keybindings: {}", e);
+                Vec::new()
+            }
+        };
+        self.bindings = parsed
+            .into_iter()
+            .filter_map(|(combo, action)| match keysym_from_name(&combo.key) {
+                Some(keysym) => Some(((combo.modifiers, keysym), action)),
+                None => {
+                    warn!("Ignoring key binding {}: unknown key {}", combo, combo.key);
+                    None
+                }
+            })
+            .collect();
+        debug!("Loaded {} keybindings", self.bindings.len());
+    }
+
+    /// Handle a key press; returns the action of a binding it triggers
+    ///
+    /// `keysym` is the key's unshifted Latin keysym.
+    pub fn key_pressed(&mut self, keycode: Keycode, modifiers: &ModifiersState, keysym: Keysym) -> Option<BindingAction> {
+        let held = KeyModifiers {
+            logo: modifiers.logo,
+            ctrl: modifiers.ctrl,
+            alt: modifiers.alt,
+            shift: modifiers.shift,
+        };
+        let action = self.bindings.get(&(held, keysym))?.clone();
+        self.pressed.insert(keycode);
+        Some(action)
+    }
+
+    /// Handle a key release; returns whether the key triggered a binding
+    /// and the release must not reach clients either
+    pub fn key_released(&mut self, keycode: Keycode) -> bool {
+        self.pressed.remove(&keycode)
+    }
+}
+
+/// Keysym of a key name, e.g. `Return`, `q` or `F1`; letters resolve to
+/// lowercase keysyms, as the unshifted keys produce
+fn keysym_from_name(name: &str) -> Option<Keysym> {
+    [xkb::KEYSYM_NO_FLAGS, xkb::KEYSYM_CASE_INSENSITIVE]
+        .into_iter()
+        .map(|flags| xkb::keysym_from_name(name, flags))
+        .find(|keysym| *keysym != Keysym::NoSymbol)
+}
@@ -0,0 +1,249 @@
+// Exclusive keyboard grabs
+//
+// Serves zwp_xwayland_keyboard_grab_manager_v1 to Xwayland and to native
+// clients the security policy allows, such as on-screen keyboards and remote
+// desktop servers. While a grab is active every key goes to the grabbing
+// surface. The grab ends when the client destroys it or disconnects, when the
+// surface goes away, or - unless configured to be kept - when keyboard focus
+// moves to another surface.
+
+use compositor_utils::prelude::*;
+use smithay::backend::input::{KeyState, Keycode};
+use smithay::input::keyboard::{GrabStartData, KeyboardGrab, KeyboardInnerHandle, ModifiersState};
+use smithay::input::{Seat, SeatHandler};
+use smithay::utils::{IsAlive, Serial};
+use wayland_protocols::xwayland::keyboard_grab::zv1::server::{
+    zwp_xwayland_keyboard_grab_manager_v1::{self, ZwpXwaylandKeyboardGrabManagerV1},
+    zwp_xwayland_keyboard_grab_v1::{self, ZwpXwaylandKeyboardGrabV1},
+};
+use wayland_server::backend::{ClientId, GlobalId};
+use wayland_server::protocol::wl_surface::WlSurface;
+use wayland_server::{Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource};
+
+const MANAGER_VERSION: u32 = 1;
+
+/// Handler for keyboard grab requests
+pub trait KeyboardGrabHandler: SeatHandler + Sized {
+    /// A client asked to grab the keyboard of `seat` for one of its surfaces
+    ///
+    /// The handler checks its policy and, if the grab is allowed, sets an
+    /// [`ExclusiveKeyboardGrab`] on the seat's keyboard.
+    fn grab_keyboard(&mut self, client: &Client, surface: WlSurface, seat: Seat<Self>, grab: ZwpXwaylandKeyboardGrabV1);
+}
+
+/// Data of the keyboard grab manager global
+pub struct KeyboardGrabGlobalData {
+    filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
+}
+
+/// Data of a keyboard grab object
+pub struct KeyboardGrabData<D: SeatHandler> {
+    /// Seat the grab was requested on; `None` if it was already gone
+    seat: Option<Seat<D>>,
+}
+
+/// State of the keyboard grab manager global
+#[derive(Debug)]
+pub struct KeyboardGrabState {
+    global: GlobalId,
+    keep_on_focus_loss: bool,
+}
+
+impl KeyboardGrabState {
+    /// Register the keyboard grab manager global for clients passing `filter`
+    pub fn new<D, F>(display: &DisplayHandle, filter: F) -> Self
+    where
+        D: GlobalDispatch<ZwpXwaylandKeyboardGrabManagerV1, KeyboardGrabGlobalData>
+            + Dispatch<ZwpXwaylandKeyboardGrabManagerV1, ()>
+            + Dispatch<ZwpXwaylandKeyboardGrabV1, KeyboardGrabData<D>>
+            + KeyboardGrabHandler
+            + 'static,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+    {
+        let data = KeyboardGrabGlobalData { filter: Box::new(filter) };
+        let global = display.create_global::<D, ZwpXwaylandKeyboardGrabManagerV1, _>(MANAGER_VERSION, data);
+        Self {
+            global,
+            keep_on_focus_loss: false,
+        }
+    }
+
+    /// Keyboard grab manager global
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Keep new grabs when the grabbing surface loses focus instead of releasing them
+    pub fn set_keep_on_focus_loss(&mut self, keep: bool) {
+        self.keep_on_focus_loss = keep;
+    }
+
+    /// Whether new grabs are kept when the grabbing surface loses focus
+    pub fn keeps_on_focus_loss(&self) -> bool {
+        self.keep_on_focus_loss
+    }
+}
+
+impl<D> GlobalDispatch<ZwpXwaylandKeyboardGrabManagerV1, KeyboardGrabGlobalData, D> for KeyboardGrabState
+where
+    D: GlobalDispatch<ZwpXwaylandKeyboardGrabManagerV1, KeyboardGrabGlobalData>
+        + Dispatch<ZwpXwaylandKeyboardGrabManagerV1, ()>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwpXwaylandKeyboardGrabManagerV1>,
+        _global_data: &KeyboardGrabGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, global_data: &KeyboardGrabGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> Dispatch<ZwpXwaylandKeyboardGrabManagerV1, (), D> for KeyboardGrabState
+where
+    D: Dispatch<ZwpXwaylandKeyboardGrabManagerV1, ()>
+        + Dispatch<ZwpXwaylandKeyboardGrabV1, KeyboardGrabData<D>>
+        + KeyboardGrabHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        client: &Client,
+        _manager: &ZwpXwaylandKeyboardGrabManagerV1,
+        request: zwp_xwayland_keyboard_grab_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwp_xwayland_keyboard_grab_manager_v1::Request::GrabKeyboard { id, surface, seat } => {
+                let seat = Seat::<D>::from_resource(&seat);
+                let grab = data_init.init(id, KeyboardGrabData { seat: seat.clone() });
+                if surface.client().as_ref() != Some(client) {
+                    warn!("Ignoring keyboard grab for a surface of another client");
+                    return;
+                }
+                if let Some(seat) = seat {
+                    state.grab_keyboard(client, surface, seat, grab);
+                }
+            }
+            zwp_xwayland_keyboard_grab_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZwpXwaylandKeyboardGrabV1, KeyboardGrabData<D>, D> for KeyboardGrabState
+where
+    D: Dispatch<ZwpXwaylandKeyboardGrabV1, KeyboardGrabData<D>> + SeatHandler + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _grab: &ZwpXwaylandKeyboardGrabV1,
+        request: zwp_xwayland_keyboard_grab_v1::Request,
+        _data: &KeyboardGrabData<D>,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwp_xwayland_keyboard_grab_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, grab: &ZwpXwaylandKeyboardGrabV1, data: &KeyboardGrabData<D>) {
+        // Release right away instead of at the next key press
+        let Some(keyboard) = data.seat.as_ref().and_then(Seat::get_keyboard) else {
+            return;
+        };
+        let active = keyboard
+            .with_grab(|_, active| {
+                active
+                    .downcast_ref::<ExclusiveKeyboardGrab<D>>()
+                    .is_some_and(|active| active.grab == *grab)
+            })
+            .unwrap_or(false);
+        if active {
+            info!("Keyboard grab released by its client");
+            keyboard.unset_grab(state);
+        }
+    }
+}
+
+/// Keyboard grab sending every key to one surface
+pub struct ExclusiveKeyboardGrab<D: SeatHandler> {
+    grab: ZwpXwaylandKeyboardGrabV1,
+    start_data: GrabStartData<D>,
+    /// Keep the grab, and focus, when something else asks for focus
+    keep_on_focus_loss: bool,
+}
+
+impl<D: SeatHandler + 'static> ExclusiveKeyboardGrab<D> {
+    /// Grab the keyboard for `focus` until `grab` is destroyed
+    pub fn new(grab: ZwpXwaylandKeyboardGrabV1, focus: D::KeyboardFocus, keep_on_focus_loss: bool) -> Self {
+        Self {
+            grab,
+            start_data: GrabStartData { focus: Some(focus) },
+            keep_on_focus_loss,
+        }
+    }
+
+    /// Whether both the grab object and the grabbing surface still exist
+    fn is_alive(&self) -> bool {
+        self.grab.is_alive() && self.start_data.focus.as_ref().is_some_and(|focus| focus.alive())
+    }
+}
+
+impl<D: SeatHandler + 'static> KeyboardGrab<D> for ExclusiveKeyboardGrab<D> {
+    fn input(
+        &mut self,
+        data: &mut D,
+        handle: &mut KeyboardInnerHandle<'_, D>,
+        keycode: Keycode,
+        state: KeyState,
+        modifiers: Option<ModifiersState>,
+        serial: Serial,
+        time: u32,
+    ) {
+        if self.is_alive() {
+            handle.set_focus(data, self.start_data.focus.clone(), serial);
+        } else {
+            debug!("Keyboard grab released, grabbing surface is gone");
+            handle.unset_grab(self, data, serial, false);
+        }
+        handle.input(data, keycode, state, modifiers, serial, time)
+    }
+
+    fn set_focus(
+        &mut self,
+        data: &mut D,
+        handle: &mut KeyboardInnerHandle<'_, D>,
+        focus: Option<D::KeyboardFocus>,
+        serial: Serial,
+    ) {
+        if focus == self.start_data.focus {
+            handle.set_focus(data, focus, serial);
+            return;
+        }
+        if self.keep_on_focus_loss && self.is_alive() {
+            return;
+        }
+        info!("Keyboard grab released on focus change");
+        handle.unset_grab(self, data, serial, false);
+        handle.set_focus(data, focus, serial);
+    }
+
+    fn start_data(&self) -> &GrabStartData<D> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, _data: &mut D) {}
+}
@@ -0,0 +1,104 @@
+// Force quit ("xkill") mode
+//
+// A keybinding arms kill mode and the cursor becomes a crosshair. Clicking a
+// window resolves the owning client's process from its socket credentials and
+// opens a pidfd for it right away, so the process that gets killed after the
+// user confirms is the one that was clicked even if the PID is reused.
+
+use crate::dialog::Dialog;
+use compositor_utils::prelude::*;
+use smithay::input::pointer::CursorIcon;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use wayland_server::backend::{ClientId, ObjectId};
+
+/// Process of a Wayland client
+#[derive(Debug)]
+pub struct ClientProcess {
+    pub pid: i32,
+    /// Stable handle to the process; `None` on kernels without pidfd support
+    pidfd: Option<OwnedFd>,
+}
+
+impl ClientProcess {
+    /// Take a handle to a process by PID
+    pub fn open(pid: i32) -> Self {
+        let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+        let pidfd = (fd >= 0).then(|| unsafe { OwnedFd::from_raw_fd(fd as i32) });
+        if pidfd.is_none() {
+            debug!("pidfd_open failed for pid {}: {}", pid, std::io::Error::last_os_error());
+        }
+        Self { pid, pidfd }
+    }
+
+    /// Send SIGKILL to the process
+    pub fn kill(&self) -> Result<()> {
+        let result = match &self.pidfd {
+            Some(pidfd) => unsafe {
+                libc::syscall(
+                    libc::SYS_pidfd_send_signal,
+                    pidfd.as_raw_fd(),
+                    libc::SIGKILL,
+                    std::ptr::null::<libc::siginfo_t>(),
+                    0,
+                ) as i32
+            },
+            None => unsafe { libc::kill(self.pid, libc::SIGKILL) },
+        };
+        if result != 0 {
+            return Err(CompositorError::system(format!(
+                "Failed to kill pid {}: {}",
+                self.pid,
+                std::io::Error::last_os_error()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Window picked in kill mode, waiting for confirmation
+#[derive(Debug)]
+pub struct KillTarget {
+    pub client: ClientId,
+    pub surface: ObjectId,
+    /// Window title or app ID shown in the confirmation overlay
+    pub name: String,
+    /// `None` if the client's credentials could not be read
+    pub process: Option<ClientProcess>,
+    /// Confirmation overlay, answering whether to kill
+    pub dialog: Dialog<bool>,
+}
+
+/// Kill mode state
+#[derive(Debug, Default)]
+pub enum KillMode {
+    #[default]
+    Inactive,
+    /// Waiting for the user to click a window
+    Selecting,
+    /// Waiting for the user to confirm killing the clicked window
+    Confirming(Box<KillTarget>),
+}
+
+impl KillMode {
+    /// Whether pointer clicks should pick a window instead of reaching clients
+    pub fn is_selecting(&self) -> bool {
+        matches!(self, Self::Selecting)
+    }
+
+    /// Window awaiting confirmation
+    pub fn target(&self) -> Option<&KillTarget> {
+        match self {
+            Self::Confirming(target) => Some(target.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Cursor to show while kill mode is active
+    pub fn cursor_icon(&self) -> Option<CursorIcon> {
+        match self {
+            Self::Inactive => None,
+            Self::Selecting => Some(CursorIcon::Crosshair),
+            Self::Confirming(_) => Some(CursorIcon::Default),
+        }
+    }
+}
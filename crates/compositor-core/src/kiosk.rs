@@ -0,0 +1,116 @@
+// Kiosk mode
+//
+// Runs a single application fullscreen for signage and dedicated displays.
+// The application is launched once the Wayland socket is up and relaunched
+// after a delay whenever it exits. While kiosk mode is active the compositor
+// makes new windows fullscreen, turns off workspace switching, show desktop
+// and interactive window management, and only accepts connections from the
+// kiosk application and whitelisted helper clients, which also keeps the app
+// bar and launcher out.
+
+use compositor_utils::prelude::*;
+use config::KioskConfig;
+use crate::wayland_socket::client_command;
+use std::process::{Child, Stdio};
+use std::time::{Duration, Instant};
+
+/// Launches the kiosk application and restarts it when it exits
+#[derive(Debug)]
+pub struct KioskSupervisor {
+    config: KioskConfig,
+    process: Option<Child>,
+    /// When to launch the application next; `None` while it runs
+    launch_at: Option<Instant>,
+    /// Socket the application connects to
+    wayland_display: Option<String>,
+}
+
+impl KioskSupervisor {
+    /// Create a supervisor with the given configuration
+    pub fn new(config: KioskConfig) -> Self {
+        let launch_at = config.enabled.then(Instant::now);
+        Self {
+            config,
+            process: None,
+            launch_at,
+            wayland_display: None,
+        }
+    }
+
+    /// Apply new configuration
+    ///
+    /// A running application is kept; it is launched right away if kiosk
+    /// mode was just enabled.
+    pub fn set_config(&mut self, config: KioskConfig) {
+        if config.enabled && self.process.is_none() {
+            self.launch_at = Some(Instant::now());
+        } else if !config.enabled {
+            self.launch_at = None;
+        }
+        self.config = config;
+    }
+
+    /// Set the socket the application connects to
+    pub fn set_wayland_display(&mut self, wayland_display: Option<String>) {
+        self.wayland_display = wayland_display;
+    }
+
+    /// Whether kiosk mode is active
+    pub fn is_active(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Clients allowed to connect: the kiosk application and the whitelist
+    ///
+    /// `None` while kiosk mode is off, when every client may connect.
+    pub fn allowed_clients(&self) -> Option<Vec<String>> {
+        if !self.config.enabled {
+            return None;
+        }
+        let program = self.config.command.first().cloned();
+        Some(program.into_iter().chain(self.config.allowed_clients.iter().cloned()).collect())
+    }
+
+    /// Launch or relaunch the application when due; call from the event loop
+    pub fn poll(&mut self, now: Instant) {
+        if !self.config.enabled {
+            return;
+        }
+        if let Some(child) = self.process.as_mut() {
+            match child.try_wait() {
+                Ok(None) => return,
+                Ok(Some(status)) => warn!("Kiosk application exited with {}", status),
+                Err(e) => warn!("Failed to check kiosk application: {}", e),
+            }
+            self.process = None;
+            self.launch_at = Some(now + Duration::from_millis(self.config.restart_delay));
+        }
+        if self.launch_at.is_some_and(|launch_at| now >= launch_at) {
+            self.launch(now);
+        }
+    }
+
+    /// Time of the next launch attempt, if one is pending
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.launch_at
+    }
+
+    fn launch(&mut self, now: Instant) {
+        let Some((program, args)) = self.config.command.split_first() else {
+            self.launch_at = None;
+            return;
+        };
+        let mut command = client_command(program, self.wayland_display.as_deref());
+        match command.args(args).stdin(Stdio::null()).spawn() {
+            Ok(child) => {
+                info!("Launched kiosk application: {} (pid {})", program, child.id());
+                self.process = Some(child);
+                self.launch_at = None;
+            }
+            Err(e) => {
+                warn!("Failed to launch kiosk application {}: {}", program, e);
+                self.launch_at = Some(now + Duration::from_millis(self.config.restart_delay));
+            }
+        }
+    }
+}
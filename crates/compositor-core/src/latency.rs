@@ -0,0 +1,189 @@
+// Protocol round-trip latency tracking
+//
+// Two round trips show how quickly a client's toolkit reacts: from sending an
+// xdg_surface configure to the client acking it, and from sending a frame
+// callback to the surface's next commit. Both are measured per client and
+// published with the client statistics, to tell slow toolkits from overloaded
+// clients.
+
+use ipc::protocol::{ClientLatencyStats, ClientProcessInfo, LatencySummary};
+use smithay::utils::Serial;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use wayland_server::backend::{ClientId, ObjectId};
+
+/// Round trips kept per client and kind for the aggregate statistics
+const LATENCY_HISTORY: usize = 256;
+
+/// Recent round trips of one kind
+#[derive(Debug, Default)]
+struct LatencyHistory {
+    samples: u64,
+    recent: VecDeque<Duration>,
+}
+
+impl LatencyHistory {
+    fn record(&mut self, latency: Duration) {
+        self.samples += 1;
+        if self.recent.len() == LATENCY_HISTORY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(latency);
+    }
+
+    fn summary(&self) -> LatencySummary {
+        if self.recent.is_empty() {
+            return LatencySummary { samples: self.samples, ..LatencySummary::default() };
+        }
+        let mut sorted: Vec<Duration> = self.recent.iter().copied().collect();
+        sorted.sort_unstable();
+        let millis = |duration: Duration| duration.as_secs_f32() * 1000.0;
+        let p95_index = ((sorted.len() as f32 * 0.95).ceil() as usize).saturating_sub(1);
+        LatencySummary {
+            samples: self.samples,
+            average_ms: millis(sorted.iter().sum::<Duration>()) / sorted.len() as f32,
+            p95_ms: millis(sorted[p95_index]),
+            max_ms: millis(sorted[sorted.len() - 1]),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ClientLatency {
+    process: Option<ClientProcessInfo>,
+    configure: LatencyHistory,
+    frame_callback: LatencyHistory,
+}
+
+/// Round trips awaiting the client's answer on one surface
+#[derive(Debug)]
+struct PendingRoundTrips {
+    client: ClientId,
+    /// Sent configures, oldest first
+    configures: VecDeque<(Serial, Instant)>,
+    /// When the last batch of frame callbacks was sent
+    frame_callback: Option<Instant>,
+}
+
+/// Configure and frame callback round-trip latencies of all clients
+#[derive(Debug)]
+pub struct ProtocolLatencyTracker {
+    clients: HashMap<ClientId, ClientLatency>,
+    pending: HashMap<ObjectId, PendingRoundTrips>,
+    publisher: watch::Sender<Vec<ClientLatencyStats>>,
+}
+
+impl ProtocolLatencyTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self {
+            clients: HashMap::new(),
+            pending: HashMap::new(),
+            publisher: watch::channel(Vec::new()).0,
+        }
+    }
+
+    /// Channel for IPC to read published latencies
+    pub fn subscribe(&self) -> watch::Receiver<Vec<ClientLatencyStats>> {
+        self.publisher.subscribe()
+    }
+
+    fn pending(&mut self, client: ClientId, surface: ObjectId) -> &mut PendingRoundTrips {
+        self.pending.entry(surface).or_insert_with(|| PendingRoundTrips {
+            client,
+            configures: VecDeque::new(),
+            frame_callback: None,
+        })
+    }
+
+    fn client(&mut self, client: ClientId, process: impl FnOnce() -> Option<ClientProcessInfo>) -> &mut ClientLatency {
+        self.clients.entry(client).or_insert_with(|| ClientLatency {
+            process: process(),
+            configure: LatencyHistory::default(),
+            frame_callback: LatencyHistory::default(),
+        })
+    }
+
+    /// Record a configure sent to a surface
+    pub fn configure_sent(&mut self, client: ClientId, surface: ObjectId, serial: Serial, now: Instant) {
+        self.pending(client, surface).configures.push_back((serial, now));
+    }
+
+    /// Record an ack_configure; acking a serial also acks all earlier configures
+    pub fn configure_acked(
+        &mut self,
+        process: impl FnOnce() -> Option<ClientProcessInfo>,
+        surface: &ObjectId,
+        serial: Serial,
+        now: Instant,
+    ) {
+        let Some(pending) = self.pending.get_mut(surface) else {
+            return;
+        };
+        let mut acked = None;
+        while pending.configures.front().is_some_and(|&(sent_serial, _)| sent_serial <= serial) {
+            acked = pending.configures.pop_front();
+        }
+        let Some((_, sent)) = acked else {
+            return;
+        };
+        let client = pending.client.clone();
+        self.client(client, process).configure.record(now.saturating_duration_since(sent));
+    }
+
+    /// Record frame callbacks sent to a surface
+    pub fn frame_callbacks_sent(&mut self, client: ClientId, surface: ObjectId, now: Instant) {
+        self.pending(client, surface).frame_callback.get_or_insert(now);
+    }
+
+    /// Record a surface commit, completing a frame callback round trip
+    pub fn surface_committed(
+        &mut self,
+        process: impl FnOnce() -> Option<ClientProcessInfo>,
+        surface: &ObjectId,
+        now: Instant,
+    ) {
+        let Some(pending) = self.pending.get_mut(surface) else {
+            return;
+        };
+        let Some(sent) = pending.frame_callback.take() else {
+            return;
+        };
+        let client = pending.client.clone();
+        self.client(client, process).frame_callback.record(now.saturating_duration_since(sent));
+    }
+
+    /// Forget a destroyed surface
+    pub fn surface_destroyed(&mut self, surface: &ObjectId) {
+        self.pending.remove(surface);
+    }
+
+    /// Publish latencies for IPC
+    ///
+    /// Clients for which `is_alive` returns false have disconnected and are
+    /// dropped; `connection_id` numbers clients as the resource accounting does.
+    pub fn publish(&mut self, is_alive: impl Fn(&ClientId) -> bool, connection_id: impl Fn(&ClientId) -> Option<u64>) {
+        self.clients.retain(|client, _| is_alive(client));
+        self.pending.retain(|_, pending| is_alive(&pending.client));
+
+        let mut stats: Vec<ClientLatencyStats> = self
+            .clients
+            .iter()
+            .map(|(client, latency)| ClientLatencyStats {
+                id: connection_id(client),
+                process: latency.process.clone(),
+                configure: latency.configure.summary(),
+                frame_callback: latency.frame_callback.summary(),
+            })
+            .collect();
+        stats.sort_by_key(|stats| stats.id);
+        self.publisher.send_replace(stats);
+    }
+}
+
+impl Default for ProtocolLatencyTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
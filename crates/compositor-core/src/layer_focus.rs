@@ -0,0 +1,123 @@
+// Keyboard focus of layer surfaces
+//
+// Layer surfaces choose how they take keyboard focus through the
+// keyboard_interactivity property of wlr-layer-shell:
+//
+// * none - never focused; panels and widgets that are only clicked
+// * on_demand - focused like a window when clicked, e.g. a launcher
+// * exclusive - on the top and overlay layers, holds focus for as long as it
+//   is mapped, e.g. a password prompt; the topmost one wins. On the bottom and
+//   background layers it behaves like on_demand, as the protocol allows.
+//
+// When the last exclusive surface goes away or gives up exclusivity, focus
+// returns to the surface that had it before.
+
+use smithay::reexports::wayland_server::backend::ObjectId;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::Resource;
+use smithay::wayland::shell::wlr_layer::{KeyboardInteractivity, Layer};
+
+/// Keyboard interactivity of one layer surface
+#[derive(Debug, Clone)]
+struct LayerEntry {
+    surface: WlSurface,
+    layer: Layer,
+    interactivity: KeyboardInteractivity,
+}
+
+impl LayerEntry {
+    /// Whether this surface holds focus exclusively
+    fn is_exclusive(&self) -> bool {
+        self.interactivity == KeyboardInteractivity::Exclusive && matches!(self.layer, Layer::Top | Layer::Overlay)
+    }
+}
+
+/// Keyboard interactivity of all layer surfaces
+#[derive(Debug, Default)]
+pub struct LayerFocus {
+    /// Layer surfaces in the order they were mapped
+    entries: Vec<LayerEntry>,
+    /// Focus before an exclusive surface took it, restored when that goes away
+    restore: Option<WlSurface>,
+    /// Set when exclusivity changed and the focus needs re-evaluating
+    changed: bool,
+}
+
+impl LayerFocus {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track a new layer surface; it starts with no keyboard interactivity
+    pub fn insert(&mut self, surface: WlSurface, layer: Layer) {
+        self.remove(&surface.id());
+        self.entries.push(LayerEntry {
+            surface,
+            layer,
+            interactivity: KeyboardInteractivity::None,
+        });
+    }
+
+    /// Apply the committed layer and keyboard interactivity of a layer surface
+    pub fn update(&mut self, surface: &ObjectId, layer: Layer, interactivity: KeyboardInteractivity) {
+        let Some(entry) = self.entries.iter_mut().find(|entry| entry.surface.id() == *surface) else {
+            return;
+        };
+        let was_exclusive = entry.is_exclusive();
+        entry.layer = layer;
+        entry.interactivity = interactivity;
+        self.changed |= was_exclusive != entry.is_exclusive() || interactivity == KeyboardInteractivity::None;
+    }
+
+    /// Stop tracking a destroyed layer surface
+    pub fn remove(&mut self, surface: &ObjectId) {
+        let count = self.entries.len();
+        self.entries.retain(|entry| entry.surface.id() != *surface);
+        self.changed |= self.entries.len() != count;
+        if self.restore.as_ref().is_some_and(|restore| restore.id() == *surface) {
+            self.restore = None;
+        }
+    }
+
+    /// Whether a surface is a tracked layer surface
+    pub fn contains(&self, surface: &ObjectId) -> bool {
+        self.entries.iter().any(|entry| entry.surface.id() == *surface)
+    }
+
+    /// Topmost layer surface holding focus exclusively
+    pub fn exclusive(&self) -> Option<&WlSurface> {
+        let overlay = self.entries.iter().rev().find(|entry| entry.is_exclusive() && entry.layer == Layer::Overlay);
+        overlay
+            .or_else(|| self.entries.iter().rev().find(|entry| entry.is_exclusive()))
+            .map(|entry| &entry.surface)
+    }
+
+    /// Whether a surface may receive keyboard focus
+    ///
+    /// Surfaces other than layer surfaces always may.
+    pub fn accepts_focus(&self, surface: &ObjectId) -> bool {
+        self.entries
+            .iter()
+            .find(|entry| entry.surface.id() == *surface)
+            .is_none_or(|entry| entry.interactivity != KeyboardInteractivity::None)
+    }
+
+    /// Remember the focus an exclusive surface takes over, to restore it later
+    pub fn save_focus(&mut self, focus: Option<WlSurface>) {
+        let is_exclusive = |surface: &WlSurface| self.exclusive().is_some_and(|exclusive| exclusive == surface);
+        if focus.as_ref().is_none_or(|focus| !is_exclusive(focus)) {
+            self.restore = focus;
+        }
+    }
+
+    /// Focus to go back to now that no surface holds focus exclusively
+    pub fn take_restore(&mut self) -> Option<WlSurface> {
+        self.restore.take().filter(|surface| surface.is_alive())
+    }
+
+    /// Whether the focus needs re-evaluating, clearing the flag
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+}
@@ -0,0 +1,261 @@
+// Saved window layouts
+//
+// A layout records which applications are open, where their windows are,
+// which output and workspace they are on and the active workspace of every
+// output. Layouts are saved as RON files, one per name, and restored on IPC
+// request or when the session starts. Restoring places windows that are
+// already open right away; missing applications are relaunched through the
+// Exec line of their .desktop file and placed once their window shows up
+// with the same app ID.
+
+use compositor_utils::prelude::*;
+use ipc::protocol::{is_valid_layout_name, LayoutRequest};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::startup_feedback::launch_command;
+use tokio::sync::{mpsc, watch};
+
+const LAYOUT_EXTENSION: &str = "ron";
+
+/// Saved state of one window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowSnapshot {
+    pub app_id: String,
+    pub title: Option<String>,
+    pub output: String,
+    pub workspace: usize,
+    pub sticky: bool,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub maximized: bool,
+    pub fullscreen: bool,
+}
+
+/// Saved arrangement of windows and workspaces
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LayoutSnapshot {
+    pub name: String,
+    /// Active workspace by output name
+    pub active_workspaces: HashMap<String, usize>,
+    pub windows: Vec<WindowSnapshot>,
+}
+
+/// Stores layouts on disk and takes layout requests from IPC
+#[derive(Debug)]
+pub struct LayoutStore {
+    directory: PathBuf,
+    sender: mpsc::UnboundedSender<LayoutRequest>,
+    receiver: mpsc::UnboundedReceiver<LayoutRequest>,
+    names: watch::Sender<Vec<String>>,
+}
+
+impl LayoutStore {
+    /// Create a store for layouts in `directory`
+    pub fn new(directory: PathBuf) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let store = Self {
+            directory,
+            sender,
+            receiver,
+            names: watch::channel(Vec::new()).0,
+        };
+        store.refresh_names();
+        store
+    }
+
+    /// Store layouts in another directory
+    pub fn set_directory(&mut self, directory: PathBuf) {
+        self.directory = directory;
+        self.refresh_names();
+    }
+
+    /// Channel for IPC to submit layout requests
+    pub fn sender(&self) -> mpsc::UnboundedSender<LayoutRequest> {
+        self.sender.clone()
+    }
+
+    /// Channel for IPC to read the names of saved layouts
+    pub fn subscribe(&self) -> watch::Receiver<Vec<String>> {
+        self.names.subscribe()
+    }
+
+    /// Take all pending requests in submission order
+    pub fn drain(&mut self) -> Vec<LayoutRequest> {
+        let mut requests = Vec::new();
+        while let Ok(request) = self.receiver.try_recv() {
+            requests.push(request);
+        }
+        requests
+    }
+
+    /// Write a layout, replacing one with the same name
+    pub fn save(&self, layout: &LayoutSnapshot) -> Result<()> {
+        let content = ron::ser::to_string_pretty(layout, ron::ser::PrettyConfig::default())
+            .map_err(|e| CompositorError::runtime(format!("Failed to serialize layout: {}", e)))?;
+        std::fs::create_dir_all(&self.directory)
+            .map_err(|e| CompositorError::runtime(format!("Failed to create layout directory: {}", e)))?;
+        std::fs::write(self.path(&layout.name)?, content)
+            .map_err(|e| CompositorError::runtime(format!("Failed to write layout {}: {}", layout.name, e)))?;
+        info!("Saved layout {} with {} windows", layout.name, layout.windows.len());
+        self.refresh_names();
+        Ok(())
+    }
+
+    /// Read a saved layout
+    pub fn load(&self, name: &str) -> Result<LayoutSnapshot> {
+        let content = std::fs::read_to_string(self.path(name)?)
+            .map_err(|e| CompositorError::runtime(format!("Failed to read layout {}: {}", name, e)))?;
+        ron::from_str(&content)
+            .map_err(|e| CompositorError::runtime(format!("Failed to parse layout {}: {}", name, e)))
+    }
+
+    /// Remove a saved layout
+    pub fn delete(&self, name: &str) -> Result<()> {
+        std::fs::remove_file(self.path(name)?)
+            .map_err(|e| CompositorError::runtime(format!("Failed to delete layout {}: {}", name, e)))?;
+        info!("Deleted layout {}", name);
+        self.refresh_names();
+        Ok(())
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        if !is_valid_layout_name(name) {
+            return Err(CompositorError::runtime(format!("Invalid layout name: {:?}", name)));
+        }
+        Ok(self.directory.join(format!("{}.{}", name, LAYOUT_EXTENSION)))
+    }
+
+    /// Publish the names of the layouts on disk
+    fn refresh_names(&self) {
+        let mut names: Vec<String> = std::fs::read_dir(&self.directory)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == LAYOUT_EXTENSION))
+            .filter_map(|path| path.file_stem()?.to_str().map(str::to_string))
+            .collect();
+        names.sort();
+        self.names.send_replace(names);
+    }
+}
+
+/// Windows of a layout being restored that have not shown up yet
+#[derive(Debug, Default)]
+pub struct PendingRestore {
+    windows: Vec<WindowSnapshot>,
+}
+
+impl PendingRestore {
+    /// Create an empty restore
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait for windows to appear, replacing any previous restore
+    pub fn start(&mut self, windows: Vec<WindowSnapshot>) {
+        self.windows = windows;
+    }
+
+    /// Take the saved state for a new window of `app_id`, preferring the same title
+    pub fn take(&mut self, app_id: &str, title: Option<&str>) -> Option<WindowSnapshot> {
+        let index = self
+            .windows
+            .iter()
+            .position(|window| window.app_id == app_id && window.title.as_deref() == title)
+            .or_else(|| self.windows.iter().position(|window| window.app_id == app_id))?;
+        Some(self.windows.remove(index))
+    }
+
+    /// Whether windows are still expected
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// Windows still expected
+    pub fn windows(&self) -> &[WindowSnapshot] {
+        &self.windows
+    }
+}
+
+/// Launch an application by app ID through its .desktop file
+pub fn launch_app(app_id: &str, wayland_display: Option<&str>, activation_token: Option<&str>) -> Result<()> {
+    let command = desktop_exec(app_id)
+        .ok_or_else(|| CompositorError::runtime(format!("No desktop entry for {}", app_id)))?;
+    if command.is_empty() {
+        return Err(CompositorError::runtime(format!("Empty Exec line for {}", app_id)));
+    }
+    let pid = launch_command(&command, wayland_display, activation_token)?;
+    info!("Relaunched {} (pid {})", app_id, pid);
+    Ok(())
+}
+
+/// Command line from the Exec key of the .desktop file for `app_id`
+fn desktop_exec(app_id: &str) -> Option<Vec<String>> {
+    let file_name = format!("{}.desktop", app_id);
+    application_dirs()
+        .iter()
+        .map(|dir| dir.join(&file_name))
+        .find_map(|path| parse_desktop_exec(&std::fs::read_to_string(path).ok()?))
+}
+
+/// XDG application directories, most specific first
+fn application_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    data_home
+        .into_iter()
+        .chain(data_dirs.split(':').map(PathBuf::from))
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// Exec command of the [Desktop Entry] group, without field codes
+fn parse_desktop_exec(content: &str) -> Option<Vec<String>> {
+    let mut in_entry = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+        } else if let Some(exec) = line.strip_prefix("Exec=").filter(|_| in_entry) {
+            let args: Vec<String> = split_exec(exec)
+                .into_iter()
+                .filter(|arg| !(arg.len() == 2 && arg.starts_with('%')))
+                .map(|arg| arg.replace("%%", "%"))
+                .collect();
+            return (!args.is_empty()).then_some(args);
+        }
+    }
+    None
+}
+
+/// Split an Exec value into arguments, honouring double quotes and escapes
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => current.extend(chars.next()),
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        args.push(current);
+    }
+    args
+}
@@ -7,33 +7,135 @@
 // - Integration with the Vulkan renderer
 
 use compositor_utils::prelude::*;
-use vulkan_renderer::VulkanRenderer;
+use vulkan_renderer::{BlurQuality, PresentMode, UiAntialiasing, VulkanRenderer};
+use std::collections::HashMap;
 use std::sync::{atomic::AtomicBool, Arc};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, watch};
+use ipc::protocol::{BufferFormatUsage, ClientLatencyStats, ClientResourceUsage, DisplayTransform, FocusModeOverride, GpuMemoryStats, LaunchRequest, LayoutRequest, OutputInfo, PresentMode as IpcPresentMode, ProtocolHandler, ThemePreviewRequest, WindowEvent, WindowOperation, WindowSummary};
+use ipc::auth::{IpcPermissions, PermissionTier};
+use ipc::dbus::DBusManager;
+use ipc::socket::{JsonControlServer, SocketServer};
+use compositor_utils::frame_stats::FrameStatistics;
+use compositor_utils::params::ParameterRegistry;
+use ui_framework::effects::{self, EffectParameters};
+use accessibility::AtspiBridge;
+use output_config::SensorProxyBridge;
+use frame_callbacks::FramePresented;
+use render_wakeups::{FrameInFlight, RenderWakeups};
+use suspend::{SuspendMonitor, SuspendState};
+use render_state::{FrameReadback, OutputDamage, RenderOutput, RenderState};
+use smithay::reexports::calloop::{ping::make_ping, LoopSignal};
 
 pub mod wayland;
 pub mod window;
 pub mod input;
 pub mod output;
 pub mod surface;
+pub mod surface_manager;
 pub mod backend;
 pub mod session;
+pub mod libinput;
+pub mod frame_scheduler;
+pub mod osk;
+pub mod zoom;
+pub mod hot_corners;
+pub mod focus_mode;
+pub mod blur;
+pub mod show_desktop;
+pub mod workspace;
+pub mod automation;
+pub mod accessibility;
+pub mod click_assist;
+pub mod benchmark;
+pub mod responsiveness;
+pub mod kill_mode;
+pub mod credentials;
+pub mod resource_usage;
+pub mod latency;
+pub mod output_config;
+pub mod keyboard_grab;
+pub mod cursor_visibility;
+pub mod pointer_barriers;
+pub mod move_grab;
+pub mod resize_grab;
+pub mod layout_snapshot;
+pub mod kiosk;
+pub mod wayland_socket;
+pub mod doctor;
+pub mod stacking;
+pub mod layer_focus;
+pub mod buffer_formats;
+pub mod frame_rate_cap;
+pub mod app_scale;
+pub mod window_transaction;
+pub mod workspace_theme;
+pub mod window_identity;
+pub mod startup_feedback;
+pub mod color_picker;
+pub mod screenshot;
+pub mod scheduling;
+pub mod placement;
+pub mod animation_rate;
+pub mod frame_callbacks;
+pub mod render_wakeups;
+pub mod geometry;
+pub mod output_power;
+pub mod lid;
+pub mod suspend;
+pub mod surface_style;
+pub mod urgency;
+pub mod screencopy;
+pub mod theme_preview;
+pub mod render_state;
+pub mod dialog;
+pub mod region_select;
+pub mod quick_settings;
 
 // Re-export core types
 pub use wayland::WaylandServer;
 pub use session::{SessionManager, SessionState};
 pub use backend::Backend;
+pub use frame_scheduler::{FrameScheduler, OutputFrameScheduler, OutputId};
+
+/// Upper bound on how long the render thread sleeps, so backend events keep
+/// being polled until they arrive on file descriptors the loop watches
+const BACKEND_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 /// Main compositor instance
 pub struct Compositor {
     wayland_server: WaylandServer,
     renderer: VulkanRenderer,
     backend: Backend,
+    frame_scheduler: FrameScheduler,
+    /// Internal render scale per output name; outputs not listed render natively
+    render_scale: watch::Sender<HashMap<String, f32>>,
+    /// Theme the output background colors come from
+    theme: watch::Sender<config::ThemeConfig>,
+    /// Requested swapchain present mode
+    present_mode: watch::Sender<IpcPresentMode>,
+    /// GPU memory usage published for IPC metrics
+    gpu_memory: watch::Sender<GpuMemoryStats>,
+    /// Scheduling priority the render and input threads ask for when running
+    scheduling: config::SchedulingConfig,
+    /// Effect values tunable at runtime over IPC, read every frame
+    parameters: Arc<ParameterRegistry>,
+    /// Reloaded configurations, re-applied while running
+    config_changes: Option<broadcast::Receiver<config::CompositorConfig>>,
+    /// Channel asking the configuration to be reloaded, e.g. over IPC
+    config_reloads: Option<mpsc::UnboundedSender<()>>,
+    /// Input injection and control socket permissions, read when the
+    /// control socket starts
+    automation: config::AutomationConfig,
     running: Arc<AtomicBool>,
 }
 
 impl Compositor {
-    /// Create a new compositor instance
-    pub async fn new() -> Result<Self> {
+    /// Create a new compositor instance, exposing the configured protocols
+    /// on the configured sockets
+    ///
+    /// The other sections are applied with `apply_config`.
+    pub async fn new(config: &config::CompositorConfig) -> Result<Self> {
         info!("Initializing custom compositor");
         
         // Initialize renderer first
@@ -48,71 +150,554 @@ impl Compositor {
             .map_err(|e| CompositorError::init(format!("Failed to initialize backend: {}", e)))?;
         
         // Initialize Wayland server
-        let mut wayland_server = WaylandServer::new()
+        let mut wayland_server = WaylandServer::with_protocols(config.protocols.clone())
             .map_err(|e| CompositorError::init(format!("Failed to initialize Wayland server: {}", e)))?;
         
+        // Deliver input from the session's devices to clients
+        if let Some(access) = backend.device_access() {
+            wayland_server.add_libinput(access)
+                .map_err(|e| CompositorError::init(format!("Failed to initialize input devices: {}", e)))?;
+        }
+        
+        // Let clients allocate buffers in the formats and modifiers the GPU samples best
+        wayland_server.state.set_dmabuf_formats(&renderer.dmabuf_formats());
+        
         // Initialize wl_drm protocol support via EGL backend
         wayland_server.initialize_wl_drm()
             .map_err(|e| CompositorError::init(format!("Failed to initialize wl_drm protocol: {}", e)))?;
         
         // Start listening for client connections
-        wayland_server.start_listening()
+        wayland_server.start_listening(&config.wayland.sockets)
             .map_err(|e| CompositorError::init(format!("Failed to start Wayland server: {}", e)))?;
         
+        let parameters = Arc::new(ParameterRegistry::new());
+        EffectParameters::register(&parameters, EffectParameters::default());
+        
         info!("Compositor initialized successfully");
         
         Ok(Self {
             wayland_server,
             renderer,
             backend,
+            frame_scheduler: FrameScheduler::new(),
+            render_scale: watch::channel(HashMap::new()).0,
+            theme: watch::channel(config::ThemeConfig::default()).0,
+            present_mode: watch::channel(IpcPresentMode::default()).0,
+            gpu_memory: watch::channel(GpuMemoryStats::default()).0,
+            scheduling: config::SchedulingConfig::default(),
+            parameters,
+            config_changes: None,
+            config_reloads: None,
+            automation: config::AutomationConfig::default(),
             running: Arc::new(AtomicBool::new(true)),
         })
     }
     
+    /// Apply every section of the loaded configuration before running
+    ///
+    /// Restores the configured login layout, so call it once at startup.
+    pub fn apply_config(&mut self, config: &config::CompositorConfig) {
+        self.wayland_server.state.apply_config(config);
+        apply_render_config(&mut self.renderer, &self.parameters, config);
+        publish_render_config(&self.render_scale, &self.theme, &self.present_mode, config);
+        self.scheduling = config.performance.scheduling.clone();
+        self.automation = config.automation.clone();
+        
+        if let Some(name) = &config.layouts.restore_at_login {
+            if let Err(e) = self.restore_layout(name) {
+                warn!("Failed to restore layout {} at login: {}", name, e);
+            }
+        }
+    }
+    
+    /// Re-apply every section of each configuration received while
+    /// running, e.g. from `ConfigManager::subscribe_to_changes`
+    ///
+    /// IPC reload requests are sent to `reloads`.
+    pub fn follow_config(&mut self, changes: broadcast::Receiver<config::CompositorConfig>, reloads: mpsc::UnboundedSender<()>) {
+        self.config_changes = Some(changes);
+        self.config_reloads = Some(reloads);
+    }
+    
+    /// Channel for IPC to change per-output render scale
+    pub fn render_scale_sender(&self) -> watch::Sender<HashMap<String, f32>> {
+        self.render_scale.clone()
+    }
+    
+    /// Channel for IPC to turn focus mode on or off, or back to its schedule
+    pub fn focus_mode_sender(&self) -> watch::Sender<FocusModeOverride> {
+        self.wayland_server.state.focus_mode.override_sender()
+    }
+    
+    /// Channel for IPC to change the present mode at runtime
+    pub fn present_mode_sender(&self) -> watch::Sender<IpcPresentMode> {
+        self.present_mode.clone()
+    }
+    
+    /// Channel for IPC to subscribe to window title and app ID changes
+    pub fn window_events_sender(&self) -> broadcast::Sender<WindowEvent> {
+        self.wayland_server.state.window_identities.sender()
+    }
+    
+    /// Channel for IPC to change output scales at runtime
+    pub fn output_scale_sender(&self) -> watch::Sender<HashMap<String, f64>> {
+        self.wayland_server.state.output_scale_requests.sender()
+    }
+    
+    /// Channel for IPC to rotate and flip outputs at runtime
+    pub fn output_transform_sender(&self) -> watch::Sender<HashMap<String, DisplayTransform>> {
+        self.wayland_server.state.output_transform_requests.sender()
+    }
+    
+    /// Frame statistics recorder for IPC queries
+    pub fn frame_stats(&self) -> Arc<FrameStatistics> {
+        self.wayland_server.state.frame_stats.clone()
+    }
+    
+    /// App IDs of launched apps whose dock icons bounce until they show a window
+    pub fn bouncing_dock_icons_receiver(&self) -> watch::Receiver<Vec<String>> {
+        self.wayland_server.state.startup_feedback.bouncing_receiver()
+    }
+    
+    /// App IDs whose dock entries are highlighted while a window of theirs
+    /// asks for attention
+    pub fn urgent_dock_entries_receiver(&self) -> watch::Receiver<Vec<String>> {
+        self.wayland_server.state.urgent_windows.urgent_apps_receiver()
+    }
+    
+    /// Restore a saved window layout, e.g. the configured login layout at startup
+    pub fn restore_layout(&mut self, name: &str) -> Result<()> {
+        self.wayland_server.state.restore_layout(name)
+    }
+    
+    /// Channel for IPC to save, restore and delete window layouts
+    pub fn layout_request_sender(&self) -> mpsc::UnboundedSender<LayoutRequest> {
+        self.wayland_server.state.layouts.sender()
+    }
+    
+    /// Channel for IPC to submit atomic window batches
+    pub fn window_batch_sender(&self) -> mpsc::UnboundedSender<Vec<WindowOperation>> {
+        self.wayland_server.state.window_batches.sender()
+    }
+    
+    /// Channel for IPC and D-Bus to list the mapped windows
+    pub fn windows_receiver(&self) -> watch::Receiver<Vec<WindowSummary>> {
+        self.wayland_server.state.window_identities.windows_receiver()
+    }
+    
+    /// Channel for IPC and D-Bus to list the connected outputs
+    pub fn outputs_receiver(&self) -> watch::Receiver<Vec<OutputInfo>> {
+        self.wayland_server.state.output_list.subscribe()
+    }
+    
+    /// Channel for IPC to open and close theme previews
+    pub fn theme_preview_sender(&self) -> mpsc::UnboundedSender<ThemePreviewRequest> {
+        self.wayland_server.state.theme_preview.sender()
+    }
+    
+    /// Channel for IPC to launch applications with activation tokens
+    pub fn launch_request_sender(&self) -> mpsc::UnboundedSender<LaunchRequest> {
+        self.wayland_server.state.launch_requests.sender()
+    }
+    
+    /// Channel for IPC to read the names of saved window layouts
+    pub fn saved_layouts_receiver(&self) -> watch::Receiver<Vec<String>> {
+        self.wayland_server.state.layouts.subscribe()
+    }
+    
+    /// Protocol handler answering IPC requests with the compositor's channels
+    pub fn protocol_handler(&self) -> ProtocolHandler {
+        let mut handler = ProtocolHandler::new()
+            .with_automation(self.wayland_server.state.automation.sender())
+            .with_flat_accel_toggle(self.wayland_server.state.libinput_devices.flat_accel_sender())
+            .with_region_screenshots(self.wayland_server.state.region_selection.sender())
+            .with_input_injection(self.automation.allow_input_injection)
+            .with_permissions(ipc_permissions(&self.automation.permissions))
+            .with_parameters(self.parameters.clone())
+            .with_focus_mode(self.focus_mode_sender())
+            .with_render_scale(self.render_scale_sender())
+            .with_output_scale(self.output_scale_sender())
+            .with_output_transform(self.output_transform_sender())
+            .with_present_mode(self.present_mode_sender())
+            .with_window_events(self.window_events_sender())
+            .with_windows(self.windows_receiver())
+            .with_outputs(self.outputs_receiver())
+            .with_theme_previews(self.theme_preview_sender())
+            .with_gpu_memory(self.gpu_memory_receiver())
+            .with_frame_stats(self.frame_stats())
+            .with_client_usage(self.client_usage_receiver())
+            .with_client_latency(self.client_latency_receiver())
+            .with_buffer_formats(self.buffer_formats_receiver())
+            .with_layouts(self.layout_request_sender(), self.saved_layouts_receiver())
+            .with_window_batches(self.window_batch_sender())
+            .with_launches(self.launch_request_sender());
+        if let Some(reloads) = &self.config_reloads {
+            handler = handler.with_config_reload(reloads.clone());
+        }
+        handler
+    }
+    
+    /// Channel for IPC to read per-client resource usage
+    pub fn client_usage_receiver(&self) -> watch::Receiver<Vec<ClientResourceUsage>> {
+        self.wayland_server.state.client_usage.subscribe()
+    }
+    
+    /// Channel for IPC to read per-client protocol latencies
+    pub fn client_latency_receiver(&self) -> watch::Receiver<Vec<ClientLatencyStats>> {
+        self.wayland_server.state.protocol_latency.subscribe()
+    }
+    
+    /// Channel for IPC to read buffer format statistics
+    pub fn buffer_formats_receiver(&self) -> watch::Receiver<Vec<BufferFormatUsage>> {
+        self.wayland_server.state.buffer_formats.subscribe()
+    }
+    
+    /// Channel for IPC to read GPU memory usage
+    pub fn gpu_memory_receiver(&self) -> watch::Receiver<GpuMemoryStats> {
+        self.gpu_memory.subscribe()
+    }
+    
     /// Get the Wayland socket name for client connections
     pub fn wayland_socket_name(&self) -> Option<&str> {
         self.wayland_server.socket_name()
     }
     
+    /// Run a scripted benchmark scenario instead of serving clients
+    pub fn run_benchmark(&mut self, scenario: &benchmark::BenchmarkScenario) -> Result<benchmark::BenchmarkReport> {
+        benchmark::run_benchmark(&mut self.renderer, scenario)
+    }
+    
     /// Start the compositor main loop
     pub async fn run(self) -> Result<()> {
         info!("Starting compositor main loop");
         
+        let protocol_handler = Arc::new(self.protocol_handler());
+        
         // Split self to move parts into different tasks
-        let Self { wayland_server, backend, renderer, running } = self;
+        let Self { mut wayland_server, backend, renderer, frame_scheduler, render_scale: render_scale_sender, theme: theme_sender, present_mode: present_mode_sender, gpu_memory, scheduling, parameters, config_changes, running, .. } = self;
+        let render_scale = render_scale_sender.subscribe();
+        let theme = theme_sender.subscribe();
+        let present_mode = present_mode_sender.subscribe();
+        // Both threads apply their own sections of a reloaded configuration
+        let mut render_config_changes = config_changes.as_ref().map(broadcast::Receiver::resubscribe);
+        wayland_server.state.config_changes = config_changes;
+        let frame_stats = wayland_server.state.frame_stats.clone();
+        let frame_presented = wayland_server.state.frame_callbacks.sender();
+        let wayland_loop = wayland_server.loop_signal();
+        let mut render_state = wayland_server.state.render_state.subscribe();
+        let readbacks = wayland_server.state.render_state.readback_sender();
+        let surfaces = wayland_server.state.surfaces.clone();
         
-        // Spawn background tasks for backend and renderer
+        // Wakes the render thread, e.g. to shut down or draw new surface state
+        let (render_waker, render_wake) = make_ping()
+            .map_err(|e| CompositorError::runtime(format!("Failed to create render thread wakeup: {}", e)))?;
+        wayland_server.state.render_state.set_waker(render_waker.clone());
+        
+        // Run backend and renderer on their own thread, so its scheduling
+        // priority does not carry over to other tasks of the runtime
         let running_clone = running.clone();
-        let compositor_handle = tokio::spawn(async move {
-            let mut backend = backend;
-            let _renderer = renderer; // Keep renderer for future use
-            
-            while running_clone.load(std::sync::atomic::Ordering::Relaxed) {
-                // Process backend events (input, output changes, etc.)
-                if let Err(e) = backend.process_events().await {
-                    error!("Backend error: {}", e);
-                    break;
-                }
+        let runtime = tokio::runtime::Handle::current();
+        let render_scheduling = scheduling.clone();
+        let compositor_thread = std::thread::Builder::new().name("render".to_string()).spawn(move || {
+            scheduling::raise_thread_priority(&render_scheduling, "render");
+            scheduling::pin_thread(&render_scheduling.render_affinity, "render");
+            // TODO: Pin texture uploads to upload_affinity once they move
+            // off the render thread
+            runtime.block_on(async move {
+                let mut backend = backend;
+                let mut frame_scheduler = frame_scheduler;
+                let mut renderer = renderer;
+                let mut applied_render_scale = 1.0;
+                let mut applied_background = None;
+                let mut applied_present_mode = PresentMode::default();
+                let mut applied_effects = None;
+                let mut wakeups = match RenderWakeups::new(render_wake) {
+                    Ok(wakeups) => wakeups,
+                    Err(e) => {
+                        error!("{}", e);
+                        return;
+                    }
+                };
+                let mut finished = Vec::new();
+                let mut suspend = SuspendMonitor::new();
+                let mut outputs: Vec<RenderOutput> = Vec::new();
+                // Region requested from a frame whose pixels were not taken yet
+                let mut reading_back = None;
+                let mut damage = OutputDamage::default();
                 
-                // Render frame (placeholder for now)
-                // TODO: Implement proper frame rendering
-                
-                // Yield to other tasks
-                tokio::time::sleep(std::time::Duration::from_millis(16)).await; // ~60 FPS
+                while running_clone.load(std::sync::atomic::Ordering::Relaxed) {
+                    // Process backend events (input, output changes, vblanks, etc.)
+                    if let Err(e) = backend.process_events().await {
+                        error!("Backend error: {}", e);
+                        break;
+                    }
+                    
+                    // Draw nothing while the session is away, and recover the GPU
+                    // state once it is back or the system woke from sleep
+                    match suspend.check(!backend.is_paused()) {
+                        SuspendState::Running => {}
+                        SuspendState::Paused => {
+                            finished.clear();
+                            if let Err(e) = wakeups.set_refresh_deadline(None) {
+                                error!("{}", e);
+                                break;
+                            }
+                            if let Err(e) = wakeups.wait(Some(BACKEND_POLL_INTERVAL)) {
+                                error!("{}", e);
+                                break;
+                            }
+                            continue;
+                        }
+                        SuspendState::Resumed => {
+                            info!("Resumed, recovering GPU state");
+                            // Frames in flight before the sleep never complete
+                            finished.clear();
+                            if let Err(e) = renderer.resume() {
+                                error!("Failed to recover the renderer after resume: {}", e);
+                                break;
+                            }
+                            frame_scheduler.reset_all(Instant::now());
+                            damage.damage_all();
+                        }
+                    }
+                    
+                    // Apply the renderer settings of a reloaded configuration
+                    if let Some(config) = render_config_changes.as_mut().and_then(latest_config) {
+                        apply_render_config(&mut renderer, &parameters, &config);
+                        publish_render_config(&render_scale_sender, &theme_sender, &present_mode_sender, &config);
+                        frame_scheduler.schedule_redraw_all();
+                        damage.damage_all();
+                    }
+                    
+                    // Recreate the swapchain when a different present mode was requested
+                    let requested_present_mode = match *present_mode.borrow() {
+                        IpcPresentMode::Fifo => PresentMode::Fifo,
+                        IpcPresentMode::Mailbox => PresentMode::Mailbox,
+                        IpcPresentMode::Immediate => PresentMode::Immediate,
+                    };
+                    if requested_present_mode != applied_present_mode {
+                        if let Err(e) = renderer.set_present_mode(requested_present_mode) {
+                            error!("Failed to set present mode {:?}: {}", requested_present_mode, e);
+                        }
+                        applied_present_mode = requested_present_mode;
+                    }
+                    
+                    // Draw with the effect values tuned over IPC since the last frame
+                    let effects = EffectParameters::from_registry(&parameters);
+                    if applied_effects != Some(effects) {
+                        renderer.set_corner_radius(effects.corner_radius);
+                        renderer.set_default_blur_radius(effects.blur_radius);
+                        // Each class keeps its shadow strength relative to the theme's
+                        let theme_shadow = theme.borrow().shadow_intensity;
+                        renderer.set_shadow_scale(if theme_shadow > 0.0 { effects.shadow_intensity / theme_shadow } else { 1.0 });
+                        applied_effects = Some(effects);
+                        frame_scheduler.schedule_redraw_all();
+                        damage.damage_all();
+                    }
+                    
+                    // Drive the outputs the Wayland loop published, and draw its
+                    // stacking and styles on every output
+                    if render_state.has_changed().unwrap_or(false) {
+                        let mut state = render_state.borrow_and_update().clone();
+                        outputs = std::mem::take(&mut state.outputs);
+                        sync_outputs(&mut frame_scheduler, &outputs);
+                        apply_render_state(&mut renderer, state);
+                        frame_scheduler.schedule_redraw_all();
+                        damage.damage_all();
+                    }
+                    
+                    // Upload the content surfaces committed since the last frame once,
+                    // redrawing the outputs that show them
+                    let flushed = surfaces
+                        .lock()
+                        .ok()
+                        .filter(|surfaces| surfaces.has_pending_uploads())
+                        .map(|mut surfaces| surfaces.flush_pending_uploads(&mut renderer));
+                    if let Some(mut flushed) = flushed {
+                        for (surface_id, e) in &flushed.failed {
+                            warn!("Failed to upload surface {}: {}", surface_id, e);
+                        }
+                        damage.add(flushed.damage.take(), &outputs);
+                        let showing = outputs
+                            .iter()
+                            .filter(|output| flushed.uploaded.iter().any(|surface_id| !output.offscreen_surfaces.contains(surface_id)));
+                        for output in showing {
+                            if let Some(scheduler) = frame_scheduler.find_output(&output.name).and_then(|output_id| frame_scheduler.output_mut(output_id)) {
+                                scheduler.schedule_redraw();
+                            }
+                        }
+                    }
+                    
+                    // Report the frames the GPU finished since the last wakeup
+                    for frame in finished.drain(..) {
+                        frame_finished(&mut renderer, &mut frame_scheduler, &frame_stats, &frame_presented, &wayland_loop, frame);
+                    }
+                    
+                    // Render every output whose refresh cycle is due
+                    let now = Instant::now();
+                    for output_id in frame_scheduler.due_outputs(now) {
+                        let scale = frame_scheduler
+                            .output(output_id)
+                            .and_then(|output| render_scale.borrow().get(output.name()).copied())
+                            .unwrap_or(1.0);
+                        if scale != applied_render_scale {
+                            if let Err(e) = renderer.set_render_scale(scale) {
+                                error!("Failed to set render scale {}: {}", scale, e);
+                            }
+                            applied_render_scale = scale;
+                            damage.damage_all();
+                        }
+                        let background = {
+                            let theme = theme.borrow();
+                            frame_scheduler
+                                .output(output_id)
+                                .map_or_else(|| theme.color(config::ColorToken::Background), |output| theme.background_color_for(output.name()))
+                        };
+                        if applied_background != Some(background) {
+                            renderer.set_background_color(background);
+                            applied_background = Some(background);
+                            damage.damage_all();
+                        }
+                        
+                        // Limit surface uploads while frames take longer than a refresh cycle
+                        renderer.set_frame_budget(frame_scheduler.output(output_id).map(|output| output.refresh_interval()));
+                        
+                        // Draw the surfaces this output shows
+                        let output = frame_scheduler
+                            .output(output_id)
+                            .and_then(|scheduler| outputs.iter().find(|output| output.name == scheduler.name()));
+                        renderer.set_offscreen_surfaces(output.map(|output| output.offscreen_surfaces.clone()).unwrap_or_default());
+                        if let Some(output) = output {
+                            renderer.set_output_region(output.geometry);
+                        }
+                        
+                        // Present only what changed since the output's last frame, where known
+                        match output.and_then(|output| damage.take(&output.name)) {
+                            Some(regions) => regions.into_iter().for_each(|rect| renderer.add_region_damage(rect)),
+                            None => renderer.damage_all(),
+                        }
+                        // TODO: Render compositor content
+                        // - Draw windows with app_scales overrides through renderer.surface_view_for_scale
+                        //   at the output scale over their buffer scale
+                        // - Apply effects (glassmorphism, etc.)
+                        // Read back the region waiting for this output's frame
+                        if let Some(region) = output.and_then(|output| output.readback) {
+                            match renderer.request_readback(region) {
+                                Ok(()) => reading_back = Some(region),
+                                Err(e) => {
+                                    debug!("Cannot read the frame back: {}", e);
+                                    let _ = readbacks.send(FrameReadback::Failed(region));
+                                    wayland_loop.wakeup();
+                                }
+                            }
+                        }
+                        if let Err(e) = renderer.begin_frame().and_then(|_| renderer.end_frame()) {
+                            debug!("Cannot draw output {}: {}", output.map_or("", |output| output.name.as_str()), e);
+                        }
+                        frame_scheduler.frame_submitted(output_id, now);
+                        
+                        // Sleep until the GPU finishes the frame where its fence can be
+                        // watched; otherwise report it right away
+                        let frame = FrameInFlight { output: output_id, submitted: now };
+                        let watched = match renderer.take_frame_completion() {
+                            Some(completion) => wakeups.watch_frame(frame, completion).map_err(|e| warn!("{}", e)).is_ok(),
+                            None => false,
+                        };
+                        if !watched {
+                            frame_finished(&mut renderer, &mut frame_scheduler, &frame_stats, &frame_presented, &wayland_loop, frame);
+                        }
+                    }
+                    
+                    // Hand pixels read back from finished frames to the Wayland loop
+                    let readback = match renderer.take_readback() {
+                        Ok(pixels) => pixels.map(FrameReadback::Read),
+                        Err(e) => {
+                            warn!("Failed to read the frame back: {}", e);
+                            reading_back.map(FrameReadback::Failed)
+                        }
+                    };
+                    if let Some(readback) = readback {
+                        reading_back = None;
+                        let _ = readbacks.send(readback);
+                        wayland_loop.wakeup();
+                    }
+                    
+                    // Publish GPU memory usage for metrics
+                    if let Some(usage) = renderer.memory_usage() {
+                        gpu_memory.send_if_modified(|stats| {
+                            let updated = GpuMemoryStats {
+                                used: usage.device_local_usage(),
+                                budget: usage.device_local_budget(),
+                                from_driver: usage.from_driver,
+                            };
+                            std::mem::replace(stats, updated) != updated
+                        });
+                    }
+                    
+                    // Give back GPU memory while nothing is being drawn
+                    if frame_scheduler.next_deadline().is_none() {
+                        if let Err(e) = renderer.maintain_if_idle(now) {
+                            warn!("Idle GPU maintenance failed: {}", e);
+                        }
+                    }
+                    
+                    // Sleep until the next output wants a frame, a frame finishes or
+                    // the thread is woken to shut down
+                    if let Err(e) = wakeups.set_refresh_deadline(frame_scheduler.next_deadline()) {
+                        error!("{}", e);
+                        break;
+                    }
+                    match wakeups.wait(Some(BACKEND_POLL_INTERVAL)) {
+                        Ok(frames) => finished = frames,
+                        Err(e) => {
+                            error!("{}", e);
+                            break;
+                        }
+                    }
+                }
+                info!("Background compositor tasks completed");
+            })
+        })?;
+        
+        // Forward accessibility events to screen readers
+        let atspi_handle = tokio::spawn(AtspiBridge::new(wayland_server.state.accessibility.tree()).run());
+        
+        // Follow the accelerometer on convertibles
+        let sensor_proxy_handle = tokio::spawn(SensorProxyBridge::new(wayland_server.state.auto_rotation.sender()).run());
+        
+        // Connect the quick settings panel to NetworkManager, BlueZ, PipeWire and the backlight
+        let quick_settings_handle = tokio::spawn(wayland_server.state.quick_settings.bridge().run());
+        
+        // Answer requests on the control socket, e.g. from the app bar and scripts
+        let control_socket_handle = tokio::spawn(serve_control_socket(protocol_handler.clone()));
+        
+        // Serve the same requests to panels on the session bus
+        let dbus_handle = tokio::spawn(async move {
+            if let Err(e) = DBusManager::new(protocol_handler).run().await {
+                warn!("D-Bus service is not available: {}", e);
             }
-            info!("Background compositor tasks completed");
         });
         
         // Run Wayland server in current thread (since EventLoop is not Send)
-        // This will block until the server shuts down
+        // This will block until the server shuts down; it handles input
+        scheduling::raise_thread_priority(&scheduling, "input");
+        scheduling::pin_thread(&scheduling.event_loop_affinity, "input");
         let wayland_result = wayland_server.run_async().await;
         
         // Signal background tasks to stop
         running.store(false, std::sync::atomic::Ordering::Relaxed);
+        render_waker.ping();
+        atspi_handle.abort();
+        sensor_proxy_handle.abort();
+        quick_settings_handle.abort();
+        control_socket_handle.abort();
+        dbus_handle.abort();
         
         // Wait for background tasks to complete
-        if let Err(e) = compositor_handle.await {
-            error!("Error waiting for compositor tasks: {}", e);
+        match tokio::task::spawn_blocking(move || compositor_thread.join()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(_)) => error!("Render thread panicked"),
+            Err(e) => error!("Error waiting for compositor tasks: {}", e),
         }
         
         // Check if wayland server had any errors
@@ -151,23 +736,6 @@ impl Compositor {
         Ok(())
     }
     
-    /// Render a frame
-    #[allow(dead_code)]
-    async fn render_frame(&mut self) -> Result<()> {
-        // Begin frame
-        self.renderer.begin_frame()?;
-        
-        // TODO: Render compositor content
-        // - Render windows
-        // - Render UI elements
-        // - Apply effects (glassmorphism, etc.)
-        
-        // End frame and present
-        self.renderer.end_frame()?;
-        
-        Ok(())
-    }
-    
     /// Shutdown the compositor
     pub async fn shutdown(&mut self) -> Result<()> {
         info!("Shutting down compositor");
@@ -181,3 +749,205 @@ impl Compositor {
         Ok(())
     }
 }
+
+/// Give each published output its own refresh loop, follow changes of
+/// their geometry and refresh rate, and drop those that were removed
+fn sync_outputs(frame_scheduler: &mut FrameScheduler, outputs: &[RenderOutput]) {
+    for output_id in frame_scheduler.output_ids() {
+        let removed = frame_scheduler
+            .output(output_id)
+            .is_some_and(|scheduler| !outputs.iter().any(|output| output.name == scheduler.name()));
+        if removed {
+            frame_scheduler.remove_output(output_id);
+        }
+    }
+    for output in outputs {
+        let Some(output_id) = frame_scheduler.find_output(&output.name) else {
+            // Frames are paced by present timing or the refresh timer; the
+            // backend reports no page flips
+            frame_scheduler.add_output(OutputFrameScheduler::new(output.name.clone(), output.geometry, output.refresh_mhz, false));
+            continue;
+        };
+        if let Some(scheduler) = frame_scheduler.output_mut(output_id) {
+            if scheduler.geometry() != output.geometry {
+                scheduler.set_geometry(output.geometry);
+            }
+            scheduler.set_refresh_rate(output.refresh_mhz);
+        }
+    }
+}
+
+/// Control socket permission tiers of the configuration, for a compositor
+/// running as the current user
+fn ipc_permissions(config: &config::IpcPermissionsConfig) -> IpcPermissions {
+    let tier = |tier: config::IpcTier| match tier {
+        config::IpcTier::None => PermissionTier::None,
+        config::IpcTier::ReadOnly => PermissionTier::ReadOnly,
+        config::IpcTier::Control => PermissionTier::Control,
+        config::IpcTier::Privileged => PermissionTier::Privileged,
+    };
+    let permissions = IpcPermissions::for_current_user()
+        .with_owner(tier(config.owner))
+        .with_others(tier(config.others));
+    let permissions = config
+        .uids
+        .iter()
+        .fold(permissions, |permissions, (&uid, &uid_tier)| permissions.with_uid(uid, tier(uid_tier)));
+    config
+        .gids
+        .iter()
+        .fold(permissions, |permissions, (&gid, &gid_tier)| permissions.with_gid(gid, tier(gid_tier)))
+}
+
+/// Serve IPC requests on the control socket until accepting connections fails
+async fn serve_control_socket(handler: Arc<ProtocolHandler>) {
+    let Some(path) = ipc::socket::default_socket_path() else {
+        warn!("XDG_RUNTIME_DIR is not set, not starting the control socket");
+        return;
+    };
+    let mut server = match SocketServer::new(&path) {
+        Ok(server) => server,
+        Err(e) => {
+            warn!("Failed to create control socket {}: {}", path.display(), e);
+            return;
+        }
+    };
+    if let Err(e) = server.start().await {
+        warn!("Failed to start control socket {}: {}", path.display(), e);
+        return;
+    }
+    if let Err(e) = JsonControlServer::new(handler).run(server).await {
+        warn!("Control socket stopped: {}", e);
+    }
+}
+
+/// Apply the configured antialiasing, corner and blur radii, shadows, blur
+/// quality and theme style to the renderer
+fn apply_render_config(renderer: &mut VulkanRenderer, parameters: &ParameterRegistry, config: &config::CompositorConfig) {
+    renderer.set_ui_antialiasing(match config.performance.ui_antialiasing {
+        config::UiAntialiasingQuality::Off => UiAntialiasing::Off,
+        config::UiAntialiasingQuality::Analytic => UiAntialiasing::Analytic,
+        config::UiAntialiasingQuality::Msaa2x => UiAntialiasing::Msaa { samples: 2 },
+        config::UiAntialiasingQuality::Msaa4x => UiAntialiasing::Msaa { samples: 4 },
+        config::UiAntialiasingQuality::Msaa8x => UiAntialiasing::Msaa { samples: 8 },
+    });
+    // The radii and shadows stay tunable over IPC from the configured values
+    let corner_radius = config.theme.corner_radius;
+    match parameters.set(effects::params::CORNER_RADIUS, corner_radius) {
+        Ok(corner_radius) => renderer.set_corner_radius(corner_radius),
+        Err(e) => warn!("Invalid corner radius {}: {}", corner_radius, e),
+    }
+    let blur_radius = config.blur.default_radius;
+    match parameters.set(effects::params::BLUR_RADIUS, blur_radius) {
+        Ok(blur_radius) => renderer.set_default_blur_radius(blur_radius),
+        Err(e) => warn!("Invalid blur radius {}: {}", blur_radius, e),
+    }
+    let shadow_intensity = config.theme.shadow_intensity;
+    if let Err(e) = parameters.set(effects::params::SHADOW_INTENSITY, shadow_intensity) {
+        warn!("Invalid shadow intensity {}: {}", shadow_intensity, e);
+    }
+    renderer.set_shadow_scale(1.0);
+    let blur_quality = &config.performance.blur_quality;
+    renderer.set_blur_quality(BlurQuality {
+        samples: blur_quality.samples(),
+        downsample_levels: blur_quality.downsample_levels(),
+        backdrop_update_interval: blur_quality.backdrop_update_interval(),
+    });
+    renderer.set_neomorphism(
+        config.theme.style() == config::ThemeStyle::Neomorphism,
+        config.theme.neomorphism.light_angle,
+        config.theme.neomorphic_surface_color(),
+    );
+}
+
+/// Publish the configured render scales, background colors and present
+/// mode to the channels the render thread reads every frame
+fn publish_render_config(
+    render_scale: &watch::Sender<HashMap<String, f32>>,
+    theme: &watch::Sender<config::ThemeConfig>,
+    present_mode: &watch::Sender<IpcPresentMode>,
+    config: &config::CompositorConfig,
+) {
+    render_scale.send_replace(config.performance.output_render_scale.clone());
+    theme.send_replace(config.theme.clone());
+    present_mode.send_replace(match config.performance.present_mode {
+        config::PresentMode::Fifo => IpcPresentMode::Fifo,
+        config::PresentMode::Mailbox => IpcPresentMode::Mailbox,
+        config::PresentMode::Immediate => IpcPresentMode::Immediate,
+    });
+}
+
+/// The newest configuration received since the last call; older ones
+/// and those lost to lagging behind are skipped
+pub(crate) fn latest_config(changes: &mut broadcast::Receiver<config::CompositorConfig>) -> Option<config::CompositorConfig> {
+    let mut latest = None;
+    loop {
+        match changes.try_recv() {
+            Ok(config) => latest = Some(config),
+            Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+            Err(_) => return latest,
+        }
+    }
+}
+
+/// Hand the surface state the Wayland loop published to the renderer
+fn apply_render_state(renderer: &mut VulkanRenderer, state: RenderState) {
+    renderer.set_stacking_order(state.stacking_order);
+    // Uploads of surfaces on other workspaces wait while frames are over budget
+    renderer.set_hidden_surfaces(state.hidden_surfaces);
+    renderer.set_rescaled_surfaces(state.rescaled_surfaces);
+    renderer.set_surface_corner_radii(state.corner_radii);
+    renderer.set_neomorphic_surfaces(state.neomorphic_surfaces);
+    renderer.set_surface_borders(state.borders);
+    renderer.set_surface_shadows(state.shadows);
+    renderer.set_focused_surface(state.focused_surface);
+    renderer.set_dimming(state.dimming);
+    renderer.set_surface_geometry(state.surface_geometry);
+    renderer.set_surface_blurs(state.blur);
+    renderer.set_wallpaper(state.wallpaper);
+    renderer.set_ui(state.ui);
+}
+
+/// Report a frame the GPU finished to the frame scheduler, frame statistics
+/// and frame callbacks, and wake the Wayland loop to send the callbacks
+fn frame_finished(
+    renderer: &mut VulkanRenderer,
+    frame_scheduler: &mut FrameScheduler,
+    frame_stats: &FrameStatistics,
+    frame_presented: &mpsc::UnboundedSender<FramePresented>,
+    wayland_loop: &LoopSignal,
+    frame: FrameInFlight,
+) {
+    let Some(refresh_interval) = frame_scheduler.output(frame.output).map(|output| output.refresh_interval()) else {
+        return;
+    };
+    
+    // Pace against the actual present time when the driver reports it;
+    // otherwise the scheduler keeps using its refresh timer
+    let presented = match renderer.wait_for_present(refresh_interval) {
+        Ok(Some(presented)) => {
+            frame_scheduler.on_vblank(frame.output, presented);
+            // TODO: Only count surfaces visible on this output
+            frame_stats.frame_presented(presented, refresh_interval);
+            Some(presented)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Present wait failed: {}", e);
+            None
+        }
+    };
+    
+    // Fire the frame callbacks of the surfaces in this frame; without
+    // present timing the finished frame is expected on the next refresh
+    if let Some(output) = frame_scheduler.output(frame.output) {
+        let _ = frame_presented.send(FramePresented {
+            output: output.name().to_string(),
+            submitted: frame.submitted,
+            presented: presented.unwrap_or_else(|| output.predicted_vblank(Instant::now())),
+            refresh: refresh_interval,
+            vsync: presented.is_some(),
+        });
+        wayland_loop.wakeup();
+    }
+}
@@ -0,0 +1,196 @@
+// Input devices through libinput
+//
+// libinput enumerates the seat's input devices through udev and opens them
+// through the session, so the compositor needs no access to /dev/input of
+// its own and loses the devices when the session is switched away. Its
+// events are dispatched on the Wayland event loop, where they are translated
+// into events for the seat's keyboard, pointer and touch.
+//
+// Device settings such as acceleration and tap-to-click are applied to each
+// device as it is added, and to all present devices when the config changes.
+// Pointers can have their own acceleration profile and speed by device name,
+// and the pointer in use can be switched to a flat profile for gaming until
+// it is toggled back.
+
+use crate::session::DeviceAccess;
+use compositor_utils::prelude::*;
+use config::InputConfig;
+use smithay::backend::libinput::LibinputInputBackend;
+use smithay::reexports::input::{self, Device, DeviceCapability, Libinput, LibinputInterface};
+use std::os::fd::{FromRawFd, IntoRawFd, OwnedFd};
+use std::path::Path;
+use tokio::sync::mpsc;
+
+impl LibinputInterface for DeviceAccess {
+    fn open_restricted(&mut self, path: &Path, _flags: i32) -> std::result::Result<OwnedFd, i32> {
+        match self.acquire_device(path.to_string_lossy().into_owned()) {
+            // SAFETY: the session hands over a newly opened fd we now own
+            Ok(fd) => Ok(unsafe { OwnedFd::from_raw_fd(fd) }),
+            Err(e) => {
+                warn!("Failed to open input device {}: {}", path.display(), e);
+                Err(libc::EACCES)
+            }
+        }
+    }
+
+    fn close_restricted(&mut self, fd: OwnedFd) {
+        if let Err(e) = self.release_device(fd.into_raw_fd()) {
+            warn!("Failed to close input device: {}", e);
+        }
+    }
+}
+
+/// Input backend for the devices of a seat, e.g. "seat0"
+pub fn libinput_backend(access: DeviceAccess, seat_name: &str) -> Result<LibinputInputBackend> {
+    let mut context = Libinput::new_with_udev(access);
+    context
+        .udev_assign_seat(seat_name)
+        .map_err(|()| CompositorError::Backend(format!("Failed to assign libinput to {}", seat_name)))?;
+    Ok(LibinputInputBackend::new(context))
+}
+
+/// Input devices present on the seat and the settings applied to them
+#[derive(Debug)]
+pub struct LibinputDevices {
+    devices: Vec<Device>,
+    config: InputConfig,
+    /// Pointer that moved last
+    active_pointer: Option<Device>,
+    /// Pointer switched to a flat acceleration profile
+    flat_pointer: Option<Device>,
+    /// Flat acceleration toggles requested over IPC
+    toggle_sender: mpsc::UnboundedSender<()>,
+    toggle_requests: mpsc::UnboundedReceiver<()>,
+}
+
+impl LibinputDevices {
+    pub fn new() -> Self {
+        let (toggle_sender, toggle_requests) = mpsc::unbounded_channel();
+        Self {
+            devices: Vec::new(),
+            config: InputConfig::default(),
+            active_pointer: None,
+            flat_pointer: None,
+            toggle_sender,
+            toggle_requests,
+        }
+    }
+
+    /// Channel for IPC to toggle the flat acceleration profile
+    pub fn flat_accel_sender(&self) -> mpsc::UnboundedSender<()> {
+        self.toggle_sender.clone()
+    }
+
+    /// Apply the flat acceleration toggles requested since the last call
+    pub fn process_toggle_requests(&mut self) {
+        while self.toggle_requests.try_recv().is_ok() {
+            self.toggle_flat_accel();
+        }
+    }
+
+    /// Apply the settings to a device that was plugged in and track it
+    pub fn add(&mut self, mut device: Device) {
+        configure_device(&mut device, &self.config, false);
+        self.devices.push(device);
+    }
+
+    /// Stop tracking an unplugged device
+    pub fn remove(&mut self, device: &Device) {
+        self.devices.retain(|present| present != device);
+        if self.active_pointer.as_ref() == Some(device) {
+            self.active_pointer = None;
+        }
+        if self.flat_pointer.as_ref() == Some(device) {
+            self.flat_pointer = None;
+        }
+    }
+
+    /// Apply new settings to all present devices
+    pub fn set_config(&mut self, config: InputConfig) {
+        self.config = config;
+        for device in &mut self.devices {
+            let flat = self.flat_pointer.as_ref() == Some(&*device);
+            configure_device(device, &self.config, flat);
+        }
+    }
+
+    /// Record the pointer that moved, for the flat acceleration toggle
+    pub fn pointer_moved(&mut self, device: &Device) {
+        if self.active_pointer.as_ref() != Some(device) {
+            self.active_pointer = Some(device.clone());
+        }
+    }
+
+    /// Switch the pointer that moved last to a flat acceleration profile, or
+    /// the pointer switched earlier back to its configured profile
+    ///
+    /// Returns whether a pointer is now flat.
+    pub fn toggle_flat_accel(&mut self) -> bool {
+        if let Some(mut device) = self.flat_pointer.take() {
+            configure_device(&mut device, &self.config, false);
+            info!("Pointer {} back to its configured acceleration", device.name());
+            return false;
+        }
+        let pointer = self
+            .active_pointer
+            .clone()
+            .or_else(|| self.devices.iter().find(|device| device.config_accel_is_available()).cloned());
+        let Some(mut device) = pointer else {
+            debug!("No pointer to switch to flat acceleration");
+            return false;
+        };
+        configure_device(&mut device, &self.config, true);
+        info!("Pointer {} switched to flat acceleration", device.name());
+        self.flat_pointer = Some(device);
+        true
+    }
+}
+
+impl Default for LibinputDevices {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Apply the settings to a device; `flat` overrides its acceleration profile
+fn configure_device(device: &mut Device, settings: &InputConfig, flat: bool) {
+    if !device.has_capability(DeviceCapability::Pointer) {
+        return;
+    }
+    let name = device.name().to_string();
+    let warn_failed = |setting: &str, result: std::result::Result<(), input::DeviceConfigError>| {
+        if let Err(e) = result {
+            warn!("Failed to set {} of {}: {:?}", setting, name, e);
+        }
+    };
+
+    let accel = settings.accel_for(&name);
+    let profile = match accel.accel_profile {
+        _ if flat => Some(input::AccelProfile::Flat),
+        Some(config::AccelProfile::Flat) => Some(input::AccelProfile::Flat),
+        Some(config::AccelProfile::Adaptive) => Some(input::AccelProfile::Adaptive),
+        // Restores the device default after the flat toggle
+        None => device.config_accel_default_profile(),
+    };
+    if let Some(profile) = profile.filter(|profile| device.config_accel_profiles().contains(profile)) {
+        warn_failed("acceleration profile", device.config_accel_set_profile(profile));
+    }
+    if let Some(speed) = accel.accel_speed.filter(|_| device.config_accel_is_available()) {
+        warn_failed("pointer speed", device.config_accel_set_speed(speed));
+    }
+    if device.config_scroll_has_natural_scroll() {
+        warn_failed("natural scrolling", device.config_scroll_set_natural_scroll_enabled(settings.natural_scroll));
+    }
+    if device.config_tap_finger_count() > 0 {
+        warn_failed("tap-to-click", device.config_tap_set_enabled(settings.tap_to_click));
+    }
+    if let Some(method) = settings.click_method {
+        let method = match method {
+            config::ClickMethod::ButtonAreas => input::ClickMethod::ButtonAreas,
+            config::ClickMethod::Clickfinger => input::ClickMethod::Clickfinger,
+        };
+        if device.config_click_methods().contains(&method) {
+            warn_failed("click method", device.config_click_set_method(method));
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// Laptop lid switch and docking
+//
+// libinput reports the lid switch from udev. Closing the lid turns the
+// built-in outputs off and moves their windows to the external outputs, or
+// suspends when there are none or the config asks for it. Opening the lid
+// turns the built-in outputs back on. Connecting or disconnecting outputs
+// while the lid is closed, e.g. docking a closed laptop, is decided the same
+// way. After every change the output profile matching the outputs in use is
+// applied.
+
+use config::{LidCloseAction, LidConfig, OutputProfile};
+
+/// What to do after the lid or the connected outputs changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LidResponse {
+    /// Nothing changes
+    None,
+    /// Turn these built-in outputs off, moving their windows to the others
+    Disable(Vec<String>),
+    /// Turn these built-in outputs back on
+    Enable(Vec<String>),
+    /// Suspend the system
+    Suspend,
+}
+
+/// Lid state and the built-in outputs turned off for it
+#[derive(Debug, Default)]
+pub struct LidSwitch {
+    config: LidConfig,
+    closed: bool,
+    /// Built-in outputs turned off while the lid is closed
+    disabled: Vec<String>,
+}
+
+impl LidSwitch {
+    pub fn new(config: LidConfig) -> Self {
+        Self { config, closed: false, disabled: Vec::new() }
+    }
+
+    /// Apply a new configuration; takes effect the next
+    /// time the lid or the outputs change
+    pub fn set_config(&mut self, config: LidConfig) {
+        self.config = config;
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// The lid closed or opened; `outputs` are the outputs in use
+    pub fn toggle(&mut self, closed: bool, outputs: &[String]) -> LidResponse {
+        if closed == self.closed {
+            return LidResponse::None;
+        }
+        self.closed = closed;
+        if closed {
+            return self.closed_response(outputs);
+        }
+        match std::mem::take(&mut self.disabled) {
+            disabled if disabled.is_empty() => LidResponse::None,
+            disabled => LidResponse::Enable(disabled),
+        }
+    }
+
+    /// Outputs were connected or disconnected; `outputs` are the outputs in
+    /// use, without the built-in outputs turned off for the lid
+    pub fn outputs_changed(&mut self, outputs: &[String]) -> LidResponse {
+        if !self.closed || self.config.on_close != LidCloseAction::ExternalOnly {
+            return LidResponse::None;
+        }
+        let external = outputs.iter().any(|output| !self.config.is_internal(output));
+        match (self.disabled.is_empty(), external) {
+            // Docked with the lid closed
+            (true, true) => self.closed_response(outputs),
+            // Undocked with the lid closed; the built-in outputs stay off
+            // until the lid opens
+            (false, false) => LidResponse::Suspend,
+            _ => LidResponse::None,
+        }
+    }
+
+    fn closed_response(&mut self, outputs: &[String]) -> LidResponse {
+        match self.config.on_close {
+            LidCloseAction::Ignore => LidResponse::None,
+            LidCloseAction::Suspend => LidResponse::Suspend,
+            LidCloseAction::ExternalOnly => {
+                let (internal, external): (Vec<String>, Vec<String>) =
+                    outputs.iter().cloned().partition(|output| self.config.is_internal(output));
+                if external.is_empty() {
+                    return LidResponse::Suspend;
+                }
+                if internal.is_empty() {
+                    return LidResponse::None;
+                }
+                self.disabled = internal.clone();
+                LidResponse::Disable(internal)
+            }
+        }
+    }
+}
+
+/// The profile for exactly these outputs, if any
+pub fn matching_profile<'a>(profiles: &'a [OutputProfile], outputs: &[String]) -> Option<&'a OutputProfile> {
+    profiles.iter().find(|profile| {
+        profile.outputs.len() == outputs.len() && outputs.iter().all(|output| profile.outputs.contains_key(output))
+    })
+}
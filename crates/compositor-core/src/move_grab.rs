@@ -0,0 +1,160 @@
+// Interactive window move
+//
+// Started from an xdg_toplevel move request. The window follows the pointer
+// across outputs; when the pointer enters an output with a different scale
+// or transform, the window is told the new preferred values right away so it
+// re-renders for the output it is dragged onto before it is dropped. On
+// release the window is fitted onto the output it landed on.
+
+use crate::wayland::WaylandServerState;
+use smithay::desktop::Window;
+use smithay::input::pointer::{
+    AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+    GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+    GestureSwipeUpdateEvent, GrabStartData, MotionEvent, PointerGrab, PointerInnerHandle, RelativeMotionEvent,
+};
+use smithay::output::Output;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::utils::{Logical, Point};
+
+/// Pointer grab moving a window
+pub struct MoveSurfaceGrab {
+    pub start_data: GrabStartData<WaylandServerState>,
+    pub window: Window,
+    pub initial_window_location: Point<i32, Logical>,
+    /// Output under the pointer, whose scale the window was last told
+    pub output: Option<Output>,
+}
+
+impl PointerGrab<WaylandServerState> for MoveSurfaceGrab {
+    fn motion(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        _focus: Option<(WlSurface, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // No client gets pointer focus while a window is moved
+        handle.motion(data, None, event);
+
+        let delta = event.location - self.start_data.location;
+        let location = (self.initial_window_location.to_f64() + delta).to_i32_round();
+        data.drag_window(&self.window, location, event.location, &mut self.output);
+    }
+
+    fn relative_motion(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        focus: Option<(WlSurface, Point<f64, Logical>)>,
+        event: &RelativeMotionEvent,
+    ) {
+        handle.relative_motion(data, focus, event);
+    }
+
+    fn button(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &ButtonEvent,
+    ) {
+        handle.button(data, event);
+        if handle.current_pressed().is_empty() {
+            // No more buttons are pressed, release the grab
+            handle.unset_grab(self, data, event.serial, event.time, true);
+        }
+    }
+
+    fn axis(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        details: AxisFrame,
+    ) {
+        handle.axis(data, details);
+    }
+
+    fn frame(&mut self, data: &mut WaylandServerState, handle: &mut PointerInnerHandle<'_, WaylandServerState>) {
+        handle.frame(data);
+    }
+
+    fn gesture_swipe_begin(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GestureSwipeBeginEvent,
+    ) {
+        handle.gesture_swipe_begin(data, event);
+    }
+
+    fn gesture_swipe_update(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GestureSwipeUpdateEvent,
+    ) {
+        handle.gesture_swipe_update(data, event);
+    }
+
+    fn gesture_swipe_end(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GestureSwipeEndEvent,
+    ) {
+        handle.gesture_swipe_end(data, event);
+    }
+
+    fn gesture_pinch_begin(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GesturePinchBeginEvent,
+    ) {
+        handle.gesture_pinch_begin(data, event);
+    }
+
+    fn gesture_pinch_update(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GesturePinchUpdateEvent,
+    ) {
+        handle.gesture_pinch_update(data, event);
+    }
+
+    fn gesture_pinch_end(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GesturePinchEndEvent,
+    ) {
+        handle.gesture_pinch_end(data, event);
+    }
+
+    fn gesture_hold_begin(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GestureHoldBeginEvent,
+    ) {
+        handle.gesture_hold_begin(data, event);
+    }
+
+    fn gesture_hold_end(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GestureHoldEndEvent,
+    ) {
+        handle.gesture_hold_end(data, event);
+    }
+
+    fn start_data(&self) -> &GrabStartData<WaylandServerState> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, data: &mut WaylandServerState) {
+        data.finish_window_drag(&self.window, self.output.as_ref());
+    }
+}
@@ -0,0 +1,214 @@
+// On-screen keyboard integration for touch sessions
+//
+// Tracks which kinds of input devices are present to detect touch-only
+// sessions, and shows or hides a virtual keyboard when text-input focus enters
+// or leaves an editable field. The keyboard is either an external input-method
+// client launched on demand (e.g. squeekboard) or a built-in keyboard drawn by
+// the ui-framework.
+
+use compositor_utils::prelude::*;
+use crate::wayland_socket::client_command;
+use std::process::Child;
+
+/// External on-screen keyboard launched by default
+pub const DEFAULT_OSK_COMMAND: &[&str] = &["squeekboard"];
+
+/// How the on-screen keyboard is provided
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OskMode {
+    /// Launch an external input-method client; it shows and hides itself as
+    /// the input method is activated and deactivated
+    External { command: Vec<String> },
+    /// Built-in minimal keyboard rendered by the compositor UI
+    BuiltIn,
+    /// Never show an on-screen keyboard
+    Disabled,
+}
+
+impl Default for OskMode {
+    fn default() -> Self {
+        Self::External {
+            command: DEFAULT_OSK_COMMAND.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// On-screen keyboard state
+#[derive(Debug)]
+pub struct OnScreenKeyboard {
+    mode: OskMode,
+    touch_devices: usize,
+    keyboard_devices: usize,
+    /// Text-input focus is on an editable field
+    text_input_active: bool,
+    visible: bool,
+    process: Option<Child>,
+    /// Socket an external keyboard connects to
+    wayland_display: Option<String>,
+}
+
+impl OnScreenKeyboard {
+    /// Create on-screen keyboard integration with the default external keyboard
+    pub fn new() -> Self {
+        Self::with_mode(OskMode::default())
+    }
+
+    /// Create on-screen keyboard integration with the given mode
+    pub fn with_mode(mode: OskMode) -> Self {
+        Self {
+            mode,
+            touch_devices: 0,
+            keyboard_devices: 0,
+            text_input_active: false,
+            visible: false,
+            process: None,
+            wayland_display: None,
+        }
+    }
+
+    /// Set the socket an external keyboard connects to
+    pub fn set_wayland_display(&mut self, wayland_display: Option<String>) {
+        self.wayland_display = wayland_display;
+    }
+
+    /// Change how the keyboard is provided
+    pub fn set_mode(&mut self, mode: OskMode) {
+        if self.mode != mode {
+            self.hide();
+            self.stop_process();
+            self.mode = mode;
+            self.update();
+        }
+    }
+
+    /// Record an input device being added
+    pub fn device_added(&mut self, has_touch: bool, has_keyboard: bool) {
+        self.touch_devices += has_touch as usize;
+        self.keyboard_devices += has_keyboard as usize;
+        self.update();
+    }
+
+    /// Record an input device being removed
+    pub fn device_removed(&mut self, has_touch: bool, has_keyboard: bool) {
+        if has_touch {
+            self.touch_devices = self.touch_devices.saturating_sub(1);
+        }
+        if has_keyboard {
+            self.keyboard_devices = self.keyboard_devices.saturating_sub(1);
+        }
+        self.update();
+    }
+
+    /// Whether the session has touch input but no physical keyboard
+    pub fn is_touch_session(&self) -> bool {
+        self.touch_devices > 0 && self.keyboard_devices == 0
+    }
+
+    /// Update whether text-input focus is on an editable field
+    pub fn set_text_input_active(&mut self, active: bool) {
+        if self.text_input_active != active {
+            debug!("Text input {}", if active { "activated" } else { "deactivated" });
+            self.text_input_active = active;
+            self.update();
+        }
+    }
+
+    /// Whether the built-in keyboard should currently be drawn
+    pub fn builtin_visible(&self) -> bool {
+        self.visible && self.mode == OskMode::BuiltIn
+    }
+
+    /// Whether the on-screen keyboard is currently shown
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide the keyboard based on session type and text-input focus
+    fn update(&mut self) {
+        if self.is_touch_session() && self.text_input_active {
+            self.show();
+        } else {
+            self.hide();
+        }
+    }
+
+    fn show(&mut self) {
+        if self.visible {
+            return;
+        }
+
+        match self.mode.clone() {
+            OskMode::Disabled => return,
+            OskMode::BuiltIn => {}
+            OskMode::External { command } => {
+                if !self.process_running() {
+                    let Some((program, args)) = command.split_first() else {
+                        warn!("On-screen keyboard command is empty");
+                        return;
+                    };
+                    match client_command(program, self.wayland_display.as_deref()).args(args).spawn() {
+                        Ok(child) => {
+                            info!("Launched on-screen keyboard: {} (pid {})", program, child.id());
+                            self.process = Some(child);
+                        }
+                        Err(e) => {
+                            warn!("Failed to launch on-screen keyboard {}: {}", program, e);
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.visible = true;
+        debug!("On-screen keyboard shown");
+    }
+
+    fn hide(&mut self) {
+        if self.visible {
+            // External keyboards hide themselves when the input method is
+            // deactivated; keep the process so it is ready for the next field.
+            self.visible = false;
+            debug!("On-screen keyboard hidden");
+        }
+    }
+
+    /// Check whether the launched keyboard process is still alive
+    fn process_running(&mut self) -> bool {
+        match self.process.as_mut().map(|child| child.try_wait()) {
+            Some(Ok(None)) => true,
+            Some(Ok(Some(status))) => {
+                warn!("On-screen keyboard exited: {}", status);
+                self.process = None;
+                false
+            }
+            Some(Err(e)) => {
+                warn!("Failed to query on-screen keyboard process: {}", e);
+                self.process = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn stop_process(&mut self) {
+        if let Some(mut child) = self.process.take() {
+            if let Err(e) = child.kill() {
+                debug!("Failed to stop on-screen keyboard: {}", e);
+            }
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Default for OnScreenKeyboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for OnScreenKeyboard {
+    fn drop(&mut self) {
+        self.stop_process();
+    }
+}
@@ -0,0 +1,284 @@
+// Runtime output configuration
+//
+// IPC sets the desired scale or transform of an output by name; the
+// compositor picks up changes on its own thread, updates the wl_output and
+// xdg_output state, sends new preferred scales to the surfaces on the output
+// and re-lays out its windows and layer surfaces, so clients adapt without
+// reconnecting. Convertibles can also follow the accelerometer orientation
+// reported by iio-sensor-proxy.
+
+use compositor_utils::dbus::{Connection, Value, PROPERTIES_INTERFACE};
+use compositor_utils::prelude::*;
+use ipc::protocol::DisplayTransform;
+use smithay::utils::{Logical, Point, Size, Transform};
+use std::collections::HashMap;
+use tokio::sync::watch;
+
+/// iio-sensor-proxy on the system bus
+const SENSOR_PROXY_NAME: &str = "net.hadess.SensorProxy";
+const SENSOR_PROXY_PATH: &str = "/net/hadess/SensorProxy";
+const SENSOR_PROXY_INTERFACE: &str = "net.hadess.SensorProxy";
+
+/// Fractional scales are communicated in 1/120 steps (wp-fractional-scale-v1)
+const SCALE_DENOMINATOR: f64 = 120.0;
+
+/// Round a scale to the nearest value clients can be told exactly
+pub fn snap_scale(scale: f64) -> f64 {
+    (scale * SCALE_DENOMINATOR).round() / SCALE_DENOMINATOR
+}
+
+/// Per-output settings requested over IPC
+#[derive(Debug)]
+pub struct OutputRequests<T> {
+    sender: watch::Sender<HashMap<String, T>>,
+    receiver: watch::Receiver<HashMap<String, T>>,
+}
+
+impl<T: Clone> OutputRequests<T> {
+    /// Create an empty request set
+    pub fn new() -> Self {
+        let (sender, receiver) = watch::channel(HashMap::new());
+        Self { sender, receiver }
+    }
+
+    /// Channel for IPC to request settings
+    pub fn sender(&self) -> watch::Sender<HashMap<String, T>> {
+        self.sender.clone()
+    }
+
+    /// Requested settings by output name, if they changed since the last call
+    pub fn take_changed(&mut self) -> Option<HashMap<String, T>> {
+        if !self.receiver.has_changed().unwrap_or(false) {
+            return None;
+        }
+        Some(self.receiver.borrow_and_update().clone())
+    }
+}
+
+impl<T: Clone> Default for OutputRequests<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Convert an IPC transform to the output transform
+pub fn output_transform(transform: DisplayTransform) -> Transform {
+    match transform {
+        DisplayTransform::Normal => Transform::Normal,
+        DisplayTransform::Rotate90 => Transform::_90,
+        DisplayTransform::Rotate180 => Transform::_180,
+        DisplayTransform::Rotate270 => Transform::_270,
+        DisplayTransform::Flipped => Transform::Flipped,
+        DisplayTransform::Flipped90 => Transform::Flipped90,
+        DisplayTransform::Flipped180 => Transform::Flipped180,
+        DisplayTransform::Flipped270 => Transform::Flipped270,
+    }
+}
+
+/// Direction of a rotation hotkey
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationDirection {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Rotate a transform by a quarter turn, keeping its flip
+///
+/// Output transforms rotate counter-clockwise, as in wl_output.transform.
+pub fn rotate_transform(transform: Transform, direction: RotationDirection) -> Transform {
+    let quarter_turn = match direction {
+        RotationDirection::CounterClockwise => Transform::_90,
+        RotationDirection::Clockwise => Transform::_270,
+    };
+    let rotations = [Transform::Normal, Transform::_90, Transform::_180, Transform::_270];
+    let flipped = [Transform::Flipped, Transform::Flipped90, Transform::Flipped180, Transform::Flipped270];
+    let step = rotations.iter().position(|&t| t == quarter_turn).unwrap_or(0);
+    if let Some(index) = rotations.iter().position(|&t| t == transform) {
+        rotations[(index + step) % 4]
+    } else if let Some(index) = flipped.iter().position(|&t| t == transform) {
+        flipped[(index + step) % 4]
+    } else {
+        transform
+    }
+}
+
+/// Map a position on an absolute input device (touchscreen, tablet) to the output
+///
+/// Both positions are normalized to 0.0 - 1.0. The device is mounted on the
+/// panel in its native orientation, so undoing the output transform maps it
+/// onto the rotated picture.
+pub fn map_absolute_position(x: f64, y: f64, transform: Transform) -> (f64, f64) {
+    let position = transform.invert().transform_point_in(Point::<f64, Logical>::from((x, y)), &Size::from((1.0, 1.0)));
+    (position.x, position.y)
+}
+
+/// Accelerometer orientation as reported by iio-sensor-proxy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeviceOrientation {
+    /// Orientation unknown, e.g. the device lies flat
+    #[default]
+    Undefined,
+    Normal,
+    BottomUp,
+    LeftUp,
+    RightUp,
+}
+
+impl DeviceOrientation {
+    /// Parse the AccelerometerOrientation property of net.hadess.SensorProxy
+    pub fn from_sensor_proxy(value: &str) -> Self {
+        match value {
+            "normal" => Self::Normal,
+            "bottom-up" => Self::BottomUp,
+            "left-up" => Self::LeftUp,
+            "right-up" => Self::RightUp,
+            _ => Self::Undefined,
+        }
+    }
+
+    /// Output transform that keeps the picture upright
+    pub fn transform(self) -> Option<Transform> {
+        match self {
+            Self::Undefined => None,
+            Self::Normal => Some(Transform::Normal),
+            Self::BottomUp => Some(Transform::_180),
+            Self::LeftUp => Some(Transform::_90),
+            Self::RightUp => Some(Transform::_270),
+        }
+    }
+}
+
+/// Accelerometer-driven rotation of the built-in display
+#[derive(Debug)]
+pub struct AutoRotation {
+    enabled: bool,
+    /// Rotation lock set by the user
+    locked: bool,
+    sender: watch::Sender<DeviceOrientation>,
+    receiver: watch::Receiver<DeviceOrientation>,
+}
+
+impl AutoRotation {
+    /// Create auto-rotation, disabled until configured
+    pub fn new() -> Self {
+        let (sender, receiver) = watch::channel(DeviceOrientation::Undefined);
+        Self {
+            enabled: false,
+            locked: false,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Enable or disable following the accelerometer
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Lock or unlock the current rotation
+    pub fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+        // Apply the latest orientation right away when unlocking
+        self.receiver.mark_changed();
+    }
+
+    /// Whether the rotation is locked
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Channel for the sensor proxy bridge to report orientation changes
+    pub fn sender(&self) -> watch::Sender<DeviceOrientation> {
+        self.sender.clone()
+    }
+
+    /// Transform to apply, if the orientation changed since the last call
+    pub fn take_transform(&mut self) -> Option<Transform> {
+        if !self.enabled || self.locked || !self.receiver.has_changed().unwrap_or(false) {
+            return None;
+        }
+        self.receiver.borrow_and_update().transform()
+    }
+}
+
+impl Default for AutoRotation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads the accelerometer orientation from iio-sensor-proxy
+pub struct SensorProxyBridge {
+    orientation: watch::Sender<DeviceOrientation>,
+}
+
+impl SensorProxyBridge {
+    /// Create a bridge reporting to the given auto-rotation channel
+    pub fn new(orientation: watch::Sender<DeviceOrientation>) -> Self {
+        Self { orientation }
+    }
+
+    /// Report an AccelerometerOrientation property value
+    pub fn orientation_changed(&self, value: &str) {
+        let orientation = DeviceOrientation::from_sensor_proxy(value);
+        debug!("Accelerometer orientation {:?}", orientation);
+        self.orientation.send_if_modified(|current| std::mem::replace(current, orientation) != orientation);
+    }
+
+    /// Follow the accelerometer until the compositor shuts down
+    pub async fn run(self) {
+        info!("Sensor proxy bridge started");
+        if let Err(e) = self.follow().await {
+            // Devices without an accelerometer have no sensor proxy
+            info!("No accelerometer orientation: {}", e);
+            self.orientation.closed().await;
+        }
+        info!("Sensor proxy bridge stopped");
+    }
+
+    /// Claim the accelerometer and report its orientation until the
+    /// compositor shuts down or the system bus goes away
+    async fn follow(&self) -> Result<()> {
+        let (connection, mut incoming) = Connection::system().await?;
+        connection
+            .add_match(&format!(
+                "type='signal',sender='{}',path='{}',interface='{}',member='PropertiesChanged'",
+                SENSOR_PROXY_NAME, SENSOR_PROXY_PATH, PROPERTIES_INTERFACE
+            ))
+            .await?;
+        connection
+            .call_method(SENSOR_PROXY_NAME, SENSOR_PROXY_PATH, SENSOR_PROXY_INTERFACE, "ClaimAccelerometer", Vec::new())
+            .await?;
+        let orientation = connection
+            .get_property(SENSOR_PROXY_NAME, SENSOR_PROXY_PATH, SENSOR_PROXY_INTERFACE, "AccelerometerOrientation")
+            .await?;
+        self.orientation_changed(orientation.as_str().unwrap_or_default());
+
+        loop {
+            tokio::select! {
+                message = incoming.recv() => {
+                    let Some(message) = message else { break };
+                    if !message.is_signal(PROPERTIES_INTERFACE, "PropertiesChanged")
+                        || message.body.first().and_then(Value::as_str) != Some(SENSOR_PROXY_INTERFACE)
+                    {
+                        continue;
+                    }
+                    // PropertiesChanged(s interface, a{sv} changed, as invalidated)
+                    if let Some(value) = message.body.get(1).and_then(|changed| changed.get("AccelerometerOrientation")) {
+                        self.orientation_changed(value.as_str().unwrap_or_default());
+                    }
+                }
+                _ = self.orientation.closed() => {
+                    if let Err(e) = connection
+                        .call_method(SENSOR_PROXY_NAME, SENSOR_PROXY_PATH, SENSOR_PROXY_INTERFACE, "ReleaseAccelerometer", Vec::new())
+                        .await
+                    {
+                        debug!("Failed to release the accelerometer: {}", e);
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        Err(CompositorError::ipc("System bus connection closed"))
+    }
+}
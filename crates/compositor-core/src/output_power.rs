@@ -0,0 +1,201 @@
+// Output power saving
+//
+// Outputs turn off after a configured time without input. Before that they
+// fade down to a dim brightness, so the user notices and can keep them on by
+// moving the pointer or pressing a key, which fades them back up instead of
+// cutting over. Input wakes outputs that are already off right away. The
+// fades step at the output animation rate and frames in between interpolate.
+
+use compositor_utils::animation_tick::{Interpolated, Lerp};
+use config::PowerSaveConfig;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Time between fade steps when nothing else wakes the event loop
+const FADE_STEP_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Power state of an output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerPhase {
+    /// At full brightness
+    On,
+    /// Fading down towards turning off
+    Dimming,
+    /// Turned off until input arrives
+    Off,
+    /// Fading back up after input during the dim fade
+    Restoring { from: f32, started: Instant },
+}
+
+/// Power state and brightness of one output
+#[derive(Debug, Clone)]
+struct OutputState {
+    phase: PowerPhase,
+    /// Brightness of the last two animation steps
+    brightness: Interpolated<f32>,
+}
+
+/// Outputs to turn on or off after a call
+#[derive(Debug, Default, PartialEq)]
+pub struct PowerChanges {
+    pub off: Vec<String>,
+    pub on: Vec<String>,
+}
+
+/// Dims and turns off outputs after inactivity
+#[derive(Debug)]
+pub struct OutputPower {
+    config: PowerSaveConfig,
+    last_activity: Instant,
+    last_step: Option<Instant>,
+    outputs: HashMap<String, OutputState>,
+}
+
+impl OutputPower {
+    pub fn new(config: PowerSaveConfig, now: Instant) -> Self {
+        Self {
+            config,
+            last_activity: now,
+            last_step: None,
+            outputs: HashMap::new(),
+        }
+    }
+
+    /// Apply a new configuration; counts as activity so
+    /// a shorter timeout does not turn outputs off at once
+    pub fn set_config(&mut self, config: PowerSaveConfig, now: Instant) -> PowerChanges {
+        self.config = config;
+        self.activity(now)
+    }
+
+    /// Track an output, starting at full brightness
+    pub fn add_output(&mut self, output: &str) {
+        self.outputs.insert(
+            output.to_string(),
+            OutputState { phase: PowerPhase::On, brightness: Interpolated::new(1.0) },
+        );
+    }
+
+    /// Forget a removed output
+    pub fn remove_output(&mut self, output: &str) {
+        self.outputs.remove(output);
+    }
+
+    /// Input arrived: fade dimming outputs back up and wake outputs that are off
+    pub fn activity(&mut self, now: Instant) -> PowerChanges {
+        self.last_activity = now;
+        let mut changes = PowerChanges::default();
+        for (name, output) in &mut self.outputs {
+            match output.phase {
+                PowerPhase::Dimming => {
+                    output.phase = PowerPhase::Restoring { from: output.brightness.current(), started: now };
+                }
+                PowerPhase::Off => {
+                    output.phase = PowerPhase::On;
+                    output.brightness.set(1.0);
+                    changes.on.push(name.clone());
+                }
+                PowerPhase::On | PowerPhase::Restoring { .. } => {}
+            }
+        }
+        changes
+    }
+
+    /// Step fades to `now`, start dimming idle outputs and turn off those
+    /// that finished dimming
+    pub fn advance(&mut self, now: Instant) -> PowerChanges {
+        self.last_step = Some(now);
+        let mut changes = PowerChanges::default();
+        let names: Vec<String> = self.outputs.keys().cloned().collect();
+        for name in names {
+            let timing = self.timing(&name);
+            let (dim_brightness, undim) = (self.config.dim_brightness, self.undim_duration());
+            let Some(output) = self.outputs.get_mut(&name) else { continue };
+            match (output.phase, timing) {
+                (PowerPhase::On, Some((dim_at, _))) if now >= dim_at => output.phase = PowerPhase::Dimming,
+                // Power saving turned off for the output mid fade
+                (PowerPhase::Dimming, None) => {
+                    output.phase = PowerPhase::Restoring { from: output.brightness.current(), started: now };
+                }
+                _ => {}
+            }
+            match (output.phase, timing) {
+                (PowerPhase::Dimming, Some((dim_at, off_at))) => {
+                    output.brightness.step(1.0_f32.lerp(dim_brightness, progress(dim_at, off_at, now)));
+                    if now >= off_at {
+                        output.phase = PowerPhase::Off;
+                        changes.off.push(name);
+                    }
+                }
+                (PowerPhase::Restoring { from, started }, _) => {
+                    let t = progress(started, started + undim, now);
+                    output.brightness.step(from.lerp(1.0, t));
+                    if t >= 1.0 {
+                        output.phase = PowerPhase::On;
+                    }
+                }
+                _ => output.brightness.step(output.brightness.current()),
+            }
+        }
+        changes
+    }
+
+    /// Whether any output is fading
+    pub fn is_animating(&self) -> bool {
+        self.outputs
+            .values()
+            .any(|output| matches!(output.phase, PowerPhase::Dimming | PowerPhase::Restoring { .. }))
+    }
+
+    /// Power state of an output
+    pub fn phase(&self, output: &str) -> Option<PowerPhase> {
+        self.outputs.get(output).map(|output| output.phase)
+    }
+
+    /// Brightness to draw an output with, `alpha` of the way from the
+    /// previous animation step to the last
+    pub fn brightness(&self, output: &str, alpha: f32) -> f32 {
+        self.outputs.get(output).map_or(1.0, |output| output.brightness.value(alpha))
+    }
+
+    /// When the next fade step is due, or the next output starts dimming
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if self.is_animating() {
+            return Some(self.last_step.map_or_else(Instant::now, |last| last + FADE_STEP_INTERVAL));
+        }
+        self.outputs
+            .iter()
+            .filter(|(_, output)| output.phase == PowerPhase::On)
+            .filter_map(|(name, _)| self.timing(name))
+            .map(|(dim_at, _)| dim_at)
+            .min()
+    }
+
+    /// When an output starts dimming and when it turns off, if it does
+    fn timing(&self, output: &str) -> Option<(Instant, Instant)> {
+        let off_after = Duration::from_secs(self.config.off_after_for(output)?);
+        let off_at = self.last_activity + off_after;
+        let dim = Duration::from_millis(self.config.dim_duration).min(off_after);
+        Some((off_at - dim, off_at))
+    }
+
+    fn undim_duration(&self) -> Duration {
+        Duration::from_millis(self.config.undim_duration)
+    }
+}
+
+impl Default for OutputPower {
+    fn default() -> Self {
+        Self::new(PowerSaveConfig::default(), Instant::now())
+    }
+}
+
+/// Eased progress of a fade from `start` to `end`, from 0.0 to 1.0
+fn progress(start: Instant, end: Instant, now: Instant) -> f32 {
+    let duration = end.saturating_duration_since(start);
+    if duration.is_zero() {
+        return 1.0;
+    }
+    let t = (now.saturating_duration_since(start).as_secs_f32() / duration.as_secs_f32()).min(1.0);
+    t * t * (3.0 - 2.0 * t)
+}
@@ -0,0 +1,210 @@
+// Window placement
+//
+// New windows are mapped right away but only placed once their first buffer
+// gives them a size, and the app ID is usually known by then too. Windows are
+// placed inside the usable area of their output, the part layer-shell panels
+// do not reserve with exclusive zones, by the configured policy. Where each
+// application's last window closed is remembered for the remember-last policy.
+
+use config::PlacementPolicy;
+use smithay::utils::{Logical, Point, Rectangle, Size};
+use std::collections::{HashMap, HashSet};
+use wayland_server::backend::ObjectId;
+
+/// Offset between cascaded windows
+const CASCADE_STEP: i32 = 32;
+
+/// Places new windows
+#[derive(Debug, Default)]
+pub struct WindowPlacer {
+    policy: PlacementPolicy,
+    /// Windows mapped but not placed yet, waiting for their size
+    pending: HashSet<ObjectId>,
+    /// Location of the last closed window of each app ID
+    last_positions: HashMap<String, Point<i32, Logical>>,
+}
+
+impl WindowPlacer {
+    pub fn new(policy: PlacementPolicy) -> Self {
+        Self { policy, ..Default::default() }
+    }
+
+    /// Apply a new policy
+    pub fn set_policy(&mut self, policy: PlacementPolicy) {
+        self.policy = policy;
+    }
+
+    /// Place a window once it has a size
+    pub fn window_mapped(&mut self, window: ObjectId) {
+        self.pending.insert(window);
+    }
+
+    /// Whether a window still waits to be placed
+    pub fn is_pending(&self, window: &ObjectId) -> bool {
+        self.pending.contains(window)
+    }
+
+    /// Stop waiting to place a window, e.g. when it becomes fullscreen
+    pub fn cancel(&mut self, window: &ObjectId) {
+        self.pending.remove(window);
+    }
+
+    /// Remember where a closed window was, for its application's next window
+    pub fn window_closed(&mut self, window: &ObjectId, app_id: Option<&str>, location: Option<Point<i32, Logical>>) {
+        self.pending.remove(window);
+        if let (Some(app_id), Some(location)) = (app_id, location) {
+            self.last_positions.insert(app_id.to_string(), location);
+        }
+    }
+
+    /// Location for a pending window of `size` in the usable `area` of its
+    /// output, next to the `others` windows shown there
+    pub fn place(
+        &mut self,
+        window: &ObjectId,
+        size: Size<i32, Logical>,
+        area: Rectangle<i32, Logical>,
+        others: &[Rectangle<i32, Logical>],
+        app_id: Option<&str>,
+    ) -> Point<i32, Logical> {
+        self.pending.remove(window);
+        let location = match self.policy {
+            PlacementPolicy::Cascade => cascade(size, area, others),
+            PlacementPolicy::Center => center(size, area),
+            PlacementPolicy::Smart => least_overlap(size, area, others),
+            PlacementPolicy::RememberLast => app_id
+                .and_then(|app_id| self.last_positions.get(app_id))
+                .copied()
+                .filter(|last| area.contains_rect(Rectangle::new(*last, size)))
+                .unwrap_or_else(|| least_overlap(size, area, others)),
+        };
+        clamp(location, size, area)
+    }
+}
+
+/// Diagonally below the top-left corner, stepping past windows already
+/// there and starting over when the window would leave the area
+fn cascade(size: Size<i32, Logical>, area: Rectangle<i32, Logical>, others: &[Rectangle<i32, Logical>]) -> Point<i32, Logical> {
+    let mut location = area.loc;
+    while others.iter().any(|other| other.loc == location) {
+        location += Point::from((CASCADE_STEP, CASCADE_STEP));
+        if !area.contains_rect(Rectangle::new(location, size)) {
+            return area.loc;
+        }
+    }
+    location
+}
+
+fn center(size: Size<i32, Logical>, area: Rectangle<i32, Logical>) -> Point<i32, Logical> {
+    area.loc + Point::from(((area.size.w - size.w) / 2, (area.size.h - size.h) / 2))
+}
+
+/// The candidate location where the window covers the least of the others;
+/// candidates are the area's corners and the spots next to each window,
+/// ties going to the topmost, then leftmost
+fn least_overlap(size: Size<i32, Logical>, area: Rectangle<i32, Logical>, others: &[Rectangle<i32, Logical>]) -> Point<i32, Logical> {
+    let right = area.loc.x + area.size.w - size.w;
+    let bottom = area.loc.y + area.size.h - size.h;
+    let mut xs = vec![area.loc.x, right];
+    let mut ys = vec![area.loc.y, bottom];
+    for other in others {
+        xs.extend([other.loc.x + other.size.w, other.loc.x - size.w, other.loc.x]);
+        ys.extend([other.loc.y + other.size.h, other.loc.y - size.h, other.loc.y]);
+    }
+
+    let overlap = |location: Point<i32, Logical>| -> i64 {
+        let rect = Rectangle::new(location, size);
+        others
+            .iter()
+            .filter_map(|other| rect.intersection(*other))
+            .map(|covered| covered.size.w as i64 * covered.size.h as i64)
+            .sum()
+    };
+    ys.iter()
+        .flat_map(|&y| xs.iter().map(move |&x| Point::from((x, y))))
+        .map(|location| clamp(location, size, area))
+        .min_by_key(|&location| (overlap(location), location.y, location.x))
+        .unwrap_or(area.loc)
+}
+
+/// Keep a window inside the area, its top-left corner if it is too large
+fn clamp(location: Point<i32, Logical>, size: Size<i32, Logical>, area: Rectangle<i32, Logical>) -> Point<i32, Logical> {
+    let x = location.x.min(area.loc.x + area.size.w - size.w).max(area.loc.x);
+    let y = location.y.min(area.loc.y + area.size.h - size.h).max(area.loc.y);
+    (x, y).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::new((x, y).into(), (w, h).into())
+    }
+
+    /// Usable area of a 1920x1080 output below a 32 pixel panel
+    fn area() -> Rectangle<i32, Logical> {
+        rect(0, 32, 1920, 1048)
+    }
+
+    fn place(placer: &mut WindowPlacer, size: (i32, i32), others: &[Rectangle<i32, Logical>], app_id: Option<&str>) -> Point<i32, Logical> {
+        let window = ObjectId::null();
+        placer.window_mapped(window.clone());
+        let location = placer.place(&window, size.into(), area(), others, app_id);
+        assert!(!placer.is_pending(&window));
+        location
+    }
+
+    #[test]
+    fn test_cascade_steps_past_windows() {
+        let mut placer = WindowPlacer::new(PlacementPolicy::Cascade);
+        assert_eq!(place(&mut placer, (800, 600), &[], None), Point::from((0, 32)));
+
+        let others = [rect(0, 32, 800, 600), rect(32, 64, 800, 600)];
+        assert_eq!(place(&mut placer, (800, 600), &others, None), Point::from((64, 96)));
+    }
+
+    #[test]
+    fn test_cascade_starts_over_at_area_edge() {
+        let mut placer = WindowPlacer::new(PlacementPolicy::Cascade);
+        let others = [rect(0, 32, 800, 1000), rect(32, 64, 800, 1000)];
+        assert_eq!(place(&mut placer, (800, 1000), &others, None), Point::from((0, 32)));
+    }
+
+    #[test]
+    fn test_center() {
+        let mut placer = WindowPlacer::new(PlacementPolicy::Center);
+        assert_eq!(place(&mut placer, (800, 600), &[], None), Point::from((560, 256)));
+    }
+
+    #[test]
+    fn test_smart_avoids_overlap() {
+        let mut placer = WindowPlacer::new(PlacementPolicy::Smart);
+        assert_eq!(place(&mut placer, (800, 600), &[], None), Point::from((0, 32)));
+
+        let others = [rect(0, 32, 1000, 600)];
+        assert_eq!(place(&mut placer, (800, 600), &others, None), Point::from((1000, 32)));
+    }
+
+    #[test]
+    fn test_remember_last_position() {
+        let mut placer = WindowPlacer::new(PlacementPolicy::RememberLast);
+        let closed = ObjectId::null();
+        placer.window_closed(&closed, Some("editor"), Some((300, 200).into()));
+
+        assert_eq!(place(&mut placer, (800, 600), &[], Some("editor")), Point::from((300, 200)));
+        // Other applications, and positions that no longer fit, fall back
+        assert_eq!(place(&mut placer, (800, 600), &[], Some("terminal")), Point::from((0, 32)));
+        assert_eq!(place(&mut placer, (1800, 600), &[], Some("editor")), Point::from((0, 32)));
+    }
+
+    #[test]
+    fn test_clamp_to_area() {
+        let area = area();
+        let size = Size::from((800, 600));
+        assert_eq!(clamp((1500, 900).into(), size, area), Point::from((1120, 480)));
+        assert_eq!(clamp((-50, 0).into(), size, area), Point::from((0, 32)));
+        // Too large windows keep their top-left corner in the area
+        assert_eq!(clamp((100, 100).into(), (2500, 1200).into(), area), Point::from((0, 32)));
+    }
+}
@@ -0,0 +1,241 @@
+// Sticky edges and pointer barriers between outputs
+//
+// Fed from the pointer motion path before the new position is applied. When
+// the pointer would move from one output onto a neighbouring one, it is held
+// at the edge until it has pushed the configured distance past it, so panels
+// and edge actions along shared edges are as easy to hit as outer screen
+// edges. Barrier edges hold the pointer back in both directions.
+
+use compositor_utils::prelude::*;
+use config::{OutputEdge, PointerBarriersConfig};
+use smithay::utils::{Logical, Point, Rectangle};
+use std::time::{Duration, Instant};
+
+/// Logical area of an output
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputArea {
+    pub name: String,
+    pub geometry: Rectangle<i32, Logical>,
+}
+
+impl OutputArea {
+    fn contains(&self, position: Point<f64, Logical>) -> bool {
+        self.geometry.to_f64().contains(position)
+    }
+
+    /// Edge crossed when moving to a position outside the output, and how far past it
+    fn exit(&self, position: Point<f64, Logical>) -> Option<(OutputEdge, f64)> {
+        let (min, max) = self.last_pixels();
+        [
+            (OutputEdge::Left, min.x - position.x),
+            (OutputEdge::Right, position.x - max.x),
+            (OutputEdge::Top, min.y - position.y),
+            (OutputEdge::Bottom, position.y - max.y),
+        ]
+        .into_iter()
+        .filter(|&(_, overshoot)| overshoot > 0.0)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Position moved back onto the output's outermost pixels
+    fn clamp(&self, position: Point<f64, Logical>) -> Point<f64, Logical> {
+        let (min, max) = self.last_pixels();
+        Point::from((position.x.clamp(min.x, max.x), position.y.clamp(min.y, max.y)))
+    }
+
+    /// Top-left and bottom-right pixels of the output
+    fn last_pixels(&self) -> (Point<f64, Logical>, Point<f64, Logical>) {
+        let geometry = self.geometry.to_f64();
+        let max = geometry.loc + geometry.size.to_point() - Point::from((1.0, 1.0));
+        (geometry.loc, max)
+    }
+}
+
+/// Push against a sticky edge that has not crossed yet
+#[derive(Debug, Clone)]
+struct EdgePush {
+    output: String,
+    edge: OutputEdge,
+    distance: f64,
+    last: Instant,
+}
+
+/// Decides whether the pointer may cross between outputs
+#[derive(Debug)]
+pub struct PointerBarriers {
+    config: PointerBarriersConfig,
+    push: Option<EdgePush>,
+}
+
+impl PointerBarriers {
+    /// Create pointer barriers with the given configuration
+    pub fn new(config: PointerBarriersConfig) -> Self {
+        Self { config, push: None }
+    }
+
+    /// Apply new configuration
+    pub fn set_config(&mut self, config: PointerBarriersConfig) {
+        self.config = config;
+        self.push = None;
+    }
+
+    /// Position the pointer moves to when heading from `from` to `to`
+    ///
+    /// Motion leaving the outputs altogether is returned unchanged; keeping
+    /// the pointer on screen is up to the caller.
+    pub fn constrain(
+        &mut self,
+        from: Point<f64, Logical>,
+        to: Point<f64, Logical>,
+        outputs: &[OutputArea],
+        now: Instant,
+    ) -> Point<f64, Logical> {
+        let Some(source) = outputs.iter().find(|output| output.contains(from)) else {
+            self.push = None;
+            return to;
+        };
+        if source.contains(to) {
+            self.push = None;
+            return to;
+        }
+        let Some(target) = outputs.iter().find(|output| output.contains(to)) else {
+            return to;
+        };
+        let Some((edge, overshoot)) = source.exit(to) else {
+            return to;
+        };
+
+        if self.is_barrier(&source.name, edge) || self.is_barrier(&target.name, opposite(edge)) {
+            return source.clamp(to);
+        }
+
+        let resistance = f64::from(self.config.sticky_edge_resistance);
+        if resistance == 0.0 {
+            return to;
+        }
+        let timeout = Duration::from_millis(self.config.sticky_edge_timeout);
+        let pushed = match &self.push {
+            Some(push) if push.output == source.name && push.edge == edge && now.duration_since(push.last) < timeout => {
+                push.distance
+            }
+            _ => 0.0,
+        };
+        let distance = pushed + overshoot;
+        if distance >= resistance {
+            debug!("Pointer crossed sticky {:?} edge of {}", edge, source.name);
+            self.push = None;
+            return to;
+        }
+
+        self.push = Some(EdgePush {
+            output: source.name.clone(),
+            edge,
+            distance,
+            last: now,
+        });
+        source.clamp(to)
+    }
+
+    fn is_barrier(&self, output: &str, edge: OutputEdge) -> bool {
+        self.config
+            .barriers
+            .iter()
+            .any(|barrier| barrier.output == output && barrier.edge == edge)
+    }
+}
+
+fn opposite(edge: OutputEdge) -> OutputEdge {
+    match edge {
+        OutputEdge::Left => OutputEdge::Right,
+        OutputEdge::Right => OutputEdge::Left,
+        OutputEdge::Top => OutputEdge::Bottom,
+        OutputEdge::Bottom => OutputEdge::Top,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::PointerBarrier;
+
+    /// Two 1920x1080 outputs side by side
+    fn outputs() -> Vec<OutputArea> {
+        vec![
+            OutputArea { name: "DP-1".to_string(), geometry: Rectangle::new((0, 0).into(), (1920, 1080).into()) },
+            OutputArea { name: "DP-2".to_string(), geometry: Rectangle::new((1920, 0).into(), (1920, 1080).into()) },
+        ]
+    }
+
+    fn sticky(resistance: u32) -> PointerBarriers {
+        PointerBarriers::new(PointerBarriersConfig { sticky_edge_resistance: resistance, ..Default::default() })
+    }
+
+    fn point(x: f64, y: f64) -> Point<f64, Logical> {
+        Point::from((x, y))
+    }
+
+    #[test]
+    fn test_crossing_without_resistance() {
+        let mut barriers = sticky(0);
+        let to = barriers.constrain(point(1910.0, 500.0), point(1930.0, 500.0), &outputs(), Instant::now());
+        assert_eq!(to, point(1930.0, 500.0));
+    }
+
+    #[test]
+    fn test_sticky_edge_releases_after_resistance() {
+        let mut barriers = sticky(50);
+        let outputs = outputs();
+        let now = Instant::now();
+
+        let held = barriers.constrain(point(1910.0, 500.0), point(1930.0, 500.0), &outputs, now);
+        assert_eq!(held, point(1919.0, 500.0));
+        let held = barriers.constrain(held, point(1950.0, 500.0), &outputs, now + Duration::from_millis(10));
+        assert_eq!(held, point(1919.0, 500.0));
+        // 11 + 31 + 9 pixels pushed past the edge
+        let crossed = barriers.constrain(held, point(1928.0, 500.0), &outputs, now + Duration::from_millis(20));
+        assert_eq!(crossed, point(1928.0, 500.0));
+    }
+
+    #[test]
+    fn test_sticky_push_starts_over() {
+        let mut barriers = sticky(50);
+        let outputs = outputs();
+        let now = Instant::now();
+
+        // After the timeout
+        barriers.constrain(point(1910.0, 500.0), point(1945.0, 500.0), &outputs, now);
+        let held = barriers.constrain(point(1919.0, 500.0), point(1945.0, 500.0), &outputs, now + Duration::from_millis(600));
+        assert_eq!(held, point(1919.0, 500.0));
+
+        // After moving away from the edge
+        barriers.constrain(point(1919.0, 500.0), point(1900.0, 500.0), &outputs, now + Duration::from_millis(610));
+        let held = barriers.constrain(point(1900.0, 500.0), point(1945.0, 500.0), &outputs, now + Duration::from_millis(620));
+        assert_eq!(held, point(1919.0, 500.0));
+    }
+
+    #[test]
+    fn test_barrier_is_never_crossed() {
+        let config = PointerBarriersConfig {
+            barriers: vec![PointerBarrier { output: "DP-2".to_string(), edge: OutputEdge::Left }],
+            ..Default::default()
+        };
+        let mut barriers = PointerBarriers::new(config);
+        let outputs = outputs();
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            let held = barriers.constrain(point(1919.0, 500.0), point(2100.0, 520.0), &outputs, now);
+            assert_eq!(held, point(1919.0, 520.0));
+        }
+        // Both directions
+        let held = barriers.constrain(point(1925.0, 500.0), point(1900.0, 500.0), &outputs, now);
+        assert_eq!(held, point(1920.0, 500.0));
+    }
+
+    #[test]
+    fn test_leaving_all_outputs_is_unchanged() {
+        let mut barriers = sticky(50);
+        let to = barriers.constrain(point(1919.0, 500.0), point(1930.0, -10.0), &outputs(), Instant::now());
+        assert_eq!(to, point(1930.0, -10.0));
+    }
+}
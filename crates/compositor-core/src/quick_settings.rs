@@ -0,0 +1,330 @@
+// Quick settings panel
+//
+// A keybinding opens the app bar's quick settings panel in the top right
+// corner of the output under the pointer. The compositor runs the panel's
+// backend bridge, applies the state it reports and sends it the commands the
+// panel triggers. The UI pass draws the panel as a card with a row per
+// available control: Wi-Fi and Bluetooth toggle on click or Enter, volume and
+// brightness are set by clicking their slider or with Left/Right. Labels are
+// rasterized in white when their text changes and tinted with theme colors.
+
+use app_bar::quick_settings::{
+    QuickSettingsBridge, QuickSettingsCommand, QuickSettingsControl, QuickSettingsEvent, QuickSettingsPanel,
+    QuickSettingsState, MIN_BRIGHTNESS,
+};
+use compositor_utils::math::Rect;
+use compositor_utils::prelude::*;
+use config::{ColorToken, ThemeConfig};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use ui_framework::focus::NavigationKey;
+use ui_framework::font::Font;
+use ui_framework::label::Label;
+use vulkan_renderer::UiPrimitive;
+
+/// Width of the panel in logical pixels
+const WIDTH: f32 = 320.0;
+/// Distance from the output's top and right edges
+const MARGIN: f32 = 12.0;
+const PADDING: f32 = 12.0;
+const ROW_HEIGHT: f32 = 52.0;
+const LABEL_SIZE: f32 = 14.0;
+const SLIDER_HEIGHT: f32 = 6.0;
+
+/// Colors and corner radius of the panel
+#[derive(Debug, Clone, Copy)]
+struct PanelStyle {
+    card: [f32; 4],
+    focus: [f32; 4],
+    text: [f32; 4],
+    secondary: [f32; 4],
+    accent: [f32; 4],
+    /// Unfilled part of sliders
+    track: [f32; 4],
+    corner_radius: f32,
+}
+
+impl PanelStyle {
+    fn new(theme: &ThemeConfig) -> Self {
+        Self {
+            card: theme.color(ColorToken::Surface),
+            focus: theme.color(ColorToken::SurfaceVariant),
+            text: theme.color(ColorToken::OnSurface),
+            secondary: theme.color(ColorToken::OnSurfaceVariant),
+            accent: theme.color(ColorToken::Accent),
+            track: theme.color(ColorToken::Outline),
+            corner_radius: theme.corner_radius,
+        }
+    }
+}
+
+/// Row of one control
+struct Row {
+    control: QuickSettingsControl,
+    rect: Rect,
+    /// Filled part of the slider, for volume and brightness
+    slider: Option<(Rect, f32)>,
+}
+
+/// Quick settings panel shown by the compositor
+pub struct QuickSettings {
+    state: QuickSettingsState,
+    panel: QuickSettingsPanel,
+    /// Output area the panel is shown in while open
+    anchor: Option<Rect>,
+    events: mpsc::UnboundedReceiver<QuickSettingsEvent>,
+    commands: Option<mpsc::UnboundedSender<QuickSettingsCommand>>,
+    style: PanelStyle,
+    font: Option<Arc<Font>>,
+    /// Labels rasterized for the texts currently shown
+    labels: HashMap<String, Label>,
+}
+
+impl QuickSettings {
+    pub fn new() -> Self {
+        Self {
+            state: QuickSettingsState::default(),
+            panel: QuickSettingsPanel::new(),
+            anchor: None,
+            events: mpsc::unbounded_channel().1,
+            commands: None,
+            style: PanelStyle::new(&ThemeConfig::default()),
+            font: None,
+            labels: HashMap::new(),
+        }
+    }
+
+    /// Bridge connecting the panel to its backends; spawn its `run`
+    pub fn bridge(&mut self) -> QuickSettingsBridge {
+        let (events, events_receiver) = mpsc::unbounded_channel();
+        let (commands, commands_receiver) = mpsc::unbounded_channel();
+        self.events = events_receiver;
+        self.commands = Some(commands);
+        QuickSettingsBridge::new(events, commands_receiver)
+    }
+
+    /// Style the panel after a theme, with the font of compositor dialogs
+    pub fn set_theme(&mut self, theme: &ThemeConfig, font: Option<Arc<Font>>) {
+        self.style = PanelStyle::new(theme);
+        self.font = font;
+        self.labels.clear();
+        self.update_labels();
+    }
+
+    /// Apply backend state changes
+    pub fn process_events(&mut self) {
+        let mut changed = false;
+        while let Ok(event) = self.events.try_recv() {
+            changed |= self.state.apply(event);
+        }
+        if changed && self.is_open() {
+            self.update_labels();
+        }
+    }
+
+    /// Whether the panel is shown and takes keyboard input
+    pub fn is_open(&self) -> bool {
+        self.panel.is_open()
+    }
+
+    /// Open the panel over `output`, or close it if open
+    pub fn toggle(&mut self, output: Rect) {
+        self.anchor = self.panel.toggle(&self.state).then_some(output);
+        self.update_labels();
+    }
+
+    pub fn close(&mut self) {
+        self.panel.close();
+        self.anchor = None;
+        self.update_labels();
+    }
+
+    /// Apply a key while the panel is open; returns whether it was consumed
+    pub fn key(&mut self, key: NavigationKey) -> bool {
+        let (consumed, command) = self.panel.handle_key(key, &self.state);
+        if let Some(command) = command {
+            self.send(command);
+        }
+        if !self.panel.is_open() {
+            self.anchor = None;
+        }
+        self.update_labels();
+        consumed
+    }
+
+    /// Toggle or set the control at `point`; a click outside the panel
+    /// closes it. Returns whether the click was consumed.
+    pub fn click(&mut self, point: Vec2) -> bool {
+        let Some((card, rows)) = self.layout() else { return false };
+        if !card.contains(point) {
+            self.close();
+            return false;
+        }
+        let Some(row) = rows.iter().find(|row| row.rect.contains(point)) else { return true };
+        let command = match (row.control, row.slider) {
+            (QuickSettingsControl::Volume, Some((track, _))) if track_hit(track, point) => {
+                Some(QuickSettingsCommand::SetVolume(slider_fraction(track, point)))
+            }
+            (QuickSettingsControl::Brightness, Some((track, _))) => {
+                track_hit(track, point).then(|| QuickSettingsCommand::SetBrightness(slider_fraction(track, point).max(MIN_BRIGHTNESS)))
+            }
+            (control, _) => self.state.toggle(control),
+        };
+        if let Some(command) = command {
+            self.send(command);
+        }
+        true
+    }
+
+    /// Primitives drawing the open panel, back to front
+    pub fn primitives(&self) -> Vec<UiPrimitive> {
+        let Some((card, rows)) = self.layout() else { return Vec::new() };
+        let style = &self.style;
+        let mut primitives = vec![
+            UiPrimitive::drop_shadow(card, style.corner_radius, 0.4),
+            UiPrimitive::Rect { rect: card, color: style.card, corner_radius: style.corner_radius },
+        ];
+        let inner_radius = (style.corner_radius - PADDING / 2.0).max(0.0);
+        for row in rows {
+            if self.panel.focused() == Some(row.control) {
+                primitives.push(UiPrimitive::Rect { rect: row.rect, color: style.focus, corner_radius: inner_radius });
+            }
+            let (name, value) = self.texts(row.control);
+            let text_top = row.rect.y + PADDING;
+            if let Some(label) = self.labels.get(name) {
+                let (width, height) = label.size();
+                let rect = Rect::new(row.rect.x + PADDING, text_top, width, height);
+                primitives.push(UiPrimitive::Image { rect, image: label.image.clone(), tint: style.text });
+            }
+            if let Some(label) = self.labels.get(&value) {
+                let (width, height) = label.size();
+                let rect = Rect::new((row.rect.x + row.rect.width - PADDING - width).round(), text_top, width, height);
+                let tint = if self.is_on(row.control) { style.accent } else { style.secondary };
+                primitives.push(UiPrimitive::Image { rect, image: label.image.clone(), tint });
+            }
+            if let Some((track, fraction)) = row.slider {
+                let radius = SLIDER_HEIGHT / 2.0;
+                primitives.push(UiPrimitive::Rect { rect: track, color: style.track, corner_radius: radius });
+                let filled = Rect::new(track.x, track.y, (track.width * fraction).max(SLIDER_HEIGHT), track.height);
+                let color = if self.is_on(row.control) { style.accent } else { style.secondary };
+                primitives.push(UiPrimitive::Rect { rect: filled, color, corner_radius: radius });
+            }
+        }
+        primitives
+    }
+
+    /// Send a command to the backends
+    fn send(&self, command: QuickSettingsCommand) {
+        if let Some(commands) = &self.commands {
+            let _ = commands.send(command);
+        }
+    }
+
+    /// Name and value text of a control's row
+    fn texts(&self, control: QuickSettingsControl) -> (&'static str, String) {
+        let percent = |fraction: f32| format!("{}%", (fraction * 100.0).round());
+        let value = match control {
+            QuickSettingsControl::Wifi => match &self.state.wifi {
+                Some(wifi) if wifi.enabled => wifi.network.clone().unwrap_or_else(|| "On".to_string()),
+                _ => "Off".to_string(),
+            },
+            QuickSettingsControl::Bluetooth => match &self.state.bluetooth {
+                Some(bluetooth) if bluetooth.powered => match bluetooth.connected_devices.as_slice() {
+                    [] => "On".to_string(),
+                    [device] => device.clone(),
+                    devices => format!("{} devices", devices.len()),
+                },
+                _ => "Off".to_string(),
+            },
+            QuickSettingsControl::Volume => match self.state.audio {
+                Some(audio) if audio.muted => "Muted".to_string(),
+                Some(audio) => percent(audio.volume),
+                None => String::new(),
+            },
+            QuickSettingsControl::Brightness => self.state.backlight.as_ref().map(|b| percent(b.fraction())).unwrap_or_default(),
+        };
+        (control.label(), value)
+    }
+
+    /// Whether a control is on, drawn in the accent color
+    fn is_on(&self, control: QuickSettingsControl) -> bool {
+        match control {
+            QuickSettingsControl::Wifi => self.state.wifi.as_ref().is_some_and(|wifi| wifi.enabled),
+            QuickSettingsControl::Bluetooth => self.state.bluetooth.as_ref().is_some_and(|bluetooth| bluetooth.powered),
+            QuickSettingsControl::Volume => self.state.audio.is_some_and(|audio| !audio.muted),
+            QuickSettingsControl::Brightness => true,
+        }
+    }
+
+    /// Card and rows of the open panel
+    fn layout(&self) -> Option<(Rect, Vec<Row>)> {
+        let anchor = self.anchor?;
+        let controls = self.state.controls();
+        let height = PADDING * 2.0 + ROW_HEIGHT * controls.len().max(1) as f32;
+        let card = Rect::new((anchor.x + anchor.width - MARGIN - WIDTH).round(), anchor.y + MARGIN, WIDTH, height);
+        let rows = controls
+            .into_iter()
+            .enumerate()
+            .map(|(index, control)| {
+                let rect = Rect::new(card.x + PADDING / 2.0, card.y + PADDING + ROW_HEIGHT * index as f32, WIDTH - PADDING, ROW_HEIGHT);
+                let fraction = match control {
+                    QuickSettingsControl::Volume => self.state.audio.map(|audio| audio.volume),
+                    QuickSettingsControl::Brightness => self.state.backlight.as_ref().map(|backlight| backlight.fraction()),
+                    QuickSettingsControl::Wifi | QuickSettingsControl::Bluetooth => None,
+                };
+                let track = Rect::new(
+                    rect.x + PADDING,
+                    rect.y + rect.height - PADDING - SLIDER_HEIGHT,
+                    rect.width - PADDING * 2.0,
+                    SLIDER_HEIGHT,
+                );
+                Row { control, rect, slider: fraction.map(|fraction| (track, fraction.clamp(0.0, 1.0))) }
+            })
+            .collect();
+        Some((card, rows))
+    }
+
+    /// Rasterize the labels of the open panel, dropping the rest
+    fn update_labels(&mut self) {
+        let Some(font) = self.font.clone().filter(|_| self.is_open()) else {
+            self.labels.clear();
+            return;
+        };
+        let texts: Vec<String> = self
+            .state
+            .controls()
+            .into_iter()
+            .flat_map(|control| {
+                let (name, value) = self.texts(control);
+                [name.to_string(), value]
+            })
+            .filter(|text| !text.is_empty())
+            .collect();
+        self.labels.retain(|text, _| texts.contains(text));
+        for text in texts {
+            if let Entry::Vacant(entry) = self.labels.entry(text) {
+                if let Some(label) = Label::new(entry.key(), &font, LABEL_SIZE, [1.0; 4]) {
+                    entry.insert(label);
+                }
+            }
+        }
+    }
+}
+
+impl Default for QuickSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether `point` is on a slider, with some slack above and below the track
+fn track_hit(track: Rect, point: Vec2) -> bool {
+    point.x >= track.x && point.x <= track.x + track.width && (point.y - (track.y + track.height / 2.0)).abs() <= PADDING
+}
+
+/// Slider value at `point`
+fn slider_fraction(track: Rect, point: Vec2) -> f32 {
+    ((point.x - track.x) / track.width).clamp(0.0, 1.0)
+}
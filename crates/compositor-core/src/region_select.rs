@@ -0,0 +1,156 @@
+// Region screenshots
+//
+// A keybinding or an IPC request shows the region selection overlay over the
+// output under the pointer. The user drags out the region, adjusts it with
+// the pointer or the arrow keys, and confirms it with Enter to screenshot it
+// from the next frame, drawn without the overlay. The overlay's shapes come
+// from ui-framework; its size readout is rasterized whenever the text
+// changes.
+
+use compositor_utils::math::Rect;
+use compositor_utils::prelude::*;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use ui_framework::focus::{FocusAction, NavigationKey};
+use ui_framework::font::Font;
+use ui_framework::label::Label;
+use ui_framework::region_select::{OverlayPrimitive, RegionSelectStyle, RegionSelector};
+use vulkan_renderer::UiPrimitive;
+
+/// Height of the size readout's text in logical pixels
+const READOUT_SIZE: f32 = 13.0;
+
+/// Region selection for screenshots
+#[derive(Debug)]
+pub struct RegionSelection {
+    selector: RegionSelector,
+    style: RegionSelectStyle,
+    font: Option<Arc<Font>>,
+    /// Size readout and its label, rasterized for the current text
+    readout: Option<(String, Label)>,
+    sender: mpsc::UnboundedSender<()>,
+    receiver: mpsc::UnboundedReceiver<()>,
+}
+
+impl RegionSelection {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            selector: RegionSelector::default(),
+            style: RegionSelectStyle::default(),
+            font: None,
+            readout: None,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Channel for IPC to start selecting a region
+    pub fn sender(&self) -> mpsc::UnboundedSender<()> {
+        self.sender.clone()
+    }
+
+    /// Whether a selection was requested over IPC since the last call
+    pub fn take_requests(&mut self) -> bool {
+        let mut requested = false;
+        while self.receiver.try_recv().is_ok() {
+            requested = true;
+        }
+        requested
+    }
+
+    /// Set the font of the size readout
+    pub fn set_font(&mut self, font: Option<Arc<Font>>) {
+        self.font = font;
+        self.readout = None;
+        self.update_readout();
+    }
+
+    /// Whether the overlay is shown and takes pointer and keyboard input
+    pub fn is_active(&self) -> bool {
+        self.selector.is_active()
+    }
+
+    /// Show the overlay over `screen`; `windows` are the rectangles of
+    /// visible windows, topmost first, for clicks and snapping
+    pub fn start(&mut self, screen: Rect, windows: Vec<Rect>) {
+        self.selector.start(screen, windows);
+        self.update_readout();
+    }
+
+    pub fn pointer_pressed(&mut self, position: Vec2) {
+        self.selector.pointer_pressed(position, self.style.handle_size);
+        self.update_readout();
+    }
+
+    pub fn pointer_moved(&mut self, position: Vec2) {
+        if self.selector.is_active() {
+            self.selector.pointer_moved(position);
+            self.update_readout();
+        }
+    }
+
+    pub fn pointer_released(&mut self, position: Vec2) {
+        if self.selector.is_active() {
+            self.selector.pointer_released(position);
+            self.update_readout();
+        }
+    }
+
+    /// Apply a key; returns the region to capture once confirmed
+    ///
+    /// Arrows move the selection, or its right or bottom edge with `resize`,
+    /// by a pixel or by ten with `coarse`.
+    pub fn navigate(&mut self, key: NavigationKey, resize: bool, coarse: bool) -> Option<Rect> {
+        let action = self.selector.navigate(key, resize, coarse);
+        self.update_readout();
+        match action {
+            FocusAction::Activate(region) => Some(region),
+            FocusAction::Dismiss => {
+                info!("Region selection cancelled");
+                None
+            }
+            FocusAction::Moved(_) | FocusAction::Ignored => None,
+        }
+    }
+
+    /// Primitives drawing the overlay, back to front
+    pub fn primitives(&self) -> Vec<UiPrimitive> {
+        self.selector
+            .primitives(&self.style)
+            .into_iter()
+            .filter_map(|primitive| match primitive {
+                OverlayPrimitive::Fill { rect, color } => Some(UiPrimitive::Rect { rect, color, corner_radius: 0.0 }),
+                OverlayPrimitive::Outline { rect, width, color } => {
+                    Some(UiPrimitive::Outline { rect, width, color, corner_radius: 0.0 })
+                }
+                OverlayPrimitive::Label { text, position, color } => {
+                    let (_, label) = self.readout.as_ref().filter(|(readout, _)| *readout == text)?;
+                    let (width, height) = label.size();
+                    let rect = Rect::new(position.x, position.y, width, height);
+                    Some(UiPrimitive::Image { rect, image: label.image.clone(), tint: color })
+                }
+            })
+            .collect()
+    }
+
+    /// Rasterize the size readout when its text changed
+    fn update_readout(&mut self) {
+        let Some(font) = &self.font else { return };
+        let Some((text, _)) = self.selector.readout(&self.style) else {
+            self.readout = None;
+            return;
+        };
+        if self.readout.as_ref().is_some_and(|(readout, _)| *readout == text) {
+            return;
+        }
+        // White, tinted with the readout color when drawn
+        self.readout = Label::new(&text, font, READOUT_SIZE, [1.0; 4]).map(|label| (text, label));
+    }
+}
+
+impl Default for RegionSelection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,197 @@
+// Scene state handed to the render thread
+//
+// Outputs, windows, their stacking and their styles live in the Wayland state
+// on the event loop thread, while outputs are drawn on the render thread.
+// Every event loop iteration publishes what the renderer needs for the next
+// frames, and the render thread applies it before drawing, running a refresh
+// loop for each published output. Publishing a change wakes the render
+// thread to redraw; so does a running animation, which bumps the redraw
+// counter even when no surface state changed, e.g. while a wallpaper
+// cross-fades.
+//
+// A region waiting to be read back, e.g. for a screen copy, is published with
+// the output it is on. The render thread reads it back from the next frame of
+// that output and sends the pixels the other way, or that they could not be
+// read, so nobody waits on a frame that never comes.
+
+use compositor_utils::math::Rect;
+use smithay::reexports::calloop::ping::Ping;
+use tokio::sync::{mpsc, watch};
+use vulkan_renderer::{DimmingSettings, NeomorphicParams, ReadbackPixels, ReadbackRegion, SurfaceBlurRequest, UiPrimitive};
+
+/// An output the render thread draws
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOutput {
+    /// Output name, e.g. "DP-1"
+    pub name: String,
+    /// Geometry in global compositor space
+    pub geometry: Rect,
+    /// Refresh rate in millihertz
+    pub refresh_mhz: u32,
+    /// Surfaces the output does not show, e.g. windows on other outputs
+    pub offscreen_surfaces: Vec<u32>,
+    /// Region of the output's next frame to read back
+    pub readback: Option<ReadbackRegion>,
+}
+
+/// A region of a frame read back by the render thread
+#[derive(Debug, Clone)]
+pub enum FrameReadback {
+    Read(ReadbackPixels),
+    /// The region could not be read, e.g. as the swapchain does not allow it
+    Failed(ReadbackRegion),
+}
+
+/// Surface state the renderer draws with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderState {
+    /// Connected outputs
+    pub outputs: Vec<RenderOutput>,
+    /// Surfaces from bottom to top
+    pub stacking_order: Vec<u32>,
+    /// Windows on workspaces not shown right now, whose uploads may wait
+    pub hidden_surfaces: Vec<u32>,
+    /// Windows of apps with a scale override, scaled to their output with filtering
+    pub rescaled_surfaces: Vec<u32>,
+    /// Corner radius of surfaces whose class overrides the default
+    pub corner_radii: Vec<(u32, f32)>,
+    /// Shadow and highlight of each surface with the neomorphism theme
+    pub neomorphic_surfaces: Vec<(u32, NeomorphicParams)>,
+    /// Width and color of borders around surfaces, from their class or as
+    /// they are urgent
+    pub borders: Vec<(u32, f32, [f32; 4])>,
+    /// Drop shadow intensity of surfaces that cast one (0.0 - 1.0)
+    pub shadows: Vec<(u32, f32)>,
+    /// Surface with keyboard focus, which other surfaces are dimmed against
+    pub focused_surface: Option<u32>,
+    /// Dimming of surfaces without keyboard focus
+    pub dimming: DimmingSettings,
+    /// Where windows and layer surfaces are, in global coordinates
+    pub surface_geometry: Vec<(u32, Rect)>,
+    /// Blur behind surfaces matched by a blur rule, its regions relative to
+    /// their geometry
+    pub blur: Vec<(u32, SurfaceBlurRequest)>,
+    /// Wallpapers of the outputs' active workspaces below the surfaces,
+    /// back to front, in global coordinates
+    pub wallpaper: Vec<UiPrimitive>,
+    /// Compositor-drawn UI over the surfaces, back to front, in global coordinates
+    pub ui: Vec<UiPrimitive>,
+    /// Counts redraws requested without a change to the surface state
+    pub redraw: u64,
+}
+
+/// Publishes the render state to the render thread
+#[derive(Debug)]
+pub struct RenderStateChannel {
+    sender: watch::Sender<RenderState>,
+    /// Wakes the render thread once it runs
+    waker: Option<Ping>,
+    readback_sender: mpsc::UnboundedSender<FrameReadback>,
+    readback_receiver: mpsc::UnboundedReceiver<FrameReadback>,
+}
+
+impl RenderStateChannel {
+    pub fn new() -> Self {
+        let (readback_sender, readback_receiver) = mpsc::unbounded_channel();
+        Self {
+            sender: watch::channel(RenderState::default()).0,
+            waker: None,
+            readback_sender,
+            readback_receiver,
+        }
+    }
+
+    /// Channel for the render thread to follow the state
+    pub fn subscribe(&self) -> watch::Receiver<RenderState> {
+        self.sender.subscribe()
+    }
+
+    /// Channel for the render thread to report read back regions
+    pub fn readback_sender(&self) -> mpsc::UnboundedSender<FrameReadback> {
+        self.readback_sender.clone()
+    }
+
+    /// Regions read back since the last call
+    pub fn take_readbacks(&mut self) -> Vec<FrameReadback> {
+        std::iter::from_fn(|| self.readback_receiver.try_recv().ok()).collect()
+    }
+
+    /// Wake the render thread with `waker` when the state changes
+    pub fn set_waker(&mut self, waker: Ping) {
+        self.waker = Some(waker);
+    }
+
+    /// Wake the render thread, e.g. to upload committed surface content
+    pub fn wake(&self) {
+        if let Some(ref waker) = self.waker {
+            waker.ping();
+        }
+    }
+
+    /// Publish the state if it changed, or `redraw` is set, and wake the
+    /// render thread to draw it
+    pub fn publish(&self, mut state: RenderState, redraw: bool) {
+        let changed = self.sender.send_if_modified(|published| {
+            state.redraw = published.redraw.wrapping_add(redraw as u64);
+            if *published == state {
+                return false;
+            }
+            *published = state;
+            true
+        });
+        if changed {
+            self.wake();
+        }
+    }
+}
+
+impl Default for RenderStateChannel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What changed on the output the swapchain last presented, for incremental
+/// present
+///
+/// Outputs share the swapchain, so only damage since the previous frame of
+/// the same output can be described; frames of any other output, and frames
+/// after anything but surface content changed, are fully damaged.
+#[derive(Debug, Default)]
+pub struct OutputDamage {
+    /// Output drawn last
+    output: Option<String>,
+    /// Regions changed on it since, in global coordinates; `None` if unknown
+    regions: Option<Vec<Rect>>,
+}
+
+impl OutputDamage {
+    /// Mark everything as changed, e.g. after the scene changed
+    pub fn damage_all(&mut self) {
+        self.regions = None;
+    }
+
+    /// Add regions surface commits changed, or everything for `None`
+    pub fn add(&mut self, damage: Option<Vec<Rect>>, outputs: &[RenderOutput]) {
+        let geometry = outputs
+            .iter()
+            .find(|output| self.output.as_ref() == Some(&output.name))
+            .map(|output| output.geometry);
+        match (damage, geometry, self.regions.as_mut()) {
+            (Some(damage), Some(geometry), Some(regions)) => {
+                regions.extend(damage.into_iter().filter(|rect| rect.intersects(&geometry)));
+            }
+            (None, _, _) => self.regions = None,
+            _ => {}
+        }
+    }
+
+    /// Take the regions changed on `output` for the frame about to be drawn;
+    /// `None` if its whole frame changed
+    pub fn take(&mut self, output: &str) -> Option<Vec<Rect>> {
+        let regions = if self.output.as_deref() == Some(output) { self.regions.take() } else { None };
+        self.output = Some(output.to_string());
+        self.regions = Some(Vec::new());
+        regions
+    }
+}
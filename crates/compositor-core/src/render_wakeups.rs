@@ -0,0 +1,104 @@
+// Render thread wakeups
+//
+// The render thread sleeps in a calloop event loop until the next output
+// refresh is due, a submitted frame finishes on the GPU, or another thread
+// wakes it, e.g. to shut down. Finished frames are signalled through sync
+// files exported from Vulkan fences, so the thread neither polls nor blocks
+// while the GPU renders, and refreshes are timed by the loop's timers rather
+// than fixed sleeps.
+
+use crate::frame_scheduler::OutputId;
+use compositor_utils::prelude::*;
+use smithay::reexports::calloop::generic::Generic;
+use smithay::reexports::calloop::ping::PingSource;
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
+use smithay::reexports::calloop::{EventLoop, Interest, Mode, PostAction, RegistrationToken};
+use std::os::fd::OwnedFd;
+use std::time::{Duration, Instant};
+
+/// A submitted frame the GPU is still rendering
+#[derive(Debug, Clone, Copy)]
+pub struct FrameInFlight {
+    pub output: OutputId,
+    /// When rendering of the frame started
+    pub submitted: Instant,
+}
+
+/// What woke the render thread
+#[derive(Debug, Default)]
+struct Fired {
+    refresh: bool,
+    completed: Vec<FrameInFlight>,
+}
+
+/// Event loop the render thread sleeps in between frames
+pub struct RenderWakeups {
+    event_loop: EventLoop<'static, Fired>,
+    /// Timer for the next refresh that is due
+    refresh: Option<(Instant, RegistrationToken)>,
+    fired: Fired,
+}
+
+impl RenderWakeups {
+    /// Create the loop; pinging the other end of `wake` wakes the thread
+    pub fn new(wake: PingSource) -> Result<Self> {
+        let event_loop = EventLoop::try_new()
+            .map_err(|e| CompositorError::runtime(format!("Failed to create render event loop: {}", e)))?;
+        event_loop
+            .handle()
+            .insert_source(wake, |(), &mut (), _| {})
+            .map_err(|e| CompositorError::runtime(format!("Failed to insert render wakeup source: {}", e)))?;
+        Ok(Self {
+            event_loop,
+            refresh: None,
+            fired: Fired::default(),
+        })
+    }
+
+    /// Wake up for the refresh due at `deadline`, or for none
+    pub fn set_refresh_deadline(&mut self, deadline: Option<Instant>) -> Result<()> {
+        if self.refresh.map(|(armed, _)| armed) == deadline {
+            return Ok(());
+        }
+        if let Some((_, token)) = self.refresh.take() {
+            self.event_loop.handle().remove(token);
+        }
+        if let Some(deadline) = deadline {
+            let token = self
+                .event_loop
+                .handle()
+                .insert_source(Timer::from_deadline(deadline), |_, &mut (), fired| {
+                    fired.refresh = true;
+                    TimeoutAction::Drop
+                })
+                .map_err(|e| CompositorError::runtime(format!("Failed to insert refresh timer: {}", e)))?;
+            self.refresh = Some((deadline, token));
+        }
+        Ok(())
+    }
+
+    /// Wake up once the sync file of a submitted frame becomes readable
+    pub fn watch_frame(&mut self, frame: FrameInFlight, completion: OwnedFd) -> Result<()> {
+        self.event_loop
+            .handle()
+            .insert_source(Generic::new(completion, Interest::READ, Mode::OneShot), move |_, _, fired| {
+                fired.completed.push(frame);
+                Ok(PostAction::Remove)
+            })
+            .map_err(|e| CompositorError::runtime(format!("Failed to watch frame completion: {}", e)))?;
+        Ok(())
+    }
+
+    /// Sleep until woken or `timeout` passes; returns the frames the GPU
+    /// finished in the meantime
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<Vec<FrameInFlight>> {
+        self.event_loop
+            .dispatch(timeout, &mut self.fired)
+            .map_err(|e| CompositorError::runtime(format!("Render event loop error: {}", e)))?;
+        // A fired timer is gone; the next deadline arms a new one
+        if std::mem::take(&mut self.fired.refresh) {
+            self.refresh = None;
+        }
+        Ok(std::mem::take(&mut self.fired.completed))
+    }
+}
@@ -0,0 +1,319 @@
+// Interactive window resize
+//
+// Started from an xdg_toplevel resize request. Pointer motion sets the size
+// the window should have, but a configure is sent at most once per refresh of
+// the window's output and only after the client has acked and committed the
+// previous one. A slow client, e.g. at 4K, then renders the latest size
+// instead of working through a queue of stale ones. Resizing from the top or
+// left edge also moves the window; the move is applied when the client
+// commits the size it acked, so the opposite edge stays still.
+
+use crate::wayland::WaylandServerState;
+use smithay::desktop::Window;
+use smithay::input::pointer::{
+    AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+    GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+    GestureSwipeUpdateEvent, GrabStartData, MotionEvent, PointerGrab, PointerInnerHandle, RelativeMotionEvent,
+};
+use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::ResizeEdge;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::utils::{Logical, Point, Serial, Size};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy)]
+struct InFlightConfigure {
+    serial: Serial,
+    size: Size<i32, Logical>,
+    acked: bool,
+}
+
+/// Throttled configures of a window being resized
+#[derive(Debug)]
+pub struct InteractiveResize {
+    pub window: Window,
+    edges: ResizeEdge,
+    initial_location: Point<i32, Logical>,
+    initial_size: Size<i32, Logical>,
+    min_size: Size<i32, Logical>,
+    max_size: Size<i32, Logical>,
+    /// Minimum time between configures, the refresh interval of the output
+    interval: Duration,
+    /// Size the pointer asks for that has not been sent yet
+    requested: Option<Size<i32, Logical>>,
+    /// Configure the client has not committed yet
+    in_flight: Option<InFlightConfigure>,
+    /// Size of the last configure sent
+    last_size: Size<i32, Logical>,
+    last_configure: Option<Instant>,
+    /// Whether the pointer button was released
+    released: bool,
+}
+
+impl InteractiveResize {
+    /// Start resizing a window from the given edges
+    ///
+    /// `min_size` and `max_size` are the client's limits, 0 meaning unlimited.
+    pub fn new(
+        window: Window,
+        edges: ResizeEdge,
+        location: Point<i32, Logical>,
+        size: Size<i32, Logical>,
+        (min_size, max_size): (Size<i32, Logical>, Size<i32, Logical>),
+        interval: Duration,
+    ) -> Self {
+        Self {
+            window,
+            edges,
+            initial_location: location,
+            initial_size: size,
+            min_size,
+            max_size,
+            interval,
+            requested: None,
+            in_flight: None,
+            last_size: size,
+            last_configure: None,
+            released: false,
+        }
+    }
+
+    /// Whether `surface` is the window being resized
+    pub fn is_window(&self, surface: &WlSurface) -> bool {
+        self.window.toplevel().is_some_and(|toplevel| toplevel.wl_surface() == surface)
+    }
+
+    /// Ask for the size the pointer moved the grabbed edges to
+    pub fn pointer_moved(&mut self, delta: Point<f64, Logical>) {
+        let delta = delta.to_i32_round::<i32>();
+        let mut size = self.initial_size;
+        if self.has_edge(ResizeEdge::Left) {
+            size.w -= delta.x;
+        } else if self.has_edge(ResizeEdge::Right) {
+            size.w += delta.x;
+        }
+        if self.has_edge(ResizeEdge::Top) {
+            size.h -= delta.y;
+        } else if self.has_edge(ResizeEdge::Bottom) {
+            size.h += delta.y;
+        }
+        self.requested = Some(self.clamp(size));
+    }
+
+    /// Send the remaining size with the final configure once the button is
+    /// released
+    pub fn release(&mut self) {
+        self.released = true;
+        // The final configure also tells the client the resize is over
+        self.requested = Some(self.requested.unwrap_or(self.last_size));
+    }
+
+    /// Whether the pointer button was released
+    pub fn is_released(&self) -> bool {
+        self.released
+    }
+
+    /// Size to configure now, if one is waiting and the client is ready
+    pub fn configure_due(&self, now: Instant) -> Option<Size<i32, Logical>> {
+        let requested = self.requested?;
+        if self.in_flight.is_some() {
+            return None;
+        }
+        let throttled = !self.released && self.last_configure.is_some_and(|last| now < last + self.interval);
+        (!throttled).then_some(requested)
+    }
+
+    /// Record a configure sent with the size `configure_due` returned
+    pub fn configure_sent(&mut self, serial: Serial, size: Size<i32, Logical>, now: Instant) {
+        self.requested = None;
+        self.in_flight = Some(InFlightConfigure { serial, size, acked: false });
+        self.last_size = size;
+        self.last_configure = Some(now);
+    }
+
+    /// Handle the client acking a configure
+    pub fn configure_acked(&mut self, serial: Serial) {
+        if let Some(in_flight) = &mut self.in_flight {
+            // Later configures carry the resize size too
+            in_flight.acked |= serial.is_no_older_than(&in_flight.serial);
+        }
+    }
+
+    /// Handle a commit of the window; returns where to place the window when
+    /// the commit applies the acked size
+    pub fn committed(&mut self) -> Option<Point<i32, Logical>> {
+        let in_flight = self.in_flight.filter(|in_flight| in_flight.acked)?;
+        self.in_flight = None;
+        let mut location = self.initial_location;
+        if self.has_edge(ResizeEdge::Left) {
+            location.x += self.initial_size.w - in_flight.size.w;
+        }
+        if self.has_edge(ResizeEdge::Top) {
+            location.y += self.initial_size.h - in_flight.size.h;
+        }
+        Some(location)
+    }
+
+    /// Whether the final configure after release has been committed
+    pub fn is_finished(&self) -> bool {
+        self.released && self.requested.is_none() && self.in_flight.is_none()
+    }
+
+    /// When a throttled configure becomes due
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if self.requested.is_none() || self.in_flight.is_some() {
+            return None;
+        }
+        Some(self.last_configure.map_or_else(Instant::now, |last| last + self.interval))
+    }
+
+    fn has_edge(&self, edge: ResizeEdge) -> bool {
+        (self.edges as u32) & (edge as u32) != 0
+    }
+
+    fn clamp(&self, size: Size<i32, Logical>) -> Size<i32, Logical> {
+        let limit = |value: i32, min: i32, max: i32| {
+            let value = value.max(min.max(1));
+            if max > 0 {
+                value.min(max)
+            } else {
+                value
+            }
+        };
+        (limit(size.w, self.min_size.w, self.max_size.w), limit(size.h, self.min_size.h, self.max_size.h)).into()
+    }
+}
+
+/// Pointer grab resizing a window
+pub struct ResizeSurfaceGrab {
+    pub start_data: GrabStartData<WaylandServerState>,
+}
+
+impl PointerGrab<WaylandServerState> for ResizeSurfaceGrab {
+    fn motion(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        _focus: Option<(WlSurface, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // No client gets pointer focus while a window is resized
+        handle.motion(data, None, event);
+        data.resize_window_motion(event.location - self.start_data.location);
+    }
+
+    fn relative_motion(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        focus: Option<(WlSurface, Point<f64, Logical>)>,
+        event: &RelativeMotionEvent,
+    ) {
+        handle.relative_motion(data, focus, event);
+    }
+
+    fn button(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &ButtonEvent,
+    ) {
+        handle.button(data, event);
+        if handle.current_pressed().is_empty() {
+            // No more buttons are pressed, release the grab
+            handle.unset_grab(self, data, event.serial, event.time, true);
+        }
+    }
+
+    fn axis(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        details: AxisFrame,
+    ) {
+        handle.axis(data, details);
+    }
+
+    fn frame(&mut self, data: &mut WaylandServerState, handle: &mut PointerInnerHandle<'_, WaylandServerState>) {
+        handle.frame(data);
+    }
+
+    fn gesture_swipe_begin(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GestureSwipeBeginEvent,
+    ) {
+        handle.gesture_swipe_begin(data, event);
+    }
+
+    fn gesture_swipe_update(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GestureSwipeUpdateEvent,
+    ) {
+        handle.gesture_swipe_update(data, event);
+    }
+
+    fn gesture_swipe_end(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GestureSwipeEndEvent,
+    ) {
+        handle.gesture_swipe_end(data, event);
+    }
+
+    fn gesture_pinch_begin(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GesturePinchBeginEvent,
+    ) {
+        handle.gesture_pinch_begin(data, event);
+    }
+
+    fn gesture_pinch_update(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GesturePinchUpdateEvent,
+    ) {
+        handle.gesture_pinch_update(data, event);
+    }
+
+    fn gesture_pinch_end(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GesturePinchEndEvent,
+    ) {
+        handle.gesture_pinch_end(data, event);
+    }
+
+    fn gesture_hold_begin(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GestureHoldBeginEvent,
+    ) {
+        handle.gesture_hold_begin(data, event);
+    }
+
+    fn gesture_hold_end(
+        &mut self,
+        data: &mut WaylandServerState,
+        handle: &mut PointerInnerHandle<'_, WaylandServerState>,
+        event: &GestureHoldEndEvent,
+    ) {
+        handle.gesture_hold_end(data, event);
+    }
+
+    fn start_data(&self) -> &GrabStartData<WaylandServerState> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, data: &mut WaylandServerState) {
+        data.finish_window_resize();
+    }
+}
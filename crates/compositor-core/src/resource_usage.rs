@@ -0,0 +1,253 @@
+// Per-client resource accounting
+//
+// Surfaces, attached buffer memory, GPU texture copies and frame callback
+// requests are tallied per client connection. Usage is published for IPC once
+// per second; clients over a configured soft limit are logged and, if the
+// policy says so, have their frame callbacks slowed down.
+
+use compositor_utils::prelude::*;
+use config::{ClientLimitsConfig, ResourceLimitAction};
+use ipc::protocol::{ClientProcessInfo, ClientResourceUsage, ResourceLimit};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use wayland_server::backend::{ClientId, ObjectId};
+
+/// Interval the callback rate is measured over and usage is published at
+const USAGE_WINDOW: Duration = Duration::from_secs(1);
+
+const MEGABYTE: u64 = 1024 * 1024;
+
+/// Memory held for a surface's current buffer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferUsage {
+    /// Size of the client's buffer
+    pub buffer_bytes: u64,
+    /// Size of the compositor's GPU copy; 0 for imported (zero-copy) buffers
+    pub texture_bytes: u64,
+}
+
+#[derive(Debug, Default)]
+struct SurfaceAccount {
+    buffer: BufferUsage,
+    /// Callbacks queued on the surface and not sent yet
+    queued_callbacks: usize,
+    last_throttled_callback: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct ClientAccount {
+    id: u64,
+    process: Option<ClientProcessInfo>,
+    surfaces: HashMap<ObjectId, SurfaceAccount>,
+    /// Times frame callbacks were requested within the usage window
+    callbacks: VecDeque<Instant>,
+    exceeded: Vec<ResourceLimit>,
+}
+
+impl ClientAccount {
+    fn buffer_bytes(&self) -> u64 {
+        self.surfaces.values().map(|surface| surface.buffer.buffer_bytes).sum()
+    }
+
+    fn texture_bytes(&self) -> u64 {
+        self.surfaces.values().map(|surface| surface.buffer.texture_bytes).sum()
+    }
+
+    fn callbacks_per_second(&self) -> f32 {
+        self.callbacks.len() as f32 / USAGE_WINDOW.as_secs_f32()
+    }
+}
+
+/// Resource usage of all client connections
+#[derive(Debug)]
+pub struct ClientResourceTracker {
+    config: ClientLimitsConfig,
+    clients: HashMap<ClientId, ClientAccount>,
+    next_id: u64,
+    last_published: Option<Instant>,
+    publisher: watch::Sender<Vec<ClientResourceUsage>>,
+}
+
+impl ClientResourceTracker {
+    /// Create a tracker with the given soft limits
+    pub fn new(config: ClientLimitsConfig) -> Self {
+        Self {
+            config,
+            clients: HashMap::new(),
+            next_id: 1,
+            last_published: None,
+            publisher: watch::channel(Vec::new()).0,
+        }
+    }
+
+    /// Apply new soft limits
+    pub fn set_config(&mut self, config: ClientLimitsConfig) {
+        self.config = config;
+    }
+
+    /// Channel for IPC to read published usage
+    pub fn subscribe(&self) -> watch::Receiver<Vec<ClientResourceUsage>> {
+        self.publisher.subscribe()
+    }
+
+    fn account(&mut self, client: ClientId, process: impl FnOnce() -> Option<ClientProcessInfo>) -> &mut ClientAccount {
+        let next_id = &mut self.next_id;
+        self.clients.entry(client).or_insert_with(|| {
+            let id = *next_id;
+            *next_id += 1;
+            ClientAccount {
+                id,
+                process: process(),
+                surfaces: HashMap::new(),
+                callbacks: VecDeque::new(),
+                exceeded: Vec::new(),
+            }
+        })
+    }
+
+    /// Record a new surface
+    pub fn surface_created(
+        &mut self,
+        client: ClientId,
+        process: impl FnOnce() -> Option<ClientProcessInfo>,
+        surface: ObjectId,
+    ) {
+        self.account(client, process).surfaces.entry(surface).or_default();
+    }
+
+    /// Record a surface commit
+    ///
+    /// `buffer` is `None` when the commit kept the previous buffer.
+    /// `queued_callbacks` is the number of frame callbacks waiting on the
+    /// surface after the commit, including ones counted at earlier commits.
+    pub fn surface_committed(
+        &mut self,
+        client: ClientId,
+        process: impl FnOnce() -> Option<ClientProcessInfo>,
+        surface: ObjectId,
+        buffer: Option<BufferUsage>,
+        queued_callbacks: usize,
+        now: Instant,
+    ) {
+        let account = self.account(client, process);
+        let surface = account.surfaces.entry(surface).or_default();
+        if let Some(buffer) = buffer {
+            surface.buffer = buffer;
+        }
+        let new_callbacks = queued_callbacks.saturating_sub(surface.queued_callbacks);
+        surface.queued_callbacks = queued_callbacks;
+        account.callbacks.extend(std::iter::repeat_n(now, new_callbacks));
+    }
+
+    /// Forget a destroyed surface
+    pub fn surface_destroyed(&mut self, surface: &ObjectId) {
+        for account in self.clients.values_mut() {
+            if account.surfaces.remove(surface).is_some() {
+                break;
+            }
+        }
+    }
+
+    /// Whether the queued frame callbacks of a surface should be sent now
+    ///
+    /// Always true unless the client is over a limit and the policy is to
+    /// throttle, in which case callbacks are spaced to the throttled rate.
+    /// When true, the caller is expected to send every queued callback.
+    pub fn frame_callbacks_due(&mut self, client: &ClientId, surface: &ObjectId, now: Instant) -> bool {
+        let throttled = self.is_throttled(client);
+        let interval = Duration::from_secs(1) / self.config.throttled_callback_rate.max(1);
+        let Some(surface) = self.clients.get_mut(client).and_then(|account| account.surfaces.get_mut(surface)) else {
+            return true;
+        };
+        if throttled {
+            if surface.last_throttled_callback.is_some_and(|last| now.duration_since(last) < interval) {
+                return false;
+            }
+            surface.last_throttled_callback = Some(now);
+        }
+        surface.queued_callbacks = 0;
+        true
+    }
+
+    /// Whether a client's frame callbacks are being throttled
+    pub fn is_throttled(&self, client: &ClientId) -> bool {
+        self.config.action == ResourceLimitAction::Throttle
+            && self.clients.get(client).is_some_and(|account| !account.exceeded.is_empty())
+    }
+
+    /// Connection number of a client, once it has created a surface
+    pub fn connection_id(&self, client: &ClientId) -> Option<u64> {
+        self.clients.get(client).map(|account| account.id)
+    }
+
+    /// Check limits and publish usage for IPC, at most once per usage window
+    ///
+    /// Clients for which `is_alive` returns false have disconnected and are
+    /// dropped. Returns whether usage was published.
+    pub fn publish(&mut self, now: Instant, is_alive: impl Fn(&ClientId) -> bool) -> bool {
+        if self.last_published.is_some_and(|last| now.duration_since(last) < USAGE_WINDOW) {
+            return false;
+        }
+        self.last_published = Some(now);
+        self.clients.retain(|client, _| is_alive(client));
+
+        let mut usage = Vec::with_capacity(self.clients.len());
+        for account in self.clients.values_mut() {
+            while account
+                .callbacks
+                .front()
+                .is_some_and(|&time| now.duration_since(time) > USAGE_WINDOW)
+            {
+                account.callbacks.pop_front();
+            }
+
+            let exceeded = exceeded_limits(&self.config, account);
+            if let Some(limit) = exceeded.iter().find(|limit| !account.exceeded.contains(limit)) {
+                warn!("Client {} is over its {:?} soft limit", describe(account), limit);
+            }
+            account.exceeded = exceeded;
+
+            usage.push(ClientResourceUsage {
+                id: account.id,
+                process: account.process.clone(),
+                surfaces: account.surfaces.len() as u32,
+                buffer_bytes: account.buffer_bytes(),
+                texture_bytes: account.texture_bytes(),
+                callbacks_per_second: account.callbacks_per_second(),
+                exceeded_limits: account.exceeded.clone(),
+                throttled: self.config.action == ResourceLimitAction::Throttle && !account.exceeded.is_empty(),
+            });
+        }
+        usage.sort_by_key(|usage| usage.id);
+        self.publisher.send_replace(usage);
+        true
+    }
+}
+
+/// Soft limits a client is over; limits set to 0 are ignored
+fn exceeded_limits(config: &ClientLimitsConfig, account: &ClientAccount) -> Vec<ResourceLimit> {
+    let over = |limit: u32, value: u64| limit > 0 && value > u64::from(limit);
+    let mut exceeded = Vec::new();
+    if over(config.max_surfaces, account.surfaces.len() as u64) {
+        exceeded.push(ResourceLimit::Surfaces);
+    }
+    if over(config.max_buffer_memory_mb, account.buffer_bytes() / MEGABYTE) {
+        exceeded.push(ResourceLimit::BufferMemory);
+    }
+    if over(config.max_texture_memory_mb, account.texture_bytes() / MEGABYTE) {
+        exceeded.push(ResourceLimit::TextureMemory);
+    }
+    if over(config.max_callback_rate, account.callbacks.len() as u64) {
+        exceeded.push(ResourceLimit::CallbackRate);
+    }
+    exceeded
+}
+
+fn describe(account: &ClientAccount) -> String {
+    match &account.process {
+        Some(ClientProcessInfo { pid, executable: Some(executable), .. }) => format!("#{} {} (pid {})", account.id, executable, pid),
+        Some(ClientProcessInfo { pid, .. }) => format!("#{} (pid {})", account.id, pid),
+        None => format!("#{}", account.id),
+    }
+}
@@ -0,0 +1,166 @@
+// Unresponsive application detection
+//
+// Shell clients are pinged (xdg_wm_base.ping) periodically and right after a
+// window close request. A client that does not pong within the timeout is
+// reported once as unresponsive, so the compositor can offer to keep waiting
+// or force quit it. Choosing to wait hides the dialog for another timeout.
+
+use config::UnresponsiveDetectionConfig;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use wayland_server::backend::ClientId;
+
+/// User's answer to the "application is not responding" dialog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnresponsiveChoice {
+    /// Keep waiting for the application
+    Wait,
+    /// Kill the application's process
+    ForceQuit,
+}
+
+#[derive(Debug)]
+struct ClientPing {
+    /// Unanswered ping and when it was sent
+    pending: Option<Instant>,
+    last_answer: Instant,
+    unresponsive: bool,
+    /// The user chose to wait; the dialog stays hidden until then
+    waiting_until: Option<Instant>,
+}
+
+impl ClientPing {
+    fn new(now: Instant) -> Self {
+        Self {
+            pending: None,
+            last_answer: now,
+            unresponsive: false,
+            waiting_until: None,
+        }
+    }
+}
+
+/// Tracks ping round trips of shell clients
+#[derive(Debug)]
+pub struct ResponsivenessMonitor {
+    config: UnresponsiveDetectionConfig,
+    clients: HashMap<ClientId, ClientPing>,
+}
+
+impl ResponsivenessMonitor {
+    /// Create a monitor with the given configuration
+    pub fn new(config: UnresponsiveDetectionConfig) -> Self {
+        Self {
+            config,
+            clients: HashMap::new(),
+        }
+    }
+
+    /// Apply new configuration
+    pub fn set_config(&mut self, config: UnresponsiveDetectionConfig) {
+        self.config = config;
+        if !self.config.enabled {
+            self.clients.clear();
+        }
+    }
+
+    /// Clients that should be pinged now, out of the clients that have windows
+    ///
+    /// The returned clients are recorded as pinged at `now`. Clients missing
+    /// from `clients` have disconnected or closed all windows and are forgotten.
+    pub fn pings_due(&mut self, clients: &HashSet<ClientId>, now: Instant) -> Vec<ClientId> {
+        self.clients.retain(|client, _| clients.contains(client));
+        if !self.config.enabled {
+            return Vec::new();
+        }
+
+        let interval = Duration::from_millis(self.config.ping_interval);
+        let mut due = Vec::new();
+        for client in clients {
+            let ping = self.clients.entry(client.clone()).or_insert_with(|| ClientPing::new(now));
+            if ping.pending.is_none() && now.duration_since(ping.last_answer) >= interval {
+                ping.pending = Some(now);
+                due.push(client.clone());
+            }
+        }
+        due
+    }
+
+    /// Ping a client immediately, e.g. after asking it to close a window
+    ///
+    /// Returns whether a ping should be sent; a pending ping is kept as is.
+    pub fn ping_now(&mut self, client: ClientId, now: Instant) -> bool {
+        if !self.config.enabled {
+            return false;
+        }
+        let ping = self.clients.entry(client).or_insert_with(|| ClientPing::new(now));
+        if ping.pending.is_some() {
+            return false;
+        }
+        ping.pending = Some(now);
+        true
+    }
+
+    /// Record a pong; returns whether the client had been reported unresponsive
+    pub fn pong(&mut self, client: &ClientId, now: Instant) -> bool {
+        let Some(ping) = self.clients.get_mut(client) else {
+            return false;
+        };
+        ping.pending = None;
+        ping.last_answer = now;
+        ping.waiting_until = None;
+        std::mem::take(&mut ping.unresponsive)
+    }
+
+    /// Clients whose dialog should be shown now
+    ///
+    /// Each client is returned once when its ping times out, and again when a
+    /// wait chosen by the user runs out without an answer.
+    pub fn poll(&mut self, now: Instant) -> Vec<ClientId> {
+        let timeout = Duration::from_millis(self.config.ping_timeout);
+        let mut newly_unresponsive = Vec::new();
+        for (client, ping) in &mut self.clients {
+            let Some(sent) = ping.pending else { continue };
+            let show = match (ping.unresponsive, ping.waiting_until) {
+                (false, _) => now.duration_since(sent) >= timeout,
+                (true, Some(until)) => now >= until,
+                (true, None) => false,
+            };
+            if show {
+                ping.unresponsive = true;
+                ping.waiting_until = None;
+                newly_unresponsive.push(client.clone());
+            }
+        }
+        newly_unresponsive
+    }
+
+    /// The user chose to keep waiting for a client
+    pub fn wait(&mut self, client: &ClientId, now: Instant) {
+        if let Some(ping) = self.clients.get_mut(client) {
+            ping.waiting_until = Some(now + Duration::from_millis(self.config.ping_timeout));
+        }
+    }
+
+    /// Whether a client is currently considered unresponsive
+    pub fn is_unresponsive(&self, client: &ClientId) -> bool {
+        self.clients.get(client).is_some_and(|ping| ping.unresponsive)
+    }
+
+    /// Earliest time a ping is due or times out
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if !self.config.enabled {
+            return None;
+        }
+        let interval = Duration::from_millis(self.config.ping_interval);
+        let timeout = Duration::from_millis(self.config.ping_timeout);
+        self.clients
+            .values()
+            .filter_map(|ping| match (ping.pending, ping.unresponsive) {
+                (None, _) => Some(ping.last_answer + interval),
+                (Some(sent), false) => Some(sent + timeout),
+                (Some(_), true) => ping.waiting_until,
+            })
+            .min()
+    }
+}
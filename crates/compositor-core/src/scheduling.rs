@@ -0,0 +1,217 @@
+// Thread scheduling priorities
+//
+// The render and input threads ask for round-robin realtime scheduling so
+// frames and input stay on time while other processes load the CPU. Within
+// RLIMIT_RTPRIO, or with CAP_SYS_NICE, the thread sets SCHED_RR itself;
+// otherwise it falls back to a negative nice value. SCHED_RESET_ON_FORK keeps
+// applications the compositor launches from inheriting the priority.
+//
+// Threads can also be pinned to CPUs and to a NUMA node, whose memory they
+// then prefer. Pinning is best effort: where the kernel or the system does
+// not support it the thread keeps running anywhere.
+
+use compositor_utils::prelude::*;
+use config::{SchedulingConfig, ThreadAffinity};
+use std::collections::BTreeSet;
+use std::io;
+
+/// set_mempolicy mode preferring one node and falling back to others
+const MPOL_PREFERRED: libc::c_int = 1;
+
+/// Priority a thread ended up with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadPriority {
+    /// SCHED_RR at the given priority
+    Realtime(u32),
+    /// Normal scheduling at the given nice value
+    Nice(i32),
+    /// Nothing could be raised
+    Unchanged,
+}
+
+/// Raise the calling thread's priority as configured
+///
+/// `role` names the thread in logs, e.g. "render".
+pub fn raise_thread_priority(config: &SchedulingConfig, role: &str) -> ThreadPriority {
+    let priority = if config.realtime {
+        set_realtime(config.realtime_priority)
+            .inspect_err(|e| debug!("Realtime scheduling refused for {} thread: {}", role, e))
+            .ok()
+    } else {
+        None
+    };
+    // TODO: Ask RealtimeKit (org.freedesktop.RealtimeKit1 MakeThreadRealtime,
+    // then MakeThreadHighPriority) on the system bus before falling back to
+    // nice once a D-Bus client dependency is available
+    let priority = priority.or_else(|| {
+        set_nice(config.nice)
+            .inspect_err(|e| debug!("Nice value {} refused for {} thread: {}", config.nice, role, e))
+            .ok()
+    });
+
+    let priority = priority.unwrap_or(ThreadPriority::Unchanged);
+    match priority {
+        ThreadPriority::Realtime(priority) => info!("{} thread runs at realtime priority {}", role, priority),
+        ThreadPriority::Nice(nice) => info!("{} thread runs at nice {}", role, nice),
+        ThreadPriority::Unchanged => warn!("Could not raise the scheduling priority of the {} thread", role),
+    }
+    priority
+}
+
+/// Pin the calling thread as configured; returns whether it was pinned
+///
+/// `role` names the thread in logs, e.g. "render".
+pub fn pin_thread(affinity: &ThreadAffinity, role: &str) -> bool {
+    if !affinity.is_set() {
+        return false;
+    }
+    let node_cpus = match affinity.numa_node.map(numa_node_cpus).transpose() {
+        Ok(node_cpus) => node_cpus,
+        Err(e) => {
+            warn!("Not pinning the {} thread to NUMA node {:?}: {}", role, affinity.numa_node, e);
+            return false;
+        }
+    };
+    let cpus: BTreeSet<usize> = match node_cpus {
+        Some(node_cpus) if affinity.cpus.is_empty() => node_cpus,
+        Some(node_cpus) => affinity.cpus.iter().copied().filter(|cpu| node_cpus.contains(cpu)).collect(),
+        None => affinity.cpus.iter().copied().collect(),
+    };
+    if cpus.is_empty() {
+        warn!("Not pinning the {} thread: none of CPUs {:?} are on NUMA node {:?}", role, affinity.cpus, affinity.numa_node);
+        return false;
+    }
+
+    if let Err(e) = set_affinity(&cpus) {
+        warn!("Failed to pin the {} thread to CPUs {:?}: {}", role, cpus, e);
+        return false;
+    }
+    if let Some(node) = affinity.numa_node {
+        if let Err(e) = prefer_node_memory(node) {
+            debug!("Memory of the {} thread does not prefer NUMA node {}: {}", role, node, e);
+        }
+    }
+    info!("{} thread pinned to CPUs {:?}", role, cpus);
+    true
+}
+
+/// Restrict the calling thread to the given CPUs
+fn set_affinity(cpus: &BTreeSet<usize>) -> io::Result<()> {
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for &cpu in cpus.iter().filter(|&&cpu| cpu < config::MAX_CPUS) {
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    // A pid of 0 is the calling thread
+    if unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Make the calling thread allocate memory from a NUMA node while it has
+/// free memory
+fn prefer_node_memory(node: usize) -> io::Result<()> {
+    let bits = libc::c_ulong::BITS as usize;
+    let mut mask = vec![0 as libc::c_ulong; node / bits + 1];
+    mask[node / bits] |= 1 << (node % bits);
+    let result = unsafe {
+        libc::syscall(libc::SYS_set_mempolicy, MPOL_PREFERRED, mask.as_ptr(), (mask.len() * bits) as libc::c_ulong)
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// CPUs of a NUMA node, from sysfs
+fn numa_node_cpus(node: usize) -> io::Result<BTreeSet<usize>> {
+    let list = std::fs::read_to_string(format!("/sys/devices/system/node/node{}/cpulist", node))?;
+    parse_cpu_list(list.trim())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("invalid CPU list {:?}", list.trim())))
+}
+
+/// Parse a kernel CPU list such as `0-3,8-11`
+fn parse_cpu_list(list: &str) -> Option<BTreeSet<usize>> {
+    let mut cpus = BTreeSet::new();
+    for range in list.split(',').filter(|range| !range.is_empty()) {
+        let (first, last): (usize, usize) = match range.split_once('-') {
+            Some((first, last)) => (first.parse().ok()?, last.parse().ok()?),
+            None => {
+                let cpu = range.parse().ok()?;
+                (cpu, cpu)
+            }
+        };
+        cpus.extend(first..=last);
+    }
+    Some(cpus)
+}
+
+/// Switch the calling thread to SCHED_RR, raising the soft RLIMIT_RTPRIO up
+/// to the hard limit if needed; the priority is capped at the hard limit
+fn set_realtime(priority: u32) -> io::Result<ThreadPriority> {
+    let mut priority = priority.clamp(1, 99);
+    let limit = raise_soft_limit(libc::RLIMIT_RTPRIO, priority as libc::rlim_t)?;
+    if limit < priority as libc::rlim_t {
+        if limit == 0 && !has_cap_sys_nice() {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "RLIMIT_RTPRIO is 0"));
+        }
+        // Without the capability only priorities within the limit are allowed
+        if !has_cap_sys_nice() {
+            priority = limit as u32;
+        }
+    }
+
+    let param = libc::sched_param { sched_priority: priority as libc::c_int };
+    // A pid of 0 is the calling thread
+    let result = unsafe { libc::sched_setscheduler(0, libc::SCHED_RR | libc::SCHED_RESET_ON_FORK, &param) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(ThreadPriority::Realtime(priority))
+}
+
+/// Set the nice value of the calling thread, raising the soft RLIMIT_NICE
+/// up to the hard limit if needed
+fn set_nice(nice: i32) -> io::Result<ThreadPriority> {
+    let nice = nice.clamp(-20, 19);
+    if nice < 0 {
+        // RLIMIT_NICE stores the lowest allowed nice value as 20 - nice
+        raise_soft_limit(libc::RLIMIT_NICE, (20 - nice) as libc::rlim_t)?;
+    }
+    let tid = unsafe { libc::gettid() };
+    // On Linux, setpriority with a thread ID changes only that thread
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, nice) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(ThreadPriority::Nice(nice))
+}
+
+/// Raise a soft resource limit towards `wanted`, as far as the hard limit
+/// allows; returns the new soft limit
+fn raise_soft_limit(resource: libc::__rlimit_resource_t, wanted: libc::rlim_t) -> io::Result<libc::rlim_t> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    if unsafe { libc::getrlimit(resource, &mut limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if limit.rlim_cur >= wanted {
+        return Ok(limit.rlim_cur);
+    }
+    let raised = libc::rlimit { rlim_cur: wanted.min(limit.rlim_max), rlim_max: limit.rlim_max };
+    if unsafe { libc::setrlimit(resource, &raised) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(raised.rlim_cur)
+}
+
+/// Whether the process has CAP_SYS_NICE in its effective set
+fn has_cap_sys_nice() -> bool {
+    const CAP_SYS_NICE: u32 = 23;
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            let effective = status.lines().find_map(|line| line.strip_prefix("CapEff:"))?;
+            u64::from_str_radix(effective.trim(), 16).ok()
+        })
+        .is_some_and(|effective| effective & (1 << CAP_SYS_NICE) != 0)
+}
@@ -0,0 +1,303 @@
+// Screen capture for clients
+//
+// Serves zwlr_screencopy_manager_v1 to clients the security policy allows,
+// such as grim and wf-recorder. A client asks to capture an output, or a
+// region of it, and learns the size and wl_shm format of the buffer to
+// provide. Copies wait for the next composed frame, which is read back from
+// the GPU like screenshots, and the pixels are written into the client's
+// buffer before the frame is reported ready.
+//
+// TODO: Offer dmabuf buffers (linux_dmabuf event) and copy on the GPU
+// TODO: Serve ext-image-copy-capture-v1 for single-window captures once
+// smithay provides its image capture sources
+
+use compositor_utils::prelude::*;
+use smithay::output::Output;
+use smithay::reexports::wayland_protocols_wlr::screencopy::v1::server::{
+    zwlr_screencopy_frame_v1::{self, ZwlrScreencopyFrameV1},
+    zwlr_screencopy_manager_v1::{self, ZwlrScreencopyManagerV1},
+};
+use smithay::utils::{Logical, Point, Rectangle, Size};
+use smithay::wayland::shm::{with_buffer_contents, with_buffer_contents_mut};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use vulkan_renderer::{ReadbackPixels, ReadbackRegion};
+use wayland_server::backend::{ClientId, GlobalId};
+use wayland_server::protocol::{wl_buffer::WlBuffer, wl_shm};
+use wayland_server::{Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource};
+
+const MANAGER_VERSION: u32 = 3;
+
+/// Format of the buffers clients copy into; supported by every wl_shm
+const SHM_FORMAT: wl_shm::Format = wl_shm::Format::Xrgb8888;
+
+/// Handler for screen capture requests
+pub trait ScreencopyHandler: Sized {
+    fn screencopy_state(&mut self) -> &mut ScreencopyState;
+
+    /// Part of the composed frame showing `region` of an output, in logical
+    /// coordinates relative to the output, or all of the output when `None`;
+    /// `None` if frames of the output cannot be read back
+    fn capture_region(&self, output: &Output, region: Option<Rectangle<i32, Logical>>) -> Option<ReadbackRegion>;
+}
+
+/// Data of the screencopy manager global
+pub struct ScreencopyGlobalData {
+    filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
+}
+
+/// Data of a screencopy frame object
+#[derive(Debug)]
+pub struct ScreencopyFrameData {
+    /// Part of the composed frame to copy; `None` if the capture failed
+    region: Option<ReadbackRegion>,
+    /// A buffer was already given to copy into
+    used: AtomicBool,
+}
+
+/// Copy waiting for the next composed frame
+#[derive(Debug)]
+struct PendingCopy {
+    frame: ZwlrScreencopyFrameV1,
+    buffer: WlBuffer,
+    region: ReadbackRegion,
+    with_damage: bool,
+}
+
+/// State of the screencopy manager global
+#[derive(Debug)]
+pub struct ScreencopyState {
+    global: GlobalId,
+    pending: Vec<PendingCopy>,
+}
+
+impl ScreencopyState {
+    /// Register the screencopy manager global for clients passing `filter`
+    pub fn new<D, F>(display: &DisplayHandle, filter: F) -> Self
+    where
+        D: GlobalDispatch<ZwlrScreencopyManagerV1, ScreencopyGlobalData>
+            + Dispatch<ZwlrScreencopyManagerV1, ()>
+            + Dispatch<ZwlrScreencopyFrameV1, ScreencopyFrameData>
+            + ScreencopyHandler
+            + 'static,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+    {
+        let data = ScreencopyGlobalData { filter: Box::new(filter) };
+        let global = display.create_global::<D, ZwlrScreencopyManagerV1, _>(MANAGER_VERSION, data);
+        Self { global, pending: Vec::new() }
+    }
+
+    /// Screencopy manager global
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Region of the next frame to read back for waiting copies, covering
+    /// all of them
+    pub fn readback_region(&self) -> Option<ReadbackRegion> {
+        self.pending.iter().map(|copy| copy.region).reduce(union)
+    }
+
+    /// Copy pixels read back for waiting copies into their buffers, `time`
+    /// being when the frame was presented on CLOCK_MONOTONIC
+    ///
+    /// Returns whether the pixels were read for the copies; pixels read for
+    /// other tools are left to them.
+    pub fn frame_read(&mut self, pixels: &ReadbackPixels, time: Duration) -> bool {
+        if self.readback_region() != Some(pixels.requested) {
+            return false;
+        }
+        for copy in self.pending.drain(..) {
+            if !copy.frame.is_alive() {
+                continue;
+            }
+            if !contains(pixels.region, copy.region) || !copy_into(&copy.buffer, pixels, copy.region) {
+                debug!("Screencopy of {:?} failed", copy.region);
+                copy.frame.failed();
+                continue;
+            }
+            copy.frame.flags(zwlr_screencopy_frame_v1::Flags::empty());
+            if copy.with_damage {
+                // TODO: Report only the damaged part once frames track damage per output
+                copy.frame.damage(0, 0, copy.region.width, copy.region.height);
+            }
+            let seconds = time.as_secs();
+            copy.frame.ready((seconds >> 32) as u32, seconds as u32, time.subsec_nanos());
+        }
+        true
+    }
+
+    /// Fail the waiting copies when their region could not be read back
+    ///
+    /// Returns whether the region was the copies'.
+    pub fn readback_failed(&mut self, region: ReadbackRegion) -> bool {
+        if self.readback_region() != Some(region) {
+            return false;
+        }
+        for copy in self.pending.drain(..) {
+            if copy.frame.is_alive() {
+                debug!("Screencopy of {:?} failed, the frame cannot be read back", copy.region);
+                copy.frame.failed();
+            }
+        }
+        true
+    }
+
+    fn frame_destroyed(&mut self, frame: &ZwlrScreencopyFrameV1) {
+        self.pending.retain(|copy| copy.frame != *frame);
+    }
+}
+
+impl<D> GlobalDispatch<ZwlrScreencopyManagerV1, ScreencopyGlobalData, D> for ScreencopyState
+where
+    D: GlobalDispatch<ZwlrScreencopyManagerV1, ScreencopyGlobalData>
+        + Dispatch<ZwlrScreencopyManagerV1, ()>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrScreencopyManagerV1>,
+        _global_data: &ScreencopyGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, global_data: &ScreencopyGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> Dispatch<ZwlrScreencopyManagerV1, (), D> for ScreencopyState
+where
+    D: Dispatch<ZwlrScreencopyManagerV1, ()>
+        + Dispatch<ZwlrScreencopyFrameV1, ScreencopyFrameData>
+        + ScreencopyHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _manager: &ZwlrScreencopyManagerV1,
+        request: zwlr_screencopy_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        // TODO: Draw the cursor into captures with overlay_cursor once the
+        // cursor is rendered; captures leave it out until then
+        let (frame, output, area) = match request {
+            zwlr_screencopy_manager_v1::Request::CaptureOutput { frame, output, .. } => (frame, output, None),
+            zwlr_screencopy_manager_v1::Request::CaptureOutputRegion { frame, output, x, y, width, height, .. } => {
+                let area = Rectangle::new(Point::from((x, y)), Size::from((width, height)));
+                (frame, output, Some(area))
+            }
+            zwlr_screencopy_manager_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+
+        let region = Output::from_resource(&output)
+            .and_then(|output| state.capture_region(&output, area))
+            .filter(|region| region.width > 0 && region.height > 0);
+        let frame = data_init.init(frame, ScreencopyFrameData { region, used: AtomicBool::new(false) });
+        let Some(region) = region else {
+            debug!("Screencopy of an output that cannot be captured");
+            frame.failed();
+            return;
+        };
+        frame.buffer(SHM_FORMAT, region.width, region.height, region.width * 4);
+        if frame.version() >= 3 {
+            frame.buffer_done();
+        }
+    }
+}
+
+impl<D> Dispatch<ZwlrScreencopyFrameV1, ScreencopyFrameData, D> for ScreencopyState
+where
+    D: Dispatch<ZwlrScreencopyFrameV1, ScreencopyFrameData> + ScreencopyHandler + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        frame: &ZwlrScreencopyFrameV1,
+        request: zwlr_screencopy_frame_v1::Request,
+        data: &ScreencopyFrameData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let (buffer, with_damage) = match request {
+            zwlr_screencopy_frame_v1::Request::Copy { buffer } => (buffer, false),
+            zwlr_screencopy_frame_v1::Request::CopyWithDamage { buffer } => (buffer, true),
+            zwlr_screencopy_frame_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+        if data.used.swap(true, Ordering::Relaxed) {
+            frame.post_error(zwlr_screencopy_frame_v1::Error::AlreadyUsed, "Frame was already copied");
+            return;
+        }
+        let Some(region) = data.region else {
+            // The client was told the capture failed
+            return;
+        };
+        let fits = with_buffer_contents(&buffer, |_, _, info| {
+            info.format == SHM_FORMAT
+                && info.width == region.width as i32
+                && info.height == region.height as i32
+                && info.stride >= region.width as i32 * 4
+        });
+        if !fits.unwrap_or(false) {
+            frame.post_error(zwlr_screencopy_frame_v1::Error::InvalidBuffer, "Buffer does not match the advertised one");
+            return;
+        }
+        state.screencopy_state().pending.push(PendingCopy { frame: frame.clone(), buffer, region, with_damage });
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, frame: &ZwlrScreencopyFrameV1, _data: &ScreencopyFrameData) {
+        state.screencopy_state().frame_destroyed(frame);
+    }
+}
+
+/// Smallest region covering both
+fn union(a: ReadbackRegion, b: ReadbackRegion) -> ReadbackRegion {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    let right = (a.x + a.width as i32).max(b.x + b.width as i32);
+    let bottom = (a.y + a.height as i32).max(b.y + b.height as i32);
+    ReadbackRegion { x, y, width: (right - x) as u32, height: (bottom - y) as u32 }
+}
+
+/// Whether `inner` lies entirely within `outer`
+fn contains(outer: ReadbackRegion, inner: ReadbackRegion) -> bool {
+    inner.x >= outer.x
+        && inner.y >= outer.y
+        && inner.x + inner.width as i32 <= outer.x + outer.width as i32
+        && inner.y + inner.height as i32 <= outer.y + outer.height as i32
+}
+
+/// Write the pixels of a region into an shm buffer as XRGB8888; returns
+/// false if the buffer is not backed by enough memory
+fn copy_into(buffer: &WlBuffer, pixels: &ReadbackPixels, region: ReadbackRegion) -> bool {
+    let written = with_buffer_contents_mut(buffer, |ptr, len, info| {
+        let (offset, stride) = (info.offset as usize, info.stride as usize);
+        let (width, height) = (region.width as usize, region.height as usize);
+        if offset + stride * height > len {
+            return false;
+        }
+        // SAFETY: the pool holds at least `stride * height` bytes past `offset`, checked above
+        let data = unsafe { std::slice::from_raw_parts_mut(ptr.add(offset), stride * height) };
+        for row in 0..height {
+            for column in 0..width {
+                let Some([r, g, b, a]) = pixels.pixel(region.x + column as i32, region.y + row as i32) else {
+                    continue;
+                };
+                let at = row * stride + column * 4;
+                // XRGB8888 is stored little-endian, blue first
+                data[at..at + 4].copy_from_slice(&[b, g, r, a]);
+            }
+        }
+        true
+    });
+    written.unwrap_or(false)
+}
@@ -0,0 +1,160 @@
+// Screenshots
+//
+// Keybindings capture the active window, the whole output or a region the
+// user selects; a region can also be selected over IPC. The region is
+// read back from the next composed frame, encoded as a PNG and saved to the
+// configured directory under a timestamped name, and optionally offered on
+// the clipboard as image/png.
+
+use compositor_utils::prelude::*;
+use config::ScreenshotConfig;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use vulkan_renderer::{ReadbackPixels, ReadbackRegion};
+
+/// What a screenshot captures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenshotTarget {
+    /// The window with keyboard focus
+    ActiveWindow,
+    /// The whole output
+    Output,
+    /// A region selected by the user
+    Region,
+}
+
+/// A captured screenshot, encoded and ready to save
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    pub target: ScreenshotTarget,
+    /// File the screenshot is saved to
+    pub path: PathBuf,
+    pub png: Arc<[u8]>,
+}
+
+impl Screenshot {
+    /// Write the PNG, creating the directory if missing
+    pub fn save(&self) -> Result<()> {
+        if let Some(directory) = self.path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::write(&self.path, &self.png)?;
+        Ok(())
+    }
+}
+
+/// Screenshot capture state
+#[derive(Debug)]
+pub struct Screenshots {
+    config: ScreenshotConfig,
+    /// Capture waiting for its frame, and the region of the frame to read
+    pending: Option<(ScreenshotTarget, ReadbackRegion)>,
+    /// Captured screenshots not yet saved
+    captured: Vec<Screenshot>,
+}
+
+impl Screenshots {
+    pub fn new(config: ScreenshotConfig) -> Self {
+        Self {
+            config,
+            pending: None,
+            captured: Vec::new(),
+        }
+    }
+
+    /// Apply new configuration
+    pub fn set_config(&mut self, config: ScreenshotConfig) {
+        self.config = config;
+    }
+
+    pub fn config(&self) -> &ScreenshotConfig {
+        &self.config
+    }
+
+    /// Capture a region of the next frame, in pixels of the frame,
+    /// replacing a capture still waiting for its frame
+    pub fn capture(&mut self, target: ScreenshotTarget, region: ReadbackRegion) {
+        debug!("Capturing {:?} screenshot of {:?}", target, region);
+        self.pending = Some((target, region));
+    }
+
+    /// Region of the frame to read back for a waiting capture
+    pub fn readback_region(&self) -> Option<ReadbackRegion> {
+        self.pending.map(|(_, region)| region)
+    }
+
+    /// Take pixels read back for a waiting capture
+    ///
+    /// Returns whether the pixels were the capture's; pixels read for other
+    /// tools are left to them.
+    pub fn frame_read(&mut self, pixels: &ReadbackPixels) -> bool {
+        let Some((target, _)) = self.pending.filter(|(_, region)| *region == pixels.requested) else {
+            return false;
+        };
+        self.pending = None;
+
+        let rgba: Vec<u8> = pixels.pixels.iter().flatten().copied().collect();
+        let png = compositor_utils::png::encode_rgba(&rgba, pixels.region.width, pixels.region.height);
+        let path = self.config.directory.join(file_name("Screenshot", SystemTime::now()));
+        self.captured.push(Screenshot { target, path, png: png.into() });
+        true
+    }
+
+    /// Give up a waiting capture when its region could not be read back
+    ///
+    /// Returns whether the region was the capture's.
+    pub fn readback_failed(&mut self, region: ReadbackRegion) -> bool {
+        if self.readback_region() != Some(region) {
+            return false;
+        }
+        warn!("Screenshot of {:?} failed, the frame cannot be read back", region);
+        self.pending = None;
+        true
+    }
+
+    /// File to save annotations drawn now to, next to the screenshots
+    pub fn annotations_path(&self) -> PathBuf {
+        self.config.directory.join(file_name("Annotations", SystemTime::now()))
+    }
+
+    /// Take the screenshots captured since the last call
+    pub fn take_captured(&mut self) -> Vec<Screenshot> {
+        std::mem::take(&mut self.captured)
+    }
+}
+
+impl Default for Screenshots {
+    fn default() -> Self {
+        Self::new(ScreenshotConfig::default())
+    }
+}
+
+/// File name of a PNG saved at `time`, e.g.
+/// `Screenshot 2026-10-15 14-03-22.png`, in UTC
+fn file_name(kind: &str, time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let (days, time_of_day) = (seconds / 86_400, seconds % 86_400);
+
+    // Civil date from days since the epoch, after Howard Hinnant's algorithm
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{} {:04}-{:02}-{:02} {:02}-{:02}-{:02}.png",
+        kind,
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60
+    )
+}
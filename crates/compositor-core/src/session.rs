@@ -42,6 +42,40 @@ pub enum SessionState {
     Terminating,
 }
 
+/// Opens and closes devices through the session thread
+#[derive(Debug, Clone)]
+pub struct DeviceAccess {
+    command_tx: mpsc::Sender<SessionMessage>,
+}
+
+impl DeviceAccess {
+    /// Acquire access to a device
+    pub fn acquire_device(&self, path: String) -> Result<i32> {
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.command_tx
+            .send(SessionMessage::AcquireDevice { path, response_tx })
+            .map_err(|e| CompositorError::Backend(format!("Failed to send acquire device command: {}", e)))?;
+            
+        response_rx
+            .recv()
+            .map_err(|e| CompositorError::Backend(format!("Failed to receive acquire device response: {}", e)))?
+    }
+    
+    /// Release access to a device
+    pub fn release_device(&self, fd: i32) -> Result<()> {
+        let (response_tx, response_rx) = mpsc::channel();
+        
+        self.command_tx
+            .send(SessionMessage::ReleaseDevice { fd, response_tx })
+            .map_err(|e| CompositorError::Backend(format!("Failed to send release device command: {}", e)))?;
+            
+        response_rx
+            .recv()
+            .map_err(|e| CompositorError::Backend(format!("Failed to receive release device response: {}", e)))?
+    }
+}
+
 /// Session manager for handling DRM device access and privilege separation
 pub struct SessionManager {
     /// Channel for sending commands to the session thread
@@ -76,28 +110,20 @@ impl SessionManager {
     
     /// Acquire access to a DRM device
     pub fn acquire_device(&self, path: String) -> Result<i32> {
-        let (response_tx, response_rx) = mpsc::channel();
-        
-        self.command_tx
-            .send(SessionMessage::AcquireDevice { path, response_tx })
-            .map_err(|e| CompositorError::Backend(format!("Failed to send acquire device command: {}", e)))?;
-            
-        response_rx
-            .recv()
-            .map_err(|e| CompositorError::Backend(format!("Failed to receive acquire device response: {}", e)))?
+        self.device_access().acquire_device(path)
     }
     
     /// Release access to a DRM device
     pub fn release_device(&self, fd: i32) -> Result<()> {
-        let (response_tx, response_rx) = mpsc::channel();
-        
-        self.command_tx
-            .send(SessionMessage::ReleaseDevice { fd, response_tx })
-            .map_err(|e| CompositorError::Backend(format!("Failed to send release device command: {}", e)))?;
-            
-        response_rx
-            .recv()
-            .map_err(|e| CompositorError::Backend(format!("Failed to receive release device response: {}", e)))?
+        self.device_access().release_device(fd)
+    }
+    
+    /// Handle for opening devices through the session from other threads,
+    /// e.g. input devices for libinput
+    pub fn device_access(&self) -> DeviceAccess {
+        DeviceAccess {
+            command_tx: self.command_tx.clone(),
+        }
     }
     
     /// Check for session events (non-blocking)
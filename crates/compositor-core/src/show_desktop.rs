@@ -0,0 +1,217 @@
+// Show desktop and peek
+//
+// "Show desktop" slides every window off the nearest screen edge to reveal
+// the wallpaper and desktop widgets, and slides them back when toggled again.
+// "Peek" does the same only while a key is held and restores the previous
+// layout on release. The slide steps at the window animation rate and frames
+// in between interpolate window positions.
+
+use compositor_utils::animation_tick::Interpolated;
+use compositor_utils::prelude::*;
+use smithay::utils::{Logical, Point, Rectangle};
+use std::time::{Duration, Instant};
+use wayland_server::backend::ObjectId;
+
+/// Default duration of the slide animation
+pub const DEFAULT_SLIDE_DURATION: Duration = Duration::from_millis(250);
+
+/// Current show-desktop mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShowDesktopMode {
+    /// Windows are in their normal layout
+    Inactive,
+    /// Windows are moved aside until toggled back
+    Shown,
+    /// Windows are moved aside while the peek key is held
+    Peek,
+}
+
+/// A window moved aside, with where it came from and where it goes
+#[derive(Debug, Clone)]
+struct MovedWindow {
+    id: ObjectId,
+    origin: Point<i32, Logical>,
+    aside: Point<i32, Logical>,
+}
+
+/// Show desktop / peek state and animation
+#[derive(Debug)]
+pub struct ShowDesktop {
+    mode: ShowDesktopMode,
+    windows: Vec<MovedWindow>,
+    /// Animation position: 0.0 = normal layout, 1.0 = fully aside
+    progress: f32,
+    /// Positions of the last two animation steps, interpolated when drawn
+    steps: Interpolated<f32>,
+    /// Progress at the start of the current animation and where it is heading
+    animation: Option<(Instant, f32, f32)>,
+    duration: Duration,
+}
+
+impl ShowDesktop {
+    /// Create show-desktop state with the default animation duration
+    pub fn new() -> Self {
+        Self {
+            mode: ShowDesktopMode::Inactive,
+            windows: Vec::new(),
+            progress: 0.0,
+            steps: Interpolated::new(0.0),
+            animation: None,
+            duration: DEFAULT_SLIDE_DURATION,
+        }
+    }
+
+    /// Set the slide animation duration
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    /// Current mode
+    pub fn mode(&self) -> ShowDesktopMode {
+        self.mode
+    }
+
+    /// Whether windows are moving
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some() || !self.steps.is_settled()
+    }
+
+    /// Toggle show desktop for the given windows on an output
+    pub fn toggle(
+        &mut self,
+        windows: impl IntoIterator<Item = (ObjectId, Rectangle<i32, Logical>)>,
+        output: Rectangle<i32, Logical>,
+        now: Instant,
+    ) {
+        match self.mode {
+            ShowDesktopMode::Inactive => self.move_aside(windows, output, ShowDesktopMode::Shown, now),
+            ShowDesktopMode::Shown | ShowDesktopMode::Peek => self.restore(now),
+        }
+    }
+
+    /// Start peeking at the desktop while a key is held
+    pub fn begin_peek(
+        &mut self,
+        windows: impl IntoIterator<Item = (ObjectId, Rectangle<i32, Logical>)>,
+        output: Rectangle<i32, Logical>,
+        now: Instant,
+    ) {
+        if self.mode == ShowDesktopMode::Inactive {
+            self.move_aside(windows, output, ShowDesktopMode::Peek, now);
+        }
+    }
+
+    /// Stop peeking and restore the previous layout
+    pub fn end_peek(&mut self, now: Instant) {
+        if self.mode == ShowDesktopMode::Peek {
+            self.restore(now);
+        }
+    }
+
+    /// Slide all moved windows back to their original positions
+    pub fn restore(&mut self, now: Instant) {
+        if self.mode != ShowDesktopMode::Inactive {
+            debug!("Restoring window layout from {:?}", self.mode);
+            self.mode = ShowDesktopMode::Inactive;
+            self.animate_to(0.0, now);
+        }
+    }
+
+    /// Forget a window, e.g. when it is destroyed while moved aside
+    pub fn remove_window(&mut self, id: &ObjectId) {
+        self.windows.retain(|window| &window.id != id);
+    }
+
+    /// Step the animation to `now`
+    pub fn step(&mut self, now: Instant) {
+        if let Some((start, from, to)) = self.animation {
+            let t = if self.duration.is_zero() {
+                1.0
+            } else {
+                (now.saturating_duration_since(start).as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+            };
+            // Ease-out cubic
+            let eased = 1.0 - (1.0 - t).powi(3);
+            self.progress = from + (to - from) * eased;
+
+            if t >= 1.0 {
+                self.animation = None;
+            }
+        }
+        self.steps.step(self.progress);
+    }
+
+    /// Position each moved window should have, `alpha` of the way from the
+    /// previous animation step to the last
+    pub fn positions(&mut self, alpha: f32) -> Vec<(ObjectId, Point<i32, Logical>)> {
+        let progress = self.steps.value(alpha);
+        let positions = self
+            .windows
+            .iter()
+            .map(|window| {
+                let x = window.origin.x as f32 + (window.aside.x - window.origin.x) as f32 * progress;
+                let y = window.origin.y as f32 + (window.aside.y - window.origin.y) as f32 * progress;
+                (window.id.clone(), Point::from((x.round() as i32, y.round() as i32)))
+            })
+            .collect();
+
+        // Once fully restored there is nothing left to track
+        if self.mode == ShowDesktopMode::Inactive && !self.is_animating() {
+            self.windows.clear();
+        }
+
+        positions
+    }
+
+    fn move_aside(
+        &mut self,
+        windows: impl IntoIterator<Item = (ObjectId, Rectangle<i32, Logical>)>,
+        output: Rectangle<i32, Logical>,
+        mode: ShowDesktopMode,
+        now: Instant,
+    ) {
+        self.windows = windows
+            .into_iter()
+            .map(|(id, geometry)| MovedWindow {
+                id,
+                origin: geometry.loc,
+                aside: aside_position(geometry, output),
+            })
+            .collect();
+        debug!("Moving {} windows aside ({:?})", self.windows.len(), mode);
+
+        self.mode = mode;
+        self.animate_to(1.0, now);
+    }
+
+    fn animate_to(&mut self, target: f32, now: Instant) {
+        self.animation = Some((now, self.progress, target));
+    }
+}
+
+impl Default for ShowDesktop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Position just past the screen edge closest to the window's center
+fn aside_position(window: Rectangle<i32, Logical>, output: Rectangle<i32, Logical>) -> Point<i32, Logical> {
+    let center_x = window.loc.x + window.size.w / 2;
+    let center_y = window.loc.y + window.size.h / 2;
+    let to_left = center_x - output.loc.x;
+    let to_right = output.loc.x + output.size.w - center_x;
+    let to_top = center_y - output.loc.y;
+    let to_bottom = output.loc.y + output.size.h - center_y;
+
+    let nearest = to_left.min(to_right).min(to_top).min(to_bottom);
+    if nearest == to_left {
+        (output.loc.x - window.size.w, window.loc.y).into()
+    } else if nearest == to_right {
+        (output.loc.x + output.size.w, window.loc.y).into()
+    } else if nearest == to_top {
+        (window.loc.x, output.loc.y - window.size.h).into()
+    } else {
+        (window.loc.x, output.loc.y + output.size.h).into()
+    }
+}
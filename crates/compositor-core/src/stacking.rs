@@ -0,0 +1,146 @@
+// Stacking order of surfaces
+//
+// Every mapped toplevel, layer surface and lock surface sits in one of a
+// fixed set of stacking layers, from the wallpaper at the bottom to the lock
+// screen at the top. Within a layer the most recently raised surface is on
+// top. The renderer draws surfaces in this order and input goes to the
+// topmost surface, so neither depends on the order surfaces were created in.
+
+use smithay::reexports::wayland_server::backend::ObjectId;
+use smithay::wayland::shell::wlr_layer::Layer;
+
+/// Stacking layer, from bottom to top
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StackLayer {
+    /// Wallpapers (background layer surfaces)
+    Background,
+    /// Desktop widgets below windows (bottom layer surfaces)
+    Bottom,
+    /// Regular windows
+    Normal,
+    /// Windows kept above regular ones
+    Floating,
+    /// Fullscreen windows, covering panels
+    Fullscreen,
+    /// Panels and docks (top layer surfaces)
+    Top,
+    /// Notifications and on-screen displays (overlay layer surfaces)
+    Overlay,
+    /// Session lock surfaces, above everything
+    Lock,
+}
+
+impl From<Layer> for StackLayer {
+    fn from(layer: Layer) -> Self {
+        match layer {
+            Layer::Background => Self::Background,
+            Layer::Bottom => Self::Bottom,
+            Layer::Top => Self::Top,
+            Layer::Overlay => Self::Overlay,
+        }
+    }
+}
+
+/// Stacking order of all stacked surfaces
+#[derive(Debug, Default)]
+pub struct StackingOrder {
+    /// Surfaces from bottom to top, grouped by layer
+    entries: Vec<(ObjectId, StackLayer)>,
+}
+
+impl StackingOrder {
+    /// Create an empty stacking order
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stack a surface on top of a layer, moving it there if already stacked
+    pub fn insert(&mut self, surface: ObjectId, layer: StackLayer) {
+        self.remove(&surface);
+        let index = self.layer_end(layer);
+        self.entries.insert(index, (surface, layer));
+    }
+
+    /// Remove a surface from the stacking order
+    pub fn remove(&mut self, surface: &ObjectId) {
+        self.entries.retain(|(id, _)| id != surface);
+    }
+
+    /// Remove every surface of a layer, e.g. the lock surfaces on unlock
+    pub fn clear_layer(&mut self, layer: StackLayer) {
+        self.entries.retain(|&(_, other)| other != layer);
+    }
+
+    /// Layer a surface is stacked in
+    pub fn layer(&self, surface: &ObjectId) -> Option<StackLayer> {
+        self.entries.iter().find(|(id, _)| id == surface).map(|&(_, layer)| layer)
+    }
+
+    /// Move a surface to the top of another layer, e.g. when it goes fullscreen
+    pub fn set_layer(&mut self, surface: &ObjectId, layer: StackLayer) {
+        if self.layer(surface).is_some_and(|current| current != layer) {
+            self.insert(surface.clone(), layer);
+        }
+    }
+
+    /// Raise a surface to the top of its layer
+    pub fn raise(&mut self, surface: &ObjectId) {
+        if let Some(layer) = self.layer(surface) {
+            self.insert(surface.clone(), layer);
+        }
+    }
+
+    /// Lower a surface to the bottom of its layer
+    pub fn lower(&mut self, surface: &ObjectId) {
+        let Some(layer) = self.layer(surface) else { return };
+        self.remove(surface);
+        let index = self.layer_start(layer);
+        self.entries.insert(index, (surface.clone(), layer));
+    }
+
+    /// Stack a surface directly above `sibling`
+    ///
+    /// Returns false, leaving the order unchanged, unless both surfaces are
+    /// stacked in the same layer.
+    pub fn restack_above(&mut self, surface: &ObjectId, sibling: &ObjectId) -> bool {
+        let (Some(layer), Some(sibling_layer)) = (self.layer(surface), self.layer(sibling)) else {
+            return false;
+        };
+        if layer != sibling_layer || surface == sibling {
+            return false;
+        }
+        self.remove(surface);
+        let index = self.position(sibling).map_or(self.layer_end(layer), |index| index + 1);
+        self.entries.insert(index, (surface.clone(), layer));
+        true
+    }
+
+    /// Surfaces from bottom to top
+    pub fn bottom_to_top(&self) -> impl DoubleEndedIterator<Item = &ObjectId> {
+        self.entries.iter().map(|(id, _)| id)
+    }
+
+    /// Surfaces from top to bottom, the order to search for input targets
+    pub fn top_to_bottom(&self) -> impl Iterator<Item = &ObjectId> {
+        self.bottom_to_top().rev()
+    }
+
+    /// Protocol IDs of the surfaces from bottom to top, for the renderer
+    pub fn protocol_ids(&self) -> Vec<u32> {
+        self.bottom_to_top().map(ObjectId::protocol_id).collect()
+    }
+
+    fn position(&self, surface: &ObjectId) -> Option<usize> {
+        self.entries.iter().position(|(id, _)| id == surface)
+    }
+
+    /// Index of the first surface of `layer` or above
+    fn layer_start(&self, layer: StackLayer) -> usize {
+        self.entries.partition_point(|&(_, other)| other < layer)
+    }
+
+    /// Index after the last surface of `layer` or below
+    fn layer_end(&self, layer: StackLayer) -> usize {
+        self.entries.partition_point(|&(_, other)| other <= layer)
+    }
+}
@@ -0,0 +1,180 @@
+// Startup notification
+//
+// Applications the compositor launches, from the launcher, the dock or a
+// keybinding, get an xdg-activation token in XDG_ACTIVATION_TOKEN and
+// DESKTOP_STARTUP_ID. Until the app shows its first window, the cursor turns
+// busy and the app's dock icon bounces. A launch ends when the app activates
+// a surface with its token, when a window with the launched app ID appears for
+// clients that ignore the token, or after a timeout for apps that never show
+// a window. Launches requested over IPC take the same path.
+
+use crate::wayland_socket::client_command;
+use compositor_utils::prelude::*;
+use config::StartupFeedbackConfig;
+use ipc::protocol::LaunchRequest;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+
+#[derive(Debug)]
+struct PendingLaunch {
+    token: String,
+    app_id: Option<String>,
+    deadline: Instant,
+}
+
+/// Applications launched by the compositor that have not shown a window yet
+#[derive(Debug)]
+pub struct StartupFeedback {
+    config: StartupFeedbackConfig,
+    launches: Vec<PendingLaunch>,
+    /// App IDs whose dock icons bounce, for the app bar
+    bouncing: watch::Sender<Vec<String>>,
+}
+
+impl StartupFeedback {
+    /// Create startup feedback with the given configuration
+    pub fn new(config: StartupFeedbackConfig) -> Self {
+        Self {
+            config,
+            launches: Vec::new(),
+            bouncing: watch::channel(Vec::new()).0,
+        }
+    }
+
+    /// Apply new configuration
+    pub fn set_config(&mut self, config: StartupFeedbackConfig) {
+        self.config = config;
+        self.publish();
+    }
+
+    /// Follow an app launched with an activation token
+    pub fn launch_started(&mut self, token: &str, app_id: Option<&str>, now: Instant) {
+        self.launches.push(PendingLaunch {
+            token: token.to_string(),
+            app_id: app_id.map(str::to_string),
+            deadline: now + Duration::from_secs(self.config.timeout),
+        });
+        self.publish();
+    }
+
+    /// End the launch a token was created for, when the app activates it or
+    /// fails to start; returns whether one ended
+    pub fn launch_ended(&mut self, token: &str) -> bool {
+        self.end_launches(|launch| launch.token == token)
+    }
+
+    /// End launches of an app when a window with its app ID appears; returns
+    /// whether any ended
+    pub fn window_shown(&mut self, app_id: &str) -> bool {
+        self.end_launches(|launch| launch.app_id.as_deref() == Some(app_id))
+    }
+
+    /// Give up on launches past their timeout; returns whether any ended
+    pub fn poll(&mut self, now: Instant) -> bool {
+        self.end_launches(|launch| launch.deadline <= now)
+    }
+
+    /// When `poll` needs to be called next
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.launches.iter().map(|launch| launch.deadline).min()
+    }
+
+    /// Whether the cursor should show that an app is starting
+    pub fn busy_cursor(&self) -> bool {
+        self.config.busy_cursor && !self.launches.is_empty()
+    }
+
+    /// Channel for the app bar to follow which dock icons bounce
+    pub fn bouncing_receiver(&self) -> watch::Receiver<Vec<String>> {
+        self.bouncing.subscribe()
+    }
+
+    fn end_launches(&mut self, ended: impl Fn(&PendingLaunch) -> bool) -> bool {
+        let count = self.launches.len();
+        self.launches.retain(|launch| !ended(launch));
+        if self.launches.len() == count {
+            return false;
+        }
+        self.publish();
+        true
+    }
+
+    /// Publish the app IDs whose dock icons bounce
+    fn publish(&self) {
+        let mut app_ids: Vec<String> = if self.config.bounce_dock_icon {
+            self.launches.iter().filter_map(|launch| launch.app_id.clone()).collect()
+        } else {
+            Vec::new()
+        };
+        app_ids.sort();
+        app_ids.dedup();
+        self.bouncing.send_if_modified(|bouncing| {
+            if *bouncing == app_ids {
+                return false;
+            }
+            *bouncing = app_ids;
+            true
+        });
+    }
+}
+
+/// Launches requested over IPC, waiting for the event loop
+#[derive(Debug)]
+pub struct LaunchQueue {
+    sender: mpsc::UnboundedSender<LaunchRequest>,
+    receiver: mpsc::UnboundedReceiver<LaunchRequest>,
+}
+
+impl LaunchQueue {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self { sender, receiver }
+    }
+
+    /// Channel for IPC to request launches
+    pub fn sender(&self) -> mpsc::UnboundedSender<LaunchRequest> {
+        self.sender.clone()
+    }
+
+    /// Take the requests received since the last call
+    pub fn drain(&mut self) -> Vec<LaunchRequest> {
+        std::iter::from_fn(|| self.receiver.try_recv().ok()).collect()
+    }
+}
+
+impl Default for LaunchQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Launch a client command line, passing it the activation token in the
+/// environment variables of xdg-activation and legacy startup notification
+///
+/// Returns the process ID. The process is reaped on a background thread
+/// once it exits.
+pub fn launch_command(command: &[String], wayland_display: Option<&str>, activation_token: Option<&str>) -> Result<u32> {
+    let Some((program, args)) = command.split_first() else {
+        return Err(CompositorError::runtime("Empty launch command"));
+    };
+    let mut client = client_command(program, wayland_display);
+    if let Some(token) = activation_token {
+        client.env("XDG_ACTIVATION_TOKEN", token).env("DESKTOP_STARTUP_ID", token);
+    }
+    let mut child = client
+        .args(args)
+        .stdin(Stdio::null())
+        .spawn()
+        .map_err(|e| CompositorError::runtime(format!("Failed to launch {}: {}", program, e)))?;
+    let pid = child.id();
+    // Reap the client when it exits so it does not linger as a zombie
+    let reaper = std::thread::Builder::new().name(format!("reap-{}", pid)).spawn(move || {
+        let _ = child.wait();
+    });
+    if let Err(e) = reaper {
+        warn!("Failed to watch launched {} ({}): {}", program, pid, e);
+    }
+    Ok(pid)
+}
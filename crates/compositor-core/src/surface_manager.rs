@@ -2,160 +2,251 @@
 //
 // This module provides the interface between the Wayland server (which receives
 // client surface data) and the Vulkan renderer (which renders textures to screen).
+// Commits are queued on the event loop thread and uploaded by the render thread,
+// which owns the renderer, once per frame. Surfaces are known by the protocol
+// IDs of their wl_surface, like everywhere else the renderer sees them.
 
+use compositor_utils::math::Rect;
 use compositor_utils::prelude::*;
 use vulkan_renderer::{VulkanRenderer, SurfaceBuffer};
-use smithay::wayland::buffer::Buffer as WaylandBuffer;
+use vulkan_renderer::surface_renderer::{DmaBufFormat, ShmFormat};
+use ash::vk;
+use drm_fourcc::DrmFourcc;
+use smithay::backend::allocator::Buffer as _;
+use smithay::reexports::wayland_server::protocol::{wl_buffer::WlBuffer, wl_shm};
 use smithay::wayland::shm;
 use smithay::wayland::dmabuf;
-use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
+use std::os::fd::AsRawFd;
+
+/// Buffer content committed by a client but not yet uploaded to the GPU
+struct PendingUpload {
+    /// Commit serial this buffer was attached with
+    serial: u64,
+    buffer: SurfaceBuffer,
+}
+
+/// Surfaces uploaded by `flush_pending_uploads`
+#[derive(Debug, Default)]
+pub struct FlushedUploads {
+    /// Surfaces whose content was uploaded
+    pub uploaded: Vec<u32>,
+    /// Surfaces whose content failed to upload, with the error
+    pub failed: Vec<(u32, CompositorError)>,
+    /// Regions the flushed commits changed, in global coordinates; `None`
+    /// if they cannot be bounded, e.g. as a surface was removed
+    pub damage: Option<Vec<Rect>>,
+}
 
 /// Surface manager that coordinates between Wayland and Vulkan
+#[derive(Default)]
 pub struct SurfaceManager {
-    renderer: Arc<Mutex<VulkanRenderer>>,
-    /// Map of Wayland surface ID to our internal surface ID
-    surface_mapping: HashMap<u64, u32>,
-    next_surface_id: u32,
+    /// Latest commit serial seen for each surface
+    commit_serials: HashMap<u32, u64>,
+    /// Most recent not-yet-uploaded buffer for each surface
+    pending_uploads: HashMap<u32, PendingUpload>,
+    /// Destroyed surfaces whose textures are still to be removed
+    pending_removals: Vec<u32>,
+    /// Damage of the pending commits in global coordinates
+    pending_damage: Vec<Rect>,
+    /// A pending commit's damage could not be placed
+    damage_unbounded: bool,
+    next_commit_serial: u64,
+    /// Number of committed buffers superseded before they were uploaded
+    dropped_uploads: u64,
 }
 
 impl SurfaceManager {
     /// Create a new surface manager
-    pub fn new(renderer: Arc<Mutex<VulkanRenderer>>) -> Self {
+    pub fn new() -> Self {
         info!("Initializing surface manager");
-        
-        Self {
-            renderer,
-            surface_mapping: HashMap::new(),
-            next_surface_id: 1,
-        }
-    }
-    
-    /// Register a new Wayland surface
-    pub fn register_surface(&mut self, wayland_surface_id: u64) -> u32 {
-        let surface_id = self.next_surface_id;
-        self.next_surface_id += 1;
-        
-        self.surface_mapping.insert(wayland_surface_id, surface_id);
-        
-        info!("Registered surface: Wayland {} -> Internal {}", wayland_surface_id, surface_id);
-        surface_id
+        Self::default()
     }
-    
+
     /// Handle surface buffer commit from Wayland client
-    pub fn handle_surface_commit(&mut self, wayland_surface_id: u64, buffer: &WaylandBuffer) -> Result<()> {
-        let surface_id = match self.surface_mapping.get(&wayland_surface_id) {
-            Some(&id) => id,
-            None => {
-                // Auto-register unknown surfaces
-                self.register_surface(wayland_surface_id)
-            }
-        };
-        
+    ///
+    /// The buffer is not uploaded immediately. It is queued under a new commit
+    /// serial and replaces any earlier content that has not been uploaded yet,
+    /// so clients committing faster than we render only cost one upload per
+    /// frame. Call `flush_pending_uploads` once per frame to upload.
+    ///
+    /// `damage` is what the commit changed in global coordinates, or `None`
+    /// where the surface's position is not known.
+    pub fn handle_surface_commit(&mut self, surface_id: u32, buffer: &WlBuffer, damage: Option<Vec<Rect>>) -> Result<u64> {
         // Convert Wayland buffer to our surface buffer format
         let surface_buffer = self.convert_wayland_buffer(buffer)?;
-        
-        // Update the renderer
-        if let Ok(mut renderer) = self.renderer.lock() {
-            renderer.update_surface_buffer(surface_id, surface_buffer)?;
-            debug!("Updated surface {} with new buffer", surface_id);
-        } else {
-            warn!("Failed to lock renderer for surface update");
+        match damage {
+            Some(damage) => self.pending_damage.extend(damage),
+            None => self.damage_unbounded = true,
+        }
+
+        self.next_commit_serial += 1;
+        let serial = self.next_commit_serial;
+        self.commit_serials.insert(surface_id, serial);
+
+        let pending = PendingUpload { serial, buffer: surface_buffer };
+        if let Some(superseded) = self.pending_uploads.insert(surface_id, pending) {
+            self.dropped_uploads += 1;
+            debug!("Surface {} commit {} superseded by {} before upload",
+                   surface_id, superseded.serial, serial);
         }
-        
-        Ok(())
+
+        Ok(serial)
     }
-    
-    /// Remove a surface
-    pub fn remove_surface(&mut self, wayland_surface_id: u64) -> Result<()> {
-        if let Some(surface_id) = self.surface_mapping.remove(&wayland_surface_id) {
-            if let Ok(mut renderer) = self.renderer.lock() {
-                renderer.remove_surface(surface_id)?;
-                info!("Removed surface: Wayland {} -> Internal {}", wayland_surface_id, surface_id);
+
+    /// Upload the most recent pending buffer of every surface to the renderer,
+    /// and remove the textures of destroyed surfaces
+    ///
+    /// A failed upload does not hold up the other surfaces; its error is
+    /// returned with the surfaces that were uploaded.
+    pub fn flush_pending_uploads(&mut self, renderer: &mut VulkanRenderer) -> FlushedUploads {
+        let unbounded = std::mem::take(&mut self.damage_unbounded) || !self.pending_removals.is_empty();
+        let damage = std::mem::take(&mut self.pending_damage);
+        let mut flushed = FlushedUploads {
+            damage: (!unbounded).then_some(damage),
+            ..Default::default()
+        };
+
+        for surface_id in self.pending_removals.drain(..) {
+            if let Err(e) = renderer.remove_surface(surface_id) {
+                flushed.failed.push((surface_id, e));
             }
         }
-        Ok(())
+
+        for (surface_id, pending) in self.pending_uploads.drain() {
+            let uploaded = match pending.buffer {
+                SurfaceBuffer::Shm { data, width, height, stride: _, format } => {
+                    let format = match format {
+                        ShmFormat::Argb8888 | ShmFormat::Xrgb8888 => vk::Format::B8G8R8A8_UNORM,
+                        ShmFormat::Rgba8888 | ShmFormat::Rgbx8888 => vk::Format::R8G8B8A8_UNORM,
+                    };
+                    renderer.update_surface_buffer(surface_id, &data, width, height, format)
+                }
+                // TODO: Import DMA-BUFs once the renderer can sample them
+                // without a copy; until then their surfaces keep old content
+                SurfaceBuffer::DmaBuf { .. } => {
+                    debug!("Skipping DMA-BUF upload of surface {}", surface_id);
+                    continue;
+                }
+            };
+            match uploaded {
+                Ok(()) => {
+                    debug!("Updated surface {} with buffer from commit {}", surface_id, pending.serial);
+                    flushed.uploaded.push(surface_id);
+                }
+                Err(e) => flushed.failed.push((surface_id, e)),
+            }
+        }
+
+        flushed
+    }
+
+    /// Whether any surface has committed content waiting for upload, or a
+    /// destroyed surface's texture waits for removal
+    pub fn has_pending_uploads(&self) -> bool {
+        !self.pending_uploads.is_empty() || !self.pending_removals.is_empty()
+    }
+
+    /// Latest commit serial for a surface, if it has committed a buffer
+    pub fn commit_serial(&self, surface_id: u32) -> Option<u64> {
+        self.commit_serials.get(&surface_id).copied()
+    }
+
+    /// Number of committed buffers dropped because newer content replaced them
+    pub fn dropped_upload_count(&self) -> u64 {
+        self.dropped_uploads
+    }
+
+    /// Remove a destroyed surface; its texture is removed with the next flush
+    pub fn remove_surface(&mut self, surface_id: u32) {
+        self.pending_uploads.remove(&surface_id);
+        if self.commit_serials.remove(&surface_id).is_some() {
+            self.pending_removals.push(surface_id);
+        }
     }
-    
+
     /// Convert Wayland buffer to our surface buffer format
-    fn convert_wayland_buffer(&self, buffer: &WaylandBuffer) -> Result<SurfaceBuffer> {
+    fn convert_wayland_buffer(&self, buffer: &WlBuffer) -> Result<SurfaceBuffer> {
         // Try to handle as DMA-BUF first
         if let Ok(dmabuf) = dmabuf::get_dmabuf(buffer) {
-            debug!("Converting DMA-BUF: {}x{}, format: {:?}", 
-                   dmabuf.width(), dmabuf.height(), dmabuf.format());
-            
+            let size = dmabuf.size();
+            debug!("Converting DMA-BUF: {}x{}, format: {:?}",
+                   size.w, size.h, dmabuf.format());
+
             let format = match dmabuf.format().code {
                 // Common formats - map to our enum
-                fourcc::DRM_FORMAT_ARGB8888 => vulkan_renderer::surface_renderer::DmaBufFormat::Argb8888,
-                fourcc::DRM_FORMAT_XRGB8888 => vulkan_renderer::surface_renderer::DmaBufFormat::Xrgb8888,
-                fourcc::DRM_FORMAT_RGBA8888 => vulkan_renderer::surface_renderer::DmaBufFormat::Rgba8888,
-                fourcc::DRM_FORMAT_RGBX8888 => vulkan_renderer::surface_renderer::DmaBufFormat::Rgbx8888,
+                DrmFourcc::Argb8888 => DmaBufFormat::Argb8888,
+                DrmFourcc::Xrgb8888 => DmaBufFormat::Xrgb8888,
+                DrmFourcc::Rgba8888 => DmaBufFormat::Rgba8888,
+                DrmFourcc::Rgbx8888 => DmaBufFormat::Rgbx8888,
                 _ => {
                     warn!("Unsupported DMA-BUF format: {:?}", dmabuf.format());
-                    vulkan_renderer::surface_renderer::DmaBufFormat::Argb8888 // Fallback
+                    DmaBufFormat::Argb8888 // Fallback
                 }
             };
-            
+
+            let fd = dmabuf
+                .handles()
+                .next()
+                .ok_or_else(|| CompositorError::wayland("DMA-BUF without planes"))?;
             return Ok(SurfaceBuffer::DmaBuf {
-                width: dmabuf.width(),
-                height: dmabuf.height(),
+                width: size.w.max(0) as u32,
+                height: size.h.max(0) as u32,
                 format,
-                modifier: dmabuf.format().modifier,
-                fd: dmabuf.planes()[0].fd, // Use first plane's FD
+                modifier: dmabuf.format().modifier.into(),
+                fd: fd.as_raw_fd(), // Use first plane's FD
             });
         }
-        
+
         // Try to handle as SHM buffer
         if let Ok((data, shm_attributes)) = shm::with_buffer_contents(buffer, |ptr, len, data| {
-            let slice = unsafe { std::slice::from_raw_parts(ptr, len) };
-            (slice.to_vec(), data.clone())
+            let pool = unsafe { std::slice::from_raw_parts(ptr, len) };
+            (tightly_packed(pool, &data), data)
         }) {
-            debug!("Converting SHM buffer: {}x{}, format: {:?}", 
+            debug!("Converting SHM buffer: {}x{}, format: {:?}",
                    shm_attributes.width, shm_attributes.height, shm_attributes.format);
-            
+            let data = data.ok_or_else(|| CompositorError::wayland("SHM buffer exceeds its pool"))?;
+
             let format = match shm_attributes.format {
-                wayland_server::protocol::wl_shm::Format::Argb8888 => 
-                    vulkan_renderer::surface_renderer::ShmFormat::Argb8888,
-                wayland_server::protocol::wl_shm::Format::Xrgb8888 => 
-                    vulkan_renderer::surface_renderer::ShmFormat::Xrgb8888,
-                wayland_server::protocol::wl_shm::Format::Rgba8888 => 
-                    vulkan_renderer::surface_renderer::ShmFormat::Rgba8888,
-                wayland_server::protocol::wl_shm::Format::Rgbx8888 => 
-                    vulkan_renderer::surface_renderer::ShmFormat::Rgbx8888,
+                wl_shm::Format::Argb8888 => ShmFormat::Argb8888,
+                wl_shm::Format::Xrgb8888 => ShmFormat::Xrgb8888,
+                wl_shm::Format::Rgba8888 => ShmFormat::Rgba8888,
+                wl_shm::Format::Rgbx8888 => ShmFormat::Rgbx8888,
                 _ => {
                     warn!("Unsupported SHM format: {:?}", shm_attributes.format);
-                    vulkan_renderer::surface_renderer::ShmFormat::Argb8888 // Fallback
+                    ShmFormat::Argb8888 // Fallback
                 }
             };
-            
+
+            let width = shm_attributes.width.max(0) as u32;
             return Ok(SurfaceBuffer::Shm {
                 data,
-                width: shm_attributes.width as u32,
-                height: shm_attributes.height as u32,
-                stride: shm_attributes.stride as u32,
+                width,
+                height: shm_attributes.height.max(0) as u32,
+                stride: width * 4,
                 format,
             });
         }
-        
+
         Err(CompositorError::wayland("Unknown buffer type - not SHM or DMA-BUF"))
     }
-    
+
     /// Get number of active surfaces
     pub fn surface_count(&self) -> usize {
-        self.surface_mapping.len()
+        self.commit_serials.len()
     }
 }
 
-impl Drop for SurfaceManager {
-    fn drop(&mut self) {
-        info!("Surface manager shutting down with {} active surfaces", self.surface_count());
-        
-        // Clean up all surfaces
-        let wayland_ids: Vec<u64> = self.surface_mapping.keys().cloned().collect();
-        for wayland_id in wayland_ids {
-            if let Err(e) = self.remove_surface(wayland_id) {
-                error!("Failed to cleanup surface {}: {}", wayland_id, e);
-            }
-        }
+/// Rows of an SHM buffer without the padding of its stride, as the renderer
+/// expects 4 bytes per pixel; `None` if the buffer does not fit its pool
+fn tightly_packed(pool: &[u8], data: &shm::BufferData) -> Option<Vec<u8>> {
+    let row = data.width.max(0) as usize * 4;
+    let stride = data.stride.max(0) as usize;
+    let offset = data.offset.max(0) as usize;
+    let mut packed = Vec::with_capacity(row * data.height.max(0) as usize);
+    for y in 0..data.height.max(0) as usize {
+        let start = offset + y * stride;
+        packed.extend_from_slice(pool.get(start..start + row)?);
     }
+    Some(packed)
 }
@@ -0,0 +1,114 @@
+// Decoration style per surface class
+//
+// Corner radius, shadow and border come from the theme and can be
+// overridden per class of surface: normal windows, dialogs (toplevels with a
+// parent), popups and panels (layer-shell surfaces). Surfaces are classified
+// as they are created or reparented, and the decoration and effect passes
+// look up each surface's style by its ID. With the neomorphism theme, each
+// class is also extruded by its own shadow and highlight.
+
+use config::{NeomorphicStyle, SurfaceClass, SurfaceStyle, ThemeConfig, ThemeStyle};
+use smithay::reexports::wayland_server::backend::ObjectId;
+use smithay::wayland::shell::xdg::ToplevelSurface;
+use std::collections::HashMap;
+
+/// Surface classes and the theme they are styled by
+#[derive(Debug)]
+pub struct SurfaceStyles {
+    theme: ThemeConfig,
+    classes: HashMap<ObjectId, SurfaceClass>,
+}
+
+impl SurfaceStyles {
+    pub fn new(theme: ThemeConfig) -> Self {
+        Self { theme, classes: HashMap::new() }
+    }
+
+    /// Apply a new theme
+    pub fn set_theme(&mut self, theme: ThemeConfig) {
+        self.theme = theme;
+    }
+
+    /// Classify a new or reparented surface
+    pub fn assign(&mut self, surface: ObjectId, class: SurfaceClass) {
+        self.classes.insert(surface, class);
+    }
+
+    /// Forget a destroyed surface
+    pub fn remove(&mut self, surface: &ObjectId) {
+        self.classes.remove(surface);
+    }
+
+    /// Class of a surface; unknown surfaces are styled as windows
+    pub fn class(&self, surface: &ObjectId) -> SurfaceClass {
+        self.classes.get(surface).copied().unwrap_or(SurfaceClass::Window)
+    }
+
+    /// Decoration of a surface
+    pub fn style(&self, surface: &ObjectId) -> SurfaceStyle {
+        self.theme.surface_style(self.class(surface))
+    }
+
+    /// Corner radius of the surfaces whose radius differs from normal
+    /// windows', by protocol ID, for the renderer
+    pub fn corner_radii(&self) -> Vec<(u32, f32)> {
+        let default = self.theme.surface_style(SurfaceClass::Window).corner_radius;
+        self.classes
+            .iter()
+            .map(|(surface, &class)| (surface.protocol_id(), self.theme.surface_style(class).corner_radius))
+            .filter(|&(_, radius)| radius != default)
+            .collect()
+    }
+
+    /// Border width and color of the surfaces whose class has a border, by
+    /// protocol ID, for the renderer
+    pub fn borders(&self) -> Vec<(u32, f32, [f32; 4])> {
+        self.classes
+            .iter()
+            .map(|(surface, &class)| (surface.protocol_id(), self.theme.surface_style(class)))
+            .filter(|(_, style)| style.border_width > 0.0)
+            .map(|(surface_id, style)| (surface_id, style.border_width, style.border_color))
+            .collect()
+    }
+
+    /// Drop shadow intensity of the surfaces whose class casts one, by
+    /// protocol ID, for the renderer; empty with the neomorphism theme,
+    /// whose extrusion replaces drop shadows
+    pub fn shadows(&self) -> Vec<(u32, f32)> {
+        if self.theme.style() == ThemeStyle::Neomorphism {
+            return Vec::new();
+        }
+        self.classes
+            .iter()
+            .map(|(surface, &class)| (surface.protocol_id(), self.theme.surface_style(class).shadow_intensity))
+            .filter(|&(_, intensity)| intensity > 0.0)
+            .collect()
+    }
+
+    /// Extrusion of every classified surface by protocol ID, for the
+    /// renderer; empty unless the theme is neomorphic
+    pub fn neomorphic_surfaces(&self) -> Vec<(u32, NeomorphicStyle)> {
+        if self.theme.style() != ThemeStyle::Neomorphism {
+            return Vec::new();
+        }
+        self.classes
+            .iter()
+            .map(|(surface, &class)| (surface.protocol_id(), self.theme.neomorphic_style(class)))
+            .collect()
+    }
+}
+
+impl Default for SurfaceStyles {
+    fn default() -> Self {
+        Self::new(ThemeConfig::default())
+    }
+}
+
+/// Class of a toplevel: a dialog if it has a parent, a window otherwise
+pub fn toplevel_class(toplevel: &ToplevelSurface) -> SurfaceClass {
+    if toplevel.parent().is_some() {
+        SurfaceClass::Dialog
+    } else {
+        SurfaceClass::Window
+    }
+}
@@ -0,0 +1,79 @@
+// System suspend and resume
+//
+// While the session is inactive, e.g. switched to another VT or suspended,
+// the render loop draws nothing. When it is back, or the system woke from a
+// suspend the session did not report, the GPU state is recovered before the
+// next frame, since swapchain images and frames in flight do not survive
+// every driver's suspend. CLOCK_MONOTONIC stops while the system sleeps and
+// CLOCK_BOOTTIME does not, so their difference growing between two checks
+// means the system slept in between.
+//
+// TODO: Also pause on logind's PrepareForSleep once a D-Bus client
+// dependency is available, so no frame is in flight when the system sleeps
+
+use std::time::Duration;
+
+/// Sleep shorter than this is taken for clock jitter
+const MIN_SLEEP: Duration = Duration::from_millis(500);
+
+/// What the render loop should do after a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuspendState {
+    /// Draw as usual
+    Running,
+    /// Draw nothing until the session is back
+    Paused,
+    /// Recover the GPU state and redraw everything, then draw as usual
+    Resumed,
+}
+
+/// Notices the session going away and the system waking from sleep
+#[derive(Debug)]
+pub struct SuspendMonitor {
+    /// How far CLOCK_BOOTTIME was ahead of CLOCK_MONOTONIC at the last check
+    slept: Duration,
+    paused: bool,
+}
+
+impl SuspendMonitor {
+    pub fn new() -> Self {
+        Self { slept: sleep_time(), paused: false }
+    }
+
+    /// Check once per render loop iteration; `session_active` tells whether
+    /// the session has access to devices
+    pub fn check(&mut self, session_active: bool) -> SuspendState {
+        let slept = sleep_time();
+        let woke = slept.saturating_sub(self.slept) >= MIN_SLEEP;
+        self.slept = slept;
+        if !session_active {
+            self.paused = true;
+            return SuspendState::Paused;
+        }
+        if std::mem::take(&mut self.paused) || woke {
+            SuspendState::Resumed
+        } else {
+            SuspendState::Running
+        }
+    }
+}
+
+impl Default for SuspendMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Time the system spent suspended since boot
+fn sleep_time() -> Duration {
+    clock(libc::CLOCK_BOOTTIME).saturating_sub(clock(libc::CLOCK_MONOTONIC))
+}
+
+fn clock(id: libc::clockid_t) -> Duration {
+    let mut time = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: clock_gettime only writes the timespec we pass
+    if unsafe { libc::clock_gettime(id, &mut time) } != 0 {
+        return Duration::ZERO;
+    }
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
@@ -0,0 +1,266 @@
+// Live theme preview
+//
+// Before committing a theme to the configuration, users can see it applied to
+// sample compositor UI: an app bar, a window with its title bar and a button,
+// and a notification, on a floating card centered on the output. A preview
+// is opened over IPC with the candidate theme keys in TOML, applied over the
+// current theme so a few keys are enough, and stays open until closed over
+// IPC. Reloading the configuration re-applies the candidate keys over the new
+// theme.
+
+use compositor_utils::math::Rect;
+use compositor_utils::prelude::*;
+use config::{ColorToken, NeomorphicStyle, SurfaceClass, ThemeConfig, ThemeStyle};
+use ipc::protocol::ThemePreviewRequest;
+use smithay::utils::{Logical, Point, Rectangle, Size};
+use tokio::sync::mpsc;
+use vulkan_renderer::{neomorphic_primitives, NeomorphicParams, UiPrimitive};
+
+/// Size of the preview card in logical pixels
+const CARD_SIZE: (i32, i32) = (480, 320);
+
+/// Space between the card's edge and the sample UI in logical pixels
+const MARGIN: i32 = 16;
+
+/// Height of the sample app bar and title bar in logical pixels
+const BAR_HEIGHT: i32 = 32;
+
+/// Part of the sample UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewElementKind {
+    /// Card the sample UI is drawn on, in the theme background color
+    Card,
+    AppBar,
+    Window,
+    TitleBar,
+    Button,
+    Notification,
+}
+
+/// Rectangle of sample UI to draw, styled by the previewed theme
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewElement {
+    pub kind: PreviewElementKind,
+    /// Position in global logical coordinates
+    pub rect: Rectangle<i32, Logical>,
+    pub color: [f32; 4],
+    pub corner_radius: f32,
+    pub border_width: f32,
+    pub border_color: [f32; 4],
+    pub shadow_intensity: f32,
+    /// Extrusion in the neomorphic rendering mode
+    pub neomorphic: Option<NeomorphicStyle>,
+    /// Sample text and its color
+    pub label: Option<(&'static str, [f32; 4])>,
+}
+
+/// Theme preview opened over IPC
+#[derive(Debug)]
+pub struct ThemePreview {
+    sender: mpsc::UnboundedSender<ThemePreviewRequest>,
+    receiver: mpsc::UnboundedReceiver<ThemePreviewRequest>,
+    /// Configured theme the candidate keys apply over
+    base: ThemeConfig,
+    /// Candidate theme keys in TOML and the theme they make
+    preview: Option<(String, ThemeConfig)>,
+    /// The preview opened, changed or closed since the last frame
+    changed: bool,
+}
+
+impl ThemePreview {
+    pub fn new(base: ThemeConfig) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self { sender, receiver, base, preview: None, changed: false }
+    }
+
+    /// Channel for IPC to open and close previews
+    pub fn sender(&self) -> mpsc::UnboundedSender<ThemePreviewRequest> {
+        self.sender.clone()
+    }
+
+    /// Apply the candidate keys over a new configured theme, e.g. after a
+    /// config reload
+    pub fn set_base(&mut self, base: ThemeConfig) {
+        self.base = base;
+        let Some((overrides, _)) = self.preview.take() else { return };
+        self.open(overrides);
+    }
+
+    /// Apply requests received since the last call
+    pub fn process_requests(&mut self) {
+        while let Ok(request) = self.receiver.try_recv() {
+            match request {
+                ThemePreviewRequest::Open { theme } => self.open(theme),
+                ThemePreviewRequest::Close => {
+                    if self.preview.take().is_some() {
+                        info!("Theme preview closed");
+                        self.changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether a preview is shown
+    pub fn is_open(&self) -> bool {
+        self.preview.is_some()
+    }
+
+    /// Theme shown in the preview
+    pub fn theme(&self) -> Option<&ThemeConfig> {
+        self.preview.as_ref().map(|(_, theme)| theme)
+    }
+
+    /// Whether the preview needs redrawing; clears the flag
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Sample UI to draw over everything on an output, back to front
+    pub fn elements(&self, output: Rectangle<i32, Logical>) -> Vec<PreviewElement> {
+        let Some(theme) = self.theme() else { return Vec::new() };
+        let card_size = Size::from(CARD_SIZE);
+        let card_offset = Point::from(((output.size.w - card_size.w) / 2, (output.size.h - card_size.h) / 2));
+        let card = Rectangle::new(output.loc + card_offset, card_size);
+        let inner_width = card.size.w - 2 * MARGIN;
+        let app_bar = Rectangle::new(card.loc + Point::from((MARGIN, MARGIN)), Size::from((inner_width, BAR_HEIGHT)));
+        let window = Rectangle::new(
+            card.loc + Point::from((MARGIN, 2 * MARGIN + BAR_HEIGHT)),
+            Size::from((inner_width * 2 / 3, card.size.h - 3 * MARGIN - BAR_HEIGHT)),
+        );
+        let title_bar = Rectangle::new(window.loc, Size::from((window.size.w, BAR_HEIGHT)));
+        let button = Rectangle::new(
+            window.loc + Point::from((window.size.w - MARGIN - 80, window.size.h - MARGIN - BAR_HEIGHT)),
+            Size::from((80, BAR_HEIGHT)),
+        );
+        let notification_width = inner_width - window.size.w - MARGIN;
+        let notification = Rectangle::new(
+            Point::from((window.loc.x + window.size.w + MARGIN, window.loc.y)),
+            Size::from((notification_width, 2 * BAR_HEIGHT)),
+        );
+
+        let palette = theme.palette();
+        let on_surface = palette.color(ColorToken::OnSurface);
+        let element = |kind, rect, class, color, label| {
+            let style = theme.surface_style(class);
+            PreviewElement {
+                kind,
+                rect,
+                color,
+                corner_radius: style.corner_radius,
+                border_width: style.border_width,
+                border_color: style.border_color,
+                shadow_intensity: style.shadow_intensity,
+                neomorphic: (theme.style() == ThemeStyle::Neomorphism).then(|| theme.neomorphic_style(class)),
+                label,
+            }
+        };
+        let card_color = match theme.style() {
+            ThemeStyle::Glassmorphism => palette.color(ColorToken::Background),
+            ThemeStyle::Neomorphism => theme.neomorphic_surface_color(),
+        };
+        vec![
+            PreviewElement {
+                border_width: 0.0,
+                shadow_intensity: 0.0,
+                neomorphic: None,
+                ..element(PreviewElementKind::Card, card, SurfaceClass::Window, card_color, None)
+            },
+            element(PreviewElementKind::AppBar, app_bar, SurfaceClass::Panel, palette.color(ColorToken::Surface), Some(("12:00", on_surface))),
+            element(PreviewElementKind::Window, window, SurfaceClass::Window, palette.color(ColorToken::Surface), None),
+            PreviewElement {
+                border_width: 0.0,
+                shadow_intensity: 0.0,
+                neomorphic: None,
+                ..element(
+                    PreviewElementKind::TitleBar,
+                    title_bar,
+                    SurfaceClass::Window,
+                    palette.color(ColorToken::SurfaceVariant),
+                    Some(("Sample window", on_surface)),
+                )
+            },
+            PreviewElement {
+                corner_radius: theme.surface_style(SurfaceClass::Popup).corner_radius.min(BAR_HEIGHT as f32 / 2.0),
+                border_width: 0.0,
+                ..element(
+                    PreviewElementKind::Button,
+                    button,
+                    SurfaceClass::Popup,
+                    palette.color(ColorToken::Accent),
+                    Some(("Button", palette.color(ColorToken::OnAccent))),
+                )
+            },
+            element(
+                PreviewElementKind::Notification,
+                notification,
+                SurfaceClass::Popup,
+                palette.color(ColorToken::Surface),
+                Some(("Notification", on_surface)),
+            ),
+        ]
+    }
+
+    /// Sample UI on an output as the renderer draws it, back to front
+    pub fn primitives(&self, output: Rectangle<i32, Logical>) -> Vec<UiPrimitive> {
+        let Some(theme) = self.theme() else { return Vec::new() };
+        let mut primitives = Vec::new();
+        for element in self.elements(output) {
+            let rect = Rect::new(
+                element.rect.loc.x as f32,
+                element.rect.loc.y as f32,
+                element.rect.size.w as f32,
+                element.rect.size.h as f32,
+            );
+            match element.neomorphic {
+                Some(style) => primitives.extend(neomorphic_primitives(
+                    rect,
+                    element.corner_radius,
+                    &NeomorphicParams {
+                        distance: style.distance,
+                        blur: style.blur,
+                        shadow_intensity: style.shadow_intensity,
+                        highlight_intensity: style.highlight_intensity,
+                    },
+                    theme.neomorphism.light_angle,
+                    theme.neomorphic_surface_color(),
+                )),
+                None if element.shadow_intensity > 0.0 => {
+                    primitives.push(UiPrimitive::drop_shadow(rect, element.corner_radius, element.shadow_intensity));
+                }
+                None => {}
+            }
+            primitives.push(UiPrimitive::Rect { rect, color: element.color, corner_radius: element.corner_radius });
+            if element.border_width > 0.0 {
+                primitives.push(UiPrimitive::Outline {
+                    rect,
+                    width: element.border_width,
+                    color: element.border_color,
+                    corner_radius: element.corner_radius,
+                });
+            }
+        }
+        primitives
+    }
+
+    /// Show a preview of the candidate keys over the configured theme
+    fn open(&mut self, overrides: String) {
+        match self.base.with_overrides(&overrides) {
+            Ok(theme) => {
+                info!("Previewing theme {:?}", theme.name);
+                self.preview = Some((overrides, theme));
+            }
+            Err(e) => {
+                warn!("Cannot preview theme: {}", e);
+                self.preview = None;
+            }
+        }
+        self.changed = true;
+    }
+}
+
+impl Default for ThemePreview {
+    fn default() -> Self {
+        Self::new(ThemeConfig::default())
+    }
+}
@@ -0,0 +1,154 @@
+// Urgent windows
+//
+// A window asks for attention by ringing the bell or by activating an
+// xdg-activation token it may not take focus with, e.g. a chat client
+// receiving a message in the background. Until it gets keyboard focus it is
+// outlined in the theme's accent color, pulsing a few times and then staying
+// lit. IPC subscribers learn about it, and the app bar highlights the dock
+// entry of the window's app. Pulses step at the window animation rate and
+// frames in between interpolate.
+
+use compositor_utils::animation_tick::{Interpolated, Lerp};
+use smithay::reexports::wayland_server::backend::ObjectId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Width of the border of urgent windows in pixels
+pub const BORDER_WIDTH: f32 = 3.0;
+
+/// Duration of one pulse of the border
+const PULSE_PERIOD: Duration = Duration::from_millis(800);
+
+/// Pulses before the border stays lit
+const PULSE_COUNT: u32 = 3;
+
+/// Border strength at the low point of a pulse
+const PULSE_LOW: f32 = 0.25;
+
+/// When a window became urgent and its border strength
+#[derive(Debug, Clone)]
+struct UrgentWindow {
+    app_id: String,
+    since: Instant,
+    /// Border strength of the last two animation steps
+    strength: Interpolated<f32>,
+}
+
+/// Windows waiting for the user's attention
+#[derive(Debug)]
+pub struct UrgentWindows {
+    accent_color: [f32; 4],
+    windows: HashMap<ObjectId, UrgentWindow>,
+    last_step: Option<Instant>,
+    /// App IDs whose dock entries are highlighted, for the app bar
+    urgent_apps: watch::Sender<Vec<String>>,
+}
+
+impl UrgentWindows {
+    pub fn new(accent_color: [f32; 4]) -> Self {
+        Self {
+            accent_color,
+            windows: HashMap::new(),
+            last_step: None,
+            urgent_apps: watch::channel(Vec::new()).0,
+        }
+    }
+
+    /// Outline urgent windows in a new accent color
+    pub fn set_accent_color(&mut self, color: [f32; 4]) {
+        self.accent_color = color;
+    }
+
+    /// Mark a window of an app urgent; returns false if it already was
+    pub fn set_urgent(&mut self, window: ObjectId, app_id: &str, now: Instant) -> bool {
+        if self.windows.contains_key(&window) {
+            return false;
+        }
+        let urgent = UrgentWindow { app_id: app_id.to_string(), since: now, strength: Interpolated::new(1.0) };
+        self.windows.insert(window, urgent);
+        self.publish();
+        true
+    }
+
+    /// Clear a window's urgency when it gets focus or is destroyed; returns
+    /// whether it was urgent
+    pub fn clear(&mut self, window: &ObjectId) -> bool {
+        if self.windows.remove(window).is_none() {
+            return false;
+        }
+        self.publish();
+        true
+    }
+
+    /// Whether a window waits for attention
+    pub fn is_urgent(&self, window: &ObjectId) -> bool {
+        self.windows.contains_key(window)
+    }
+
+    /// Step border pulses to `now`
+    pub fn advance(&mut self, now: Instant) {
+        self.last_step = Some(now);
+        for window in self.windows.values_mut() {
+            window.strength.step(pulse(now.saturating_duration_since(window.since)));
+        }
+    }
+
+    /// Whether a border is still pulsing
+    pub fn is_animating(&self) -> bool {
+        self.windows.values().any(|window| {
+            !window.strength.is_settled()
+                || self.last_step.is_none_or(|last| last < window.since + PULSE_PERIOD * PULSE_COUNT)
+        })
+    }
+
+    /// Border width and color of urgent windows by protocol ID, `alpha` of
+    /// the way from the previous animation step to the last, for the renderer
+    pub fn borders(&self, alpha: f32) -> Vec<(u32, f32, [f32; 4])> {
+        let [r, g, b, a] = self.accent_color;
+        self.windows
+            .iter()
+            .map(|(window, urgent)| (window.protocol_id(), BORDER_WIDTH, [r, g, b, a * urgent.strength.value(alpha)]))
+            .collect()
+    }
+
+    /// Channel for the app bar to follow which dock entries are highlighted
+    pub fn urgent_apps_receiver(&self) -> watch::Receiver<Vec<String>> {
+        self.urgent_apps.subscribe()
+    }
+
+    /// Publish the app IDs with urgent windows
+    fn publish(&self) {
+        let mut app_ids: Vec<String> = self
+            .windows
+            .values()
+            .map(|window| window.app_id.clone())
+            .filter(|app_id| !app_id.is_empty())
+            .collect();
+        app_ids.sort();
+        app_ids.dedup();
+        self.urgent_apps.send_if_modified(|urgent_apps| {
+            if *urgent_apps == app_ids {
+                return false;
+            }
+            *urgent_apps = app_ids;
+            true
+        });
+    }
+}
+
+impl Default for UrgentWindows {
+    fn default() -> Self {
+        Self::new(config::ThemeConfig::default().color(config::ColorToken::Accent))
+    }
+}
+
+/// Border strength `elapsed` after a window became urgent: full at first,
+/// dipping once per pulse and full again after the last
+fn pulse(elapsed: Duration) -> f32 {
+    if elapsed >= PULSE_PERIOD * PULSE_COUNT {
+        return 1.0;
+    }
+    let phase = elapsed.as_secs_f32() / PULSE_PERIOD.as_secs_f32();
+    PULSE_LOW.lerp(1.0, 0.5 + 0.5 * (phase * std::f32::consts::TAU).cos())
+}
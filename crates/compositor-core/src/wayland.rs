@@ -54,6 +54,7 @@
 //! - `security_context` - Application sandboxing and privilege separation
 //! - `idle_inhibit` - Power management integration
 //! - `keyboard_shortcuts_inhibit` - Gaming and full-screen application support
+//! - `xwayland_keyboard_grab` - Exclusive keyboard grabs for on-screen keyboards and remote control
 //!
 //! ### Advanced Features
 //! - `xdg_foreign` - Cross-surface window embedding
@@ -99,10 +100,74 @@
 
 // filepath: /home/shane/vscode/custom_compositor/crates/compositor-core/src/wayland.rs
 use compositor_utils::prelude::*;
-use vulkan_renderer::VulkanRenderer;
+use vulkan_renderer::{DimmingSettings, NeomorphicParams, ReadbackRegion, SurfaceBlurRequest, UiPrimitive, VulkanRenderer};
+use config::{BindingAction, HotCornerAction};
+use crate::osk::OnScreenKeyboard;
+use crate::zoom::WindowZoomManager;
+use crate::focus_mode::{self, FocusModeState};
+use crate::blur::BlurManager;
+use crate::show_desktop::ShowDesktop;
+use crate::workspace::{WorkspaceManager, DEFAULT_WORKSPACE_COUNT};
+use crate::stacking::{StackLayer, StackingOrder};
+use crate::layer_focus::LayerFocus;
+use crate::workspace_theme::WorkspaceThemes;
+use crate::output_power::{OutputPower, PowerChanges};
+use crate::lid::{self, LidResponse, LidSwitch};
+use crate::surface_style::{toplevel_class, SurfaceStyles};
+use crate::urgency::UrgentWindows;
+use crate::automation::AutomationQueue;
+use crate::kiosk::KioskSupervisor;
+use crate::wayland_socket;
+use crate::startup_feedback::{launch_command, LaunchQueue, StartupFeedback};
+use crate::layout_snapshot::{launch_app, LayoutSnapshot, LayoutStore, PendingRestore, WindowSnapshot};
+use crate::window_transaction::{PendingTransaction, TransactionQueue};
+use crate::accessibility::WindowAccessibility;
+use crate::click_assist::{AssistAction, ButtonDisposition, ClickAssist};
+use crate::hot_corners::HotCornerTracker;
+use crate::cursor_visibility::CursorVisibility;
+use crate::pointer_barriers::{OutputArea, PointerBarriers};
+use crate::move_grab::MoveSurfaceGrab;
+use crate::resize_grab::{InteractiveResize, ResizeSurfaceGrab};
+use crate::responsiveness::{ResponsivenessMonitor, UnresponsiveChoice};
+use crate::kill_mode::{ClientProcess, KillMode, KillTarget};
+use crate::dialog::{Dialog, DialogHit, DialogKey, DialogStyle};
+use ui_framework::annotation::{AnnotationCommand, AnnotationOverlay};
+use ui_framework::focus::NavigationKey;
+use crate::region_select::RegionSelection;
+use crate::quick_settings::QuickSettings;
+use crate::color_picker::ColorPicker;
+use crate::screenshot::{Screenshot, ScreenshotTarget, Screenshots};
+use crate::input::KeyBindings;
+use crate::geometry::ToGeometry;
+use crate::libinput::{libinput_backend, LibinputDevices};
+use crate::session::DeviceAccess;
+use crate::placement::WindowPlacer;
+use crate::animation_rate::{AnimationClass, AnimationRates};
+use crate::frame_callbacks::{FrameCallbackQueue, FramePresented};
+use crate::credentials::{ClientCredentials, SecurityPolicy};
+use crate::resource_usage::{BufferUsage, ClientResourceTracker};
+use crate::buffer_formats::{BufferFormat, BufferFormatStats};
+use crate::frame_rate_cap::FrameRateCaps;
+use crate::app_scale::AppScales;
+use crate::window_identity::{self, WindowIdentities};
+use crate::latency::ProtocolLatencyTracker;
+use crate::keyboard_grab::{ExclusiveKeyboardGrab, KeyboardGrabData, KeyboardGrabGlobalData, KeyboardGrabHandler, KeyboardGrabState};
+use crate::theme_preview::ThemePreview;
+use crate::frame_scheduler::DEFAULT_REFRESH_MHZ;
+use crate::surface_manager::SurfaceManager;
+use crate::render_state::{FrameReadback, RenderOutput, RenderState, RenderStateChannel};
+use crate::screencopy::{ScreencopyFrameData, ScreencopyGlobalData, ScreencopyHandler, ScreencopyState};
+use crate::output_config::{map_absolute_position, output_transform, rotate_transform, snap_scale, AutoRotation, OutputRequests, RotationDirection};
+use compositor_utils::accessibility::Politeness;
+use std::collections::HashSet;
+use compositor_utils::accessibility::AccessibilityTree;
+use compositor_utils::frame_stats::FrameStatistics;
+use compositor_utils::math::Rect;
+use ipc::protocol::{AutomationRequest, ClientProcessInfo, DisplayTransform, LayoutRequest, OutputInfo, SyntheticInput, WindowGeometry, WindowOperation};
 // Graphics and buffer format handling
 use drm_fourcc::{DrmFourcc, DrmModifier};
 use std::os::fd::OwnedFd;
+use std::os::unix::net::UnixStream;
 use wayland_server::Resource;
 use nix::libc;
 // Smithay framework - High-performance Wayland compositor building blocks
@@ -110,62 +175,88 @@ use smithay::{
     // Hardware abstraction layer for GPU and display devices
     backend::{
         allocator::{dmabuf::Dmabuf, Buffer, Format, gbm::GbmDevice},
+        input::{
+            AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device as _, DeviceCapability, Event as _, InputBackend, InputEvent, KeyState,
+            KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionEvent, Switch, SwitchState,
+            SwitchToggleEvent, TouchEvent,
+        },
         drm::{DrmNode, DrmDeviceFd},
         egl::{EGLContext, EGLDisplay},
+        renderer::buffer_dimensions,
     },
     utils::DeviceFd,
     
     // Desktop environment abstractions
-    desktop::{Space, Window},
+    desktop::{layer_map_for_output, Space, Window, WindowSurfaceType},
     
     // Input handling and seat management
-    input::{Seat, SeatHandler, SeatState, pointer::PointerHandle},
+    input::{
+        Seat, SeatHandler, SeatState,
+        keyboard::{FilterResult, Keycode, Keysym, XkbConfig},
+        pointer::{AxisFrame, ButtonEvent, Focus, MotionEvent, PointerHandle, RelativeMotionEvent},
+        touch::{DownEvent, MotionEvent as TouchMotionEvent, UpEvent},
+    },
     
     // Display output management
-    output::{Output, PhysicalProperties, Subpixel},
+    output::{Output, PhysicalProperties, Scale, Subpixel},
     wayland::output::{OutputHandler, OutputManagerState},
     
     // Core framework components
     reexports::{
-        calloop::{EventLoop, LoopSignal},
+        calloop::{generic::Generic, EventLoop, Interest, LoopHandle, LoopSignal, Mode as EventMode, PostAction},
         wayland_server::{
-            backend::{ClientData, ClientId, DisconnectReason},
+            backend::{ClientData, ClientId, DisconnectReason, ObjectId},
             protocol::wl_surface::WlSurface,
             protocol::wl_seat::WlSeat,
-            Display,
+            protocol::{wl_buffer::WlBuffer, wl_output},
+            Display, DisplayHandle,
         },
         wayland_protocols::xdg::{
-            shell::server::xdg_toplevel::XdgToplevel,
+            shell::server::xdg_toplevel::{self, XdgToplevel},
+        },
+        wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1::TrancheFlags,
+        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
+        wayland_protocols::xwayland::keyboard_grab::zv1::server::{
+            zwp_xwayland_keyboard_grab_manager_v1::ZwpXwaylandKeyboardGrabManagerV1,
+            zwp_xwayland_keyboard_grab_v1::ZwpXwaylandKeyboardGrabV1,
+        },
+        wayland_protocols_wlr::screencopy::v1::server::{
+            zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1,
+            zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1,
         },
     },
     
     // Utility types for timing and geometry
-    utils::{Clock, Monotonic, Serial, Point, Logical},
+    utils::{Clock, Monotonic, Serial, Point, Logical, Rectangle, Size, Transform, SERIAL_COUNTER},
     wayland::{
         buffer::BufferHandler,
-        compositor::{CompositorClientState, CompositorHandler, CompositorState, with_states},
-        dmabuf::{DmabufHandler, DmabufState, DmabufGlobal, ImportNotifier},
+        compositor::{
+            add_destruction_hook, get_parent, send_surface_state, BufferAssignment, CompositorClientState, CompositorHandler,
+            CompositorState, Damage, SurfaceAttributes, with_states,
+        },
+        dmabuf::{get_dmabuf, DmabufFeedback, DmabufFeedbackBuilder, DmabufHandler, DmabufState, DmabufGlobal, ImportNotifier},
         drm_syncobj::{DrmSyncobjHandler, DrmSyncobjState, supports_syncobj_eventfd},
-        pointer_constraints::{PointerConstraintsHandler, PointerConstraintsState},
-        presentation::PresentationState,
+        pointer_constraints::{with_pointer_constraint, PointerConstraintsHandler, PointerConstraintsState},
+        presentation::{PresentationFeedbackCachedState, PresentationState, Refresh},
         relative_pointer::RelativePointerManagerState,
         selection::{
-            SelectionHandler,
+            SelectionHandler, SelectionTarget,
             primary_selection::{PrimarySelectionHandler, PrimarySelectionState},
-            data_device::{DataDeviceHandler, DataDeviceState, ClientDndGrabHandler, ServerDndGrabHandler},
+            data_device::{set_data_device_selection, DataDeviceHandler, DataDeviceState, ClientDndGrabHandler, ServerDndGrabHandler},
         },
         tablet_manager::{TabletManagerState, TabletSeatHandler},
         shell::{
             xdg::{
-                PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState,
+                Configure, PopupSurface, PositionerState, ShellClient, SurfaceCachedState, ToplevelSurface, XdgShellHandler,
+                XdgShellState, XdgToplevelSurfaceData,
                 decoration::{XdgDecorationHandler, XdgDecorationState},
             },
-            wlr_layer::{WlrLayerShellHandler, WlrLayerShellState, LayerSurface, Layer},
+            wlr_layer::{WlrLayerShellHandler, WlrLayerShellState, LayerSurface, LayerSurfaceCachedState, Layer},
         },
 
-        shm::{ShmHandler, ShmState},
+        shm::{shm_format_to_fourcc, with_buffer_contents, ShmHandler, ShmState},
         viewporter::ViewporterState,
-        fractional_scale::{FractionalScaleHandler, FractionalScaleManagerState},
+        fractional_scale::{with_fractional_scale, FractionalScaleHandler, FractionalScaleManagerState},
         content_type::ContentTypeState,
         alpha_modifier::AlphaModifierState,
         single_pixel_buffer::SinglePixelBufferState,
@@ -181,13 +272,12 @@ use smithay::{
         keyboard_shortcuts_inhibit::{KeyboardShortcutsInhibitHandler, KeyboardShortcutsInhibitState},
         pointer_gestures::PointerGesturesState,
         virtual_keyboard::VirtualKeyboardManagerState,
-        text_input::TextInputManagerState,
+        text_input::{TextInputManagerState, TextInputSeat},
         input_method::{InputMethodHandler, InputMethodManagerState},
         session_lock::{SessionLockHandler, SessionLockManagerState},
-        security_context::{SecurityContextHandler, SecurityContextState},
-        xdg_activation::{XdgActivationHandler, XdgActivationState},
+        security_context::{SecurityContext, SecurityContextHandler, SecurityContextListenerSource, SecurityContextState},
+        xdg_activation::{XdgActivationHandler, XdgActivationState, XdgActivationToken, XdgActivationTokenData},
         foreign_toplevel_list::{ForeignToplevelListState, ForeignToplevelListHandler},
-        socket::ListeningSocketSource,
         // Test import for xdg_system_bell protocol
         xdg_system_bell::{XdgSystemBellHandler, XdgSystemBellState},
     },
@@ -222,11 +312,50 @@ pub struct ClientState {
     /// needs to track for each client, including surface management data,
     /// buffer tracking, and client capability information.
     pub compositor_state: CompositorClientState,
+    
+    /// Peer credentials captured when the client connected
+    ///
+    /// Identifies the client's process for security policy matching, IPC
+    /// window queries and disconnect diagnostics.
+    pub credentials: Option<ClientCredentials>,
+    
+    /// Connected through a restricted socket or a security context
+    ///
+    /// Restricted clients never see privileged globals, whatever their
+    /// credentials.
+    pub restricted: bool,
+    
+    /// Security context the client connected through, for sandboxed clients
+    ///
+    /// Names the sandbox engine and application as reported by the sandbox.
+    pub security_context: Option<SecurityContext>,
+}
+
+impl ClientState {
+    /// Describe the client's process for log messages
+    fn describe(&self) -> String {
+        match &self.credentials {
+            Some(credentials) => credentials.to_string(),
+            None => "unknown process".to_string(),
+        }
+    }
 }
 
 impl ClientData for ClientState {
-    fn initialized(&self, _client_id: ClientId) {}
-    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    fn initialized(&self, client_id: ClientId) {
+        debug!("Client {:?} connected: {}", client_id, self.describe());
+    }
+    
+    fn disconnected(&self, client_id: ClientId, reason: DisconnectReason) {
+        match reason {
+            DisconnectReason::ProtocolError(error) => {
+                warn!("Client {:?} ({}) disconnected after protocol error: {}", client_id, self.describe(), error);
+            }
+            DisconnectReason::ConnectionClosed => {
+                debug!("Client {:?} disconnected: {}", client_id, self.describe());
+            }
+        }
+    }
 }
 
 /// Main Wayland server state containing all protocol implementations and compositor resources
@@ -272,6 +401,8 @@ impl ClientData for ClientState {
 /// - `security_context_state` - Application sandboxing
 /// - `idle_inhibit_manager_state` - Power management integration
 /// - `keyboard_shortcuts_inhibit_state` - Gaming mode support
+/// - `keyboard_grab_state` - Exclusive keyboard grabs
+/// - `screencopy_state` - Screen capture for privileged clients
 ///
 /// ### Advanced Features
 /// - `xdg_foreign_state` - Cross-surface window embedding
@@ -344,6 +475,10 @@ pub struct WaylandServerState {
     /// and modifiers to clients for optimal GPU buffer compatibility.
     pub dmabuf_global: DmabufGlobal,
     
+    /// Formats and modifiers advertised over linux-dmabuf, those the GPU can
+    /// sample once the renderer reported them
+    pub dmabuf_formats: Vec<Format>,
+    
     /// DRM synchronization object state for explicit GPU sync (drm-syncobj)
     ///
     /// Provides frame-perfect GPU synchronization using kernel DRM sync objects,
@@ -414,6 +549,20 @@ pub struct WaylandServerState {
     /// multi-seat configurations and input device hotplugging.
     pub seat_state: SeatState<Self>,
     
+    /// The seat with the keyboard, pointer and touch of libinput devices
+    pub seat: Seat<Self>,
+    
+    /// Buttons whose press the compositor consumed, e.g. a kill mode click,
+    /// so their release does not reach clients either
+    intercepted_buttons: HashSet<u32>,
+    
+    /// Keys whose press answered a compositor dialog, so their release does
+    /// not reach clients either
+    intercepted_keys: HashSet<Keycode>,
+    
+    /// libinput devices and the settings applied to them
+    pub libinput_devices: LibinputDevices,
+    
     /// Relative pointer state for 3D navigation and gaming (relative-pointer)
     ///
     /// Provides raw pointer input for 3D viewport navigation, gaming, and
@@ -436,25 +585,35 @@ pub struct WaylandServerState {
     ///
     /// Enables professional graphics tablet integration with pressure sensitivity,
     /// tilt detection, and tool recognition for digital art workflows.
-    pub tablet_manager_state: TabletManagerState,
+    /// `None` when disabled in the protocols configuration.
+    pub tablet_manager_state: Option<TabletManagerState>,
     
     /// Virtual keyboard integration state (virtual-keyboard)
     ///
     /// Provides software keyboard integration for touch interfaces and
     /// accessibility applications requiring on-screen keyboards.
-    pub virtual_keyboard_manager_state: VirtualKeyboardManagerState,
+    /// `None` when input methods are disabled in the protocols configuration.
+    pub virtual_keyboard_manager_state: Option<VirtualKeyboardManagerState>,
     
     /// Advanced text input state with IME support (text-input)
     ///
     /// Enables sophisticated text input with input method editor (IME) support
     /// for international text input and complex writing systems.
-    pub text_input_manager_state: TextInputManagerState,
+    /// `None` when input methods are disabled in the protocols configuration.
+    pub text_input_manager_state: Option<TextInputManagerState>,
     
     /// Input method editor integration state (input-method)
     ///
     /// Provides deep integration with input method editors for seamless
     /// international text input and composition.
-    pub input_method_manager_state: InputMethodManagerState,
+    /// `None` when input methods are disabled in the protocols configuration.
+    pub input_method_manager_state: Option<InputMethodManagerState>,
+    
+    /// On-screen keyboard integration for touch sessions
+    ///
+    /// Shows a virtual keyboard when text-input focus enters an editable field
+    /// on sessions without a physical keyboard, and hides it when focus leaves.
+    pub on_screen_keyboard: OnScreenKeyboard,
     
     // ============================================================================
     // Selection and Data Transfer Protocols - Clipboard and DnD
@@ -540,6 +699,18 @@ pub struct WaylandServerState {
     /// gaming, full-screen applications, and kiosk modes.
     pub keyboard_shortcuts_inhibit_state: KeyboardShortcutsInhibitState,
     
+    /// Exclusive keyboard grabs (xwayland-keyboard-grab)
+    ///
+    /// Lets on-screen keyboards, remote desktop servers and Xwayland receive
+    /// every key press, subject to the keyboard grab policy.
+    pub keyboard_grab_state: KeyboardGrabState,
+    
+    /// Screen capture (wlr-screencopy)
+    ///
+    /// Lets screenshot and recording tools the security policy allows copy
+    /// composed frames into their buffers.
+    pub screencopy_state: ScreencopyState,
+    
     /// System notification and audio feedback (xdg-system-bell)
     ///
     /// Provides system bell functionality with audio feedback and visual
@@ -566,12 +737,314 @@ pub struct WaylandServerState {
     /// within the compositor's coordinate system.
     pub space: Space<Window>,
     
+    /// Per-window content magnification for accessibility
+    ///
+    /// Tracks windows whose content is enlarged within their own geometry,
+    /// independent of full-screen zoom, keyed by toplevel surface.
+    pub window_zoom: WindowZoomManager,
+    
+    /// Do-not-disturb / focus mode state
+    ///
+    /// Decides when notifications are suppressed and unfocused windows dimmed,
+    /// based on schedule, focused application and IPC overrides.
+    pub focus_mode: FocusModeState,
+    
+    /// Notification layer surfaces held back while focus mode is active,
+    /// with their layer and namespace, mapped once it ends
+    suppressed_layer_surfaces: Vec<(LayerSurface, Layer, String)>,
+    
+    /// Rule-based background blur behind client surfaces
+    ///
+    /// Tracks which toplevels and layer surfaces request blur behind their
+    /// transparent regions, masked by each surface's opaque region.
+    pub blur: BlurManager,
+    
+    /// Show desktop / peek state
+    ///
+    /// Remembers where windows were before they were slid aside so the
+    /// previous layout can be restored.
+    pub show_desktop: ShowDesktop,
+    
+    /// Per-output workspaces and window placement
+    ///
+    /// Decides which windows are visible on the active workspace; sticky
+    /// windows are shown on every workspace of their output.
+    pub workspaces: WorkspaceManager,
+    
+    /// Wallpaper and accent color of each output's active workspace
+    ///
+    /// Cross-fades between the old and new workspace appearance whenever an
+    /// output switches workspaces.
+    pub workspace_themes: WorkspaceThemes,
+    
+    /// Dimming and turning off outputs after inactivity
+    pub output_power: OutputPower,
+    
+    /// Laptop lid state and the built-in outputs turned off for it
+    pub lid: LidSwitch,
+    
+    /// Outputs turned off for the lid, with where they were in the layout
+    disabled_outputs: Vec<(Output, Point<i32, Logical>)>,
+    
+    /// Output layouts applied when exactly their outputs are in use
+    output_profiles: Vec<config::OutputProfile>,
+    
+    /// Stacking order of windows, layer surfaces and lock surfaces
+    ///
+    /// The renderer draws surfaces in this order; fullscreen windows stack
+    /// above panels and the lock screen above everything.
+    pub stacking: StackingOrder,
+    
+    /// Keyboard interactivity of layer surfaces
+    ///
+    /// Keeps focus on exclusive panels and prompts and away from layer
+    /// surfaces that do not take keyboard input.
+    pub layer_focus: LayerFocus,
+    
+    /// Pointer warp, focus and synthetic input requests from IPC
+    ///
+    /// Drained once per event loop iteration and applied through the seat
+    /// so automation behaves exactly like real input.
+    pub automation: AutomationQueue,
+    
+    /// Kiosk application supervisor
+    ///
+    /// While kiosk mode is active the kiosk application is kept running and
+    /// fullscreen, and window management is turned off.
+    pub kiosk: KioskSupervisor,
+    
+    /// Saved window layouts and layout requests from IPC
+    ///
+    /// Snapshots of windows, outputs and workspaces are written to and read
+    /// from the layout directory on the compositor thread.
+    pub layouts: LayoutStore,
+    
+    /// Windows of a restored layout waiting for their application to start
+    ///
+    /// Matched by app ID when the app ID of a new window becomes known, then
+    /// moved to their saved output, workspace and geometry.
+    pub pending_restore: PendingRestore,
+    
+    /// Atomic window batches from IPC
+    ///
+    /// Batches are applied one at a time between frames, and only once every
+    /// window resized by the batch has committed its new size.
+    pub window_batches: TransactionQueue,
+    
+    /// Window batch waiting for its resized windows
+    ///
+    /// Configure acknowledgements and commits of the resized windows are
+    /// reported to it until it is ready to apply.
+    pub pending_transaction: Option<PendingTransaction>,
+    
+    /// Window being resized interactively
+    ///
+    /// Configures are throttled to the output refresh rate and to the
+    /// client's acks and commits.
+    pub interactive_resize: Option<InteractiveResize>,
+    
+    /// Client windows mirrored into the accessibility tree
+    ///
+    /// Window titles and keyboard focus are exposed to screen readers
+    /// through the AT-SPI bridge.
+    pub accessibility: WindowAccessibility,
+    
+    /// Dwell clicking and simulated secondary click
+    ///
+    /// Turns resting the pointer or holding the primary button into clicks
+    /// for users who cannot press buttons reliably.
+    pub click_assist: ClickAssist,
+    
+    /// Actions fired by resting the pointer in a screen corner or against
+    /// an edge, or by swiping in from an edge
+    pub hot_corners: HotCornerTracker,
+    
+    /// Cursor hiding while typing and after pointer inactivity
+    ///
+    /// Tracks the output the pointer is on and leaves the cursor alone while
+    /// a client holds a pointer constraint.
+    pub cursor_visibility: CursorVisibility,
+    
+    /// Busy cursor and dock icon bouncing while launched apps start
+    pub startup_feedback: StartupFeedback,
+    
+    /// Application launches requested over IPC
+    pub launch_requests: LaunchQueue,
+    
+    /// Sticky edges and barriers between outputs
+    ///
+    /// Holds the pointer at edges shared between outputs until it pushes
+    /// far enough, or for good at configured barrier edges.
+    pub pointer_barriers: PointerBarriers,
+    
+    /// Opt-in per-surface frame statistics
+    ///
+    /// Commits are recorded here and matched against present times from the
+    /// render loop so developers can query delivered FPS and latency over IPC.
+    pub frame_stats: Arc<FrameStatistics>,
+    
+    /// Ping tracking for unresponsive application detection
+    ///
+    /// Shell clients that stop answering xdg_wm_base pings are reported so
+    /// the user can choose to keep waiting or force quit them.
+    pub responsiveness: ResponsivenessMonitor,
+    
+    /// "Not responding" dialogs shown over the windows of unresponsive
+    /// clients, in the order they opened
+    unresponsive_dialogs: Vec<(ClientId, Dialog<UnresponsiveChoice>)>,
+    
+    /// Colors and font of compositor dialogs, from the theme
+    dialog_style: DialogStyle,
+    
+    /// Force quit ("xkill") mode
+    ///
+    /// While selecting, pointer clicks pick a window whose client is killed
+    /// once the user confirms.
+    pub kill_mode: KillMode,
+    
+    /// Color picker
+    ///
+    /// While active, a loupe follows the pointer and a click copies the
+    /// color under it to the clipboard.
+    pub color_picker: ColorPicker,
+    
+    /// Strokes drawn over the screen in annotation mode
+    ///
+    /// While active, pointer buttons draw instead of reaching clients. The
+    /// strokes stay visible after leaving until cleared.
+    pub annotation: AnnotationOverlay,
+    
+    /// Overlay selecting a region to screenshot
+    ///
+    /// While active, pointer buttons and the arrow, Enter and Escape keys
+    /// adjust the selection instead of reaching clients.
+    pub region_selection: RegionSelection,
+    
+    /// Wi-Fi, Bluetooth, volume and brightness controls
+    ///
+    /// While open, the arrow, Tab, Enter and Escape keys and clicks on the
+    /// panel operate it instead of reaching clients.
+    pub quick_settings: QuickSettings,
+    
+    /// Screenshots of the active window or output, captured from composed frames
+    pub screenshots: Screenshots,
+    
+    /// Compositor keybindings, matched before keys reach clients
+    pub key_bindings: KeyBindings,
+    
+    /// Placement of new windows by the configured policy
+    pub window_placer: WindowPlacer,
+    
+    /// Window with keyboard focus, shown as activated
+    active_window: Option<Window>,
+    
+    /// Corner radius, shadow and border of each surface by its class
+    pub surface_styles: SurfaceStyles,
+    
+    /// Windows asking for attention, outlined until they get focus
+    pub urgent_windows: UrgentWindows,
+    
+    /// Focus the window under the pointer as it moves instead of on click
+    focus_follows_mouse: bool,
+    
+    /// How often window and workspace animations step
+    pub animation_rates: AnimationRates,
+    
+    /// Frame callbacks held until a frame showing their commit is presented
+    pub frame_callbacks: FrameCallbackQueue,
+    
+    /// Policy for binding privileged protocols
+    ///
+    /// Shared with the global filters, which match it against client
+    /// credentials and the kind of socket the client connected through.
+    pub security_policy: Arc<SecurityPolicy>,
+    
+    /// Per-client resource accounting
+    ///
+    /// Tallies surfaces, buffer memory and frame callback rates per client,
+    /// publishes them for IPC and enforces the configured soft limits.
+    pub client_usage: ClientResourceTracker,
+    
+    /// Per-client protocol round-trip latencies
+    ///
+    /// Measures configure-to-ack and frame-callback-to-commit times to help
+    /// diagnose slow toolkits and overloaded clients.
+    pub protocol_latency: ProtocolLatencyTracker,
+    
+    /// Pixel formats and modifiers of the buffers clients attach
+    ///
+    /// Published for IPC and used to order the scanout tranche of dmabuf
+    /// feedback when scanout formats are preferred.
+    pub buffer_formats: BufferFormatStats,
+    
+    /// Frame callback rate caps from window rules
+    pub frame_rate_caps: FrameRateCaps,
+    
+    /// Per-app scale overrides from window rules
+    pub app_scales: AppScales,
+    
+    /// Window titles and app IDs, published to taskbars
+    pub window_identities: WindowIdentities,
+    
+    /// Output scales requested over IPC
+    ///
+    /// Applied on the compositor thread so scale changes reach clients and
+    /// re-layout windows without restarting anything.
+    pub output_scale_requests: OutputRequests<f64>,
+    
+    /// Output transforms requested over IPC
+    ///
+    /// Applied on the compositor thread like scale changes, rotating or
+    /// flipping the output and re-laying out its windows.
+    pub output_transform_requests: OutputRequests<DisplayTransform>,
+    
+    /// Accelerometer-driven rotation of the built-in display
+    ///
+    /// Follows the orientation reported by iio-sensor-proxy on convertibles,
+    /// unless disabled in the configuration or locked by the user.
+    pub auto_rotation: AutoRotation,
+    
+    /// Connected outputs with their geometry and active workspace,
+    /// published for IPC and D-Bus clients to list
+    pub output_list: tokio::sync::watch::Sender<Vec<OutputInfo>>,
+    
+    /// Sample compositor UI styled with a candidate theme, opened over IPC
+    pub theme_preview: ThemePreview,
+    
+    /// Outputs and the stacking and styles of surfaces, published for the
+    /// render thread
+    pub render_state: RenderStateChannel,
+    
+    /// Committed surface content waiting for the render thread to upload it
+    pub surfaces: Arc<Mutex<SurfaceManager>>,
+    
+    /// Dimming of windows without keyboard focus
+    pub window_dimming: DimmingSettings,
+    
+    /// Surface with keyboard focus and when it got focus, so frames keep
+    /// being drawn while the other windows fade
+    pub focused_surface: (Option<u32>, std::time::Instant),
+    
+    /// Reloaded configurations, applied on the next iteration
+    pub config_changes: Option<tokio::sync::broadcast::Receiver<config::CompositorConfig>>,
+    
     /// High-precision timing clock for animation and synchronization
     ///
     /// Provides monotonic time references for frame timing, animation,
     /// and temporal coordination across the compositor.
     pub clock: Clock<Monotonic>,
     
+    /// Handle to the event loop
+    ///
+    /// Used to listen on the sockets of security contexts created by
+    /// sandbox engines.
+    pub loop_handle: LoopHandle<'static, WaylandServerState>,
+    
+    /// Handle to the display
+    ///
+    /// Used to insert clients accepted outside the main listening sockets.
+    pub display_handle: DisplayHandle,
+    
     /// Wayland socket name for client connections
     ///
     /// The name of the Wayland socket (e.g., "wayland-0") that clients
@@ -588,29 +1061,3175 @@ pub struct WaylandServerState {
     /// OpenGL rendering paths and wl_drm protocol support.
     pub egl_context: Option<EGLContext>,
     
-    /// EGL display for wl_drm protocol integration
-    ///
-    /// Manages the EGL display connection for legacy applications using
-    /// the wl_drm protocol for buffer sharing.
-    pub egl_display: Option<EGLDisplay>,
+    /// EGL display for wl_drm protocol integration
+    ///
+    /// Manages the EGL display connection for legacy applications using
+    /// the wl_drm protocol for buffer sharing.
+    pub egl_display: Option<EGLDisplay>,
+    
+    /// DRM node for direct GPU resource management
+    ///
+    /// Provides access to the GPU device node for direct hardware resource
+    /// management and explicit synchronization support.
+    pub drm_node: Option<DrmNode>,
+    
+    /// DRM device file descriptor for explicit sync support
+    ///
+    /// File descriptor for the DRM device, used for explicit synchronization
+    /// and direct hardware access operations.
+    pub drm_device_fd: Option<DrmDeviceFd>,
+    
+    /// Vulkan renderer for high-performance surface compositing
+    ///
+    /// The core Vulkan-based rendering engine that performs surface compositing,
+    /// applies effects (glassmorphism, neomorphism), and outputs frames.
+    pub renderer: Option<Arc<Mutex<VulkanRenderer>>>,
+}
+
+impl WaylandServerState {
+    /// Toggle "show desktop", sliding all windows aside or back
+    pub fn toggle_show_desktop(&mut self) {
+        if self.kiosk.is_active() {
+            return;
+        }
+        let (windows, output) = self.show_desktop_targets();
+        self.show_desktop.toggle(windows, output, std::time::Instant::now());
+    }
+    
+    /// Start peeking at the desktop while the peek key is held
+    pub fn begin_desktop_peek(&mut self) {
+        if self.kiosk.is_active() {
+            return;
+        }
+        let (windows, output) = self.show_desktop_targets();
+        self.show_desktop.begin_peek(windows, output, std::time::Instant::now());
+    }
+    
+    /// Stop peeking and restore the previous window layout
+    pub fn end_desktop_peek(&mut self) {
+        self.show_desktop.end_peek(std::time::Instant::now());
+    }
+    
+    /// Advance the show-desktop animation, moving windows accordingly, and
+    /// the borders of urgent windows
+    ///
+    /// Call every event loop iteration; the animations step at the window
+    /// animation rate and frames in between interpolate. Returns whether one
+    /// is still running.
+    pub fn update_window_animations(&mut self) -> bool {
+        let now = std::time::Instant::now();
+        if self.animation_rates.tick(AnimationClass::Windows, now) {
+            self.show_desktop.step(now);
+            self.urgent_windows.advance(now);
+        }
+        let alpha = self.animation_rates.alpha(AnimationClass::Windows, now);
+        for (id, location) in self.show_desktop.positions(alpha) {
+            let window = self
+                .space
+                .elements()
+                .find(|window| window.toplevel().is_some_and(|toplevel| toplevel.wl_surface().id() == id))
+                .cloned();
+            if let Some(window) = window {
+                self.space.map_element(window, location, false);
+            }
+        }
+        self.show_desktop.is_animating() || self.urgent_windows.is_animating()
+    }
+    
+    /// Advance animations and publish the outputs and the stacking and styles
+    /// of surfaces for the render thread, if they changed
+    ///
+    /// Call every event loop iteration. All outputs are redrawn while an
+    /// animation runs and when a theme preview opens, changes or closes.
+    pub fn publish_render_state(&mut self) {
+        // Keep redrawing while windows slide, urgent windows' borders pulse,
+        // workspace wallpapers cross-fade or outputs fade for power saving
+        let mut redraw = self.update_window_animations();
+        redraw |= self.update_workspace_themes();
+        redraw |= self.update_output_power();
+        redraw |= self.theme_preview.take_changed();
+        // The click countdown ring fills while it runs
+        redraw |= self.click_assist.feedback(std::time::Instant::now()).is_some();
+        let (focused_surface, focused_at) = self.focused_surface;
+        redraw |= self.window_dimming.enabled && focused_at.elapsed() < self.window_dimming.transition;
+        
+        let alpha = self.animation_rates.alpha(AnimationClass::Windows, std::time::Instant::now());
+        let state = RenderState {
+            outputs: self.render_outputs(),
+            stacking_order: self.stacking.protocol_ids(),
+            hidden_surfaces: self.hidden_surface_ids(),
+            rescaled_surfaces: self.app_scales.surface_ids(),
+            corner_radii: self.surface_styles.corner_radii(),
+            // With the neomorphism theme, each class has its own shadow and highlight
+            neomorphic_surfaces: self
+                .surface_styles
+                .neomorphic_surfaces()
+                .into_iter()
+                .map(|(surface_id, style)| {
+                    (surface_id, NeomorphicParams {
+                        distance: style.distance,
+                        blur: style.blur,
+                        shadow_intensity: style.shadow_intensity,
+                        highlight_intensity: style.highlight_intensity,
+                    })
+                })
+                .collect(),
+            borders: self.surface_borders(alpha),
+            shadows: self.surface_styles.shadows(),
+            focused_surface,
+            dimming: self.window_dimming,
+            surface_geometry: self.surface_geometry(),
+            blur: self.surface_blurs(),
+            wallpaper: self.wallpaper_primitives(),
+            ui: self.ui_primitives(),
+            ..Default::default()
+        };
+        self.render_state.publish(state, redraw);
+    }
+    
+    /// Borders of surfaces from their class, where urgent windows' pulsing
+    /// border takes the place of their own
+    ///
+    /// The focused window's border is its focus ring, drawn in the accent
+    /// color of the workspace it is on.
+    fn surface_borders(&self, alpha: f32) -> Vec<(u32, f32, [f32; 4])> {
+        let urgent = self.urgent_windows.borders(alpha);
+        let focus_ring = self.focused_surface.0.and_then(|surface_id| {
+            let window = self
+                .space
+                .elements()
+                .find(|window| window.toplevel().is_some_and(|toplevel| toplevel.wl_surface().id().protocol_id() == surface_id))?;
+            let geometry = self.space.element_geometry(window)?;
+            Some((surface_id, self.accent_color_at(geometry.loc.to_f64())))
+        });
+        let mut borders: Vec<_> = self
+            .surface_styles
+            .borders()
+            .into_iter()
+            .filter(|(surface_id, ..)| !urgent.iter().any(|(urgent_id, ..)| urgent_id == surface_id))
+            .map(|(surface_id, width, color)| match focus_ring {
+                Some((focused, accent)) if focused == surface_id => (surface_id, width, accent),
+                _ => (surface_id, width, color),
+            })
+            .collect();
+        borders.extend(urgent);
+        borders
+    }
+    
+    /// Where windows and layer surfaces are in global coordinates, for the
+    /// effects drawn around them
+    fn surface_geometry(&self) -> Vec<(u32, Rect)> {
+        let mut geometry = Vec::new();
+        for window in self.space.elements() {
+            if let (Some(toplevel), Some(rect)) = (window.toplevel(), self.space.element_geometry(window)) {
+                geometry.push((toplevel.wl_surface().id().protocol_id(), render_rect(rect)));
+            }
+        }
+        for output in self.space.outputs() {
+            let Some(output_geometry) = self.space.output_geometry(output) else { continue };
+            let layer_map = layer_map_for_output(output);
+            for layer in layer_map.layers() {
+                if let Some(mut rect) = layer_map.layer_geometry(layer) {
+                    rect.loc += output_geometry.loc;
+                    geometry.push((layer.wl_surface().id().protocol_id(), render_rect(rect)));
+                }
+            }
+        }
+        geometry
+    }
+    
+    /// Blur behind windows and layer surfaces matched by a blur rule, with
+    /// regions relative to their geometry
+    fn surface_blurs(&self) -> Vec<(u32, SurfaceBlurRequest)> {
+        let mut blurs = Vec::new();
+        for window in self.space.elements() {
+            let Some(toplevel) = window.toplevel() else { continue };
+            let Some(blur) = self.blur.get(&toplevel.wl_surface().id()) else { continue };
+            // The window geometry leaves out client-side shadows around it
+            let geometry = window.geometry();
+            let size = (geometry.loc.x + geometry.size.w, geometry.loc.y + geometry.size.h).into();
+            blurs.push((toplevel.wl_surface().id().protocol_id(), blur.request(size, geometry.loc)));
+        }
+        for output in self.space.outputs() {
+            let layer_map = layer_map_for_output(output);
+            for layer in layer_map.layers() {
+                let (Some(blur), Some(rect)) = (self.blur.get(&layer.wl_surface().id()), layer_map.layer_geometry(layer)) else {
+                    continue;
+                };
+                blurs.push((layer.wl_surface().id().protocol_id(), blur.request(rect.size, Point::default())));
+            }
+        }
+        blurs
+    }
+    
+    /// Wallpaper of the active workspace of every output, cross-faded while
+    /// workspaces switch
+    fn wallpaper_primitives(&mut self) -> Vec<UiPrimitive> {
+        let alpha = self.animation_rates.alpha(AnimationClass::Workspaces, std::time::Instant::now());
+        let outputs: Vec<(String, Rect)> = self
+            .space
+            .outputs()
+            .filter_map(|output| Some((output.name(), render_rect(self.space.output_geometry(output)?))))
+            .collect();
+        outputs
+            .into_iter()
+            .flat_map(|(name, rect)| self.workspace_themes.wallpaper_primitives(&name, rect, alpha))
+            .collect()
+    }
+    
+    /// Compositor-drawn UI on every output, back to front
+    fn ui_primitives(&self) -> Vec<UiPrimitive> {
+        let now = std::time::Instant::now();
+        // Annotations are drawn over surfaces, below the rest of the UI
+        let mut primitives = self.annotation.primitives();
+        // Dwell and secondary click countdown around the cursor
+        if let Some(feedback) = self.click_assist.feedback(now) {
+            let position = (feedback.position.x as f64, feedback.position.y as f64);
+            primitives.extend(feedback.primitives(self.accent_color_at(position.into())));
+        }
+        // The color picker's loupe next to the pointer, kept on its output
+        let pointer = self.seat.get_pointer().map(|pointer| pointer.current_location());
+        if let Some(location) = pointer.filter(|_| self.color_picker.is_active()) {
+            let output = self.space.output_under(location).next().or_else(|| self.space.outputs().next());
+            if let Some(geometry) = output.and_then(|output| self.space.output_geometry(output)) {
+                let pointer = Vec2::new(location.x as f32, location.y as f32);
+                primitives.extend(self.color_picker.loupe_primitives(pointer, render_rect(geometry), self.accent_color_at(location)));
+            }
+        }
+        primitives.extend(self.region_selection.primitives());
+        primitives.extend(self.quick_settings.primitives());
+        // Dialogs over the windows they are about
+        for (client, dialog) in &self.unresponsive_dialogs {
+            primitives.extend(dialog.primitives(self.client_dialog_anchor(client)));
+        }
+        if let Some(target) = self.kill_mode.target() {
+            primitives.extend(target.dialog.primitives(self.kill_dialog_anchor(target)));
+        }
+        // The theme preview is drawn over the rest of the UI
+        for output in self.space.outputs() {
+            if let Some(geometry) = self.space.output_geometry(output) {
+                primitives.extend(self.theme_preview.primitives(geometry));
+            }
+        }
+        // Outputs fading for power saving are darkened as a whole
+        let alpha = self.animation_rates.alpha(AnimationClass::Outputs, now);
+        for output in self.space.outputs() {
+            let brightness = self.output_power.brightness(&output.name(), alpha);
+            let Some(geometry) = self.space.output_geometry(output).filter(|_| brightness < 1.0) else {
+                continue;
+            };
+            primitives.push(UiPrimitive::Rect {
+                rect: render_rect(geometry),
+                color: [0.0, 0.0, 0.0, 1.0 - brightness.clamp(0.0, 1.0)],
+                corner_radius: 0.0,
+            });
+        }
+        primitives
+    }
+    
+    /// Accent color of the workspace shown where `position` is, for
+    /// compositor UI drawn there
+    fn accent_color_at(&self, position: Point<f64, Logical>) -> [f32; 4] {
+        let alpha = self.animation_rates.alpha(AnimationClass::Workspaces, std::time::Instant::now());
+        let output = self.space.output_under(position).next().or_else(|| self.space.outputs().next());
+        self.workspace_themes.accent_color(&output.map_or_else(String::new, |output| output.name()), alpha)
+    }
+    
+    /// Outputs for the render thread to draw, each with the surfaces it does
+    /// not show
+    fn render_outputs(&self) -> Vec<RenderOutput> {
+        // TODO: Read back other outputs once captures can target them; the
+        // readback regions are on the first one
+        let readback = self.readback_region();
+        self.space
+            .outputs()
+            .enumerate()
+            .filter_map(|(index, output)| {
+                let geometry = self.space.output_geometry(output)?;
+                let mut offscreen_surfaces = Vec::new();
+                for window in self.space.elements() {
+                    let hidden = window.toplevel().is_some_and(|toplevel| {
+                        let id = toplevel.wl_surface().id();
+                        self.workspaces.placement(&id).is_some() && !self.workspaces.is_visible(&id)
+                    });
+                    if hidden || !self.space.outputs_for_element(window).contains(output) {
+                        window.with_surfaces(|surface, _| offscreen_surfaces.push(surface.id().protocol_id()));
+                    }
+                }
+                // Notifications focus mode holds back are not shown anywhere
+                for (surface, ..) in &self.suppressed_layer_surfaces {
+                    offscreen_surfaces.push(surface.wl_surface().id().protocol_id());
+                }
+                // Layer surfaces are shown on their own output only
+                for other in self.space.outputs().filter(|other| *other != output) {
+                    for layer in layer_map_for_output(other).layers() {
+                        layer.with_surfaces(|surface, _| offscreen_surfaces.push(surface.id().protocol_id()));
+                    }
+                }
+                Some(RenderOutput {
+                    name: output.name(),
+                    geometry: render_rect(geometry),
+                    refresh_mhz: output
+                        .current_mode()
+                        .map_or(DEFAULT_REFRESH_MHZ, |mode| mode.refresh.max(0) as u32),
+                    offscreen_surfaces,
+                    readback: readback.filter(|_| index == 0),
+                })
+            })
+            .collect()
+    }
+    
+    /// Region of the next frame to read back for a waiting screenshot, else
+    /// for frames clients asked to copy, else the pixels around the pointer
+    /// for the color picker's loupe
+    fn readback_region(&self) -> Option<ReadbackRegion> {
+        self.screenshots.readback_region()
+            .or_else(|| self.screencopy_state.readback_region())
+            .or_else(|| self.color_picker.readback_region())
+    }
+    
+    /// Hand regions the render thread read back to the screenshot, screen
+    /// copies or color picker waiting for them, and give those up when their
+    /// region could not be read
+    ///
+    /// Call every event loop iteration, before screenshots are processed.
+    pub fn process_readbacks(&mut self) {
+        let presented = std::time::Duration::from(self.clock.now());
+        for readback in self.render_state.take_readbacks() {
+            match readback {
+                FrameReadback::Read(pixels) => {
+                    if !self.screenshots.frame_read(&pixels) && !self.screencopy_state.frame_read(&pixels, presented) {
+                        self.color_picker.frame_read(pixels);
+                    }
+                }
+                FrameReadback::Failed(region) => {
+                    if !self.screenshots.readback_failed(region) && !self.screencopy_state.readback_failed(region) {
+                        self.color_picker.readback_failed(region);
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Publish the connected outputs and where windows live for IPC, if they changed
+    ///
+    /// Call every event loop iteration.
+    pub fn publish_outputs(&mut self) {
+        let outputs: Vec<OutputInfo> = self
+            .space
+            .outputs()
+            .filter_map(|output| {
+                let geometry = self.space.output_geometry(output)?;
+                Some(OutputInfo {
+                    name: output.name(),
+                    geometry: WindowGeometry {
+                        x: geometry.loc.x,
+                        y: geometry.loc.y,
+                        width: geometry.size.w.max(0) as u32,
+                        height: geometry.size.h.max(0) as u32,
+                    },
+                    scale: output.current_scale().fractional_scale(),
+                    refresh: output.current_mode().map_or(0, |mode| mode.refresh),
+                    active_workspace: self.workspaces.active_workspace(&output.name()).unwrap_or(0),
+                    workspace_count: self.workspaces.workspace_count(&output.name()).unwrap_or(0),
+                })
+            })
+            .collect();
+        self.output_list.send_if_modified(|published| {
+            if *published == outputs {
+                return false;
+            }
+            *published = outputs;
+            true
+        });
+        self.window_identities.update_placements(&self.workspaces);
+    }
+    
+    /// Switch the active workspace of an output, cross-fading its wallpaper and accent
+    pub fn switch_workspace(&mut self, output: &str, workspace: usize) -> Result<()> {
+        self.workspaces.switch_to(output, workspace)?;
+        self.workspace_themes.workspace_switched(output, workspace, std::time::Instant::now());
+        
+        // Focus follows to the new workspace
+        let active_hidden = self
+            .active_window
+            .as_ref()
+            .and_then(|window| window.toplevel())
+            .is_some_and(|toplevel| !self.workspaces.is_visible(&toplevel.wl_surface().id()));
+        if active_hidden {
+            self.focus_topmost_window();
+        }
+        Ok(())
+    }
+    
+    /// Advance workspace wallpaper and accent cross-fades
+    ///
+    /// Call every event loop iteration; cross-fades step at the workspace
+    /// animation rate. Returns whether a cross-fade is still running.
+    pub fn update_workspace_themes(&mut self) -> bool {
+        let now = std::time::Instant::now();
+        if self.animation_rates.tick(AnimationClass::Workspaces, now) {
+            self.workspace_themes.advance(now);
+        }
+        self.workspace_themes.is_animating()
+    }
+    
+    /// Advance power saving fades and turn idle outputs off
+    ///
+    /// Call every event loop iteration; fades step at the output animation
+    /// rate. Returns whether an output is fading, so frames keep being drawn.
+    pub fn update_output_power(&mut self) -> bool {
+        let now = std::time::Instant::now();
+        if self.animation_rates.tick(AnimationClass::Outputs, now) {
+            let changes = self.output_power.advance(now);
+            self.apply_power_changes(changes);
+        }
+        self.output_power.is_animating()
+    }
+    
+    /// Apply every configuration section the Wayland loop owns
+    ///
+    /// Called with the loaded configuration at startup and again with each
+    /// reloaded one; renderer settings are applied by the render thread.
+    pub fn apply_config(&mut self, config: &config::CompositorConfig) {
+        self.set_input_config(&config.input);
+        self.set_power_save_config(config.power_save.clone());
+        self.set_lid_config(config.lid.clone());
+        self.auto_rotation.set_enabled(config.display.auto_rotate);
+        self.set_output_profiles(config.display.profiles.clone());
+        self.set_kiosk_config(config.kiosk.clone());
+        self.set_window_config(config.window.clone());
+        self.focus_mode.set_config(config.focus_mode.clone());
+        self.hot_corners.set_config(config.hot_corners.clone());
+        self.click_assist.set_config(config.pointer_accessibility.clone());
+        self.cursor_visibility.set_config(config.cursor.clone());
+        self.pointer_barriers.set_config(config.pointer_barriers.clone());
+        self.responsiveness.set_config(config.unresponsive_detection.clone());
+        self.blur.set_config(config.blur.clone());
+        self.client_usage.set_config(config.client_limits.clone());
+        self.startup_feedback.set_config(config.startup_feedback.clone());
+        self.color_picker.set_config(config.color_picker.clone());
+        self.screenshots.set_config(config.screenshot.clone());
+        self.key_bindings.set_config(&config.bindings);
+        self.layouts.set_directory(config.layouts.directory.clone());
+        
+        // Performance
+        self.animation_rates.set_config(&config.performance.animation_rates);
+        self.frame_callbacks.set_config(&config.performance.frame_callbacks);
+        self.frame_stats.set_enabled(config.performance.frame_statistics);
+        self.buffer_formats.set_prefer_scanout(config.performance.prefer_scanout_formats);
+        self.update_dmabuf_feedback();
+        
+        // Security; protocol exposure only affects clients connecting later
+        self.security_policy.set_privileged_clients(config.security.privileged_clients.clone());
+        self.security_policy.set_protocol_exposure(config.security.protocol_exposure.clone());
+        self.security_policy.set_keyboard_grab_policy(config.security.keyboard_grab);
+        self.keyboard_grab_state.set_keep_on_focus_loss(config.security.keep_keyboard_grab_on_focus_loss);
+        
+        // Window rules: sticky placement, frame rate caps and scale overrides
+        self.workspaces.set_rules(config.window_rules.clone());
+        self.frame_rate_caps.set_rules(config.window_rules.clone());
+        let rescaled = self.app_scales.set_rules(config.window_rules.clone());
+        self.refresh_preferred_scales(&rescaled);
+        
+        // Styles of windows, dialogs, popups and panels follow the theme; an
+        // open theme preview applies its candidate keys over the new theme
+        let theme = &config.theme;
+        let accent = theme.color(config::ColorToken::Accent);
+        self.surface_styles.set_theme(theme.clone());
+        self.theme_preview.set_base(theme.clone());
+        self.dialog_style = DialogStyle::new(theme);
+        self.region_selection.set_font(self.dialog_style.font().cloned());
+        self.quick_settings.set_theme(theme, self.dialog_style.font().cloned());
+        self.urgent_windows.set_accent_color(accent);
+        self.workspace_themes.set_config(config.workspaces.clone(), accent);
+        self.window_dimming = DimmingSettings {
+            enabled: config.window_dimming.enabled,
+            strength: config.window_dimming.strength,
+            desaturation: config.window_dimming.desaturation,
+            transition: std::time::Duration::from_millis(config.window_dimming.transition_duration),
+        };
+    }
+    
+    /// Apply the newest reloaded configuration, if it changed since the
+    /// last call
+    pub fn process_config_changes(&mut self) {
+        if let Some(config) = self.config_changes.as_mut().and_then(crate::latest_config) {
+            info!("Applying reloaded configuration");
+            self.apply_config(&config);
+        }
+    }
+    
+    /// Apply new power saving settings
+    pub fn set_power_save_config(&mut self, config: config::PowerSaveConfig) {
+        let changes = self.output_power.set_config(config, std::time::Instant::now());
+        self.apply_power_changes(changes);
+    }
+    
+    fn apply_power_changes(&mut self, changes: PowerChanges) {
+        for output in &changes.off {
+            info!("Turning output {} off after inactivity", output);
+        }
+        for output in &changes.on {
+            info!("Turning output {} back on", output);
+        }
+        // TODO: Set DPMS on the outputs' connectors once outputs are driven
+        // by the DRM backend
+    }
+    
+    /// Apply new lid switch settings
+    pub fn set_lid_config(&mut self, config: config::LidConfig) {
+        self.lid.set_config(config);
+    }
+    
+    /// Apply new output profiles
+    pub fn set_output_profiles(&mut self, profiles: Vec<config::OutputProfile>) {
+        self.output_profiles = profiles;
+        self.apply_output_profile();
+    }
+    
+    /// React to outputs being connected or disconnected, e.g. docking
+    pub fn outputs_changed(&mut self) {
+        // TODO: Call on DRM connector hotplug once outputs are driven by the
+        // DRM backend
+        let response = self.lid.outputs_changed(&self.output_names());
+        self.apply_lid_response(response);
+    }
+    
+    fn lid_toggled(&mut self, closed: bool) {
+        info!("Lid {}", if closed { "closed" } else { "opened" });
+        let response = self.lid.toggle(closed, &self.output_names());
+        self.apply_lid_response(response);
+    }
+    
+    fn apply_lid_response(&mut self, response: LidResponse) {
+        match response {
+            LidResponse::None => return,
+            LidResponse::Disable(outputs) => {
+                for output in &outputs {
+                    self.disable_output(output);
+                }
+            }
+            LidResponse::Enable(outputs) => {
+                for output in &outputs {
+                    self.enable_output(output);
+                }
+            }
+            LidResponse::Suspend => {
+                // TODO: Call Suspend on org.freedesktop.login1 and hold a
+                // handle-lid-switch inhibitor lock, so logind leaves the lid
+                // to us, once a D-Bus client dependency is available
+                info!("Suspending after the lid closed");
+                if let Err(e) = std::process::Command::new("systemctl").arg("suspend").spawn() {
+                    warn!("Failed to suspend: {}", e);
+                }
+                return;
+            }
+        }
+        self.apply_output_profile();
+    }
+    
+    /// Names of the outputs in use
+    fn output_names(&self) -> Vec<String> {
+        self.space.outputs().map(|output| output.name()).collect()
+    }
+    
+    /// Take an output out of the layout, moving its windows to another output
+    fn disable_output(&mut self, name: &str) {
+        let Some(output) = self.space.outputs().find(|output| output.name() == name).cloned() else { return };
+        let Some(target) = self.space.outputs().find(|other| **other != output).cloned() else {
+            warn!("Not turning off {}, the only output in use", name);
+            return;
+        };
+        let Some(geometry) = self.space.output_geometry(&output) else { return };
+        let Some(target_location) = self.space.output_geometry(&target).map(|geometry| geometry.loc) else { return };
+        
+        info!("Turning off output {}, moving its windows to {}", name, target.name());
+        let windows = self.windows_on_output(&output);
+        self.space.unmap_output(&output);
+        for window in &windows {
+            if let Some(location) = self.space.element_location(window) {
+                self.space.map_element(window.clone(), target_location + (location - geometry.loc), false);
+            }
+        }
+        self.relayout_output(&target, windows);
+        self.workspaces.move_output_windows(name, &target.name());
+        self.disabled_outputs.push((output, geometry.loc));
+        // TODO: Disable the output's CRTC once outputs are driven by the DRM
+        // backend
+    }
+    
+    /// Put an output turned off for the lid back where it was in the layout
+    fn enable_output(&mut self, name: &str) {
+        let Some(index) = self.disabled_outputs.iter().position(|(output, _)| output.name() == name) else {
+            return;
+        };
+        let (output, location) = self.disabled_outputs.remove(index);
+        info!("Turning output {} back on", name);
+        self.space.map_output(&output, location);
+        layer_map_for_output(&output).arrange();
+    }
+    
+    /// Apply the output profile matching the outputs in use, if any
+    fn apply_output_profile(&mut self) {
+        let Some(profile) = lid::matching_profile(&self.output_profiles, &self.output_names()).cloned() else {
+            return;
+        };
+        info!("Applying output profile {}", profile.name);
+        for (name, settings) in &profile.outputs {
+            if let Some(scale) = settings.scale {
+                if let Err(e) = self.set_output_scale(name, scale) {
+                    warn!("Output profile {}: {}", profile.name, e);
+                }
+            }
+            let Some(position) = settings.position else { continue };
+            let Some(output) = self.space.outputs().find(|output| output.name() == *name).cloned() else { continue };
+            let Some(previous) = self.space.output_geometry(&output).map(|geometry| geometry.loc) else { continue };
+            let position = Point::from(position);
+            if position == previous {
+                continue;
+            }
+            // Windows move along with their output
+            let windows = self.windows_on_output(&output);
+            self.space.map_output(&output, position);
+            for window in &windows {
+                if let Some(location) = self.space.element_location(window) {
+                    self.space.map_element(window.clone(), position + (location - previous), false);
+                }
+            }
+            self.relayout_output(&output, windows);
+        }
+    }
+    
+    /// Apply window management settings
+    pub fn set_window_config(&mut self, config: config::WindowConfig) {
+        self.window_placer.set_policy(config.placement);
+        self.focus_follows_mouse = config.focus_follows_mouse;
+    }
+    
+    /// Toggle whether a window is shown on every workspace (sticky keybinding)
+    ///
+    /// Returns the new sticky state.
+    pub fn toggle_window_sticky(&mut self, surface: &WlSurface) -> bool {
+        if self.kiosk.is_active() {
+            return self.workspaces.is_sticky(&surface.id());
+        }
+        let sticky = self.workspaces.toggle_sticky(&surface.id());
+        info!("Window {:?} is {} sticky", surface.id(), if sticky { "now" } else { "no longer" });
+        sticky
+    }
+    
+    /// Apply pending layout requests from IPC
+    pub fn process_layout_requests(&mut self) {
+        for request in self.layouts.drain() {
+            let result = match &request {
+                LayoutRequest::Save { name } => self.layouts.save(&self.snapshot_layout(name)),
+                LayoutRequest::Restore { name } => self.restore_layout(name),
+                LayoutRequest::Delete { name } => self.layouts.delete(name),
+            };
+            if let Err(e) = result {
+                warn!("Layout request {:?} failed: {}", request, e);
+            }
+        }
+    }
+    
+    /// Apply the next window batch from IPC once its resized windows are ready
+    ///
+    /// A batch naming an unknown window is dropped as a whole. Resizes are
+    /// configured right away; moves and workspace changes of every window in
+    /// the batch wait until the resized windows have committed, so nothing
+    /// is shown half arranged.
+    pub fn process_window_batches(&mut self) {
+        let now = std::time::Instant::now();
+        if let Some(transaction) = self.pending_transaction.take() {
+            if !transaction.is_ready(now) {
+                self.pending_transaction = Some(transaction);
+                return;
+            }
+            self.apply_window_operations(transaction.into_operations());
+        }
+        
+        let Some(operations) = self.window_batches.pop() else { return };
+        if self.kiosk.is_active() {
+            warn!("Ignoring window batch in kiosk mode");
+            return;
+        }
+        if let Some(operation) = operations.iter().find(|operation| self.window_by_id(operation.window_id()).is_none()) {
+            warn!("Ignoring window batch: window {} not found", operation.window_id());
+            return;
+        }
+        
+        let mut transaction = PendingTransaction::new(operations.clone(), now);
+        for operation in &operations {
+            let WindowOperation::Resize { window_id, width, height } = *operation else { continue };
+            let Some(toplevel) = self.window_by_id(window_id).and_then(|window| window.toplevel().cloned()) else {
+                continue;
+            };
+            toplevel.with_pending_state(|state| state.size = Some((width, height).into()));
+            let serial = self.send_configure(&toplevel);
+            transaction.expect_configure(toplevel.wl_surface().id(), serial);
+        }
+        debug!("Applying window batch of {} operations", operations.len());
+        if transaction.is_ready(now) {
+            self.apply_window_operations(transaction.into_operations());
+        } else {
+            self.pending_transaction = Some(transaction);
+        }
+    }
+    
+    /// Move windows and change their workspaces, skipping windows closed meanwhile
+    fn apply_window_operations(&mut self, operations: Vec<WindowOperation>) {
+        for operation in operations {
+            let Some(window) = self.window_by_id(operation.window_id()).cloned() else { continue };
+            let Some(id) = window.toplevel().map(|toplevel| toplevel.wl_surface().id()) else { continue };
+            match operation {
+                WindowOperation::Move { x, y, .. } => self.space.map_element(window, (x, y), false),
+                WindowOperation::MoveToWorkspace { workspace, .. } => {
+                    let Some(placement) = self.workspaces.placement(&id).cloned() else { continue };
+                    self.workspaces.place_window(&id, &placement.output, workspace, placement.sticky);
+                }
+                // Applied by the client before the batch became ready
+                WindowOperation::Resize { .. } => {}
+            }
+        }
+    }
+    
+    /// Mapped window with the given ID (the protocol ID of its wl_surface)
+    fn window_by_id(&self, window_id: u32) -> Option<&Window> {
+        // TODO: Use stable window IDs shared with the foreign toplevel list
+        self.space.elements().find(|window| {
+            window
+                .toplevel()
+                .is_some_and(|toplevel| toplevel.wl_surface().id().protocol_id() == window_id)
+        })
+    }
+    
+    /// Current arrangement of windows and workspaces
+    ///
+    /// Windows without an app ID are left out, since they can neither be
+    /// relaunched nor recognized when they come back.
+    pub fn snapshot_layout(&self, name: &str) -> LayoutSnapshot {
+        let active_workspaces = self
+            .space
+            .outputs()
+            .filter_map(|output| Some((output.name(), self.workspaces.active_workspace(&output.name())?)))
+            .collect();
+        let windows = self
+            .space
+            .elements()
+            .filter_map(|window| {
+                let toplevel = window.toplevel()?;
+                let placement = self.workspaces.placement(&toplevel.wl_surface().id())?;
+                let (app_id, title) = Self::toplevel_identity(toplevel);
+                let location = self.space.element_location(window)?;
+                let size = window.geometry().size;
+                let states = toplevel.current_state().states;
+                Some(WindowSnapshot {
+                    app_id: app_id?,
+                    title,
+                    output: placement.output.clone(),
+                    workspace: placement.workspace,
+                    sticky: placement.sticky,
+                    x: location.x,
+                    y: location.y,
+                    width: size.w,
+                    height: size.h,
+                    maximized: states.contains(xdg_toplevel::State::Maximized),
+                    fullscreen: states.contains(xdg_toplevel::State::Fullscreen),
+                })
+            })
+            .collect();
+        LayoutSnapshot {
+            name: name.to_string(),
+            active_workspaces,
+            windows,
+        }
+    }
+    
+    /// Restore a saved layout
+    ///
+    /// Open windows are placed right away; applications without a window are
+    /// relaunched and their windows placed when they appear.
+    pub fn restore_layout(&mut self, name: &str) -> Result<()> {
+        if self.kiosk.is_active() {
+            return Err(CompositorError::runtime("Layouts cannot be restored in kiosk mode"));
+        }
+        let layout = self.layouts.load(name)?;
+        info!("Restoring layout {} with {} windows", name, layout.windows.len());
+        for (output, workspace) in &layout.active_workspaces {
+            if let Err(e) = self.switch_workspace(output, *workspace) {
+                debug!("Not restoring active workspace: {}", e);
+            }
+        }
+        
+        let mut pending = PendingRestore::new();
+        pending.start(layout.windows);
+        let open: Vec<Window> = self.space.elements().cloned().collect();
+        for window in open {
+            let Some(toplevel) = window.toplevel().cloned() else { continue };
+            let (Some(app_id), title) = Self::toplevel_identity(&toplevel) else { continue };
+            if let Some(saved) = pending.take(&app_id, title.as_deref()) {
+                self.place_restored_window(&window, &saved);
+            }
+        }
+        for saved in pending.windows() {
+            let token = self.start_launch(Some(&saved.app_id));
+            if let Err(e) = launch_app(&saved.app_id, self.socket_name.as_deref(), Some(&token)) {
+                warn!("Not relaunching {}: {}", saved.app_id, e);
+                self.end_launch(&token);
+            }
+        }
+        self.pending_restore = pending;
+        Ok(())
+    }
+    
+    /// Apply kiosk mode settings and restrict connections to the kiosk whitelist
+    pub fn set_kiosk_config(&mut self, kiosk: config::KioskConfig) {
+        self.kiosk.set_config(kiosk);
+        self.workspaces.set_locked(self.kiosk.is_active());
+        self.security_policy.set_connection_whitelist(self.kiosk.allowed_clients());
+    }
+    
+    /// Place a new window by the placement policy once it has a size
+    ///
+    /// Maximized and fullscreen windows fill their output and are not placed.
+    fn place_window(&mut self, surface: &WlSurface) {
+        let Some(window) = self.window_for_surface(surface).cloned() else { return };
+        let Some(toplevel) = window.toplevel() else { return };
+        let size = window.geometry().size;
+        if size.is_empty() {
+            return;
+        }
+        let states = toplevel.current_state().states;
+        if states.contains(xdg_toplevel::State::Maximized) || states.contains(xdg_toplevel::State::Fullscreen) {
+            self.window_placer.cancel(&surface.id());
+            return;
+        }
+        let Some(placement) = self.workspaces.placement(&surface.id()).cloned() else { return };
+        let Some(area) = self.usable_area(&placement.output) else { return };
+        
+        let on_workspace = self.workspaces.windows_on(&placement.output, placement.workspace);
+        let others: Vec<Rectangle<i32, Logical>> = self
+            .space
+            .elements()
+            .filter(|other| **other != window)
+            .filter(|other| other.toplevel().is_some_and(|toplevel| on_workspace.contains(&toplevel.wl_surface().id())))
+            .filter_map(|other| self.space.element_geometry(other))
+            .collect();
+        let (app_id, _) = Self::toplevel_identity(toplevel);
+        let location = self.window_placer.place(&surface.id(), size, area, &others, app_id.as_deref());
+        debug!("Placing window {:?} at {:?}", surface.id(), location);
+        self.space.map_element(window, location, false);
+    }
+    
+    /// Part of an output not reserved by layer-shell exclusive zones, in
+    /// global coordinates; the primary output if `output_name` is unknown
+    fn usable_area(&self, output_name: &str) -> Option<Rectangle<i32, Logical>> {
+        let output = self
+            .space
+            .outputs()
+            .find(|output| output.name() == output_name)
+            .or_else(|| self.space.outputs().next())?;
+        let geometry = self.space.output_geometry(output)?;
+        let mut area = layer_map_for_output(output).non_exclusive_zone();
+        area.loc += geometry.loc;
+        Some(area)
+    }
+    
+    /// Make a window fill the primary output, as every kiosk window does
+    fn make_kiosk_fullscreen(&mut self, window: &Window) {
+        let Some(toplevel) = window.toplevel().cloned() else { return };
+        let Some(output) = self.space.outputs().next().cloned() else { return };
+        let Some(geometry) = self.space.output_geometry(&output) else { return };
+        toplevel.with_pending_state(|state| {
+            state.states.set(xdg_toplevel::State::Fullscreen);
+            state.size = Some(geometry.size);
+        });
+        self.send_configure(&toplevel);
+        self.space.map_element(window.clone(), geometry.loc, true);
+        self.stacking.set_layer(&toplevel.wl_surface().id(), StackLayer::Fullscreen);
+    }
+    
+    /// Move a window between the normal and fullscreen stacking layers
+    fn update_fullscreen_layer(&mut self, surface: &WlSurface, fullscreen: bool) {
+        let id = surface.id();
+        match self.stacking.layer(&id) {
+            Some(StackLayer::Fullscreen) if !fullscreen => self.stacking.set_layer(&id, StackLayer::Normal),
+            Some(_) if fullscreen => self.stacking.set_layer(&id, StackLayer::Fullscreen),
+            _ => {}
+        }
+    }
+    
+    /// Place a new window if it belongs to the layout being restored
+    fn restore_pending_window(&mut self, surface: &WlSurface, app_id: &str, title: Option<&str>) {
+        let Some(window) = self.window_for_surface(surface).cloned() else { return };
+        if let Some(saved) = self.pending_restore.take(app_id, title) {
+            self.place_restored_window(&window, &saved);
+        }
+    }
+    
+    /// Move a window to its saved output, workspace, geometry and state
+    fn place_restored_window(&mut self, window: &Window, saved: &WindowSnapshot) {
+        let Some(toplevel) = window.toplevel().cloned() else { return };
+        if !self.workspaces.place_window(&toplevel.wl_surface().id(), &saved.output, saved.workspace, saved.sticky) {
+            debug!("Output {} of restored window {} is gone, leaving it in place", saved.output, saved.app_id);
+            return;
+        }
+        debug!("Restoring window {} on {} workspace {}", saved.app_id, saved.output, saved.workspace);
+        toplevel.with_pending_state(|state| {
+            state.size = Some((saved.width, saved.height).into());
+            if saved.maximized {
+                state.states.set(xdg_toplevel::State::Maximized);
+            } else {
+                state.states.unset(xdg_toplevel::State::Maximized);
+            }
+            if saved.fullscreen {
+                state.states.set(xdg_toplevel::State::Fullscreen);
+            } else {
+                state.states.unset(xdg_toplevel::State::Fullscreen);
+            }
+        });
+        self.send_configure(&toplevel);
+        self.space.map_element(window.clone(), (saved.x, saved.y), false);
+        self.update_fullscreen_layer(toplevel.wl_surface(), saved.fullscreen);
+    }
+    
+    /// App ID and title of a toplevel
+    fn toplevel_identity(toplevel: &ToplevelSurface) -> (Option<String>, Option<String>) {
+        with_states(toplevel.wl_surface(), |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .map(|data| {
+                    let data = data.lock().unwrap();
+                    (data.app_id.clone(), data.title.clone())
+                })
+                .unwrap_or_default()
+        })
+    }
+    
+    /// Store a toplevel's new title or app ID on its window and publish it,
+    /// re-evaluating window rules when the app ID changed
+    fn toplevel_identity_changed(&mut self, toplevel: &ToplevelSurface) {
+        let Some(window) = self.window_for_surface(toplevel.wl_surface()).cloned() else { return };
+        let (app_id, title) = Self::toplevel_identity(toplevel);
+        let change = self.window_identities.update(
+            &window,
+            title.as_deref().unwrap_or_default(),
+            app_id.as_deref().unwrap_or_default(),
+        );
+        if change.title {
+            self.accessibility.set_title(&toplevel.wl_surface().id(), title.as_deref().unwrap_or_default());
+        }
+        if change.app_id {
+            debug!("Window {:?} app ID changed to {:?}", toplevel.wl_surface().id(), app_id);
+            if app_id.as_deref().is_some_and(|app_id| self.startup_feedback.window_shown(app_id)) {
+                self.startup_feedback_changed();
+            }
+            self.apply_window_rules(toplevel.wl_surface(), app_id.as_deref(), title.as_deref());
+        }
+    }
+    
+    /// Apply the window rules matching a toplevel's app ID
+    fn apply_window_rules(&mut self, surface: &WlSurface, app_id: Option<&str>, title: Option<&str>) {
+        self.blur.assign_toplevel(surface.id(), app_id);
+        if let Some(app_id) = app_id {
+            self.frame_rate_caps.apply_rules(&surface.id(), app_id);
+            if self.app_scales.apply_rules(&surface.id(), app_id) {
+                self.refresh_preferred_scales(&[surface.id()]);
+            }
+            if self.workspaces.apply_rules(&surface.id(), app_id) && !self.pending_restore.is_empty() {
+                self.restore_pending_window(surface, app_id, title);
+            }
+        }
+    }
+    
+    /// Apply pending automation requests from IPC through the given seat
+    pub fn process_automation(&mut self, seat: &Seat<Self>) {
+        for request in self.automation.drain() {
+            let serial = SERIAL_COUNTER.next_serial();
+            let time = std::time::Duration::from(self.clock.now()).as_millis() as u32;
+            
+            match request {
+                AutomationRequest::WarpPointer { x, y } => {
+                    let Some(pointer) = seat.get_pointer() else { continue };
+                    let location: Point<f64, Logical> = (x, y).into();
+                    let focus = self.space.element_under(location).and_then(|(window, origin)| {
+                        Some((window.toplevel()?.wl_surface().clone(), origin.to_f64()))
+                    });
+                    pointer.motion(self, focus, &MotionEvent { location, serial, time });
+                    pointer.frame(self);
+                }
+                AutomationRequest::Input(SyntheticInput::PointerButton { button, pressed }) => {
+                    let Some(pointer) = seat.get_pointer() else { continue };
+                    let state = if pressed { ButtonState::Pressed } else { ButtonState::Released };
+                    pointer.button(self, &ButtonEvent { serial, time, button, state });
+                    pointer.frame(self);
+                }
+                AutomationRequest::Input(SyntheticInput::Click { button }) => {
+                    let Some(pointer) = seat.get_pointer() else { continue };
+                    pointer.button(self, &ButtonEvent { serial, time, button, state: ButtonState::Pressed });
+                    pointer.frame(self);
+                    let serial = SERIAL_COUNTER.next_serial();
+                    pointer.button(self, &ButtonEvent { serial, time, button, state: ButtonState::Released });
+                    pointer.frame(self);
+                }
+                AutomationRequest::Input(SyntheticInput::Key { keycode, pressed }) => {
+                    let Some(keyboard) = seat.get_keyboard() else { continue };
+                    let state = if pressed { KeyState::Pressed } else { KeyState::Released };
+                    // Linux input event codes are offset by 8 in XKB
+                    keyboard.input::<(), _>(self, (keycode + 8).into(), state, serial, time, |_, _, _| {
+                        FilterResult::Forward
+                    });
+                }
+                AutomationRequest::FocusWindow { window_id } => {
+                    let window = self.window_by_id(window_id).cloned();
+                    let Some(window) = window else {
+                        warn!("Cannot focus window {}: not found", window_id);
+                        continue;
+                    };
+                    self.focus_window(seat, &window, serial);
+                }
+                AutomationRequest::SwitchWorkspace { output, workspace } => {
+                    if let Err(e) = self.switch_workspace(&output, workspace) {
+                        warn!("Cannot switch output {} to workspace {}: {}", output, workspace, e);
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Move keyboard focus to a surface, as far as layer surfaces allow
+    ///
+    /// Layer surfaces without keyboard interactivity are never focused, so
+    /// clicking a panel leaves focus where it was. While a layer surface holds
+    /// focus exclusively, the requested surface gets focus once it goes away.
+    pub fn set_keyboard_focus(&mut self, seat: &Seat<Self>, surface: Option<WlSurface>, serial: Serial) {
+        let Some(keyboard) = seat.get_keyboard() else { return };
+        if surface.as_ref().is_some_and(|surface| !self.layer_focus.accepts_focus(&surface.id())) {
+            return;
+        }
+        if let Some(exclusive) = self.layer_focus.exclusive() {
+            if surface.as_ref() != Some(exclusive) {
+                debug!("Keyboard focus held by an exclusive layer surface");
+                self.layer_focus.save_focus(surface);
+                return;
+            }
+        }
+        keyboard.set_focus(self, surface, serial);
+    }
+    
+    /// Hand focus to the topmost exclusive layer surface, or back from it
+    ///
+    /// Call after layer surfaces commit or are destroyed.
+    pub fn process_layer_focus(&mut self, seat: &Seat<Self>) {
+        if !self.layer_focus.take_changed() {
+            return;
+        }
+        let Some(keyboard) = seat.get_keyboard() else { return };
+        let current = keyboard.current_focus();
+        let serial = SERIAL_COUNTER.next_serial();
+        
+        if let Some(exclusive) = self.layer_focus.exclusive().cloned() {
+            if current.as_ref() != Some(&exclusive) {
+                info!("Exclusive layer surface took keyboard focus");
+                self.layer_focus.save_focus(current);
+                keyboard.set_focus(self, Some(exclusive), serial);
+            }
+            return;
+        }
+        if let Some(restore) = self.layer_focus.take_restore() {
+            keyboard.set_focus(self, Some(restore), serial);
+        } else if current.is_some_and(|surface| !surface.is_alive() || !self.layer_focus.accepts_focus(&surface.id())) {
+            // Focus was left on a layer surface that no longer takes keyboard input
+            keyboard.set_focus(self, None, serial);
+        }
+    }
+    
+    /// Send clicks whose dwell or secondary click countdown has completed
+    ///
+    /// Call when `click_assist.next_deadline()` passes.
+    pub fn process_click_assist(&mut self, seat: &Seat<Self>) {
+        let Some(action) = self.click_assist.poll(std::time::Instant::now()) else { return };
+        let Some(pointer) = seat.get_pointer() else { return };
+        self.send_assist_action(&pointer, action);
+    }
+    
+    /// Re-evaluate focus mode from its schedule, the focused window and the
+    /// override set over IPC, and map the notifications it held back once
+    /// they are allowed again
+    ///
+    /// Call every event loop iteration, which runs at least every
+    /// `MAX_DISPATCH_INTERVAL`, and when keyboard focus changes.
+    pub fn process_focus_mode(&mut self) {
+        let window = self.active_window(&self.seat.clone());
+        let app_id = window.as_ref().map(window_identity::app_id).filter(|app_id| !app_id.is_empty());
+        let fullscreen = window
+            .as_ref()
+            .and_then(|window| window.toplevel())
+            .is_some_and(|toplevel| toplevel.current_state().states.contains(xdg_toplevel::State::Fullscreen));
+        self.focus_mode.update(focus_mode::local_minute_of_day(), app_id.as_deref(), fullscreen);
+        
+        let (allowed, suppressed) = std::mem::take(&mut self.suppressed_layer_surfaces)
+            .into_iter()
+            .filter(|(surface, ..)| surface.alive())
+            .partition::<Vec<_>, _>(|(_, _, namespace)| self.focus_mode.allows_layer_surface(namespace));
+        self.suppressed_layer_surfaces = suppressed;
+        for (surface, layer, namespace) in allowed {
+            info!("Showing notification surface '{}' held back by focus mode", namespace);
+            self.map_layer_surface(surface, layer, namespace);
+        }
+    }
+    
+    /// Add a layer surface to stacking, blur, styles and keyboard focus
+    fn map_layer_surface(&mut self, surface: LayerSurface, layer: Layer, namespace: String) {
+        self.blur.assign_layer_surface(surface.wl_surface().id(), &namespace);
+        self.stacking.insert(surface.wl_surface().id(), layer.into());
+        self.surface_styles.assign(surface.wl_surface().id(), config::SurfaceClass::Panel);
+        self.layer_focus.insert(surface.wl_surface().clone(), layer);
+        
+        // Log layer-specific integration details
+        match layer {
+            Layer::Background => {
+                info!("Background layer surface - setting up wallpaper/background rendering");
+                // TODO: Configure for full-screen background rendering
+                // TODO: Set up background blur effect support
+                // TODO: Integrate with wallpaper management system
+            }
+            Layer::Bottom => {
+                info!("Bottom layer surface - setting up below-window elements");
+                // TODO: Configure for widget and decoration rendering
+                // TODO: Set up exclusive zone management for bottom layer
+                // TODO: Integrate with desktop widget system
+            }
+            Layer::Top => {
+                info!("Top layer surface - setting up panel/taskbar integration");
+                // TODO: Configure for panel rendering with glassmorphism effects
+                // TODO: Set up exclusive zone calculation for panels
+                // TODO: Integrate with app bar and taskbar systems
+                // TODO: Configure panel transparency and blur effects
+            }
+            Layer::Overlay => {
+                info!("Overlay layer surface - setting up notification/popup system");
+                // TODO: Configure for notification and modal dialog rendering
+                // TODO: Set up temporary surface lifecycle management
+                // TODO: Integrate with notification daemon and system dialogs
+            }
+        }
+        
+        // TODO: Comprehensive layer surface setup
+        // TODO: Apply anchoring and positioning constraints
+        // TODO: Configure exclusive zones based on surface role
+        // TODO: Set up output-specific rendering if targeted output specified
+        // TODO: Integrate with compositor's layer management system
+        // TODO: Configure glassmorphism effects for appropriate layer types
+        
+        debug!("Layer surface '{}' integrated into {:?} layer", namespace, layer);
+    }
+    
+    /// Fire the hot corner the pointer rests in
+    ///
+    /// Call when `hot_corners.next_deadline()` passes.
+    pub fn process_hot_corners(&mut self) {
+        if let Some(action) = self.hot_corners.poll(std::time::Instant::now()) {
+            self.run_hot_corner_action(action);
+        }
+    }
+    
+    /// Position on the output at `location` that hot corners are computed
+    /// from, switching them to that output's size
+    fn hot_corner_position(&mut self, location: Point<f64, Logical>) -> Option<Vec2> {
+        let output = self.space.output_under(location).next()?;
+        let geometry = self.space.output_geometry(output)?;
+        let size = Vec2::new(geometry.size.w as f32, geometry.size.h as f32);
+        if self.hot_corners.output_size() != size {
+            self.hot_corners.set_output_size(size);
+        }
+        Some((location - geometry.loc.to_f64()).to_geometry().into())
+    }
+    
+    /// Run the action of a hot corner or screen edge
+    fn run_hot_corner_action(&mut self, action: HotCornerAction) {
+        match action {
+            HotCornerAction::None => {}
+            HotCornerAction::ShowDesktop => self.toggle_show_desktop(),
+            HotCornerAction::Exec(command) => {
+                let command: Vec<String> = command.split_whitespace().map(String::from).collect();
+                if let Err(e) = self.launch(&command, None) {
+                    warn!("Failed to launch {:?}: {}", command, e);
+                }
+            }
+            // TODO: Open the overview and the launcher once the compositor has them
+            HotCornerAction::Overview | HotCornerAction::Launcher => {
+                debug!("Hot corner action {:?} is not available yet", action);
+            }
+        }
+    }
+    
+    /// Send the button events of an assisted click
+    fn send_assist_action(&mut self, pointer: &PointerHandle<Self>, action: AssistAction) {
+        let time = std::time::Duration::from(self.clock.now()).as_millis() as u32;
+        
+        let button = match action {
+            AssistAction::Click(button) | AssistAction::Press(button) => button,
+        };
+        pointer.button(self, &ButtonEvent { serial: SERIAL_COUNTER.next_serial(), time, button, state: ButtonState::Pressed });
+        pointer.frame(self);
+        if let AssistAction::Click(_) = action {
+            pointer.button(self, &ButtonEvent { serial: SERIAL_COUNTER.next_serial(), time, button, state: ButtonState::Released });
+            pointer.frame(self);
+        }
+    }
+    
+    /// Show the cursor again on pointer motion
+    pub fn cursor_motion(&mut self, location: Point<f64, Logical>) {
+        let output = self.space.output_under(location).next().map(|output| output.name());
+        if self.cursor_visibility.pointer_motion(output.as_deref(), std::time::Instant::now()) {
+            debug!("Cursor shown on pointer motion");
+            // TODO: Damage the cursor area on the output once the cursor is rendered
+        }
+    }
+    
+    /// Apply sticky edges and barriers to pointer motion from `from` to `to`
+    ///
+    /// Call from the pointer motion path before moving the pointer.
+    pub fn constrain_pointer_motion(&mut self, from: Point<f64, Logical>, to: Point<f64, Logical>) -> Point<f64, Logical> {
+        let outputs: Vec<OutputArea> = self
+            .space
+            .outputs()
+            .filter_map(|output| {
+                Some(OutputArea {
+                    name: output.name(),
+                    geometry: self.space.output_geometry(output)?,
+                })
+            })
+            .collect();
+        self.pointer_barriers.constrain(from, to, &outputs, std::time::Instant::now())
+    }
+    
+    /// Hide the cursor while typing, if configured
+    ///
+    /// Call for key presses other than modifiers.
+    pub fn cursor_key_pressed(&mut self, seat: &Seat<Self>) {
+        let constrained = Self::pointer_constrained(seat);
+        if self.cursor_visibility.key_pressed(constrained) {
+            // TODO: Damage the cursor area on the output once the cursor is rendered
+        }
+    }
+    
+    /// Hide the cursor after pointer inactivity
+    ///
+    /// Call when `cursor_visibility.next_deadline()` passes.
+    pub fn process_cursor_visibility(&mut self, seat: &Seat<Self>) {
+        let constrained = Self::pointer_constrained(seat);
+        if self.cursor_visibility.poll(constrained, std::time::Instant::now()) {
+            // TODO: Damage the cursor area on the output once the cursor is rendered
+        }
+    }
+    
+    /// Deliver seat work queued since the last iteration, e.g. automation
+    /// requests and clicks whose dwell completed
+    pub fn process_seat(&mut self) {
+        let seat = self.seat.clone();
+        self.process_automation(&seat);
+        self.libinput_devices.process_toggle_requests();
+        self.process_layer_focus(&seat);
+        self.process_click_assist(&seat);
+        self.process_hot_corners();
+        self.process_cursor_visibility(&seat);
+        self.process_screenshots(&seat);
+        let focused = seat.get_keyboard().and_then(|keyboard| keyboard.current_focus());
+        self.update_text_input_activity(&seat, focused.as_ref());
+    }
+    
+    /// Show the on-screen keyboard only while the focused surface has an
+    /// enabled text input
+    ///
+    /// Clients enable and disable text input without a callback, so call
+    /// after dispatching their requests as well as when focus changes.
+    fn update_text_input_activity(&mut self, seat: &Seat<Self>, focused: Option<&WlSurface>) {
+        let mut text_input_active = false;
+        if let Some(focused) = focused {
+            seat.text_input().with_active_text_input(|_text_input, surface| {
+                text_input_active |= surface == focused;
+            });
+        }
+        self.on_screen_keyboard.set_text_input_active(text_input_active);
+    }
+    
+    /// Apply input device settings and the keyboard layout
+    pub fn set_input_config(&mut self, config: &config::InputConfig) {
+        self.libinput_devices.set_config(config.clone());
+        self.set_keyboard_config(&config.keyboard);
+    }
+    
+    /// Apply the keyboard layout and key repeat settings
+    fn set_keyboard_config(&mut self, config: &config::KeyboardConfig) {
+        let Some(keyboard) = self.seat.get_keyboard() else { return };
+        let xkb = XkbConfig {
+            rules: &config.rules,
+            model: &config.model,
+            layout: &config.layout,
+            variant: &config.variant,
+            options: config.options.clone(),
+        };
+        if let Err(e) = keyboard.set_xkb_config(self, xkb) {
+            warn!("Failed to apply keyboard layout {:?}: {:?}", config.layout, e);
+        }
+        keyboard.change_repeat_info(config.repeat_rate as i32, config.repeat_delay as i32);
+    }
+    
+    /// Translate an input device event into seat events for clients
+    pub fn process_input_event<B: InputBackend>(&mut self, event: InputEvent<B>) {
+        let seat = self.seat.clone();
+        if !matches!(event, InputEvent::DeviceAdded { .. } | InputEvent::DeviceRemoved { .. }) {
+            let changes = self.output_power.activity(std::time::Instant::now());
+            self.apply_power_changes(changes);
+        }
+        match event {
+            InputEvent::Keyboard { event } => {
+                let serial = SERIAL_COUNTER.next_serial();
+                self.handle_key(&seat, event.key_code(), event.state(), serial, event.time_msec());
+            }
+            InputEvent::PointerMotion { event } => {
+                let Some(pointer) = seat.get_pointer() else { return };
+                let from = pointer.current_location();
+                let to = self.clamp_to_outputs(from, from + event.delta());
+                let to = self.constrain_pointer_motion(from, to);
+                let relative = RelativeMotionEvent {
+                    delta: event.delta(),
+                    delta_unaccel: event.delta_unaccel(),
+                    utime: event.time(),
+                };
+                self.pointer_motion(&pointer, to, event.time_msec(), Some(relative));
+            }
+            InputEvent::PointerMotionAbsolute { event } => {
+                let Some(pointer) = seat.get_pointer() else { return };
+                let Some(location) = self.absolute_location::<B, _>(&event) else { return };
+                self.pointer_motion(&pointer, location, event.time_msec(), None);
+            }
+            InputEvent::PointerButton { event } => {
+                self.pointer_button(&seat, event.button_code(), event.state(), event.time_msec());
+            }
+            InputEvent::PointerAxis { event } => {
+                let Some(pointer) = seat.get_pointer() else { return };
+                let source = event.source();
+                let mut frame = AxisFrame::new(event.time_msec()).source(source);
+                for axis in [Axis::Horizontal, Axis::Vertical] {
+                    let amount = event.amount(axis);
+                    let v120 = event.amount_v120(axis);
+                    // Wheels report only discrete steps; 15 degrees per step
+                    let value = amount.or_else(|| v120.map(|steps| steps * 15.0 / 120.0)).unwrap_or(0.0);
+                    if value != 0.0 {
+                        frame = frame.relative_direction(axis, event.relative_direction(axis)).value(axis, value);
+                        if let Some(steps) = v120 {
+                            frame = frame.v120(axis, steps as i32);
+                        }
+                    } else if source == AxisSource::Finger && amount.is_some() {
+                        // Fingers lifted from the touchpad, kinetic scrolling may start
+                        frame = frame.stop(axis);
+                    }
+                }
+                pointer.axis(self, frame);
+                pointer.frame(self);
+            }
+            InputEvent::TouchDown { event } => {
+                let Some(touch) = seat.get_touch() else { return };
+                let Some(location) = self.absolute_location::<B, _>(&event) else { return };
+                let serial = SERIAL_COUNTER.next_serial();
+                self.focus_under(&seat, location, serial);
+                let focus = self.surface_under(location);
+                touch.down(self, focus, &DownEvent { slot: event.slot(), location, serial, time: event.time_msec() });
+                if let Some(position) = self.hot_corner_position(location) {
+                    self.hot_corners.touch_down(event.slot().into(), position);
+                }
+            }
+            InputEvent::TouchMotion { event } => {
+                let Some(touch) = seat.get_touch() else { return };
+                let Some(location) = self.absolute_location::<B, _>(&event) else { return };
+                let focus = self.surface_under(location);
+                touch.motion(self, focus, &TouchMotionEvent { slot: event.slot(), location, time: event.time_msec() });
+                let action = self
+                    .hot_corner_position(location)
+                    .and_then(|position| self.hot_corners.touch_motion(event.slot().into(), position, std::time::Instant::now()));
+                if let Some(action) = action {
+                    self.run_hot_corner_action(action);
+                }
+            }
+            InputEvent::TouchUp { event } => {
+                let Some(touch) = seat.get_touch() else { return };
+                let serial = SERIAL_COUNTER.next_serial();
+                touch.up(self, &UpEvent { slot: event.slot(), serial, time: event.time_msec() });
+                self.hot_corners.touch_up(event.slot().into());
+            }
+            InputEvent::TouchFrame { .. } => {
+                if let Some(touch) = seat.get_touch() {
+                    touch.frame(self);
+                }
+            }
+            InputEvent::TouchCancel { .. } => {
+                if let Some(touch) = seat.get_touch() {
+                    touch.cancel(self);
+                }
+                self.hot_corners.touch_cancel();
+            }
+            InputEvent::DeviceAdded { device } => {
+                info!("Input device added: {}", device.name());
+                // Touch-only sessions get the on-screen keyboard
+                self.on_screen_keyboard.device_added(
+                    device.has_capability(DeviceCapability::Touch),
+                    device.has_capability(DeviceCapability::Keyboard),
+                );
+            }
+            InputEvent::DeviceRemoved { device } => {
+                info!("Input device removed: {}", device.name());
+                self.on_screen_keyboard.device_removed(
+                    device.has_capability(DeviceCapability::Touch),
+                    device.has_capability(DeviceCapability::Keyboard),
+                );
+            }
+            InputEvent::SwitchToggle { event } => {
+                if event.switch() == Some(Switch::Lid) {
+                    self.lid_toggled(event.state() == SwitchState::On);
+                }
+            }
+            // TODO: Forward gestures and tablet tools
+            _ => {}
+        }
+    }
+    
+    /// Move the pointer to `location` and send motion to the surface under it
+    fn pointer_motion(
+        &mut self,
+        pointer: &PointerHandle<Self>,
+        location: Point<f64, Logical>,
+        time: u32,
+        relative: Option<RelativeMotionEvent>,
+    ) {
+        // TODO: Hold locked pointers in place and keep confined pointers in
+        // their region once constraints are activated on pointer focus
+        let serial = SERIAL_COUNTER.next_serial();
+        let focus = self.surface_under(location);
+        pointer.motion(self, focus.clone(), &MotionEvent { location, serial, time });
+        if let Some(relative) = relative {
+            pointer.relative_motion(self, focus, &relative);
+        }
+        pointer.frame(self);
+        
+        if self.focus_follows_mouse && !pointer.is_grabbed() {
+            self.focus_under_pointer(location);
+        }
+        self.cursor_motion(location);
+        self.color_picker_motion(location);
+        if self.annotation.is_drawing() {
+            self.annotation.extend_stroke(Vec2::new(location.x as f32, location.y as f32), 1.0);
+        }
+        self.region_selection.pointer_moved(Vec2::new(location.x as f32, location.y as f32));
+        let position = location.to_geometry().into();
+        if let Some(action) = self.click_assist.pointer_motion(position, std::time::Instant::now()) {
+            self.send_assist_action(pointer, action);
+        }
+        // Dragging a window into a corner does not fire it
+        if !pointer.is_grabbed() {
+            let action = self
+                .hot_corner_position(location)
+                .and_then(|position| self.hot_corners.pointer_motion(position, std::time::Instant::now()));
+            if let Some(action) = action {
+                self.run_hot_corner_action(action);
+            }
+        }
+    }
+    
+    /// Pass a button event to clients unless the compositor consumes it
+    ///
+    /// Pressing a button focuses and raises the window under the pointer.
+    fn pointer_button(&mut self, seat: &Seat<Self>, button: u32, state: ButtonState, time: u32) {
+        let Some(pointer) = seat.get_pointer() else { return };
+        let pressed = state == ButtonState::Pressed;
+        if !pressed && self.intercepted_buttons.remove(&button) {
+            // A stroke or a region drag ends with the button that started it
+            self.annotation.end_stroke();
+            let location = pointer.current_location();
+            self.region_selection.pointer_released(Vec2::new(location.x as f32, location.y as f32));
+            return;
+        }
+        if pressed {
+            let dh = self.display_handle.clone();
+            let location = pointer.current_location();
+            if self.dialog_click(&dh, location)
+                || self.kill_mode_click(&dh, location)
+                || self.color_picker_click(seat)
+                || self.annotation_click(location)
+                || self.region_select_click(location)
+                || self.quick_settings.click(Vec2::new(location.x as f32, location.y as f32))
+            {
+                self.intercepted_buttons.insert(button);
+                return;
+            }
+        }
+        match self.click_assist.button(button, pressed, std::time::Instant::now()) {
+            ButtonDisposition::Forward => {}
+            ButtonDisposition::Suppress => return,
+            ButtonDisposition::Replace(action) => {
+                self.send_assist_action(&pointer, action);
+                return;
+            }
+        }
+        
+        let serial = SERIAL_COUNTER.next_serial();
+        if pressed && !pointer.is_grabbed() {
+            self.focus_under(seat, pointer.current_location(), serial);
+        }
+        pointer.button(self, &ButtonEvent { serial, time, button, state });
+        pointer.frame(self);
+    }
+    
+    /// Focus and raise the window under `location`, or focus the layer
+    /// surface there if it takes keyboard input
+    fn focus_under(&mut self, seat: &Seat<Self>, location: Point<f64, Logical>, serial: Serial) {
+        if let Some((surface, _)) = self.layer_surface_under(location, &[Layer::Overlay, Layer::Top]) {
+            self.set_keyboard_focus(seat, Some(surface), serial);
+            return;
+        }
+        let window = self.space.element_under(location).map(|(window, _)| window.clone());
+        if let Some(window) = window {
+            self.focus_window(seat, &window, serial);
+        }
+    }
+    
+    /// Raise a window and give it keyboard focus
+    fn focus_window(&mut self, seat: &Seat<Self>, window: &Window, serial: Serial) {
+        // Activation follows keyboard focus in focus_changed
+        self.space.raise_element(window, false);
+        let surface = window.toplevel().map(|toplevel| toplevel.wl_surface().clone());
+        if let Some(surface) = &surface {
+            self.stacking.raise(&surface.id());
+        }
+        self.set_keyboard_focus(seat, surface, serial);
+    }
+    
+    /// Focus the window under the pointer without raising it, unless a
+    /// layer surface such as a launcher has keyboard focus
+    fn focus_under_pointer(&mut self, location: Point<f64, Logical>) {
+        let seat = self.seat.clone();
+        let Some(keyboard) = seat.get_keyboard() else { return };
+        let focus_on_window = keyboard
+            .current_focus()
+            .is_none_or(|surface| self.window_for_surface(&surface).is_some());
+        if !focus_on_window || self.layer_surface_under(location, &[Layer::Overlay, Layer::Top]).is_some() {
+            return;
+        }
+        let Some(window) = self.space.element_under(location).map(|(window, _)| window.clone()) else { return };
+        if self.active_window.as_ref() == Some(&window) {
+            return;
+        }
+        let surface = window.toplevel().map(|toplevel| toplevel.wl_surface().clone());
+        self.set_keyboard_focus(&seat, surface, SERIAL_COUNTER.next_serial());
+    }
+    
+    /// Give keyboard focus to the topmost visible window, or to nothing
+    /// when there is none, e.g. after the focused window closed
+    fn focus_topmost_window(&mut self) {
+        let window = self
+            .stacking
+            .top_to_bottom()
+            .filter(|id| self.workspaces.placement(id).is_some() && self.workspaces.is_visible(id))
+            .find_map(|id| {
+                self.space
+                    .elements()
+                    .find(|window| window.toplevel().is_some_and(|toplevel| toplevel.wl_surface().id() == *id))
+            })
+            .cloned();
+        let seat = self.seat.clone();
+        let serial = SERIAL_COUNTER.next_serial();
+        match window {
+            Some(window) => self.focus_window(&seat, &window, serial),
+            None => self.set_keyboard_focus(&seat, None, serial),
+        }
+    }
+    
+    /// Show the window with keyboard focus as activated and the previous
+    /// one as not, and announce the change
+    fn set_active_window(&mut self, window: Option<Window>) {
+        if self.active_window == window {
+            return;
+        }
+        if let Some(previous) = self.active_window.take() {
+            self.send_activated(&previous, false);
+        }
+        if let Some(window) = &window {
+            self.send_activated(window, true);
+            self.set_urgent(window, false);
+        }
+        self.active_window = window;
+    }
+    
+    /// Mark a window as asking for attention, unless it has focus, or
+    /// clear that, and announce the change
+    fn set_urgent(&mut self, window: &Window, urgent: bool) {
+        let Some(id) = window.toplevel().map(|toplevel| toplevel.wl_surface().id()) else { return };
+        let changed = if urgent && self.active_window.as_ref() != Some(window) {
+            self.urgent_windows.set_urgent(id.clone(), &window_identity::app_id(window), std::time::Instant::now())
+        } else {
+            self.urgent_windows.clear(&id)
+        };
+        if changed {
+            debug!("Window {:?} urgent: {}", id, urgent);
+            self.window_identities.window_urgency(window, urgent);
+        }
+    }
+    
+    fn send_activated(&mut self, window: &Window, activated: bool) {
+        if !window.set_activated(activated) {
+            return;
+        }
+        self.window_identities.window_activated(window, activated);
+        // Windows not configured yet get the state with their initial configure
+        if let Some(toplevel) = window.toplevel().filter(|toplevel| toplevel.is_initial_configure_sent()).cloned() {
+            self.send_configure(&toplevel);
+        }
+    }
+    
+    /// Surface under a point and its origin, layer surfaces above and below
+    /// windows included
+    fn surface_under(&self, location: Point<f64, Logical>) -> Option<(WlSurface, Point<f64, Logical>)> {
+        self.layer_surface_under(location, &[Layer::Overlay, Layer::Top])
+            .or_else(|| {
+                let (window, origin) = self.space.element_under(location)?;
+                let (surface, offset) = window.surface_under(location - origin.to_f64(), WindowSurfaceType::ALL)?;
+                Some((surface, (origin + offset).to_f64()))
+            })
+            .or_else(|| self.layer_surface_under(location, &[Layer::Bottom, Layer::Background]))
+    }
+    
+    /// Surface of the topmost layer surface in `layers` under a point
+    fn layer_surface_under(&self, location: Point<f64, Logical>, layers: &[Layer]) -> Option<(WlSurface, Point<f64, Logical>)> {
+        let output = self.space.output_under(location).next()?;
+        let output_origin = self.space.output_geometry(output)?.loc;
+        let layer_map = layer_map_for_output(output);
+        let local = location - output_origin.to_f64();
+        layers.iter().find_map(|&layer| {
+            let layer_surface = layer_map.layer_under(layer, local)?;
+            let layer_origin = layer_map.layer_geometry(layer_surface)?.loc;
+            let (surface, offset) = layer_surface.surface_under(local - layer_origin.to_f64(), WindowSurfaceType::ALL)?;
+            Some((surface, (output_origin + layer_origin + offset).to_f64()))
+        })
+    }
+    
+    /// Keep relative pointer motion from leaving the outputs
+    fn clamp_to_outputs(&self, from: Point<f64, Logical>, to: Point<f64, Logical>) -> Point<f64, Logical> {
+        if self.space.output_under(to).next().is_some() {
+            return to;
+        }
+        let Some(geometry) = self
+            .space
+            .output_under(from)
+            .next()
+            .or_else(|| self.space.outputs().next())
+            .and_then(|output| self.space.output_geometry(output))
+        else {
+            return to;
+        };
+        let geometry = geometry.to_f64();
+        let x = to.x.clamp(geometry.loc.x, geometry.loc.x + geometry.size.w - 1.0);
+        let y = to.y.clamp(geometry.loc.y, geometry.loc.y + geometry.size.h - 1.0);
+        (x, y).into()
+    }
+    
+    /// Position of an absolute pointer or touch event
+    fn absolute_location<B: InputBackend, E: AbsolutePositionEvent<B>>(&self, event: &E) -> Option<Point<f64, Logical>> {
+        // TODO: Map touchscreens and tablets to their own output
+        let output = self.space.outputs().next()?;
+        let geometry = self.space.output_geometry(output)?;
+        Some(event.position_transformed(geometry.size) + geometry.loc.to_f64())
+    }
+    
+    /// Launch a command for the launcher, the dock, a keybinding or IPC,
+    /// with startup feedback until the app shows a window
+    pub fn launch(&mut self, command: &[String], app_id: Option<&str>) -> Result<()> {
+        let token = self.start_launch(app_id);
+        match launch_command(command, self.socket_name.as_deref(), Some(&token)) {
+            Ok(pid) => {
+                info!("Launched {:?} (pid {})", command, pid);
+                Ok(())
+            }
+            Err(e) => {
+                self.end_launch(&token);
+                Err(e)
+            }
+        }
+    }
+    
+    /// Launch applications requested over IPC, each with its activation token
+    pub fn process_launch_requests(&mut self) {
+        for request in self.launch_requests.drain() {
+            if self.kiosk.is_active() {
+                warn!("Ignoring launch of {:?} in kiosk mode", request.command);
+                continue;
+            }
+            if let Err(e) = self.launch(&request.command, request.app_id.as_deref()) {
+                warn!("Failed to launch {:?}: {}", request.command, e);
+            }
+        }
+    }
+    
+    /// Create the activation token for an app about to be launched and start
+    /// its startup feedback
+    fn start_launch(&mut self, app_id: Option<&str>) -> String {
+        let data = XdgActivationTokenData { app_id: app_id.map(str::to_string), ..Default::default() };
+        let token = self.xdg_activation_state.create_external_token(data).0.as_str().to_string();
+        self.startup_feedback.launch_started(&token, app_id, std::time::Instant::now());
+        self.startup_feedback_changed();
+        token
+    }
+    
+    /// Stop the startup feedback of a launch that failed
+    fn end_launch(&mut self, token: &str) {
+        self.xdg_activation_state.remove_token(&XdgActivationToken::from(token.to_string()));
+        if self.startup_feedback.launch_ended(token) {
+            self.startup_feedback_changed();
+        }
+    }
+    
+    /// Give up on launched apps that never showed a window
+    ///
+    /// Call when `startup_feedback.next_deadline()` passes.
+    pub fn process_startup_feedback(&mut self) {
+        if self.startup_feedback.poll(std::time::Instant::now()) {
+            debug!("Launched app did not show a window in time");
+            self.startup_feedback_changed();
+        }
+    }
+    
+    /// Update the cursor after launches started or ended
+    fn startup_feedback_changed(&mut self) {
+        // TODO: Show the progress cursor shape while busy once the cursor is rendered
+        debug!("Startup busy cursor {}", if self.startup_feedback.busy_cursor() { "shown" } else { "hidden" });
+    }
+    
+    /// Whether the focused surface holds an active pointer lock or confinement
+    fn pointer_constrained(seat: &Seat<Self>) -> bool {
+        let Some(pointer) = seat.get_pointer() else { return false };
+        let Some(surface) = pointer.current_focus() else { return false };
+        with_pointer_constraint(&surface, &pointer, |constraint| constraint.is_some_and(|constraint| constraint.is_active()))
+    }
+    
+    /// Ping shell clients that are due and report those that stopped answering
+    ///
+    /// Call every event loop iteration; the loop also wakes up when
+    /// `responsiveness.next_deadline()` passes.
+    pub fn check_responsiveness(&mut self) {
+        let now = std::time::Instant::now();
+        let toplevels = self.xdg_shell_state.toplevel_surfaces().to_vec();
+        let clients: HashSet<ClientId> = toplevels
+            .iter()
+            .filter_map(|toplevel| toplevel.wl_surface().client())
+            .map(|client| client.id())
+            .collect();
+        self.unresponsive_dialogs.retain(|(client, _)| clients.contains(client));
+        
+        for client in self.responsiveness.pings_due(&clients, now) {
+            if let Some(toplevel) = Self::toplevel_of_client(&toplevels, &client) {
+                Self::send_ping(toplevel);
+            }
+        }
+        
+        for client in self.responsiveness.poll(now) {
+            let name = Self::toplevel_of_client(&toplevels, &client)
+                .map(Self::application_name)
+                .unwrap_or_else(|| "Application".to_string());
+            warn!("{} is not responding", name);
+            let dialog = Dialog::new(
+                &format!("{} is not responding", name),
+                &[("Force Quit", UnresponsiveChoice::ForceQuit), ("Wait", UnresponsiveChoice::Wait)],
+                &self.dialog_style,
+            );
+            self.unresponsive_dialogs.retain(|(shown, _)| *shown != client);
+            self.unresponsive_dialogs.push((client, dialog));
+            self.accessibility
+                .tree()
+                .announce(format!("{} is not responding", name), Politeness::Assertive);
+        }
+    }
+    
+    /// Apply the user's answer to the "not responding" dialog
+    pub fn respond_to_unresponsive(&mut self, dh: &DisplayHandle, client: &ClientId, choice: UnresponsiveChoice) {
+        self.unresponsive_dialogs.retain(|(shown, _)| shown != client);
+        match choice {
+            UnresponsiveChoice::Wait => self.responsiveness.wait(client, std::time::Instant::now()),
+            UnresponsiveChoice::ForceQuit => {
+                let process = Self::client_process(dh, client);
+                info!("Force quitting unresponsive client (pid {:?})", process.as_ref().map(|process| process.pid));
+                Self::force_quit(dh, client, process.as_ref());
+            }
+        }
+    }
+    
+    /// Where a client's dialog is shown: over its first window, or over the
+    /// first output if it has none mapped
+    fn client_dialog_anchor(&self, client: &ClientId) -> Rect {
+        self.dialog_anchor(|toplevel| toplevel.wl_surface().client().is_some_and(|window_client| window_client.id() == *client))
+    }
+    
+    /// Where a dialog about a window is shown: over the first window matching
+    /// `about`, or over the first output if none is mapped
+    fn dialog_anchor(&self, about: impl Fn(&ToplevelSurface) -> bool) -> Rect {
+        let window = self.space.elements().find(|window| window.toplevel().is_some_and(&about));
+        window
+            .and_then(|window| self.space.element_geometry(window))
+            .or_else(|| self.space.outputs().next().and_then(|output| self.space.output_geometry(output)))
+            .map(render_rect)
+            .unwrap_or_else(|| Rect::new(0.0, 0.0, 0.0, 0.0))
+    }
+    
+    /// Where kill mode's confirmation is shown: over the picked window
+    fn kill_dialog_anchor(&self, target: &KillTarget) -> Rect {
+        self.dialog_anchor(|toplevel| toplevel.wl_surface().id() == target.surface)
+    }
+    
+    /// Press the dialog button under the pointer, topmost dialog first
+    ///
+    /// Returns whether the click was on a dialog and must not reach clients.
+    /// Clicking outside kill mode's confirmation cancels it.
+    fn dialog_click(&mut self, dh: &DisplayHandle, location: Point<f64, Logical>) -> bool {
+        let point = Vec2::new(location.x as f32, location.y as f32);
+        if let Some(target) = self.kill_mode.target() {
+            match target.dialog.hit(self.kill_dialog_anchor(target), point) {
+                DialogHit::Button(true) => self.confirm_kill(dh),
+                DialogHit::Button(false) | DialogHit::Outside => self.cancel_kill_mode(),
+                DialogHit::Card => {}
+            }
+            return true;
+        }
+        for index in (0..self.unresponsive_dialogs.len()).rev() {
+            let (client, dialog) = &self.unresponsive_dialogs[index];
+            match dialog.hit(self.client_dialog_anchor(client), point) {
+                DialogHit::Button(choice) => {
+                    let client = client.clone();
+                    self.respond_to_unresponsive(dh, &client, choice);
+                    return true;
+                }
+                DialogHit::Card => return true,
+                DialogHit::Outside => {}
+            }
+        }
+        false
+    }
+    
+    /// Index of the "not responding" dialog over the focused client
+    fn focused_unresponsive_dialog(&self, focus: Option<&ClientId>) -> Option<usize> {
+        let focus = focus?;
+        self.unresponsive_dialogs.iter().position(|(client, _)| client == focus)
+    }
+    
+    /// Whether a dialog answers keys: kill mode's confirmation, or the
+    /// dialog over the focused client
+    fn dialog_has_keyboard(&self, focus: Option<&ClientId>) -> bool {
+        self.kill_mode.target().is_some() || self.focused_unresponsive_dialog(focus).is_some()
+    }
+    
+    /// Apply a key pressed while a dialog has the keyboard
+    fn dialog_key(&mut self, dh: &DisplayHandle, focus: Option<&ClientId>, key: DialogKey) {
+        if let KillMode::Confirming(target) = &mut self.kill_mode {
+            match target.dialog.key(key) {
+                Some(true) => self.confirm_kill(dh),
+                Some(false) => self.cancel_kill_mode(),
+                None => {}
+            }
+            return;
+        }
+        let Some(index) = self.focused_unresponsive_dialog(focus) else { return };
+        let (client, dialog) = &mut self.unresponsive_dialogs[index];
+        if let Some(choice) = dialog.key(key) {
+            let client = client.clone();
+            self.respond_to_unresponsive(dh, &client, choice);
+        }
+    }
+    
+    /// Pass a key event to the focused client unless it is part of a keybinding
+    ///
+    /// Keybindings are off in kiosk mode and while a client grabs the keyboard.
+    /// While kill mode asks for confirmation or a dialog is shown over the
+    /// focused client, Tab, Enter and Escape answer it instead, and while
+    /// annotating, Ctrl+Z, Ctrl+S and Escape are annotation commands. While
+    /// selecting a screenshot region, the arrows adjust it, with Shift its
+    /// size and with Ctrl by ten pixels, Enter takes the screenshot and
+    /// Escape cancels. While the quick settings panel is open, the arrows,
+    /// Home, End and Tab move between and adjust its controls, Enter toggles
+    /// and Escape closes it. Typing other than modifiers hides the cursor, if
+    /// configured.
+    pub fn handle_key(&mut self, seat: &Seat<Self>, keycode: Keycode, state: KeyState, serial: Serial, time: u32) {
+        let Some(keyboard) = seat.get_keyboard() else { return };
+        let bindings_enabled = !self.kiosk.is_active() && !keyboard.is_grabbed();
+        let focus = keyboard.current_focus().and_then(|surface| surface.client()).map(|client| client.id());
+        let dialog_open = self.dialog_has_keyboard(focus.as_ref());
+        let annotating = self.annotation.is_active();
+        let selecting_region = self.region_selection.is_active();
+        let quick_settings_open = self.quick_settings.is_open();
+        let mut dialog_key = None;
+        let mut annotation_command = None;
+        let mut region_key = None;
+        let mut quick_settings_key = None;
+        let mut typed = false;
+        let action = keyboard.input(self, keycode, state, serial, time, |data, modifiers, handle| {
+            typed = state == KeyState::Pressed && !handle.modified_sym().is_modifier_key();
+            if state == KeyState::Pressed && dialog_open {
+                dialog_key = match handle.modified_sym() {
+                    Keysym::Tab => Some(DialogKey::Next),
+                    Keysym::Return | Keysym::KP_Enter => Some(DialogKey::Press),
+                    Keysym::Escape => Some(DialogKey::Cancel),
+                    _ => None,
+                };
+                if dialog_key.is_some() {
+                    data.intercepted_keys.insert(keycode);
+                    return FilterResult::Intercept(None);
+                }
+            }
+            if state == KeyState::Pressed && selecting_region && !dialog_open {
+                let key = match handle.modified_sym() {
+                    Keysym::Left | Keysym::KP_Left => Some(NavigationKey::Left),
+                    Keysym::Right | Keysym::KP_Right => Some(NavigationKey::Right),
+                    Keysym::Up | Keysym::KP_Up => Some(NavigationKey::Up),
+                    Keysym::Down | Keysym::KP_Down => Some(NavigationKey::Down),
+                    Keysym::Return | Keysym::KP_Enter => Some(NavigationKey::Activate),
+                    Keysym::Escape => Some(NavigationKey::Cancel),
+                    _ => None,
+                };
+                if let Some(key) = key {
+                    region_key = Some((key, modifiers.shift, modifiers.ctrl));
+                    data.intercepted_keys.insert(keycode);
+                    return FilterResult::Intercept(None);
+                }
+            }
+            if state == KeyState::Pressed && quick_settings_open && !dialog_open && !selecting_region {
+                quick_settings_key = match handle.modified_sym() {
+                    Keysym::Left | Keysym::KP_Left => Some(NavigationKey::Left),
+                    Keysym::Right | Keysym::KP_Right => Some(NavigationKey::Right),
+                    Keysym::Up | Keysym::KP_Up => Some(NavigationKey::Up),
+                    Keysym::Down | Keysym::KP_Down => Some(NavigationKey::Down),
+                    Keysym::Home | Keysym::KP_Home => Some(NavigationKey::Home),
+                    Keysym::End | Keysym::KP_End => Some(NavigationKey::End),
+                    Keysym::Tab => Some(NavigationKey::Tab),
+                    Keysym::ISO_Left_Tab => Some(NavigationKey::BackTab),
+                    Keysym::Return | Keysym::KP_Enter => Some(NavigationKey::Activate),
+                    Keysym::Escape => Some(NavigationKey::Cancel),
+                    _ => None,
+                };
+                if quick_settings_key.is_some() {
+                    data.intercepted_keys.insert(keycode);
+                    return FilterResult::Intercept(None);
+                }
+            }
+            if state == KeyState::Pressed && annotating && !dialog_open {
+                annotation_command = match (modifiers.ctrl, handle.raw_latin_sym_or_raw_current_sym()) {
+                    (true, Some(Keysym::z)) => Some(AnnotationCommand::Undo),
+                    (true, Some(Keysym::s)) => Some(AnnotationCommand::Save),
+                    (_, Some(Keysym::Escape)) => Some(AnnotationCommand::Leave),
+                    _ => None,
+                };
+                if annotation_command.is_some() {
+                    data.intercepted_keys.insert(keycode);
+                    return FilterResult::Intercept(None);
+                }
+            }
+            match state {
+                KeyState::Pressed if bindings_enabled => {
+                    let action = handle
+                        .raw_latin_sym_or_raw_current_sym()
+                        .and_then(|keysym| data.key_bindings.key_pressed(keycode, modifiers, keysym));
+                    action.map_or(FilterResult::Forward, |action| FilterResult::Intercept(Some(action)))
+                }
+                KeyState::Released if data.intercepted_keys.remove(&keycode) => FilterResult::Intercept(None),
+                KeyState::Released if data.key_bindings.key_released(keycode) => FilterResult::Intercept(None),
+                _ => FilterResult::Forward,
+            }
+        });
+        if typed {
+            self.cursor_key_pressed(seat);
+        }
+        if let Some(key) = dialog_key {
+            let dh = self.display_handle.clone();
+            self.dialog_key(&dh, focus.as_ref(), key);
+        }
+        if let Some(command) = annotation_command {
+            self.annotation_command(command);
+        }
+        if let Some(key) = quick_settings_key {
+            self.quick_settings.key(key);
+        }
+        if let Some((key, resize, coarse)) = region_key {
+            if let Some(region) = self.region_selection.navigate(key, resize, coarse) {
+                let area = Rectangle::new(
+                    (region.x as i32, region.y as i32).into(),
+                    (region.width as i32, region.height as i32).into(),
+                );
+                self.capture_screenshot(ScreenshotTarget::Region, area);
+            }
+        }
+        if let Some(action) = action.flatten() {
+            self.run_binding(seat, action);
+        }
+    }
+    
+    /// Run the action of a keybinding
+    pub fn run_binding(&mut self, seat: &Seat<Self>, action: BindingAction) {
+        debug!("Running keybinding action {:?}", action);
+        match action {
+            BindingAction::None => {}
+            BindingAction::Spawn(command) => {
+                if let Err(e) = self.launch(&command, None) {
+                    warn!("Failed to launch {:?}: {}", command, e);
+                }
+            }
+            BindingAction::CloseWindow => {
+                let toplevel = self.active_window(seat).and_then(|window| window.toplevel().cloned());
+                if let Some(toplevel) = toplevel {
+                    self.close_window(&toplevel);
+                }
+            }
+            BindingAction::Workspace(workspace) => {
+                let Some(output) = self.active_output(seat) else { return };
+                if let Err(e) = self.switch_workspace(&output, workspace) {
+                    debug!("Not switching workspace: {}", e);
+                }
+            }
+            BindingAction::MoveToWorkspace(workspace) => {
+                if let Some(toplevel) = self.active_window(seat).and_then(|window| window.toplevel().cloned()) {
+                    self.workspaces.move_window(&toplevel.wl_surface().id(), workspace);
+                }
+            }
+            BindingAction::ToggleSticky => {
+                if let Some(toplevel) = self.active_window(seat).and_then(|window| window.toplevel().cloned()) {
+                    self.toggle_window_sticky(toplevel.wl_surface());
+                }
+            }
+            BindingAction::KillMode => self.toggle_kill_mode(),
+            BindingAction::ColorPicker => self.toggle_color_picker(),
+            BindingAction::Annotate => self.toggle_annotation(),
+            BindingAction::ScreenshotWindow => self.screenshot_active_window(seat),
+            BindingAction::ScreenshotOutput => self.screenshot_output(),
+            BindingAction::ScreenshotRegion => self.select_screenshot_region(),
+            BindingAction::RotateClockwise | BindingAction::RotateCounterClockwise => {
+                let direction = match action {
+                    BindingAction::RotateClockwise => RotationDirection::Clockwise,
+                    _ => RotationDirection::CounterClockwise,
+                };
+                let Some(output) = self.active_output(seat) else { return };
+                if let Err(e) = self.rotate_output(&output, direction) {
+                    warn!("{}", e);
+                }
+            }
+            BindingAction::QuickSettings => {
+                let location = seat.get_pointer().map(|pointer| pointer.current_location()).unwrap_or_default();
+                let output = self.space.output_under(location).next().or_else(|| self.space.outputs().next());
+                if let Some(geometry) = output.and_then(|output| self.space.output_geometry(output)) {
+                    self.quick_settings.toggle(render_rect(geometry));
+                }
+            }
+            BindingAction::ToggleRotationLock => {
+                let locked = !self.auto_rotation.is_locked();
+                self.set_rotation_lock(locked);
+            }
+            BindingAction::ToggleFlatAccel => {
+                self.libinput_devices.toggle_flat_accel();
+            }
+        }
+    }
+    
+    /// Window with keyboard focus
+    fn active_window(&self, seat: &Seat<Self>) -> Option<Window> {
+        let surface = seat.get_keyboard()?.current_focus()?;
+        self.window_for_surface(&surface).cloned()
+    }
+    
+    /// Name of the output under the pointer, or of the primary output
+    fn active_output(&self, seat: &Seat<Self>) -> Option<String> {
+        let pointer = seat.get_pointer().map(|pointer| pointer.current_location());
+        pointer
+            .and_then(|location| self.space.output_under(location).next())
+            .or_else(|| self.space.outputs().next())
+            .map(|output| output.name())
+    }
+    
+    /// Arm kill mode (force quit keybinding), or leave it if already active
+    pub fn toggle_kill_mode(&mut self) {
+        self.kill_mode = match self.kill_mode {
+            KillMode::Inactive => {
+                info!("Kill mode armed");
+                self.accessibility
+                    .tree()
+                    .announce("Click a window to force quit it", Politeness::Assertive);
+                KillMode::Selecting
+            }
+            _ => KillMode::Inactive,
+        };
+    }
+    
+    /// Pick the window under the pointer while kill mode is selecting
+    ///
+    /// Returns whether the click was consumed and must not reach clients.
+    pub fn kill_mode_click(&mut self, dh: &DisplayHandle, location: Point<f64, Logical>) -> bool {
+        if !self.kill_mode.is_selecting() {
+            return false;
+        }
+        // Clicks outside windows keep kill mode selecting
+        let Some(toplevel) = self
+            .space
+            .element_under(location)
+            .and_then(|(window, _)| window.toplevel().cloned())
+        else {
+            return true;
+        };
+        let Some(client) = toplevel.wl_surface().client() else { return true };
+        
+        let name = Self::application_name(&toplevel);
+        let process = Self::client_process(dh, &client.id());
+        info!("Kill mode picked {} (pid {:?})", name, process.as_ref().map(|process| process.pid));
+        self.accessibility.tree().announce(
+            format!("Force quit {}? Press Tab to choose Force Quit and Enter to confirm, or Escape to cancel", name),
+            Politeness::Assertive,
+        );
+        let dialog = Dialog::new(
+            &format!("Force quit {}?", name),
+            &[("Force Quit", true), ("Cancel", false)],
+            &self.dialog_style,
+        );
+        self.kill_mode = KillMode::Confirming(Box::new(KillTarget {
+            client: client.id(),
+            surface: toplevel.wl_surface().id(),
+            name,
+            process,
+            dialog,
+        }));
+        true
+    }
+    
+    /// Kill the client of the window picked in kill mode
+    pub fn confirm_kill(&mut self, dh: &DisplayHandle) {
+        let KillMode::Confirming(target) = std::mem::take(&mut self.kill_mode) else { return };
+        info!("Force quitting {}", target.name);
+        Self::force_quit(dh, &target.client, target.process.as_ref());
+    }
+    
+    /// Leave kill mode without killing anything
+    pub fn cancel_kill_mode(&mut self) {
+        self.kill_mode = KillMode::Inactive;
+    }
+    
+    /// Start the color picker (keybinding), or leave it if already active
+    pub fn toggle_color_picker(&mut self) {
+        if self.color_picker.is_active() {
+            self.color_picker.cancel();
+            return;
+        }
+        info!("Color picker started");
+        self.color_picker.start();
+        // The loupe opens where the pointer already is
+        if let Some(pointer) = self.seat.get_pointer() {
+            self.color_picker_motion(pointer.current_location());
+        }
+        // TODO: Show the crosshair cursor once the cursor is rendered
+    }
+    
+    /// Start annotation mode (keybinding), or leave it keeping the strokes
+    pub fn toggle_annotation(&mut self) {
+        self.annotation.toggle();
+        if self.annotation.is_active() {
+            self.accessibility.tree().announce(
+                "Drawing annotations. Ctrl+Z undoes, Ctrl+S saves and Escape clears and stops drawing",
+                Politeness::Assertive,
+            );
+        }
+    }
+    
+    /// Start a stroke at the pointer while annotation mode is active
+    ///
+    /// Returns whether the click was consumed and must not reach clients.
+    fn annotation_click(&mut self, location: Point<f64, Logical>) -> bool {
+        if !self.annotation.is_active() {
+            return false;
+        }
+        // TODO: Draw with tablet tools, with their pressure, once they are forwarded
+        self.annotation.begin_stroke(Vec2::new(location.x as f32, location.y as f32), 1.0);
+        true
+    }
+    
+    /// Apply a key pressed while annotating
+    fn annotation_command(&mut self, command: AnnotationCommand) {
+        match command {
+            AnnotationCommand::Undo => {
+                self.annotation.undo();
+            }
+            AnnotationCommand::Save => self.save_annotations(),
+            AnnotationCommand::Leave => {
+                self.annotation.clear();
+                self.annotation.set_active(false);
+            }
+        }
+    }
+    
+    /// Save the annotations as a PNG covering every output, from a thread as
+    /// rasterizing them at full size takes a while
+    fn save_annotations(&mut self) {
+        if self.annotation.is_empty() {
+            return;
+        }
+        let (width, height) = self
+            .space
+            .outputs()
+            .filter_map(|output| self.space.output_geometry(output))
+            .fold((0, 0), |(width, height), geometry| {
+                (width.max(geometry.loc.x + geometry.size.w), height.max(geometry.loc.y + geometry.size.h))
+            });
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let annotation = self.annotation.clone();
+        let path = self.screenshots.annotations_path();
+        std::thread::spawn(move || {
+            if let Err(e) = annotation.save_png(&path, width as u32, height as u32) {
+                warn!("Failed to save annotations to {}: {}", path.display(), e);
+            }
+        });
+        self.accessibility.tree().announce("Annotations saved", Politeness::Polite);
+    }
+    
+    /// Move the color picker's loupe with the pointer
+    pub fn color_picker_motion(&mut self, location: Point<f64, Logical>) {
+        if !self.color_picker.is_active() {
+            return;
+        }
+        // TODO: Read back the output under the pointer once each output
+        // renders its own frame; the renderer composes the first one
+        let Some(output) = self.space.outputs().next() else { return };
+        let Some(geometry) = self.space.output_geometry(output) else { return };
+        let pixel = (location - geometry.loc.to_f64()).to_physical(output.current_scale().fractional_scale());
+        self.color_picker.pointer_moved(pixel.x.floor() as i32, pixel.y.floor() as i32);
+    }
+    
+    /// Copy the color under the pointer while the color picker is active
+    ///
+    /// Returns whether the click was consumed and must not reach clients.
+    pub fn color_picker_click(&mut self, seat: &Seat<Self>) -> bool {
+        if !self.color_picker.is_active() {
+            return false;
+        }
+        // Clicks before the loupe has pixels keep the picker active
+        let Some(color) = self.color_picker.pick() else { return true };
+        info!("Picked color {}", color.text);
+        self.set_clipboard_text(seat, &color.text);
+        if self.color_picker.config().notify {
+            // TODO: Also show a notification toast once the compositor draws them
+            self.accessibility
+                .tree()
+                .announce(format!("Copied {} to the clipboard", color.text), Politeness::Polite);
+        }
+        true
+    }
+    
+    /// Screenshot the window with keyboard focus (keybinding)
+    pub fn screenshot_active_window(&mut self, seat: &Seat<Self>) {
+        let Some(window) = self.active_window(seat) else {
+            debug!("No active window to screenshot");
+            return;
+        };
+        let Some(geometry) = self.space.element_geometry(&window) else { return };
+        // TODO: With include_decorations, grow the region by the title bar
+        // and borders of server-side decorated windows once they are drawn
+        self.capture_screenshot(ScreenshotTarget::ActiveWindow, geometry);
+    }
+    
+    /// Screenshot the whole output (keybinding)
+    pub fn screenshot_output(&mut self) {
+        let Some(geometry) = self.space.outputs().next().and_then(|output| self.space.output_geometry(output)) else {
+            return;
+        };
+        self.capture_screenshot(ScreenshotTarget::Output, geometry);
+    }
+    
+    /// Show the region selection overlay on the output under the pointer
+    /// (keybinding, IPC); confirming a region screenshots it
+    pub fn select_screenshot_region(&mut self) {
+        if self.region_selection.is_active() {
+            return;
+        }
+        let location = self.seat.get_pointer().map(|pointer| pointer.current_location()).unwrap_or_default();
+        let output = self.space.output_under(location).next().or_else(|| self.space.outputs().next());
+        let Some(screen) = output.and_then(|output| self.space.output_geometry(output)) else { return };
+        // Topmost first, for clicks to select the window on top
+        let windows = self
+            .space
+            .elements()
+            .rev()
+            .filter_map(|window| self.space.element_geometry(window))
+            .filter(|geometry| geometry.overlaps(screen))
+            .map(render_rect)
+            .collect();
+        info!("Selecting a screenshot region");
+        self.region_selection.start(render_rect(screen), windows);
+        self.accessibility.tree().announce(
+            "Drag to select a region or click a window, then press Enter to take a screenshot or Escape to cancel",
+            Politeness::Assertive,
+        );
+    }
+    
+    /// Start dragging or adjusting the region while selecting one
+    ///
+    /// Returns whether the click was consumed and must not reach clients.
+    fn region_select_click(&mut self, location: Point<f64, Logical>) -> bool {
+        if !self.region_selection.is_active() {
+            return false;
+        }
+        self.region_selection.pointer_pressed(Vec2::new(location.x as f32, location.y as f32));
+        true
+    }
+    
+    /// Capture a logical region from the next frame
+    fn capture_screenshot(&mut self, target: ScreenshotTarget, area: Rectangle<i32, Logical>) {
+        // TODO: Read back the output the region is on once each output
+        // renders its own frame; the renderer composes the first one
+        let Some(output) = self.space.outputs().next() else { return };
+        let Some(output_geometry) = self.space.output_geometry(output) else { return };
+        let region = Self::output_readback_region(output, Rectangle::new(area.loc - output_geometry.loc, area.size));
+        // TODO: Draw the cursor into the frame when include_pointer is set
+        // once the cursor is rendered; screenshots leave it out until then
+        self.screenshots.capture(target, region);
+    }
+    
+    /// Physical region of the composed frame showing a logical area relative
+    /// to an output
+    fn output_readback_region(output: &Output, area: Rectangle<i32, Logical>) -> ReadbackRegion {
+        let scale = output.current_scale().fractional_scale() as f32;
+        ReadbackRegion::from(area.to_geometry().to_physical(scale))
+    }
+    
+    /// Save captured screenshots and place the latest on the clipboard
+    ///
+    /// Call after frames are read back.
+    pub fn process_screenshots(&mut self, seat: &Seat<Self>) {
+        let captured = self.screenshots.take_captured();
+        captured.iter().for_each(Self::save_screenshot);
+        let Some(latest) = captured.last() else { return };
+        if self.screenshots.config().copy_to_clipboard {
+            self.set_clipboard(seat, &CLIPBOARD_IMAGE_MIME_TYPES, latest.png.clone());
+        }
+        // TODO: Also show a notification toast once the compositor draws them
+        let message = match latest.target {
+            ScreenshotTarget::ActiveWindow => "Window screenshot taken",
+            ScreenshotTarget::Output => "Screenshot taken",
+            ScreenshotTarget::Region => "Region screenshot taken",
+        };
+        self.accessibility.tree().announce(message, Politeness::Polite);
+    }
+    
+    /// Write a screenshot from a thread, saving large images must not stall frames
+    fn save_screenshot(screenshot: &Screenshot) {
+        let screenshot = screenshot.clone();
+        std::thread::spawn(move || match screenshot.save() {
+            Ok(()) => info!("Saved screenshot to {}", screenshot.path.display()),
+            Err(e) => warn!("Failed to save screenshot to {}: {}", screenshot.path.display(), e),
+        });
+    }
+    
+    /// Offer text as the clipboard selection, e.g. a picked color
+    pub fn set_clipboard_text(&mut self, seat: &Seat<Self>, text: &str) {
+        self.set_clipboard(seat, &CLIPBOARD_TEXT_MIME_TYPES, Arc::from(text.as_bytes()));
+    }
+    
+    /// Offer data as the clipboard selection in the given mime types
+    fn set_clipboard(&mut self, seat: &Seat<Self>, mime_types: &'static [&'static str], data: Arc<[u8]>) {
+        let offered = mime_types.iter().map(|mime| mime.to_string()).collect();
+        set_data_device_selection(&self.display_handle, seat, offered, ClipboardData { mime_types, data });
+    }
+    
+    /// Process of a client, from its socket credentials
+    fn client_process(dh: &DisplayHandle, client: &ClientId) -> Option<ClientProcess> {
+        let credentials = dh.backend_handle().get_client_credentials(client.clone()).ok()?;
+        Some(ClientProcess::open(credentials.pid))
+    }
+    
+    /// Kill a client's process and disconnect it
+    fn force_quit(dh: &DisplayHandle, client: &ClientId, process: Option<&ClientProcess>) {
+        if let Some(Err(e)) = process.map(ClientProcess::kill) {
+            warn!("{}", e);
+        }
+        // Disconnect even if the process could not be killed, e.g. across PID namespaces
+        dh.backend_handle().kill_client(client.clone(), DisconnectReason::ConnectionClosed);
+    }
+    
+    /// Ask a window to close, watching for the application to hang
+    pub fn close_window(&mut self, toplevel: &ToplevelSurface) {
+        toplevel.send_close();
+        let Some(client) = toplevel.wl_surface().client() else { return };
+        if self.responsiveness.ping_now(client.id(), std::time::Instant::now()) {
+            Self::send_ping(toplevel);
+        }
+    }
+    
+    fn toplevel_of_client<'a>(toplevels: &'a [ToplevelSurface], client: &ClientId) -> Option<&'a ToplevelSurface> {
+        toplevels
+            .iter()
+            .find(|toplevel| toplevel.wl_surface().client().is_some_and(|owner| owner.id() == *client))
+    }
+    
+    fn send_ping(toplevel: &ToplevelSurface) {
+        if let Err(e) = toplevel.client().send_ping(SERIAL_COUNTER.next_serial()) {
+            debug!("Ping not sent: {:?}", e);
+        }
+    }
+    
+    /// Title of a toplevel, falling back to its app ID, for user-facing messages
+    fn application_name(toplevel: &ToplevelSurface) -> String {
+        with_states(toplevel.wl_surface(), |states| {
+            let data = states.data_map.get::<XdgToplevelSurfaceData>()?.lock().unwrap();
+            data.title.clone().or_else(|| data.app_id.clone())
+        })
+        .unwrap_or_else(|| "Application".to_string())
+    }
+    
+    /// Credentials a client connected with
+    pub fn client_credentials(client: &wayland_server::Client) -> Option<&ClientCredentials> {
+        client.get_data::<ClientState>()?.credentials.as_ref()
+    }
+    
+    /// Process owning a surface, as reported by IPC window queries
+    pub fn window_process_info(surface: &WlSurface) -> Option<ClientProcessInfo> {
+        Self::client_process_info(&surface.client()?)
+    }
+    
+    fn client_process_info(client: &wayland_server::Client) -> Option<ClientProcessInfo> {
+        Self::client_credentials(client).map(ClientCredentials::process_info)
+    }
+    
+    /// Memory held for a newly attached buffer
+    fn buffer_usage(assignment: &BufferAssignment) -> BufferUsage {
+        let BufferAssignment::NewBuffer(buffer) = assignment else {
+            return BufferUsage::default();
+        };
+        if let Ok(dmabuf) = get_dmabuf(buffer) {
+            // Imported without a copy; assume 4 bytes per pixel for the client's allocation
+            let size = dmabuf.size();
+            return BufferUsage {
+                buffer_bytes: size.w.max(0) as u64 * size.h.max(0) as u64 * 4,
+                texture_bytes: 0,
+            };
+        }
+        with_buffer_contents(buffer, |_, _, data| BufferUsage {
+            buffer_bytes: data.stride.max(0) as u64 * data.height.max(0) as u64,
+            texture_bytes: data.width.max(0) as u64 * data.height.max(0) as u64 * 4,
+        })
+        .unwrap_or_default()
+    }
+    
+    /// Format of a newly attached buffer; `None` when the buffer was removed
+    fn buffer_format(assignment: &BufferAssignment) -> Option<BufferFormat> {
+        let BufferAssignment::NewBuffer(buffer) = assignment else {
+            return None;
+        };
+        if let Ok(dmabuf) = get_dmabuf(buffer) {
+            return Some(BufferFormat::dmabuf(dmabuf.format()));
+        }
+        with_buffer_contents(buffer, |_, _, data| shm_format_to_fourcc(data.format))
+            .ok()
+            .flatten()
+            .map(BufferFormat::shm)
+    }
+    
+    /// Check client resource limits and publish client statistics for IPC
+    pub fn publish_client_stats(&mut self, dh: &DisplayHandle) {
+        let backend = dh.backend_handle();
+        let is_alive = |client: &ClientId| backend.get_client_data(client.clone()).is_ok();
+        let now = std::time::Instant::now();
+        if self.client_usage.publish(now, is_alive) {
+            let client_usage = &self.client_usage;
+            self.protocol_latency.publish(is_alive, |client| client_usage.connection_id(client));
+        }
+        if self.buffer_formats.publish(now) {
+            self.update_dmabuf_feedback();
+        }
+    }
+    
+    /// Formats advertised over linux-dmabuf until the renderer reports the
+    /// GPU's
+    fn fallback_dmabuf_formats() -> Vec<Format> {
+        vec![
+            Format {
+                code: DrmFourcc::Xrgb8888,
+                modifier: DrmModifier::Linear,
+            },
+            Format {
+                code: DrmFourcc::Argb8888,
+                modifier: DrmModifier::Linear,
+            },
+        ]
+    }
+    
+    /// Advertise the formats and modifiers the GPU can sample, e.g. tiled and
+    /// compressed ones, over linux-dmabuf
+    ///
+    /// Replaces the dmabuf global, so call it before clients connect.
+    /// Formats unknown to DRM are skipped; without any the fallback linear
+    /// formats stay advertised.
+    pub fn set_dmabuf_formats(&mut self, formats: &[vulkan_renderer::DmabufFormat]) {
+        let formats: Vec<Format> = formats
+            .iter()
+            .filter_map(|format| {
+                let code = DrmFourcc::try_from(format.fourcc).ok()?;
+                Some(Format { code, modifier: DrmModifier::from(format.modifier) })
+            })
+            .collect();
+        if formats.is_empty() {
+            warn!("GPU reported no dmabuf formats, advertising linear XRGB8888 and ARGB8888");
+            return;
+        }
+        info!("Advertising {} dmabuf formats and modifiers", formats.len());
+        self.dmabuf_formats = formats;
+        
+        let dh = self.display_handle.clone();
+        if let Some(node) = self.drm_node {
+            self.enable_dmabuf_feedback(&dh, node);
+            return;
+        }
+        let global = self.dmabuf_state.create_global::<Self>(&dh, self.dmabuf_formats.clone());
+        let old_global = std::mem::replace(&mut self.dmabuf_global, global);
+        self.dmabuf_state.disable_global::<Self>(&dh, &old_global);
+        self.dmabuf_state.destroy_global::<Self>(&dh, old_global);
+    }
+    
+    /// Dmabuf feedback for a device, with a scanout tranche when `scanout` is not empty
+    fn dmabuf_feedback(&self, device: libc::dev_t, scanout: Vec<Format>) -> std::io::Result<DmabufFeedback> {
+        let mut builder = DmabufFeedbackBuilder::new(device, self.dmabuf_formats.iter().copied());
+        if !scanout.is_empty() {
+            builder = builder.add_preference_tranche(device, Some(TrancheFlags::Scanout), scanout);
+        }
+        builder.build()
+    }
+    
+    /// Replace the dmabuf global with one sending feedback for the DRM device
+    ///
+    /// Clients that bound the old global keep using it without feedback.
+    fn enable_dmabuf_feedback(&mut self, dh: &DisplayHandle, node: DrmNode) {
+        let (scanout, _) = self.buffer_formats.advertise(&self.dmabuf_formats);
+        let feedback = match self.dmabuf_feedback(node.dev_id(), scanout) {
+            Ok(feedback) => feedback,
+            Err(e) => {
+                warn!("Failed to build dmabuf feedback: {}", e);
+                return;
+            }
+        };
+        let global = self.dmabuf_state.create_global_with_default_feedback::<Self>(dh, &feedback);
+        let old_global = std::mem::replace(&mut self.dmabuf_global, global);
+        self.dmabuf_state.disable_global::<Self>(dh, &old_global);
+        self.dmabuf_state.destroy_global::<Self>(dh, old_global);
+        info!("✅ linux-dmabuf feedback enabled for {:?}", node.dev_path());
+    }
+    
+    /// Resend dmabuf feedback if the scanout tranche changed
+    ///
+    /// Called after statistics are published and when scanout preference is
+    /// toggled; clients reallocate their buffers on receiving new feedback.
+    pub fn update_dmabuf_feedback(&mut self) {
+        let Some(node) = self.drm_node else { return };
+        let (scanout, changed) = self.buffer_formats.advertise(&self.dmabuf_formats);
+        if !changed {
+            return;
+        }
+        match self.dmabuf_feedback(node.dev_id(), scanout) {
+            Ok(feedback) => self.dmabuf_state.set_default_feedback(&self.dmabuf_global, &feedback),
+            Err(e) => warn!("Failed to build dmabuf feedback: {}", e),
+        }
+    }
+    
+    /// Protocol IDs of windows on workspaces not shown right now, for the renderer
+    pub fn hidden_surface_ids(&self) -> Vec<u32> {
+        self.stacking
+            .bottom_to_top()
+            .filter(|id| self.workspaces.placement(id).is_some() && !self.workspaces.is_visible(id))
+            .map(ObjectId::protocol_id)
+            .collect()
+    }
+    
+    /// Send a configure to a toplevel and start timing the client's ack
+    fn send_configure(&mut self, toplevel: &ToplevelSurface) -> Serial {
+        let serial = toplevel.send_configure();
+        if let Some(client) = toplevel.wl_surface().client() {
+            self.protocol_latency
+                .configure_sent(client.id(), toplevel.wl_surface().id(), serial, std::time::Instant::now());
+        }
+        serial
+    }
+    
+    /// Send the queued frame callbacks of a surface
+    ///
+    /// Callbacks of clients throttled for exceeding their resource limits are
+    /// held back until the throttled rate allows them, as are callbacks of
+    /// windows whose rules cap their frame rate. Returns false when held back.
+    pub fn send_frame_callbacks(&mut self, surface: &WlSurface, time: std::time::Duration) -> bool {
+        let Some(client) = surface.client() else { return true };
+        let now = std::time::Instant::now();
+        let mut window = surface.clone();
+        while let Some(parent) = get_parent(&window) {
+            window = parent;
+        }
+        if !self.frame_rate_caps.callbacks_due(&window.id(), &surface.id(), now) {
+            return false;
+        }
+        if !self.client_usage.frame_callbacks_due(&client.id(), &surface.id(), now) {
+            return false;
+        }
+        let sent = with_states(surface, |states| {
+            let callbacks: Vec<_> = states
+                .cached_state
+                .get::<SurfaceAttributes>()
+                .current()
+                .frame_callbacks
+                .drain(..)
+                .collect();
+            for callback in &callbacks {
+                callback.done(time.as_millis() as u32);
+            }
+            !callbacks.is_empty()
+        });
+        if sent {
+            self.frame_rate_caps.callbacks_sent(&window.id(), &surface.id(), now);
+            self.protocol_latency.frame_callbacks_sent(client.id(), surface.id(), now);
+        }
+        true
+    }
+    
+    /// Next time the event loop has work without being woken, e.g. frame
+    /// callbacks reaching the max latency
+    pub fn next_deadline(&self) -> Option<std::time::Instant> {
+        [
+            self.frame_callbacks.next_deadline(),
+            self.kiosk.next_deadline(),
+            self.click_assist.next_deadline(),
+            self.hot_corners.next_deadline(),
+            self.cursor_visibility.next_deadline(),
+            self.output_power.next_deadline(),
+            self.responsiveness.next_deadline(),
+        ]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+    
+    /// Fire the frame callbacks of surfaces shown in frames presented since
+    /// the last call, and of surfaces that waited the max latency
+    ///
+    /// Presented surfaces also get their presentation-time feedback; the
+    /// feedback of surfaces whose callbacks fired without a presented frame
+    /// is discarded.
+    pub fn process_frame_callbacks(&mut self) {
+        let now = std::time::Instant::now();
+        let clock_now = std::time::Duration::from(self.clock.now());
+        for frame in self.frame_callbacks.presentations() {
+            let Some(output) = self.space.outputs().find(|output| output.name() == frame.output).cloned() else {
+                continue;
+            };
+            let space = &self.space;
+            let workspaces = &self.workspaces;
+            let surfaces = self
+                .frame_callbacks
+                .take_presented(&frame, |surface| Self::surface_shown_on(space, workspaces, surface, &output));
+            if surfaces.is_empty() {
+                continue;
+            }
+            
+            // Presentation times are on the monotonic clock clients bound
+            let time = clock_now.saturating_sub(now.saturating_duration_since(frame.presented));
+            let sequence = self.frame_callbacks.sequence(&frame);
+            for surface in surfaces {
+                self.send_presentation_feedback(&surface, &output, &frame, time, sequence);
+                if !self.send_frame_callbacks(&surface, time) {
+                    self.frame_callbacks.queue(&surface, now);
+                }
+            }
+        }
+        
+        for surface in self.frame_callbacks.take_overdue(now) {
+            with_states(&surface, |states| {
+                let mut feedback = states.cached_state.get::<PresentationFeedbackCachedState>();
+                for callback in feedback.current().callbacks.drain(..) {
+                    callback.discarded();
+                }
+            });
+            if !self.send_frame_callbacks(&surface, clock_now) {
+                self.frame_callbacks.queue(&surface, now);
+            }
+        }
+    }
+    
+    /// Send the presentation-time feedback a surface asked for with its last commit
+    fn send_presentation_feedback(
+        &self,
+        surface: &WlSurface,
+        output: &Output,
+        frame: &FramePresented,
+        time: std::time::Duration,
+        sequence: u64,
+    ) {
+        let kind = if frame.vsync {
+            wp_presentation_feedback::Kind::Vsync | wp_presentation_feedback::Kind::HwCompletion
+        } else {
+            wp_presentation_feedback::Kind::empty()
+        };
+        with_states(surface, |states| {
+            let mut feedback = states.cached_state.get::<PresentationFeedbackCachedState>();
+            for callback in feedback.current().callbacks.drain(..) {
+                callback.presented(output, time, Refresh::fixed(frame.refresh), sequence, kind);
+            }
+        });
+    }
+    
+    /// Whether frames of an output show a surface
+    ///
+    /// Surfaces whose output is not known, such as popups and cursors, count
+    /// as shown on every output.
+    fn surface_shown_on(space: &Space<Window>, workspaces: &WorkspaceManager, surface: &WlSurface, output: &Output) -> bool {
+        let mut root = surface.clone();
+        while let Some(parent) = get_parent(&root) {
+            root = parent;
+        }
+        let window = space
+            .elements()
+            .find(|window| window.toplevel().is_some_and(|toplevel| toplevel.wl_surface() == &root));
+        if let Some(window) = window {
+            let hidden = workspaces.placement(&root.id()).is_some() && !workspaces.is_visible(&root.id());
+            return !hidden && space.outputs_for_element(window).contains(output);
+        }
+        let layer_output = space
+            .outputs()
+            .find(|output| layer_map_for_output(output).layer_for_surface(&root, WindowSurfaceType::TOPLEVEL).is_some());
+        layer_output.is_none_or(|layer_output| layer_output == output)
+    }
+    
+    /// Global filter advertising `interface` only to clients the exposure policy allows
+    fn exposure_filter(
+        policy: &Arc<SecurityPolicy>,
+        interface: &'static str,
+    ) -> impl Fn(&wayland_server::Client) -> bool + Send + Sync + 'static {
+        let policy = policy.clone();
+        move |client| {
+            let credentials = Self::client_credentials(client);
+            let allowed = policy.allows_global(interface, credentials, Self::is_restricted(client));
+            if !allowed {
+                info!(
+                    "Hiding {} from {}",
+                    interface,
+                    credentials.map_or_else(|| "unknown process".to_string(), ToString::to_string)
+                );
+            }
+            allowed
+        }
+    }
+    
+    /// Global filter hiding the keyboard grab manager from clients the policy denies
+    fn keyboard_grab_filter(policy: &Arc<SecurityPolicy>) -> impl Fn(&wayland_server::Client) -> bool + Send + Sync + 'static {
+        let policy = policy.clone();
+        move |client| !Self::is_restricted(client) && policy.allows_keyboard_grab(Self::client_credentials(client))
+    }
+    
+    /// Whether a client connected through a restricted socket or a security context
+    fn is_restricted(client: &wayland_server::Client) -> bool {
+        client.get_data::<ClientState>().is_some_and(|state| state.restricted)
+    }
+    
+    /// Admit a new client connection unless the kiosk whitelist refuses it
+    ///
+    /// Clients of security contexts are always restricted.
+    fn accept_client(
+        &mut self,
+        display_handle: &mut DisplayHandle,
+        stream: UnixStream,
+        restricted: bool,
+        security_context: Option<SecurityContext>,
+    ) {
+        let credentials = ClientCredentials::from_stream(&stream);
+        if !self.security_policy.allows_connection(credentials.as_ref()) {
+            match &credentials {
+                Some(credentials) => warn!("Refusing client outside the kiosk whitelist: {}", credentials),
+                None => warn!("Refusing client with unknown credentials in kiosk mode"),
+            }
+            return;
+        }
+        let client_state = ClientState {
+            credentials,
+            restricted: restricted || security_context.is_some(),
+            security_context,
+            ..ClientState::default()
+        };
+        if let Err(err) = display_handle.insert_client(stream, Arc::new(client_state)) {
+            error!("Failed to insert client: {}", err);
+        }
+    }
+    
+    /// Apply output scales requested over IPC since the last call
+    pub fn apply_output_scale_requests(&mut self) {
+        let Some(scales) = self.output_scale_requests.take_changed() else { return };
+        for (name, scale) in scales {
+            if let Err(e) = self.set_output_scale(&name, scale) {
+                warn!("{}", e);
+            }
+        }
+    }
+    
+    /// Change an output's scale at runtime
+    ///
+    /// Bound wl_output and xdg_output objects receive the new scale and logical
+    /// size, surfaces on the output get new preferred buffer and fractional
+    /// scales, and windows and layer surfaces are re-laid out for the new
+    /// logical size.
+    pub fn set_output_scale(&mut self, name: &str, scale: f64) -> Result<()> {
+        let output = self
+            .space
+            .outputs()
+            .find(|output| output.name() == name)
+            .cloned()
+            .ok_or_else(|| CompositorError::wayland(format!("Cannot scale unknown output {}", name)))?;
+        let scale = snap_scale(scale);
+        if output.current_scale().fractional_scale() == scale {
+            return Ok(());
+        }
+        
+        info!("Changing scale of output {} to {}", name, scale);
+        let windows = self.windows_on_output(&output);
+        output.change_current_state(None, None, Some(Scale::Fractional(scale)), None);
+        self.relayout_output(&output, windows);
+        
+        // TODO: Re-rasterize compositor UI (app bar, overlays, cursors) at the new
+        // scale once it is drawn through the UI framework
+        Ok(())
+    }
+    
+    /// Apply output transforms requested over IPC since the last call
+    pub fn apply_output_transform_requests(&mut self) {
+        let Some(transforms) = self.output_transform_requests.take_changed() else { return };
+        for (name, transform) in transforms {
+            if let Err(e) = self.set_output_transform(&name, output_transform(transform)) {
+                warn!("{}", e);
+            }
+        }
+    }
+    
+    /// Follow the accelerometer on the built-in display
+    pub fn apply_auto_rotation(&mut self) {
+        let Some(transform) = self.auto_rotation.take_transform() else { return };
+        // TODO: Pick the internal panel (eDP/LVDS) once outputs report their connector type
+        let Some(name) = self.space.outputs().next().map(|output| output.name()) else { return };
+        if let Err(e) = self.set_output_transform(&name, transform) {
+            warn!("{}", e);
+        }
+    }
+    
+    /// Rotate an output by a quarter turn, e.g. from a rotation hotkey
+    pub fn rotate_output(&mut self, name: &str, direction: RotationDirection) -> Result<()> {
+        let transform = self
+            .space
+            .outputs()
+            .find(|output| output.name() == name)
+            .map(|output| output.current_transform())
+            .ok_or_else(|| CompositorError::wayland(format!("Cannot rotate unknown output {}", name)))?;
+        self.set_output_transform(name, rotate_transform(transform, direction))
+    }
+    
+    /// Lock or unlock auto-rotation at the current orientation
+    pub fn set_rotation_lock(&mut self, locked: bool) {
+        info!("Rotation lock {}", if locked { "enabled" } else { "disabled" });
+        self.auto_rotation.set_locked(locked);
+    }
+    
+    /// Rotate or flip an output at runtime
+    ///
+    /// Bound wl_output and xdg_output objects receive the new transform and
+    /// logical size, surfaces on the output get the new preferred buffer
+    /// transform, and windows and layer surfaces are re-laid out.
+    pub fn set_output_transform(&mut self, name: &str, transform: Transform) -> Result<()> {
+        let output = self
+            .space
+            .outputs()
+            .find(|output| output.name() == name)
+            .cloned()
+            .ok_or_else(|| CompositorError::wayland(format!("Cannot rotate unknown output {}", name)))?;
+        if output.current_transform() == transform {
+            return Ok(());
+        }
+        
+        info!("Changing transform of output {} to {:?}", name, transform);
+        let windows = self.windows_on_output(&output);
+        output.change_current_state(None, Some(transform), None, None);
+        self.relayout_output(&output, windows);
+        Ok(())
+    }
+    
+    /// Map a normalized touchscreen or tablet position to the output it is mounted on
+    ///
+    /// Absolute input devices keep reporting in the panel's native
+    /// orientation, so positions are rotated with the output.
+    pub fn absolute_input_position(&self, output: &Output, x: f64, y: f64) -> Option<Point<f64, Logical>> {
+        let geometry = self.space.output_geometry(output)?;
+        let (x, y) = map_absolute_position(x, y, output.current_transform());
+        Some(Point::from((
+            geometry.loc.x as f64 + x * geometry.size.w as f64,
+            geometry.loc.y as f64 + y * geometry.size.h as f64,
+        )))
+    }
+    
+    /// Windows currently shown on an output
+    fn windows_on_output(&self, output: &Output) -> Vec<Window> {
+        self.space
+            .elements()
+            .filter(|window| self.space.outputs_for_element(window).contains(output))
+            .cloned()
+            .collect()
+    }
+    
+    /// Re-lay out an output after its logical size changed
+    ///
+    /// `windows` are the windows that were on the output before the change.
+    fn relayout_output(&mut self, output: &Output, windows: Vec<Window>) {
+        // Layer surfaces are re-anchored against the new logical size and configured
+        layer_map_for_output(output).arrange();
+        
+        let Some(output_geometry) = self.space.output_geometry(output) else {
+            return;
+        };
+        for window in windows {
+            self.send_preferred_scale(&window, output);
+            let Some(toplevel) = window.toplevel().cloned() else { continue };
+            
+            let fills_output = toplevel.with_pending_state(|state| {
+                state.states.contains(xdg_toplevel::State::Maximized)
+                    || state.states.contains(xdg_toplevel::State::Fullscreen)
+            });
+            if fills_output {
+                toplevel.with_pending_state(|state| state.size = Some(output_geometry.size));
+                self.send_configure(&toplevel);
+                self.space.map_element(window, output_geometry.loc, false);
+                continue;
+            }
+            
+            // Keep floating windows reachable when the logical output shrinks
+            let Some(geometry) = self.space.element_geometry(&window) else { continue };
+            let max_x = output_geometry.loc.x + (output_geometry.size.w - geometry.size.w).max(0);
+            let max_y = output_geometry.loc.y + (output_geometry.size.h - geometry.size.h).max(0);
+            let location = Point::from((
+                geometry.loc.x.clamp(output_geometry.loc.x, max_x),
+                geometry.loc.y.clamp(output_geometry.loc.y, max_y),
+            ));
+            if location != geometry.loc {
+                self.space.map_element(window, location, false);
+            }
+        }
+    }
+    
+    /// Move a window during an interactive move
+    ///
+    /// `pointer` is the pointer location and `output` the output it was on at
+    /// the last call. Entering an output with a different scale or transform
+    /// sends the window its new preferred values right away.
+    pub fn drag_window(
+        &mut self,
+        window: &Window,
+        location: Point<i32, Logical>,
+        pointer: Point<f64, Logical>,
+        output: &mut Option<Output>,
+    ) {
+        self.space.map_element(window.clone(), location, true);
+        // TODO: Schedule redraws of the outputs the window left and entered
+        // once surface damage reaches the frame scheduler
+        
+        let entered = self.space.output_under(pointer).next().cloned();
+        if entered.is_none() || entered == *output {
+            return;
+        }
+        if let Some(entered) = &entered {
+            let rendering_changed = output.as_ref().is_none_or(|previous| {
+                previous.current_scale().fractional_scale() != entered.current_scale().fractional_scale()
+                    || previous.current_transform() != entered.current_transform()
+            });
+            if rendering_changed {
+                debug!(
+                    "Window dragged onto output {} (scale {})",
+                    entered.name(),
+                    entered.current_scale().fractional_scale()
+                );
+                self.send_preferred_scale(window, entered);
+            }
+        }
+        *output = entered;
+    }
+    
+    /// Resize the window being resized interactively by the pointer's
+    /// movement since the resize started
+    pub fn resize_window_motion(&mut self, delta: Point<f64, Logical>) {
+        let Some(resize) = &mut self.interactive_resize else { return };
+        resize.pointer_moved(delta);
+        self.send_resize_configure();
+    }
+    
+    /// Send the final configure of an interactive resize once the client is
+    /// ready for it
+    pub fn finish_window_resize(&mut self) {
+        let Some(resize) = &mut self.interactive_resize else { return };
+        resize.release();
+        self.send_resize_configure();
+    }
+    
+    /// Send a throttled configure of an interactive resize
+    ///
+    /// Call when `interactive_resize.next_deadline()` passes.
+    pub fn send_resize_configure(&mut self) {
+        let Some(resize) = &mut self.interactive_resize else { return };
+        let now = std::time::Instant::now();
+        let Some(size) = resize.configure_due(now) else { return };
+        let Some(toplevel) = resize.window.toplevel() else { return };
+        let released = resize.is_released();
+        toplevel.with_pending_state(|state| {
+            state.size = Some(size);
+            if released {
+                state.states.unset(xdg_toplevel::State::Resizing);
+            }
+        });
+        if let Some(serial) = toplevel.send_pending_configure() {
+            resize.configure_sent(serial, size, now);
+        }
+    }
+    
+    /// Move the resized window once the client committed a configured size,
+    /// keeping the edges opposite the grabbed ones in place
+    fn resize_window_committed(&mut self, location: Point<i32, Logical>) {
+        let Some(resize) = &self.interactive_resize else { return };
+        self.space.map_element(resize.window.clone(), location, false);
+        // TODO: Schedule a redraw of the window's outputs once surface damage
+        // reaches the frame scheduler
+        if resize.is_finished() {
+            debug!("Interactive resize finished");
+            self.interactive_resize = None;
+        } else {
+            self.send_resize_configure();
+        }
+    }
+    
+    /// Fit a window onto the output it was dropped on after an interactive move
+    ///
+    /// Maximized and fullscreen windows take the new output's size, other
+    /// windows are shrunk if larger than the output, and the top edge is kept
+    /// on the output so the title bar stays reachable.
+    pub fn finish_window_drag(&mut self, window: &Window, output: Option<&Output>) {
+        let Some(output) = output else { return };
+        let Some(output_geometry) = self.space.output_geometry(output) else { return };
+        let Some(location) = self.space.element_location(window) else { return };
+        let Some(toplevel) = window.toplevel().cloned() else { return };
+        
+        let fills_output = toplevel.with_pending_state(|state| {
+            state.states.contains(xdg_toplevel::State::Maximized)
+                || state.states.contains(xdg_toplevel::State::Fullscreen)
+        });
+        if fills_output {
+            toplevel.with_pending_state(|state| state.size = Some(output_geometry.size));
+            self.send_configure(&toplevel);
+            self.space.map_element(window.clone(), output_geometry.loc, false);
+            return;
+        }
+        
+        let size = window.geometry().size;
+        let fitted: Size<i32, Logical> = (size.w.min(output_geometry.size.w), size.h.min(output_geometry.size.h)).into();
+        if fitted != size {
+            toplevel.with_pending_state(|state| state.size = Some(fitted));
+            self.send_configure(&toplevel);
+        }
+        let max_y = output_geometry.loc.y + output_geometry.size.h - fitted.h;
+        let top = location.y.clamp(output_geometry.loc.y, max_y);
+        if top != location.y {
+            self.space.map_element(window.clone(), (location.x, top), false);
+        }
+    }
+    
+    /// Tell every surface of a window the preferred scales and buffer transform
+    /// of an output, or the scale its window rules override it with
+    fn send_preferred_scale(&self, window: &Window, output: &Output) {
+        let output_scale = output.current_scale().fractional_scale();
+        let scale = window
+            .toplevel()
+            .map_or(output_scale, |toplevel| self.app_scales.preferred_scale(&toplevel.wl_surface().id(), output_scale));
+        let transform = output.current_transform();
+        window.with_surfaces(|surface, states| {
+            with_fractional_scale(states, |fractional| fractional.set_preferred_scale(scale));
+            send_surface_state(surface, states, scale.ceil() as i32, transform);
+        });
+    }
+    
+    /// Send windows their preferred scales again after their scale overrides changed
+    pub fn refresh_preferred_scales(&self, windows: &[ObjectId]) {
+        for window in self.space.elements() {
+            let Some(toplevel) = window.toplevel() else { continue };
+            if !windows.contains(&toplevel.wl_surface().id()) {
+                continue;
+            }
+            let output = self
+                .space
+                .outputs_for_element(window)
+                .into_iter()
+                .next()
+                .or_else(|| self.space.outputs().next().cloned());
+            if let Some(output) = output {
+                debug!("Scale override of window {:?} changed", toplevel.wl_surface().id());
+                self.send_preferred_scale(window, &output);
+            }
+        }
+    }
+    
+    /// Global position of the origin of a window's or layer surface's
+    /// surface; `None` for other surfaces, e.g. subsurfaces and popups
+    fn surface_origin(&self, surface: &WlSurface) -> Option<Point<i32, Logical>> {
+        if let Some(window) = self.window_for_surface(surface) {
+            return self.space.element_location(window).map(|location| location - window.geometry().loc);
+        }
+        self.space.outputs().find_map(|output| {
+            let layer_map = layer_map_for_output(output);
+            let layer = layer_map.layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)?;
+            let geometry = layer_map.layer_geometry(layer)?;
+            Some(geometry.loc + self.space.output_geometry(output)?.loc)
+        })
+    }
     
-    /// DRM node for direct GPU resource management
-    ///
-    /// Provides access to the GPU device node for direct hardware resource
-    /// management and explicit synchronization support.
-    pub drm_node: Option<DrmNode>,
+    /// Window whose toplevel is the given surface
+    fn window_for_surface(&self, surface: &WlSurface) -> Option<&Window> {
+        self.space.elements().find(|window| {
+            window.toplevel().is_some_and(|toplevel| toplevel.wl_surface() == surface)
+        })
+    }
     
-    /// DRM device file descriptor for explicit sync support
-    ///
-    /// File descriptor for the DRM device, used for explicit synchronization
-    /// and direct hardware access operations.
-    pub drm_device_fd: Option<DrmDeviceFd>,
+    /// Scale of the output a surface is shown on, or of the primary output,
+    /// unless window rules override it
+    fn preferred_scale(&self, surface: &WlSurface) -> f64 {
+        let output_scale = self
+            .window_for_surface(surface)
+            .and_then(|window| self.space.outputs_for_element(window).into_iter().next())
+            .or_else(|| self.space.outputs().next().cloned())
+            .map_or(1.0, |output| output.current_scale().fractional_scale());
+        self.app_scales.preferred_scale(&surface.id(), output_scale)
+    }
     
-    /// Vulkan renderer for high-performance surface compositing
-    ///
-    /// The core Vulkan-based rendering engine that performs surface compositing,
-    /// applies effects (glassmorphism, neomorphism), and outputs frames.
-    pub renderer: Option<Arc<Mutex<VulkanRenderer>>>,
+    /// Windows with their geometry and the output they are moved off
+    fn show_desktop_targets(&self) -> (Vec<(ObjectId, Rectangle<i32, Logical>)>, Rectangle<i32, Logical>) {
+        let output = self
+            .space
+            .outputs()
+            .next()
+            .and_then(|output| self.space.output_geometry(output))
+            .unwrap_or_default();
+        let windows = self
+            .space
+            .elements()
+            .filter_map(|window| {
+                let geometry = self.space.element_geometry(window)?;
+                Some((window.toplevel()?.wl_surface().id(), geometry))
+            })
+            .collect();
+        (windows, output)
+    }
 }
 
 /// High-performance Wayland compositor server with Vulkan acceleration
@@ -649,7 +4268,7 @@ pub struct WaylandServerState {
 /// // Create and configure server
 /// let mut server = WaylandServer::new()?;
 /// server.initialize_wl_drm()?;
-/// server.start_listening()?;
+/// server.start_listening(&config.wayland.sockets)?;
 /// 
 /// // Set Vulkan renderer
 /// server.set_renderer(vulkan_renderer);
@@ -710,6 +4329,40 @@ pub struct WaylandServer {
     /// graceful shutdown, pause/resume functionality, and integration with
     /// external process management systems.
     pub loop_signal: LoopSignal,
+    
+    /// Optional protocols enabled at creation
+    ///
+    /// Protocols set up with hardware later, such as DRM leasing, are only
+    /// initialized when enabled here.
+    pub protocols: config::ProtocolsConfig,
+}
+
+/// A logical rectangle in the renderer's coordinates
+fn render_rect(rect: Rectangle<i32, Logical>) -> Rect {
+    Rect::new(rect.loc.x as f32, rect.loc.y as f32, rect.size.w as f32, rect.size.h as f32)
+}
+
+/// `rect` of a surface whose origin is at `origin`, in global coordinates
+fn render_rect_f64(rect: Rectangle<f64, Logical>, origin: Point<i32, Logical>) -> Rect {
+    Rect::new(
+        (rect.loc.x + origin.x as f64) as f32,
+        (rect.loc.y + origin.y as f64) as f32,
+        rect.size.w as f32,
+        rect.size.h as f32,
+    )
+}
+
+/// Damage of a commit in surface coordinates, converting damage given in
+/// buffer coordinates by the buffer's scale and transform
+fn surface_damage(damage: &[Damage], buffer: &WlBuffer, scale: i32, transform: wl_output::Transform) -> Vec<Rectangle<f64, Logical>> {
+    let buffer_size = buffer_dimensions(buffer).unwrap_or_default().to_f64();
+    damage
+        .iter()
+        .map(|damage| match damage {
+            Damage::Surface(rect) => rect.to_f64(),
+            Damage::Buffer(rect) => rect.to_f64().to_logical(scale.max(1) as f64, transform.into(), &buffer_size),
+        })
+        .collect()
 }
 
 impl WaylandServer {
@@ -779,9 +4432,17 @@ impl WaylandServer {
     /// // Server with GPU acceleration
     /// let mut server = WaylandServer::new()?;
     /// server.initialize_wl_drm()?;  // Enable hardware acceleration
-    /// server.start_listening()?;    // Begin accepting clients
+    /// server.start_listening(&config.wayland.sockets)?;    // Begin accepting clients
     /// ```
     pub fn new() -> Result<Self> {
+        Self::with_protocols(config::ProtocolsConfig::default())
+    }
+    
+    /// Create a server advertising only the enabled optional protocols
+    ///
+    /// Rarely used protocols (tablet, input method, DRM lease) can be turned
+    /// off for minimal deployments; their state is then never created.
+    pub fn with_protocols(protocols: config::ProtocolsConfig) -> Result<Self> {
         info!("Initializing high-performance Wayland compositor with complete protocol support");
         debug!("Target configuration: 4K displays, Vulkan acceleration, zero-copy GPU buffers");
         
@@ -789,7 +4450,7 @@ impl WaylandServer {
         let event_loop = EventLoop::try_new()
             .map_err(|e| CompositorError::wayland(format!("Failed to create event loop: {}", e)))?;
         
-        let _loop_handle = event_loop.handle();
+        let loop_handle = event_loop.handle();
         let loop_signal = event_loop.get_signal();
         
         // Create display with the loop handle
@@ -797,31 +4458,28 @@ impl WaylandServer {
             .map_err(|e| CompositorError::wayland(format!("Failed to create display: {}", e)))?;
         
         let dh = display.handle();
+        let security_policy = Arc::new(SecurityPolicy::new());
         
         // Initialize compositor state
         let compositor_state = CompositorState::new::<WaylandServerState>(&dh);
         let xdg_shell_state = XdgShellState::new::<WaylandServerState>(&dh);
-        let wlr_layer_shell_state = WlrLayerShellState::new::<WaylandServerState>(&dh);
+        let wlr_layer_shell_state = WlrLayerShellState::new_with_filter::<WaylandServerState, _>(&dh, WaylandServerState::exposure_filter(&security_policy, "zwlr_layer_shell_v1"));
         let shm_state = ShmState::new::<WaylandServerState>(&dh, vec![]);
         
         // Initialize dmabuf state for zero-copy GPU buffer sharing
         let mut dmabuf_state = DmabufState::new();
         
-        // Create common formats for dmabuf support
-        let formats = vec![
-            Format {
-                code: DrmFourcc::Xrgb8888,
-                modifier: DrmModifier::Linear,
-            },
-            Format {
-                code: DrmFourcc::Argb8888, 
-                modifier: DrmModifier::Linear,
-            },
-        ];
-        
-        let dmabuf_global = dmabuf_state.create_global::<WaylandServerState>(&dh, formats);
+        // Advertise formats without feedback until the DRM device is known
+        let dmabuf_formats = WaylandServerState::fallback_dmabuf_formats();
+        let dmabuf_global = dmabuf_state.create_global::<WaylandServerState>(&dh, dmabuf_formats.clone());
         
-        let seat_state = SeatState::new();
+        let mut seat_state = SeatState::new();
+        let mut seat = seat_state.new_wl_seat(&dh, "seat0");
+        let keyboard = config::KeyboardConfig::default();
+        seat.add_keyboard(XkbConfig::default(), keyboard.repeat_delay as i32, keyboard.repeat_rate as i32)
+            .map_err(|e| CompositorError::wayland(format!("Failed to add keyboard to seat: {}", e)))?;
+        seat.add_pointer();
+        seat.add_touch();
         
         // Initialize output manager with xdg-output support for multi-monitor configuration
         let output_manager_state = OutputManagerState::new_with_xdg_output::<WaylandServerState>(&dh);
@@ -857,7 +4515,25 @@ impl WaylandServer {
         let fractional_scale_manager_state = FractionalScaleManagerState::new::<WaylandServerState>(&dh);
         
         // Initialize tablet manager for professional graphics tablet integration
-        let tablet_manager_state = TabletManagerState::new::<WaylandServerState>(&dh);
+        let tablet_manager_state = protocols
+            .tablet
+            .then(|| TabletManagerState::new::<WaylandServerState>(&dh));
+        
+        // Text input, input method and virtual keyboard go together: none is
+        // useful without the others
+        let (virtual_keyboard_manager_state, text_input_manager_state, input_method_manager_state) = if protocols.input_method {
+            (
+                Some(VirtualKeyboardManagerState::new::<WaylandServerState, _>(&dh, WaylandServerState::exposure_filter(&security_policy, "zwp_virtual_keyboard_manager_v1"))),
+                Some(TextInputManagerState::new::<WaylandServerState>(&dh)),
+                Some(InputMethodManagerState::new::<WaylandServerState, _>(&dh, WaylandServerState::exposure_filter(&security_policy, "zwp_input_method_manager_v2"))),
+            )
+        } else {
+            (None, None, None)
+        };
+        info!(
+            "Optional protocols: tablet {}, input method {}, DRM lease {}",
+            protocols.tablet, protocols.input_method, protocols.drm_lease
+        );
         
         // Create default output (4K setup)
         let output = Output::new(
@@ -884,6 +4560,14 @@ impl WaylandServer {
         let mut space = Space::default();
         space.map_output(&output, (0, 0));
         
+        // Create workspaces for the output
+        let mut workspaces = WorkspaceManager::new();
+        workspaces.add_output(output.name(), DEFAULT_WORKSPACE_COUNT);
+        let mut workspace_themes = WorkspaceThemes::default();
+        workspace_themes.add_output(&output.name(), 0);
+        let mut output_power = OutputPower::default();
+        output_power.add_output(&output.name());
+        
         let clock = Clock::new();
         
         let state = WaylandServerState {
@@ -893,6 +4577,7 @@ impl WaylandServer {
             shm_state,
             dmabuf_state,
             dmabuf_global,
+            dmabuf_formats,
             output_manager_state,
             relative_pointer_manager_state,
             pointer_constraints_state,
@@ -914,19 +4599,90 @@ impl WaylandServer {
             drm_lease_state: None, // Will be initialized when DRM device is configured
             idle_inhibit_manager_state: IdleInhibitManagerState::new::<WaylandServerState>(&dh),
             keyboard_shortcuts_inhibit_state: KeyboardShortcutsInhibitState::new::<WaylandServerState>(&dh),
+            keyboard_grab_state: KeyboardGrabState::new::<WaylandServerState, _>(&dh, WaylandServerState::keyboard_grab_filter(&security_policy)),
+            screencopy_state: ScreencopyState::new::<WaylandServerState, _>(&dh, WaylandServerState::exposure_filter(&security_policy, "zwlr_screencopy_manager_v1")),
             pointer_gestures_state: PointerGesturesState::new::<WaylandServerState>(&dh),
-            virtual_keyboard_manager_state: VirtualKeyboardManagerState::new::<WaylandServerState, _>(&dh, |_client| true),
-            text_input_manager_state: TextInputManagerState::new::<WaylandServerState>(&dh),
-            input_method_manager_state: InputMethodManagerState::new::<WaylandServerState, _>(&dh, |_client| true),
-            session_lock_manager_state: SessionLockManagerState::new::<WaylandServerState, _>(&dh, |_client| true),
-            security_context_state: SecurityContextState::new::<WaylandServerState, _>(&dh, |_client| true),
+            virtual_keyboard_manager_state,
+            text_input_manager_state,
+            input_method_manager_state,
+            on_screen_keyboard: OnScreenKeyboard::new(),
+            session_lock_manager_state: SessionLockManagerState::new::<WaylandServerState, _>(&dh, WaylandServerState::exposure_filter(&security_policy, "ext_session_lock_manager_v1")),
+            security_context_state: SecurityContextState::new::<WaylandServerState, _>(&dh, WaylandServerState::exposure_filter(&security_policy, "wp_security_context_manager_v1")),
             xdg_activation_state: XdgActivationState::new::<WaylandServerState>(&dh),
-            foreign_toplevel_list_state: ForeignToplevelListState::new::<WaylandServerState>(&dh),
+            foreign_toplevel_list_state: ForeignToplevelListState::new_with_filter::<WaylandServerState>(&dh, WaylandServerState::exposure_filter(&security_policy, "ext_foreign_toplevel_list_v1")),
             xdg_system_bell_state: XdgSystemBellState::new::<WaylandServerState>(&dh),
             drm_syncobj_state: None, // Will be initialized when DRM device is configured
             seat_state,
+            seat,
+            intercepted_buttons: HashSet::new(),
+            intercepted_keys: HashSet::new(),
+            libinput_devices: LibinputDevices::new(),
             space,
+            window_zoom: WindowZoomManager::new(),
+            focus_mode: FocusModeState::default(),
+            suppressed_layer_surfaces: Vec::new(),
+            blur: BlurManager::default(),
+            show_desktop: ShowDesktop::new(),
+            workspaces,
+            workspace_themes,
+            output_power,
+            lid: LidSwitch::default(),
+            disabled_outputs: Vec::new(),
+            output_profiles: Vec::new(),
+            stacking: StackingOrder::new(),
+            layer_focus: LayerFocus::new(),
+            automation: AutomationQueue::new(),
+            kiosk: KioskSupervisor::new(config::KioskConfig::default()),
+            layouts: LayoutStore::new(config::LayoutsConfig::default().directory),
+            pending_restore: PendingRestore::new(),
+            window_batches: TransactionQueue::new(),
+            pending_transaction: None,
+            interactive_resize: None,
+            accessibility: WindowAccessibility::new(Arc::new(AccessibilityTree::new("Custom Compositor"))),
+            click_assist: ClickAssist::new(config::PointerAccessibilityConfig::default()),
+            hot_corners: HotCornerTracker::new(config::HotCornersConfig::default(), Vec2::ZERO),
+            cursor_visibility: CursorVisibility::new(config::CursorConfig::default()),
+            startup_feedback: StartupFeedback::new(config::StartupFeedbackConfig::default()),
+            launch_requests: LaunchQueue::new(),
+            pointer_barriers: PointerBarriers::new(config::PointerBarriersConfig::default()),
+            frame_stats: Arc::new(FrameStatistics::new()),
+            responsiveness: ResponsivenessMonitor::new(config::UnresponsiveDetectionConfig::default()),
+            unresponsive_dialogs: Vec::new(),
+            dialog_style: DialogStyle::default(),
+            kill_mode: KillMode::Inactive,
+            color_picker: ColorPicker::default(),
+            annotation: AnnotationOverlay::default(),
+            region_selection: RegionSelection::default(),
+            quick_settings: QuickSettings::default(),
+            screenshots: Screenshots::default(),
+            key_bindings: KeyBindings::new(&config::BindingsConfig::default()),
+            window_placer: WindowPlacer::new(config::PlacementPolicy::default()),
+            active_window: None,
+            surface_styles: SurfaceStyles::default(),
+            urgent_windows: UrgentWindows::default(),
+            focus_follows_mouse: false,
+            animation_rates: AnimationRates::new(&config::AnimationRatesConfig::default()),
+            frame_callbacks: FrameCallbackQueue::default(),
+            security_policy,
+            client_usage: ClientResourceTracker::new(config::ClientLimitsConfig::default()),
+            protocol_latency: ProtocolLatencyTracker::new(),
+            buffer_formats: BufferFormatStats::new(),
+            frame_rate_caps: FrameRateCaps::new(),
+            app_scales: AppScales::new(),
+            window_identities: WindowIdentities::new(),
+            output_scale_requests: OutputRequests::new(),
+            output_transform_requests: OutputRequests::new(),
+            auto_rotation: AutoRotation::new(),
+            output_list: tokio::sync::watch::channel(Vec::new()).0,
+            theme_preview: ThemePreview::default(),
+            render_state: RenderStateChannel::new(),
+            surfaces: Arc::new(Mutex::new(SurfaceManager::new())),
+            window_dimming: DimmingSettings::default(),
+            focused_surface: (None, std::time::Instant::now()),
+            config_changes: None,
             clock,
+            loop_handle,
+            display_handle: dh.clone(),
             socket_name: None,
             egl_context: None, // Will be initialized when backend is configured
             egl_display: None, // Will be initialized for wl_drm protocol support
@@ -942,6 +4698,7 @@ impl WaylandServer {
             state,
             display,
             loop_signal,
+            protocols,
         })
     }
     
@@ -978,7 +4735,7 @@ impl WaylandServer {
         self.state.drm_node = drm_node;
         
         // Initialize EGL display and explicit sync if we have a DRM node
-        if let Some(ref drm_node) = self.state.drm_node {
+        if let Some(drm_node) = self.state.drm_node {
             // Get the device path - dev_path() returns Option<PathBuf>
             let device_path = match drm_node.dev_path() {
                 Some(path) => path,
@@ -1017,19 +4774,27 @@ impl WaylandServer {
                     warn!("DRM device does not support syncobj eventfd, explicit sync unavailable");
                 }
                 
+                // Send dmabuf feedback naming this device, with a scanout tranche of
+                // plane formats once preferred
+                self.state.buffer_formats.set_scanout_formats(scanout_plane_formats(device_fd));
+                let dh = self.display.handle();
+                self.state.enable_dmabuf_feedback(&dh, drm_node);
+                
                 // Store the device fd regardless of sync support for potential future use
                 self.state.drm_device_fd = drm_device_fd;
                 
                 // Initialize DRM lease state for direct hardware access
-                info!("Initializing DRM lease support for VR/gaming/CAD applications");
-                let dh = self.display.handle();
-                match DrmLeaseState::new::<WaylandServerState>(&dh, drm_node) {
-                    Ok(drm_lease_state) => {
-                        self.state.drm_lease_state = Some(drm_lease_state);
-                        info!("✅ DRM lease protocol initialized for direct hardware access");
-                    }
-                    Err(e) => {
-                        warn!("Failed to initialize DRM lease state: {}", e);
+                if self.protocols.drm_lease {
+                    info!("Initializing DRM lease support for VR/gaming/CAD applications");
+                    let dh = self.display.handle();
+                    match DrmLeaseState::new::<WaylandServerState>(&dh, &drm_node) {
+                        Ok(drm_lease_state) => {
+                            self.state.drm_lease_state = Some(drm_lease_state);
+                            info!("✅ DRM lease protocol initialized for direct hardware access");
+                        }
+                        Err(e) => {
+                            warn!("Failed to initialize DRM lease state: {}", e);
+                        }
                     }
                 }
             }
@@ -1094,41 +4859,103 @@ impl WaylandServer {
         Ok(())
     }
     
-    /// Start listening on a Wayland socket and integrate with event loop
-    pub fn start_listening(&mut self) -> Result<()> {
-        info!("Starting Wayland socket and integrating with event loop");
+    /// Start listening on the configured Wayland sockets and integrate with event loop
+    ///
+    /// Clients the compositor launches itself connect through the first
+    /// socket; the compositor's own WAYLAND_DISPLAY is left untouched.
+    pub fn start_listening(&mut self, sockets: &[config::WaylandSocketConfig]) -> Result<()> {
+        info!("Starting Wayland sockets and integrating with event loop");
+        
+        for socket_config in sockets {
+            let bound = wayland_socket::bind(socket_config)?;
+            if self.state.socket_name.is_none() {
+                self.state.socket_name = Some(bound.display.clone());
+            }
+            
+            // Insert socket into event loop
+            let restricted = bound.restricted;
+            let mut display_handle = self.display.handle();
+            self.event_loop
+                .handle()
+                .insert_source(
+                    Generic::new(bound.socket, Interest::READ, EventMode::Level),
+                    move |_, socket, state| {
+                        // Handle new client connections
+                        while let Some(stream) = socket.accept()? {
+                            state.accept_client(&mut display_handle, stream, restricted, None);
+                        }
+                        Ok(PostAction::Continue)
+                    },
+                )
+                .map_err(|e| CompositorError::wayland(format!("Failed to insert socket source: {}", e)))?;
+        }
         
-        // Create listening socket
-        let socket_source = ListeningSocketSource::new_auto()
-            .map_err(|e| CompositorError::wayland(format!("Failed to create socket: {}", e)))?;
+        let socket_name = self
+            .state
+            .socket_name
+            .clone()
+            .ok_or_else(|| CompositorError::wayland("No Wayland socket configured"))?;
+        info!("Wayland server listening on socket: {}", socket_name);
         
-        let socket_name = socket_source.socket_name().to_string_lossy().into_owned();
-        self.state.socket_name = Some(socket_name.clone());
+        // Hand the socket to the clients launched by the compositor
+        self.state.kiosk.set_wayland_display(Some(socket_name.clone()));
+        self.state.on_screen_keyboard.set_wayland_display(Some(socket_name));
         
-        // Insert socket into event loop
-        let mut display_handle = self.display.handle();
+        Ok(())
+    }
+    
+    /// Wake the event loop when clients send requests, so they are
+    /// dispatched right away instead of on the next timeout
+    fn watch_client_requests(&mut self) -> Result<()> {
+        let poll_fd = self
+            .display
+            .backend()
+            .poll_fd()
+            .try_clone_to_owned()
+            .map_err(|e| CompositorError::wayland(format!("Failed to watch client requests: {}", e)))?;
+        self.event_loop
+            .handle()
+            .insert_source(Generic::new(poll_fd, Interest::READ, EventMode::Level), |_, _, _| {
+                // Requests are dispatched at the start of each iteration
+                Ok(PostAction::Continue)
+            })
+            .map_err(|e| CompositorError::wayland(format!("Failed to insert client request source: {}", e)))?;
+        Ok(())
+    }
+    
+    /// Deliver events of the session's input devices to the seat
+    pub fn add_libinput(&mut self, access: DeviceAccess) -> Result<()> {
+        let backend = libinput_backend(access, "seat0")?;
         self.event_loop
             .handle()
-            .insert_source(socket_source, move |client_stream, _, _state| {
-                // Handle new client connections
-                if let Err(err) = display_handle.insert_client(client_stream, Arc::new(ClientState::default())) {
-                    error!("Failed to insert client: {}", err);
+            .insert_source(backend, |event, _, state| {
+                match &event {
+                    InputEvent::DeviceAdded { device } => state.libinput_devices.add(device.clone()),
+                    InputEvent::DeviceRemoved { device } => state.libinput_devices.remove(device),
+                    InputEvent::PointerMotion { event } => state.libinput_devices.pointer_moved(&event.device()),
+                    _ => {}
                 }
+                state.process_input_event(event)
             })
-            .map_err(|e| CompositorError::wayland(format!("Failed to insert socket source: {}", e)))?;
-        
-        info!("Wayland server listening on socket: {}", socket_name);
-        info!("Set WAYLAND_DISPLAY={} to connect clients", socket_name);
-        
-        // Set environment variable for clients
-        std::env::set_var("WAYLAND_DISPLAY", &socket_name);
-        
+            .map_err(|e| CompositorError::wayland(format!("Failed to insert libinput source: {}", e)))?;
+        info!("Input devices attached to seat0");
         Ok(())
     }
     
+    /// How long the next event loop iteration may sleep
+    fn dispatch_timeout(&self) -> std::time::Duration {
+        let now = std::time::Instant::now();
+        self.state
+            .next_deadline()
+            .map_or(MAX_DISPATCH_INTERVAL, |deadline| {
+                deadline.saturating_duration_since(now).min(MAX_DISPATCH_INTERVAL)
+            })
+    }
+    
     /// Run the event loop (blocking)
     pub fn run(mut self) -> Result<()> {
         info!("Starting Wayland server event loop");
+        self.watch_client_requests()?;
         
         // Main event loop using smithay's standard pattern
         loop {
@@ -1144,8 +4971,32 @@ impl WaylandServer {
                 break;
             }
             
-            // Run event loop iteration
-            if let Err(e) = self.event_loop.dispatch(Some(std::time::Duration::from_millis(16)), &mut self.state) {
+            let dh = self.display.handle();
+            self.state.publish_client_stats(&dh);
+            self.state.process_config_changes();
+            self.state.apply_output_scale_requests();
+            self.state.apply_output_transform_requests();
+            self.state.apply_auto_rotation();
+            self.state.process_layout_requests();
+            self.state.process_window_batches();
+            self.state.process_launch_requests();
+            self.state.publish_outputs();
+            self.state.theme_preview.process_requests();
+            self.state.quick_settings.process_events();
+            if self.state.region_selection.take_requests() {
+                self.state.select_screenshot_region();
+            }
+            self.state.process_frame_callbacks();
+            self.state.kiosk.poll(std::time::Instant::now());
+            self.state.check_responsiveness();
+            self.state.process_focus_mode();
+            self.state.process_readbacks();
+            self.state.process_seat();
+            self.state.publish_render_state();
+            
+            // Sleep until clients send requests, a source fires or a deadline passes
+            let timeout = self.dispatch_timeout();
+            if let Err(e) = self.event_loop.dispatch(Some(timeout), &mut self.state) {
                 error!("Event loop error: {}", e);
                 break;
             }
@@ -1158,6 +5009,7 @@ impl WaylandServer {
     /// Run the event loop asynchronously (non-blocking)
     pub async fn run_async(mut self) -> Result<()> {
         info!("Starting Wayland server async event loop");
+        self.watch_client_requests()?;
         
         // Async event loop using smithay's standard pattern
         loop {
@@ -1173,8 +5025,32 @@ impl WaylandServer {
                 break;
             }
             
-            // Run event loop iteration with async yield
-            if let Err(e) = self.event_loop.dispatch(Some(std::time::Duration::from_millis(16)), &mut self.state) {
+            let dh = self.display.handle();
+            self.state.publish_client_stats(&dh);
+            self.state.process_config_changes();
+            self.state.apply_output_scale_requests();
+            self.state.apply_output_transform_requests();
+            self.state.apply_auto_rotation();
+            self.state.process_layout_requests();
+            self.state.process_window_batches();
+            self.state.process_launch_requests();
+            self.state.publish_outputs();
+            self.state.theme_preview.process_requests();
+            self.state.quick_settings.process_events();
+            if self.state.region_selection.take_requests() {
+                self.state.select_screenshot_region();
+            }
+            self.state.process_frame_callbacks();
+            self.state.kiosk.poll(std::time::Instant::now());
+            self.state.check_responsiveness();
+            self.state.process_focus_mode();
+            self.state.process_readbacks();
+            self.state.process_seat();
+            self.state.publish_render_state();
+            
+            // Sleep until clients send requests, a source fires or a deadline passes
+            let timeout = self.dispatch_timeout();
+            if let Err(e) = self.event_loop.dispatch(Some(timeout), &mut self.state) {
                 error!("Event loop error: {}", e);
                 break;
             }
@@ -1387,6 +5263,19 @@ impl CompositorHandler for WaylandServerState {
     /// - Lazy initialization of optional features (scaling, etc.)
     fn new_surface(&mut self, surface: &WlSurface) {
         debug!("New Wayland surface created: ID {:?}", surface.id());
+        
+        if let Some(client) = surface.client() {
+            self.client_usage.surface_created(client.id(), || Self::client_process_info(&client), surface.id());
+        }
+        add_destruction_hook::<Self, _>(surface, |state, surface| {
+            state.client_usage.surface_destroyed(&surface.id());
+            state.protocol_latency.surface_destroyed(&surface.id());
+            state.buffer_formats.surface_destroyed(&surface.id());
+            state.frame_callbacks.remove(&surface.id());
+            if let Ok(mut surfaces) = state.surfaces.lock() {
+                surfaces.remove_surface(surface.id().protocol_id());
+            }
+        });
         debug!("Surface initialization: pending/current state setup, damage tracking enabled");
         
         // TODO: Initialize surface-specific optimizations
@@ -1461,6 +5350,127 @@ impl CompositorHandler for WaylandServerState {
             debug!("Commit processing complete - surface ready for next frame");
         });
         
+        // Queue content attached with new damage for the render thread to upload,
+        // with the damage in global coordinates where the surface's position is known
+        let content = with_states(surface, |states| {
+            let mut attributes = states.cached_state.get::<SurfaceAttributes>();
+            let attributes = attributes.current();
+            let damage = std::mem::take(&mut attributes.damage);
+            match &attributes.buffer {
+                Some(BufferAssignment::NewBuffer(buffer)) if !damage.is_empty() => {
+                    let damage = surface_damage(&damage, buffer, attributes.buffer_scale, attributes.buffer_transform);
+                    Some(Some((buffer.clone(), damage)))
+                }
+                Some(BufferAssignment::Removed) => Some(None),
+                _ => None,
+            }
+        });
+        if let Some(content) = content {
+            let origin = self.surface_origin(surface);
+            if let Ok(mut surfaces) = self.surfaces.lock() {
+                let surface_id = surface.id().protocol_id();
+                match content {
+                    Some((buffer, damage)) => {
+                        let damage = origin.map(|origin| {
+                            damage.into_iter().map(|rect| render_rect_f64(rect, origin)).collect()
+                        });
+                        if let Err(e) = surfaces.handle_surface_commit(surface_id, &buffer, damage) {
+                            debug!("Cannot upload the buffer of surface {}: {}", surface_id, e);
+                        }
+                    }
+                    None => surfaces.remove_surface(surface_id),
+                }
+            }
+            self.render_state.wake();
+        }
+        
+        // Re-apply blur rules (app IDs may change) and refresh the opaque mask
+        let (buffer_usage, buffer_format, queued_callbacks, wants_feedback) = with_states(surface, |states| {
+            let mut attributes = states.cached_state.get::<SurfaceAttributes>();
+            let attributes = attributes.current();
+            (
+                attributes.buffer.as_ref().map(Self::buffer_usage),
+                attributes.buffer.as_ref().map(Self::buffer_format),
+                attributes.frame_callbacks.len(),
+                !states.cached_state.get::<PresentationFeedbackCachedState>().current().callbacks.is_empty(),
+            )
+        });
+        if queued_callbacks > 0 || wants_feedback {
+            // TODO: Schedule a redraw of the outputs showing the surface
+            self.frame_callbacks.queue(surface, std::time::Instant::now());
+        }
+        if let Some(format) = buffer_format {
+            self.buffer_formats.surface_committed(surface.id(), format);
+        }
+        if let Some(client) = surface.client() {
+            let now = std::time::Instant::now();
+            self.client_usage.surface_committed(
+                client.id(),
+                || Self::client_process_info(&client),
+                surface.id(),
+                buffer_usage,
+                queued_callbacks,
+                now,
+            );
+            self.protocol_latency.surface_committed(|| Self::client_process_info(&client), &surface.id(), now);
+        }
+        if let Some(transaction) = &mut self.pending_transaction {
+            transaction.surface_committed(&surface.id());
+        }
+        let resized_location = self
+            .interactive_resize
+            .as_mut()
+            .filter(|resize| resize.is_window(surface))
+            .and_then(InteractiveResize::committed);
+        if let Some(location) = resized_location {
+            self.resize_window_committed(location);
+        }
+        if self.layer_focus.contains(&surface.id()) {
+            let (layer, interactivity) = with_states(surface, |states| {
+                let mut cached = states.cached_state.get::<LayerSurfaceCachedState>();
+                let cached = cached.current();
+                (cached.layer, cached.keyboard_interactivity)
+            });
+            self.layer_focus.update(&surface.id(), layer, interactivity);
+        }
+        
+        let (toplevel_app_id, toplevel_title, opaque_region) = with_states(surface, |states| {
+            let toplevel_data = states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .map(|data| {
+                    let data = data.lock().unwrap();
+                    (data.app_id.clone(), data.title.clone())
+                });
+            let (toplevel_app_id, toplevel_title) = toplevel_data.unzip();
+            let opaque_region = states
+                .cached_state
+                .get::<SurfaceAttributes>()
+                .current()
+                .opaque_region
+                .clone();
+            (toplevel_app_id, toplevel_title.flatten(), opaque_region)
+        });
+        if let Some(app_id) = &toplevel_app_id {
+            self.apply_window_rules(surface, app_id.as_deref(), toplevel_title.as_deref());
+        }
+        if self.window_placer.is_pending(&surface.id()) {
+            self.place_window(surface);
+            // New windows take keyboard focus once they are placed
+            if !self.window_placer.is_pending(&surface.id()) {
+                if let Some(window) = self.window_for_surface(surface).cloned() {
+                    let seat = self.seat.clone();
+                    self.focus_window(&seat, &window, SERIAL_COUNTER.next_serial());
+                }
+            }
+        }
+        self.blur.update_opaque_region(&surface.id(), opaque_region.as_ref());
+        self.frame_stats.surface_committed(
+            surface.id().protocol_id(),
+            toplevel_app_id.flatten().as_deref(),
+            std::time::Instant::now(),
+        );
+        
         // Update compositor space to reflect surface changes
         self.space.refresh();
         debug!("Compositor space refreshed - surface changes integrated");
@@ -1558,24 +5568,37 @@ impl XdgShellHandler for WaylandServerState {
     fn new_toplevel(&mut self, surface: ToplevelSurface) {
         info!("New toplevel window created - initializing window management");
         
+        // Place the window on the active workspace of the primary output
+        let output_name = self.space.outputs().next().map(|output| output.name()).unwrap_or_default();
+        self.workspaces.add_window(surface.wl_surface().id(), &output_name, false);
+        self.stacking.insert(surface.wl_surface().id(), StackLayer::Normal);
+        self.surface_styles.assign(surface.wl_surface().id(), toplevel_class(&surface));
+        
+        // Titles usually arrive with the first commit
+        self.accessibility.add_window(surface.wl_surface().id(), "");
+        
         // Create window object and integrate with compositor space management
         let window = Window::new_wayland_window(surface);
         
-        // Apply intelligent window placement
-        // TODO: Implement smart placement algorithm to avoid window overlap
-        // TODO: Consider output geometry and available space
-        // TODO: Apply user-configured placement policies (cascade, center, etc.)
-        let initial_position = (100, 100); // Placeholder for smart placement
+        // Map at the top-left of the usable area; the placement policy moves
+        // the window once its first buffer gives it a size
+        let initial_position = self.usable_area(&output_name).map_or_else(Point::default, |area| area.loc);
         
         // Map window to compositor space with initial positioning
-        self.space.map_element(window, initial_position, false);
+        self.space.map_element(window.clone(), initial_position, false);
+        self.window_identities.window_mapped::<Self>(&window, &mut self.foreign_toplevel_list_state);
         
         info!("Toplevel window mapped to compositor space at position: {:?}", initial_position);
         
+        if self.kiosk.is_active() {
+            self.make_kiosk_fullscreen(&window);
+        } else if let Some(toplevel) = window.toplevel() {
+            self.window_placer.window_mapped(toplevel.wl_surface().id());
+        }
+        
         // TODO: Configure default window state and properties
         // TODO: Apply server-side decorations for glassmorphism theme
         // TODO: Register window with app bar for taskbar integration
-        // TODO: Set up window for focus management and input handling
         
         debug!("Toplevel window ready for user interaction and rendering");
     }
@@ -1606,8 +5629,9 @@ impl XdgShellHandler for WaylandServerState {
     /// - **Fast Positioning** - Optimized constraint solving for interactive responsiveness
     /// - **Minimal State** - Lightweight popup state management
     /// - **Efficient Rendering** - Optimized for temporary content display
-    fn new_popup(&mut self, _surface: PopupSurface, _positioner: PositionerState) {
+    fn new_popup(&mut self, surface: PopupSurface, _positioner: PositionerState) {
         debug!("New popup created - setting up transient surface management");
+        self.surface_styles.assign(surface.wl_surface().id(), config::SurfaceClass::Popup);
         
         // TODO: Implement comprehensive popup management
         // TODO: Apply positioning constraints from PositionerState
@@ -1619,16 +5643,97 @@ impl XdgShellHandler for WaylandServerState {
         debug!("Popup surface ready for constraint-based positioning");
     }
     
-    fn toplevel_destroyed(&mut self, _surface: ToplevelSurface) {
+    fn client_pong(&mut self, client: ShellClient) {
+        let client_id = self
+            .xdg_shell_state
+            .toplevel_surfaces()
+            .iter()
+            .find(|toplevel| toplevel.client() == client)
+            .and_then(|toplevel| toplevel.wl_surface().client())
+            .map(|client| client.id());
+        if let Some(client_id) = client_id {
+            if self.responsiveness.pong(&client_id, std::time::Instant::now()) {
+                info!("Application is responding again");
+                self.unresponsive_dialogs.retain(|(shown, _)| *shown != client_id);
+            }
+        }
+    }
+    
+    fn ack_configure(&mut self, surface: WlSurface, configure: Configure) {
+        let serial = match &configure {
+            Configure::Toplevel(configure) => configure.serial,
+            Configure::Popup(configure) => configure.serial,
+        };
+        let client = surface.client();
+        self.protocol_latency.configure_acked(
+            || client.as_ref().and_then(Self::client_process_info),
+            &surface.id(),
+            serial,
+            std::time::Instant::now(),
+        );
+        if let Some(transaction) = &mut self.pending_transaction {
+            transaction.configure_acked(&surface.id(), serial);
+        }
+        if let Some(resize) = self.interactive_resize.as_mut().filter(|resize| resize.is_window(&surface)) {
+            resize.configure_acked(serial);
+        }
+    }
+    
+    fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
         info!("Toplevel window destroyed");
-        // TODO: Remove window from space
+        let window = self.window_for_surface(surface.wl_surface()).cloned();
+        if let Some(window) = &window {
+            self.window_identities.window_closed(window, &mut self.foreign_toplevel_list_state);
+        }
+        let location = window.as_ref().and_then(|window| self.space.element_location(window));
+        let (app_id, _) = Self::toplevel_identity(&surface);
+        self.window_placer.window_closed(&surface.wl_surface().id(), app_id.as_deref(), location);
+        if self.interactive_resize.as_ref().is_some_and(|resize| resize.is_window(surface.wl_surface())) {
+            self.interactive_resize = None;
+        }
+        self.window_zoom.remove_window(&surface.wl_surface().id());
+        self.blur.remove_surface(&surface.wl_surface().id());
+        self.show_desktop.remove_window(&surface.wl_surface().id());
+        self.workspaces.remove_window(&surface.wl_surface().id());
+        self.frame_rate_caps.remove_window(&surface.wl_surface().id());
+        self.app_scales.remove_window(&surface.wl_surface().id());
+        self.accessibility.remove_window(&surface.wl_surface().id());
+        self.stacking.remove(&surface.wl_surface().id());
+        self.surface_styles.remove(&surface.wl_surface().id());
+        self.urgent_windows.clear(&surface.wl_surface().id());
+        if let Some(transaction) = &mut self.pending_transaction {
+            transaction.surface_destroyed(&surface.wl_surface().id());
+        }
+        self.frame_stats.surface_destroyed(surface.wl_surface().id().protocol_id());
+        
+        if let Some(window) = &window {
+            self.space.unmap_elem(window);
+            if self.active_window.as_ref() == Some(window) {
+                self.active_window = None;
+                self.focus_topmost_window();
+            }
+        }
+    }
+    
+    fn title_changed(&mut self, surface: ToplevelSurface) {
+        self.toplevel_identity_changed(&surface);
     }
     
-    fn popup_destroyed(&mut self, _surface: PopupSurface) {
+    fn app_id_changed(&mut self, surface: ToplevelSurface) {
+        self.toplevel_identity_changed(&surface);
+    }
+    
+    fn popup_destroyed(&mut self, surface: PopupSurface) {
         debug!("Popup destroyed");
+        self.surface_styles.remove(&surface.wl_surface().id());
         // TODO: Handle popup destruction
     }
     
+    fn parent_changed(&mut self, surface: ToplevelSurface) {
+        // Toplevels with a parent are styled as dialogs
+        self.surface_styles.assign(surface.wl_surface().id(), toplevel_class(&surface));
+    }
+    
     fn grab(&mut self, _surface: PopupSurface, _seat: WlSeat, _serial: Serial) {
         debug!("Popup grab requested");
         // TODO: Handle popup grabs
@@ -1638,6 +5743,85 @@ impl XdgShellHandler for WaylandServerState {
         debug!("Popup reposition requested");
         // TODO: Handle popup repositioning
     }
+    
+    fn resize_request(&mut self, surface: ToplevelSurface, seat: WlSeat, serial: Serial, edges: xdg_toplevel::ResizeEdge) {
+        if self.kiosk.is_active() || self.interactive_resize.is_some() {
+            return;
+        }
+        let Some(seat) = Seat::<Self>::from_resource(&seat) else { return };
+        let Some(pointer) = seat.get_pointer() else { return };
+        
+        // Only start from the button press the client got the serial for, on this window
+        if !pointer.has_grab(serial) {
+            return;
+        }
+        let Some(start_data) = pointer.grab_start_data() else { return };
+        let pressed_on_window = start_data
+            .focus
+            .as_ref()
+            .is_some_and(|(focus, _)| focus.id().same_client_as(&surface.wl_surface().id()));
+        if !pressed_on_window {
+            return;
+        }
+        
+        let Some(window) = self.window_for_surface(surface.wl_surface()).cloned() else { return };
+        let Some(location) = self.space.element_location(&window) else { return };
+        let limits = with_states(surface.wl_surface(), |states| {
+            let mut cached = states.cached_state.get::<SurfaceCachedState>();
+            let cached = cached.current();
+            (cached.min_size, cached.max_size)
+        });
+        // Configure at most once per refresh of the output the window is on
+        let refresh = self
+            .space
+            .outputs_for_element(&window)
+            .first()
+            .and_then(|output| output.current_mode())
+            .map_or(60_000, |mode| mode.refresh)
+            .max(1);
+        let interval = std::time::Duration::from_secs_f64(1000.0 / refresh as f64);
+        
+        debug!("Starting interactive resize of surface {:?} from {:?}", surface.wl_surface().id(), edges);
+        surface.with_pending_state(|state| {
+            state.states.set(xdg_toplevel::State::Resizing);
+        });
+        let size = window.geometry().size;
+        self.interactive_resize = Some(InteractiveResize::new(window, edges, location, size, limits, interval));
+        pointer.set_grab(self, ResizeSurfaceGrab { start_data }, serial, Focus::Clear);
+    }
+    
+    fn move_request(&mut self, surface: ToplevelSurface, seat: WlSeat, serial: Serial) {
+        if self.kiosk.is_active() {
+            return;
+        }
+        let Some(seat) = Seat::<Self>::from_resource(&seat) else { return };
+        let Some(pointer) = seat.get_pointer() else { return };
+        
+        // Only start from the button press the client got the serial for, on this window
+        if !pointer.has_grab(serial) {
+            return;
+        }
+        let Some(start_data) = pointer.grab_start_data() else { return };
+        let pressed_on_window = start_data
+            .focus
+            .as_ref()
+            .is_some_and(|(focus, _)| focus.id().same_client_as(&surface.wl_surface().id()));
+        if !pressed_on_window {
+            return;
+        }
+        
+        let Some(window) = self.window_for_surface(surface.wl_surface()).cloned() else { return };
+        let Some(initial_window_location) = self.space.element_location(&window) else { return };
+        debug!("Starting interactive move of surface {:?}", surface.wl_surface().id());
+        let output = self.space.output_under(start_data.location).next().cloned();
+        let grab = MoveSurfaceGrab {
+            start_data,
+            window,
+            initial_window_location,
+            output,
+        };
+        pointer.set_grab(self, grab, serial, Focus::Clear);
+    }
 }
 
 // ============================================================================
@@ -1752,50 +5936,19 @@ impl WlrLayerShellHandler for WaylandServerState {
     /// - **Minimal Layout Recalculation** - Smart exclusive zone updates
     fn new_layer_surface(
         &mut self, 
-        _surface: LayerSurface, 
+        surface: LayerSurface, 
         _wl_output: Option<wayland_server::protocol::wl_output::WlOutput>, 
         layer: Layer, 
         namespace: String
     ) {
         info!("New layer surface created: namespace='{}', layer={:?}", namespace, layer);
         
-        // Log layer-specific integration details
-        match layer {
-            Layer::Background => {
-                info!("Background layer surface - setting up wallpaper/background rendering");
-                // TODO: Configure for full-screen background rendering
-                // TODO: Set up background blur effect support
-                // TODO: Integrate with wallpaper management system
-            }
-            Layer::Bottom => {
-                info!("Bottom layer surface - setting up below-window elements");
-                // TODO: Configure for widget and decoration rendering
-                // TODO: Set up exclusive zone management for bottom layer
-                // TODO: Integrate with desktop widget system
-            }
-            Layer::Top => {
-                info!("Top layer surface - setting up panel/taskbar integration");
-                // TODO: Configure for panel rendering with glassmorphism effects
-                // TODO: Set up exclusive zone calculation for panels
-                // TODO: Integrate with app bar and taskbar systems
-                // TODO: Configure panel transparency and blur effects
-            }
-            Layer::Overlay => {
-                info!("Overlay layer surface - setting up notification/popup system");
-                // TODO: Configure for notification and modal dialog rendering
-                // TODO: Set up temporary surface lifecycle management
-                // TODO: Integrate with notification daemon and system dialogs
-            }
+        if !self.focus_mode.allows_layer_surface(&namespace) {
+            info!("Focus mode active - suppressing notification surface '{}'", namespace);
+            self.suppressed_layer_surfaces.push((surface, layer, namespace));
+            return;
         }
-        
-        // TODO: Comprehensive layer surface setup
-        // TODO: Apply anchoring and positioning constraints
-        // TODO: Configure exclusive zones based on surface role
-        // TODO: Set up output-specific rendering if targeted output specified
-        // TODO: Integrate with compositor's layer management system
-        // TODO: Configure glassmorphism effects for appropriate layer types
-        
-        debug!("Layer surface '{}' integrated into {:?} layer", namespace, layer);
+        self.map_layer_surface(surface, layer, namespace);
     }
     
     /// Handle destruction of layer shell surfaces
@@ -1825,9 +5978,15 @@ impl WlrLayerShellHandler for WaylandServerState {
     /// - **Batched Layout Updates** - Efficient recalculation of multiple changes
     /// - **Minimal Redraw** - Only affected areas need re-rendering
     /// - **Resource Pooling** - Reuse surface state for new layer surfaces
-    fn layer_destroyed(&mut self, _surface: LayerSurface) {
+    fn layer_destroyed(&mut self, surface: LayerSurface) {
         info!("Layer surface destroyed - updating desktop layout");
         
+        self.suppressed_layer_surfaces.retain(|(suppressed, ..)| *suppressed != surface);
+        self.blur.remove_surface(&surface.wl_surface().id());
+        self.stacking.remove(&surface.wl_surface().id());
+        self.layer_focus.remove(&surface.wl_surface().id());
+        self.surface_styles.remove(&surface.wl_surface().id());
+        
         // TODO: Comprehensive layer surface cleanup
         // TODO: Remove surface from appropriate layer in space management
         // TODO: Recalculate exclusive zones and update window layout
@@ -1862,8 +6021,22 @@ impl SeatHandler for WaylandServerState {
         &mut self.seat_state
     }
     
-    fn focus_changed(&mut self, _seat: &Seat<Self>, _focused: Option<&Self::KeyboardFocus>) {
+    fn focus_changed(&mut self, seat: &Seat<Self>, focused: Option<&Self::KeyboardFocus>) {
         debug!("Focus changed for seat");
+        
+        self.update_text_input_activity(seat, focused);
+        
+        // Other windows fade to dimmed, see `publish_render_state`
+        self.focused_surface = (focused.map(|surface| surface.id().protocol_id()), std::time::Instant::now());
+        
+        // Let screen readers announce the newly focused window
+        self.accessibility.focus_window(focused.map(|surface| surface.id()).as_ref());
+        
+        let window = focused.and_then(|surface| self.window_for_surface(surface)).cloned();
+        self.set_active_window(window);
+        
+        // Focusing a trigger app turns focus mode on
+        self.process_focus_mode();
     }
     
     fn cursor_image(&mut self, _seat: &Seat<Self>, _image: smithay::input::pointer::CursorImageStatus) {
@@ -1915,7 +6088,7 @@ impl XdgDecorationHandler for WaylandServerState {
         toplevel.with_pending_state(|state| {
             state.decoration_mode = Some(wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode::ServerSide);
         });
-        toplevel.send_configure();
+        self.send_configure(&toplevel);
         
         debug!("Configured server-side decorations for toplevel window");
     }
@@ -1945,7 +6118,7 @@ impl XdgDecorationHandler for WaylandServerState {
             }
         }
         
-        toplevel.send_configure();
+        self.send_configure(&toplevel);
         debug!("Applied decoration mode: {:?}", mode);
     }
     
@@ -1956,7 +6129,7 @@ impl XdgDecorationHandler for WaylandServerState {
         toplevel.with_pending_state(|state| {
             state.decoration_mode = Some(wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode::ServerSide);
         });
-        toplevel.send_configure();
+        self.send_configure(&toplevel);
         
         debug!("Reset to server-side decorations (default)");
     }
@@ -1966,8 +6139,49 @@ impl XdgDecorationHandler for WaylandServerState {
 // Selection Handler Implementation
 // ============================================================================
 
+/// Upper bound on how long the event loop sleeps, so requests queued over
+/// channels, e.g. from IPC, and the kiosk application exiting are noticed
+const MAX_DISPATCH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Mime types of text the compositor puts on the clipboard
+const CLIPBOARD_TEXT_MIME_TYPES: [&str; 3] = ["text/plain;charset=utf-8", "text/plain", "UTF8_STRING"];
+
+/// Mime types of images the compositor puts on the clipboard
+const CLIPBOARD_IMAGE_MIME_TYPES: [&str; 1] = ["image/png"];
+
+/// Age after which an activation token no longer lets a window take focus
+const ACTIVATION_TOKEN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Data of a selection the compositor offers
+#[derive(Debug, Clone)]
+pub struct ClipboardData {
+    mime_types: &'static [&'static str],
+    data: Arc<[u8]>,
+}
+
 impl SelectionHandler for WaylandServerState {
-    type SelectionUserData = ();
+    type SelectionUserData = ClipboardData;
+    
+    fn send_selection(
+        &mut self,
+        _ty: SelectionTarget,
+        mime_type: String,
+        fd: std::os::fd::OwnedFd,
+        _seat: Seat<Self>,
+        user_data: &Self::SelectionUserData,
+    ) {
+        if !user_data.mime_types.contains(&mime_type.as_str()) {
+            return;
+        }
+        // Write from a thread, a client reading slowly must not block the compositor
+        let data = user_data.data.clone();
+        std::thread::spawn(move || {
+            use std::io::Write;
+            if let Err(e) = std::fs::File::from(fd).write_all(&data) {
+                debug!("Failed to send clipboard data: {}", e);
+            }
+        });
+    }
 }
 
 // ============================================================================
@@ -2052,10 +6266,12 @@ impl FractionalScaleHandler for WaylandServerState {
     fn new_fractional_scale(&mut self, surface: WlSurface) {
         info!("New fractional scale instantiated for surface: {:?}", surface.id());
         
-        // TODO: Implement fractional scale calculation based on output configuration
-        // TODO: Send appropriate scale factor to client for 4K display optimization
-        // TODO: Integrate with output scale management for consistent scaling
-        debug!("Fractional scale handler ready for sub-pixel precision scaling");
+        // Scale changes of the output are sent later by set_output_scale
+        let scale = self.preferred_scale(&surface);
+        with_states(&surface, |states| {
+            with_fractional_scale(states, |fractional| fractional.set_preferred_scale(scale));
+        });
+        debug!("Preferred fractional scale {} sent to surface {:?}", scale, surface.id());
     }
 }
 
@@ -2216,6 +6432,55 @@ impl KeyboardShortcutsInhibitHandler for WaylandServerState {
     }
 }
 
+// ============================================================================
+// Keyboard Grab Handler Implementation
+// ============================================================================
+
+impl KeyboardGrabHandler for WaylandServerState {
+    fn grab_keyboard(&mut self, client: &wayland_server::Client, surface: WlSurface, seat: Seat<Self>, grab: ZwpXwaylandKeyboardGrabV1) {
+        let credentials = Self::client_credentials(client);
+        let process = credentials.map_or_else(|| "unknown process".to_string(), ToString::to_string);
+        
+        // The policy may have changed since the client bound the global
+        if !self.security_policy.allows_keyboard_grab(credentials) {
+            info!("Denied keyboard grab to {}", process);
+            return;
+        }
+        let Some(keyboard) = seat.get_keyboard() else {
+            return;
+        };
+        
+        info!("Keyboard grabbed by {} for surface {:?}", process, surface.id());
+        // TODO: Keep a compositor escape shortcut working during grabs once keybindings exist
+        let serial = SERIAL_COUNTER.next_serial();
+        let keep_on_focus_loss = self.keyboard_grab_state.keeps_on_focus_loss();
+        keyboard.set_focus(self, Some(surface.clone()), serial);
+        keyboard.set_grab(self, ExclusiveKeyboardGrab::new(grab, surface, keep_on_focus_loss), serial);
+    }
+}
+
+// ============================================================================
+// Screencopy Handler Implementation
+// ============================================================================
+
+impl ScreencopyHandler for WaylandServerState {
+    fn screencopy_state(&mut self) -> &mut ScreencopyState {
+        &mut self.screencopy_state
+    }
+
+    fn capture_region(&self, output: &Output, region: Option<Rectangle<i32, Logical>>) -> Option<ReadbackRegion> {
+        // TODO: Capture other outputs once each output renders its own
+        // frame; the renderer composes the first one
+        if self.space.outputs().next() != Some(output) {
+            return None;
+        }
+        let size = self.space.output_geometry(output)?.size;
+        let output_area = Rectangle::new(Point::from((0, 0)), size);
+        let area = region.map_or(Some(output_area), |region| region.intersection(output_area))?;
+        Some(Self::output_readback_region(output, area))
+    }
+}
+
 // ============================================================================
 // Session Lock Handler Implementation
 // ============================================================================
@@ -2234,11 +6499,13 @@ impl SessionLockHandler for WaylandServerState {
 
     fn unlock(&mut self) {
         // Handle unlock request
+        self.stacking.clear_layer(StackLayer::Lock);
         info!("Session unlocked");
     }
 
-    fn new_surface(&mut self, _surface: smithay::wayland::session_lock::LockSurface, _output: smithay::reexports::wayland_server::protocol::wl_output::WlOutput) {
+    fn new_surface(&mut self, surface: smithay::wayland::session_lock::LockSurface, _output: smithay::reexports::wayland_server::protocol::wl_output::WlOutput) {
         // Handle new lock surface
+        self.stacking.insert(surface.wl_surface().id(), StackLayer::Lock);
         info!("New lock surface created for output");
     }
 }
@@ -2248,14 +6515,22 @@ impl SessionLockHandler for WaylandServerState {
 // ============================================================================
 
 impl SecurityContextHandler for WaylandServerState {
-    fn context_created(&mut self, _source: smithay::wayland::security_context::SecurityContextListenerSource, _security_context: smithay::wayland::security_context::SecurityContext) {
-        info!("Security context created for sandboxed application");
+    fn context_created(&mut self, source: SecurityContextListenerSource, security_context: SecurityContext) {
+        info!(
+            "Security context created for sandboxed application {} ({})",
+            security_context.app_id.as_deref().unwrap_or("unknown"),
+            security_context.sandbox_engine.as_deref().unwrap_or("unknown sandbox")
+        );
         
-        // TODO: Implement sandboxed execution environment
-        // TODO: Apply security restrictions based on context capabilities
-        // TODO: Isolate context from sensitive system resources
-        // TODO: Track context permissions and enforce access controls
-        debug!("Security context established with capability-based permissions");
+        // Clients of the context are restricted, so the exposure policy
+        // hides privileged globals from them
+        let mut display_handle = self.display_handle.clone();
+        let result = self.loop_handle.insert_source(source, move |stream, _, state| {
+            state.accept_client(&mut display_handle, stream, true, Some(security_context.clone()));
+        });
+        if let Err(e) = result {
+            warn!("Failed to listen for security context clients: {}", e);
+        }
     }
 }
 
@@ -2268,14 +6543,40 @@ impl XdgActivationHandler for WaylandServerState {
         &mut self.xdg_activation_state
     }
     
-    fn request_activation(&mut self, _token: smithay::wayland::xdg_activation::XdgActivationToken, _token_data: smithay::wayland::xdg_activation::XdgActivationTokenData, _surface: WlSurface) {
+    fn request_activation(&mut self, token: XdgActivationToken, token_data: XdgActivationTokenData, surface: WlSurface) {
         info!("Window activation requested for surface with token");
         
-        // TODO: Implement focus management and window activation
-        // TODO: Validate activation request against security policies
-        // TODO: Switch focus to requested surface if authorized
-        // TODO: Update window stack order and input focus
-        debug!("Processing window activation request");
+        // An app launched by the compositor activating its token has started
+        let launched = self.startup_feedback.launch_ended(&token);
+        if launched {
+            self.startup_feedback_changed();
+        }
+        
+        let Some(window) = self.window_for_surface(&surface).cloned() else {
+            debug!("Activation requested for a surface that is not a window");
+            return;
+        };
+        
+        // Focus may only be taken with a token of an app we launched, or one
+        // made recently for input to the focused client; otherwise the
+        // window is marked urgent instead of stealing focus
+        let seat = self.seat.clone();
+        let from_focused_input = token_data.timestamp.elapsed() < ACTIVATION_TOKEN_TIMEOUT
+            && token_data.serial.as_ref().is_some_and(|(serial, _)| {
+                seat.get_keyboard()
+                    .and_then(|keyboard| keyboard.last_enter())
+                    .is_some_and(|last_enter| serial.is_no_older_than(&last_enter))
+            });
+        let visible = window
+            .toplevel()
+            .is_some_and(|toplevel| self.workspaces.is_visible(&toplevel.wl_surface().id()));
+        if (launched || from_focused_input) && visible {
+            debug!("Activating window with token");
+            self.focus_window(&seat, &window, SERIAL_COUNTER.next_serial());
+        } else {
+            debug!("Window may not take focus, marking it urgent");
+            self.set_urgent(&window, true);
+        }
     }
 }
 
@@ -2324,6 +6625,28 @@ impl DrmLeaseHandler for WaylandServerState {
     }
 }
 
+/// Fourcc codes accepted by any plane of a DRM device
+fn scanout_plane_formats(device: &DrmDeviceFd) -> Vec<u32> {
+    use smithay::reexports::drm::{control::Device as _, ClientCapability, Device as _};
+    
+    // Primary and cursor planes are only listed with universal planes enabled
+    if let Err(e) = device.set_client_capability(ClientCapability::UniversalPlanes, true) {
+        debug!("Universal planes unavailable: {}", e);
+    }
+    let planes = match device.plane_handles() {
+        Ok(planes) => planes,
+        Err(e) => {
+            warn!("Failed to list DRM planes: {}", e);
+            return Vec::new();
+        }
+    };
+    planes
+        .into_iter()
+        .filter_map(|plane| device.get_plane(plane).ok())
+        .flat_map(|info| info.formats().to_vec())
+        .collect()
+}
+
 // ============================================================================
 // Foreign Toplevel List Handler Implementation
 // ============================================================================
@@ -2342,8 +6665,15 @@ impl XdgSystemBellHandler for WaylandServerState {
     fn ring(&mut self, surface: Option<WlSurface>) {
         if let Some(surface) = surface {
             info!("System bell ring requested for surface: {:?}", surface.id());
+            // Windows ringing the bell in the background ask for attention
+            let mut root = surface.clone();
+            while let Some(parent) = get_parent(&root) {
+                root = parent;
+            }
+            if let Some(window) = self.window_for_surface(&root).cloned() {
+                self.set_urgent(&window, true);
+            }
             // TODO: Implement audio feedback system integration
-            // TODO: Flash window/surface to provide visual bell indication
             // TODO: Send notification to desktop environment for accessibility
         } else {
             info!("Global system bell ring requested");
@@ -2446,6 +6776,12 @@ smithay::delegate_session_lock!(WaylandServerState);      // Screen locking (ses
 smithay::delegate_security_context!(WaylandServerState);  // Application sandboxing (security-context)
 smithay::delegate_idle_inhibit!(WaylandServerState);      // Power management (idle-inhibit)
 smithay::delegate_keyboard_shortcuts_inhibit!(WaylandServerState); // Gaming mode shortcuts (keyboard-shortcuts-inhibit)
+wayland_server::delegate_global_dispatch!(WaylandServerState: [ZwpXwaylandKeyboardGrabManagerV1: KeyboardGrabGlobalData] => KeyboardGrabState); // Exclusive keyboard grabs (xwayland-keyboard-grab)
+wayland_server::delegate_dispatch!(WaylandServerState: [ZwpXwaylandKeyboardGrabManagerV1: ()] => KeyboardGrabState);
+wayland_server::delegate_dispatch!(WaylandServerState: [ZwpXwaylandKeyboardGrabV1: KeyboardGrabData<WaylandServerState>] => KeyboardGrabState);
+wayland_server::delegate_global_dispatch!(WaylandServerState: [ZwlrScreencopyManagerV1: ScreencopyGlobalData] => ScreencopyState); // Screen capture (wlr-screencopy)
+wayland_server::delegate_dispatch!(WaylandServerState: [ZwlrScreencopyManagerV1: ()] => ScreencopyState);
+wayland_server::delegate_dispatch!(WaylandServerState: [ZwlrScreencopyFrameV1: ScreencopyFrameData] => ScreencopyState);
 
 //
 // Direct Hardware Access Protocols - VR headsets, gaming displays, and specialized hardware
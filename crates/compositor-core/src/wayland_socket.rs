@@ -0,0 +1,110 @@
+// Wayland listening sockets
+//
+// The compositor can listen on several sockets, each with its own location,
+// permissions and owning group. Clients connecting through a restricted
+// socket, e.g. sandboxed applications, don't see privileged globals. The
+// compositor does not export WAYLAND_DISPLAY into its own environment; the
+// clients it launches itself get the first socket through `client_command`.
+
+use compositor_utils::prelude::*;
+use config::WaylandSocketConfig;
+use nix::sys::stat::{fchmodat, umask, FchmodatFlags, Mode};
+use nix::unistd::{chown, Group};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use wayland_server::{BindError, ListeningSocket};
+
+/// Numbered socket names tried when no name is configured
+const AUTO_SOCKET_NUMBERS: std::ops::Range<usize> = 1..33;
+
+/// A bound listening socket
+#[derive(Debug)]
+pub struct BoundSocket {
+    pub socket: ListeningSocket,
+    /// Value for WAYLAND_DISPLAY: the name in $XDG_RUNTIME_DIR, or an absolute path
+    pub display: String,
+    pub restricted: bool,
+}
+
+/// Bind a socket with the configured location and permissions
+pub fn bind(config: &WaylandSocketConfig) -> Result<BoundSocket> {
+    let directory = config
+        .directory
+        .clone()
+        .or_else(|| std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from))
+        .ok_or_else(|| CompositorError::wayland("XDG_RUNTIME_DIR is not set and no socket directory is configured"))?;
+
+    // Create the socket accessible to us only, so no one else can connect
+    // before its configured mode and group are set. The umask is process
+    // wide, but sockets are bound at startup before other threads create files.
+    let umask_before = umask(Mode::from_bits_truncate(0o177));
+    let bound = match &config.name {
+        Some(name) => {
+            let path = directory.join(name);
+            bind_path(&path).map(|socket| (socket, path))
+        }
+        None => bind_auto(&directory),
+    };
+    umask(umask_before);
+    let (socket, path) = bound?;
+    set_permissions(&path, config, umask_before)?;
+
+    // Clients resolve bare names against XDG_RUNTIME_DIR
+    let display = match (&config.directory, path.file_name()) {
+        (None, Some(name)) => name.to_string_lossy().into_owned(),
+        _ => path.to_string_lossy().into_owned(),
+    };
+    info!("Listening on Wayland socket {}{}", path.display(), if config.restricted { " (restricted)" } else { "" });
+    Ok(BoundSocket {
+        socket,
+        display,
+        restricted: config.restricted,
+    })
+}
+
+/// Command for a client the compositor launches, connected to `wayland_display`
+pub fn client_command(program: &str, wayland_display: Option<&str>) -> Command {
+    let mut command = Command::new(program);
+    if let Some(wayland_display) = wayland_display {
+        command.env("WAYLAND_DISPLAY", wayland_display);
+    }
+    command
+}
+
+fn bind_path(path: &Path) -> Result<ListeningSocket> {
+    ListeningSocket::bind_absolute(path.to_path_buf())
+        .map_err(|e| CompositorError::wayland(format!("Failed to bind socket {}: {}", path.display(), e)))
+}
+
+/// Bind the first free wayland-N socket in a directory
+fn bind_auto(directory: &Path) -> Result<(ListeningSocket, PathBuf)> {
+    for number in AUTO_SOCKET_NUMBERS {
+        let path = directory.join(format!("wayland-{}", number));
+        match ListeningSocket::bind_absolute(path.clone()) {
+            Ok(socket) => return Ok((socket, path)),
+            Err(BindError::AlreadyInUse) => continue,
+            Err(e) => {
+                return Err(CompositorError::wayland(format!("Failed to bind socket {}: {}", path.display(), e)));
+            }
+        }
+    }
+    Err(CompositorError::wayland(format!("No free Wayland socket name in {}", directory.display())))
+}
+
+/// Apply the configured group and mode; without a mode, the permissions
+/// the umask in effect before binding would have given
+fn set_permissions(path: &Path, config: &WaylandSocketConfig, umask_before: Mode) -> Result<()> {
+    if let Some(group) = &config.group {
+        let group = Group::from_name(group)
+            .map_err(|e| CompositorError::wayland(format!("Failed to look up group {}: {}", group, e)))?
+            .ok_or_else(|| CompositorError::wayland(format!("Unknown socket group: {}", group)))?;
+        chown(path, None, Some(group.gid))
+            .map_err(|e| CompositorError::wayland(format!("Failed to set group of {}: {}", path.display(), e)))?;
+    }
+    let mode = config
+        .mode
+        .map_or(Mode::from_bits_truncate(0o777) & !umask_before, Mode::from_bits_truncate);
+    fchmodat(None, path, mode, FchmodatFlags::FollowSymlink)
+        .map_err(|e| CompositorError::wayland(format!("Failed to set mode of {}: {}", path.display(), e)))?;
+    Ok(())
+}
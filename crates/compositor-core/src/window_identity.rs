@@ -0,0 +1,192 @@
+// Window title and app ID tracking
+//
+// Clients may change an xdg_toplevel's title and app ID at any time, e.g. a
+// browser retitling its window for the active tab. The current values are
+// stored on the Window element together with its ext-foreign-toplevel-list
+// handle, and every change is forwarded to foreign toplevel list clients and
+// to IPC subscribers so taskbars stay in sync. IPC subscribers also learn
+// which window is active and which windows ask for attention, and IPC and
+// D-Bus clients can list the windows as of the latest event, along with the
+// output and workspace each one lives on.
+
+use crate::workspace::WorkspaceManager;
+use ipc::protocol::{WindowEvent, WindowSummary};
+use smithay::desktop::Window;
+use smithay::reexports::wayland_server::Resource;
+use smithay::wayland::foreign_toplevel_list::{ForeignToplevelHandle, ForeignToplevelListHandler, ForeignToplevelListState};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::{broadcast, watch};
+
+/// Window events buffered per IPC subscriber before it starts lagging
+const EVENT_CAPACITY: usize = 64;
+
+/// Title, app ID and foreign toplevel handle, stored in a window's user data
+#[derive(Debug)]
+struct WindowIdentity {
+    title: String,
+    app_id: String,
+    handle: ForeignToplevelHandle,
+}
+
+/// What a title or app ID update changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IdentityChange {
+    pub title: bool,
+    pub app_id: bool,
+}
+
+/// Publishes window titles and app IDs
+#[derive(Debug)]
+pub struct WindowIdentities {
+    events: broadcast::Sender<WindowEvent>,
+    /// Mapped windows, for IPC to list
+    windows: watch::Sender<Vec<WindowSummary>>,
+}
+
+impl WindowIdentities {
+    /// Create a tracker with no subscribers
+    pub fn new() -> Self {
+        Self {
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            windows: watch::channel(Vec::new()).0,
+        }
+    }
+
+    /// Channel for IPC to subscribe to window events
+    pub fn sender(&self) -> broadcast::Sender<WindowEvent> {
+        self.events.clone()
+    }
+
+    /// Channel for IPC to list the mapped windows
+    pub fn windows_receiver(&self) -> watch::Receiver<Vec<WindowSummary>> {
+        self.windows.subscribe()
+    }
+
+    /// Announce a newly mapped window, before its title and app ID are known
+    pub fn window_mapped<D: ForeignToplevelListHandler>(&self, window: &Window, list: &mut ForeignToplevelListState) {
+        let Some(window_id) = window_id(window) else { return };
+        let handle = list.new_toplevel::<D>("", "");
+        window.user_data().insert_if_missing_threadsafe(|| {
+            Mutex::new(WindowIdentity { title: String::new(), app_id: String::new(), handle })
+        });
+        self.publish(WindowEvent::Opened { window_id, title: String::new(), app_id: String::new() });
+    }
+
+    /// Store a window's current title and app ID, announcing what changed
+    pub fn update(&self, window: &Window, title: &str, app_id: &str) -> IdentityChange {
+        let (Some(window_id), Some(identity)) = (window_id(window), window.user_data().get::<Mutex<WindowIdentity>>())
+        else {
+            return IdentityChange::default();
+        };
+        let mut identity = identity.lock().unwrap();
+        let change = IdentityChange { title: identity.title != title, app_id: identity.app_id != app_id };
+        if change.title {
+            identity.title = title.to_string();
+            identity.handle.send_title(title);
+            self.publish(WindowEvent::TitleChanged { window_id, title: title.to_string() });
+        }
+        if change.app_id {
+            identity.app_id = app_id.to_string();
+            identity.handle.send_app_id(app_id);
+            self.publish(WindowEvent::AppIdChanged { window_id, app_id: app_id.to_string() });
+        }
+        if change != IdentityChange::default() {
+            identity.handle.send_done();
+        }
+        change
+    }
+
+    /// Announce that a window gained or lost keyboard focus
+    ///
+    /// ext-foreign-toplevel-list carries no window state, so only IPC
+    /// subscribers learn about it.
+    // TODO: Send the activated state to wlr-foreign-toplevel-management
+    // clients once that protocol is implemented
+    pub fn window_activated(&self, window: &Window, activated: bool) {
+        let Some(window_id) = window_id(window) else { return };
+        self.publish(if activated {
+            WindowEvent::Activated { window_id }
+        } else {
+            WindowEvent::Deactivated { window_id }
+        });
+    }
+
+    /// Announce that a window asked for attention or no longer needs it,
+    /// so taskbars can highlight its entry
+    pub fn window_urgency(&self, window: &Window, urgent: bool) {
+        let Some(window_id) = window_id(window) else { return };
+        self.publish(if urgent {
+            WindowEvent::Urgent { window_id }
+        } else {
+            WindowEvent::UrgencyCleared { window_id }
+        });
+    }
+
+    /// Announce that a window was destroyed
+    pub fn window_closed(&self, window: &Window, list: &mut ForeignToplevelListState) {
+        let Some(window_id) = window_id(window) else { return };
+        if let Some(identity) = window.user_data().get::<Mutex<WindowIdentity>>() {
+            list.remove_toplevel(&identity.lock().unwrap().handle);
+        }
+        self.publish(WindowEvent::Closed { window_id });
+    }
+
+    /// Record the output and workspace of each window in the window list
+    pub fn update_placements(&self, workspaces: &WorkspaceManager) {
+        let placements: HashMap<u32, (&str, usize)> = workspaces
+            .placements()
+            .map(|(window, placement)| (window.protocol_id(), (placement.output.as_str(), placement.workspace)))
+            .collect();
+        self.windows.send_if_modified(|windows| {
+            let mut modified = false;
+            for window in windows.iter_mut() {
+                let (output, workspace) = placements
+                    .get(&window.window_id)
+                    .map_or((None, None), |(output, workspace)| (Some(output.to_string()), Some(*workspace)));
+                if window.output != output || window.workspace != workspace {
+                    window.output = output;
+                    window.workspace = workspace;
+                    modified = true;
+                }
+            }
+            modified
+        });
+    }
+
+    /// Send an event to IPC subscribers, if there are any, and update the
+    /// window list
+    fn publish(&self, event: WindowEvent) {
+        self.windows.send_modify(|windows| event.apply(windows));
+        let _ = self.events.send(event);
+    }
+}
+
+impl Default for WindowIdentities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Title of a window, empty until the client sets one
+pub fn title(window: &Window) -> String {
+    window
+        .user_data()
+        .get::<Mutex<WindowIdentity>>()
+        .map(|identity| identity.lock().unwrap().title.clone())
+        .unwrap_or_default()
+}
+
+/// App ID of a window, empty until the client sets one
+pub fn app_id(window: &Window) -> String {
+    window
+        .user_data()
+        .get::<Mutex<WindowIdentity>>()
+        .map(|identity| identity.lock().unwrap().app_id.clone())
+        .unwrap_or_default()
+}
+
+/// ID of a window in IPC messages: the protocol ID of its wl_surface
+fn window_id(window: &Window) -> Option<u32> {
+    Some(window.toplevel()?.wl_surface().id().protocol_id())
+}
@@ -0,0 +1,116 @@
+// Atomic multi-window changes from IPC
+//
+// Scripts can move, resize and reassign several windows in one IPC batch.
+// A batch is checked as a whole before anything changes and applied between
+// frames. Resized windows are configured first; the new positions and
+// workspaces of every window in the batch are applied together once each
+// resized window has committed a buffer of its new size, or when clients take
+// too long, so the layout never shows up half changed.
+
+use compositor_utils::prelude::*;
+use ipc::protocol::WindowOperation;
+use smithay::reexports::wayland_server::backend::ObjectId;
+use smithay::utils::Serial;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Longest a batch waits for resized windows before it is applied anyway
+pub const TRANSACTION_TIMEOUT: Duration = Duration::from_millis(150);
+
+/// Queue of window batches waiting to be applied
+#[derive(Debug)]
+pub struct TransactionQueue {
+    sender: mpsc::UnboundedSender<Vec<WindowOperation>>,
+    receiver: mpsc::UnboundedReceiver<Vec<WindowOperation>>,
+}
+
+impl TransactionQueue {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self { sender, receiver }
+    }
+
+    /// Channel for IPC to submit batches
+    pub fn sender(&self) -> mpsc::UnboundedSender<Vec<WindowOperation>> {
+        self.sender.clone()
+    }
+
+    /// Take the oldest batch; batches are applied one at a time in order
+    pub fn pop(&mut self) -> Option<Vec<WindowOperation>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Default for TransactionQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A batch waiting for its resized windows to catch up
+#[derive(Debug)]
+pub struct PendingTransaction {
+    /// Operations applied once every window is ready
+    operations: Vec<WindowOperation>,
+    /// Configure serial each resized window has to acknowledge
+    configures: HashMap<ObjectId, Serial>,
+    /// Windows that acknowledged their configure and wait for the commit
+    acked: HashSet<ObjectId>,
+    deadline: Instant,
+}
+
+impl PendingTransaction {
+    /// Wait until `now + TRANSACTION_TIMEOUT` at the latest before applying `operations`
+    pub fn new(operations: Vec<WindowOperation>, now: Instant) -> Self {
+        Self {
+            operations,
+            configures: HashMap::new(),
+            acked: HashSet::new(),
+            deadline: now + TRANSACTION_TIMEOUT,
+        }
+    }
+
+    /// Wait for a window to acknowledge and commit a configure
+    pub fn expect_configure(&mut self, surface: ObjectId, serial: Serial) {
+        self.configures.insert(surface, serial);
+    }
+
+    /// Note a configure acknowledgement
+    pub fn configure_acked(&mut self, surface: &ObjectId, serial: Serial) {
+        if self.configures.get(surface).is_some_and(|expected| serial.is_no_older_than(expected)) {
+            self.acked.insert(surface.clone());
+        }
+    }
+
+    /// Note a commit; a commit after the acknowledgement carries the new size
+    pub fn surface_committed(&mut self, surface: &ObjectId) {
+        if self.acked.remove(surface) {
+            self.configures.remove(surface);
+        }
+    }
+
+    /// Stop waiting for a destroyed window
+    pub fn surface_destroyed(&mut self, surface: &ObjectId) {
+        self.configures.remove(surface);
+        self.acked.remove(surface);
+    }
+
+    /// Whether the batch can be applied
+    pub fn is_ready(&self, now: Instant) -> bool {
+        if self.configures.is_empty() {
+            return true;
+        }
+        if now >= self.deadline {
+            warn!("{} windows did not resize in time, applying window batch anyway", self.configures.len());
+            return true;
+        }
+        false
+    }
+
+    /// Operations to apply now that the batch is ready
+    pub fn into_operations(self) -> Vec<WindowOperation> {
+        self.operations
+    }
+}
@@ -0,0 +1,239 @@
+// Workspace management
+//
+// Each output has its own set of workspaces with one active at a time.
+// Windows belong to a workspace on an output, except sticky windows which are
+// shown on every workspace of their output. Layout and the window switcher
+// query visibility through this module so sticky windows are handled the
+// same way everywhere.
+
+use compositor_utils::prelude::*;
+use config::WindowRule;
+use std::collections::HashMap;
+use wayland_server::backend::ObjectId;
+
+/// Index of a workspace on an output
+pub type WorkspaceIndex = usize;
+
+/// Number of workspaces created for each output
+pub const DEFAULT_WORKSPACE_COUNT: usize = 4;
+
+/// Where a window lives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowPlacement {
+    pub output: String,
+    pub workspace: WorkspaceIndex,
+    /// Shown on every workspace of `output`
+    pub sticky: bool,
+    /// App ID window rules were last applied for
+    app_id: Option<String>,
+}
+
+/// Workspaces of a single output
+#[derive(Debug, Clone)]
+struct OutputWorkspaces {
+    count: usize,
+    active: WorkspaceIndex,
+}
+
+/// Workspace state for all outputs
+#[derive(Debug, Default)]
+pub struct WorkspaceManager {
+    outputs: HashMap<String, OutputWorkspaces>,
+    windows: HashMap<ObjectId, WindowPlacement>,
+    rules: Vec<WindowRule>,
+    /// Workspace switching is turned off, e.g. in kiosk mode
+    locked: bool,
+}
+
+impl WorkspaceManager {
+    /// Create a workspace manager with no outputs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the window rules applied when a window's app ID becomes known
+    pub fn set_rules(&mut self, rules: Vec<WindowRule>) {
+        self.rules = rules;
+    }
+
+    /// Turn workspace switching off or back on
+    pub fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+    }
+
+    /// Register an output with its workspaces
+    pub fn add_output(&mut self, output: impl Into<String>, count: usize) {
+        let output = output.into();
+        debug!("Adding {} workspaces for output {}", count, output);
+        self.outputs.insert(output, OutputWorkspaces { count: count.max(1), active: 0 });
+    }
+
+    /// Active workspace of an output
+    pub fn active_workspace(&self, output: &str) -> Option<WorkspaceIndex> {
+        self.outputs.get(output).map(|workspaces| workspaces.active)
+    }
+
+    /// Number of workspaces of an output
+    pub fn workspace_count(&self, output: &str) -> Option<usize> {
+        self.outputs.get(output).map(|workspaces| workspaces.count)
+    }
+
+    /// Switch the active workspace of an output
+    pub fn switch_to(&mut self, output: &str, workspace: WorkspaceIndex) -> Result<()> {
+        if self.locked {
+            return Err(CompositorError::runtime("Workspace switching is disabled"));
+        }
+        let workspaces = self
+            .outputs
+            .get_mut(output)
+            .ok_or_else(|| CompositorError::runtime(format!("Unknown output: {}", output)))?;
+        if workspace >= workspaces.count {
+            return Err(CompositorError::runtime(format!(
+                "Workspace {} does not exist on output {}", workspace, output
+            )));
+        }
+        workspaces.active = workspace;
+        info!("Switched output {} to workspace {}", output, workspace);
+        Ok(())
+    }
+
+    /// Place a new window on the active workspace of an output
+    pub fn add_window(&mut self, window: ObjectId, output: &str, sticky: bool) {
+        let workspace = self.active_workspace(output).unwrap_or(0);
+        self.windows.insert(window, WindowPlacement {
+            output: output.to_string(),
+            workspace,
+            sticky,
+            app_id: None,
+        });
+    }
+
+    /// Apply window rules for a window's app ID; returns whether the app ID changed
+    ///
+    /// Rules are only applied when the app ID changes, so a sticky state
+    /// toggled by the user is not overridden on every commit. Later matching
+    /// rules take precedence.
+    pub fn apply_rules(&mut self, window: &ObjectId, app_id: &str) -> bool {
+        let Some(placement) = self.windows.get_mut(window) else {
+            return false;
+        };
+        if placement.app_id.as_deref() == Some(app_id) {
+            return false;
+        }
+        placement.app_id = Some(app_id.to_string());
+
+        let sticky = self
+            .rules
+            .iter()
+            .rev()
+            .filter(|rule| rule.matches(app_id))
+            .find_map(|rule| rule.sticky);
+        if let Some(sticky) = sticky {
+            self.set_sticky(window, sticky);
+        }
+        true
+    }
+
+    /// Forget a destroyed window
+    pub fn remove_window(&mut self, window: &ObjectId) {
+        self.windows.remove(window);
+    }
+
+    /// Placement of a window
+    pub fn placement(&self, window: &ObjectId) -> Option<&WindowPlacement> {
+        self.windows.get(window)
+    }
+
+    /// All placed windows and where they live
+    pub fn placements(&self) -> impl Iterator<Item = (&ObjectId, &WindowPlacement)> {
+        self.windows.iter()
+    }
+
+    /// Move a window to another workspace on its output
+    pub fn move_window(&mut self, window: &ObjectId, workspace: WorkspaceIndex) {
+        if let Some(placement) = self.windows.get_mut(window) {
+            placement.workspace = workspace;
+        }
+    }
+
+    /// Put a window on a workspace of another output, e.g. when restoring a layout
+    ///
+    /// Returns false if the output is unknown; the workspace is clamped to
+    /// the workspaces the output has.
+    pub fn place_window(&mut self, window: &ObjectId, output: &str, workspace: WorkspaceIndex, sticky: bool) -> bool {
+        let Some(count) = self.outputs.get(output).map(|workspaces| workspaces.count) else {
+            return false;
+        };
+        let Some(placement) = self.windows.get_mut(window) else {
+            return false;
+        };
+        placement.output = output.to_string();
+        placement.workspace = workspace.min(count - 1);
+        placement.sticky = sticky;
+        true
+    }
+
+    /// Put all windows of an output on the same workspaces of another, e.g.
+    /// when the output turns off
+    pub fn move_output_windows(&mut self, from: &str, to: &str) {
+        let windows: Vec<(ObjectId, WorkspaceIndex, bool)> = self
+            .windows
+            .iter()
+            .filter(|(_, placement)| placement.output == from)
+            .map(|(window, placement)| (window.clone(), placement.workspace, placement.sticky))
+            .collect();
+        for (window, workspace, sticky) in windows {
+            self.place_window(&window, to, workspace, sticky);
+        }
+    }
+
+    /// Make a window sticky or not
+    ///
+    /// A window that stops being sticky stays on the workspace currently
+    /// active on its output, where the user is looking at it.
+    pub fn set_sticky(&mut self, window: &ObjectId, sticky: bool) {
+        let active = self
+            .windows
+            .get(window)
+            .and_then(|placement| self.active_workspace(&placement.output));
+        if let Some(placement) = self.windows.get_mut(window) {
+            if placement.sticky && !sticky {
+                placement.workspace = active.unwrap_or(placement.workspace);
+            }
+            placement.sticky = sticky;
+            debug!("Window {:?} sticky: {}", window, sticky);
+        }
+    }
+
+    /// Toggle whether a window is sticky, returning the new state
+    pub fn toggle_sticky(&mut self, window: &ObjectId) -> bool {
+        let sticky = !self.is_sticky(window);
+        self.set_sticky(window, sticky);
+        sticky
+    }
+
+    /// Whether a window is sticky
+    pub fn is_sticky(&self, window: &ObjectId) -> bool {
+        self.windows.get(window).is_some_and(|placement| placement.sticky)
+    }
+
+    /// Whether a window should be shown right now
+    pub fn is_visible(&self, window: &ObjectId) -> bool {
+        self.windows.get(window).is_some_and(|placement| {
+            placement.sticky || self.active_workspace(&placement.output) == Some(placement.workspace)
+        })
+    }
+
+    /// Windows shown on a given workspace of an output, including sticky ones
+    ///
+    /// Used by the layout engine and window switcher.
+    pub fn windows_on(&self, output: &str, workspace: WorkspaceIndex) -> Vec<ObjectId> {
+        self.windows
+            .iter()
+            .filter(|(_, placement)| {
+                placement.output == output && (placement.sticky || placement.workspace == workspace)
+            })
+            .map(|(window, _)| window.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,244 @@
+// Per-workspace wallpapers and accent colors
+//
+// Every workspace can show its own wallpaper and override the theme accent
+// color, as configured in the [workspaces] section. Switching workspaces
+// cross-fades each output from the old wallpaper and accent to the new ones
+// instead of cutting over, using the same eased progress as the other
+// compositor animations. The cross-fade steps at the workspace animation
+// rate and frames in between interpolate.
+//
+// Wallpapers are PNG files, decoded once when first shown and stretched over
+// their output below every surface.
+
+use compositor_utils::animation_tick::{Interpolated, Lerp};
+use compositor_utils::math::Rect;
+use compositor_utils::prelude::*;
+use config::WorkspacesConfig;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use vulkan_renderer::{UiFilter, UiImage, UiPrimitive};
+
+/// Wallpaper and accent color of a workspace
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspaceAppearance {
+    pub wallpaper: Option<PathBuf>,
+    pub accent_color: [f32; 4],
+}
+
+/// Wallpapers to draw on an output, mid cross-fade or settled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WallpaperBlend<'a> {
+    /// Wallpaper of the previous workspace, fading out
+    pub from: Option<&'a Path>,
+    /// Wallpaper of the active workspace
+    pub to: Option<&'a Path>,
+    /// Opacity of `to` over `from`, 1.0 once the cross-fade has finished
+    pub mix: f32,
+}
+
+/// Appearance of one output and its running cross-fade
+#[derive(Debug, Clone)]
+struct OutputAppearance {
+    workspace: usize,
+    current: WorkspaceAppearance,
+    /// Appearance faded from and when the fade started
+    fade: Option<(WorkspaceAppearance, Instant)>,
+    /// Cross-fade progress of the last two animation steps
+    mix: Interpolated<f32>,
+}
+
+/// Wallpapers and accent colors of the active workspace of every output
+#[derive(Debug)]
+pub struct WorkspaceThemes {
+    config: WorkspacesConfig,
+    /// Theme accent, used by workspaces without an override
+    default_accent: [f32; 4],
+    outputs: HashMap<String, OutputAppearance>,
+    /// Decoded wallpapers by path, `None` where the file could not be read
+    images: HashMap<PathBuf, Option<Arc<UiImage>>>,
+}
+
+impl WorkspaceThemes {
+    /// Create themes from the workspaces configuration and the theme accent
+    pub fn new(config: WorkspacesConfig, default_accent: [f32; 4]) -> Self {
+        Self {
+            config,
+            default_accent,
+            outputs: HashMap::new(),
+            images: HashMap::new(),
+        }
+    }
+
+    /// Apply a new configuration without fading
+    pub fn set_config(&mut self, config: WorkspacesConfig, default_accent: [f32; 4]) {
+        self.config = config;
+        self.default_accent = default_accent;
+        // Wallpaper files may have changed along with the configuration
+        self.images.clear();
+        let names: Vec<(String, usize)> = self
+            .outputs
+            .iter()
+            .map(|(name, output)| (name.clone(), output.workspace))
+            .collect();
+        for (name, workspace) in names {
+            let current = self.resolve(&name, workspace);
+            if let Some(output) = self.outputs.get_mut(&name) {
+                output.current = current;
+                output.fade = None;
+                output.mix.set(1.0);
+            }
+        }
+    }
+
+    /// Show the appearance of an output's active workspace right away
+    pub fn add_output(&mut self, output: &str, workspace: usize) {
+        let current = self.resolve(output, workspace);
+        self.outputs.insert(
+            output.to_string(),
+            OutputAppearance { workspace, current, fade: None, mix: Interpolated::new(1.0) },
+        );
+    }
+
+    /// Forget a removed output
+    pub fn remove_output(&mut self, output: &str) {
+        self.outputs.remove(output);
+    }
+
+    /// Cross-fade an output to the appearance of its new active workspace
+    pub fn workspace_switched(&mut self, output: &str, workspace: usize, now: Instant) {
+        let target = self.resolve(output, workspace);
+        let fade = !self.duration().is_zero();
+        let Some(state) = self.outputs.get_mut(output) else {
+            self.add_output(output, workspace);
+            return;
+        };
+        state.workspace = workspace;
+        if state.current == target {
+            return;
+        }
+        let previous = std::mem::replace(&mut state.current, target);
+        state.fade = fade.then_some((previous, now));
+        state.mix.set(if fade { 0.0 } else { 1.0 });
+    }
+
+    /// Step cross-fades to `now` and finish completed ones; returns whether
+    /// any is still running
+    pub fn advance(&mut self, now: Instant) -> bool {
+        let duration = self.duration();
+        let progress: Vec<(String, f32)> = self
+            .outputs
+            .iter()
+            .map(|(name, output)| {
+                let mix = output.fade.as_ref().map_or(1.0, |(_, start)| self.progress(*start, now));
+                (name.clone(), mix)
+            })
+            .collect();
+        for (name, mix) in progress {
+            let Some(output) = self.outputs.get_mut(&name) else {
+                continue;
+            };
+            output.mix.step(mix);
+            // Keep fading until frames stop interpolating towards the end
+            let elapsed = output.fade.as_ref().is_some_and(|(_, start)| now.duration_since(*start) >= duration);
+            if elapsed && output.mix.is_settled() {
+                output.fade = None;
+            }
+        }
+        self.is_animating()
+    }
+
+    /// Whether any output is cross-fading
+    pub fn is_animating(&self) -> bool {
+        self.outputs.values().any(|output| output.fade.is_some())
+    }
+
+    /// Accent color of an output, blended during a cross-fade `alpha` of the
+    /// way from the previous animation step to the last
+    pub fn accent_color(&self, output: &str, alpha: f32) -> [f32; 4] {
+        let Some(state) = self.outputs.get(output) else {
+            return self.default_accent;
+        };
+        let to = state.current.accent_color;
+        let Some((previous, _)) = &state.fade else {
+            return to;
+        };
+        previous.accent_color.lerp(to, state.mix.value(alpha))
+    }
+
+    /// Wallpapers to draw on an output, `alpha` of the way from the previous
+    /// animation step to the last
+    pub fn wallpaper(&self, output: &str, alpha: f32) -> Option<WallpaperBlend<'_>> {
+        let state = self.outputs.get(output)?;
+        let to = state.current.wallpaper.as_deref();
+        Some(match &state.fade {
+            Some((previous, _)) => WallpaperBlend {
+                from: previous.wallpaper.as_deref(),
+                to,
+                mix: state.mix.value(alpha),
+            },
+            None => WallpaperBlend { from: None, to, mix: 1.0 },
+        })
+    }
+
+    /// Wallpapers of an output as primitives covering `rect`, back to front,
+    /// cross-faded `alpha` of the way from the previous animation step to the last
+    pub fn wallpaper_primitives(&mut self, output: &str, rect: Rect, alpha: f32) -> Vec<UiPrimitive> {
+        let Some(blend) = self.wallpaper(output, alpha) else {
+            return Vec::new();
+        };
+        let (from, to, mix) = (blend.from.map(Path::to_path_buf), blend.to.map(Path::to_path_buf), blend.mix);
+        let from = from.and_then(|path| self.image(path));
+        let to = to.and_then(|path| self.image(path));
+        // The previous wallpaper stays opaque below the new one and fades
+        // out only where no new wallpaper covers it
+        let from_opacity = if to.is_some() { 1.0 } else { 1.0 - mix };
+        [(from, from_opacity), (to, mix)]
+            .into_iter()
+            .filter(|(_, opacity)| *opacity > 0.0)
+            .filter_map(|(image, opacity)| Some(UiPrimitive::Image { rect, image: image?, tint: [1.0, 1.0, 1.0, opacity] }))
+            .collect()
+    }
+
+    /// Decoded wallpaper at `path`, read on first use
+    fn image(&mut self, path: PathBuf) -> Option<Arc<UiImage>> {
+        self.images
+            .entry(path)
+            .or_insert_with_key(|path| {
+                let decoded = std::fs::read(path)
+                    .map_err(CompositorError::from)
+                    .and_then(|png| compositor_utils::png::decode_rgba(&png))
+                    .and_then(|image| UiImage::new(image.width, image.height, image.rgba, UiFilter::Linear));
+                decoded.map_err(|e| warn!("Cannot load wallpaper {}: {}", path.display(), e)).ok()
+            })
+            .clone()
+    }
+
+    fn resolve(&self, output: &str, workspace: usize) -> WorkspaceAppearance {
+        WorkspaceAppearance {
+            wallpaper: self.config.wallpaper_for(output, workspace).map(PathBuf::from),
+            accent_color: self.config.accent_color_for(output, workspace).unwrap_or(self.default_accent),
+        }
+    }
+
+    fn duration(&self) -> Duration {
+        Duration::from_millis(self.config.crossfade_duration)
+    }
+
+    /// Eased cross-fade progress from 0.0 to 1.0
+    fn progress(&self, start: Instant, now: Instant) -> f32 {
+        let duration = self.duration();
+        if duration.is_zero() {
+            return 1.0;
+        }
+        let t = (now.duration_since(start).as_secs_f32() / duration.as_secs_f32()).min(1.0);
+        1.0 - (1.0 - t).powi(3)
+    }
+}
+
+impl Default for WorkspaceThemes {
+    fn default() -> Self {
+        Self::new(WorkspacesConfig::default(), config::ThemeConfig::default().color(config::ColorToken::Accent))
+    }
+}
@@ -0,0 +1,182 @@
+// Per-window content magnification
+//
+// Enlarges a single window's content within its own geometry, independent of
+// any full-screen zoom. Like a viewporter source crop, the compositor samples
+// a sub-rectangle of the window's buffer and scales it up to the window's
+// logical size; the visible region can be panned with the keyboard or by
+// following the pointer.
+
+use compositor_utils::math::Rect;
+use compositor_utils::prelude::*;
+use std::collections::HashMap;
+use wayland_server::backend::ObjectId;
+
+/// Smallest magnification (no zoom)
+pub const MIN_ZOOM: f32 = 1.0;
+
+/// Largest supported magnification
+pub const MAX_ZOOM: f32 = 8.0;
+
+/// Multiplicative step used by zoom in/out
+pub const ZOOM_STEP: f32 = 1.25;
+
+/// Fraction of the visible region moved by one keyboard pan step
+pub const PAN_STEP_FRACTION: f32 = 0.1;
+
+/// Magnification state of a single window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowZoom {
+    /// Magnification factor (1.0 = unzoomed)
+    pub scale: f32,
+    /// Center of the visible region in window-local logical coordinates,
+    /// normalized to 0.0-1.0 across the window
+    pub center: Vec2,
+}
+
+impl Default for WindowZoom {
+    fn default() -> Self {
+        Self {
+            scale: MIN_ZOOM,
+            center: Vec2::new(0.5, 0.5),
+        }
+    }
+}
+
+impl WindowZoom {
+    /// Whether this window is magnified at all
+    pub fn is_zoomed(&self) -> bool {
+        self.scale > MIN_ZOOM
+    }
+
+    /// Set the magnification factor, keeping the visible region inside the window
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.clamp_center();
+    }
+
+    /// Pan by a delta in window-local logical pixels
+    pub fn pan_by(&mut self, delta: Vec2, window_size: Vec2) {
+        if window_size.x <= 0.0 || window_size.y <= 0.0 {
+            return;
+        }
+        self.center += delta / window_size;
+        self.clamp_center();
+    }
+
+    /// Pan one keyboard step in the given direction (components -1, 0 or 1)
+    pub fn pan_step(&mut self, direction: Vec2) {
+        self.center += direction * PAN_STEP_FRACTION / self.scale;
+        self.clamp_center();
+    }
+
+    /// Follow the pointer: map a pointer position over the window to the
+    /// matching point of the content, so moving to an edge reveals that edge
+    pub fn follow_pointer(&mut self, pointer: Vec2, window_size: Vec2) {
+        if window_size.x <= 0.0 || window_size.y <= 0.0 {
+            return;
+        }
+        self.center = (pointer / window_size).clamp(Vec2::ZERO, Vec2::ONE);
+        self.clamp_center();
+    }
+
+    /// Visible region of the content in window-local logical coordinates
+    ///
+    /// This is the source crop that gets scaled up to the full window size.
+    pub fn source_rect(&self, window_size: Vec2) -> Rect {
+        let visible = window_size / self.scale;
+        let origin = self.center * window_size - visible * 0.5;
+        Rect::new(origin.x, origin.y, visible.x, visible.y)
+    }
+
+    /// Texture coordinate offset and scale for sampling the visible region
+    pub fn texture_transform(&self) -> ([f32; 2], [f32; 2]) {
+        let extent = 1.0 / self.scale;
+        let offset = self.center - Vec2::splat(extent * 0.5);
+        (offset.to_array(), [extent, extent])
+    }
+
+    /// Map a point on the window (as displayed) back to content coordinates,
+    /// so input can be delivered where the user sees it
+    pub fn to_content(&self, point: Vec2, window_size: Vec2) -> Vec2 {
+        let source = self.source_rect(window_size);
+        Vec2::new(source.x, source.y) + point / self.scale
+    }
+
+    /// Keep the visible region within the window bounds
+    fn clamp_center(&mut self) {
+        let half = 0.5 / self.scale;
+        self.center = self.center.clamp(Vec2::splat(half), Vec2::splat(1.0 - half));
+    }
+}
+
+/// Magnification state for all windows, keyed by toplevel surface
+#[derive(Debug, Default)]
+pub struct WindowZoomManager {
+    zooms: HashMap<ObjectId, WindowZoom>,
+}
+
+impl WindowZoomManager {
+    /// Create a manager with no magnified windows
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Magnification of a window, if it is zoomed
+    pub fn get(&self, surface: &ObjectId) -> Option<&WindowZoom> {
+        self.zooms.get(surface)
+    }
+
+    /// Zoom a window in by one step
+    pub fn zoom_in(&mut self, surface: ObjectId) -> f32 {
+        let zoom = self.zooms.entry(surface.clone()).or_default();
+        zoom.set_scale(zoom.scale * ZOOM_STEP);
+        let scale = zoom.scale;
+        debug!("Window {:?} zoomed to {:.2}x", surface, scale);
+        scale
+    }
+
+    /// Zoom a window out by one step, forgetting it once back at 1.0x
+    pub fn zoom_out(&mut self, surface: &ObjectId) -> f32 {
+        let Some(zoom) = self.zooms.get_mut(surface) else {
+            return MIN_ZOOM;
+        };
+        zoom.set_scale(zoom.scale / ZOOM_STEP);
+        let scale = zoom.scale;
+        if !zoom.is_zoomed() {
+            self.zooms.remove(surface);
+        }
+        debug!("Window {:?} zoomed to {:.2}x", surface, scale);
+        scale
+    }
+
+    /// Reset a window to its unmagnified size
+    pub fn reset(&mut self, surface: &ObjectId) {
+        self.zooms.remove(surface);
+    }
+
+    /// Pan a zoomed window by one keyboard step
+    pub fn pan_step(&mut self, surface: &ObjectId, direction: Vec2) {
+        if let Some(zoom) = self.zooms.get_mut(surface) {
+            zoom.pan_step(direction);
+        }
+    }
+
+    /// Pan a zoomed window by a pointer drag delta
+    pub fn pan_by(&mut self, surface: &ObjectId, delta: Vec2, window_size: Vec2) {
+        if let Some(zoom) = self.zooms.get_mut(surface) {
+            zoom.pan_by(delta, window_size);
+        }
+    }
+
+    /// Make a zoomed window follow the pointer
+    pub fn follow_pointer(&mut self, surface: &ObjectId, pointer: Vec2, window_size: Vec2) {
+        if let Some(zoom) = self.zooms.get_mut(surface) {
+            zoom.follow_pointer(pointer, window_size);
+        }
+    }
+
+    /// Forget a window that was destroyed
+    pub fn remove_window(&mut self, surface: &ObjectId) {
+        self.zooms.remove(surface);
+    }
+}
@@ -0,0 +1,201 @@
+//! Keybindings
+//!
+//! The `bindings` section maps key combinations to compositor actions:
+//!
+//! ```toml
+//! [bindings]
+//! "Super+Return" = { spawn = ["foot"] }
+//! "Super+Q" = "close_window"
+//! "Super+2" = { workspace = 1 }
+//! "Super+Shift+C" = "none"
+//! ```
+//!
+//! A combination is any number of modifiers (`Super`, `Ctrl`, `Alt`,
+//! `Shift`) and a key, joined with `+`. Keys are XKB keysym names such as
+//! `Return`, `Print` or `F1`, matched on the unshifted key, so `Super+Shift+1`
+//! is written with `1` rather than `!`. Layers merge bindings over the
+//! defaults, see [`crate::layer`]; `none` removes a default binding.
+
+use crate::ConfigError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Modifier keys held in a key combination
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct KeyModifiers {
+    /// Super, the logo key
+    pub logo: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+/// A key combination, e.g. `Super+Shift+Q`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyCombo {
+    pub modifiers: KeyModifiers,
+    /// Keysym name of the key, e.g. `Return` or `q`; single letters are
+    /// lowercase, the unshifted keysym
+    pub key: String,
+}
+
+impl FromStr for KeyCombo {
+    type Err = ConfigError;
+
+    fn from_str(combo: &str) -> Result<Self, Self::Err> {
+        let invalid = |message: String| ConfigError::Validation {
+            message: format!("Invalid key binding \"{}\": {}", combo, message),
+        };
+        let mut parts: Vec<&str> = combo.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|key| !key.is_empty()).ok_or_else(|| invalid("missing key".to_string()))?;
+        if key.contains(char::is_whitespace) {
+            return Err(invalid("key names cannot contain spaces".to_string()));
+        }
+
+        let mut modifiers = KeyModifiers::default();
+        for part in parts {
+            let modifier = match part.to_ascii_lowercase().as_str() {
+                "super" | "logo" | "mod4" | "win" => &mut modifiers.logo,
+                "ctrl" | "control" => &mut modifiers.ctrl,
+                "alt" | "mod1" => &mut modifiers.alt,
+                "shift" => &mut modifiers.shift,
+                _ => return Err(invalid(format!("unknown modifier {}", part))),
+            };
+            if std::mem::replace(modifier, true) {
+                return Err(invalid(format!("{} given twice", part)));
+            }
+        }
+
+        let key = match key.to_ascii_lowercase().as_str() {
+            "enter" => "Return".to_string(),
+            "esc" => "Escape".to_string(),
+            "space" => "space".to_string(),
+            "del" => "Delete".to_string(),
+            _ if key.chars().count() == 1 => key.to_lowercase(),
+            _ => key.to_string(),
+        };
+        Ok(Self { modifiers, key })
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let held = [
+            (self.modifiers.logo, "Super"),
+            (self.modifiers.ctrl, "Ctrl"),
+            (self.modifiers.alt, "Alt"),
+            (self.modifiers.shift, "Shift"),
+        ];
+        for (_, name) in held.iter().filter(|(held, _)| *held) {
+            write!(f, "{}+", name)?;
+        }
+        if self.key.chars().count() == 1 {
+            write!(f, "{}", self.key.to_uppercase())
+        } else {
+            write!(f, "{}", self.key)
+        }
+    }
+}
+
+/// What a keybinding does
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BindingAction {
+    /// Nothing; removes a default binding
+    None,
+    /// Launch a command line, e.g. `["foot"]`
+    Spawn(Vec<String>),
+    /// Ask the active window to close
+    CloseWindow,
+    /// Switch the active output to a workspace, by index starting at 0
+    Workspace(usize),
+    /// Move the active window to a workspace of its output
+    MoveToWorkspace(usize),
+    /// Show the active window on every workspace of its output, or only on
+    /// its own again
+    ToggleSticky,
+    /// Pick a window to force quit
+    KillMode,
+    /// Start or leave the color picker
+    ColorPicker,
+    /// Start or leave drawing annotations over the screen; while drawing,
+    /// Ctrl+Z undoes, Ctrl+S saves them as a PNG next to screenshots and
+    /// Escape clears them and leaves
+    Annotate,
+    /// Screenshot the active window
+    ScreenshotWindow,
+    /// Screenshot the whole output
+    ScreenshotOutput,
+    /// Select a region of the output under the pointer to screenshot
+    ScreenshotRegion,
+    /// Rotate the output under the pointer by a quarter turn clockwise
+    RotateClockwise,
+    /// Rotate the output under the pointer by a quarter turn counter-clockwise
+    RotateCounterClockwise,
+    /// Stop or resume following the accelerometer
+    ToggleRotationLock,
+    /// Open or close the quick settings panel
+    QuickSettings,
+    /// Switch the pointer in use to a flat acceleration profile, e.g. for
+    /// games, or back to its configured one
+    ToggleFlatAccel,
+}
+
+/// Compositor keybindings, keyed by key combination
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BindingsConfig {
+    pub keys: BTreeMap<String, BindingAction>,
+}
+
+impl BindingsConfig {
+    /// Parsed combinations and their actions, leaving out removed bindings
+    ///
+    /// Fails on combinations that do not parse and on conflicts, two
+    /// bindings written differently for the same combination, such as
+    /// `Super+Enter` and `Mod4+Return`.
+    pub fn parse(&self) -> Result<Vec<(KeyCombo, BindingAction)>, ConfigError> {
+        let mut parsed: Vec<(KeyCombo, &str, &BindingAction)> = Vec::with_capacity(self.keys.len());
+        for (written, action) in &self.keys {
+            let combo: KeyCombo = written.parse()?;
+            if let Some((_, other, _)) = parsed.iter().find(|(parsed, _, _)| *parsed == combo) {
+                return Err(ConfigError::Validation {
+                    message: format!("Key bindings \"{}\" and \"{}\" conflict, both are {}", other, written, combo),
+                });
+            }
+            parsed.push((combo, written, action));
+        }
+        Ok(parsed
+            .into_iter()
+            .filter(|(_, _, action)| **action != BindingAction::None)
+            .map(|(combo, _, action)| (combo, action.clone()))
+            .collect())
+    }
+}
+
+impl Default for BindingsConfig {
+    fn default() -> Self {
+        let mut keys = BTreeMap::from([
+            ("Super+Return".to_string(), BindingAction::Spawn(vec!["foot".to_string()])),
+            ("Super+Q".to_string(), BindingAction::CloseWindow),
+            ("Super+Shift+S".to_string(), BindingAction::ToggleSticky),
+            ("Ctrl+Alt+Escape".to_string(), BindingAction::KillMode),
+            ("Super+Shift+C".to_string(), BindingAction::ColorPicker),
+            ("Super+Shift+A".to_string(), BindingAction::Annotate),
+            ("Print".to_string(), BindingAction::ScreenshotOutput),
+            ("Super+Print".to_string(), BindingAction::ScreenshotWindow),
+            ("Shift+Print".to_string(), BindingAction::ScreenshotRegion),
+            ("Super+Ctrl+Right".to_string(), BindingAction::RotateClockwise),
+            ("Super+Ctrl+Left".to_string(), BindingAction::RotateCounterClockwise),
+            ("Super+Ctrl+O".to_string(), BindingAction::ToggleRotationLock),
+            ("Super+Ctrl+S".to_string(), BindingAction::QuickSettings),
+        ]);
+        for workspace in 0..4 {
+            keys.insert(format!("Super+{}", workspace + 1), BindingAction::Workspace(workspace));
+            keys.insert(format!("Super+Shift+{}", workspace + 1), BindingAction::MoveToWorkspace(workspace));
+        }
+        Self { keys }
+    }
+}
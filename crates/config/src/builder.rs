@@ -0,0 +1,53 @@
+//! Building configurations programmatically
+
+use crate::section::ConfigSection;
+use crate::{CompositorConfig, ConfigError, WindowRule};
+
+/// Builds a validated configuration, starting from the defaults
+#[derive(Debug, Clone, Default)]
+pub struct CompositorConfigBuilder {
+    config: CompositorConfig,
+}
+
+impl CompositorConfigBuilder {
+    /// Start from the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from an existing configuration, e.g. one loaded from a file
+    pub fn from_config(config: CompositorConfig) -> Self {
+        Self { config }
+    }
+
+    /// Replace a section
+    pub fn section<S: ConfigSection>(mut self, section: S) -> Self {
+        self.config.set_section(section);
+        self
+    }
+
+    /// Modify a section in place
+    pub fn with_section<S: ConfigSection>(mut self, modify: impl FnOnce(&mut S)) -> Self {
+        modify(self.config.section_mut());
+        self
+    }
+
+    /// Append a window rule; later rules take precedence
+    pub fn window_rule(mut self, rule: WindowRule) -> Self {
+        self.config.window_rules.push(rule);
+        self
+    }
+
+    /// Validate and return the configuration
+    pub fn build(self) -> Result<CompositorConfig, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+impl CompositorConfig {
+    /// Builder starting from the default configuration
+    pub fn builder() -> CompositorConfigBuilder {
+        CompositorConfigBuilder::new()
+    }
+}
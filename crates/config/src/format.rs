@@ -0,0 +1,72 @@
+//! Reading and writing configuration files
+//!
+//! Configurations serialize losslessly: parsing the output of
+//! [`CompositorConfig::to_string_as`] yields the same configuration, in
+//! either format, so tools can load, edit and save a file without dropping
+//! settings they do not know about.
+
+use crate::{CompositorConfig, ConfigError};
+use std::path::{Path, PathBuf};
+
+/// Serialization format of a configuration file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    #[default]
+    Toml,
+    Ron,
+}
+
+impl ConfigFormat {
+    /// Format of a file by its extension; TOML unless it ends in `.ron`
+    pub fn from_path(path: &Path) -> Self {
+        if path.extension() == Some("ron".as_ref()) {
+            Self::Ron
+        } else {
+            Self::Toml
+        }
+    }
+}
+
+/// Default location of the configuration file,
+/// e.g. `~/.config/custom-compositor/config.toml`
+pub fn default_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("/etc"))
+        .join("custom-compositor")
+        .join("config.toml")
+}
+
+impl CompositorConfig {
+    /// Parse a configuration, without environment overrides or validation
+    pub fn parse(content: &str, format: ConfigFormat) -> Result<Self, ConfigError> {
+        Ok(match format {
+            ConfigFormat::Toml => toml::from_str(content)?,
+            ConfigFormat::Ron => ron::from_str(content)?,
+        })
+    }
+
+    /// Serialize the configuration
+    pub fn to_string_as(&self, format: ConfigFormat) -> Result<String, ConfigError> {
+        Ok(match format {
+            ConfigFormat::Toml => toml::to_string_pretty(self)?,
+            ConfigFormat::Ron => ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?,
+        })
+    }
+
+    /// Read a configuration file in the format its extension names, without
+    /// environment overrides or validation
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Self::parse(&std::fs::read_to_string(path)?, ConfigFormat::from_path(path))
+    }
+
+    /// Write a configuration file in the format its extension names,
+    /// creating its directory
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let content = self.to_string_as(ConfigFormat::from_path(path))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
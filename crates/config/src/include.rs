@@ -0,0 +1,110 @@
+//! Splitting the configuration across files
+//!
+//! A TOML configuration file may list further files to merge into it:
+//!
+//! ```toml
+//! include = ["keybindings.toml", "outputs.toml"]
+//! ```
+//!
+//! Paths are relative to the including file, and included files may include
+//! others. Included files are merged in order, then the including file's own
+//! settings on top: tables merge key by key, arrays of tables such as
+//! `window_rules` are appended and any other value is replaced. The merged
+//! configuration is validated as a whole.
+
+use crate::{CompositorConfig, ConfigError, ConfigFormat};
+use std::path::{Path, PathBuf};
+
+/// Configuration loaded from a file and the files it includes
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    pub config: CompositorConfig,
+    /// Canonical paths of every file read, the including file last, for
+    /// hot-reload watching
+    pub files: Vec<PathBuf>,
+}
+
+impl CompositorConfig {
+    /// Read a configuration file and merge in the files it includes, without
+    /// environment overrides or validation
+    ///
+    /// Fails if a file includes itself, directly or through other files.
+    pub fn load_with_includes(path: &Path) -> Result<LoadedConfig, ConfigError> {
+        if ConfigFormat::from_path(path) == ConfigFormat::Ron {
+            let config = Self::load(path)?;
+            if !config.include.is_empty() {
+                return Err(ConfigError::Include {
+                    path: path.to_path_buf(),
+                    message: "includes are only supported in TOML files".to_string(),
+                });
+            }
+            let file = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+            return Ok(LoadedConfig { config, files: vec![file] });
+        }
+
+        let mut files = Vec::new();
+        let (merged, include) = read_merged(path, &mut Vec::new(), &mut files)?;
+        let mut config: Self = toml::Value::Table(merged).try_into()?;
+        config.include = include;
+        Ok(LoadedConfig { config, files })
+    }
+}
+
+/// Read a file merged with its includes, returning its own include list
+pub(crate) fn read_merged(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<(toml::Table, Vec<PathBuf>), ConfigError> {
+    let include_error = |message: String| ConfigError::Include { path: path.to_path_buf(), message };
+    let canonical = path.canonicalize().map_err(|e| include_error(e.to_string()))?;
+    if stack.contains(&canonical) {
+        let cycle: Vec<String> = stack
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|file| file.display().to_string())
+            .collect();
+        return Err(include_error(format!("include cycle: {}", cycle.join(" -> "))));
+    }
+
+    let content = std::fs::read_to_string(&canonical).map_err(|e| include_error(e.to_string()))?;
+    let mut table: toml::Table = toml::from_str(&content)?;
+    let include: Vec<PathBuf> = match table.remove("include") {
+        Some(include) => include
+            .try_into()
+            .map_err(|_| include_error("include must be a list of paths".to_string()))?,
+        None => Vec::new(),
+    };
+
+    stack.push(canonical.clone());
+    let directory = canonical.parent().unwrap_or(Path::new("/"));
+    let mut merged = toml::Table::new();
+    for included in &include {
+        let (included, _) = read_merged(&directory.join(included), stack, files)?;
+        merge(&mut merged, included);
+    }
+    stack.pop();
+
+    merge(&mut merged, table);
+    if !files.contains(&canonical) {
+        files.push(canonical);
+    }
+    Ok((merged, include))
+}
+
+/// Merge `overlay` into `base`
+pub(crate) fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (Some(toml::Value::Array(base)), toml::Value::Array(overlay))
+                if base.iter().chain(&overlay).all(toml::Value::is_table) =>
+            {
+                base.extend(overlay);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Layered configuration
+//!
+//! The configuration is merged from layers, each overriding the ones before
+//! it:
+//!
+//! 1. the system configuration, [`SYSTEM_CONFIG_PATH`]
+//! 2. the user configuration, see [`default_config_path`]
+//! 3. runtime overrides, from the command line and environment variables
+//!
+//! Layers are partial: a file only sets the keys it specifies, on top of the
+//! built-in defaults and the layers before it. Layers merge like included
+//! files, see [`crate::include`], so `window_rules` from the user file are
+//! appended to the system ones; arrays in a layer replace the defaults. RON
+//! files cannot be partial and override every setting.
+
+use crate::include::{merge, read_merged};
+use crate::{default_config_path, CompositorConfig, ConfigError, ConfigFormat, LoadedConfig};
+use std::path::{Path, PathBuf};
+
+/// Location of the system-wide configuration file
+pub const SYSTEM_CONFIG_PATH: &str = "/etc/custom-compositor/config.toml";
+
+/// A source of configuration settings
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigLayer {
+    /// A configuration file and the files it includes; skipped if missing
+    File(PathBuf),
+    /// Settings given at runtime, e.g. on the command line
+    Overrides(toml::Table),
+}
+
+impl ConfigLayer {
+    /// The system configuration file
+    pub fn system() -> Self {
+        Self::File(PathBuf::from(SYSTEM_CONFIG_PATH))
+    }
+
+    /// The user's configuration file
+    pub fn user() -> Self {
+        Self::File(default_config_path())
+    }
+
+    /// Runtime overrides from `key=value` arguments, e.g.
+    /// `display.scale_factor=1.5` or `theme.name=nord`
+    ///
+    /// Values are TOML; anything that does not parse as TOML is a string.
+    pub fn from_args<S: AsRef<str>>(args: impl IntoIterator<Item = S>) -> Result<Self, ConfigError> {
+        let mut overrides = toml::Table::new();
+        for arg in args {
+            let arg = arg.as_ref();
+            let invalid = |message: &str| ConfigError::Override(format!("{}: {}", arg, message));
+            let (key, value) = arg.split_once('=').ok_or_else(|| invalid("expected key=value"))?;
+            let keys: Vec<&str> = key.trim().split('.').collect();
+            if keys.iter().any(|key| key.is_empty()) {
+                return Err(invalid("empty key"));
+            }
+            let value = value.trim();
+            let value = toml::from_str::<toml::Table>(&format!("value = {}", value))
+                .ok()
+                .and_then(|mut table| table.remove("value"))
+                .unwrap_or_else(|| toml::Value::String(value.to_string()));
+
+            // Build the nested tables the dotted key names
+            let (last, parents) = keys.split_last().expect("split yields at least one key");
+            let mut layer = toml::Table::new();
+            layer.insert(last.to_string(), value);
+            for parent in parents.iter().rev() {
+                let mut table = toml::Table::new();
+                table.insert(parent.to_string(), toml::Value::Table(layer));
+                layer = table;
+            }
+            merge(&mut overrides, layer);
+        }
+        Ok(Self::Overrides(overrides))
+    }
+
+    /// Runtime overrides from `COMPOSITOR_*` environment variables
+    pub fn environment() -> Result<Self, ConfigError> {
+        Ok(Self::Overrides(crate::env_overrides()?))
+    }
+
+    /// File of a file layer
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::File(path) => Some(path),
+            Self::Overrides(_) => None,
+        }
+    }
+
+    /// Settings the layer sets
+    pub(crate) fn settings(&self) -> Result<toml::Table, ConfigError> {
+        self.read(&mut Vec::new()).map(|(settings, _)| settings)
+    }
+
+    /// Settings of the layer, recording the files read
+    fn read(&self, files: &mut Vec<PathBuf>) -> Result<(toml::Table, Vec<PathBuf>), ConfigError> {
+        match self {
+            Self::File(path) if !path.exists() => Ok(Default::default()),
+            Self::File(path) if ConfigFormat::from_path(path) == ConfigFormat::Ron => {
+                let loaded = CompositorConfig::load_with_includes(path)?;
+                files.extend(loaded.files);
+                Ok((toml::Table::try_from(&loaded.config)?, Vec::new()))
+            }
+            Self::File(path) => read_merged(path, &mut Vec::new(), files),
+            Self::Overrides(overrides) => Ok((overrides.clone(), Vec::new())),
+        }
+    }
+}
+
+/// The layers of a standard session: system and user configuration files,
+/// then environment variables
+pub fn default_layers() -> Result<Vec<ConfigLayer>, ConfigError> {
+    Ok(vec![ConfigLayer::system(), ConfigLayer::user(), ConfigLayer::environment()?])
+}
+
+impl CompositorConfig {
+    /// Merge configuration layers over the defaults, without validation
+    ///
+    /// `include` is set to the includes of the last file layer, the one
+    /// changes are saved to.
+    pub fn load_layers(layers: &[ConfigLayer]) -> Result<LoadedConfig, ConfigError> {
+        let mut files = Vec::new();
+        let (merged, include) = merge_layers(layers, &mut files)?;
+        let mut config: Self = toml::Value::Table(over_defaults(merged)?).try_into()?;
+        config.include = include;
+        Ok(LoadedConfig { config, files })
+    }
+
+    /// Settings that differ from what `layers` give, as a partial layer that
+    /// turns them into this configuration when merged over them
+    ///
+    /// Settings the layers have but this configuration leaves unset cannot be
+    /// expressed and are left out.
+    pub fn layer_over(&self, layers: &[ConfigLayer]) -> Result<toml::Table, ConfigError> {
+        let (merged, _) = merge_layers(layers, &mut Vec::new())?;
+        let base = over_defaults(merged.clone())?;
+        let mut layer = difference(&base, Some(&merged), toml::Table::try_from(self)?);
+        layer.remove("include");
+        Ok(layer)
+    }
+}
+
+/// Settings of all layers merged, and the includes of the last file layer
+fn merge_layers(layers: &[ConfigLayer], files: &mut Vec<PathBuf>) -> Result<(toml::Table, Vec<PathBuf>), ConfigError> {
+    let mut merged = toml::Table::new();
+    let mut include = Vec::new();
+    for layer in layers {
+        let (settings, layer_include) = layer.read(files)?;
+        merge(&mut merged, settings);
+        if layer.path().is_some() {
+            include = layer_include;
+        }
+    }
+    Ok((merged, include))
+}
+
+/// Complete merged layers with the defaults; arrays replace the defaults
+fn over_defaults(merged: toml::Table) -> Result<toml::Table, ConfigError> {
+    fn replace(base: &mut toml::Table, overlay: toml::Table) {
+        for (key, value) in overlay {
+            match (base.get_mut(&key), value) {
+                (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => replace(base, overlay),
+                (_, value) => {
+                    base.insert(key, value);
+                }
+            }
+        }
+    }
+
+    let mut table = toml::Table::try_from(CompositorConfig::default())?;
+    replace(&mut table, merged);
+    Ok(table)
+}
+
+/// Keys of `target` that differ from `base`
+///
+/// Arrays of tables that the layers in `layered` set and `target` extends
+/// keep only the added tables, as merging appends them.
+fn difference(base: &toml::Table, layered: Option<&toml::Table>, target: toml::Table) -> toml::Table {
+    let mut layer = toml::Table::new();
+    for (key, value) in target {
+        let layered_value = layered.and_then(|layered| layered.get(&key));
+        let value = match (base.get(&key), value) {
+            (Some(base), value) if *base == value => continue,
+            (Some(toml::Value::Table(base)), toml::Value::Table(target)) => {
+                let table = difference(base, layered_value.and_then(toml::Value::as_table), target);
+                if table.is_empty() {
+                    continue;
+                }
+                toml::Value::Table(table)
+            }
+            (Some(toml::Value::Array(base)), toml::Value::Array(target))
+                if layered_value.is_some()
+                    && target.iter().all(toml::Value::is_table)
+                    && target.starts_with(base) =>
+            {
+                toml::Value::Array(target[base.len()..].to_vec())
+            }
+            (_, value) => value,
+        };
+        layer.insert(key, value);
+    }
+    layer
+}
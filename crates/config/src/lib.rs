@@ -3,14 +3,39 @@
 //! This crate provides hot-reloadable configuration management with support for
 //! multiple formats (TOML, RON) and environment-based overrides. It's designed
 //! for 4K displays with DPI-aware defaults and glassmorphism/neomorphism theming.
+//!
+//! External tools such as settings GUIs and provisioning scripts can use it as
+//! a library: [`CompositorConfig::load`] and [`CompositorConfig::save`] read and
+//! write files losslessly, [`CompositorConfig::section`] gives typed access to
+//! each section and [`CompositorConfigBuilder`] assembles validated
+//! configurations. Large configurations can be split across files with
+//! `include`, see [`include`], and system, user and runtime settings are
+//! layered, see [`layer`].
+
+pub mod bindings;
+pub mod builder;
+pub mod format;
+pub mod include;
+pub mod layer;
+pub mod section;
+
+pub use bindings::{BindingAction, BindingsConfig, KeyCombo, KeyModifiers};
+pub use builder::CompositorConfigBuilder;
+pub use format::{default_config_path, ConfigFormat};
+pub use include::LoadedConfig;
+pub use layer::{default_layers, ConfigLayer};
+pub use section::{ConfigSection, SECTION_KEYS};
+pub use compositor_utils::theme_tokens::{ColorToken, InteractionState, Palette, StateColors};
 
 use anyhow::{Context, Result};
 use notify::{Config as NotifyConfig, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
 use thiserror::Error;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, mpsc, RwLock};
 use tracing::{debug, error, info};
 use compositor_utils::error::CompositorError;
 
@@ -26,6 +51,12 @@ pub enum ConfigError {
     #[error("RON parsing error: {0}")]
     RonParsing(#[from] ron::error::SpannedError),
     
+    #[error("TOML serialization error: {0}")]
+    TomlSerialization(#[from] toml::ser::Error),
+    
+    #[error("RON serialization error: {0}")]
+    RonSerialization(#[from] ron::Error),
+    
     #[error("File watching error: {0}")]
     Watcher(#[from] notify::Error),
     
@@ -34,6 +65,12 @@ pub enum ConfigError {
     
     #[error("Environment override error: {0}")]
     Environment(String),
+    
+    #[error("Runtime override error: {0}")]
+    Override(String),
+    
+    #[error("Failed to include {path}: {message}")]
+    Include { path: PathBuf, message: String },
 }
 
 impl From<ConfigError> for CompositorError {
@@ -55,6 +92,13 @@ pub struct DisplayConfig {
     pub vsync: bool,
     /// Enable adaptive sync (FreeSync/G-Sync)
     pub adaptive_sync: bool,
+    /// Rotate the built-in display with the device's accelerometer (iio-sensor-proxy)
+    #[serde(default)]
+    pub auto_rotate: bool,
+    /// Output layouts applied when exactly their outputs are in use, e.g.
+    /// when docked at a desk
+    #[serde(default)]
+    pub profiles: Vec<OutputProfile>,
 }
 
 impl Default for DisplayConfig {
@@ -65,10 +109,33 @@ impl Default for DisplayConfig {
             refresh_rate: 60,
             vsync: true,
             adaptive_sync: true,
+            auto_rotate: false,
+            profiles: vec![],
         }
     }
 }
 
+/// Output layout for one set of outputs in use
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputProfile {
+    /// Profile name shown in logs
+    pub name: String,
+    /// Settings by output name, e.g. "eDP-1"; the profile applies when these
+    /// are exactly the outputs in use
+    pub outputs: BTreeMap<String, ProfileOutput>,
+}
+
+/// Settings a profile gives one output
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileOutput {
+    /// Scale factor; keeps the current scale when unset
+    #[serde(default)]
+    pub scale: Option<f64>,
+    /// Top left corner in the logical layout; keeps the current position when unset
+    #[serde(default)]
+    pub position: Option<(i32, i32)>,
+}
+
 /// App bar configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppBarConfig {
@@ -88,6 +155,12 @@ pub struct AppBarConfig {
     pub glassmorphism: bool,
     /// Blur radius for glassmorphism
     pub blur_radius: f32,
+    /// Media controls widget
+    #[serde(default)]
+    pub media: MediaWidgetConfig,
+    /// Clock widget and its calendar popover
+    #[serde(default)]
+    pub clock: ClockConfig,
 }
 
 impl Default for AppBarConfig {
@@ -101,14 +174,86 @@ impl Default for AppBarConfig {
             transparency: 0.85,
             glassmorphism: true,
             blur_radius: 20.0,
+            media: MediaWidgetConfig::default(),
+            clock: ClockConfig::default(),
+        }
+    }
+}
+
+/// When the app bar shows the media controls widget
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaWidgetVisibility {
+    /// Whenever a media player is running
+    Always,
+    /// Only while a player is playing or paused
+    #[default]
+    WhenActive,
+    /// Never
+    Never,
+}
+
+/// Media controls widget for MPRIS media players
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaWidgetConfig {
+    pub visibility: MediaWidgetVisibility,
+    /// Expand to show artwork and track details while hovered
+    pub expand_on_hover: bool,
+}
+
+impl Default for MediaWidgetConfig {
+    fn default() -> Self {
+        Self {
+            visibility: MediaWidgetVisibility::default(),
+            expand_on_hover: true,
         }
     }
 }
 
+/// First day of the week in the calendar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeekStart {
+    #[default]
+    Monday,
+    Sunday,
+}
+
+/// Additional time zone shown in the clock popover
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldClockConfig {
+    /// tz database name, e.g. "America/New_York"
+    pub time_zone: String,
+    /// Label shown instead of the zone's city name
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl WorldClockConfig {
+    /// Label to show, e.g. "New York" for "America/New_York"
+    pub fn display_label(&self) -> String {
+        self.label.clone().unwrap_or_else(|| {
+            let city = self.time_zone.rsplit('/').next().unwrap_or(&self.time_zone);
+            city.replace('_', " ")
+        })
+    }
+}
+
+/// Clock widget and its calendar popover
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClockConfig {
+    #[serde(default)]
+    pub week_start: WeekStart,
+    /// Time zones listed below the calendar
+    #[serde(default)]
+    pub world_clocks: Vec<WorldClockConfig>,
+}
+
 /// Theme configuration for glassmorphism/neomorphism
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThemeConfig {
-    /// Theme name
+    /// Theme name; "neomorphism" selects the neomorphic rendering mode,
+    /// anything else glassmorphism
     pub name: String,
     /// Primary color (RGBA)
     pub primary_color: [f32; 4],
@@ -116,16 +261,192 @@ pub struct ThemeConfig {
     pub secondary_color: [f32; 4],
     /// Accent color (RGBA)
     pub accent_color: [f32; 4],
-    /// Background color (RGBA)
+    /// Background color (RGBA), also shown on outputs without a wallpaper
     pub background_color: [f32; 4],
+    /// Colors of semantic roles (RGBA), replacing those derived from the
+    /// colors above, e.g. `on_surface` or `warning`
+    #[serde(default)]
+    pub colors: std::collections::HashMap<ColorToken, [f32; 4]>,
+    /// Background color per output name, replacing `background_color`
+    #[serde(default)]
+    pub output_background_colors: std::collections::HashMap<String, [f32; 4]>,
     /// Corner radius for elements
     pub corner_radius: f32,
+    /// TrueType font of labels the compositor draws itself, e.g. in dialogs;
+    /// a system sans-serif font if unset
+    #[serde(default)]
+    pub font: Option<PathBuf>,
     /// Shadow intensity
     pub shadow_intensity: f32,
     /// Enable animations
     pub animations: bool,
     /// Animation duration in milliseconds
     pub animation_duration: u64,
+    /// Keyboard focus indicator for compositor UI
+    #[serde(default)]
+    pub focus_ring: FocusRingConfig,
+    /// Corner radius, shadow and border overrides per kind of surface
+    #[serde(default)]
+    pub surfaces: SurfaceStylesConfig,
+    /// Light and extrusion of the neomorphic rendering mode
+    #[serde(default)]
+    pub neomorphism: NeomorphismConfig,
+}
+
+/// How surfaces and compositor UI are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeStyle {
+    /// Translucent surfaces over a blurred backdrop
+    Glassmorphism,
+    /// Matte surfaces extruded from the background by soft shadows and highlights
+    Neomorphism,
+}
+
+/// Neomorphic rendering settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NeomorphismConfig {
+    /// Direction the light comes from in degrees, counter-clockwise from the
+    /// right; 135 lights surfaces from the top left
+    pub light_angle: f32,
+    /// How far shadows and highlights reach out from surfaces in pixels
+    pub distance: f32,
+    /// Softness of shadows and highlights in pixels
+    pub blur: f32,
+    /// Strength of the highlight towards the light (0.0 - 1.0)
+    pub highlight_intensity: f32,
+    /// Matte color of surfaces and the background they are extruded from
+    /// (RGBA); defaults to the theme background color
+    #[serde(default)]
+    pub surface_color: Option<[f32; 4]>,
+    /// Overrides per kind of surface
+    #[serde(default)]
+    pub elements: NeomorphicElementsConfig,
+}
+
+impl Default for NeomorphismConfig {
+    fn default() -> Self {
+        Self {
+            light_angle: 135.0,
+            distance: 8.0,
+            blur: 16.0,
+            highlight_intensity: 0.5,
+            surface_color: None,
+            elements: NeomorphicElementsConfig::default(),
+        }
+    }
+}
+
+/// Neomorphic overrides per surface class; unset values use the defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NeomorphicElementsConfig {
+    #[serde(default)]
+    pub window: NeomorphicElementConfig,
+    #[serde(default)]
+    pub dialog: NeomorphicElementConfig,
+    #[serde(default)]
+    pub popup: NeomorphicElementConfig,
+    #[serde(default)]
+    pub panel: NeomorphicElementConfig,
+}
+
+/// Neomorphic overrides of one surface class
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NeomorphicElementConfig {
+    /// Shadow and highlight distance in pixels
+    #[serde(default)]
+    pub distance: Option<f32>,
+    /// Shadow and highlight softness in pixels
+    #[serde(default)]
+    pub blur: Option<f32>,
+    /// Strength of the shadow away from the light (0.0 - 1.0)
+    #[serde(default)]
+    pub shadow_intensity: Option<f32>,
+    /// Strength of the highlight towards the light (0.0 - 1.0)
+    #[serde(default)]
+    pub highlight_intensity: Option<f32>,
+}
+
+/// Neomorphic extrusion of a surface class with the defaults filled in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NeomorphicStyle {
+    pub distance: f32,
+    pub blur: f32,
+    pub shadow_intensity: f32,
+    pub highlight_intensity: f32,
+}
+
+/// Kinds of surfaces styled separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SurfaceClass {
+    /// Normal application windows
+    Window,
+    /// Toplevels with a parent window
+    Dialog,
+    /// Menus, tooltips and other popups
+    Popup,
+    /// Bars, docks and other layer-shell surfaces
+    Panel,
+}
+
+/// Decoration overrides per surface class; unset values use the theme's
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SurfaceStylesConfig {
+    #[serde(default)]
+    pub window: SurfaceStyleConfig,
+    #[serde(default)]
+    pub dialog: SurfaceStyleConfig,
+    #[serde(default)]
+    pub popup: SurfaceStyleConfig,
+    #[serde(default)]
+    pub panel: SurfaceStyleConfig,
+}
+
+/// Decoration overrides of one surface class
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SurfaceStyleConfig {
+    /// Corner radius in pixels
+    #[serde(default)]
+    pub corner_radius: Option<f32>,
+    /// Shadow intensity (0.0 - 1.0)
+    #[serde(default)]
+    pub shadow_intensity: Option<f32>,
+    /// Border width in pixels; 0 draws no border
+    #[serde(default)]
+    pub border_width: Option<f32>,
+    /// Border color (RGBA)
+    #[serde(default)]
+    pub border_color: Option<[f32; 4]>,
+}
+
+/// Decoration of a surface class with the theme defaults filled in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceStyle {
+    pub corner_radius: f32,
+    pub shadow_intensity: f32,
+    pub border_width: f32,
+    pub border_color: [f32; 4],
+}
+
+/// Keyboard focus indicator appearance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FocusRingConfig {
+    /// Outline color (RGBA)
+    pub color: [f32; 4],
+    /// Outline width in pixels
+    pub width: f32,
+    /// Gap between the focused element and the outline in pixels
+    pub offset: f32,
+}
+
+impl Default for FocusRingConfig {
+    fn default() -> Self {
+        Self {
+            color: [0.0, 0.5, 1.0, 1.0], // Matches the default accent color
+            width: 2.0,
+            offset: 2.0,
+        }
+    }
 }
 
 impl Default for ThemeConfig {
@@ -136,12 +457,114 @@ impl Default for ThemeConfig {
             secondary_color: [0.3, 0.3, 0.3, 0.6],   // Lighter semi-transparent
             accent_color: [0.0, 0.5, 1.0, 1.0],      // Blue accent
             background_color: [0.05, 0.05, 0.05, 0.9], // Almost black with transparency
+            colors: std::collections::HashMap::new(),
+            output_background_colors: std::collections::HashMap::new(),
             corner_radius: 12.0,
+            font: None,
             shadow_intensity: 0.3,
             animations: true,
             animation_duration: 250,
+            focus_ring: FocusRingConfig::default(),
+            surfaces: SurfaceStylesConfig::default(),
+            neomorphism: NeomorphismConfig::default(),
+        }
+    }
+}
+
+impl ThemeConfig {
+    /// Background color of an output
+    pub fn background_color_for(&self, output: &str) -> [f32; 4] {
+        self.output_background_colors.get(output).copied().unwrap_or_else(|| self.color(ColorToken::Background))
+    }
+
+    /// Colors of every semantic role: the primary color is the surface, the
+    /// secondary color its variant and outline, and text colors contrast
+    /// with what they are drawn on; `colors` overrides any of them
+    pub fn palette(&self) -> Palette {
+        let mut palette = Palette::new(self.background_color, self.primary_color, self.secondary_color, self.accent_color);
+        for (&token, &color) in &self.colors {
+            palette.set(token, color);
+        }
+        palette
+    }
+
+    /// Color of a semantic role
+    pub fn color(&self, token: ColorToken) -> [f32; 4] {
+        self.palette().color(token)
+    }
+
+    /// This theme with keys of a `[theme]` table in TOML replaced, e.g. by a
+    /// candidate theme to preview; fails if the result is not a valid theme
+    pub fn with_overrides(&self, overrides: &str) -> Result<Self, ConfigError> {
+        let toml::Value::Table(mut theme) = toml::Value::try_from(self)? else {
+            unreachable!("themes serialize to tables");
+        };
+        include::merge(&mut theme, toml::from_str(overrides)?);
+        let theme: Self = toml::Value::Table(theme).try_into()?;
+        CompositorConfig { theme: theme.clone(), ..Default::default() }.validate()?;
+        Ok(theme)
+    }
+
+    /// Overrides of every surface class
+    fn surface_overrides(&self) -> impl Iterator<Item = &SurfaceStyleConfig> {
+        [&self.surfaces.window, &self.surfaces.dialog, &self.surfaces.popup, &self.surfaces.panel].into_iter()
+    }
+
+    /// Decoration of a surface class; unset overrides fall back to the
+    /// theme's corner radius and shadow, and to no border
+    pub fn surface_style(&self, class: SurfaceClass) -> SurfaceStyle {
+        let overrides = match class {
+            SurfaceClass::Window => &self.surfaces.window,
+            SurfaceClass::Dialog => &self.surfaces.dialog,
+            SurfaceClass::Popup => &self.surfaces.popup,
+            SurfaceClass::Panel => &self.surfaces.panel,
+        };
+        SurfaceStyle {
+            corner_radius: overrides.corner_radius.unwrap_or(self.corner_radius),
+            shadow_intensity: overrides.shadow_intensity.unwrap_or(self.shadow_intensity),
+            border_width: overrides.border_width.unwrap_or(0.0),
+            border_color: overrides.border_color.unwrap_or_else(|| self.color(ColorToken::Outline)),
+        }
+    }
+
+    /// Rendering mode selected by the theme name
+    pub fn style(&self) -> ThemeStyle {
+        if self.name.eq_ignore_ascii_case("neomorphism") {
+            ThemeStyle::Neomorphism
+        } else {
+            ThemeStyle::Glassmorphism
+        }
+    }
+
+    /// Neomorphic overrides of every surface class
+    fn neomorphic_overrides(&self) -> impl Iterator<Item = &NeomorphicElementConfig> {
+        let elements = &self.neomorphism.elements;
+        [&elements.window, &elements.dialog, &elements.popup, &elements.panel].into_iter()
+    }
+
+    /// Neomorphic extrusion of a surface class; unset overrides fall back to
+    /// the neomorphism defaults and the class's shadow intensity
+    pub fn neomorphic_style(&self, class: SurfaceClass) -> NeomorphicStyle {
+        let elements = &self.neomorphism.elements;
+        let overrides = match class {
+            SurfaceClass::Window => &elements.window,
+            SurfaceClass::Dialog => &elements.dialog,
+            SurfaceClass::Popup => &elements.popup,
+            SurfaceClass::Panel => &elements.panel,
+        };
+        NeomorphicStyle {
+            distance: overrides.distance.unwrap_or(self.neomorphism.distance),
+            blur: overrides.blur.unwrap_or(self.neomorphism.blur),
+            shadow_intensity: overrides.shadow_intensity.unwrap_or(self.surface_style(class).shadow_intensity),
+            highlight_intensity: overrides.highlight_intensity.unwrap_or(self.neomorphism.highlight_intensity),
         }
     }
+
+    /// Opaque matte color of neomorphic surfaces
+    pub fn neomorphic_surface_color(&self) -> [f32; 4] {
+        let [r, g, b, _] = self.neomorphism.surface_color.unwrap_or_else(|| self.color(ColorToken::Background));
+        [r, g, b, 1.0]
+    }
 }
 
 /// Performance configuration
@@ -159,6 +582,237 @@ pub struct PerformanceConfig {
     pub memory_pool_size: u64,
     /// Enable performance profiling
     pub profiling: bool,
+    /// Internal render scale per output name (0.25 - 1.0), upscaled on scanout
+    #[serde(default)]
+    pub output_render_scale: std::collections::HashMap<String, f32>,
+    /// Antialiasing quality for compositor-drawn decorations and widgets
+    #[serde(default)]
+    pub ui_antialiasing: UiAntialiasingQuality,
+    /// Record per-surface frame statistics for clients to query over IPC
+    #[serde(default)]
+    pub frame_statistics: bool,
+    /// Cost of background blur behind client surfaces
+    #[serde(default)]
+    pub blur_quality: BlurQualityConfig,
+    /// Ask dmabuf clients to prefer formats the display hardware can scan
+    /// out directly, most used first
+    #[serde(default)]
+    pub prefer_scanout_formats: bool,
+    /// How frames are synchronized with the display refresh
+    #[serde(default)]
+    pub present_mode: PresentMode,
+    /// Scheduling priority of the render and input threads
+    #[serde(default)]
+    pub scheduling: SchedulingConfig,
+    /// How often animations step, per class of effect
+    #[serde(default)]
+    pub animation_rates: AnimationRatesConfig,
+    /// When clients are told to draw their next frame
+    #[serde(default)]
+    pub frame_callbacks: FrameCallbackConfig,
+}
+
+/// Longest frame callback latency that can be configured
+pub const MAX_FRAME_CALLBACK_LATENCY_MS: u32 = 1000;
+
+/// Frame callback pacing
+///
+/// Clients draw their next frame when their frame callbacks fire, which is
+/// once the frame showing their last commit reaches the display.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameCallbackConfig {
+    /// Longest a client waits for its frame callbacks, in milliseconds, e.g.
+    /// when its surface is not on any output that presents frames
+    pub max_latency_ms: u32,
+}
+
+impl Default for FrameCallbackConfig {
+    fn default() -> Self {
+        Self { max_latency_ms: 100 }
+    }
+}
+
+/// Highest animation step rate
+pub const MAX_ANIMATION_RATE: u32 = 1000;
+
+/// Animation steps per second for each class of effect; 0 steps on every
+/// display refresh
+///
+/// Lower rates save CPU time on high-refresh outputs, e.g. 60 on a 144Hz
+/// monitor. Frames between two steps are interpolated.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnimationRatesConfig {
+    /// Windows moving, e.g. sliding aside for show desktop
+    #[serde(default)]
+    pub windows: u32,
+    /// Workspace wallpaper and accent color cross-fades
+    #[serde(default)]
+    pub workspaces: u32,
+    /// Outputs fading down before power saving turns them off
+    #[serde(default)]
+    pub outputs: u32,
+    /// Compositor UI such as the app bar's widgets
+    #[serde(default)]
+    pub ui: u32,
+}
+
+/// Scheduling priority of the compositor's render and input threads
+///
+/// A raised priority keeps frames on time while other processes load the
+/// CPU. Realtime scheduling needs an RLIMIT_RTPRIO of at least `realtime_priority`,
+/// CAP_SYS_NICE or RealtimeKit; without them the threads fall back to `nice`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchedulingConfig {
+    /// Ask for round-robin realtime scheduling (SCHED_RR)
+    pub realtime: bool,
+    /// Realtime priority (1 - 99)
+    pub realtime_priority: u32,
+    /// Nice value when realtime scheduling is off or refused (-20 - 19)
+    pub nice: i32,
+    /// CPUs and NUMA node of the render thread
+    #[serde(default)]
+    pub render_affinity: ThreadAffinity,
+    /// CPUs and NUMA node of texture uploads
+    #[serde(default)]
+    pub upload_affinity: ThreadAffinity,
+    /// CPUs and NUMA node of the Wayland event loop thread, which also
+    /// handles input
+    #[serde(default)]
+    pub event_loop_affinity: ThreadAffinity,
+}
+
+impl Default for SchedulingConfig {
+    fn default() -> Self {
+        Self {
+            realtime: true,
+            realtime_priority: 10,
+            nice: -10,
+            render_affinity: ThreadAffinity::default(),
+            upload_affinity: ThreadAffinity::default(),
+            event_loop_affinity: ThreadAffinity::default(),
+        }
+    }
+}
+
+/// CPUs thread affinities can address, those of a `cpu_set_t`
+pub const MAX_CPUS: usize = 1024;
+
+/// Where a compositor thread may run, e.g. to keep rendering on the NUMA
+/// node the GPU is attached to; unset pins nothing
+///
+/// With both set, the thread runs on the listed CPUs of the node. Pinning
+/// is skipped on systems that do not support it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThreadAffinity {
+    /// CPUs the thread may run on, e.g. [2, 3]
+    #[serde(default)]
+    pub cpus: Vec<usize>,
+    /// NUMA node whose CPUs the thread runs on and whose memory it prefers
+    #[serde(default)]
+    pub numa_node: Option<usize>,
+}
+
+impl ThreadAffinity {
+    /// Whether the thread is pinned at all
+    pub fn is_set(&self) -> bool {
+        !self.cpus.is_empty() || self.numa_node.is_some()
+    }
+}
+
+/// Preset trading blur fidelity for frame time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlurQualityTier {
+    /// Cheapest blur, for integrated GPUs driving 4K outputs
+    Low,
+    /// Quarter-resolution blur, balanced for 4K
+    #[default]
+    Medium,
+    /// Half-resolution blur
+    High,
+    /// Full-resolution blur with the widest kernel
+    Ultra,
+}
+
+/// Blur quality tier with optional per-setting overrides
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlurQualityConfig {
+    /// Preset the settings below default to
+    #[serde(default)]
+    pub tier: BlurQualityTier,
+    /// Texture samples per blur direction (2 - 64)
+    #[serde(default)]
+    pub samples: Option<u32>,
+    /// Times the backdrop is halved in size before blurring (0 - 4)
+    #[serde(default)]
+    pub downsample_levels: Option<u32>,
+    /// Frames between refreshes of a changing backdrop (1 - 60)
+    ///
+    /// Above 1 the blur lags behind the content under it, which is hardly
+    /// visible for mostly static backgrounds.
+    #[serde(default)]
+    pub backdrop_update_interval: Option<u32>,
+}
+
+impl BlurQualityConfig {
+    /// Texture samples per blur direction
+    pub fn samples(&self) -> u32 {
+        self.samples.unwrap_or(match self.tier {
+            BlurQualityTier::Low => 4,
+            BlurQualityTier::Medium => 8,
+            BlurQualityTier::High => 16,
+            BlurQualityTier::Ultra => 32,
+        })
+    }
+    
+    /// Times the backdrop is halved in size before blurring
+    pub fn downsample_levels(&self) -> u32 {
+        self.downsample_levels.unwrap_or(match self.tier {
+            BlurQualityTier::Low => 3,
+            BlurQualityTier::Medium => 2,
+            BlurQualityTier::High => 1,
+            BlurQualityTier::Ultra => 0,
+        })
+    }
+    
+    /// Frames between refreshes of a changing backdrop
+    pub fn backdrop_update_interval(&self) -> u32 {
+        self.backdrop_update_interval.unwrap_or(match self.tier {
+            BlurQualityTier::Low => 4,
+            BlurQualityTier::Medium => 2,
+            BlurQualityTier::High | BlurQualityTier::Ultra => 1,
+        })
+    }
+}
+
+/// Swapchain present mode, trading tearing for latency
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentMode {
+    /// Wait for vblank; no tearing, up to a frame of extra latency
+    Fifo,
+    /// Replace the queued frame at vblank; no tearing, lower latency
+    #[default]
+    Mailbox,
+    /// Present right away; lowest latency, may tear
+    Immediate,
+}
+
+/// Antialiasing quality for compositor-drawn UI
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UiAntialiasingQuality {
+    /// Hard edges
+    Off,
+    /// Edge coverage computed in shaders
+    #[default]
+    Analytic,
+    /// Analytic coverage plus 2x multisampling
+    Msaa2x,
+    /// Analytic coverage plus 4x multisampling
+    Msaa4x,
+    /// Analytic coverage plus 8x multisampling
+    Msaa8x,
 }
 
 impl Default for PerformanceConfig {
@@ -170,6 +824,15 @@ impl Default for PerformanceConfig {
             frame_limiting: true,
             memory_pool_size: 512, // 512MB
             profiling: false,
+            output_render_scale: std::collections::HashMap::new(),
+            ui_antialiasing: UiAntialiasingQuality::default(),
+            frame_statistics: false,
+            blur_quality: BlurQualityConfig::default(),
+            prefer_scanout_formats: false,
+            present_mode: PresentMode::default(),
+            scheduling: SchedulingConfig::default(),
+            animation_rates: AnimationRatesConfig::default(),
+            frame_callbacks: FrameCallbackConfig::default(),
         }
     }
 }
@@ -204,179 +867,1677 @@ impl Default for PluginConfig {
     }
 }
 
-/// Main configuration structure
+/// Action triggered by a hot corner or screen edge
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotCornerAction {
+    /// Do nothing
+    None,
+    /// Show the window overview
+    Overview,
+    /// Minimize all windows to reveal the desktop
+    ShowDesktop,
+    /// Open the application launcher
+    Launcher,
+    /// Run a custom command
+    Exec(String),
+}
+
+/// Hot corner and screen edge configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CompositorConfig {
-    /// Display configuration
-    pub display: DisplayConfig,
-    /// App bar configuration
-    pub app_bar: AppBarConfig,
-    /// Theme configuration
-    pub theme: ThemeConfig,
-    /// Performance configuration
-    pub performance: PerformanceConfig,
-    /// Plugin configuration
-    pub plugins: PluginConfig,
+pub struct HotCornersConfig {
+    /// Enable hot corners and edge actions
+    pub enabled: bool,
+    /// Action for the top-left corner
+    pub top_left: HotCornerAction,
+    /// Action for the top-right corner
+    pub top_right: HotCornerAction,
+    /// Action for the bottom-left corner
+    pub bottom_left: HotCornerAction,
+    /// Action for the bottom-right corner
+    pub bottom_right: HotCornerAction,
+    /// Action for the top edge (pointer dwell or touch swipe from the edge)
+    pub top_edge: HotCornerAction,
+    /// Action for the bottom edge
+    pub bottom_edge: HotCornerAction,
+    /// Action for the left edge
+    pub left_edge: HotCornerAction,
+    /// Action for the right edge
+    pub right_edge: HotCornerAction,
+    /// Size of the corner trigger area in pixels
+    pub corner_size: u32,
+    /// Time the pointer must rest in a corner or against an edge, in milliseconds
+    pub dwell_time: u64,
+    /// Minimum time between two triggers, in milliseconds
+    pub cooldown: u64,
+    /// Distance a touch must travel inward from an edge to count as a swipe, in pixels
+    pub edge_swipe_distance: u32,
 }
 
-impl Default for CompositorConfig {
+impl Default for HotCornersConfig {
     fn default() -> Self {
         Self {
-            display: DisplayConfig::default(),
-            app_bar: AppBarConfig::default(),
-            theme: ThemeConfig::default(),
-            performance: PerformanceConfig::default(),
-            plugins: PluginConfig::default(),
+            enabled: true,
+            top_left: HotCornerAction::Overview,
+            top_right: HotCornerAction::None,
+            bottom_left: HotCornerAction::None,
+            bottom_right: HotCornerAction::ShowDesktop,
+            top_edge: HotCornerAction::None,
+            bottom_edge: HotCornerAction::None,
+            left_edge: HotCornerAction::Launcher,
+            right_edge: HotCornerAction::None,
+            corner_size: 4,
+            dwell_time: 150,
+            cooldown: 1000,
+            edge_swipe_distance: 60,
         }
     }
 }
 
-impl CompositorConfig {
-    /// Validate configuration values
-    pub fn validate(&self) -> Result<(), ConfigError> {
-        // Validate display configuration
-        if self.display.scale_factor <= 0.0 {
-            return Err(ConfigError::Validation {
-                message: "Display scale factor must be positive".to_string(),
-            });
-        }
-        
-        if self.display.refresh_rate == 0 {
-            return Err(ConfigError::Validation {
-                message: "Display refresh rate must be positive".to_string(),
-            });
-        }
-        
-        // Validate app bar configuration
-        if self.app_bar.transparency < 0.0 || self.app_bar.transparency > 1.0 {
-            return Err(ConfigError::Validation {
-                message: "App bar transparency must be between 0.0 and 1.0".to_string(),
-            });
-        }
-        
-        // Validate theme colors (RGBA values should be 0.0-1.0)
-        for color in [
-            &self.theme.primary_color,
-            &self.theme.secondary_color,
-            &self.theme.accent_color,
-            &self.theme.background_color,
-        ] {
-            for &component in color {
-                if !(0.0..=1.0).contains(&component) {
-                    return Err(ConfigError::Validation {
-                        message: "Color components must be between 0.0 and 1.0".to_string(),
-                    });
-                }
-            }
-        }
-        
-        // Validate performance configuration
-        if self.performance.max_fps == 0 {
-            return Err(ConfigError::Validation {
-                message: "Maximum FPS must be positive".to_string(),
-            });
+/// Side of an output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputEdge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// Output edge the pointer cannot cross
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PointerBarrier {
+    /// Output name, e.g. "DP-1"
+    pub output: String,
+    pub edge: OutputEdge,
+}
+
+/// Pointer resistance and barriers between outputs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointerBarriersConfig {
+    /// Distance in logical pixels the pointer must push against an edge shared
+    /// with another output before crossing it; 0 disables sticky edges
+    pub sticky_edge_resistance: u32,
+    /// Time after which a push against a sticky edge starts over, in milliseconds
+    pub sticky_edge_timeout: u64,
+    /// Output edges the pointer cannot cross at all
+    #[serde(default)]
+    pub barriers: Vec<PointerBarrier>,
+}
+
+impl Default for PointerBarriersConfig {
+    fn default() -> Self {
+        Self {
+            sticky_edge_resistance: 0,
+            sticky_edge_timeout: 500,
+            barriers: vec![],
         }
-        
-        Ok(())
     }
-    
-    /// Apply environment variable overrides
-    pub fn apply_env_overrides(&mut self) -> Result<(), ConfigError> {
-        // Display overrides
-        if let Ok(resolution) = std::env::var("COMPOSITOR_RESOLUTION") {
-            let parts: Vec<&str> = resolution.split('x').collect();
-            if parts.len() == 2 {
-                self.display.resolution = (
-                    parts[0].parse().map_err(|_| ConfigError::Environment(
-                        "Invalid resolution width".to_string()
-                    ))?,
-                    parts[1].parse().map_err(|_| ConfigError::Environment(
-                        "Invalid resolution height".to_string()
-                    ))?,
-                );
-            }
+}
+
+/// Keyboard layout and key repeat
+///
+/// Empty XKB names use the system defaults, e.g. from `XKB_DEFAULT_LAYOUT`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyboardConfig {
+    /// XKB rules file, e.g. "evdev"
+    pub rules: String,
+    /// XKB keyboard model, e.g. "pc105"
+    pub model: String,
+    /// Comma separated XKB layouts, e.g. "us,de"
+    pub layout: String,
+    /// Comma separated variants of the layouts, e.g. ",nodeadkeys"
+    pub variant: String,
+    /// Comma separated XKB options, e.g. "grp:alt_shift_toggle"
+    pub options: Option<String>,
+    /// Time a key must be held before it repeats, in milliseconds
+    pub repeat_delay: u32,
+    /// Key repeats per second; 0 disables repeat
+    pub repeat_rate: u32,
+}
+
+impl Default for KeyboardConfig {
+    fn default() -> Self {
+        Self {
+            rules: String::new(),
+            model: String::new(),
+            layout: String::new(),
+            variant: String::new(),
+            options: None,
+            repeat_delay: 600,
+            repeat_rate: 25,
         }
-        
-        if let Ok(scale) = std::env::var("COMPOSITOR_SCALE") {
-            self.display.scale_factor = scale.parse().map_err(|_| {
-                ConfigError::Environment("Invalid scale factor".to_string())
-            })?;
+    }
+}
+
+/// Pointer acceleration curve of libinput devices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccelProfile {
+    /// Constant acceleration factor, e.g. for gaming mice
+    Flat,
+    /// Acceleration grows with pointer speed
+    Adaptive,
+}
+
+/// How clickpads without physical buttons decide which button was clicked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClickMethod {
+    /// The area of the pad that is clicked, e.g. bottom right for secondary
+    ButtonAreas,
+    /// The number of fingers on the pad, e.g. two for secondary
+    Clickfinger,
+}
+
+/// Input device settings, applied to devices as they are plugged in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputConfig {
+    /// Pointer acceleration profile; unset keeps the device default
+    #[serde(default)]
+    pub accel_profile: Option<AccelProfile>,
+    /// Pointer speed from -1.0 (slowest) to 1.0 (fastest); unset keeps the
+    /// device default
+    #[serde(default)]
+    pub accel_speed: Option<f64>,
+    /// Acceleration of specific pointers by libinput device name, e.g.
+    /// "Logitech G502", overriding the settings above
+    #[serde(default)]
+    pub devices: BTreeMap<String, PointerAccelConfig>,
+    /// Scroll content in the direction the fingers move on touchpads
+    pub natural_scroll: bool,
+    /// Tap the touchpad to click
+    pub tap_to_click: bool,
+    /// Click method of clickpads; unset keeps the device default
+    #[serde(default)]
+    pub click_method: Option<ClickMethod>,
+    /// Keyboard layout and key repeat
+    #[serde(default)]
+    pub keyboard: KeyboardConfig,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            accel_profile: None,
+            accel_speed: None,
+            devices: BTreeMap::new(),
+            natural_scroll: false,
+            tap_to_click: true,
+            click_method: None,
+            keyboard: KeyboardConfig::default(),
         }
-        
-        // Performance overrides
-        if let Ok(gpu) = std::env::var("COMPOSITOR_GPU_ACCELERATION") {
-            self.performance.gpu_acceleration = gpu.parse().unwrap_or(true);
+    }
+}
+
+impl InputConfig {
+    /// Acceleration of a pointer: its device override, else the settings
+    /// for all pointers
+    pub fn accel_for(&self, device: &str) -> PointerAccelConfig {
+        let device = self.devices.get(device);
+        PointerAccelConfig {
+            accel_profile: device.and_then(|device| device.accel_profile).or(self.accel_profile),
+            accel_speed: device.and_then(|device| device.accel_speed).or(self.accel_speed),
         }
-        
-        if let Ok(device) = std::env::var("COMPOSITOR_VULKAN_DEVICE") {
-            self.performance.vulkan_device_preference = device;
+    }
+}
+
+/// Acceleration of one pointer device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PointerAccelConfig {
+    #[serde(default)]
+    pub accel_profile: Option<AccelProfile>,
+    /// Pointer speed from -1.0 (slowest) to 1.0 (fastest)
+    #[serde(default)]
+    pub accel_speed: Option<f64>,
+}
+
+/// Dimming and turning off outputs after inactivity
+///
+/// Outputs fade down over `dim_duration` before they turn off, so the user
+/// can move the pointer or press a key to keep them on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PowerSaveConfig {
+    /// Turn outputs off after this many seconds without input; 0 keeps them on
+    pub off_after: u64,
+    /// Per-output overrides of `off_after` by output name, e.g. "DP-1"
+    #[serde(default)]
+    pub output_off_after: BTreeMap<String, u64>,
+    /// Time outputs fade down before turning off, in milliseconds
+    pub dim_duration: u64,
+    /// Brightness outputs fade down to (0.0 - 1.0)
+    pub dim_brightness: f32,
+    /// Time dimmed outputs fade back up after input, in milliseconds
+    pub undim_duration: u64,
+}
+
+impl PowerSaveConfig {
+    /// Seconds without input after which an output turns off, if it does
+    pub fn off_after_for(&self, output: &str) -> Option<u64> {
+        let off_after = self.output_off_after.get(output).copied().unwrap_or(self.off_after);
+        (off_after > 0).then_some(off_after)
+    }
+}
+
+impl Default for PowerSaveConfig {
+    fn default() -> Self {
+        Self {
+            off_after: 600,
+            output_off_after: BTreeMap::new(),
+            dim_duration: 10_000,
+            dim_brightness: 0.3,
+            undim_duration: 250,
         }
-        
-        Ok(())
     }
 }
 
-/// Configuration manager with hot-reloading support
-pub struct ConfigManager {
-    config: Arc<RwLock<CompositorConfig>>,
-    config_path: PathBuf,
-    _watcher: Option<RecommendedWatcher>,
-    change_sender: broadcast::Sender<CompositorConfig>,
+/// What closing the laptop lid does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LidCloseAction {
+    /// Turn the built-in output off and keep working on external outputs;
+    /// suspend when there are none
+    #[default]
+    ExternalOnly,
+    /// Suspend the system
+    Suspend,
+    /// Keep the built-in output on
+    Ignore,
 }
 
-impl ConfigManager {
-    /// Create a new configuration manager
-    pub async fn new(config_path: Option<PathBuf>) -> Result<Self> {
-        let config_path = config_path.unwrap_or_else(|| {
-            dirs::config_dir()
-                .unwrap_or_else(|| PathBuf::from("/etc"))
+/// Laptop lid switch handling
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LidConfig {
+    /// What closing the lid does
+    pub on_close: LidCloseAction,
+    /// Name prefixes of built-in outputs, e.g. "eDP"
+    pub internal_outputs: Vec<String>,
+}
+
+impl LidConfig {
+    /// Whether an output is built into the laptop
+    pub fn is_internal(&self, output: &str) -> bool {
+        self.internal_outputs.iter().any(|prefix| output.starts_with(prefix.as_str()))
+    }
+}
+
+impl Default for LidConfig {
+    fn default() -> Self {
+        Self {
+            on_close: LidCloseAction::ExternalOnly,
+            internal_outputs: vec!["eDP".to_string(), "LVDS".to_string(), "DSI".to_string()],
+        }
+    }
+}
+
+/// Automatic cursor hiding
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CursorConfig {
+    /// Hide the cursor while typing until the pointer moves
+    pub hide_while_typing: bool,
+    /// Hide the cursor after this many seconds without pointer motion; 0 disables
+    pub hide_after: u64,
+}
+
+/// Pointer click assistance for users who cannot press buttons reliably
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointerAccessibilityConfig {
+    /// Click automatically when the pointer rests after moving
+    pub dwell_click: bool,
+    /// Time the pointer must rest before a dwell click, in milliseconds
+    pub dwell_time: u64,
+    /// Movement in pixels the pointer may jitter without restarting the dwell
+    pub dwell_threshold: u32,
+    /// Turn a long primary button press into a secondary click
+    pub simulated_secondary_click: bool,
+    /// Time the primary button must be held for a secondary click, in milliseconds
+    pub secondary_click_delay: u64,
+}
+
+impl Default for PointerAccessibilityConfig {
+    fn default() -> Self {
+        Self {
+            dwell_click: false,
+            dwell_time: 1200,
+            dwell_threshold: 10,
+            simulated_secondary_click: false,
+            secondary_click_delay: 1200,
+        }
+    }
+}
+
+/// Detection of applications that stop answering pings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnresponsiveDetectionConfig {
+    /// Ping clients and offer to force quit those that stop answering
+    pub enabled: bool,
+    /// Time between pings to a client, in milliseconds
+    pub ping_interval: u64,
+    /// Time a client has to answer a ping before it is considered unresponsive, in milliseconds
+    pub ping_timeout: u64,
+}
+
+impl Default for UnresponsiveDetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ping_interval: 5000,
+            ping_timeout: 5000,
+        }
+    }
+}
+
+/// What happens when a client goes over a resource soft limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceLimitAction {
+    /// Log a warning
+    #[default]
+    Warn,
+    /// Log a warning and slow down the client's frame callbacks
+    Throttle,
+}
+
+/// Per-client resource soft limits; 0 disables a limit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientLimitsConfig {
+    /// Surfaces per client
+    pub max_surfaces: u32,
+    /// Memory of attached buffers per client, in megabytes
+    pub max_buffer_memory_mb: u32,
+    /// GPU memory for copies of the client's buffers, in megabytes
+    pub max_texture_memory_mb: u32,
+    /// Frame callbacks per second across the client's surfaces
+    pub max_callback_rate: u32,
+    pub action: ResourceLimitAction,
+    /// Frame callbacks per second and surface for throttled clients
+    pub throttled_callback_rate: u32,
+}
+
+impl Default for ClientLimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_surfaces: 512,
+            max_buffer_memory_mb: 2048,
+            max_texture_memory_mb: 2048,
+            max_callback_rate: 2000,
+            action: ResourceLimitAction::Warn,
+            throttled_callback_rate: 15,
+        }
+    }
+}
+
+/// Daily time window during which focus mode is active
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusSchedule {
+    /// Start time as "HH:MM" (24-hour, local time)
+    pub start: String,
+    /// End time as "HH:MM"; may be earlier than `start` to span midnight
+    pub end: String,
+}
+
+impl FocusSchedule {
+    /// Whether a local time, in minutes since midnight, falls inside this window
+    pub fn contains(&self, minute_of_day: u32) -> bool {
+        let (Some(start), Some(end)) = (parse_time_of_day(&self.start), parse_time_of_day(&self.end)) else {
+            return false;
+        };
+        if start <= end {
+            (start..end).contains(&minute_of_day)
+        } else {
+            minute_of_day >= start || minute_of_day < end
+        }
+    }
+}
+
+/// Parse "HH:MM" into minutes since midnight
+fn parse_time_of_day(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Do-not-disturb / focus mode configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FocusModeConfig {
+    /// Suppress notification surfaces while focus mode is active
+    pub suppress_notifications: bool,
+    /// Dim windows other than the focused one
+    pub dim_unfocused: bool,
+    /// Dimming strength for unfocused windows (0.0 - 1.0)
+    pub dim_strength: f32,
+    /// Hide app bar badges while focus mode is active
+    pub hide_badges: bool,
+    /// Time windows during which focus mode turns on automatically
+    pub schedule: Vec<FocusSchedule>,
+    /// App IDs that turn focus mode on while focused (e.g. screen recorders)
+    pub trigger_apps: Vec<String>,
+    /// Turn focus mode on while a fullscreen window is focused
+    pub activate_on_fullscreen: bool,
+    /// Layer-shell namespaces treated as notifications
+    pub notification_namespaces: Vec<String>,
+}
+
+impl Default for FocusModeConfig {
+    fn default() -> Self {
+        Self {
+            suppress_notifications: true,
+            dim_unfocused: false,
+            dim_strength: 0.4,
+            hide_badges: true,
+            schedule: vec![],
+            trigger_apps: vec![],
+            activate_on_fullscreen: false,
+            notification_namespaces: vec![
+                "notifications".to_string(),
+                "mako".to_string(),
+                "dunst".to_string(),
+                "swaync".to_string(),
+            ],
+        }
+    }
+}
+
+/// Active-window highlight configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowDimmingConfig {
+    /// Dim unfocused windows
+    pub enabled: bool,
+    /// Brightness reduction for unfocused windows (0.0 - 1.0)
+    pub strength: f32,
+    /// Saturation reduction for unfocused windows (0.0 - 1.0)
+    pub desaturation: f32,
+    /// Fade duration on focus change in milliseconds
+    pub transition_duration: u64,
+}
+
+impl Default for WindowDimmingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            strength: 0.25,
+            desaturation: 0.0,
+            transition_duration: 200,
+        }
+    }
+}
+
+/// Rule requesting background blur behind matching surfaces
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlurRule {
+    /// Toplevel app ID to match; a trailing `*` matches any suffix
+    #[serde(default)]
+    pub app_id: Option<String>,
+    /// Layer-shell namespace to match; a trailing `*` matches any suffix
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Blur radius override; uses `BlurConfig::default_radius` when unset
+    #[serde(default)]
+    pub radius: Option<f32>,
+}
+
+impl BlurRule {
+    fn matches(pattern: &Option<String>, value: Option<&str>) -> bool {
+        match (pattern, value) {
+            (Some(pattern), Some(value)) => pattern_matches(pattern, value),
+            _ => false,
+        }
+    }
+}
+
+/// Match a value against a pattern where a trailing `*` matches any suffix
+pub fn pattern_matches(pattern: &str, value: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => pattern == value,
+    }
+}
+
+/// Background blur configuration for client surfaces
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlurConfig {
+    /// Enable blur behind client surfaces
+    pub enabled: bool,
+    /// Blur radius used by rules without an explicit radius
+    pub default_radius: f32,
+    /// Rules selecting which surfaces get background blur
+    pub rules: Vec<BlurRule>,
+}
+
+impl Default for BlurConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default_radius: 20.0,
+            rules: vec![],
+        }
+    }
+}
+
+impl BlurConfig {
+    /// Blur radius for a surface with the given app ID or layer namespace, if any rule matches
+    pub fn radius_for(&self, app_id: Option<&str>, namespace: Option<&str>) -> Option<f32> {
+        self.rule_for(app_id, namespace).map(|rule| rule.radius.unwrap_or(self.default_radius))
+    }
+    
+    /// First rule matching a surface with the given app ID or layer namespace, while blur is enabled
+    pub fn rule_for(&self, app_id: Option<&str>, namespace: Option<&str>) -> Option<&BlurRule> {
+        if !self.enabled {
+            return None;
+        }
+        
+        self.rules
+            .iter()
+            .find(|rule| BlurRule::matches(&rule.app_id, app_id) || BlurRule::matches(&rule.namespace, namespace))
+    }
+}
+
+/// Per-application window rule
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WindowRule {
+    /// App ID to match; a trailing `*` matches any suffix
+    pub app_id: String,
+    /// Show the window on every workspace of its output
+    #[serde(default)]
+    pub sticky: Option<bool>,
+    /// Highest rate in Hz the window is sent frame callbacks at
+    #[serde(default)]
+    pub max_fps: Option<u32>,
+    /// Scale the app renders at instead of its output's, e.g. 1.0 for
+    /// toolkits that misrender under fractional scaling; the compositor
+    /// scales its buffers to the output
+    #[serde(default)]
+    pub scale: Option<f64>,
+    /// Set to false to round the output scale up to a whole number for the
+    /// app instead of telling it a fractional scale
+    #[serde(default)]
+    pub fractional_scaling: Option<bool>,
+}
+
+impl WindowRule {
+    /// Whether this rule applies to a window with the given app ID
+    pub fn matches(&self, app_id: &str) -> bool {
+        pattern_matches(&self.app_id, app_id)
+    }
+}
+
+/// IPC automation configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AutomationConfig {
+    /// Allow IPC clients to synthesize pointer and keyboard input
+    pub allow_input_injection: bool,
+    /// Control socket permission tiers by peer credentials
+    #[serde(default)]
+    pub permissions: IpcPermissionsConfig,
+}
+
+/// What a control socket client may do, each tier including the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpcTier {
+    /// No requests at all
+    None,
+    /// Queries and event subscriptions
+    ReadOnly,
+    /// Focusing, moving and launching windows and changing settings
+    Control,
+    /// Synthesizing input and capturing the screen
+    Privileged,
+}
+
+/// Control socket permission tiers by the UID and GID of the connecting process
+///
+/// A UID entry takes precedence, then `owner` for processes running as the
+/// compositor's user, then a GID entry for the process's primary group.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IpcPermissionsConfig {
+    /// Tier of processes running as the compositor's user
+    pub owner: IpcTier,
+    /// Tier of processes matching no other entry
+    pub others: IpcTier,
+    /// Tiers of processes running as these users
+    #[serde(default)]
+    pub uids: BTreeMap<u32, IpcTier>,
+    /// Tiers of processes whose primary group is one of these
+    #[serde(default)]
+    pub gids: BTreeMap<u32, IpcTier>,
+}
+
+impl Default for IpcPermissionsConfig {
+    fn default() -> Self {
+        Self {
+            owner: IpcTier::Control,
+            others: IpcTier::None,
+            uids: BTreeMap::new(),
+            gids: BTreeMap::new(),
+        }
+    }
+}
+
+/// Which clients may grab the keyboard exclusively
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyboardGrabPolicy {
+    /// No client may grab the keyboard
+    Deny,
+    /// Only privileged clients may grab the keyboard
+    #[default]
+    Privileged,
+    /// Any client may grab the keyboard
+    Any,
+}
+
+/// Which clients a protocol global is advertised to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolExposure {
+    /// Every client
+    Everyone,
+    /// Clients outside restricted sockets and security contexts
+    Unrestricted,
+    /// Unrestricted clients on the privileged client list
+    Privileged,
+    /// No client
+    Nobody,
+}
+
+/// Client security policy configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecurityConfig {
+    /// Executables (file names or absolute paths) allowed to bind privileged
+    /// protocols such as virtual keyboard and input method; empty allows all
+    #[serde(default)]
+    pub privileged_clients: Vec<String>,
+    /// Clients allowed to grab the keyboard (on-screen keyboards, remote desktop)
+    #[serde(default)]
+    pub keyboard_grab: KeyboardGrabPolicy,
+    /// Keep a keyboard grab when the grabbing surface loses focus instead of
+    /// releasing it; focus then stays on the grabbing surface
+    #[serde(default)]
+    pub keep_keyboard_grab_on_focus_loss: bool,
+    /// Who protocol globals are advertised to, by interface name
+    /// (e.g. "zwlr_layer_shell_v1"), overriding the built-in defaults
+    #[serde(default)]
+    pub protocol_exposure: std::collections::HashMap<String, ProtocolExposure>,
+}
+
+/// Saved window layouts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutsConfig {
+    /// Directory saved layouts are stored in
+    pub directory: PathBuf,
+    /// Layout restored when the session starts, relaunching its applications
+    #[serde(default)]
+    pub restore_at_login: Option<String>,
+}
+
+impl Default for LayoutsConfig {
+    fn default() -> Self {
+        Self {
+            directory: dirs::data_dir()
+                .unwrap_or_else(|| PathBuf::from("/var/lib"))
                 .join("custom-compositor")
-                .join("config.toml")
-        });
+                .join("layouts"),
+            restore_at_login: None,
+        }
+    }
+}
+
+/// Single-application kiosk mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KioskConfig {
+    /// Run only the kiosk application, fullscreen
+    pub enabled: bool,
+    /// Application command line, e.g. ["firefox", "--kiosk", "https://example.com"]
+    #[serde(default)]
+    pub command: Vec<String>,
+    /// Delay before restarting the application after it exits, in milliseconds
+    pub restart_delay: u64,
+    /// Other executables (file names or absolute paths) allowed to connect,
+    /// e.g. the real executable behind a wrapper script or an on-screen keyboard
+    #[serde(default)]
+    pub allowed_clients: Vec<String>,
+}
+
+impl Default for KioskConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            command: vec![],
+            restart_delay: 2000,
+            allowed_clients: vec![],
+        }
+    }
+}
+
+/// A Wayland socket clients connect through
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WaylandSocketConfig {
+    /// Socket name, e.g. "wayland-1"; the first free wayland-N if not set
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Directory to create the socket in; $XDG_RUNTIME_DIR if not set
+    #[serde(default)]
+    pub directory: Option<PathBuf>,
+    /// Permission bits of the socket file, e.g. 0o660; left to the umask if not set
+    #[serde(default)]
+    pub mode: Option<u32>,
+    /// Group owning the socket file, e.g. to let another local user connect
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Hide privileged globals (layer shell, input methods, session lock,
+    /// toplevel list) from clients connecting through this socket
+    #[serde(default)]
+    pub restricted: bool,
+}
+
+/// Wayland server configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaylandConfig {
+    /// Sockets to listen on; clients the compositor launches use the first
+    pub sockets: Vec<WaylandSocketConfig>,
+}
+
+impl Default for WaylandConfig {
+    fn default() -> Self {
+        Self {
+            sockets: vec![WaylandSocketConfig::default()],
+        }
+    }
+}
+
+/// Optional protocol globals
+///
+/// Disabling protocols nobody uses saves startup time and memory on minimal
+/// and embedded deployments; clients never see disabled globals.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolsConfig {
+    /// Graphics tablets and styluses (tablet-v2)
+    pub tablet: bool,
+    /// Text input, input methods and virtual keyboards
+    pub input_method: bool,
+    /// Leasing displays to clients such as VR runtimes, set up with the DRM device
+    pub drm_lease: bool,
+}
+
+impl Default for ProtocolsConfig {
+    fn default() -> Self {
+        Self {
+            tablet: true,
+            input_method: true,
+            drm_lease: true,
+        }
+    }
+}
+
+/// Per-workspace wallpapers and accent colors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkspacesConfig {
+    /// Wallpaper of workspaces without an override
+    #[serde(default)]
+    pub wallpaper: Option<PathBuf>,
+    /// Cross-fade duration on workspace switch in milliseconds (0 to switch instantly)
+    pub crossfade_duration: u64,
+    /// Overrides for individual workspaces; later entries take precedence
+    #[serde(default)]
+    pub overrides: Vec<WorkspaceOverride>,
+}
+
+impl Default for WorkspacesConfig {
+    fn default() -> Self {
+        Self {
+            wallpaper: None,
+            crossfade_duration: 300,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl WorkspacesConfig {
+    /// Wallpaper of a workspace, if any
+    pub fn wallpaper_for(&self, output: &str, workspace: usize) -> Option<&Path> {
+        self.overrides
+            .iter()
+            .rev()
+            .filter(|entry| entry.matches(output, workspace))
+            .find_map(|entry| entry.wallpaper.as_deref())
+            .or(self.wallpaper.as_deref())
+    }
+    
+    /// Accent color override of a workspace; the theme accent applies otherwise
+    pub fn accent_color_for(&self, output: &str, workspace: usize) -> Option<[f32; 4]> {
+        self.overrides
+            .iter()
+            .rev()
+            .filter(|entry| entry.matches(output, workspace))
+            .find_map(|entry| entry.accent_color)
+    }
+}
+
+/// Wallpaper and accent color of one workspace
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceOverride {
+    /// Workspace index, starting at 0
+    pub workspace: usize,
+    /// Output the override is limited to; every output when unset
+    #[serde(default)]
+    pub output: Option<String>,
+    /// Wallpaper image
+    #[serde(default)]
+    pub wallpaper: Option<PathBuf>,
+    /// Accent color (RGBA) replacing the theme accent
+    #[serde(default)]
+    pub accent_color: Option<[f32; 4]>,
+}
+
+impl WorkspaceOverride {
+    /// Whether this override applies to a workspace of an output
+    pub fn matches(&self, output: &str, workspace: usize) -> bool {
+        self.workspace == workspace && self.output.as_deref().is_none_or(|name| name == output)
+    }
+}
+
+/// Feedback while applications launched by the compositor start up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StartupFeedbackConfig {
+    /// Show a busy cursor until a launched app maps its first window
+    pub busy_cursor: bool,
+    /// Bounce the dock icon of a launched app until it maps its first window
+    pub bounce_dock_icon: bool,
+    /// Give up on apps that show no window after this many seconds
+    pub timeout: u64,
+}
+
+impl Default for StartupFeedbackConfig {
+    fn default() -> Self {
+        Self {
+            busy_cursor: true,
+            bounce_dock_icon: true,
+            timeout: 10,
+        }
+    }
+}
+
+/// Text a picked color is copied to the clipboard as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorFormat {
+    /// `#1e90ff`
+    #[default]
+    Hex,
+    /// `rgb(30, 144, 255)`
+    Rgb,
+}
+
+/// Built-in color picker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorPickerConfig {
+    /// Format of the color copied to the clipboard
+    pub format: ColorFormat,
+    /// Pixels shown on each side of the pixel under the pointer in the loupe
+    pub loupe_radius: u32,
+    /// Size of each magnified pixel in the loupe, in logical pixels
+    pub loupe_zoom: u32,
+    /// Announce the copied color with a notification
+    pub notify: bool,
+}
+
+impl Default for ColorPickerConfig {
+    fn default() -> Self {
+        Self {
+            format: ColorFormat::Hex,
+            loupe_radius: 7,
+            loupe_zoom: 10,
+            notify: true,
+        }
+    }
+}
+
+/// Where new windows are placed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlacementPolicy {
+    /// Diagonally below the previous window
+    Cascade,
+    /// In the middle of the output
+    Center,
+    /// Where the window overlaps other windows the least
+    #[default]
+    Smart,
+    /// Where the application's last window closed, otherwise as `smart`
+    RememberLast,
+}
+
+/// Window management
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WindowConfig {
+    /// Placement of new windows within the output area not reserved by panels
+    pub placement: PlacementPolicy,
+    /// Focus the window under the pointer as it moves, without raising it,
+    /// instead of focusing and raising windows on click
+    #[serde(default)]
+    pub focus_follows_mouse: bool,
+}
+
+/// Screenshots taken with keybindings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScreenshotConfig {
+    /// Directory screenshots are saved to, created if missing
+    pub directory: PathBuf,
+    /// Include server-side decorations in active window screenshots
+    pub include_decorations: bool,
+    /// Include the pointer in screenshots
+    pub include_pointer: bool,
+    /// Also place screenshots on the clipboard as PNG images
+    pub copy_to_clipboard: bool,
+}
+
+impl Default for ScreenshotConfig {
+    fn default() -> Self {
+        Self {
+            directory: dirs::picture_dir()
+                .or_else(dirs::home_dir)
+                .unwrap_or_else(std::env::temp_dir)
+                .join("Screenshots"),
+            include_decorations: true,
+            include_pointer: false,
+            copy_to_clipboard: true,
+        }
+    }
+}
+
+/// Main configuration structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositorConfig {
+    /// Further configuration files merged into this one, relative to it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<PathBuf>,
+    /// Display configuration
+    pub display: DisplayConfig,
+    /// App bar configuration
+    pub app_bar: AppBarConfig,
+    /// Theme configuration
+    pub theme: ThemeConfig,
+    /// Performance configuration
+    pub performance: PerformanceConfig,
+    /// Plugin configuration
+    pub plugins: PluginConfig,
+    /// Hot corner and edge action configuration
+    #[serde(default)]
+    pub hot_corners: HotCornersConfig,
+    /// Dwell clicking and simulated secondary click
+    #[serde(default)]
+    pub pointer_accessibility: PointerAccessibilityConfig,
+    /// Pointer, touchpad and keyboard settings
+    #[serde(default)]
+    pub input: InputConfig,
+    /// Output dimming and turning off after inactivity
+    #[serde(default)]
+    pub power_save: PowerSaveConfig,
+    /// Laptop lid close and open handling
+    #[serde(default)]
+    pub lid: LidConfig,
+    /// Cursor hiding while typing and after inactivity
+    #[serde(default)]
+    pub cursor: CursorConfig,
+    /// Sticky edges and barriers between outputs
+    #[serde(default)]
+    pub pointer_barriers: PointerBarriersConfig,
+    /// Unresponsive application detection
+    #[serde(default)]
+    pub unresponsive_detection: UnresponsiveDetectionConfig,
+    /// Per-client resource soft limits
+    #[serde(default)]
+    pub client_limits: ClientLimitsConfig,
+    /// Do-not-disturb / focus mode configuration
+    #[serde(default)]
+    pub focus_mode: FocusModeConfig,
+    /// Active-window highlight configuration
+    #[serde(default)]
+    pub window_dimming: WindowDimmingConfig,
+    /// Background blur configuration for client surfaces
+    #[serde(default)]
+    pub blur: BlurConfig,
+    /// Per-application window rules, applied in order
+    #[serde(default)]
+    pub window_rules: Vec<WindowRule>,
+    /// IPC automation configuration
+    #[serde(default)]
+    pub automation: AutomationConfig,
+    /// Client security policy
+    #[serde(default)]
+    pub security: SecurityConfig,
+    /// Saved window layouts
+    #[serde(default)]
+    pub layouts: LayoutsConfig,
+    /// Single-application kiosk mode
+    #[serde(default)]
+    pub kiosk: KioskConfig,
+    /// Wayland sockets and their permissions
+    #[serde(default)]
+    pub wayland: WaylandConfig,
+    /// Optional protocols to advertise
+    #[serde(default)]
+    pub protocols: ProtocolsConfig,
+    /// Per-workspace wallpapers and accent colors
+    #[serde(default)]
+    pub workspaces: WorkspacesConfig,
+    /// Busy cursor and dock icon feedback while apps start
+    #[serde(default)]
+    pub startup_feedback: StartupFeedbackConfig,
+    /// Color picker loupe and clipboard format
+    #[serde(default)]
+    pub color_picker: ColorPickerConfig,
+    /// Screenshot directory and contents
+    #[serde(default)]
+    pub screenshot: ScreenshotConfig,
+    /// Keybindings, see [`bindings`]
+    #[serde(default)]
+    pub bindings: BindingsConfig,
+    /// Placement of new windows
+    #[serde(default)]
+    pub window: WindowConfig,
+}
+
+impl Default for CompositorConfig {
+    fn default() -> Self {
+        Self {
+            include: vec![],
+            display: DisplayConfig::default(),
+            app_bar: AppBarConfig::default(),
+            theme: ThemeConfig::default(),
+            performance: PerformanceConfig::default(),
+            plugins: PluginConfig::default(),
+            hot_corners: HotCornersConfig::default(),
+            pointer_accessibility: PointerAccessibilityConfig::default(),
+            input: InputConfig::default(),
+            power_save: PowerSaveConfig::default(),
+            lid: LidConfig::default(),
+            cursor: CursorConfig::default(),
+            pointer_barriers: PointerBarriersConfig::default(),
+            unresponsive_detection: UnresponsiveDetectionConfig::default(),
+            client_limits: ClientLimitsConfig::default(),
+            focus_mode: FocusModeConfig::default(),
+            window_dimming: WindowDimmingConfig::default(),
+            blur: BlurConfig::default(),
+            window_rules: vec![],
+            automation: AutomationConfig::default(),
+            security: SecurityConfig::default(),
+            layouts: LayoutsConfig::default(),
+            kiosk: KioskConfig::default(),
+            wayland: WaylandConfig::default(),
+            protocols: ProtocolsConfig::default(),
+            workspaces: WorkspacesConfig::default(),
+            startup_feedback: StartupFeedbackConfig::default(),
+            color_picker: ColorPickerConfig::default(),
+            screenshot: ScreenshotConfig::default(),
+            bindings: BindingsConfig::default(),
+            window: WindowConfig::default(),
+        }
+    }
+}
+
+impl CompositorConfig {
+    /// Rules matching an app ID, in configuration order
+    pub fn window_rules_for<'a>(&'a self, app_id: &'a str) -> impl Iterator<Item = &'a WindowRule> + 'a {
+        self.window_rules.iter().filter(move |rule| rule.matches(app_id))
+    }
+    
+    /// Validate configuration values
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        // Validate display configuration
+        if self.display.scale_factor <= 0.0 {
+            return Err(ConfigError::Validation {
+                message: "Display scale factor must be positive".to_string(),
+            });
+        }
+        
+        if self.display.refresh_rate == 0 {
+            return Err(ConfigError::Validation {
+                message: "Display refresh rate must be positive".to_string(),
+            });
+        }
+        
+        // Validate app bar configuration
+        if self.app_bar.transparency < 0.0 || self.app_bar.transparency > 1.0 {
+            return Err(ConfigError::Validation {
+                message: "App bar transparency must be between 0.0 and 1.0".to_string(),
+            });
+        }
+        
+        for clock in &self.app_bar.clock.world_clocks {
+            let zone = &clock.time_zone;
+            if zone.is_empty() || zone.starts_with('/') || zone.split('/').any(|part| part.is_empty() || part == "..") {
+                return Err(ConfigError::Validation {
+                    message: format!("Invalid world clock time zone: {:?}", zone),
+                });
+            }
+        }
+        
+        // Validate theme colors (RGBA values should be 0.0-1.0)
+        for color in [
+            &self.theme.primary_color,
+            &self.theme.secondary_color,
+            &self.theme.accent_color,
+            &self.theme.background_color,
+        ]
+        .into_iter()
+        .chain(self.theme.colors.values())
+        .chain(self.theme.output_background_colors.values())
+        .chain(self.theme.surface_overrides().filter_map(|style| style.border_color.as_ref()))
+        .chain(self.theme.neomorphism.surface_color.as_ref())
+        {
+            for &component in color {
+                if !(0.0..=1.0).contains(&component) {
+                    return Err(ConfigError::Validation {
+                        message: "Color components must be between 0.0 and 1.0".to_string(),
+                    });
+                }
+            }
+        }
+        
+        // Validate focus ring configuration
+        if self.theme.focus_ring.width <= 0.0 {
+            return Err(ConfigError::Validation {
+                message: "Focus ring width must be positive".to_string(),
+            });
+        }
+        if self.theme.focus_ring.offset < 0.0 {
+            return Err(ConfigError::Validation {
+                message: "Focus ring offset must not be negative".to_string(),
+            });
+        }
+        
+        // Validate per-surface-class decoration
+        for style in self.theme.surface_overrides() {
+            if style.corner_radius.is_some_and(|radius| radius < 0.0) || style.border_width.is_some_and(|width| width < 0.0) {
+                return Err(ConfigError::Validation {
+                    message: "Surface corner radius and border width must not be negative".to_string(),
+                });
+            }
+            if style.shadow_intensity.is_some_and(|intensity| !(0.0..=1.0).contains(&intensity)) {
+                return Err(ConfigError::Validation {
+                    message: "Surface shadow intensity must be between 0.0 and 1.0".to_string(),
+                });
+            }
+        }
+        
+        // Validate neomorphic extrusion
+        let neomorphism = &self.theme.neomorphism;
+        let mut lengths = self
+            .theme
+            .neomorphic_overrides()
+            .flat_map(|element| [element.distance, element.blur])
+            .flatten()
+            .chain([neomorphism.distance, neomorphism.blur]);
+        if lengths.any(|length| length < 0.0) {
+            return Err(ConfigError::Validation {
+                message: "Neomorphic distance and blur must not be negative".to_string(),
+            });
+        }
+        let mut intensities = self
+            .theme
+            .neomorphic_overrides()
+            .flat_map(|element| [element.shadow_intensity, element.highlight_intensity])
+            .flatten()
+            .chain([neomorphism.highlight_intensity]);
+        if intensities.any(|intensity| !(0.0..=1.0).contains(&intensity)) {
+            return Err(ConfigError::Validation {
+                message: "Neomorphic shadow and highlight intensity must be between 0.0 and 1.0".to_string(),
+            });
+        }
+        
+        // Validate input configuration
+        let speeds = std::iter::once(("all pointers", self.input.accel_speed)).chain(
+            self.input.devices.iter().map(|(device, accel)| (device.as_str(), accel.accel_speed)),
+        );
+        for (device, speed) in speeds {
+            if speed.is_some_and(|speed| !(-1.0..=1.0).contains(&speed)) {
+                return Err(ConfigError::Validation {
+                    message: format!("Pointer speed of {} must be between -1.0 and 1.0", device),
+                });
+            }
+        }
+        
+        // Validate performance configuration
+        if self.performance.max_fps == 0 {
+            return Err(ConfigError::Validation {
+                message: "Maximum FPS must be positive".to_string(),
+            });
+        }
+        for rule in &self.window_rules {
+            if rule.max_fps == Some(0) {
+                return Err(ConfigError::Validation {
+                    message: format!("Frame rate cap of window rule {} must be positive", rule.app_id),
+                });
+            }
+            if rule.scale.is_some_and(|scale| !(0.5..=4.0).contains(&scale)) {
+                return Err(ConfigError::Validation {
+                    message: format!("Scale of window rule {} must be between 0.5 and 4.0", rule.app_id),
+                });
+            }
+        }
+        for (output, &scale) in &self.performance.output_render_scale {
+            if !(0.25..=1.0).contains(&scale) {
+                return Err(ConfigError::Validation {
+                    message: format!("Render scale for output {} must be between 0.25 and 1.0", output),
+                });
+            }
+        }
+        let blur_quality = &self.performance.blur_quality;
+        if !(2..=64).contains(&blur_quality.samples()) {
+            return Err(ConfigError::Validation {
+                message: "Blur samples must be between 2 and 64".to_string(),
+            });
+        }
+        if blur_quality.downsample_levels() > 4 {
+            return Err(ConfigError::Validation {
+                message: "Blur downsample levels must be at most 4".to_string(),
+            });
+        }
+        if !(1..=60).contains(&blur_quality.backdrop_update_interval()) {
+            return Err(ConfigError::Validation {
+                message: "Blur backdrop update interval must be between 1 and 60 frames".to_string(),
+            });
+        }
+        let scheduling = &self.performance.scheduling;
+        if !(1..=99).contains(&scheduling.realtime_priority) {
+            return Err(ConfigError::Validation {
+                message: "Realtime priority must be between 1 and 99".to_string(),
+            });
+        }
+        if !(-20..=19).contains(&scheduling.nice) {
+            return Err(ConfigError::Validation {
+                message: "Nice value must be between -20 and 19".to_string(),
+            });
+        }
+        let affinities = [&scheduling.render_affinity, &scheduling.upload_affinity, &scheduling.event_loop_affinity];
+        if affinities.iter().flat_map(|affinity| &affinity.cpus).any(|&cpu| cpu >= MAX_CPUS) {
+            return Err(ConfigError::Validation {
+                message: format!("Thread affinity CPUs must be below {}", MAX_CPUS),
+            });
+        }
+        let rates = &self.performance.animation_rates;
+        if [rates.windows, rates.workspaces, rates.outputs, rates.ui].iter().any(|&rate| rate > MAX_ANIMATION_RATE) {
+            return Err(ConfigError::Validation {
+                message: format!("Animation rates must be at most {} steps per second", MAX_ANIMATION_RATE),
+            });
+        }
+        let max_latency = self.performance.frame_callbacks.max_latency_ms;
+        if max_latency == 0 || max_latency > MAX_FRAME_CALLBACK_LATENCY_MS {
+            return Err(ConfigError::Validation {
+                message: format!("Frame callback latency must be between 1 and {} ms", MAX_FRAME_CALLBACK_LATENCY_MS),
+            });
+        }
+        
+        // Validate hot corner configuration
+        if self.hot_corners.corner_size == 0 {
+            return Err(ConfigError::Validation {
+                message: "Hot corner size must be positive".to_string(),
+            });
+        }
         
-        // Load or create default configuration
-        let config = if config_path.exists() {
-            Self::load_config(&config_path).await?
-        } else {
-            let default_config = CompositorConfig::default();
-            Self::save_config(&config_path, &default_config).await?;
-            default_config
-        };
+        // Validate pointer accessibility configuration
+        if self.pointer_accessibility.dwell_time == 0 || self.pointer_accessibility.secondary_click_delay == 0 {
+            return Err(ConfigError::Validation {
+                message: "Dwell time and secondary click delay must be positive".to_string(),
+            });
+        }
+        
+        // Validate pointer barrier configuration
+        if self.pointer_barriers.barriers.iter().any(|barrier| barrier.output.trim().is_empty()) {
+            return Err(ConfigError::Validation {
+                message: "Pointer barriers must name an output".to_string(),
+            });
+        }
+        
+        // Validate unresponsive detection configuration
+        if self.unresponsive_detection.ping_interval == 0 || self.unresponsive_detection.ping_timeout == 0 {
+            return Err(ConfigError::Validation {
+                message: "Ping interval and timeout must be positive".to_string(),
+            });
+        }
+        
+        // Validate client limits configuration
+        if self.client_limits.action == ResourceLimitAction::Throttle && self.client_limits.throttled_callback_rate == 0 {
+            return Err(ConfigError::Validation {
+                message: "Throttled callback rate must be positive".to_string(),
+            });
+        }
+        
+        // Validate focus mode configuration
+        if !(0.0..=1.0).contains(&self.focus_mode.dim_strength) {
+            return Err(ConfigError::Validation {
+                message: "Focus mode dim strength must be between 0.0 and 1.0".to_string(),
+            });
+        }
+        
+        for window in &self.focus_mode.schedule {
+            if parse_time_of_day(&window.start).is_none() || parse_time_of_day(&window.end).is_none() {
+                return Err(ConfigError::Validation {
+                    message: format!(
+                        "Invalid focus mode schedule {}-{} (expected HH:MM)",
+                        window.start, window.end
+                    ),
+                });
+            }
+        }
+        
+        if !(0.0..=1.0).contains(&self.power_save.dim_brightness) {
+            return Err(ConfigError::Validation {
+                message: "Power save dim brightness must be between 0.0 and 1.0".to_string(),
+            });
+        }
+        
+        // Validate output profiles
+        for profile in &self.display.profiles {
+            if profile.outputs.is_empty() {
+                return Err(ConfigError::Validation {
+                    message: format!("Output profile {} must name at least one output", profile.name),
+                });
+            }
+            if profile.outputs.values().filter_map(|output| output.scale).any(|scale| scale <= 0.0) {
+                return Err(ConfigError::Validation {
+                    message: format!("Output profile {} has a scale that is not positive", profile.name),
+                });
+            }
+        }
+        
+        // Validate window dimming configuration
+        for value in [self.window_dimming.strength, self.window_dimming.desaturation] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(ConfigError::Validation {
+                    message: "Window dimming strength and desaturation must be between 0.0 and 1.0".to_string(),
+                });
+            }
+        }
+        
+        // Validate blur configuration
+        let blur_radii = std::iter::once(self.blur.default_radius)
+            .chain(self.blur.rules.iter().filter_map(|rule| rule.radius));
+        for radius in blur_radii {
+            if !(0.0..=128.0).contains(&radius) {
+                return Err(ConfigError::Validation {
+                    message: "Blur radius must be between 0 and 128".to_string(),
+                });
+            }
+        }
+        
+        // Validate security configuration
+        if self.security.privileged_clients.iter().any(|client| client.trim().is_empty()) {
+            return Err(ConfigError::Validation {
+                message: "Privileged client entries must not be empty".to_string(),
+            });
+        }
+        
+        // Validate layouts configuration
+        if let Some(name) = &self.layouts.restore_at_login {
+            if name.trim().is_empty() || name.contains('/') {
+                return Err(ConfigError::Validation {
+                    message: "Layout restored at login must be a layout name".to_string(),
+                });
+            }
+        }
+        
+        // Validate kiosk configuration
+        if self.kiosk.enabled && self.kiosk.command.first().is_none_or(|program| program.trim().is_empty()) {
+            return Err(ConfigError::Validation {
+                message: "Kiosk mode needs an application command".to_string(),
+            });
+        }
+        
+        // Validate Wayland socket configuration
+        if self.wayland.sockets.is_empty() {
+            return Err(ConfigError::Validation {
+                message: "At least one Wayland socket must be configured".to_string(),
+            });
+        }
+        for socket in &self.wayland.sockets {
+            if socket.name.as_ref().is_some_and(|name| name.is_empty() || name.contains('/')) {
+                return Err(ConfigError::Validation {
+                    message: "Wayland socket names must be non-empty file names".to_string(),
+                });
+            }
+            if socket.mode.is_some_and(|mode| mode > 0o777) {
+                return Err(ConfigError::Validation {
+                    message: "Wayland socket mode must be permission bits (0o000 - 0o777)".to_string(),
+                });
+            }
+        }
+        
+        // Validate workspaces configuration
+        if self.workspaces.crossfade_duration > 5000 {
+            return Err(ConfigError::Validation {
+                message: "Workspace cross-fade duration must be at most 5000 ms".to_string(),
+            });
+        }
+        let wallpapers = std::iter::once(&self.workspaces.wallpaper)
+            .chain(self.workspaces.overrides.iter().map(|entry| &entry.wallpaper));
+        for wallpaper in wallpapers.flatten() {
+            if wallpaper.as_os_str().is_empty() {
+                return Err(ConfigError::Validation {
+                    message: "Workspace wallpaper paths must not be empty".to_string(),
+                });
+            }
+        }
+        for color in self.workspaces.overrides.iter().filter_map(|entry| entry.accent_color.as_ref()) {
+            if color.iter().any(|component| !(0.0..=1.0).contains(component)) {
+                return Err(ConfigError::Validation {
+                    message: "Workspace accent color components must be between 0.0 and 1.0".to_string(),
+                });
+            }
+        }
         
+        // Validate startup feedback configuration
+        if !(1..=120).contains(&self.startup_feedback.timeout) {
+            return Err(ConfigError::Validation {
+                message: "Startup feedback timeout must be between 1 and 120 seconds".to_string(),
+            });
+        }
+        
+        // Validate color picker configuration
+        if !(1..=32).contains(&self.color_picker.loupe_radius) {
+            return Err(ConfigError::Validation {
+                message: "Color picker loupe radius must be between 1 and 32 pixels".to_string(),
+            });
+        }
+        
+        if !(2..=32).contains(&self.color_picker.loupe_zoom) {
+            return Err(ConfigError::Validation {
+                message: "Color picker loupe zoom must be between 2 and 32".to_string(),
+            });
+        }
+        
+        // Validate keybindings
+        for (combo, action) in self.bindings.parse()? {
+            if let BindingAction::Spawn(command) = &action {
+                if command.first().is_none_or(|program| program.is_empty()) {
+                    return Err(ConfigError::Validation {
+                        message: format!("Key binding {} must spawn a command", combo),
+                    });
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Apply environment variable overrides
+    pub fn apply_env_overrides(&mut self) -> Result<(), ConfigError> {
+        self.apply_overrides(env_overrides()?)
+    }
+    
+    /// Merge a partial layer of settings into this configuration, without
+    /// validation
+    pub fn apply_overrides(&mut self, overrides: toml::Table) -> Result<(), ConfigError> {
+        let include = std::mem::take(&mut self.include);
+        let mut layered = Self::load_layers(&[
+            ConfigLayer::Overrides(toml::Table::try_from(&*self)?),
+            ConfigLayer::Overrides(overrides),
+        ])?
+        .config;
+        layered.include = include;
+        *self = layered;
+        Ok(())
+    }
+}
+
+/// Settings overridden by `COMPOSITOR_*` environment variables
+pub(crate) fn env_overrides() -> Result<toml::Table, ConfigError> {
+    let mut display = toml::Table::new();
+    let mut performance = toml::Table::new();
+    
+    // Display overrides
+    if let Ok(resolution) = std::env::var("COMPOSITOR_RESOLUTION") {
+        let parts: Vec<&str> = resolution.split('x').collect();
+        if parts.len() == 2 {
+            let width: u32 = parts[0].parse().map_err(|_| ConfigError::Environment(
+                "Invalid resolution width".to_string()
+            ))?;
+            let height: u32 = parts[1].parse().map_err(|_| ConfigError::Environment(
+                "Invalid resolution height".to_string()
+            ))?;
+            display.insert("resolution".to_string(), toml::Value::try_from((width, height))?);
+        }
+    }
+    
+    if let Ok(scale) = std::env::var("COMPOSITOR_SCALE") {
+        let scale: f64 = scale.parse().map_err(|_| {
+            ConfigError::Environment("Invalid scale factor".to_string())
+        })?;
+        display.insert("scale_factor".to_string(), scale.into());
+    }
+    
+    // Performance overrides
+    if let Ok(gpu) = std::env::var("COMPOSITOR_GPU_ACCELERATION") {
+        performance.insert("gpu_acceleration".to_string(), gpu.parse().unwrap_or(true).into());
+    }
+    
+    if let Ok(device) = std::env::var("COMPOSITOR_VULKAN_DEVICE") {
+        performance.insert("vulkan_device_preference".to_string(), device.into());
+    }
+    
+    let mut overrides = toml::Table::new();
+    for (section, settings) in [("display", display), ("performance", performance)] {
+        if !settings.is_empty() {
+            overrides.insert(section.to_string(), settings.into());
+        }
+    }
+    Ok(overrides)
+}
+
+/// Quiet period after a file change before reloading, so an editor's save
+/// in several writes is reloaded once
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Configuration manager with hot-reloading support
+///
+/// The configuration is merged from layers, see [`layer`]; changes are saved
+/// to the last file layer.
+pub struct ConfigManager {
+    shared: Arc<SharedState>,
+}
+
+/// State shared between the manager and its hot-reload task
+struct SharedState {
+    config: RwLock<CompositorConfig>,
+    layers: Vec<ConfigLayer>,
+    /// Index in `layers` of the file changes are saved to
+    config_layer: usize,
+    config_path: PathBuf,
+    /// The configuration files and every file they include
+    files: RwLock<Vec<PathBuf>>,
+    /// Watches the directories of `files`, so files replaced by a rename on
+    /// save are still seen
+    watcher: std::sync::Mutex<Option<RecommendedWatcher>>,
+    change_sender: broadcast::Sender<CompositorConfig>,
+}
+
+impl ConfigManager {
+    /// Create a new configuration manager layering the system configuration,
+    /// the user configuration at `config_path` (the default path if `None`)
+    /// and environment overrides
+    pub async fn new(config_path: Option<PathBuf>) -> Result<Self> {
+        let config_path = config_path.unwrap_or_else(default_config_path);
+        Self::with_layers(vec![
+            ConfigLayer::system(),
+            ConfigLayer::File(config_path),
+            ConfigLayer::environment()?,
+        ])
+        .await
+    }
+    
+    /// Create a configuration manager merging the given layers, e.g.
+    /// [`default_layers`] followed by command line overrides
+    ///
+    /// Changes are saved to the last file layer, which is created if missing.
+    pub async fn with_layers(layers: Vec<ConfigLayer>) -> Result<Self> {
+        let config_layer = layers
+            .iter()
+            .rposition(|layer| layer.path().is_some())
+            .context("No configuration file layer to save changes to")?;
+        let config_path = layers[config_layer].path().map(Path::to_path_buf).unwrap_or_default();
+        
+        let loaded = Self::load_config(&layers).await?;
         let (change_sender, _) = broadcast::channel(32);
         
         let config_manager = Self {
-            config: Arc::new(RwLock::new(config)),
-            config_path,
-            _watcher: None,
-            change_sender,
+            shared: Arc::new(SharedState {
+                config: RwLock::new(loaded.config.clone()),
+                layers,
+                config_layer,
+                config_path,
+                files: RwLock::new(loaded.files),
+                watcher: std::sync::Mutex::new(None),
+                change_sender,
+            }),
         };
         
+        // Create the configuration file if missing
+        let shared = &config_manager.shared;
+        if !shared.config_path.exists() {
+            shared.save(&loaded.config).await?;
+            let file = shared.config_path.canonicalize().unwrap_or_else(|_| shared.config_path.clone());
+            shared.files.write().await.push(file);
+        }
+        
         info!("Configuration manager initialized");
         Ok(config_manager)
     }
     
     /// Get current configuration
     pub async fn get_config(&self) -> CompositorConfig {
-        self.config.read().await.clone()
+        self.shared.config.read().await.clone()
     }
     
     /// Update configuration
+    ///
+    /// Only settings that differ from the layers below the configuration file
+    /// are saved to it; settings of the layers above it, such as environment
+    /// overrides, keep the value the file had.
     pub async fn update_config<F>(&self, updater: F) -> Result<()>
     where
         F: FnOnce(&mut CompositorConfig),
     {
-        let mut config = self.config.write().await;
+        let mut config = self.shared.config.write().await;
+        if !config.include.is_empty() {
+            return Err(ConfigError::Include {
+                path: self.shared.config_path.clone(),
+                message: "a configuration split across files cannot be saved, edit the files instead".to_string(),
+            }
+            .into());
+        }
         updater(&mut config);
         
         // Validate updated configuration
         config.validate()?;
         
         // Save to file
-        Self::save_config(&self.config_path, &config).await?;
+        self.shared.save(&config).await?;
         
         // Notify subscribers of changes
-        let _ = self.change_sender.send(config.clone());
+        let _ = self.shared.change_sender.send(config.clone());
         
         info!("Configuration updated");
         Ok(())
@@ -384,15 +2545,57 @@ impl ConfigManager {
     
     /// Subscribe to configuration changes
     pub fn subscribe_to_changes(&self) -> broadcast::Receiver<CompositorConfig> {
-        self.change_sender.subscribe()
+        self.shared.change_sender.subscribe()
     }
     
-    /// Reload configuration from file
+    /// Reload configuration from the layers' files and the files they include
     pub async fn reload(&self) -> Result<()> {
-        let mut config = Self::load_config(&self.config_path).await?;
-        config.apply_env_overrides()?;
+        self.shared.reload().await
+    }
+    
+    /// Load configuration from layers, merging in included files
+    async fn load_config(layers: &[ConfigLayer]) -> Result<LoadedConfig> {
+        let loaded = CompositorConfig::load_layers(layers).context("Failed to load configuration")?;
+        debug!("Configuration loaded from {} layers ({} files)", layers.len(), loaded.files.len());
+        Ok(loaded)
+    }
+    
+    /// Enable hot-reloading of configuration files
+    ///
+    /// Changes to the configuration file or any file it includes are reloaded,
+    /// validated and sent to subscribers once writes settle. A configuration
+    /// that fails to load or validate is logged and the current one kept.
+    /// Must be called within a tokio runtime.
+    pub async fn enable_hot_reload(&mut self) -> Result<()> {
+        let (event_sender, events) = mpsc::unbounded_channel();
+        let mut watcher = RecommendedWatcher::new(
+            move |res: notify::Result<notify::Event>| match res {
+                Ok(event) => {
+                    let _ = event_sender.send(event);
+                }
+                Err(e) => error!("File watcher error: {}", e),
+            },
+            NotifyConfig::default(),
+        )?;
+        
+        for directory in watched_directories(&self.shared.files.read().await) {
+            watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+        }
+        *self.shared.watcher.lock().unwrap() = Some(watcher);
+        tokio::spawn(hot_reload(Arc::downgrade(&self.shared), events));
+        
+        info!("Hot-reload enabled for configuration");
+        Ok(())
+    }
+}
+
+impl SharedState {
+    /// Reload configuration from the layers' files and the files they include
+    async fn reload(&self) -> Result<()> {
+        let LoadedConfig { config, files } = ConfigManager::load_config(&self.layers).await?;
         config.validate()?;
         
+        self.watch_files(files).await;
         *self.config.write().await = config.clone();
         let _ = self.change_sender.send(config);
         
@@ -400,78 +2603,112 @@ impl ConfigManager {
         Ok(())
     }
     
-    /// Load configuration from file
-    async fn load_config(path: &Path) -> Result<CompositorConfig> {
-        let content = tokio::fs::read_to_string(path)
-            .await
-            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
-        
-        let mut config: CompositorConfig = if path.extension() == Some("ron".as_ref()) {
-            ron::from_str(&content)
-                .with_context(|| "Failed to parse RON configuration")?
-        } else {
-            toml::from_str(&content)
-                .with_context(|| "Failed to parse TOML configuration")?
-        };
-        
-        // Apply environment overrides
-        config.apply_env_overrides()?;
+    /// Save a configuration to the configuration file
+    async fn save(&self, config: &CompositorConfig) -> Result<()> {
+        let (below, above) = self.layers.split_at(self.config_layer);
+        let content = match ConfigFormat::from_path(&self.config_path) {
+            // RON files cannot be partial
+            ConfigFormat::Ron => config.to_string_as(ConfigFormat::Ron),
+            ConfigFormat::Toml => {
+                let previous = above[0].settings().unwrap_or_default();
+                let mut layer = config.layer_over(below)?;
+                for layer_above in &above[1..] {
+                    if let ConfigLayer::Overrides(overrides) = layer_above {
+                        keep_overridden(&mut layer, overrides, &previous);
+                    }
+                }
+                toml::to_string_pretty(&layer).map_err(ConfigError::from)
+            }
+        }
+        .with_context(|| "Failed to serialize configuration")?;
         
-        debug!("Configuration loaded from {}", path.display());
-        Ok(config)
-    }
-    
-    /// Save configuration to file
-    async fn save_config(path: &Path, config: &CompositorConfig) -> Result<()> {
-        // Ensure parent directory exists
-        if let Some(parent) = path.parent() {
+        if let Some(parent) = self.config_path.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
-        
-        let content = if path.extension() == Some("ron".as_ref()) {
-            ron::ser::to_string_pretty(config, ron::ser::PrettyConfig::default())
-                .with_context(|| "Failed to serialize configuration to RON")?
-        } else {
-            toml::to_string_pretty(config)
-                .with_context(|| "Failed to serialize configuration to TOML")?
-        };
-        
-        tokio::fs::write(path, content)
+        tokio::fs::write(&self.config_path, content)
             .await
-            .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+            .with_context(|| format!("Failed to write config file: {}", self.config_path.display()))?;
         
-        debug!("Configuration saved to {}", path.display());
+        debug!("Configuration saved to {}", self.config_path.display());
         Ok(())
     }
     
-    /// Enable hot-reloading of configuration files
-    pub async fn enable_hot_reload(&mut self) -> Result<()> {
-        let _config_path = self.config_path.clone();
-        let _config = self.config.clone();
-        let _sender = self.change_sender.clone();
-        
-        let mut watcher = RecommendedWatcher::new(
-            move |res: notify::Result<notify::Event>| {
-                match res {
-                    Ok(event) => {
-                        if event.kind.is_modify() {
-                            debug!("Configuration file changed, reloading...");
-                            // Note: In a real implementation, we'd need to handle this
-                            // in an async context. For now, we'll just log it.
-                            info!("Configuration file modified");
-                        }
-                    }
-                    Err(e) => error!("File watcher error: {}", e),
+    /// Watch a new set of files for hot-reload, e.g. after includes changed
+    async fn watch_files(&self, files: Vec<PathBuf>) {
+        let mut watched = self.files.write().await;
+        if let Some(watcher) = self.watcher.lock().unwrap().as_mut() {
+            let old = watched_directories(&watched);
+            let new = watched_directories(&files);
+            for directory in old.difference(&new) {
+                let _ = watcher.unwatch(directory);
+            }
+            for directory in new.difference(&old) {
+                if let Err(e) = watcher.watch(directory, RecursiveMode::NonRecursive) {
+                    error!("Failed to watch {}: {}", directory.display(), e);
                 }
-            },
-            NotifyConfig::default(),
-        )?;
-        
-        watcher.watch(&self.config_path, RecursiveMode::NonRecursive)?;
-        self._watcher = Some(watcher);
+            }
+        }
+        *watched = files;
+    }
+    
+    /// Whether a file system event changed one of the configuration files
+    async fn is_config_change(&self, event: &notify::Event) -> bool {
+        if !(event.kind.is_modify() || event.kind.is_create()) {
+            return false;
+        }
+        let files = self.files.read().await;
+        event.paths.iter().any(|path| files.contains(path))
+    }
+}
+
+/// Directories holding the configuration files
+fn watched_directories(files: &[PathBuf]) -> BTreeSet<PathBuf> {
+    files.iter().filter_map(|file| file.parent()).map(Path::to_path_buf).collect()
+}
+
+/// Drop the settings `overrides` sets from a layer to save, keeping the
+/// values the file had for them, so runtime overrides are not saved
+fn keep_overridden(layer: &mut toml::Table, overrides: &toml::Table, previous: &toml::Table) {
+    for (key, value) in overrides {
+        let previous = previous.get(key);
+        if let toml::Value::Table(overrides) = value {
+            let mut table = match layer.remove(key) {
+                Some(toml::Value::Table(table)) => table,
+                _ => toml::Table::new(),
+            };
+            let empty = toml::Table::new();
+            keep_overridden(&mut table, overrides, previous.and_then(toml::Value::as_table).unwrap_or(&empty));
+            if !table.is_empty() {
+                layer.insert(key.clone(), table.into());
+            }
+        } else if let Some(previous) = previous {
+            layer.insert(key.clone(), previous.clone());
+        } else {
+            layer.remove(key);
+        }
+    }
+}
+
+/// Reload the configuration on file changes until the manager is dropped
+async fn hot_reload(shared: Weak<SharedState>, mut events: mpsc::UnboundedReceiver<notify::Event>) {
+    while let Some(event) = events.recv().await {
+        let Some(state) = shared.upgrade() else { return };
+        if !state.is_config_change(&event).await {
+            continue;
+        }
         
-        info!("Hot-reload enabled for configuration");
-        Ok(())
+        // Wait for the writes of one save to settle
+        loop {
+            match tokio::time::timeout(RELOAD_DEBOUNCE, events.recv()).await {
+                Ok(Some(_)) => continue,
+                Ok(None) => return,
+                Err(_) => break,
+            }
+        }
+        debug!("Configuration file changed, reloading");
+        if let Err(e) = state.reload().await {
+            error!("Keeping the current configuration, reload failed: {:#}", e);
+        }
     }
 }
 
@@ -522,4 +2759,332 @@ mod tests {
         std::env::remove_var("COMPOSITOR_RESOLUTION");
         std::env::remove_var("COMPOSITOR_SCALE");
     }
+    
+    #[test]
+    fn test_focus_schedule_spanning_midnight() {
+        let schedule = FocusSchedule {
+            start: "22:00".to_string(),
+            end: "07:30".to_string(),
+        };
+        
+        assert!(schedule.contains(23 * 60));
+        assert!(schedule.contains(7 * 60 + 29));
+        assert!(!schedule.contains(7 * 60 + 30));
+        assert!(!schedule.contains(12 * 60));
+    }
+    
+    #[test]
+    fn test_workspace_override_precedence() {
+        let workspaces = WorkspacesConfig {
+            wallpaper: Some(PathBuf::from("default.png")),
+            overrides: vec![
+                WorkspaceOverride {
+                    workspace: 1,
+                    wallpaper: Some(PathBuf::from("any.png")),
+                    accent_color: Some([1.0, 0.0, 0.0, 1.0]),
+                    ..Default::default()
+                },
+                WorkspaceOverride {
+                    workspace: 1,
+                    output: Some("DP-1".to_string()),
+                    accent_color: Some([0.0, 1.0, 0.0, 1.0]),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        
+        assert_eq!(workspaces.wallpaper_for("DP-1", 0), Some(Path::new("default.png")));
+        assert_eq!(workspaces.wallpaper_for("DP-1", 1), Some(Path::new("any.png")));
+        assert_eq!(workspaces.accent_color_for("DP-1", 1), Some([0.0, 1.0, 0.0, 1.0]));
+        assert_eq!(workspaces.accent_color_for("HDMI-A-1", 1), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(workspaces.accent_color_for("DP-1", 2), None);
+    }
+
+    #[test]
+    fn test_surface_style_overrides() {
+        let mut theme = ThemeConfig::default();
+        theme.surfaces.popup = SurfaceStyleConfig {
+            corner_radius: Some(4.0),
+            border_width: Some(1.0),
+            ..Default::default()
+        };
+
+        let window = theme.surface_style(SurfaceClass::Window);
+        assert_eq!(window.corner_radius, theme.corner_radius);
+        assert_eq!(window.border_width, 0.0);
+        let popup = theme.surface_style(SurfaceClass::Popup);
+        assert_eq!(popup.corner_radius, 4.0);
+        assert_eq!(popup.border_width, 1.0);
+        assert_eq!(popup.shadow_intensity, theme.shadow_intensity);
+
+        let mut config = CompositorConfig::default();
+        config.theme.surfaces.panel.shadow_intensity = Some(2.0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_neomorphic_style() {
+        let mut theme = ThemeConfig::default();
+        assert_eq!(theme.style(), ThemeStyle::Glassmorphism);
+        theme.name = "neomorphism".to_string();
+        assert_eq!(theme.style(), ThemeStyle::Neomorphism);
+
+        theme.surfaces.popup.shadow_intensity = Some(0.2);
+        theme.neomorphism.elements.popup.distance = Some(4.0);
+        let window = theme.neomorphic_style(SurfaceClass::Window);
+        assert_eq!(window.distance, theme.neomorphism.distance);
+        assert_eq!(window.shadow_intensity, theme.shadow_intensity);
+        let popup = theme.neomorphic_style(SurfaceClass::Popup);
+        assert_eq!(popup.distance, 4.0);
+        assert_eq!(popup.blur, theme.neomorphism.blur);
+        assert_eq!(popup.shadow_intensity, 0.2);
+        assert_eq!(theme.neomorphic_surface_color()[3], 1.0);
+
+        let mut config = CompositorConfig::default();
+        config.theme.neomorphism.elements.panel.blur = Some(-1.0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_theme_palette() {
+        let mut theme = ThemeConfig::default();
+        assert_eq!(theme.color(ColorToken::Surface), theme.primary_color);
+        assert_eq!(theme.color(ColorToken::OnSurface), [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(theme.surface_style(SurfaceClass::Window).border_color, theme.secondary_color);
+
+        theme.colors.insert(ColorToken::Outline, [1.0, 0.0, 0.0, 1.0]);
+        theme.colors.insert(ColorToken::Background, [0.9, 0.9, 0.9, 1.0]);
+        assert_eq!(theme.surface_style(SurfaceClass::Window).border_color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(theme.background_color_for("DP-1"), [0.9, 0.9, 0.9, 1.0]);
+
+        let accent = theme.palette().states(ColorToken::Accent);
+        assert_eq!(accent.get(InteractionState::Normal), theme.accent_color);
+        assert_ne!(accent.hover, accent.normal);
+        assert_ne!(accent.pressed, accent.hover);
+        assert!(accent.disabled[3] < accent.normal[3]);
+
+        theme.colors.insert(ColorToken::OnSurface, [0.0, 0.0, 0.0, 1.0]);
+        let serialized = toml::to_string(&theme).unwrap();
+        assert!(serialized.contains("on_surface"));
+        let parsed: ThemeConfig = toml::from_str(&serialized).unwrap();
+        assert_eq!(parsed.color(ColorToken::OnSurface), [0.0, 0.0, 0.0, 1.0]);
+
+        let mut config = CompositorConfig::default();
+        config.theme.colors.insert(ColorToken::Warning, [2.0, 0.0, 0.0, 1.0]);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_theme_overrides() {
+        let theme = ThemeConfig::default();
+        let preview = theme
+            .with_overrides("name = \"neomorphism\"\ncorner_radius = 4.0\n[colors]\nwarning = [1.0, 0.5, 0.0, 1.0]\n")
+            .unwrap();
+        assert_eq!(preview.style(), ThemeStyle::Neomorphism);
+        assert_eq!(preview.corner_radius, 4.0);
+        assert_eq!(preview.color(ColorToken::Warning), [1.0, 0.5, 0.0, 1.0]);
+        assert_eq!(preview.accent_color, theme.accent_color);
+
+        assert!(theme.with_overrides("corner_radius = \"round\"").is_err());
+        assert!(theme.with_overrides("accent_color = [2.0, 0.0, 0.0, 1.0]").is_err());
+    }
+
+    #[test]
+    fn test_builder_and_round_trip() {
+        let config = CompositorConfig::builder()
+            .with_section::<ThemeConfig>(|theme| theme.corner_radius = 8.0)
+            .window_rule(WindowRule {
+                app_id: "electron*".to_string(),
+                max_fps: Some(30),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        assert_eq!(config.section::<ThemeConfig>().corner_radius, 8.0);
+        assert!(CompositorConfig::builder()
+            .section(PerformanceConfig { max_fps: 0, ..Default::default() })
+            .build()
+            .is_err());
+        assert!(CompositorConfig::builder()
+            .window_rule(WindowRule {
+                app_id: "legacy".to_string(),
+                scale: Some(8.0),
+                ..Default::default()
+            })
+            .build()
+            .is_err());
+        
+        for format in [ConfigFormat::Toml, ConfigFormat::Ron] {
+            let serialized = config.to_string_as(format).unwrap();
+            let parsed = CompositorConfig::parse(&serialized, format).unwrap();
+            assert_eq!(parsed.to_string_as(format).unwrap(), serialized);
+            assert_eq!(parsed.window_rules, config.window_rules);
+        }
+        
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nested").join("config.ron");
+        config.save(&path).unwrap();
+        assert_eq!(CompositorConfig::load(&path).unwrap().section::<ThemeConfig>().corner_radius, 8.0);
+    }
+    
+    #[test]
+    fn test_includes() {
+        let temp_dir = TempDir::new().unwrap();
+        let main = temp_dir.path().join("config.toml");
+        let config = CompositorConfig {
+            include: vec![PathBuf::from("rules.toml")],
+            window_rules: vec![WindowRule { app_id: "main".to_string(), ..Default::default() }],
+            theme: ThemeConfig { corner_radius: 4.0, ..Default::default() },
+            ..Default::default()
+        };
+        config.save(&main).unwrap();
+        std::fs::write(
+            temp_dir.path().join("rules.toml"),
+            "[theme]\ncorner_radius = 8.0\n\n[[window_rules]]\napp_id = \"included\"\nmax_fps = 30\n",
+        )
+        .unwrap();
+        
+        let loaded = CompositorConfig::load_with_includes(&main).unwrap();
+        assert_eq!(loaded.files.len(), 2);
+        assert_eq!(loaded.config.theme.corner_radius, 4.0);
+        let app_ids: Vec<_> = loaded.config.window_rules.iter().map(|rule| rule.app_id.as_str()).collect();
+        assert_eq!(app_ids, ["included", "main"]);
+        assert_eq!(loaded.config.include, config.include);
+        
+        std::fs::write(temp_dir.path().join("rules.toml"), "include = [\"config.toml\"]\n").unwrap();
+        assert!(matches!(CompositorConfig::load_with_includes(&main), Err(ConfigError::Include { .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_layers() {
+        let temp_dir = TempDir::new().unwrap();
+        let system = temp_dir.path().join("system.toml");
+        let user = temp_dir.path().join("user.toml");
+        std::fs::write(
+            &system,
+            "[theme]\ncorner_radius = 4.0\nshadow_intensity = 0.5\n\n[[window_rules]]\napp_id = \"system\"\n",
+        )
+        .unwrap();
+        std::fs::write(&user, "[theme]\ncorner_radius = 8.0\n\n[[window_rules]]\napp_id = \"user\"\n").unwrap();
+        let layers = vec![
+            ConfigLayer::File(system),
+            ConfigLayer::File(user.clone()),
+            ConfigLayer::from_args(["display.scale_factor=1.5", "theme.name=nord"]).unwrap(),
+        ];
+        
+        let manager = ConfigManager::with_layers(layers).await.unwrap();
+        let config = manager.get_config().await;
+        assert_eq!(config.theme.corner_radius, 8.0);
+        assert_eq!(config.theme.shadow_intensity, 0.5);
+        assert_eq!(config.theme.name, "nord");
+        assert_eq!(config.display.scale_factor, 1.5);
+        assert_eq!(config.display.resolution, DisplayConfig::default().resolution);
+        let app_ids: Vec<_> = config.window_rules.iter().map(|rule| rule.app_id.as_str()).collect();
+        assert_eq!(app_ids, ["system", "user"]);
+        
+        // Only the user's own settings are saved, without runtime overrides
+        manager.update_config(|config| config.theme.animation_duration = 150).await.unwrap();
+        let saved: toml::Table = toml::from_str(&std::fs::read_to_string(&user).unwrap()).unwrap();
+        let expected: toml::Table = toml::from_str(
+            "[theme]\ncorner_radius = 8.0\nanimation_duration = 150\n\n[[window_rules]]\napp_id = \"user\"\n",
+        )
+        .unwrap();
+        assert_eq!(saved.get("theme"), expected.get("theme"));
+        assert_eq!(saved.get("display"), None);
+        assert_eq!(
+            saved["window_rules"].as_array().unwrap()[0].get("app_id"),
+            expected["window_rules"][0].get("app_id")
+        );
+        
+        assert!(ConfigLayer::from_args(["display"]).is_err());
+    }
+    
+    #[test]
+    fn test_bindings() {
+        let combo: KeyCombo = "mod4+shift+Q".parse().unwrap();
+        assert_eq!(combo.to_string(), "Super+Shift+Q");
+        assert_eq!(combo.key, "q");
+        assert_eq!("Super+Enter".parse::<KeyCombo>().unwrap().key, "Return");
+        assert!("Super+".parse::<KeyCombo>().is_err());
+        assert!("Hyper+A".parse::<KeyCombo>().is_err());
+        assert!("Ctrl+Ctrl+A".parse::<KeyCombo>().is_err());
+        
+        let bindings: BindingsConfig = toml::from_str(
+            r#"
+            "Super+T" = { spawn = ["foot", "-e", "htop"] }
+            "Super+Q" = "none"
+            "Super+2" = { workspace = 1 }
+            "Super+S" = "toggle_sticky"
+            "Super+R" = "rotate_clockwise"
+            "#,
+        )
+        .unwrap();
+        let config = CompositorConfig { bindings, ..Default::default() };
+        let parsed = config.bindings.parse().unwrap();
+        assert_eq!(parsed.len(), 4);
+        assert!(parsed.contains(&("Super+2".parse().unwrap(), BindingAction::Workspace(1))));
+        assert!(parsed.contains(&("Super+S".parse().unwrap(), BindingAction::ToggleSticky)));
+        assert!(parsed.contains(&("Super+R".parse().unwrap(), BindingAction::RotateClockwise)));
+        config.validate().unwrap();
+        
+        let saved = toml::to_string(&config).unwrap();
+        let reloaded: CompositorConfig = toml::from_str(&saved).unwrap();
+        assert_eq!(reloaded.bindings, config.bindings);
+        
+        let mut conflicting = CompositorConfig::default();
+        conflicting.bindings.keys.insert("Mod4+Enter".to_string(), BindingAction::CloseWindow);
+        assert!(conflicting.validate().is_err());
+        CompositorConfig::default().validate().unwrap();
+    }
+    
+    #[test]
+    fn test_pointer_accel() {
+        let input: InputConfig = toml::from_str(
+            r#"
+            accel_profile = "adaptive"
+            accel_speed = 0.2
+            natural_scroll = false
+            tap_to_click = true
+            
+            [devices."Logitech G502"]
+            accel_profile = "flat"
+            "#,
+        )
+        .unwrap();
+        let bindings: BindingsConfig = toml::from_str(r#""Super+F12" = "toggle_flat_accel""#).unwrap();
+        let config = CompositorConfig { input, bindings, ..Default::default() };
+        config.validate().unwrap();
+        let mouse = config.input.accel_for("Logitech G502");
+        assert_eq!(mouse.accel_profile, Some(AccelProfile::Flat));
+        assert_eq!(mouse.accel_speed, Some(0.2));
+        assert_eq!(config.input.accel_for("Touchpad").accel_profile, Some(AccelProfile::Adaptive));
+        assert!(config.bindings.keys.values().any(|action| *action == BindingAction::ToggleFlatAccel));
+        
+        let mut too_fast = config.clone();
+        too_fast.input.devices.get_mut("Logitech G502").unwrap().accel_speed = Some(1.5);
+        assert!(too_fast.validate().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_hot_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        let mut manager = ConfigManager::new(Some(config_path.clone())).await.unwrap();
+        manager.enable_hot_reload().await.unwrap();
+        let mut changes = manager.subscribe_to_changes();
+        
+        // Save through a rename, as many editors do
+        let config = CompositorConfig {
+            theme: ThemeConfig { corner_radius: 6.0, ..Default::default() },
+            ..Default::default()
+        };
+        let staged = temp_dir.path().join("config.toml.tmp");
+        config.save(&staged).unwrap();
+        std::fs::rename(&staged, &config_path).unwrap();
+        
+        let reloaded = tokio::time::timeout(Duration::from_secs(5), changes.recv()).await.unwrap().unwrap();
+        assert_eq!(reloaded.theme.corner_radius, 6.0);
+        assert_eq!(manager.get_config().await.theme.corner_radius, 6.0);
+    }
 }
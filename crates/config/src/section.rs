@@ -0,0 +1,93 @@
+//! Typed access to configuration sections
+//!
+//! Each top-level table of the configuration file, e.g. `[theme]`, is a
+//! [`ConfigSection`], so tools can read and replace one section generically:
+//!
+//! ```
+//! use config::{CompositorConfig, ThemeConfig};
+//!
+//! let mut config = CompositorConfig::default();
+//! config.section_mut::<ThemeConfig>().corner_radius = 8.0;
+//! assert_eq!(config.section::<ThemeConfig>().corner_radius, 8.0);
+//! ```
+
+use crate::*;
+use serde::de::DeserializeOwned;
+
+/// A top-level section of the configuration
+pub trait ConfigSection: Clone + Default + Serialize + DeserializeOwned {
+    /// Key of the section in configuration files
+    const KEY: &'static str;
+
+    fn get(config: &CompositorConfig) -> &Self;
+
+    fn get_mut(config: &mut CompositorConfig) -> &mut Self;
+}
+
+macro_rules! config_sections {
+    ($($section:ty => $field:ident),* $(,)?) => {
+        $(
+            impl ConfigSection for $section {
+                const KEY: &'static str = stringify!($field);
+
+                fn get(config: &CompositorConfig) -> &Self {
+                    &config.$field
+                }
+
+                fn get_mut(config: &mut CompositorConfig) -> &mut Self {
+                    &mut config.$field
+                }
+            }
+        )*
+
+        /// Keys of all sections, in file order
+        pub const SECTION_KEYS: &[&str] = &[$(stringify!($field)),*];
+    };
+}
+
+config_sections! {
+    DisplayConfig => display,
+    AppBarConfig => app_bar,
+    ThemeConfig => theme,
+    PerformanceConfig => performance,
+    PluginConfig => plugins,
+    HotCornersConfig => hot_corners,
+    PointerAccessibilityConfig => pointer_accessibility,
+    CursorConfig => cursor,
+    PointerBarriersConfig => pointer_barriers,
+    UnresponsiveDetectionConfig => unresponsive_detection,
+    ClientLimitsConfig => client_limits,
+    FocusModeConfig => focus_mode,
+    WindowDimmingConfig => window_dimming,
+    BlurConfig => blur,
+    Vec<WindowRule> => window_rules,
+    AutomationConfig => automation,
+    SecurityConfig => security,
+    LayoutsConfig => layouts,
+    KioskConfig => kiosk,
+    WaylandConfig => wayland,
+    ProtocolsConfig => protocols,
+    WorkspacesConfig => workspaces,
+    StartupFeedbackConfig => startup_feedback,
+    ColorPickerConfig => color_picker,
+    ScreenshotConfig => screenshot,
+    BindingsConfig => bindings,
+    WindowConfig => window,
+}
+
+impl CompositorConfig {
+    /// A section of the configuration
+    pub fn section<S: ConfigSection>(&self) -> &S {
+        S::get(self)
+    }
+
+    /// A section of the configuration, for modification
+    pub fn section_mut<S: ConfigSection>(&mut self) -> &mut S {
+        S::get_mut(self)
+    }
+
+    /// Replace a section, returning the previous one
+    pub fn set_section<S: ConfigSection>(&mut self, section: S) -> S {
+        std::mem::replace(S::get_mut(self), section)
+    }
+}
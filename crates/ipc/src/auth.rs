@@ -0,0 +1,257 @@
+// Control socket permission tiers
+//
+// Any process that can open the control socket can talk to the compositor,
+// so requests are checked against the peer credentials of the connection.
+// Each peer gets a tier from its UID and GID: read-only queries, window
+// control, or privileged requests that inject input or capture the screen.
+// Processes running as the compositor's user get window control by default;
+// everything else has to be granted explicitly.
+
+use crate::protocol::IPCMessage;
+use compositor_utils::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::net::UnixStream;
+
+/// What a control socket client may do, each tier including the ones below
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PermissionTier {
+    /// No requests at all
+    #[default]
+    None,
+    /// Queries and event subscriptions
+    ReadOnly,
+    /// Focusing, moving and launching windows and changing settings
+    Control,
+    /// Synthesizing input and capturing the screen
+    Privileged,
+}
+
+/// Credentials of the process on the other end of a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    pub pid: Option<i32>,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl PeerCredentials {
+    /// Credentials of the peer of a connected stream, as of when it connected
+    pub fn of(stream: &UnixStream) -> Result<Self> {
+        let cred = stream
+            .peer_cred()
+            .map_err(|e| CompositorError::ipc(format!("Failed to get peer credentials: {}", e)))?;
+        Ok(Self {
+            pid: cred.pid(),
+            uid: cred.uid(),
+            gid: cred.gid(),
+        })
+    }
+}
+
+/// Mapping from peer credentials to permission tiers
+///
+/// A tier set for the peer's UID takes precedence, then the owner tier if the
+/// peer runs as the compositor's user, then a tier set for its GID.
+#[derive(Debug, Clone)]
+pub struct IpcPermissions {
+    owner_uid: u32,
+    owner: PermissionTier,
+    others: PermissionTier,
+    uids: HashMap<u32, PermissionTier>,
+    gids: HashMap<u32, PermissionTier>,
+}
+
+impl IpcPermissions {
+    /// Permissions of a compositor running as `owner_uid`, granting its own
+    /// user window control and nothing to anyone else
+    pub fn new(owner_uid: u32) -> Self {
+        Self {
+            owner_uid,
+            owner: PermissionTier::Control,
+            others: PermissionTier::None,
+            uids: HashMap::new(),
+            gids: HashMap::new(),
+        }
+    }
+
+    /// Permissions of a compositor running as the current user
+    pub fn for_current_user() -> Self {
+        Self::new(nix::unistd::getuid().as_raw())
+    }
+
+    /// Set the tier of processes running as the compositor's user
+    pub fn with_owner(mut self, tier: PermissionTier) -> Self {
+        self.owner = tier;
+        self
+    }
+
+    /// Set the tier of processes matching no other entry
+    pub fn with_others(mut self, tier: PermissionTier) -> Self {
+        self.others = tier;
+        self
+    }
+
+    /// Set the tier of processes running as a user
+    pub fn with_uid(mut self, uid: u32, tier: PermissionTier) -> Self {
+        self.uids.insert(uid, tier);
+        self
+    }
+
+    /// Set the tier of processes whose primary group is `gid`
+    pub fn with_gid(mut self, gid: u32, tier: PermissionTier) -> Self {
+        self.gids.insert(gid, tier);
+        self
+    }
+
+    /// Tier of a peer
+    pub fn tier_for(&self, peer: &PeerCredentials) -> PermissionTier {
+        if let Some(&tier) = self.uids.get(&peer.uid) {
+            return tier;
+        }
+        if peer.uid == self.owner_uid {
+            return self.owner;
+        }
+        self.gids.get(&peer.gid).copied().unwrap_or(self.others)
+    }
+
+    /// Whether a peer may send a request
+    pub fn allows(&self, peer: &PeerCredentials, message: &IPCMessage) -> bool {
+        self.tier_for(peer) >= message.required_tier()
+    }
+}
+
+impl Default for IpcPermissions {
+    fn default() -> Self {
+        Self::for_current_user()
+    }
+}
+
+impl IPCMessage {
+    /// Tier a client needs to send this message
+    ///
+    /// Every message is listed, so new ones have to be given a tier. Screen
+    /// capture requests, once added, belong in the privileged tier.
+    pub fn required_tier(&self) -> PermissionTier {
+        match self {
+            IPCMessage::WarpPointer { .. } | IPCMessage::InjectInput { .. } => PermissionTier::Privileged,
+            IPCMessage::FocusWindow { .. }
+            | IPCMessage::SetParameter { .. }
+            | IPCMessage::ResetParameter { .. }
+            | IPCMessage::SetFocusMode { .. }
+            | IPCMessage::SetRenderScale { .. }
+            | IPCMessage::SetOutputScale { .. }
+            | IPCMessage::SetOutputTransform { .. }
+            | IPCMessage::SetPresentMode { .. }
+            | IPCMessage::SaveLayout { .. }
+            | IPCMessage::RestoreLayout { .. }
+            | IPCMessage::DeleteLayout { .. }
+            | IPCMessage::ApplyWindowBatch { .. }
+            | IPCMessage::Launch { .. }
+            | IPCMessage::SwitchWorkspace { .. }
+            | IPCMessage::ReloadConfig
+            | IPCMessage::PreviewTheme { .. }
+            | IPCMessage::CloseThemePreview
+            | IPCMessage::ToggleFlatAccel
+            | IPCMessage::SelectScreenshotRegion => PermissionTier::Control,
+            IPCMessage::GetWindowInfo { .. }
+            | IPCMessage::GetStatus
+            | IPCMessage::ListParameters
+            | IPCMessage::GetFocusMode
+            | IPCMessage::GetRenderScale { .. }
+            | IPCMessage::GetOutputScale { .. }
+            | IPCMessage::GetOutputTransform { .. }
+            | IPCMessage::GetPresentMode
+            | IPCMessage::GetGpuMemory
+            | IPCMessage::GetFrameStats
+            | IPCMessage::GetClients
+            | IPCMessage::GetClientLatency
+            | IPCMessage::GetBufferFormats
+            | IPCMessage::ListLayouts
+            | IPCMessage::SubscribeWindowEvents
+            | IPCMessage::ListWindows
+            | IPCMessage::ListOutputs => PermissionTier::ReadOnly,
+            // Replies only ever come from the compositor
+            IPCMessage::WindowInfo { .. }
+            | IPCMessage::Status { .. }
+            | IPCMessage::Parameters { .. }
+            | IPCMessage::FocusMode { .. }
+            | IPCMessage::Accepted
+            | IPCMessage::RenderScale { .. }
+            | IPCMessage::OutputScale { .. }
+            | IPCMessage::OutputTransform { .. }
+            | IPCMessage::PresentMode { .. }
+            | IPCMessage::GpuMemory { .. }
+            | IPCMessage::FrameStats { .. }
+            | IPCMessage::Clients { .. }
+            | IPCMessage::ClientLatency { .. }
+            | IPCMessage::BufferFormats { .. }
+            | IPCMessage::Layouts { .. }
+            | IPCMessage::WindowEvent { .. }
+            | IPCMessage::Windows { .. }
+            | IPCMessage::Outputs { .. }
+            | IPCMessage::Error { .. } => PermissionTier::Privileged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWNER: u32 = 1000;
+
+    fn peer(uid: u32, gid: u32) -> PeerCredentials {
+        PeerCredentials { pid: None, uid, gid }
+    }
+
+    #[test]
+    fn owner_gets_control_and_others_nothing_by_default() {
+        let permissions = IpcPermissions::new(OWNER);
+        assert_eq!(permissions.tier_for(&peer(OWNER, 100)), PermissionTier::Control);
+        assert_eq!(permissions.tier_for(&peer(1001, 100)), PermissionTier::None);
+    }
+
+    #[test]
+    fn uid_entry_takes_precedence_over_owner_and_gid() {
+        let permissions = IpcPermissions::new(OWNER)
+            .with_uid(OWNER, PermissionTier::ReadOnly)
+            .with_gid(100, PermissionTier::Privileged);
+        assert_eq!(permissions.tier_for(&peer(OWNER, 100)), PermissionTier::ReadOnly);
+    }
+
+    #[test]
+    fn owner_takes_precedence_over_gid() {
+        let permissions = IpcPermissions::new(OWNER).with_gid(100, PermissionTier::Privileged);
+        assert_eq!(permissions.tier_for(&peer(OWNER, 100)), PermissionTier::Control);
+        assert_eq!(permissions.tier_for(&peer(1001, 100)), PermissionTier::Privileged);
+    }
+
+    #[test]
+    fn others_tier_applies_without_matching_entry() {
+        let permissions = IpcPermissions::new(OWNER)
+            .with_others(PermissionTier::ReadOnly)
+            .with_gid(100, PermissionTier::Control);
+        assert_eq!(permissions.tier_for(&peer(1001, 200)), PermissionTier::ReadOnly);
+    }
+
+    #[test]
+    fn messages_require_their_tier() {
+        assert_eq!(IPCMessage::ListWindows.required_tier(), PermissionTier::ReadOnly);
+        assert_eq!(IPCMessage::FocusWindow { window_id: 1 }.required_tier(), PermissionTier::Control);
+        assert_eq!(IPCMessage::WarpPointer { x: 0.0, y: 0.0 }.required_tier(), PermissionTier::Privileged);
+        assert_eq!(IPCMessage::Accepted.required_tier(), PermissionTier::Privileged);
+    }
+
+    #[test]
+    fn allows_compares_peer_tier_with_message_tier() {
+        let permissions = IpcPermissions::new(OWNER).with_others(PermissionTier::ReadOnly);
+        let other = peer(1001, 100);
+        assert!(permissions.allows(&other, &IPCMessage::ListOutputs));
+        assert!(!permissions.allows(&other, &IPCMessage::ReloadConfig));
+        assert!(permissions.allows(&peer(OWNER, 100), &IPCMessage::ReloadConfig));
+        assert!(!permissions.allows(&peer(OWNER, 100), &IPCMessage::InjectInput {
+            event: crate::protocol::SyntheticInput::Click { button: 0x110 },
+        }));
+    }
+}
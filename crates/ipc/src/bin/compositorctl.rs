@@ -0,0 +1,115 @@
+// compositorctl - script the compositor over its control socket
+//
+// Sends one request to the control socket and prints the result as JSON,
+// like swaymsg and hyprctl:
+//
+//   compositorctl get_tree
+//   compositorctl get_workspaces
+//   compositorctl workspace DP-1 2
+//   compositorctl subscribe
+//
+// Anything that is not a method name is run as a command. `subscribe` prints
+// one event per line until the compositor exits.
+
+use ipc::socket::{default_socket_path, JsonMethod, JsonOutcome, JsonRequest, JsonResponse};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: compositorctl [-s SOCKET] [-r] <method | command...>
+
+Methods:
+  get_tree        outputs, each with its workspaces and their windows
+  get_outputs     connected outputs
+  get_workspaces  workspaces of every output with their window counts
+  subscribe       print window events as they happen
+
+Commands:
+  focus <window_id>
+  workspace <output> <index>
+  exec <program> [args...]
+  set <parameter> <value>
+  reload
+  toggle_flat_accel
+  screenshot_region
+
+Options:
+  -s, --socket SOCKET  control socket, instead of $CUSTOM_COMPOSITOR_SOCK
+                       or $XDG_RUNTIME_DIR/custom-compositor.sock
+  -r, --raw            print compact JSON";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("compositorctl: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut socket = None;
+    let mut raw = false;
+    let mut args = std::env::args().skip(1);
+    let mut words = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-s" | "--socket" if words.is_empty() => {
+                socket = Some(PathBuf::from(args.next().ok_or("--socket needs a path")?));
+            }
+            "-r" | "--raw" if words.is_empty() => raw = true,
+            "-h" | "--help" if words.is_empty() => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => words.push(arg),
+        }
+    }
+    let method = match words.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] => return Err(USAGE.to_string()),
+        ["get_tree"] => JsonMethod::GetTree,
+        ["get_outputs"] => JsonMethod::GetOutputs,
+        ["get_workspaces"] => JsonMethod::GetWorkspaces,
+        ["subscribe"] => JsonMethod::Subscribe,
+        _ => JsonMethod::RunCommand { command: words.join(" ") },
+    };
+    let subscribe = method == JsonMethod::Subscribe;
+
+    let socket = socket
+        .or_else(default_socket_path)
+        .ok_or("XDG_RUNTIME_DIR is not set and no socket was given")?;
+    let mut stream =
+        UnixStream::connect(&socket).map_err(|e| format!("Cannot connect to {}: {}", socket.display(), e))?;
+    let mut request = serde_json::to_vec(&JsonRequest { id: Some(1), method }).map_err(|e| e.to_string())?;
+    request.push(b'\n');
+    stream.write_all(&request).map_err(|e| e.to_string())?;
+
+    let mut lines = BufReader::new(stream).lines();
+    let line = lines
+        .next()
+        .ok_or("The compositor closed the connection")?
+        .map_err(|e| e.to_string())?;
+    let response: JsonResponse = serde_json::from_str(&line).map_err(|e| format!("Invalid response: {}", e))?;
+    match response.outcome {
+        JsonOutcome::Result(result) => print_json(&result, raw)?,
+        JsonOutcome::Error(message) => return Err(message),
+    }
+    if subscribe {
+        for line in lines {
+            let event: serde_json::Value =
+                serde_json::from_str(&line.map_err(|e| e.to_string())?).map_err(|e| format!("Invalid event: {}", e))?;
+            // Events are printed one per line so they can be piped
+            print_json(&event, true)?;
+        }
+    }
+    Ok(())
+}
+
+fn print_json(value: &serde_json::Value, raw: bool) -> Result<(), String> {
+    let text = if raw { serde_json::to_string(value) } else { serde_json::to_string_pretty(value) };
+    println!("{}", text.map_err(|e| e.to_string())?);
+    Ok(())
+}
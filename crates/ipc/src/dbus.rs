@@ -1,26 +1,362 @@
 // D-Bus integration for desktop environment communication
 //
-// This module handles D-Bus communication for the compositor to integrate
-// with desktop environments, session managers, and other system services.
+// Panels and scripts that speak D-Bus rather than the IPC socket find the
+// compositor under BUS_NAME on the session bus. They can list outputs and
+// windows, focus a window, switch workspaces and reload the configuration,
+// and receive signals when windows open, close or gain focus. Method calls
+// go through the IPC protocol handler, so D-Bus callers get the same
+// behavior and permission tiers as socket clients.
 
+use crate::auth::PeerCredentials;
+use crate::protocol::{IPCMessage, OutputInfo, ProtocolHandler, WindowEvent, WindowSummary};
+use compositor_utils::dbus::{Connection, Message, MessageType, Value, FAILED_ERROR, INTROSPECTABLE_INTERFACE, UNKNOWN_METHOD_ERROR};
 use compositor_utils::prelude::*;
+use std::sync::Arc;
+use tokio::sync::broadcast;
 
-/// D-Bus integration manager
+/// Well-known name owned on the session bus
+pub const BUS_NAME: &str = "org.customcompositor.Compositor";
+
+/// Object path of the compositor object
+pub const OBJECT_PATH: &str = "/org/customcompositor/Compositor";
+
+/// Interface of the compositor object
+pub const INTERFACE: &str = "org.customcompositor.Compositor1";
+
+/// Method call on the compositor interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DBusMethod {
+    /// ListOutputs() -> a(siiuudiu)
+    ListOutputs,
+    /// ListWindows() -> a(ussbb)
+    ListWindows,
+    /// FocusWindow(u window_id)
+    FocusWindow { window_id: u32 },
+    /// SwitchWorkspace(s output, u workspace)
+    SwitchWorkspace { output: String, workspace: usize },
+    /// ReloadConfig()
+    ReloadConfig,
+}
+
+impl DBusMethod {
+    /// Member name on the bus
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ListOutputs => "ListOutputs",
+            Self::ListWindows => "ListWindows",
+            Self::FocusWindow { .. } => "FocusWindow",
+            Self::SwitchWorkspace { .. } => "SwitchWorkspace",
+            Self::ReloadConfig => "ReloadConfig",
+        }
+    }
+
+    /// Method called by a message on the compositor interface
+    pub fn from_message(message: &Message) -> Option<Self> {
+        if message.interface.as_deref().is_some_and(|interface| interface != INTERFACE) {
+            return None;
+        }
+        let arg = |index: usize| message.body.get(index);
+        match message.member.as_deref()? {
+            "ListOutputs" => Some(Self::ListOutputs),
+            "ListWindows" => Some(Self::ListWindows),
+            "FocusWindow" => Some(Self::FocusWindow {
+                window_id: u32::try_from(arg(0)?.as_i64()?).ok()?,
+            }),
+            "SwitchWorkspace" => Some(Self::SwitchWorkspace {
+                output: arg(0)?.as_str()?.to_string(),
+                workspace: usize::try_from(arg(1)?.as_i64()?).ok()?,
+            }),
+            "ReloadConfig" => Some(Self::ReloadConfig),
+            _ => None,
+        }
+    }
+
+    /// IPC request carrying out the call
+    fn into_message(self) -> IPCMessage {
+        match self {
+            Self::ListOutputs => IPCMessage::ListOutputs,
+            Self::ListWindows => IPCMessage::ListWindows,
+            Self::FocusWindow { window_id } => IPCMessage::FocusWindow { window_id },
+            Self::SwitchWorkspace { output, workspace } => IPCMessage::SwitchWorkspace { output, workspace },
+            Self::ReloadConfig => IPCMessage::ReloadConfig,
+        }
+    }
+}
+
+/// Return value of a method call
+#[derive(Debug, Clone, PartialEq)]
+pub enum DBusReply {
+    Outputs(Vec<OutputInfo>),
+    Windows(Vec<WindowSummary>),
+    /// The call returns nothing
+    Done,
+}
+
+impl DBusReply {
+    /// Body of the method return
+    pub fn body(&self) -> Vec<Value> {
+        match self {
+            Self::Outputs(outputs) => vec![Value::Array(
+                "(siiuudiu)".to_string(),
+                outputs
+                    .iter()
+                    .map(|output| {
+                        Value::Struct(vec![
+                            Value::from(output.name.as_str()),
+                            Value::Int32(output.geometry.x),
+                            Value::Int32(output.geometry.y),
+                            Value::UInt32(output.geometry.width),
+                            Value::UInt32(output.geometry.height),
+                            Value::Double(output.scale),
+                            Value::Int32(output.refresh),
+                            Value::UInt32(u32::try_from(output.active_workspace).unwrap_or(u32::MAX)),
+                        ])
+                    })
+                    .collect(),
+            )],
+            Self::Windows(windows) => vec![Value::Array(
+                "(ussbb)".to_string(),
+                windows
+                    .iter()
+                    .map(|window| {
+                        Value::Struct(vec![
+                            Value::UInt32(window.window_id),
+                            Value::from(window.title.as_str()),
+                            Value::from(window.app_id.as_str()),
+                            Value::Bool(window.focused),
+                            Value::Bool(window.urgent),
+                        ])
+                    })
+                    .collect(),
+            )],
+            Self::Done => Vec::new(),
+        }
+    }
+}
+
+/// Signal emitted on the compositor interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DBusSignal {
+    /// WindowOpened(u window_id, s title, s app_id)
+    WindowOpened { window_id: u32, title: String, app_id: String },
+    /// WindowClosed(u window_id)
+    WindowClosed { window_id: u32 },
+    /// FocusChanged(u window_id)
+    FocusChanged { window_id: u32 },
+}
+
+impl DBusSignal {
+    /// Signal announcing a window event, if panels are told about it
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match event {
+            WindowEvent::Opened { window_id, title, app_id } => Some(Self::WindowOpened {
+                window_id: *window_id,
+                title: title.clone(),
+                app_id: app_id.clone(),
+            }),
+            WindowEvent::Closed { window_id } => Some(Self::WindowClosed { window_id: *window_id }),
+            WindowEvent::Activated { window_id } => Some(Self::FocusChanged { window_id: *window_id }),
+            _ => None,
+        }
+    }
+
+    /// Member name on the bus
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::WindowOpened { .. } => "WindowOpened",
+            Self::WindowClosed { .. } => "WindowClosed",
+            Self::FocusChanged { .. } => "FocusChanged",
+        }
+    }
+
+    /// Arguments of the signal
+    pub fn body(&self) -> Vec<Value> {
+        match self {
+            Self::WindowOpened { window_id, title, app_id } => {
+                vec![Value::UInt32(*window_id), Value::from(title.as_str()), Value::from(app_id.as_str())]
+            }
+            Self::WindowClosed { window_id } | Self::FocusChanged { window_id } => vec![Value::UInt32(*window_id)],
+        }
+    }
+}
+
+/// Introspection data of the compositor object
+const INTROSPECTION: &str = r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">
+<node>
+  <interface name="org.customcompositor.Compositor1">
+    <method name="ListOutputs"><arg direction="out" type="a(siiuudiu)"/></method>
+    <method name="ListWindows"><arg direction="out" type="a(ussbb)"/></method>
+    <method name="FocusWindow"><arg name="window_id" direction="in" type="u"/></method>
+    <method name="SwitchWorkspace">
+      <arg name="output" direction="in" type="s"/>
+      <arg name="workspace" direction="in" type="u"/>
+    </method>
+    <method name="ReloadConfig"/>
+    <signal name="WindowOpened"><arg name="window_id" type="u"/><arg name="title" type="s"/><arg name="app_id" type="s"/></signal>
+    <signal name="WindowClosed"><arg name="window_id" type="u"/></signal>
+    <signal name="FocusChanged"><arg name="window_id" type="u"/></signal>
+  </interface>
+  <interface name="org.freedesktop.DBus.Introspectable">
+    <method name="Introspect"><arg direction="out" type="s"/></method>
+  </interface>
+</node>
+"#;
+
+/// Compositor service on the session bus
 pub struct DBusManager {
-    // Placeholder for D-Bus state
+    handler: Arc<ProtocolHandler>,
 }
 
 impl DBusManager {
-    /// Create a new D-Bus manager
-    pub fn new() -> Result<Self> {
+    /// Create a service answering calls with the given protocol handler
+    pub fn new(handler: Arc<ProtocolHandler>) -> Self {
         info!("Initializing D-Bus Manager");
+        Self { handler }
+    }
+
+    /// Answer a method call from a peer, checked against its permission tier
+    pub async fn call(&self, peer: &PeerCredentials, method: DBusMethod) -> Result<DBusReply> {
+        debug!("D-Bus call {} from {:?}", method.name(), peer);
+        match self.handler.handle_request(peer, method.into_message()).await? {
+            IPCMessage::Outputs { outputs } => Ok(DBusReply::Outputs(outputs)),
+            IPCMessage::Windows { windows } => Ok(DBusReply::Windows(windows)),
+            IPCMessage::Accepted => Ok(DBusReply::Done),
+            IPCMessage::Error { message } => Err(CompositorError::ipc(message)),
+            reply => Err(CompositorError::ipc(format!("Unexpected reply to D-Bus call: {:?}", reply))),
+        }
+    }
+
+    /// Subscribe to the signals to emit
+    pub fn signals(&self) -> Result<DBusSignals> {
+        Ok(DBusSignals { events: self.handler.subscribe_window_events()? })
+    }
+
+    /// Own BUS_NAME on the session bus and serve the compositor object
+    /// until the bus connection closes or window events end
+    pub async fn run(self) -> Result<()> {
+        let (connection, mut incoming) = Connection::session().await?;
+        connection.request_name(BUS_NAME).await?;
+        info!("D-Bus service started as {}", BUS_NAME);
         
-        Ok(Self {})
+        let mut signals = self.signals()?;
+        loop {
+            tokio::select! {
+                message = incoming.recv() => {
+                    let Some(message) = message else { break };
+                    if message.kind == MessageType::MethodCall {
+                        if let Err(e) = self.answer(&connection, &message).await {
+                            warn!("Failed to answer D-Bus call {:?}: {}", message.member, e);
+                        }
+                    }
+                }
+                signal = signals.next() => {
+                    let Some(signal) = signal else { break };
+                    debug!("D-Bus signal {}", signal.name());
+                    if let Err(e) = connection.emit_signal(OBJECT_PATH, INTERFACE, signal.name(), signal.body()).await {
+                        warn!("Failed to emit D-Bus signal {}: {}", signal.name(), e);
+                    }
+                }
+            }
+        }
+        info!("D-Bus service stopped");
+        Ok(())
+    }
+    
+    /// Reply to a method call received on the bus
+    async fn answer(&self, connection: &Connection, message: &Message) -> Result<()> {
+        if message.path.as_deref() != Some(OBJECT_PATH) {
+            return connection
+                .reply_error(message, "org.freedesktop.DBus.Error.UnknownObject", "No such object")
+                .await;
+        }
+        if message.is_call(INTROSPECTABLE_INTERFACE, "Introspect") {
+            return connection.reply(message, vec![Value::from(INTROSPECTION)]).await;
+        }
+        let Some(method) = DBusMethod::from_message(message) else {
+            return connection
+                .reply_error(message, UNKNOWN_METHOD_ERROR, &format!("No method {:?}", message.member))
+                .await;
+        };
+        let peer = match message.sender.as_deref() {
+            Some(sender) => Self::peer_credentials(connection, sender).await?,
+            None => return connection.reply_error(message, FAILED_ERROR, "Unknown sender").await,
+        };
+        match self.call(&peer, method).await {
+            Ok(reply) => connection.reply(message, reply.body()).await,
+            Err(e) => connection.reply_error(message, FAILED_ERROR, &e.to_string()).await,
+        }
+    }
+    
+    /// Credentials of the process owning a bus name, from the bus daemon
+    ///
+    /// The primary group comes from /proc; peers whose process is gone get
+    /// no group, so only their user can grant them a tier.
+    async fn peer_credentials(connection: &Connection, sender: &str) -> Result<PeerCredentials> {
+        let reply = connection
+            .call_method(
+                compositor_utils::dbus::BUS_NAME,
+                compositor_utils::dbus::BUS_PATH,
+                compositor_utils::dbus::BUS_INTERFACE,
+                "GetConnectionCredentials",
+                vec![Value::from(sender)],
+            )
+            .await?;
+        let credentials = reply
+            .first()
+            .ok_or_else(|| CompositorError::ipc(format!("No credentials for {}", sender)))?;
+        let uid = credentials
+            .get("UnixUserID")
+            .and_then(Value::as_i64)
+            .and_then(|uid| u32::try_from(uid).ok())
+            .ok_or_else(|| CompositorError::ipc(format!("No user ID for {}", sender)))?;
+        let pid = credentials
+            .get("ProcessID")
+            .and_then(Value::as_i64)
+            .and_then(|pid| i32::try_from(pid).ok());
+        let gid = pid.and_then(primary_gid).unwrap_or(u32::MAX);
+        Ok(PeerCredentials { pid, uid, gid })
     }
 }
 
+/// Real group ID of a process
+fn primary_gid(pid: i32) -> Option<u32> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Gid:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
 impl Default for DBusManager {
     fn default() -> Self {
-        Self::new().expect("Failed to create D-Bus manager")
+        Self::new(Arc::new(ProtocolHandler::default()))
+    }
+}
+
+/// Signals to emit, from the compositor's window events
+pub struct DBusSignals {
+    events: broadcast::Receiver<WindowEvent>,
+}
+
+impl DBusSignals {
+    /// Next signal; `None` once the compositor stops sending window events
+    pub async fn next(&mut self) -> Option<DBusSignal> {
+        loop {
+            match self.events.recv().await {
+                Ok(event) => {
+                    if let Some(signal) = DBusSignal::from_window_event(&event) {
+                        return Some(signal);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("D-Bus signals fell behind, {} window events dropped", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
     }
 }
@@ -5,6 +5,7 @@
 
 use compositor_utils::prelude::*;
 
+pub mod auth;
 pub mod dbus;
 pub mod socket;
 pub mod protocol;
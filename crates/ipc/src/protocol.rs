@@ -3,8 +3,14 @@
 // This module defines the protocol messages and serialization for
 // communication between the compositor and external applications.
 
+use crate::auth::{IpcPermissions, PeerCredentials};
 use compositor_utils::prelude::*;
+use compositor_utils::params::ParameterRegistry;
+use compositor_utils::frame_stats::{FrameStatistics, SurfaceFrameStats};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, watch};
 
 /// IPC message types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +24,8 @@ pub enum IPCMessage {
         title: String,
         app_id: String,
         geometry: WindowGeometry,
+        /// Process owning the window, when its credentials are known
+        process: Option<ClientProcessInfo>,
     },
     
     /// Request to focus a window
@@ -33,12 +41,410 @@ pub enum IPCMessage {
         memory_usage: u64,
     },
     
+    /// Request all live-tunable parameters
+    ListParameters,
+    
+    /// Set a live-tunable parameter (e.g. "effects.blur_radius")
+    SetParameter { name: String, value: f32 },
+    
+    /// Reset a live-tunable parameter to its default value
+    ResetParameter { name: String },
+    
+    /// Live-tunable parameter listing response
+    Parameters { parameters: Vec<ParameterInfo> },
+    
+    /// Request the focus mode (do-not-disturb) state
+    GetFocusMode,
+    
+    /// Force focus mode on or off, or return it to automatic scheduling
+    SetFocusMode { mode: FocusModeOverride },
+    
+    /// Focus mode state response
+    FocusMode { mode: FocusModeOverride },
+    
+    /// Move the pointer to a position in global logical coordinates
+    WarpPointer { x: f64, y: f64 },
+    
+    /// Synthesize an input event (requires the input injection permission)
+    InjectInput { event: SyntheticInput },
+    
+    /// Request accepted and queued for the compositor
+    Accepted,
+    
+    /// Request the internal render scale of an output
+    GetRenderScale { output: String },
+    
+    /// Render an output at reduced internal resolution (0.25 - 1.0) and upscale on scanout
+    SetRenderScale { output: String, scale: f32 },
+    
+    /// Render scale response
+    RenderScale { output: String, scale: f32 },
+    
+    /// Request the scale clients are told to render an output at
+    GetOutputScale { output: String },
+    
+    /// Change an output's scale (0.5 - 4.0, fractional allowed) without restarting clients
+    SetOutputScale { output: String, scale: f64 },
+    
+    /// Output scale response
+    OutputScale { output: String, scale: f64 },
+    
+    /// Request the rotation and flip of an output
+    GetOutputTransform { output: String },
+    
+    /// Rotate or flip an output at runtime
+    SetOutputTransform { output: String, transform: DisplayTransform },
+    
+    /// Output transform response
+    OutputTransform { output: String, transform: DisplayTransform },
+    
+    /// Request the requested swapchain present mode
+    GetPresentMode,
+    
+    /// Change the swapchain present mode, trading tearing for latency
+    SetPresentMode { mode: PresentMode },
+    
+    /// Present mode response
+    PresentMode { mode: PresentMode },
+    
+    /// Request GPU memory usage against the driver-reported budget
+    GetGpuMemory,
+    
+    /// GPU memory usage response
+    GpuMemory { stats: GpuMemoryStats },
+    
+    /// Request per-surface frame statistics (requires frame statistics to be enabled)
+    GetFrameStats,
+    
+    /// Per-surface frame statistics response
+    FrameStats { surfaces: Vec<SurfaceFrameStats> },
+    
+    /// Request per-client resource usage
+    GetClients,
+    
+    /// Per-client resource usage response
+    Clients { clients: Vec<ClientResourceUsage> },
+    
+    /// Request per-client protocol round-trip latencies
+    GetClientLatency,
+    
+    /// Per-client protocol round-trip latency response
+    ClientLatency { clients: Vec<ClientLatencyStats> },
+    
+    /// Request statistics on the buffer formats clients attach
+    GetBufferFormats,
+    
+    /// Buffer format statistics response
+    BufferFormats { formats: Vec<BufferFormatUsage> },
+    
+    /// Request the names of saved window layouts
+    ListLayouts,
+    
+    /// Save the current arrangement of windows and workspaces under a name
+    SaveLayout { name: String },
+    
+    /// Restore a saved layout, relaunching applications that are not running
+    RestoreLayout { name: String },
+    
+    /// Delete a saved layout
+    DeleteLayout { name: String },
+    
+    /// Saved window layouts response
+    Layouts { names: Vec<String> },
+    
+    /// Move, resize and reassign several windows as one atomic change
+    ApplyWindowBatch { operations: Vec<WindowOperation> },
+    
+    /// Launch an application with an xdg-activation token in its
+    /// environment, so its first window may take focus
+    Launch { command: Vec<String>, app_id: Option<String> },
+    
+    /// Subscribe the connection to window events, e.g. for taskbars
+    SubscribeWindowEvents,
+    
+    /// Window event pushed to subscribed connections
+    WindowEvent { event: WindowEvent },
+    
+    /// Request the mapped windows
+    ListWindows,
+    
+    /// Mapped windows response
+    Windows { windows: Vec<WindowSummary> },
+    
+    /// Request the connected outputs
+    ListOutputs,
+    
+    /// Connected outputs response
+    Outputs { outputs: Vec<OutputInfo> },
+    
+    /// Switch the active workspace of an output
+    SwitchWorkspace { output: String, workspace: usize },
+    
+    /// Reload the configuration files
+    ReloadConfig,
+    
+    /// Show sample compositor UI styled with a candidate theme: keys of the
+    /// `[theme]` table in TOML, applied over the current theme
+    PreviewTheme { theme: String },
+    
+    /// Close the theme preview
+    CloseThemePreview,
+    
+    /// Switch the pointer in use to a flat acceleration profile, or back to
+    /// its configured one
+    ToggleFlatAccel,
+    
+    /// Show the region selection overlay to screenshot a region
+    SelectScreenshotRegion,
+    
     /// Error response
     Error { message: String },
 }
 
+/// Manual focus mode override set over IPC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FocusModeOverride {
+    /// Follow the configured schedule and app triggers
+    #[default]
+    Auto,
+    /// Force focus mode on
+    On,
+    /// Force focus mode off
+    Off,
+}
+
+/// Rotation and flip of an output
+///
+/// Rotations are counter-clockwise, as in wl_output.transform; flipped
+/// variants mirror around the vertical axis before rotating.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayTransform {
+    #[default]
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    Flipped,
+    Flipped90,
+    Flipped180,
+    Flipped270,
+}
+
+/// How frames are synchronized with the display refresh
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresentMode {
+    /// Wait for vblank; no tearing, up to a frame of extra latency
+    Fifo,
+    /// Replace the queued frame at vblank; no tearing, lower latency
+    #[default]
+    Mailbox,
+    /// Present right away; lowest latency, may tear
+    Immediate,
+}
+
+/// Synthetic input event for automation and accessibility tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyntheticInput {
+    /// Press or release a pointer button (Linux input event code, e.g. 0x110 = BTN_LEFT)
+    PointerButton { button: u32, pressed: bool },
+    /// Press and release a pointer button
+    Click { button: u32 },
+    /// Press or release a key (Linux input event code, e.g. 30 = KEY_A)
+    Key { keycode: u32, pressed: bool },
+}
+
+/// Automation request forwarded from IPC to the compositor
+#[derive(Debug, Clone, PartialEq)]
+pub enum AutomationRequest {
+    /// Move the pointer to a global logical position
+    WarpPointer { x: f64, y: f64 },
+    /// Synthesize an input event
+    Input(SyntheticInput),
+    /// Give keyboard focus to a window
+    FocusWindow { window_id: u32 },
+    /// Switch the active workspace of an output
+    SwitchWorkspace { output: String, workspace: usize },
+}
+
+/// Change to one window in an atomic batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowOperation {
+    /// Move a window to a global logical position
+    Move { window_id: u32, x: i32, y: i32 },
+    /// Resize a window to a logical size
+    Resize { window_id: u32, width: i32, height: i32 },
+    /// Put a window on another workspace of its output
+    MoveToWorkspace { window_id: u32, workspace: usize },
+}
+
+impl WindowOperation {
+    /// Window the operation applies to
+    pub fn window_id(&self) -> u32 {
+        match self {
+            Self::Move { window_id, .. } | Self::Resize { window_id, .. } | Self::MoveToWorkspace { window_id, .. } => {
+                *window_id
+            }
+        }
+    }
+}
+
+/// Change in the set of windows or their titles and app IDs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowEvent {
+    /// A window was mapped
+    Opened { window_id: u32, title: String, app_id: String },
+    /// A window set a different title
+    TitleChanged { window_id: u32, title: String },
+    /// A window set a different app ID
+    AppIdChanged { window_id: u32, app_id: String },
+    /// A window was destroyed
+    Closed { window_id: u32 },
+    /// A window received keyboard focus
+    Activated { window_id: u32 },
+    /// A window lost keyboard focus
+    Deactivated { window_id: u32 },
+    /// A window asked for attention, e.g. by ringing the bell
+    Urgent { window_id: u32 },
+    /// A window no longer needs attention, usually because it got focus
+    UrgencyCleared { window_id: u32 },
+}
+
+impl WindowEvent {
+    /// Window the event is about
+    pub fn window_id(&self) -> u32 {
+        match self {
+            Self::Opened { window_id, .. }
+            | Self::TitleChanged { window_id, .. }
+            | Self::AppIdChanged { window_id, .. }
+            | Self::Closed { window_id }
+            | Self::Activated { window_id }
+            | Self::Deactivated { window_id }
+            | Self::Urgent { window_id }
+            | Self::UrgencyCleared { window_id } => *window_id,
+        }
+    }
+    
+    /// Update a list of windows with the event, for keeping it current from
+    /// a subscription
+    pub fn apply(&self, windows: &mut Vec<WindowSummary>) {
+        let window_id = self.window_id();
+        if let Self::Opened { title, app_id, .. } = self {
+            windows.retain(|window| window.window_id != window_id);
+            windows.push(WindowSummary {
+                window_id,
+                title: title.clone(),
+                app_id: app_id.clone(),
+                focused: false,
+                urgent: false,
+                output: None,
+                workspace: None,
+            });
+            return;
+        }
+        if matches!(self, Self::Closed { .. }) {
+            windows.retain(|window| window.window_id != window_id);
+            return;
+        }
+        if matches!(self, Self::Activated { .. }) {
+            windows.iter_mut().for_each(|window| window.focused = false);
+        }
+        let Some(window) = windows.iter_mut().find(|window| window.window_id == window_id) else { return };
+        match self {
+            Self::TitleChanged { title, .. } => window.title = title.clone(),
+            Self::AppIdChanged { app_id, .. } => window.app_id = app_id.clone(),
+            Self::Activated { .. } => window.focused = true,
+            Self::Deactivated { .. } => window.focused = false,
+            Self::Urgent { .. } => window.urgent = true,
+            Self::UrgencyCleared { .. } => window.urgent = false,
+            Self::Opened { .. } | Self::Closed { .. } => {}
+        }
+    }
+}
+
+/// Mapped window, as listed over IPC
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowSummary {
+    pub window_id: u32,
+    pub title: String,
+    pub app_id: String,
+    /// Whether the window has keyboard focus
+    pub focused: bool,
+    /// Whether the window asks for attention
+    pub urgent: bool,
+    /// Output the window lives on, once placed
+    #[serde(default)]
+    pub output: Option<String>,
+    /// Workspace the window lives on, once placed
+    #[serde(default)]
+    pub workspace: Option<usize>,
+}
+
+/// Connected output, as listed over IPC
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputInfo {
+    /// Connector name, e.g. "DP-1"
+    pub name: String,
+    /// Position and size in global logical coordinates
+    pub geometry: WindowGeometry,
+    pub scale: f64,
+    /// Refresh rate in mHz
+    pub refresh: i32,
+    /// Index of the active workspace
+    pub active_workspace: usize,
+    /// Number of workspaces on the output
+    pub workspace_count: usize,
+}
+
+/// Theme preview request forwarded from IPC to the compositor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThemePreviewRequest {
+    /// Show the preview with the theme keys in TOML, replacing any open one
+    Open { theme: String },
+    Close,
+}
+
+/// Window layout request forwarded from IPC to the compositor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutRequest {
+    Save { name: String },
+    Restore { name: String },
+    Delete { name: String },
+}
+
+impl LayoutRequest {
+    /// Name of the layout the request is for
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Save { name } | Self::Restore { name } | Self::Delete { name } => name,
+        }
+    }
+}
+
+/// Application launch forwarded from IPC to the compositor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchRequest {
+    /// Program and arguments
+    pub command: Vec<String>,
+    /// App ID the application's windows will have, for startup feedback
+    pub app_id: Option<String>,
+}
+
+/// Whether a layout name can be used as a file name
+pub fn is_valid_layout_name(name: &str) -> bool {
+    !name.trim().is_empty() && !name.starts_with('.') && !name.contains('/')
+}
+
+/// Device-local GPU memory usage in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GpuMemoryStats {
+    pub used: u64,
+    pub budget: u64,
+    /// Whether the numbers come from VK_EXT_memory_budget rather than heap sizes
+    pub from_driver: bool,
+}
+
 /// Window geometry information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WindowGeometry {
     pub x: i32,
     pub y: i32,
@@ -46,15 +452,335 @@ pub struct WindowGeometry {
     pub height: u32,
 }
 
+/// Process behind a client connection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientProcessInfo {
+    pub pid: i32,
+    pub uid: u32,
+    pub executable: Option<String>,
+}
+
+/// Client resource with a configurable soft limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResourceLimit {
+    Surfaces,
+    BufferMemory,
+    TextureMemory,
+    CallbackRate,
+}
+
+/// Resource usage of one client connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientResourceUsage {
+    /// Connection number, stable for the lifetime of the connection
+    pub id: u64,
+    pub process: Option<ClientProcessInfo>,
+    pub surfaces: u32,
+    /// Memory of the buffers currently attached to the client's surfaces
+    pub buffer_bytes: u64,
+    /// GPU memory the compositor holds for copies of the client's buffers
+    pub texture_bytes: u64,
+    /// Frame callbacks requested per second
+    pub callbacks_per_second: f32,
+    /// Soft limits the client is currently over
+    pub exceeded_limits: Vec<ResourceLimit>,
+    /// Whether the client's frame callbacks are being throttled
+    pub throttled: bool,
+}
+
+/// Aggregate of recent round-trip latencies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    /// Round trips measured since the client connected
+    pub samples: u64,
+    /// Statistics over the most recent round trips, in milliseconds
+    pub average_ms: f32,
+    pub p95_ms: f32,
+    pub max_ms: f32,
+}
+
+/// Protocol round-trip latencies of one client connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientLatencyStats {
+    /// Connection number, as in `ClientResourceUsage`
+    pub id: Option<u64>,
+    pub process: Option<ClientProcessInfo>,
+    /// xdg_surface configure to ack_configure
+    pub configure: LatencySummary,
+    /// Frame callback done to the next commit of the surface
+    pub frame_callback: LatencySummary,
+}
+
+/// How a client buffer is shared with the compositor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BufferKind {
+    /// wl_shm, copied to the GPU on every commit
+    Shm,
+    /// linux-dmabuf, imported without a copy
+    Dmabuf,
+}
+
+/// Use of one buffer format and modifier by clients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufferFormatUsage {
+    pub kind: BufferKind,
+    /// DRM fourcc code
+    pub fourcc: u32,
+    /// Format name, e.g. "Argb8888"
+    pub format: String,
+    /// DRM format modifier of dmabuf buffers
+    pub modifier: Option<u64>,
+    /// Surfaces with a buffer of this format currently attached
+    pub surfaces: u32,
+    /// Commits of buffers in this format since the compositor started
+    pub commits: u64,
+    /// Whether the display hardware can scan the format out directly
+    pub scanout_capable: bool,
+}
+
+/// Live-tunable parameter information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParameterInfo {
+    pub name: String,
+    pub value: f32,
+    pub default: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
 /// Protocol handler for IPC messages
 pub struct ProtocolHandler {
-    // Placeholder for protocol state
+    parameters: Option<Arc<ParameterRegistry>>,
+    focus_mode: Option<watch::Sender<FocusModeOverride>>,
+    automation: Option<mpsc::UnboundedSender<AutomationRequest>>,
+    input_injection: bool,
+    permissions: IpcPermissions,
+    render_scale: Option<watch::Sender<HashMap<String, f32>>>,
+    output_scale: Option<watch::Sender<HashMap<String, f64>>>,
+    output_transform: Option<watch::Sender<HashMap<String, DisplayTransform>>>,
+    present_mode: Option<watch::Sender<PresentMode>>,
+    window_events: Option<broadcast::Sender<WindowEvent>>,
+    windows: Option<watch::Receiver<Vec<WindowSummary>>>,
+    outputs: Option<watch::Receiver<Vec<OutputInfo>>>,
+    config_reloads: Option<mpsc::UnboundedSender<()>>,
+    flat_accel_toggles: Option<mpsc::UnboundedSender<()>>,
+    region_screenshots: Option<mpsc::UnboundedSender<()>>,
+    theme_previews: Option<mpsc::UnboundedSender<ThemePreviewRequest>>,
+    gpu_memory: Option<watch::Receiver<GpuMemoryStats>>,
+    frame_stats: Option<Arc<FrameStatistics>>,
+    client_usage: Option<watch::Receiver<Vec<ClientResourceUsage>>>,
+    client_latency: Option<watch::Receiver<Vec<ClientLatencyStats>>>,
+    buffer_formats: Option<watch::Receiver<Vec<BufferFormatUsage>>>,
+    layout_requests: Option<mpsc::UnboundedSender<LayoutRequest>>,
+    saved_layouts: Option<watch::Receiver<Vec<String>>>,
+    window_batches: Option<mpsc::UnboundedSender<Vec<WindowOperation>>>,
+    launch_requests: Option<mpsc::UnboundedSender<LaunchRequest>>,
 }
 
 impl ProtocolHandler {
     /// Create a new protocol handler
     pub fn new() -> Self {
-        Self {}
+        Self {
+            parameters: None,
+            focus_mode: None,
+            automation: None,
+            input_injection: false,
+            permissions: IpcPermissions::default(),
+            render_scale: None,
+            output_scale: None,
+            output_transform: None,
+            present_mode: None,
+            window_events: None,
+            windows: None,
+            outputs: None,
+            config_reloads: None,
+            flat_accel_toggles: None,
+            region_screenshots: None,
+            theme_previews: None,
+            gpu_memory: None,
+            frame_stats: None,
+            client_usage: None,
+            client_latency: None,
+            buffer_formats: None,
+            layout_requests: None,
+            saved_layouts: None,
+            window_batches: None,
+            launch_requests: None,
+        }
+    }
+    
+    /// Allow tuning parameters in the given registry
+    pub fn with_parameters(mut self, parameters: Arc<ParameterRegistry>) -> Self {
+        self.parameters = Some(parameters);
+        self
+    }
+    
+    /// Allow toggling focus mode through the given channel
+    pub fn with_focus_mode(mut self, focus_mode: watch::Sender<FocusModeOverride>) -> Self {
+        self.focus_mode = Some(focus_mode);
+        self
+    }
+    
+    /// Allow pointer warping and focus changes through the given channel
+    pub fn with_automation(mut self, automation: mpsc::UnboundedSender<AutomationRequest>) -> Self {
+        self.automation = Some(automation);
+        self
+    }
+    
+    /// Grant the privileged permission to synthesize input events
+    ///
+    /// Off by default, since any client holding it can type and click on
+    /// behalf of the user.
+    pub fn with_input_injection(mut self, allowed: bool) -> Self {
+        self.input_injection = allowed;
+        self
+    }
+    
+    /// Set the permission tiers of peers, checked by `handle_request`
+    pub fn with_permissions(mut self, permissions: IpcPermissions) -> Self {
+        self.permissions = permissions;
+        self
+    }
+    
+    /// Allow switching the pointer in use to a flat acceleration profile
+    /// and back through the given channel
+    pub fn with_flat_accel_toggle(mut self, flat_accel_toggles: mpsc::UnboundedSender<()>) -> Self {
+        self.flat_accel_toggles = Some(flat_accel_toggles);
+        self
+    }
+    
+    /// Allow starting region screenshots through the given channel
+    pub fn with_region_screenshots(mut self, region_screenshots: mpsc::UnboundedSender<()>) -> Self {
+        self.region_screenshots = Some(region_screenshots);
+        self
+    }
+    
+    /// Allow changing per-output render scale through the given channel
+    pub fn with_render_scale(mut self, render_scale: watch::Sender<HashMap<String, f32>>) -> Self {
+        self.render_scale = Some(render_scale);
+        self
+    }
+    
+    /// Allow changing output scales through the given channel
+    pub fn with_output_scale(mut self, output_scale: watch::Sender<HashMap<String, f64>>) -> Self {
+        self.output_scale = Some(output_scale);
+        self
+    }
+    
+    /// Allow rotating and flipping outputs through the given channel
+    pub fn with_output_transform(mut self, output_transform: watch::Sender<HashMap<String, DisplayTransform>>) -> Self {
+        self.output_transform = Some(output_transform);
+        self
+    }
+    
+    /// Allow switching the present mode through the given channel
+    pub fn with_present_mode(mut self, present_mode: watch::Sender<PresentMode>) -> Self {
+        self.present_mode = Some(present_mode);
+        self
+    }
+    
+    /// Let connections subscribe to window events sent on the given channel
+    pub fn with_window_events(mut self, window_events: broadcast::Sender<WindowEvent>) -> Self {
+        self.window_events = Some(window_events);
+        self
+    }
+    
+    /// Receive window events for a connection that sent `SubscribeWindowEvents`
+    pub fn subscribe_window_events(&self) -> Result<broadcast::Receiver<WindowEvent>> {
+        Ok(self.window_events_sender()?.subscribe())
+    }
+    
+    /// Report the mapped windows published on the given channel
+    pub fn with_windows(mut self, windows: watch::Receiver<Vec<WindowSummary>>) -> Self {
+        self.windows = Some(windows);
+        self
+    }
+    
+    /// Report the connected outputs published on the given channel
+    pub fn with_outputs(mut self, outputs: watch::Receiver<Vec<OutputInfo>>) -> Self {
+        self.outputs = Some(outputs);
+        self
+    }
+    
+    /// Allow reloading the configuration through the given channel
+    pub fn with_config_reload(mut self, config_reloads: mpsc::UnboundedSender<()>) -> Self {
+        self.config_reloads = Some(config_reloads);
+        self
+    }
+    
+    /// Allow previewing themes through the given channel
+    pub fn with_theme_previews(mut self, theme_previews: mpsc::UnboundedSender<ThemePreviewRequest>) -> Self {
+        self.theme_previews = Some(theme_previews);
+        self
+    }
+    
+    /// Report GPU memory usage published on the given channel
+    pub fn with_gpu_memory(mut self, gpu_memory: watch::Receiver<GpuMemoryStats>) -> Self {
+        self.gpu_memory = Some(gpu_memory);
+        self
+    }
+    
+    /// Report per-surface frame statistics from the given recorder
+    pub fn with_frame_stats(mut self, frame_stats: Arc<FrameStatistics>) -> Self {
+        self.frame_stats = Some(frame_stats);
+        self
+    }
+    
+    /// Report per-client resource usage published on the given channel
+    pub fn with_client_usage(mut self, client_usage: watch::Receiver<Vec<ClientResourceUsage>>) -> Self {
+        self.client_usage = Some(client_usage);
+        self
+    }
+    
+    /// Report per-client protocol latencies published on the given channel
+    pub fn with_client_latency(mut self, client_latency: watch::Receiver<Vec<ClientLatencyStats>>) -> Self {
+        self.client_latency = Some(client_latency);
+        self
+    }
+    
+    /// Report buffer format statistics published on the given channel
+    pub fn with_buffer_formats(mut self, buffer_formats: watch::Receiver<Vec<BufferFormatUsage>>) -> Self {
+        self.buffer_formats = Some(buffer_formats);
+        self
+    }
+    
+    /// Allow saving and restoring window layouts through the given channels
+    pub fn with_layouts(
+        mut self,
+        requests: mpsc::UnboundedSender<LayoutRequest>,
+        saved: watch::Receiver<Vec<String>>,
+    ) -> Self {
+        self.layout_requests = Some(requests);
+        self.saved_layouts = Some(saved);
+        self
+    }
+    
+    /// Allow atomic multi-window changes through the given channel
+    pub fn with_window_batches(mut self, window_batches: mpsc::UnboundedSender<Vec<WindowOperation>>) -> Self {
+        self.window_batches = Some(window_batches);
+        self
+    }
+    
+    /// Allow launching applications through the given channel
+    pub fn with_launches(mut self, launch_requests: mpsc::UnboundedSender<LaunchRequest>) -> Self {
+        self.launch_requests = Some(launch_requests);
+        self
+    }
+    
+    /// Handle a message received from a peer, if its tier allows it
+    pub async fn handle_request(&self, peer: &PeerCredentials, message: IPCMessage) -> Result<IPCMessage> {
+        let tier = self.permissions.tier_for(peer);
+        let required = message.required_tier();
+        if tier < required {
+            warn!(
+                "Rejected IPC request from {:?} with {:?} permission, needs {:?}: {:?}",
+                peer, tier, required, message
+            );
+            return Ok(IPCMessage::Error {
+                message: "Permission denied".to_string(),
+            });
+        }
+        self.handle_message(message).await
     }
     
     /// Handle an incoming IPC message
@@ -79,22 +805,381 @@ impl ProtocolHandler {
                         width: 800,
                         height: 600,
                     },
+                    process: None, // TODO: Fill from the owning client's credentials
                 })
             }
-            IPCMessage::FocusWindow { window_id: _ } => {
-                // TODO: Implement window focusing
-                Ok(IPCMessage::Status {
-                    version: env!("CARGO_PKG_VERSION").to_string(),
-                    active_windows: 0,
-                    memory_usage: 0,
+            IPCMessage::FocusWindow { window_id } => {
+                self.send_automation(AutomationRequest::FocusWindow { window_id })
+            }
+            IPCMessage::WarpPointer { x, y } => {
+                if !x.is_finite() || !y.is_finite() {
+                    return Ok(IPCMessage::Error { message: "Invalid pointer position".to_string() });
+                }
+                self.send_automation(AutomationRequest::WarpPointer { x, y })
+            }
+            IPCMessage::InjectInput { event } => {
+                if !self.input_injection {
+                    warn!("Rejected input injection without permission: {:?}", event);
+                    return Ok(IPCMessage::Error {
+                        message: "Input injection permission denied".to_string(),
+                    });
+                }
+                self.send_automation(AutomationRequest::Input(event))
+            }
+            IPCMessage::ListParameters => {
+                let registry = self.parameter_registry()?;
+                Ok(IPCMessage::Parameters {
+                    parameters: Self::parameter_infos(registry, |_| true),
                 })
             }
+            IPCMessage::SetParameter { name, value } => {
+                let registry = self.parameter_registry()?;
+                match registry.set(&name, value) {
+                    Ok(applied) => {
+                        debug!("Parameter {} set to {} via IPC", name, applied);
+                        Ok(IPCMessage::Parameters {
+                            parameters: Self::parameter_infos(registry, |n| n == name),
+                        })
+                    }
+                    Err(e) => Ok(IPCMessage::Error { message: e.to_string() }),
+                }
+            }
+            IPCMessage::ResetParameter { name } => {
+                let registry = self.parameter_registry()?;
+                match registry.reset(&name) {
+                    Ok(_) => Ok(IPCMessage::Parameters {
+                        parameters: Self::parameter_infos(registry, |n| n == name),
+                    }),
+                    Err(e) => Ok(IPCMessage::Error { message: e.to_string() }),
+                }
+            }
+            IPCMessage::GetFocusMode => {
+                let focus_mode = self.focus_mode_sender()?;
+                Ok(IPCMessage::FocusMode { mode: *focus_mode.borrow() })
+            }
+            IPCMessage::SetFocusMode { mode } => {
+                let focus_mode = self.focus_mode_sender()?;
+                focus_mode.send_replace(mode);
+                info!("Focus mode set to {:?} via IPC", mode);
+                Ok(IPCMessage::FocusMode { mode })
+            }
+            IPCMessage::GetRenderScale { output } => {
+                let render_scale = self.render_scale_sender()?;
+                let scale = render_scale.borrow().get(&output).copied().unwrap_or(1.0);
+                Ok(IPCMessage::RenderScale { output, scale })
+            }
+            IPCMessage::SetRenderScale { output, scale } => {
+                if !(0.25..=1.0).contains(&scale) {
+                    return Ok(IPCMessage::Error {
+                        message: "Render scale must be between 0.25 and 1.0".to_string(),
+                    });
+                }
+                let render_scale = self.render_scale_sender()?;
+                render_scale.send_modify(|scales| {
+                    scales.insert(output.clone(), scale);
+                });
+                info!("Render scale for output {} set to {} via IPC", output, scale);
+                Ok(IPCMessage::RenderScale { output, scale })
+            }
+            IPCMessage::GetOutputScale { output } => {
+                let output_scale = self.output_scale_sender()?;
+                let scale = output_scale.borrow().get(&output).copied().unwrap_or(1.0);
+                Ok(IPCMessage::OutputScale { output, scale })
+            }
+            IPCMessage::SetOutputScale { output, scale } => {
+                if !(0.5..=4.0).contains(&scale) {
+                    return Ok(IPCMessage::Error {
+                        message: "Output scale must be between 0.5 and 4.0".to_string(),
+                    });
+                }
+                let output_scale = self.output_scale_sender()?;
+                output_scale.send_modify(|scales| {
+                    scales.insert(output.clone(), scale);
+                });
+                info!("Scale for output {} set to {} via IPC", output, scale);
+                Ok(IPCMessage::OutputScale { output, scale })
+            }
+            IPCMessage::GetOutputTransform { output } => {
+                let output_transform = self.output_transform_sender()?;
+                let transform = output_transform.borrow().get(&output).copied().unwrap_or_default();
+                Ok(IPCMessage::OutputTransform { output, transform })
+            }
+            IPCMessage::SetOutputTransform { output, transform } => {
+                let output_transform = self.output_transform_sender()?;
+                output_transform.send_modify(|transforms| {
+                    transforms.insert(output.clone(), transform);
+                });
+                info!("Transform for output {} set to {:?} via IPC", output, transform);
+                Ok(IPCMessage::OutputTransform { output, transform })
+            }
+            IPCMessage::GetPresentMode => {
+                let present_mode = self.present_mode_sender()?;
+                Ok(IPCMessage::PresentMode { mode: *present_mode.borrow() })
+            }
+            IPCMessage::SetPresentMode { mode } => {
+                let present_mode = self.present_mode_sender()?;
+                present_mode.send_replace(mode);
+                info!("Present mode set to {:?} via IPC", mode);
+                Ok(IPCMessage::PresentMode { mode })
+            }
+            IPCMessage::GetGpuMemory => {
+                let gpu_memory = self
+                    .gpu_memory
+                    .as_ref()
+                    .ok_or_else(|| CompositorError::ipc("GPU memory statistics are not available"))?;
+                Ok(IPCMessage::GpuMemory { stats: *gpu_memory.borrow() })
+            }
+            IPCMessage::GetFrameStats => {
+                let frame_stats = self
+                    .frame_stats
+                    .as_ref()
+                    .filter(|frame_stats| frame_stats.is_enabled())
+                    .ok_or_else(|| CompositorError::ipc("Frame statistics are not enabled"))?;
+                Ok(IPCMessage::FrameStats { surfaces: frame_stats.snapshot(std::time::Instant::now()) })
+            }
+            IPCMessage::GetClients => {
+                let client_usage = self
+                    .client_usage
+                    .as_ref()
+                    .ok_or_else(|| CompositorError::ipc("Client resource usage is not available"))?;
+                Ok(IPCMessage::Clients { clients: client_usage.borrow().clone() })
+            }
+            IPCMessage::GetClientLatency => {
+                let client_latency = self
+                    .client_latency
+                    .as_ref()
+                    .ok_or_else(|| CompositorError::ipc("Client latency statistics are not available"))?;
+                Ok(IPCMessage::ClientLatency { clients: client_latency.borrow().clone() })
+            }
+            IPCMessage::GetBufferFormats => {
+                let buffer_formats = self
+                    .buffer_formats
+                    .as_ref()
+                    .ok_or_else(|| CompositorError::ipc("Buffer format statistics are not available"))?;
+                Ok(IPCMessage::BufferFormats { formats: buffer_formats.borrow().clone() })
+            }
+            IPCMessage::ListLayouts => {
+                let saved_layouts = self
+                    .saved_layouts
+                    .as_ref()
+                    .ok_or_else(|| CompositorError::ipc("Window layouts are not available"))?;
+                Ok(IPCMessage::Layouts { names: saved_layouts.borrow().clone() })
+            }
+            IPCMessage::SaveLayout { name } => self.send_layout_request(LayoutRequest::Save { name }),
+            IPCMessage::RestoreLayout { name } => self.send_layout_request(LayoutRequest::Restore { name }),
+            IPCMessage::DeleteLayout { name } => self.send_layout_request(LayoutRequest::Delete { name }),
+            IPCMessage::ApplyWindowBatch { operations } => self.send_window_batch(operations),
+            IPCMessage::Launch { command, app_id } => self.send_launch_request(LaunchRequest { command, app_id }),
+            IPCMessage::SubscribeWindowEvents => {
+                // The connection then forwards events from subscribe_window_events
+                self.window_events_sender()?;
+                Ok(IPCMessage::Accepted)
+            }
+            IPCMessage::ListWindows => {
+                let windows = self
+                    .windows
+                    .as_ref()
+                    .ok_or_else(|| CompositorError::ipc("Window list is not available"))?;
+                Ok(IPCMessage::Windows { windows: windows.borrow().clone() })
+            }
+            IPCMessage::ListOutputs => {
+                let outputs = self
+                    .outputs
+                    .as_ref()
+                    .ok_or_else(|| CompositorError::ipc("Output list is not available"))?;
+                Ok(IPCMessage::Outputs { outputs: outputs.borrow().clone() })
+            }
+            IPCMessage::SwitchWorkspace { output, workspace } => {
+                self.send_automation(AutomationRequest::SwitchWorkspace { output, workspace })
+            }
+            IPCMessage::ReloadConfig => {
+                let config_reloads = self
+                    .config_reloads
+                    .as_ref()
+                    .ok_or_else(|| CompositorError::ipc("Configuration reload is not available"))?;
+                info!("Configuration reload via IPC");
+                config_reloads
+                    .send(())
+                    .map_err(|_| CompositorError::ipc("Configuration is not accepting reloads"))?;
+                Ok(IPCMessage::Accepted)
+            }
+            IPCMessage::PreviewTheme { theme } => self.send_theme_preview(ThemePreviewRequest::Open { theme }),
+            IPCMessage::CloseThemePreview => self.send_theme_preview(ThemePreviewRequest::Close),
+            IPCMessage::ToggleFlatAccel => {
+                let flat_accel_toggles = self
+                    .flat_accel_toggles
+                    .as_ref()
+                    .ok_or_else(|| CompositorError::ipc("Pointer acceleration control is not available"))?;
+                flat_accel_toggles
+                    .send(())
+                    .map_err(|_| CompositorError::ipc("Compositor is not accepting pointer acceleration changes"))?;
+                Ok(IPCMessage::Accepted)
+            }
+            IPCMessage::SelectScreenshotRegion => {
+                let region_screenshots = self
+                    .region_screenshots
+                    .as_ref()
+                    .ok_or_else(|| CompositorError::ipc("Region screenshots are not available"))?;
+                region_screenshots
+                    .send(())
+                    .map_err(|_| CompositorError::ipc("Compositor is not accepting screenshots"))?;
+                Ok(IPCMessage::Accepted)
+            }
             _ => Ok(IPCMessage::Error {
                 message: "Unsupported message type".to_string(),
             }),
         }
     }
     
+    /// Get the parameter registry or fail if tuning is not available
+    fn parameter_registry(&self) -> Result<&ParameterRegistry> {
+        self.parameters
+            .as_deref()
+            .ok_or_else(|| CompositorError::ipc("Parameter tuning is not available"))
+    }
+    
+    /// Get the focus mode channel or fail if focus mode control is not available
+    fn focus_mode_sender(&self) -> Result<&watch::Sender<FocusModeOverride>> {
+        self.focus_mode
+            .as_ref()
+            .ok_or_else(|| CompositorError::ipc("Focus mode control is not available"))
+    }
+    
+    /// Get the render scale channel or fail if render scale control is not available
+    fn render_scale_sender(&self) -> Result<&watch::Sender<HashMap<String, f32>>> {
+        self.render_scale
+            .as_ref()
+            .ok_or_else(|| CompositorError::ipc("Render scale control is not available"))
+    }
+    
+    /// Get the output scale channel or fail if output scale control is not available
+    fn output_scale_sender(&self) -> Result<&watch::Sender<HashMap<String, f64>>> {
+        self.output_scale
+            .as_ref()
+            .ok_or_else(|| CompositorError::ipc("Output scale control is not available"))
+    }
+    
+    /// Get the output transform channel or fail if output rotation is not available
+    fn output_transform_sender(&self) -> Result<&watch::Sender<HashMap<String, DisplayTransform>>> {
+        self.output_transform
+            .as_ref()
+            .ok_or_else(|| CompositorError::ipc("Output rotation is not available"))
+    }
+    
+    /// Get the window event channel or fail if window events are not available
+    fn window_events_sender(&self) -> Result<&broadcast::Sender<WindowEvent>> {
+        self.window_events
+            .as_ref()
+            .ok_or_else(|| CompositorError::ipc("Window events are not available"))
+    }
+    
+    /// Get the present mode channel or fail if present mode control is not available
+    fn present_mode_sender(&self) -> Result<&watch::Sender<PresentMode>> {
+        self.present_mode
+            .as_ref()
+            .ok_or_else(|| CompositorError::ipc("Present mode control is not available"))
+    }
+    
+    /// Forward an automation request to the compositor
+    fn send_automation(&self, request: AutomationRequest) -> Result<IPCMessage> {
+        let automation = self
+            .automation
+            .as_ref()
+            .ok_or_else(|| CompositorError::ipc("Automation is not available"))?;
+        debug!("Automation request via IPC: {:?}", request);
+        automation
+            .send(request)
+            .map_err(|_| CompositorError::ipc("Compositor is not accepting automation requests"))?;
+        Ok(IPCMessage::Accepted)
+    }
+    
+    /// Forward a window layout request to the compositor
+    fn send_layout_request(&self, request: LayoutRequest) -> Result<IPCMessage> {
+        if !is_valid_layout_name(request.name()) {
+            return Ok(IPCMessage::Error {
+                message: format!("Invalid layout name: {:?}", request.name()),
+            });
+        }
+        let layout_requests = self
+            .layout_requests
+            .as_ref()
+            .ok_or_else(|| CompositorError::ipc("Window layouts are not available"))?;
+        info!("Layout request via IPC: {:?}", request);
+        layout_requests
+            .send(request)
+            .map_err(|_| CompositorError::ipc("Compositor is not accepting layout requests"))?;
+        Ok(IPCMessage::Accepted)
+    }
+    
+    /// Forward a batch of window operations to the compositor
+    fn send_window_batch(&self, operations: Vec<WindowOperation>) -> Result<IPCMessage> {
+        if operations.is_empty() {
+            return Ok(IPCMessage::Error { message: "Window batch is empty".to_string() });
+        }
+        let invalid_size = operations.iter().any(|operation| {
+            matches!(operation, WindowOperation::Resize { width, height, .. } if *width <= 0 || *height <= 0)
+        });
+        if invalid_size {
+            return Ok(IPCMessage::Error { message: "Window sizes must be positive".to_string() });
+        }
+        let window_batches = self
+            .window_batches
+            .as_ref()
+            .ok_or_else(|| CompositorError::ipc("Window batches are not available"))?;
+        debug!("Window batch via IPC with {} operations", operations.len());
+        window_batches
+            .send(operations)
+            .map_err(|_| CompositorError::ipc("Compositor is not accepting window batches"))?;
+        Ok(IPCMessage::Accepted)
+    }
+    
+    /// Forward an application launch to the compositor, which creates its
+    /// activation token
+    fn send_launch_request(&self, request: LaunchRequest) -> Result<IPCMessage> {
+        if request.command.first().is_none_or(|program| program.is_empty()) {
+            return Ok(IPCMessage::Error { message: "Launch command is empty".to_string() });
+        }
+        let launch_requests = self
+            .launch_requests
+            .as_ref()
+            .ok_or_else(|| CompositorError::ipc("Launching applications is not available"))?;
+        info!("Launch via IPC: {:?}", request.command);
+        launch_requests
+            .send(request)
+            .map_err(|_| CompositorError::ipc("Compositor is not accepting launch requests"))?;
+        Ok(IPCMessage::Accepted)
+    }
+    
+    /// Forward a theme preview request to the compositor, which parses and
+    /// validates the theme
+    fn send_theme_preview(&self, request: ThemePreviewRequest) -> Result<IPCMessage> {
+        let theme_previews = self
+            .theme_previews
+            .as_ref()
+            .ok_or_else(|| CompositorError::ipc("Theme previews are not available"))?;
+        debug!("Theme preview request via IPC: {:?}", request);
+        theme_previews
+            .send(request)
+            .map_err(|_| CompositorError::ipc("Compositor is not accepting theme previews"))?;
+        Ok(IPCMessage::Accepted)
+    }
+    
+    /// Collect parameter information for names accepted by `filter`
+    fn parameter_infos(registry: &ParameterRegistry, filter: impl Fn(&str) -> bool) -> Vec<ParameterInfo> {
+        registry
+            .list()
+            .into_iter()
+            .filter(|(name, _)| filter(name))
+            .map(|(name, spec)| ParameterInfo {
+                name,
+                value: spec.value,
+                default: spec.default,
+                min: spec.min,
+                max: spec.max,
+            })
+            .collect()
+    }
+    
     /// Serialize a message for transmission
     pub fn serialize_message(&self, message: &IPCMessage) -> Result<Vec<u8>> {
         bincode::serialize(message).map_err(|e| {
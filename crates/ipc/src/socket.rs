@@ -2,12 +2,63 @@
 //
 // This module provides Unix domain socket based IPC for high-performance
 // communication between the compositor and client applications.
+//
+// Scripts and `compositorctl` control the compositor over the control socket
+// the way swaymsg and hyprctl do, with newline-delimited JSON. Every request
+// is one line naming a method and its parameters, and is answered by one
+// line with the same ID and either a result or an error:
+//
+//   {"id": 1, "method": "get_tree"}
+//   {"id": 1, "result": [{"name": "DP-1", ..., "workspaces": [...]}]}
+//
+//   {"id": 2, "method": "run_command", "params": {"command": "workspace DP-1 2"}}
+//   {"id": 2, "result": {"success": true}}
+//
+//   {"id": 3, "method": "run_command", "params": {"command": "focus 99"}}
+//   {"id": 3, "error": "Permission denied"}
+//
+// Methods:
+//   get_tree        outputs, each with its workspaces and their windows
+//   get_outputs     connected outputs
+//   get_workspaces  workspaces of every output with their window counts
+//   run_command     run a command, see `parse_command`
+//   subscribe       turn the connection into an event stream
+//
+// After a successful `subscribe` the connection receives one line per event
+// until it closes and answers no further requests:
+//
+//   {"event": "window", "data": {"Opened": {"window_id": 42, ...}}}
+//
+// Requests are checked against the permission tier of the connecting
+// process like every other IPC request.
 
+use crate::auth::PeerCredentials;
+use crate::protocol::{IPCMessage, OutputInfo, ProtocolHandler, WindowEvent, WindowSummary};
 use compositor_utils::prelude::*;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
 use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast;
 use tokio_util::codec::{FramedWrite, LengthDelimitedCodec};
 use bytes::Bytes;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Environment variable overriding the control socket path
+pub const SOCKET_ENV: &str = "CUSTOM_COMPOSITOR_SOCK";
+
+/// File name of the control socket in $XDG_RUNTIME_DIR
+pub const SOCKET_NAME: &str = "custom-compositor.sock";
+
+/// Path of the control socket: $CUSTOM_COMPOSITOR_SOCK, or SOCKET_NAME in
+/// $XDG_RUNTIME_DIR
+pub fn default_socket_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(SOCKET_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    std::env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join(SOCKET_NAME))
+}
 
 /// Unix socket server for IPC communication
 pub struct SocketServer {
@@ -50,6 +101,15 @@ impl SocketServer {
             Err(CompositorError::ipc("Socket server not started").into())
         }
     }
+    
+    /// Accept an incoming connection along with the credentials of its peer,
+    /// for checking its requests against the permission tiers
+    pub async fn accept_with_credentials(&self) -> Result<(UnixStream, PeerCredentials)> {
+        let stream = self.accept().await?;
+        let peer = PeerCredentials::of(&stream)?;
+        debug!("Accepted IPC connection from {:?}", peer);
+        Ok((stream, peer))
+    }
 }
 
 /// Socket client for connecting to the compositor
@@ -87,3 +147,287 @@ impl Default for SocketClient {
         Self::new()
     }
 }
+
+/// Request line on the control socket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonRequest {
+    /// Echoed in the response so clients can match them up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub method: JsonMethod,
+}
+
+/// Method of a control socket request, with its parameters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum JsonMethod {
+    GetTree,
+    GetOutputs,
+    GetWorkspaces,
+    RunCommand { command: String },
+    Subscribe,
+}
+
+/// Response line on the control socket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub outcome: JsonOutcome,
+}
+
+/// Result or error of a control socket request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonOutcome {
+    Result(serde_json::Value),
+    Error(String),
+}
+
+/// Event line sent to subscribed connections
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum JsonEvent {
+    Window(WindowEvent),
+}
+
+/// Output in the `get_tree` result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreeOutput {
+    #[serde(flatten)]
+    pub output: OutputInfo,
+    pub workspaces: Vec<TreeWorkspace>,
+}
+
+/// Workspace in the `get_tree` result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeWorkspace {
+    pub index: usize,
+    /// Whether the workspace is shown on its output
+    pub active: bool,
+    pub windows: Vec<WindowSummary>,
+}
+
+/// Workspace in the `get_workspaces` result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceInfo {
+    pub output: String,
+    pub index: usize,
+    /// Whether the workspace is shown on its output
+    pub active: bool,
+    /// Number of windows on the workspace
+    pub windows: usize,
+}
+
+/// Outputs with their workspaces and the windows on each
+pub fn build_tree(outputs: &[OutputInfo], windows: &[WindowSummary]) -> Vec<TreeOutput> {
+    outputs
+        .iter()
+        .map(|output| TreeOutput {
+            output: output.clone(),
+            workspaces: (0..output.workspace_count)
+                .map(|index| TreeWorkspace {
+                    index,
+                    active: index == output.active_workspace,
+                    windows: windows
+                        .iter()
+                        .filter(|window| {
+                            window.output.as_deref() == Some(output.name.as_str()) && window.workspace == Some(index)
+                        })
+                        .cloned()
+                        .collect(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Parse a `run_command` command into the IPC request carrying it out
+///
+/// Commands:
+///   focus <window_id>
+///   workspace <output> <index>
+///   exec <program> [args...]     arguments are split on whitespace
+///   set <parameter> <value>
+///   reload
+///   toggle_flat_accel
+///   screenshot_region
+pub fn parse_command(command: &str) -> std::result::Result<IPCMessage, String> {
+    let mut words = command.split_whitespace();
+    let name = words.next().ok_or_else(|| "Command is empty".to_string())?;
+    let args: Vec<&str> = words.collect();
+    match (name, args.as_slice()) {
+        ("focus", [window_id]) => Ok(IPCMessage::FocusWindow { window_id: parse_arg(name, window_id)? }),
+        ("workspace", [output, workspace]) => Ok(IPCMessage::SwitchWorkspace {
+            output: output.to_string(),
+            workspace: parse_arg(name, workspace)?,
+        }),
+        ("exec", [_, ..]) => Ok(IPCMessage::Launch {
+            command: args.iter().map(|arg| arg.to_string()).collect(),
+            app_id: None,
+        }),
+        ("set", [parameter, value]) => Ok(IPCMessage::SetParameter {
+            name: parameter.to_string(),
+            value: parse_arg(name, value)?,
+        }),
+        ("reload", []) => Ok(IPCMessage::ReloadConfig),
+        ("toggle_flat_accel", []) => Ok(IPCMessage::ToggleFlatAccel),
+        ("screenshot_region", []) => Ok(IPCMessage::SelectScreenshotRegion),
+        ("focus" | "workspace" | "exec" | "set" | "reload" | "toggle_flat_accel" | "screenshot_region", _) => {
+            Err(format!("Wrong arguments for {:?}", name))
+        }
+        _ => Err(format!("Unknown command {:?}", name)),
+    }
+}
+
+fn parse_arg<T: std::str::FromStr>(command: &str, arg: &str) -> std::result::Result<T, String> {
+    arg.parse().map_err(|_| format!("Invalid argument {:?} for {:?}", arg, command))
+}
+
+/// Control socket answering JSON requests
+pub struct JsonControlServer {
+    handler: Arc<ProtocolHandler>,
+}
+
+impl JsonControlServer {
+    /// Create a control socket answering requests with the given protocol handler
+    pub fn new(handler: Arc<ProtocolHandler>) -> Self {
+        Self { handler }
+    }
+
+    /// Serve connections accepted on a started socket, each on its own task,
+    /// until accepting fails
+    pub async fn run(self, server: SocketServer) -> Result<()> {
+        let this = Arc::new(self);
+        loop {
+            let (stream, peer) = server.accept_with_credentials().await?;
+            let this = this.clone();
+            tokio::spawn(async move {
+                if let Err(e) = this.serve(stream, peer).await {
+                    warn!("Control socket connection failed: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Answer requests on a connection until it closes or subscribes
+    pub async fn serve(&self, stream: UnixStream, peer: PeerCredentials) -> Result<()> {
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let request = match serde_json::from_str::<JsonRequest>(&line) {
+                Ok(request) => request,
+                Err(e) => {
+                    let response = JsonResponse { id: None, outcome: JsonOutcome::Error(format!("Invalid request: {}", e)) };
+                    write_line(&mut write, &response).await?;
+                    continue;
+                }
+            };
+            let subscribe = request.method == JsonMethod::Subscribe;
+            let response = self.answer(&peer, request).await;
+            // Subscribe before answering so no event in between is missed
+            let events = match response.outcome {
+                JsonOutcome::Result(_) if subscribe => Some(self.handler.subscribe_window_events()?),
+                _ => None,
+            };
+            write_line(&mut write, &response).await?;
+            if let Some(events) = events {
+                return stream_events(events, write).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer a request from a peer, checked against its permission tier
+    pub async fn answer(&self, peer: &PeerCredentials, request: JsonRequest) -> JsonResponse {
+        let outcome = match self.dispatch(peer, request.method).await {
+            Ok(result) => JsonOutcome::Result(result),
+            Err(e) => JsonOutcome::Error(e.to_string()),
+        };
+        JsonResponse { id: request.id, outcome }
+    }
+
+    async fn dispatch(&self, peer: &PeerCredentials, method: JsonMethod) -> Result<serde_json::Value> {
+        match method {
+            JsonMethod::GetTree => to_json(&build_tree(&self.outputs(peer).await?, &self.windows(peer).await?)),
+            JsonMethod::GetOutputs => to_json(&self.outputs(peer).await?),
+            JsonMethod::GetWorkspaces => {
+                let workspaces: Vec<WorkspaceInfo> = build_tree(&self.outputs(peer).await?, &self.windows(peer).await?)
+                    .into_iter()
+                    .flat_map(|tree| {
+                        let output = tree.output.name;
+                        tree.workspaces.into_iter().map(move |workspace| WorkspaceInfo {
+                            output: output.clone(),
+                            index: workspace.index,
+                            active: workspace.active,
+                            windows: workspace.windows.len(),
+                        })
+                    })
+                    .collect();
+                to_json(&workspaces)
+            }
+            JsonMethod::RunCommand { command } => {
+                let message = parse_command(&command).map_err(CompositorError::ipc)?;
+                match self.request(peer, message).await? {
+                    IPCMessage::Accepted => Ok(serde_json::json!({ "success": true })),
+                    reply => to_json(&reply),
+                }
+            }
+            JsonMethod::Subscribe => {
+                self.request(peer, IPCMessage::SubscribeWindowEvents).await?;
+                Ok(serde_json::json!({ "success": true }))
+            }
+        }
+    }
+
+    async fn outputs(&self, peer: &PeerCredentials) -> Result<Vec<OutputInfo>> {
+        match self.request(peer, IPCMessage::ListOutputs).await? {
+            IPCMessage::Outputs { outputs } => Ok(outputs),
+            reply => Err(CompositorError::ipc(format!("Unexpected reply to ListOutputs: {:?}", reply))),
+        }
+    }
+
+    async fn windows(&self, peer: &PeerCredentials) -> Result<Vec<WindowSummary>> {
+        match self.request(peer, IPCMessage::ListWindows).await? {
+            IPCMessage::Windows { windows } => Ok(windows),
+            reply => Err(CompositorError::ipc(format!("Unexpected reply to ListWindows: {:?}", reply))),
+        }
+    }
+
+    /// Send a request to the protocol handler, turning error replies into errors
+    async fn request(&self, peer: &PeerCredentials, message: IPCMessage) -> Result<IPCMessage> {
+        match self.handler.handle_request(peer, message).await? {
+            IPCMessage::Error { message } => Err(CompositorError::ipc(message)),
+            reply => Ok(reply),
+        }
+    }
+}
+
+/// Forward window events to a subscribed connection until either side closes
+async fn stream_events(mut events: broadcast::Receiver<WindowEvent>, mut write: OwnedWriteHalf) -> Result<()> {
+    loop {
+        match events.recv().await {
+            Ok(event) => write_line(&mut write, &JsonEvent::Window(event)).await?,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Control socket subscriber fell behind, {} window events dropped", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<serde_json::Value> {
+    serde_json::to_value(value).map_err(|e| CompositorError::ipc(e.to_string()))
+}
+
+async fn write_line<T: Serialize>(write: &mut OwnedWriteHalf, value: &T) -> Result<()> {
+    let mut line = serde_json::to_vec(value).map_err(|e| CompositorError::ipc(e.to_string()))?;
+    line.push(b'\n');
+    write.write_all(&line).await?;
+    Ok(())
+}
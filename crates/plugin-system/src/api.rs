@@ -1,4 +1,10 @@
-use compositor_utils::Result;
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Command;
+use compositor_utils::{math::Rect, CompositorError, Result};
+use ipc::protocol::IPCMessage;
+use tokio::sync::{broadcast, mpsc};
+use crate::state::StateSnapshot;
 
 /// Plugin API version
 pub const PLUGIN_API_VERSION: u32 = 1;
@@ -14,8 +20,9 @@ pub type PluginInfoFn = unsafe extern "C" fn() -> *const std::os::raw::c_char;
 
 /// Plugin API interface that plugins must implement
 pub trait PluginApi {
-    /// Initialize the plugin
-    fn init(&mut self) -> Result<()>;
+    /// Initialize the plugin with the context it reaches the compositor
+    /// through
+    fn init(&mut self, context: PluginContext) -> Result<()>;
     
     /// Cleanup the plugin
     fn cleanup(&mut self);
@@ -27,10 +34,24 @@ pub trait PluginApi {
     fn api_version(&self) -> u32 {
         PLUGIN_API_VERSION
     }
+    
+    /// State to keep across a reload or restart, taken before unloading;
+    /// `None` for plugins without state
+    fn save_state(&self) -> Result<Option<StateSnapshot>> {
+        Ok(None)
+    }
+    
+    /// Restore state saved by an earlier instance, after `init`
+    ///
+    /// The snapshot may come from an older version of the plugin; check its
+    /// version before decoding.
+    fn restore_state(&mut self, _snapshot: StateSnapshot) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// Plugin capability flags
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PluginCapability {
     /// Can modify window decorations
     WindowDecorations = 1 << 0,
@@ -49,17 +70,133 @@ pub enum PluginCapability {
     
     /// Can communicate with external processes
     ExternalCommunication = 1 << 5,
+    
+    /// Can send IPC commands to the compositor
+    IpcCommands = 1 << 6,
+}
+
+impl PluginCapability {
+    /// Every capability
+    pub const ALL: [PluginCapability; 7] = [
+        Self::WindowDecorations,
+        Self::InputHandling,
+        Self::SurfaceRendering,
+        Self::WorkspaceManagement,
+        Self::SystemAccess,
+        Self::ExternalCommunication,
+        Self::IpcCommands,
+    ];
+    
+    /// Name used in plugin manifests
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::WindowDecorations => "window-decorations",
+            Self::InputHandling => "input",
+            Self::SurfaceRendering => "render",
+            Self::WorkspaceManagement => "workspaces",
+            Self::SystemAccess => "system",
+            Self::ExternalCommunication => "external-communication",
+            Self::IpcCommands => "ipc",
+        }
+    }
+    
+    /// Capability with a manifest name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|capability| capability.name() == name)
+    }
+    
+    /// What granting the capability allows, for asking the user
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::WindowDecorations => "Draw and change window decorations",
+            Self::InputHandling => "Observe keyboard and pointer input",
+            Self::SurfaceRendering => "Draw on screen",
+            Self::WorkspaceManagement => "Create, switch and rearrange workspaces",
+            Self::SystemAccess => "Access files and system resources",
+            Self::ExternalCommunication => "Communicate with other processes",
+            Self::IpcCommands => "Send commands to the compositor",
+        }
+    }
+}
+
+/// Rectangle a plugin draws over the screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverlayRect {
+    pub rect: Rect,
+    pub color: [f32; 4],
+}
+
+/// What a plugin asks the compositor to do
+#[derive(Debug, Clone)]
+pub enum PluginAction {
+    /// Draw a window's decorations in the compositor or leave them to the client
+    SetServerSideDecorations { window_id: u32, enabled: bool },
+    /// Replace everything the plugin draws over the screen
+    DrawOverlay { rects: Vec<OverlayRect> },
+    /// Show a workspace on an output
+    SwitchWorkspace { output: String, workspace: usize },
+    /// Handle a command as if it came over IPC
+    Ipc(IPCMessage),
+}
+
+/// Request from a plugin, for the compositor to carry out
+#[derive(Debug, Clone)]
+pub struct PluginRequest {
+    /// Name of the plugin making the request
+    pub plugin: String,
+    pub action: PluginAction,
+}
+
+/// Input a plugin can observe
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PluginInputEvent {
+    Key { keysym: u32, pressed: bool },
+    PointerMotion { x: f64, y: f64 },
+    PointerButton { button: u32, pressed: bool },
 }
 
 /// Plugin context provided to plugins for interacting with the compositor
+///
+/// Every compositor interface a plugin calls through the context checks the
+/// capabilities the user granted the plugin.
+#[derive(Clone)]
 pub struct PluginContext {
-    // TODO: Add compositor interfaces that plugins can use
+    /// Name of the plugin the context belongs to
+    plugin: String,
+    /// Capabilities the user granted the plugin
+    granted: HashSet<PluginCapability>,
+    /// Where requests go for the compositor to carry out
+    requests: Option<mpsc::UnboundedSender<PluginRequest>>,
+    /// Input published by the compositor
+    input: Option<broadcast::Sender<PluginInputEvent>>,
 }
 
 impl PluginContext {
-    /// Create a new plugin context
+    /// Create a context without any capabilities
     pub fn new() -> Self {
-        Self {}
+        Self::for_plugin(String::new(), [])
+    }
+    
+    /// Create the context of a plugin with the capabilities granted to it
+    pub fn for_plugin(plugin: impl Into<String>, granted: impl IntoIterator<Item = PluginCapability>) -> Self {
+        Self {
+            plugin: plugin.into(),
+            granted: granted.into_iter().collect(),
+            requests: None,
+            input: None,
+        }
+    }
+    
+    /// Send the plugin's requests to the compositor through `sender`
+    pub fn with_requests(mut self, sender: mpsc::UnboundedSender<PluginRequest>) -> Self {
+        self.requests = Some(sender);
+        self
+    }
+    
+    /// Let the plugin subscribe to input published on `sender`
+    pub fn with_input(mut self, sender: broadcast::Sender<PluginInputEvent>) -> Self {
+        self.input = Some(sender);
+        self
     }
     
     /// Get the compositor version
@@ -68,9 +205,84 @@ impl PluginContext {
     }
     
     /// Check if a capability is available
-    pub fn has_capability(&self, _capability: PluginCapability) -> bool {
-        // TODO: Implement capability checking
-        false
+    pub fn has_capability(&self, capability: PluginCapability) -> bool {
+        self.granted.contains(&capability)
+    }
+    
+    /// Fail unless a capability was granted; call at the start of every
+    /// interface that needs one
+    pub fn require(&self, capability: PluginCapability) -> Result<()> {
+        if self.has_capability(capability) {
+            Ok(())
+        } else {
+            Err(CompositorError::plugin(format!(
+                "Plugin {} was not granted the {} capability",
+                self.plugin,
+                capability.name()
+            )))
+        }
+    }
+    
+    /// Draw a window's decorations in the compositor or leave them to the
+    /// client
+    pub fn set_server_side_decorations(&self, window_id: u32, enabled: bool) -> Result<()> {
+        self.require(PluginCapability::WindowDecorations)?;
+        self.send(PluginAction::SetServerSideDecorations { window_id, enabled })
+    }
+    
+    /// Receive keyboard and pointer input from now on
+    pub fn subscribe_input(&self) -> Result<broadcast::Receiver<PluginInputEvent>> {
+        self.require(PluginCapability::InputHandling)?;
+        self.input
+            .as_ref()
+            .map(broadcast::Sender::subscribe)
+            .ok_or_else(|| self.disconnected())
+    }
+    
+    /// Replace everything the plugin draws over the screen; empty to clear
+    pub fn draw_overlay(&self, rects: Vec<OverlayRect>) -> Result<()> {
+        self.require(PluginCapability::SurfaceRendering)?;
+        self.send(PluginAction::DrawOverlay { rects })
+    }
+    
+    /// Show a workspace on an output
+    pub fn switch_workspace(&self, output: impl Into<String>, workspace: usize) -> Result<()> {
+        self.require(PluginCapability::WorkspaceManagement)?;
+        self.send(PluginAction::SwitchWorkspace { output: output.into(), workspace })
+    }
+    
+    /// Read a file
+    pub fn read_file(&self, path: impl AsRef<Path>) -> Result<Vec<u8>> {
+        self.require(PluginCapability::SystemAccess)?;
+        Ok(std::fs::read(path)?)
+    }
+    
+    /// Start another process, returning its pid
+    pub fn spawn(&self, program: &str, args: &[&str]) -> Result<u32> {
+        self.require(PluginCapability::ExternalCommunication)?;
+        let child = Command::new(program)
+            .args(args)
+            .spawn()
+            .map_err(|e| CompositorError::plugin(format!("Plugin {} failed to start {}: {}", self.plugin, program, e)))?;
+        Ok(child.id())
+    }
+    
+    /// Have the compositor handle a command as if it came over IPC
+    pub fn send_ipc(&self, message: IPCMessage) -> Result<()> {
+        self.require(PluginCapability::IpcCommands)?;
+        self.send(PluginAction::Ipc(message))
+    }
+    
+    fn send(&self, action: PluginAction) -> Result<()> {
+        let request = PluginRequest { plugin: self.plugin.clone(), action };
+        match &self.requests {
+            Some(sender) if sender.send(request).is_ok() => Ok(()),
+            _ => Err(self.disconnected()),
+        }
+    }
+    
+    fn disconnected(&self) -> CompositorError {
+        CompositorError::plugin(format!("Plugin {} is not connected to the compositor", self.plugin))
     }
 }
 
@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use compositor_utils::{CompositorError, Result};
+use crate::api::{PluginCapability, PluginContext};
+use crate::manifest::PluginManifest;
+
+/// Capabilities a plugin asks for that the user has not granted yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrantRequest {
+    pub plugin: String,
+    pub version: String,
+    pub capabilities: Vec<PluginCapability>,
+}
+
+impl GrantRequest {
+    /// Lines describing the request, for a confirmation prompt
+    pub fn describe(&self) -> Vec<String> {
+        std::iter::once(format!("{} {} asks to:", self.plugin, self.version))
+            .chain(self.capabilities.iter().map(|capability| format!("- {}", capability.description())))
+            .collect()
+    }
+}
+
+/// Capabilities the user granted each plugin, kept across restarts
+///
+/// Plugins run with only the capabilities granted to them. A plugin whose
+/// manifest asks for more, e.g. after an update, is not loaded until the
+/// user confirms the new capabilities.
+#[derive(Debug, Clone)]
+pub struct CapabilityGrants {
+    path: PathBuf,
+    /// Granted capabilities by plugin name, as manifest names
+    granted: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl CapabilityGrants {
+    /// Create grants stored at `path` that grant nothing yet
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, granted: BTreeMap::new() }
+    }
+
+    /// Load the grants stored at `path`; a missing file grants nothing
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let granted = match std::fs::read_to_string(&path) {
+            Ok(content) => ron::from_str(&content)
+                .map_err(|e| CompositorError::plugin(format!("Failed to parse {}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(CompositorError::plugin(format!("Failed to read {}: {}", path.display(), e))),
+        };
+        Ok(Self { path, granted })
+    }
+
+    /// Where grants are stored unless configured otherwise
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("/etc"))
+            .join("custom-compositor")
+            .join("plugin-grants.ron")
+    }
+
+    /// Capabilities granted to a plugin
+    pub fn granted(&self, plugin: &str) -> Vec<PluginCapability> {
+        self.granted
+            .get(plugin)
+            .into_iter()
+            .flatten()
+            .filter_map(|name| PluginCapability::from_name(name))
+            .collect()
+    }
+
+    /// Capabilities a plugin needs that were not granted, or `None` when it
+    /// may be loaded
+    pub fn review(&self, manifest: &PluginManifest) -> Result<Option<GrantRequest>> {
+        let granted = self.granted(&manifest.name);
+        let missing: Vec<PluginCapability> = manifest
+            .required_capabilities()?
+            .into_iter()
+            .filter(|capability| !granted.contains(capability))
+            .collect();
+        Ok((!missing.is_empty()).then(|| GrantRequest {
+            plugin: manifest.name.clone(),
+            version: manifest.version.clone(),
+            capabilities: missing,
+        }))
+    }
+
+    /// Grant the capabilities of a confirmed request and save
+    pub fn grant(&mut self, request: &GrantRequest) -> Result<()> {
+        self.granted
+            .entry(request.plugin.clone())
+            .or_default()
+            .extend(request.capabilities.iter().map(|capability| capability.name().to_string()));
+        self.save()
+    }
+
+    /// Revoke every capability of a plugin and save
+    pub fn revoke(&mut self, plugin: &str) -> Result<()> {
+        if self.granted.remove(plugin).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Context of a plugin limited to the capabilities it needs and was granted
+    pub fn context(&self, manifest: &PluginManifest) -> Result<PluginContext> {
+        let granted = self.granted(&manifest.name);
+        let capabilities = manifest
+            .required_capabilities()?
+            .into_iter()
+            .filter(|capability| granted.contains(capability));
+        Ok(PluginContext::for_plugin(manifest.name.clone(), capabilities))
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| CompositorError::plugin(format!("Failed to create {}: {}", dir.display(), e)))?;
+        }
+        let content = ron::ser::to_string_pretty(&self.granted, ron::ser::PrettyConfig::default())
+            .map_err(|e| CompositorError::plugin(format!("Failed to serialize plugin grants: {}", e)))?;
+        std::fs::write(&self.path, content)
+            .map_err(|e| CompositorError::plugin(format!("Failed to write {}: {}", self.path.display(), e)))
+    }
+}
+
@@ -5,16 +5,39 @@
 
 use compositor_utils::prelude::*;
 use std::collections::HashMap;
+use std::path::Path;
+use tokio::sync::{broadcast, mpsc};
 use uuid::Uuid;
+use api::{PluginApi, PluginContext, PluginInputEvent, PluginRequest};
+use loader::{NativePlugin, PluginLoader};
+use manifest::PluginManifest;
 
 pub mod loader;
 pub mod registry;
 pub mod manifest;
 pub mod api;
+pub mod state;
+pub mod grants;
+
+pub use state::{PluginStateStore, StateSnapshot};
+pub use grants::{CapabilityGrants, GrantRequest};
 
 /// Plugin system manager
 pub struct PluginSystem {
     plugins: HashMap<Uuid, LoadedPlugin>,
+    /// State plugins keep across reloads and restarts
+    state_store: PluginStateStore,
+    /// Capabilities the user granted each plugin
+    grants: CapabilityGrants,
+    /// Plugins waiting for the user to confirm their capabilities
+    pending_grants: Vec<GrantRequest>,
+    /// Shared libraries of native plugins, kept loaded while the system lives
+    loader: PluginLoader,
+    /// Requests plugins send through their contexts
+    requests: mpsc::UnboundedSender<PluginRequest>,
+    request_receiver: mpsc::UnboundedReceiver<PluginRequest>,
+    /// Input for plugins granted input handling
+    input: broadcast::Sender<PluginInputEvent>,
     _registry: registry::PluginRegistry, // Prefix with _ to indicate intentionally unused for now
 }
 
@@ -24,6 +47,9 @@ pub struct LoadedPlugin {
     pub name: String,
     pub version: String,
     pub enabled: bool,
+    /// Compositor interfaces the plugin may use, limited to its grants
+    pub context: PluginContext,
+    instance: Box<dyn PluginApi>,
 }
 
 impl PluginSystem {
@@ -31,37 +57,178 @@ impl PluginSystem {
     pub fn new() -> Result<Self> {
         info!("Initializing Plugin System");
         
+        // Unreadable grants grant nothing, so plugins ask again
+        let grants_path = CapabilityGrants::default_path();
+        let grants = CapabilityGrants::load(grants_path.clone()).unwrap_or_else(|e| {
+            warn!("Ignoring plugin capability grants: {}", e);
+            CapabilityGrants::new(grants_path)
+        });
+        let (requests, request_receiver) = mpsc::unbounded_channel();
+        let (input, _) = broadcast::channel(256);
+        
         Ok(Self {
             plugins: HashMap::new(),
+            state_store: PluginStateStore::default(),
+            grants,
+            pending_grants: Vec::new(),
+            loader: PluginLoader::new(),
+            requests,
+            request_receiver,
+            input,
             _registry: registry::PluginRegistry::new(),
         })
     }
     
-    /// Load a plugin from path
+    /// Load a native plugin from its manifest file; the entry point is
+    /// looked up next to the manifest
     pub async fn load_plugin(&mut self, path: &str) -> Result<Uuid> {
         info!("Loading plugin from: {}", path);
         
-        // TODO: Implement actual plugin loading
-        let plugin_id = Uuid::new_v4();
-        let plugin = LoadedPlugin {
-            id: plugin_id,
-            name: "Example Plugin".to_string(),
-            version: "0.1.0".to_string(),
-            enabled: true,
-        };
+        let manifest = PluginManifest::load_from_file(path)?;
+        manifest.validate()?;
+        self.require_grants(&manifest)?;
+        let library = Path::new(path).parent().unwrap_or(Path::new(".")).join(&manifest.entry_point);
+        let registration = self.loader.load_plugin(&library, &manifest)?;
+        self.add_plugin(manifest, Box::new(NativePlugin::new(registration)))
+    }
+    
+    /// Start a plugin instance and restore the state it saved before
+    ///
+    /// Plugins whose manifest asks for capabilities that were not granted
+    /// are refused and queued for the user to confirm.
+    pub fn add_plugin(&mut self, manifest: PluginManifest, mut instance: Box<dyn PluginApi>) -> Result<Uuid> {
+        self.require_grants(&manifest)?;
+        let context = self
+            .grants
+            .context(&manifest)?
+            .with_requests(self.requests.clone())
+            .with_input(self.input.clone());
+        instance.init(context.clone())?;
+        
+        // State that cannot be restored is dropped rather than keeping the
+        // plugin from loading
+        match self.state_store.load(&manifest.name) {
+            Ok(Some(snapshot)) => {
+                if let Err(e) = instance.restore_state(snapshot) {
+                    warn!("Failed to restore state of plugin {}: {}", manifest.name, e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Ignoring state of plugin {}: {}", manifest.name, e),
+        }
         
-        self.plugins.insert(plugin_id, plugin);
+        let plugin_id = Uuid::new_v4();
+        info!("Loaded plugin {} {}", manifest.name, manifest.version);
+        self.plugins.insert(
+            plugin_id,
+            LoadedPlugin {
+                id: plugin_id,
+                name: manifest.name,
+                version: manifest.version,
+                enabled: true,
+                context,
+                instance,
+            },
+        );
         Ok(plugin_id)
     }
     
-    /// Unload a plugin
+    /// Unload a plugin, saving its state for the next time it loads
     pub async fn unload_plugin(&mut self, id: Uuid) -> Result<()> {
-        if let Some(plugin) = self.plugins.remove(&id) {
-            info!("Unloaded plugin: {}", plugin.name);
+        let Some(mut plugin) = self.plugins.remove(&id) else {
+            return Ok(());
+        };
+        // A plugin is unloaded even if its state cannot be saved
+        let saved = plugin
+            .instance
+            .save_state()
+            .and_then(|snapshot| snapshot.map_or(Ok(()), |snapshot| self.state_store.save(&plugin.name, &snapshot)));
+        if let Err(e) = saved {
+            warn!("Failed to save state of plugin {}: {}", plugin.name, e);
+        }
+        plugin.instance.cleanup();
+        info!("Unloaded plugin: {}", plugin.name);
+        Ok(())
+    }
+    
+    /// Use a different directory for plugin state, e.g. from the config
+    pub fn set_state_dir(&mut self, dir: std::path::PathBuf) {
+        self.state_store = PluginStateStore::new(dir);
+    }
+    
+    /// Where plugins keep state across reloads and restarts
+    pub fn state_store(&self) -> &PluginStateStore {
+        &self.state_store
+    }
+    
+    /// Whether a plugin was granted every capability its manifest asks for
+    ///
+    /// Otherwise the missing capabilities are queued for the user to confirm
+    /// and the plugin must not be loaded.
+    pub fn check_grants(&mut self, manifest: &PluginManifest) -> Result<bool> {
+        let Some(request) = self.grants.review(manifest)? else {
+            return Ok(true);
+        };
+        info!("Plugin {} needs confirmation of capabilities {:?}", request.plugin, request.capabilities);
+        self.pending_grants.retain(|pending| pending.plugin != request.plugin);
+        self.pending_grants.push(request);
+        Ok(false)
+    }
+    
+    /// Fail unless a plugin may be loaded, see `check_grants`
+    fn require_grants(&mut self, manifest: &PluginManifest) -> Result<()> {
+        if self.check_grants(manifest)? {
+            Ok(())
+        } else {
+            Err(CompositorError::plugin(format!(
+                "Plugin {} is waiting for its capabilities to be confirmed",
+                manifest.name
+            )))
         }
+    }
+    
+    /// Capability requests waiting for the user to confirm
+    pub fn pending_grants(&self) -> &[GrantRequest] {
+        &self.pending_grants
+    }
+    
+    /// Grant a plugin the capabilities it asked for, after the user confirmed
+    pub fn confirm_grants(&mut self, plugin: &str) -> Result<()> {
+        let Some(index) = self.pending_grants.iter().position(|request| request.plugin == plugin) else {
+            return Err(CompositorError::plugin(format!("No capability request from plugin {}", plugin)));
+        };
+        let request = self.pending_grants.remove(index);
+        self.grants.grant(&request)?;
+        info!("Granted plugin {} capabilities {:?}", request.plugin, request.capabilities);
         Ok(())
     }
     
+    /// Drop a plugin's capability request after the user declined it
+    pub fn deny_grants(&mut self, plugin: &str) {
+        self.pending_grants.retain(|request| request.plugin != plugin);
+    }
+    
+    /// Revoke every capability granted to a plugin
+    pub fn revoke_grants(&mut self, plugin: &str) -> Result<()> {
+        self.grants.revoke(plugin)
+    }
+    
+    /// Requests plugins made since the last call, for the compositor to
+    /// carry out
+    pub fn take_requests(&mut self) -> Vec<PluginRequest> {
+        let mut requests = Vec::new();
+        while let Ok(request) = self.request_receiver.try_recv() {
+            requests.push(request);
+        }
+        requests
+    }
+    
+    /// Pass input on to the plugins observing it
+    pub fn publish_input(&self, event: PluginInputEvent) {
+        // Nobody may be subscribed
+        let _ = self.input.send(event);
+    }
+    
     /// List all loaded plugins
     pub fn list_plugins(&self) -> Vec<&LoadedPlugin> {
         self.plugins.values().collect()
@@ -73,3 +240,106 @@ impl Default for PluginSystem {
         Self::new().expect("Failed to create plugin system")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::StateSnapshot;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+    
+    /// Plugin counting something, reporting the count it was restored with
+    struct CounterPlugin {
+        count: u32,
+        restored: Arc<Mutex<Option<u32>>>,
+    }
+    
+    impl PluginApi for CounterPlugin {
+        fn init(&mut self, _context: PluginContext) -> Result<()> {
+            Ok(())
+        }
+        
+        fn cleanup(&mut self) {}
+        
+        fn info(&self) -> &str {
+            "counter"
+        }
+        
+        fn save_state(&self) -> Result<Option<StateSnapshot>> {
+            StateSnapshot::new(1, &self.count).map(Some)
+        }
+        
+        fn restore_state(&mut self, snapshot: StateSnapshot) -> Result<()> {
+            self.count = snapshot.decode()?;
+            *self.restored.lock().unwrap() = Some(self.count);
+            Ok(())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_unload_saves_state_for_next_load() {
+        let dir = TempDir::new().unwrap();
+        let mut system = PluginSystem::new().unwrap();
+        system.set_state_dir(dir.path().to_path_buf());
+        let manifest = PluginManifest { name: "counter".to_string(), ..Default::default() };
+        
+        let restored = Arc::new(Mutex::new(None));
+        let plugin = CounterPlugin { count: 7, restored: restored.clone() };
+        let id = system.add_plugin(manifest.clone(), Box::new(plugin)).unwrap();
+        assert_eq!(*restored.lock().unwrap(), None);
+        
+        system.unload_plugin(id).await.unwrap();
+        assert!(system.list_plugins().is_empty());
+        assert_eq!(system.state_store().restore::<u32>("counter", 1).unwrap(), Some(7));
+        
+        let plugin = CounterPlugin { count: 0, restored: restored.clone() };
+        system.add_plugin(manifest, Box::new(plugin)).unwrap();
+        assert_eq!(*restored.lock().unwrap(), Some(7));
+    }
+    
+    #[test]
+    fn test_ungranted_plugin_is_refused() {
+        let dir = TempDir::new().unwrap();
+        let mut system = PluginSystem::new().unwrap();
+        system.grants = CapabilityGrants::new(dir.path().join("grants.ron"));
+        let manifest = PluginManifest {
+            name: "recorder".to_string(),
+            capabilities: vec!["input".to_string()],
+            ..Default::default()
+        };
+        
+        let plugin = CounterPlugin { count: 0, restored: Arc::default() };
+        assert!(system.add_plugin(manifest, Box::new(plugin)).is_err());
+        assert_eq!(system.pending_grants().len(), 1);
+        assert!(system.list_plugins().is_empty());
+    }
+    
+    #[test]
+    fn test_context_gates_api_calls() {
+        let dir = TempDir::new().unwrap();
+        let mut system = PluginSystem::new().unwrap();
+        system.grants = CapabilityGrants::new(dir.path().join("grants.ron"));
+        let manifest = PluginManifest {
+            name: "switcher".to_string(),
+            capabilities: vec!["workspaces".to_string()],
+            ..Default::default()
+        };
+        system.check_grants(&manifest).unwrap();
+        system.confirm_grants("switcher").unwrap();
+        
+        let plugin = CounterPlugin { count: 0, restored: Arc::default() };
+        let id = system.add_plugin(manifest, Box::new(plugin)).unwrap();
+        let context = &system.plugins[&id].context;
+        assert!(context.subscribe_input().is_err());
+        assert!(context.read_file(dir.path().join("grants.ron")).is_err());
+        context.switch_workspace("DP-1", 2).unwrap();
+        
+        let requests = system.take_requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].plugin, "switcher");
+        assert!(matches!(
+            &requests[0].action,
+            api::PluginAction::SwitchWorkspace { output, workspace: 2 } if output == "DP-1"
+        ));
+    }
+}
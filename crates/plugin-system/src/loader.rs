@@ -1,7 +1,8 @@
+use std::ffi::CStr;
 use std::path::PathBuf;
 use libloading::{Library, Symbol};
 use compositor_utils::{CompositorError, Result};
-use crate::api::{PluginInitFn, PluginCleanupFn, PluginInfoFn, PluginRegistration};
+use crate::api::{PluginApi, PluginContext, PluginInitFn, PluginCleanupFn, PluginInfoFn, PluginRegistration};
 use crate::manifest::PluginManifest;
 
 /// Plugin loader for dynamically loading shared libraries
@@ -45,7 +46,7 @@ impl PluginLoader {
         let registration = PluginRegistration::new(
             manifest.name.clone(),
             manifest.version.clone(),
-            Vec::new(), // TODO: Parse capabilities from manifest
+            manifest.required_capabilities()?,
             *init_fn,
             *cleanup_fn,
             *info_fn,
@@ -74,3 +75,49 @@ impl Drop for PluginLoader {
         self.unload_all();
     }
 }
+
+/// Plugin in a shared library, driven through its C entry points
+///
+/// Native plugins have no way to hand over state, so they start fresh on
+/// every load.
+pub struct NativePlugin {
+    registration: PluginRegistration,
+    info: String,
+}
+
+impl NativePlugin {
+    /// Wrap a loaded library's entry points; its library must stay loaded
+    /// for as long as the plugin lives
+    pub fn new(registration: PluginRegistration) -> Self {
+        // The library returns a static NUL-terminated string, or null
+        let info = unsafe {
+            let info = (registration.info_fn)();
+            if info.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(info).to_string_lossy().into_owned()
+            }
+        };
+        Self { registration, info }
+    }
+}
+
+impl PluginApi for NativePlugin {
+    fn init(&mut self, _context: PluginContext) -> Result<()> {
+        match unsafe { (self.registration.init_fn)() } {
+            0 => Ok(()),
+            status => Err(CompositorError::plugin(format!(
+                "Plugin {} failed to initialize: {}",
+                self.registration.name, status
+            ))),
+        }
+    }
+
+    fn cleanup(&mut self) {
+        unsafe { (self.registration.cleanup_fn)() }
+    }
+
+    fn info(&self) -> &str {
+        &self.info
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use compositor_utils::{CompositorError, Result};
+use crate::api::PluginCapability;
 
 /// Plugin manifest containing metadata about a plugin
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,7 +27,8 @@ pub struct PluginManifest {
     /// Plugin dependencies
     pub dependencies: Vec<String>,
     
-    /// Plugin capabilities/permissions
+    /// Capabilities the plugin needs, by manifest name, e.g. "render",
+    /// "input" or "ipc"
     pub capabilities: Vec<String>,
     
     /// Additional metadata
@@ -56,6 +58,17 @@ impl PluginManifest {
         Ok(())
     }
     
+    /// Capabilities the plugin needs; unknown names are an error
+    pub fn required_capabilities(&self) -> Result<Vec<PluginCapability>> {
+        self.capabilities
+            .iter()
+            .map(|name| {
+                PluginCapability::from_name(name)
+                    .ok_or_else(|| CompositorError::plugin(format!("Unknown plugin capability: {}", name)))
+            })
+            .collect()
+    }
+    
     /// Validate the manifest for correctness
     pub fn validate(&self) -> Result<()> {
         if self.name.is_empty() {
@@ -70,6 +83,8 @@ impl PluginManifest {
             return Err(CompositorError::plugin("Plugin entry point cannot be empty".to_string()));
         }
         
+        self.required_capabilities()?;
+        
         // TODO: Add more validation (version format, entry point exists, etc.)
         
         Ok(())
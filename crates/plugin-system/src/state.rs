@@ -0,0 +1,147 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use compositor_utils::{CompositorError, Result};
+use tracing::warn;
+
+/// File holding a plugin's state inside its state directory
+const STATE_FILE: &str = "state.ron";
+
+/// State a plugin saved, with the version of its format
+///
+/// Plugins bump the version whenever the shape of their state changes, so a
+/// newer build can migrate or discard state saved by an older one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// Version of the plugin's state format
+    pub version: u32,
+
+    /// The state itself
+    pub data: ron::Value,
+}
+
+impl StateSnapshot {
+    /// Snapshot a plugin's state in version `version` of its format
+    pub fn new<T: Serialize>(version: u32, state: &T) -> Result<Self> {
+        let content = ron::to_string(state)
+            .map_err(|e| CompositorError::plugin(format!("Failed to serialize plugin state: {}", e)))?;
+        let data = ron::from_str(&content)
+            .map_err(|e| CompositorError::plugin(format!("Failed to serialize plugin state: {}", e)))?;
+        Ok(Self { version, data })
+    }
+
+    /// Decode the state, e.g. after checking its version
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T> {
+        self.data
+            .clone()
+            .into_rust()
+            .map_err(|e| CompositorError::plugin(format!("Failed to parse plugin state: {}", e)))
+    }
+}
+
+/// Persists plugin state across plugin hot-reloads and compositor restarts
+///
+/// Each plugin gets its own directory named after it, under
+/// `$XDG_STATE_HOME/custom-compositor/plugins` by default.
+#[derive(Debug, Clone)]
+pub struct PluginStateStore {
+    root: PathBuf,
+}
+
+impl PluginStateStore {
+    /// Create a store keeping state under `root`
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Directory plugin state is kept in unless configured otherwise
+    pub fn default_root() -> PathBuf {
+        dirs::state_dir()
+            .or_else(|| dirs::home_dir().map(|home| home.join(".local/state")))
+            .unwrap_or_else(|| PathBuf::from("/tmp"))
+            .join("custom-compositor")
+            .join("plugins")
+    }
+
+    /// Directory state is kept in
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Save a plugin's state, replacing what it saved before
+    ///
+    /// The file is replaced atomically, so a crash while saving keeps the
+    /// previous state.
+    pub fn save(&self, plugin: &str, snapshot: &StateSnapshot) -> Result<()> {
+        let dir = self.plugin_dir(plugin)?;
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| CompositorError::plugin(format!("Failed to create {}: {}", dir.display(), e)))?;
+
+        let content = ron::ser::to_string_pretty(snapshot, ron::ser::PrettyConfig::default())
+            .map_err(|e| CompositorError::plugin(format!("Failed to serialize state of {}: {}", plugin, e)))?;
+        let path = dir.join(STATE_FILE);
+        let temp = path.with_extension("ron.tmp");
+        std::fs::write(&temp, content)
+            .and_then(|()| std::fs::rename(&temp, &path))
+            .map_err(|e| CompositorError::plugin(format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    /// State a plugin saved before, or `None` if it never saved any
+    pub fn load(&self, plugin: &str) -> Result<Option<StateSnapshot>> {
+        let path = self.plugin_dir(plugin)?.join(STATE_FILE);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(CompositorError::plugin(format!("Failed to read {}: {}", path.display(), e))),
+        };
+        ron::from_str(&content)
+            .map(Some)
+            .map_err(|e| CompositorError::plugin(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+
+    /// State a plugin saved in version `version` of its format
+    ///
+    /// State saved in any other version is ignored; plugins that can migrate
+    /// older state use `load` and check the version themselves.
+    pub fn restore<T: DeserializeOwned>(&self, plugin: &str, version: u32) -> Result<Option<T>> {
+        match self.load(plugin)? {
+            Some(snapshot) if snapshot.version == version => snapshot.decode().map(Some),
+            Some(snapshot) => {
+                warn!(
+                    "Ignoring state of plugin {} saved in version {}, expected {}",
+                    plugin, snapshot.version, version
+                );
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Delete a plugin's saved state
+    pub fn remove(&self, plugin: &str) -> Result<()> {
+        let dir = self.plugin_dir(plugin)?;
+        match std::fs::remove_dir_all(&dir) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(CompositorError::plugin(format!("Failed to remove {}: {}", dir.display(), e))),
+        }
+    }
+
+    /// State directory of a plugin; names that could escape the store are rejected
+    fn plugin_dir(&self, plugin: &str) -> Result<PathBuf> {
+        let valid = !plugin.is_empty()
+            && plugin != "."
+            && plugin != ".."
+            && !plugin.contains(['/', '\\', '\0']);
+        if !valid {
+            return Err(CompositorError::plugin(format!("Invalid plugin name for state: {:?}", plugin)));
+        }
+        Ok(self.root.join(plugin))
+    }
+}
+
+impl Default for PluginStateStore {
+    fn default() -> Self {
+        Self::new(Self::default_root())
+    }
+}
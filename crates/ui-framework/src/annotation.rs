@@ -0,0 +1,383 @@
+// Screen annotation overlay (pen/highlighter tool)
+//
+// A transparent layer drawn above all surfaces where the user can sketch with
+// the pointer or a stylus. Strokes are kept as vector data so they can be
+// undone and re-rasterized at any output size, e.g. for saving to PNG. For
+// the UI pass, each stroke is rasterized over its bounds into an image once
+// finished, and again as it grows while drawn.
+
+use compositor_utils::math::Rect;
+use compositor_utils::prelude::*;
+use glam::Vec2;
+use std::path::Path;
+use std::sync::Arc;
+use vulkan_renderer::{UiFilter, UiImage, UiPrimitive};
+
+/// Drawing tool used for a stroke
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationTool {
+    /// Opaque, pressure-sensitive pen
+    Pen,
+    /// Wide, translucent marker with constant width
+    Highlighter,
+}
+
+/// Keyboard command while annotation mode is active
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationCommand {
+    /// Remove the most recent stroke
+    Undo,
+    /// Save the annotations as a PNG
+    Save,
+    /// Clear the annotations and leave annotation mode
+    Leave,
+}
+
+/// A single sampled point of a stroke
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrokePoint {
+    pub position: Vec2,
+    /// Normalized pressure (0.0-1.0); pointer devices report 1.0
+    pub pressure: f32,
+}
+
+/// A continuous stroke drawn with one tool
+#[derive(Debug, Clone)]
+pub struct Stroke {
+    pub tool: AnnotationTool,
+    pub color: [f32; 4], // RGBA
+    pub width: f32,
+    pub points: Vec<StrokePoint>,
+}
+
+impl Stroke {
+    /// Stroke radius at a given pressure
+    pub fn radius_at(&self, pressure: f32) -> f32 {
+        match self.tool {
+            // Light touches still leave a visible line
+            AnnotationTool::Pen => self.width * 0.5 * (0.25 + 0.75 * pressure.clamp(0.0, 1.0)),
+            AnnotationTool::Highlighter => self.width * 0.5,
+        }
+    }
+
+    /// Color actually composited for this stroke
+    pub fn effective_color(&self) -> [f32; 4] {
+        match self.tool {
+            AnnotationTool::Pen => self.color,
+            AnnotationTool::Highlighter => {
+                let [r, g, b, a] = self.color;
+                [r, g, b, a * HIGHLIGHTER_OPACITY]
+            }
+        }
+    }
+}
+
+/// Opacity multiplier applied to highlighter strokes
+const HIGHLIGHTER_OPACITY: f32 = 0.35;
+
+/// Width multiplier applied to highlighter strokes
+const HIGHLIGHTER_WIDTH_SCALE: f32 = 4.0;
+
+/// Annotation overlay state
+#[derive(Debug, Clone)]
+pub struct AnnotationOverlay {
+    active: bool,
+    tool: AnnotationTool,
+    color: [f32; 4],
+    width: f32,
+    strokes: Vec<Stroke>,
+    current_stroke: Option<Stroke>,
+    /// Images of the finished strokes, `None` where one could not be made
+    images: Vec<Option<StrokeImage>>,
+    /// Image of the stroke in progress
+    current_image: Option<StrokeImage>,
+}
+
+/// A stroke rasterized over its bounds, in logical pixels
+type StrokeImage = (Rect, Arc<UiImage>);
+
+impl AnnotationOverlay {
+    /// Create an inactive overlay with a red pen
+    pub fn new() -> Self {
+        Self {
+            active: false,
+            tool: AnnotationTool::Pen,
+            color: [0.95, 0.2, 0.2, 1.0],
+            width: 4.0,
+            strokes: Vec::new(),
+            current_stroke: None,
+            images: Vec::new(),
+            current_image: None,
+        }
+    }
+
+    /// Whether annotation mode is active and should capture pointer input
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Enable or disable annotation mode
+    ///
+    /// Disabling finishes any stroke in progress; existing strokes stay visible
+    /// until cleared.
+    pub fn set_active(&mut self, active: bool) {
+        if !active {
+            self.end_stroke();
+        }
+        self.active = active;
+        info!("Annotation mode {}", if active { "enabled" } else { "disabled" });
+    }
+
+    /// Toggle annotation mode (bound to a keybinding)
+    pub fn toggle(&mut self) {
+        self.set_active(!self.active);
+    }
+
+    /// Select the drawing tool for subsequent strokes
+    pub fn set_tool(&mut self, tool: AnnotationTool) {
+        self.tool = tool;
+    }
+
+    /// Get the current drawing tool
+    pub fn tool(&self) -> AnnotationTool {
+        self.tool
+    }
+
+    /// Set the color for subsequent strokes
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    /// Set the base width in pixels for subsequent strokes
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width.max(0.5);
+    }
+
+    /// Start a new stroke at the given position
+    pub fn begin_stroke(&mut self, position: Vec2, pressure: f32) {
+        if !self.active {
+            return;
+        }
+        self.end_stroke();
+
+        let width = match self.tool {
+            AnnotationTool::Pen => self.width,
+            AnnotationTool::Highlighter => self.width * HIGHLIGHTER_WIDTH_SCALE,
+        };
+        self.current_stroke = Some(Stroke {
+            tool: self.tool,
+            color: self.color,
+            width,
+            points: vec![StrokePoint { position, pressure: pressure.clamp(0.0, 1.0) }],
+        });
+        self.current_image = self.current_stroke.as_ref().and_then(stroke_image);
+    }
+
+    /// Add a point to the stroke in progress
+    pub fn extend_stroke(&mut self, position: Vec2, pressure: f32) {
+        if let Some(stroke) = &mut self.current_stroke {
+            stroke.points.push(StrokePoint { position, pressure: pressure.clamp(0.0, 1.0) });
+            self.current_image = stroke_image(stroke);
+        }
+    }
+
+    /// Whether a stroke is being drawn
+    pub fn is_drawing(&self) -> bool {
+        self.current_stroke.is_some()
+    }
+
+    /// Finish the stroke in progress
+    pub fn end_stroke(&mut self) {
+        if let Some(stroke) = self.current_stroke.take() {
+            self.images.push(self.current_image.take());
+            self.strokes.push(stroke);
+        }
+    }
+
+    /// Remove the most recent stroke, returning whether anything was undone
+    pub fn undo(&mut self) -> bool {
+        if self.current_stroke.take().is_some() {
+            self.current_image = None;
+            return true;
+        }
+        self.images.pop();
+        self.strokes.pop().is_some()
+    }
+
+    /// Remove all strokes
+    pub fn clear(&mut self) {
+        self.current_stroke = None;
+        self.strokes.clear();
+        self.current_image = None;
+        self.images.clear();
+    }
+
+    /// Whether there is anything drawn
+    pub fn is_empty(&self) -> bool {
+        self.strokes().next().is_none()
+    }
+
+    /// Primitives drawing the strokes for the UI pass, in drawing order
+    pub fn primitives(&self) -> Vec<UiPrimitive> {
+        self.images
+            .iter()
+            .chain([&self.current_image])
+            .flatten()
+            .map(|(rect, image)| UiPrimitive::Image { rect: *rect, image: image.clone(), tint: [1.0; 4] })
+            .collect()
+    }
+
+    /// All strokes in drawing order, including the one in progress
+    pub fn strokes(&self) -> impl Iterator<Item = &Stroke> {
+        self.strokes.iter().chain(self.current_stroke.iter())
+    }
+
+    /// Rasterize all strokes into a straight-alpha RGBA8 image
+    pub fn rasterize(&self, width: u32, height: u32) -> Vec<u8> {
+        let mut pixels = vec![[0.0f32; 4]; width as usize * height as usize];
+
+        for stroke in self.strokes() {
+            // Build a coverage mask first so overlapping segments of a single
+            // translucent stroke do not darken each other.
+            let coverage = stroke_coverage(stroke, Vec2::ZERO, width, height);
+            let [r, g, b, a] = stroke.effective_color();
+
+            for (pixel, &cov) in pixels.iter_mut().zip(coverage.iter()) {
+                if cov <= 0.0 {
+                    continue;
+                }
+                let src_a = a * cov;
+                let dst_a = pixel[3] * (1.0 - src_a);
+                let out_a = src_a + dst_a;
+                if out_a > 0.0 {
+                    pixel[0] = (r * src_a + pixel[0] * dst_a) / out_a;
+                    pixel[1] = (g * src_a + pixel[1] * dst_a) / out_a;
+                    pixel[2] = (b * src_a + pixel[2] * dst_a) / out_a;
+                }
+                pixel[3] = out_a;
+            }
+        }
+
+        pixels
+            .iter()
+            .flat_map(|p| p.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+            .collect()
+    }
+
+    /// Save the annotations as a transparent PNG of the given size, creating
+    /// the directory if missing
+    pub fn save_png(&self, path: impl AsRef<Path>, width: u32, height: u32) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let rgba = self.rasterize(width, height);
+        std::fs::write(path, compositor_utils::png::encode_rgba(&rgba, width, height))?;
+        info!("Saved annotations to {}", path.display());
+        Ok(())
+    }
+}
+
+impl Default for AnnotationOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rasterize a stroke over its bounds, with a pixel of slack for the
+/// anti-aliased edge; `None` if it covers no pixel
+fn stroke_image(stroke: &Stroke) -> Option<StrokeImage> {
+    let radius = stroke.points.iter().map(|point| stroke.radius_at(point.pressure)).fold(0.0, f32::max) + 1.0;
+    let min = stroke.points.iter().map(|point| point.position).reduce(Vec2::min)?;
+    let max = stroke.points.iter().map(|point| point.position).reduce(Vec2::max)?;
+    let origin = (min - radius).floor();
+    let size = (max + radius).ceil() - origin;
+    let (width, height) = (size.x as u32, size.y as u32);
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let [r, g, b, a] = stroke.effective_color().map(|channel| channel.clamp(0.0, 1.0));
+    let rgb = [r, g, b].map(|channel| (channel * 255.0).round() as u8);
+    let pixels = stroke_coverage(stroke, origin, width, height)
+        .into_iter()
+        .flat_map(|coverage| [rgb[0], rgb[1], rgb[2], (a * coverage * 255.0).round() as u8])
+        .collect();
+    let image = UiImage::new(width, height, pixels, UiFilter::Linear)
+        .map_err(|e| warn!("Cannot draw annotation stroke: {}", e))
+        .ok()?;
+    Some((Rect::new(origin.x, origin.y, size.x, size.y), image))
+}
+
+/// Compute per-pixel coverage (0.0-1.0) of a stroke over a `width` by
+/// `height` area starting at `origin`
+fn stroke_coverage(stroke: &Stroke, origin: Vec2, width: u32, height: u32) -> Vec<f32> {
+    let mut coverage = vec![0.0f32; width as usize * height as usize];
+    let mut stamp = |center: Vec2, radius: f32| {
+        let center = center - origin;
+        let min_x = (center.x - radius - 1.0).floor().max(0.0) as u32;
+        let min_y = (center.y - radius - 1.0).floor().max(0.0) as u32;
+        let max_x = ((center.x + radius + 1.0).ceil().max(0.0) as u32).min(width);
+        let max_y = ((center.y + radius + 1.0).ceil().max(0.0) as u32).min(height);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let pixel_center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                // One pixel of anti-aliasing at the edge
+                let cov = (radius + 0.5 - pixel_center.distance(center)).clamp(0.0, 1.0);
+                let slot = &mut coverage[(y * width + x) as usize];
+                *slot = slot.max(cov);
+            }
+        }
+    };
+
+    let points = &stroke.points;
+    if let Some(first) = points.first() {
+        stamp(first.position, stroke.radius_at(first.pressure));
+    }
+    for segment in points.windows(2) {
+        let (start, end) = (segment[0], segment[1]);
+        // Stamp at sub-radius spacing so the segment reads as a solid line
+        let length = start.position.distance(end.position);
+        let spacing = (stroke.radius_at(start.pressure.min(end.pressure)) * 0.5).max(0.5);
+        let steps = (length / spacing).ceil().max(1.0) as u32;
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            let position = start.position.lerp(end.position, t);
+            let pressure = start.pressure + (end.pressure - start.pressure) * t;
+            stamp(position, stroke.radius_at(pressure));
+        }
+    }
+
+    coverage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_strokes_over_their_bounds_until_undone() {
+        let mut overlay = AnnotationOverlay::new();
+        overlay.set_active(true);
+        overlay.begin_stroke(Vec2::new(10.0, 10.0), 1.0);
+        overlay.extend_stroke(Vec2::new(20.0, 10.0), 1.0);
+        assert_eq!(overlay.primitives().len(), 1, "the stroke in progress is drawn");
+        overlay.end_stroke();
+
+        let primitives = overlay.primitives();
+        let [UiPrimitive::Image { rect, image, .. }] = primitives.as_slice() else {
+            panic!("expected one stroke image, got {:?}", primitives);
+        };
+        // A 4 pixel wide pen reaches 2 pixels out, plus a pixel of slack
+        assert_eq!(*rect, Rect::new(7.0, 7.0, 16.0, 6.0));
+        assert_eq!((image.width(), image.height()), (16, 6));
+        // Pixel (15, 10) in the middle of the line is fully covered
+        let alpha = image.pixels()[((10 - 7) * 16 + (15 - 7)) * 4 + 3];
+        assert_eq!(alpha, 255);
+
+        assert!(overlay.undo());
+        assert!(overlay.primitives().is_empty());
+        assert!(!overlay.undo());
+    }
+}
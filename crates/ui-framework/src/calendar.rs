@@ -0,0 +1,205 @@
+// Month calendar for the clock popover
+//
+// Proleptic Gregorian date arithmetic on day numbers counted from the Unix
+// epoch, a month grid laid out by week, and keyboard navigation: arrow keys
+// move by day and week, Home/End jump to the start and end of the month,
+// Tab/Shift+Tab change the month and Escape closes the popover.
+
+use crate::focus::{FocusAction, NavigationKey};
+
+/// A calendar date
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    /// 1 - 12
+    pub month: u32,
+    /// 1 - 31
+    pub day: u32,
+}
+
+impl Date {
+    /// Date, if it exists
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        ((1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day))
+            .then_some(Self { year, month, day })
+    }
+
+    /// Date of a day number counted from 1970-01-01
+    pub fn from_days(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as i32;
+        Self { year, month, day }
+    }
+
+    /// Date of a Unix timestamp shifted by a UTC offset in seconds
+    pub fn from_unix(timestamp: i64, utc_offset: i32) -> Self {
+        Self::from_days((timestamp + i64::from(utc_offset)).div_euclid(86_400))
+    }
+
+    /// Day number counted from 1970-01-01
+    pub fn to_days(self) -> i64 {
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let month = i64::from(self.month);
+        let doy = (153 * if month > 2 { month - 3 } else { month + 9 } + 2) / 5 + i64::from(self.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    /// Day of the week, 0 = Monday through 6 = Sunday
+    pub fn weekday(self) -> u32 {
+        // 1970-01-01 was a Thursday
+        (self.to_days() + 3).rem_euclid(7) as u32
+    }
+
+    /// Date `days` days later (or earlier when negative)
+    pub fn add_days(self, days: i64) -> Self {
+        Self::from_days(self.to_days() + days)
+    }
+
+    /// Same day in a month `months` later, clamped to the end of shorter months
+    pub fn add_months(self, months: i32) -> Self {
+        let index = self.year * 12 + self.month as i32 - 1 + months;
+        let year = index.div_euclid(12);
+        let month = index.rem_euclid(12) as u32 + 1;
+        Self { year, month, day: self.day.min(days_in_month(year, month)) }
+    }
+
+    /// First day of the month
+    pub fn month_start(self) -> Self {
+        Self { day: 1, ..self }
+    }
+
+    /// Last day of the month
+    pub fn month_end(self) -> Self {
+        Self { day: days_in_month(self.year, self.month), ..self }
+    }
+}
+
+/// Whether a year has a February 29th
+pub fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// Number of days in a month
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// First day of the week in the month grid
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WeekStart {
+    #[default]
+    Monday,
+    Sunday,
+}
+
+/// Days of a month laid out in weeks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonthGrid {
+    pub year: i32,
+    pub month: u32,
+    /// Rows of seven days; `None` pads days of the neighbouring months
+    pub weeks: Vec<[Option<u32>; 7]>,
+}
+
+impl MonthGrid {
+    /// Lay out the month containing `date`
+    pub fn new(date: Date, week_start: WeekStart) -> Self {
+        let first = date.month_start();
+        let shift = match week_start {
+            WeekStart::Monday => 0,
+            WeekStart::Sunday => 1,
+        };
+        let column = ((first.weekday() + shift) % 7) as usize;
+        let days = days_in_month(date.year, date.month);
+        let rows = (column + days as usize).div_ceil(7);
+
+        let mut weeks = vec![[None; 7]; rows];
+        for day in 1..=days {
+            let cell = column + day as usize - 1;
+            weeks[cell / 7][cell % 7] = Some(day);
+        }
+        Self { year: date.year, month: date.month, weeks }
+    }
+
+    /// Row and column of a day of this month
+    pub fn position(&self, day: u32) -> Option<(usize, usize)> {
+        self.weeks.iter().enumerate().find_map(|(row, week)| {
+            week.iter().position(|&cell| cell == Some(day)).map(|column| (row, column))
+        })
+    }
+}
+
+/// Calendar shown in the clock popover
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarPopover {
+    today: Date,
+    selected: Date,
+    week_start: WeekStart,
+}
+
+impl CalendarPopover {
+    /// Open on today's month with today selected
+    pub fn new(today: Date, week_start: WeekStart) -> Self {
+        Self { today, selected: today, week_start }
+    }
+
+    /// Keep the today marker current while the popover stays open
+    pub fn set_today(&mut self, today: Date) {
+        self.today = today;
+    }
+
+    pub fn today(&self) -> Date {
+        self.today
+    }
+
+    /// Date with keyboard focus
+    pub fn selected(&self) -> Date {
+        self.selected
+    }
+
+    /// Select a date, e.g. on click; the grid follows to its month
+    pub fn select(&mut self, date: Date) {
+        self.selected = date;
+    }
+
+    /// Month shown
+    pub fn grid(&self) -> MonthGrid {
+        MonthGrid::new(self.selected, self.week_start)
+    }
+
+    /// Move the selection with the keyboard
+    pub fn navigate(&mut self, key: NavigationKey) -> FocusAction<Date> {
+        let selected = match key {
+            NavigationKey::Left => self.selected.add_days(-1),
+            NavigationKey::Right => self.selected.add_days(1),
+            NavigationKey::Up => self.selected.add_days(-7),
+            NavigationKey::Down => self.selected.add_days(7),
+            NavigationKey::Home => self.selected.month_start(),
+            NavigationKey::End => self.selected.month_end(),
+            NavigationKey::Tab => self.selected.add_months(1),
+            NavigationKey::BackTab => self.selected.add_months(-1),
+            NavigationKey::Activate => return FocusAction::Activate(self.selected),
+            NavigationKey::Cancel => return FocusAction::Dismiss,
+        };
+        if selected == self.selected {
+            return FocusAction::Ignored;
+        }
+        self.selected = selected;
+        FocusAction::Moved(selected)
+    }
+}
@@ -1,4 +1,6 @@
 use compositor_utils::Result;
+use compositor_utils::accessibility::{AccessibilityTree, AccessibleId, AccessibleRole, AccessibleState};
+use compositor_utils::theme_tokens::{InteractionState, StateColors};
 use glam::Vec2;
 
 /// Button component for UI framework
@@ -68,6 +70,40 @@ impl Button {
         }
     }
     
+    /// State the button is drawn in
+    pub fn interaction_state(&self) -> InteractionState {
+        if !self.is_enabled {
+            InteractionState::Disabled
+        } else if self.is_pressed {
+            InteractionState::Pressed
+        } else if self.is_hovered {
+            InteractionState::Hover
+        } else {
+            InteractionState::Normal
+        }
+    }
+    
+    /// Background color in the button's current state, e.g. from
+    /// `Palette::states(ColorToken::Accent)`
+    pub fn background(&self, colors: &StateColors) -> [f32; 4] {
+        colors.get(self.interaction_state())
+    }
+    
+    /// Expose the button to screen readers under `parent`
+    pub fn register_accessible(&self, tree: &AccessibilityTree, parent: AccessibleId) -> AccessibleId {
+        let id = tree.add(parent, AccessibleRole::PushButton, &self.text);
+        tree.set_state(id, AccessibleState::Focusable, true);
+        self.sync_accessible(tree, id);
+        id
+    }
+    
+    /// Report label and state changes to screen readers
+    pub fn sync_accessible(&self, tree: &AccessibilityTree, id: AccessibleId) {
+        tree.set_name(id, &self.text);
+        tree.set_state(id, AccessibleState::Pressed, self.is_pressed);
+        tree.set_state(id, AccessibleState::Sensitive, self.is_enabled);
+    }
+    
     /// Update button (called each frame)
     pub fn update(&mut self) -> Result<()> {
         // Update animations, state, etc.
@@ -1,5 +1,9 @@
 use compositor_utils::Result;
 use glam::Vec2;
+use std::sync::Arc;
+
+use crate::font::Font;
+use crate::shaping::{Direction, FallbackShaper, FontShaper, ShapedLine, TextShaper};
 
 /// Text alignment options
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -20,6 +24,10 @@ pub struct Text {
     pub max_width: Option<f32>,
     pub is_visible: bool,
     pub line_height: f32,
+    /// Base direction; detected from the content when `None`
+    pub direction: Option<Direction>,
+    /// Font the text is measured with; advances are estimated without one
+    pub font: Option<Arc<Font>>,
 }
 
 impl Text {
@@ -34,6 +42,8 @@ impl Text {
             max_width: None,
             is_visible: true,
             line_height: 1.2,
+            direction: None,
+            font: None,
         }
     }
     
@@ -67,6 +77,21 @@ impl Text {
         self.line_height = height.max(0.1);
     }
     
+    /// Force a base direction instead of detecting it from the content
+    pub fn set_direction(&mut self, direction: Option<Direction>) {
+        self.direction = direction;
+    }
+    
+    /// Measure with a font's metrics instead of estimated advances
+    pub fn set_font(&mut self, font: Option<Arc<Font>>) {
+        self.font = font;
+    }
+    
+    /// Shape the content as a single line
+    pub fn shape(&self, shaper: &dyn TextShaper) -> ShapedLine {
+        ShapedLine::new(&self.content, self.direction, self.font_size, shaper)
+    }
+    
     /// Update text content
     pub fn set_content(&mut self, content: String) {
         self.content = content;
@@ -78,17 +103,18 @@ impl Text {
             return Vec2::ZERO;
         }
         
-        // Simplified text metrics - in a real implementation,
-        // this would use proper font metrics
-        let char_width = self.font_size * 0.6; // Approximate
+        // Estimated advances until a font is set
+        let width = match &self.font {
+            Some(font) => self.shape(&FontShaper::new(font.clone())).width,
+            None => self.shape(&FallbackShaper).width,
+        };
         let line_height = self.font_size * self.line_height;
         
         if let Some(max_width) = self.max_width {
-            let chars_per_line = (max_width / char_width) as usize;
-            let lines = (self.content.len() + chars_per_line - 1) / chars_per_line.max(1);
-            Vec2::new(max_width, line_height * lines as f32)
+            let lines = (width / max_width.max(1.0)).ceil().max(1.0);
+            Vec2::new(max_width, line_height * lines)
         } else {
-            Vec2::new(char_width * self.content.len() as f32, line_height)
+            Vec2::new(width, line_height)
         }
     }
     
@@ -0,0 +1,156 @@
+// Damage tracking for compositor-drawn UI surfaces
+//
+// The bar, on-screen displays and notifications are drawn into cached
+// textures that are only re-rendered where they changed. Widgets register
+// their bounds on the surface, and an update to one widget, such as the clock
+// ticking over to the next minute, damages just its bounding box. A widget
+// that moves or resizes damages both where it was and where it is now. The
+// whole surface counts as damaged when it is created or resized, and when
+// the damage gets too fragmented to be worth redrawing piece by piece.
+
+use compositor_utils::math::Rect;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Rectangles beyond which damage is merged into its bounding box
+pub const MAX_DAMAGE_RECTS: usize = 8;
+
+/// Part of a surface to re-render
+#[derive(Debug, Clone, PartialEq)]
+pub enum SurfaceDamage {
+    /// The whole surface
+    Full,
+    /// Only these regions, in surface pixels, aligned to whole pixels
+    Regions(Vec<Rect>),
+}
+
+/// Damage of one UI surface and the bounds of the widgets on it
+#[derive(Debug, Clone)]
+pub struct DamageTracker<W> {
+    /// Surface size in pixels
+    width: f32,
+    height: f32,
+    /// Bounds of each widget, in surface pixels
+    widgets: HashMap<W, Rect>,
+    /// Regions changed since the last re-render
+    rects: Vec<Rect>,
+    /// The whole surface changed
+    full: bool,
+}
+
+impl<W: Eq + Hash> DamageTracker<W> {
+    /// Create a tracker for a surface of this size, fully damaged as nothing
+    /// was rendered yet
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            widgets: HashMap::new(),
+            rects: Vec::new(),
+            full: true,
+        }
+    }
+
+    /// Resize the surface, damaging all of it
+    pub fn resize(&mut self, width: f32, height: f32) {
+        if (self.width, self.height) != (width, height) {
+            self.width = width;
+            self.height = height;
+            self.damage_full();
+        }
+    }
+
+    /// Set where a widget is drawn, damaging its old and new bounds if it
+    /// moved or resized
+    pub fn set_widget_bounds(&mut self, widget: W, bounds: Rect) {
+        match self.widgets.insert(widget, bounds) {
+            Some(old) if old == bounds => {}
+            Some(old) => {
+                self.damage(old);
+                self.damage(bounds);
+            }
+            None => self.damage(bounds),
+        }
+    }
+
+    /// Remove a widget from the surface, damaging where it was
+    pub fn remove_widget(&mut self, widget: &W) {
+        if let Some(old) = self.widgets.remove(widget) {
+            self.damage(old);
+        }
+    }
+
+    /// Bounds of a widget, if it is on the surface
+    pub fn widget_bounds(&self, widget: &W) -> Option<Rect> {
+        self.widgets.get(widget).copied()
+    }
+
+    /// Damage the bounds of a widget whose content changed
+    pub fn damage_widget(&mut self, widget: &W) {
+        if let Some(bounds) = self.widget_bounds(widget) {
+            self.damage(bounds);
+        }
+    }
+
+    /// Damage a region in surface pixels
+    pub fn damage(&mut self, rect: Rect) {
+        let Some(rect) = self.clip(rect) else {
+            return;
+        };
+        if self.full || self.rects.iter().any(|damaged| contains(damaged, &rect)) {
+            return;
+        }
+        self.rects.retain(|damaged| !contains(&rect, damaged));
+        self.rects.push(rect);
+        if self.rects.len() > MAX_DAMAGE_RECTS {
+            self.rects = vec![bounding_box(&self.rects)];
+        }
+    }
+
+    /// Damage the whole surface, e.g. after a theme change
+    pub fn damage_full(&mut self) {
+        self.full = true;
+        self.rects.clear();
+    }
+
+    /// Whether any part of the surface needs re-rendering
+    pub fn is_damaged(&self) -> bool {
+        self.full || !self.rects.is_empty()
+    }
+
+    /// Take the damage to re-render; `None` when nothing changed
+    pub fn take(&mut self) -> Option<SurfaceDamage> {
+        let full = std::mem::replace(&mut self.full, false);
+        let rects = std::mem::take(&mut self.rects);
+        if full {
+            return Some(SurfaceDamage::Full);
+        }
+        (!rects.is_empty()).then_some(SurfaceDamage::Regions(rects))
+    }
+
+    /// Part of `rect` on the surface, grown to whole pixels
+    fn clip(&self, rect: Rect) -> Option<Rect> {
+        let x0 = rect.x.floor().max(0.0);
+        let y0 = rect.y.floor().max(0.0);
+        let x1 = (rect.x + rect.width).ceil().min(self.width);
+        let y1 = (rect.y + rect.height).ceil().min(self.height);
+        (x1 > x0 && y1 > y0).then(|| Rect::new(x0, y0, x1 - x0, y1 - y0))
+    }
+}
+
+/// Whether `outer` covers all of `inner`
+fn contains(outer: &Rect, inner: &Rect) -> bool {
+    inner.x >= outer.x
+        && inner.y >= outer.y
+        && inner.x + inner.width <= outer.x + outer.width
+        && inner.y + inner.height <= outer.y + outer.height
+}
+
+/// Smallest rectangle containing all of `rects`
+fn bounding_box(rects: &[Rect]) -> Rect {
+    let x0 = rects.iter().map(|r| r.x).fold(f32::INFINITY, f32::min);
+    let y0 = rects.iter().map(|r| r.y).fold(f32::INFINITY, f32::min);
+    let x1 = rects.iter().map(|r| r.x + r.width).fold(f32::NEG_INFINITY, f32::max);
+    let y1 = rects.iter().map(|r| r.y + r.height).fold(f32::NEG_INFINITY, f32::max);
+    Rect::new(x0, y0, x1 - x0, y1 - y0)
+}
@@ -1,2 +1,48 @@
 // Visual effects for glassmorphism and neomorphism
+use compositor_utils::params::ParameterRegistry;
+
+/// Live-tunable parameter names for compositor effects
+pub mod params {
+    pub const BLUR_RADIUS: &str = "effects.blur_radius";
+    pub const SHADOW_INTENSITY: &str = "effects.shadow_intensity";
+    pub const CORNER_RADIUS: &str = "effects.corner_radius";
+}
+
 pub struct EffectsRenderer;
+
+/// Effect values sampled once per frame from the parameter registry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EffectParameters {
+    pub blur_radius: f32,
+    pub shadow_intensity: f32,
+    pub corner_radius: f32,
+}
+
+impl Default for EffectParameters {
+    fn default() -> Self {
+        Self {
+            blur_radius: 20.0,
+            shadow_intensity: 0.3,
+            corner_radius: 12.0,
+        }
+    }
+}
+
+impl EffectParameters {
+    /// Register the effect parameters with their defaults and tuning ranges
+    pub fn register(registry: &ParameterRegistry, defaults: EffectParameters) {
+        registry.register(params::BLUR_RADIUS, defaults.blur_radius, 0.0, 128.0);
+        registry.register(params::SHADOW_INTENSITY, defaults.shadow_intensity, 0.0, 1.0);
+        registry.register(params::CORNER_RADIUS, defaults.corner_radius, 0.0, 64.0);
+    }
+
+    /// Read the current effect values, falling back to defaults for unregistered names
+    pub fn from_registry(registry: &ParameterRegistry) -> Self {
+        let defaults = Self::default();
+        Self {
+            blur_radius: registry.get_or(params::BLUR_RADIUS, defaults.blur_radius),
+            shadow_intensity: registry.get_or(params::SHADOW_INTENSITY, defaults.shadow_intensity),
+            corner_radius: registry.get_or(params::CORNER_RADIUS, defaults.corner_radius),
+        }
+    }
+}
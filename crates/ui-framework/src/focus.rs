@@ -0,0 +1,229 @@
+// Keyboard focus ring for compositor UI
+//
+// Every compositor-owned surface (bar, launcher, switcher, notifications)
+// registers its interactive elements here in tab order. Tab and Shift+Tab
+// cycle through them, arrow keys move to the nearest element in that
+// direction, Enter/Space activate and Escape dismisses. The focus indicator is
+// only shown once the keyboard has been used, so pointer users don't see it.
+
+use compositor_utils::accessibility::{AccessibilityTree, AccessibleId};
+use compositor_utils::math::Rect;
+use glam::Vec2;
+use std::sync::Arc;
+
+use crate::styling::FocusIndicatorStyle;
+
+/// Keys that drive focus navigation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NavigationKey {
+    Tab,
+    BackTab,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    /// Enter or Space
+    Activate,
+    /// Escape
+    Cancel,
+}
+
+/// Result of a navigation key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FocusAction<K> {
+    /// Focus moved to a new element
+    Moved(K),
+    /// The focused element should be activated
+    Activate(K),
+    /// The surface should close or give up focus
+    Dismiss,
+    /// Nothing to do (e.g. no element in that direction)
+    Ignored,
+}
+
+/// An element that can take keyboard focus
+#[derive(Debug, Clone, PartialEq)]
+pub struct FocusTarget<K> {
+    pub key: K,
+    pub bounds: Rect,
+    /// Node announced to screen readers when focused
+    pub accessible: Option<AccessibleId>,
+}
+
+impl<K> FocusTarget<K> {
+    /// Create a target without an accessible node
+    pub fn new(key: K, bounds: Rect) -> Self {
+        Self { key, bounds, accessible: None }
+    }
+}
+
+/// Focus indicator to draw around the focused element
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FocusIndicator {
+    pub bounds: Rect,
+    pub color: [f32; 4],
+    pub width: f32,
+}
+
+/// Keyboard focus over the interactive elements of one surface
+pub struct FocusRing<K> {
+    targets: Vec<FocusTarget<K>>,
+    focused: Option<usize>,
+    /// Whether the keyboard was used since the last pointer interaction
+    keyboard_active: bool,
+    accessibility: Option<Arc<AccessibilityTree>>,
+}
+
+impl<K: Clone + PartialEq> FocusRing<K> {
+    /// Create an empty focus ring
+    pub fn new() -> Self {
+        Self {
+            targets: Vec::new(),
+            focused: None,
+            keyboard_active: false,
+            accessibility: None,
+        }
+    }
+
+    /// Report focus changes to screen readers
+    pub fn with_accessibility(mut self, tree: Arc<AccessibilityTree>) -> Self {
+        self.accessibility = Some(tree);
+        self
+    }
+
+    /// Replace the focusable elements, in tab order
+    ///
+    /// Focus stays on the same key if it is still present.
+    pub fn set_targets(&mut self, targets: Vec<FocusTarget<K>>) {
+        let focused_key = self.focused_key().cloned();
+        self.targets = targets;
+        self.focused = focused_key.and_then(|key| self.index_of(&key));
+    }
+
+    /// Key of the focused element
+    pub fn focused_key(&self) -> Option<&K> {
+        self.focused.map(|index| &self.targets[index].key)
+    }
+
+    /// Focus an element by key, returning whether it exists
+    pub fn focus(&mut self, key: &K) -> bool {
+        match self.index_of(key) {
+            Some(index) => {
+                self.set_focused(Some(index));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop focus, e.g. when the surface closes
+    pub fn clear(&mut self) {
+        self.set_focused(None);
+        self.keyboard_active = false;
+    }
+
+    /// The pointer was used; hide the indicator until the next key
+    pub fn pointer_used(&mut self) {
+        self.keyboard_active = false;
+    }
+
+    /// Handle a navigation key
+    pub fn navigate(&mut self, key: NavigationKey) -> FocusAction<K> {
+        self.keyboard_active = true;
+        if key == NavigationKey::Cancel {
+            return FocusAction::Dismiss;
+        }
+        if self.targets.is_empty() {
+            return FocusAction::Ignored;
+        }
+
+        let last = self.targets.len() - 1;
+        let next = match (key, self.focused) {
+            (NavigationKey::Activate, Some(index)) => {
+                return FocusAction::Activate(self.targets[index].key.clone());
+            }
+            (NavigationKey::Activate, None) => return FocusAction::Ignored,
+            // The first key press focuses the first element
+            (_, None) => Some(0),
+            (NavigationKey::Tab, Some(index)) => Some(if index == last { 0 } else { index + 1 }),
+            (NavigationKey::BackTab, Some(index)) => Some(if index == 0 { last } else { index - 1 }),
+            (NavigationKey::Home, Some(_)) => Some(0),
+            (NavigationKey::End, Some(_)) => Some(last),
+            (NavigationKey::Up, Some(index)) => self.nearest(index, Vec2::new(0.0, -1.0)),
+            (NavigationKey::Down, Some(index)) => self.nearest(index, Vec2::new(0.0, 1.0)),
+            (NavigationKey::Left, Some(index)) => self.nearest(index, Vec2::new(-1.0, 0.0)),
+            (NavigationKey::Right, Some(index)) => self.nearest(index, Vec2::new(1.0, 0.0)),
+            // Handled above
+            (NavigationKey::Cancel, Some(_)) => None,
+        };
+
+        match next {
+            Some(index) if Some(index) != self.focused => {
+                self.set_focused(Some(index));
+                FocusAction::Moved(self.targets[index].key.clone())
+            }
+            _ => FocusAction::Ignored,
+        }
+    }
+
+    /// Indicator to draw, if the keyboard is in use and something is focused
+    pub fn indicator(&self, style: &FocusIndicatorStyle) -> Option<FocusIndicator> {
+        if !self.keyboard_active {
+            return None;
+        }
+        let bounds = self.targets[self.focused?].bounds;
+        let inset = style.offset + style.width;
+        Some(FocusIndicator {
+            bounds: Rect::new(
+                bounds.x - inset,
+                bounds.y - inset,
+                bounds.width + 2.0 * inset,
+                bounds.height + 2.0 * inset,
+            ),
+            color: style.color,
+            width: style.width,
+        })
+    }
+
+    fn index_of(&self, key: &K) -> Option<usize> {
+        self.targets.iter().position(|target| &target.key == key)
+    }
+
+    fn set_focused(&mut self, index: Option<usize>) {
+        self.focused = index;
+        if let Some(tree) = &self.accessibility {
+            tree.focus(index.and_then(|index| self.targets[index].accessible));
+        }
+    }
+
+    /// Closest element whose center lies in `direction` from the element at `from`
+    ///
+    /// Distance off the axis of movement counts double so that moving right
+    /// in a row prefers the neighbour in the same row.
+    fn nearest(&self, from: usize, direction: Vec2) -> Option<usize> {
+        let origin = self.targets[from].bounds.center();
+        self.targets
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| index != from)
+            .filter_map(|(index, target)| {
+                let delta = target.bounds.center() - origin;
+                let along = delta.dot(direction);
+                if along <= 0.0 {
+                    return None;
+                }
+                let across = (delta - direction * along).length();
+                Some((index, along + 2.0 * across))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+}
+
+impl<K: Clone + PartialEq> Default for FocusRing<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
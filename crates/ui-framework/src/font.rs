@@ -0,0 +1,361 @@
+// OpenType font metrics for text shaping
+//
+// The shaper maps characters to glyphs and positions them with the font's own
+// advances and pair kerning, read straight from the TrueType/OpenType tables:
+// cmap (formats 4 and 12), head, hhea, hmtx and the legacy kern table. Glyph
+// outlines are read from the TrueType glyf table for labels rasterized on the
+// CPU; fonts with CFF outlines measure but draw nothing. GPOS kerning and
+// GSUB substitutions are not applied.
+
+use compositor_utils::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Maximum nesting of composite glyphs
+const MAX_COMPONENT_DEPTH: u32 = 8;
+
+/// Point of a glyph outline in font units, y pointing up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlinePoint {
+    pub x: f32,
+    pub y: f32,
+    /// Whether the point is on the curve rather than a quadratic control point
+    pub on_curve: bool,
+}
+
+/// Metrics and character map of one font face
+#[derive(Debug)]
+pub struct Font {
+    data: Vec<u8>,
+    units_per_em: u16,
+    /// Distance from the baseline to the top and bottom of a line, in font
+    /// units; the descender is negative
+    ascender: i16,
+    descender: i16,
+    /// Offsets of the glyf and loca tables and whether loca has 32-bit entries
+    outlines: Option<(usize, usize, bool)>,
+    /// Offset and format of the Unicode character map subtable
+    cmap: (usize, u16),
+    /// Advance widths of the glyphs with their own horizontal metrics; later
+    /// glyphs repeat the last one
+    advances: Vec<u16>,
+    /// Horizontal kerning between glyph pairs in visual order, in font units
+    kerning: HashMap<(u16, u16), i16>,
+}
+
+impl Font {
+    /// Read a font file; of a collection, the first face is used
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    /// Parse the tables of a font file's contents
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        let invalid = |what: &str| CompositorError::graphics(format!("Invalid font: {}", what));
+
+        // Collections list the offsets of their faces' table directories
+        let directory = match data.get(..4) {
+            Some(b"ttcf") => u32_at(&data, 12).ok_or_else(|| invalid("truncated collection"))? as usize,
+            _ => 0,
+        };
+        let table_count = u16_at(&data, directory + 4).ok_or_else(|| invalid("truncated table directory"))?;
+        let table = |tag: &[u8; 4]| {
+            (0..table_count as usize).find_map(|index| {
+                let record = directory + 12 + index * 16;
+                (data.get(record..record + 4)? == tag).then_some(u32_at(&data, record + 8)? as usize)
+            })
+        };
+
+        let head = table(b"head").ok_or_else(|| invalid("missing head table"))?;
+        let units_per_em = u16_at(&data, head + 18).filter(|&units| units > 0).ok_or_else(|| invalid("bad units per em"))?;
+
+        let hhea = table(b"hhea").ok_or_else(|| invalid("missing hhea table"))?;
+        let metric_count = u16_at(&data, hhea + 34).ok_or_else(|| invalid("truncated hhea table"))?;
+        let ascender = u16_at(&data, hhea + 4).ok_or_else(|| invalid("truncated hhea table"))? as i16;
+        let descender = u16_at(&data, hhea + 6).ok_or_else(|| invalid("truncated hhea table"))? as i16;
+        let hmtx = table(b"hmtx").ok_or_else(|| invalid("missing hmtx table"))?;
+        let advances = (0..metric_count as usize)
+            .map(|glyph| u16_at(&data, hmtx + glyph * 4))
+            .collect::<Option<Vec<u16>>>()
+            .ok_or_else(|| invalid("truncated hmtx table"))?;
+
+        let cmap = table(b"cmap").ok_or_else(|| invalid("missing cmap table"))?;
+        let cmap = unicode_cmap(&data, cmap).ok_or_else(|| invalid("no Unicode character map"))?;
+
+        let kerning = table(b"kern").map(|kern| kerning_pairs(&data, kern)).unwrap_or_default();
+
+        let long_offsets = u16_at(&data, head + 50) == Some(1);
+        let outlines = table(b"glyf").zip(table(b"loca")).map(|(glyf, loca)| (glyf, loca, long_offsets));
+
+        Ok(Self { data, units_per_em, ascender, descender, outlines, cmap, advances, kerning })
+    }
+
+    /// Design units per em, which advances and kerning are given in
+    pub fn units_per_em(&self) -> u16 {
+        self.units_per_em
+    }
+
+    /// Distance from the baseline to the top of a line, in font units
+    pub fn ascender(&self) -> f32 {
+        self.ascender as f32
+    }
+
+    /// Distance from the baseline to the bottom of a line, in font units;
+    /// negative below the baseline
+    pub fn descender(&self) -> f32 {
+        self.descender as f32
+    }
+
+    /// Glyph of a character, `None` where the font has none
+    pub fn glyph_index(&self, c: char) -> Option<u16> {
+        let (offset, format) = self.cmap;
+        let glyph = match format {
+            4 => cmap4_lookup(&self.data, offset, c as u32),
+            12 => cmap12_lookup(&self.data, offset, c as u32),
+            _ => None,
+        };
+        glyph.filter(|&glyph| glyph != 0)
+    }
+
+    /// Advance width of a glyph, in font units
+    pub fn advance(&self, glyph: u16) -> u16 {
+        self.advances
+            .get(glyph as usize)
+            .or(self.advances.last())
+            .copied()
+            .unwrap_or(self.units_per_em / 2)
+    }
+
+    /// Adjustment of the advance of `left` when followed by `right`, in font units
+    pub fn kerning(&self, left: u16, right: u16) -> i16 {
+        self.kerning.get(&(left, right)).copied().unwrap_or(0)
+    }
+
+    /// Closed contours of a glyph's outline; empty for blank glyphs and
+    /// fonts without TrueType outlines
+    pub fn outline(&self, glyph: u16) -> Vec<Vec<OutlinePoint>> {
+        let mut contours = Vec::new();
+        self.append_outline(glyph, [1.0, 0.0, 0.0, 1.0, 0.0, 0.0], 0, &mut contours);
+        contours
+    }
+
+    /// Add a glyph's contours transformed by `[xx, xy, yx, yy, dx, dy]`,
+    /// resolving composite glyphs
+    fn append_outline(&self, glyph: u16, transform: [f32; 6], depth: u32, contours: &mut Vec<Vec<OutlinePoint>>) {
+        let Some((glyf, loca, long_offsets)) = self.outlines else { return };
+        let data = &self.data;
+        let offset = |glyph: usize| match long_offsets {
+            true => u32_at(data, loca + glyph * 4).map(|offset| offset as usize),
+            false => u16_at(data, loca + glyph * 2).map(|offset| offset as usize * 2),
+        };
+        let (Some(start), Some(end)) = (offset(glyph as usize), offset(glyph as usize + 1)) else { return };
+        if end <= start {
+            return;
+        }
+        let at = glyf + start;
+        let Some(contour_count) = u16_at(data, at).map(|count| count as i16) else { return };
+        let [xx, xy, yx, yy, dx, dy] = transform;
+        let apply = |x: f32, y: f32| (x * xx + y * yx + dx, x * xy + y * yy + dy);
+
+        if contour_count >= 0 {
+            if let Some(simple) = simple_outline(data, at, contour_count as usize) {
+                contours.extend(simple.into_iter().map(|contour| {
+                    contour
+                        .into_iter()
+                        .map(|point| {
+                            let (x, y) = apply(point.x, point.y);
+                            OutlinePoint { x, y, on_curve: point.on_curve }
+                        })
+                        .collect()
+                }));
+            }
+            return;
+        }
+        if depth >= MAX_COMPONENT_DEPTH {
+            return;
+        }
+
+        // Composite glyphs place other glyphs, each offset and optionally scaled
+        let mut at = at + 10;
+        loop {
+            let (Some(flags), Some(component)) = (u16_at(data, at), u16_at(data, at + 2)) else { return };
+            at += 4;
+            let (arg1, arg2) = if flags & 0x0001 != 0 {
+                let args = (u16_at(data, at).map(|arg| arg as i16 as f32), u16_at(data, at + 2).map(|arg| arg as i16 as f32));
+                at += 4;
+                args
+            } else {
+                let args = (data.get(at).map(|&arg| arg as i8 as f32), data.get(at + 1).map(|&arg| arg as i8 as f32));
+                at += 2;
+                args
+            };
+            let f2dot14 = |offset: usize| u16_at(data, offset).map_or(0.0, |value| value as i16 as f32 / 16384.0);
+            let scale = if flags & 0x0008 != 0 {
+                at += 2;
+                [f2dot14(at - 2), 0.0, 0.0, f2dot14(at - 2)]
+            } else if flags & 0x0040 != 0 {
+                at += 4;
+                [f2dot14(at - 4), 0.0, 0.0, f2dot14(at - 2)]
+            } else if flags & 0x0080 != 0 {
+                at += 8;
+                [f2dot14(at - 8), f2dot14(at - 6), f2dot14(at - 4), f2dot14(at - 2)]
+            } else {
+                [1.0, 0.0, 0.0, 1.0]
+            };
+            // Components aligned by matching points are placed without offset
+            let (ox, oy) = match (flags & 0x0002 != 0, arg1, arg2) {
+                (true, Some(x), Some(y)) => apply(x, y),
+                _ => apply(0.0, 0.0),
+            };
+            let [a, b, c, d] = scale;
+            let combined = [a * xx + b * yx, a * xy + b * yy, c * xx + d * yx, c * xy + d * yy, ox, oy];
+            self.append_outline(component, combined, depth + 1, contours);
+            if flags & 0x0020 == 0 {
+                return;
+            }
+        }
+    }
+}
+
+/// Contours of a simple glyph at `at` in the glyf table
+fn simple_outline(data: &[u8], at: usize, contour_count: usize) -> Option<Vec<Vec<OutlinePoint>>> {
+    let end_points = (0..contour_count)
+        .map(|contour| u16_at(data, at + 10 + contour * 2).map(|end| end as usize))
+        .collect::<Option<Vec<usize>>>()?;
+    let point_count = end_points.last().map_or(0, |&last| last + 1);
+    let instructions = at + 10 + contour_count * 2;
+    let mut offset = instructions + 2 + u16_at(data, instructions)? as usize;
+
+    // Flags, each optionally repeated
+    let mut flags = Vec::with_capacity(point_count);
+    while flags.len() < point_count {
+        let flag = *data.get(offset)?;
+        offset += 1;
+        let repeat = if flag & 0x08 != 0 {
+            offset += 1;
+            *data.get(offset - 1)? as usize
+        } else {
+            0
+        };
+        flags.extend(std::iter::repeat_n(flag, (repeat + 1).min(point_count - flags.len())));
+    }
+
+    // Coordinates are deltas, short ones a byte with the sign in the flags
+    let mut read_coordinates = |short: u8, same_or_positive: u8| -> Option<Vec<f32>> {
+        let mut value = 0i32;
+        let mut coordinates = Vec::with_capacity(point_count);
+        for &flag in &flags {
+            if flag & short != 0 {
+                let delta = *data.get(offset)? as i32;
+                offset += 1;
+                value += if flag & same_or_positive != 0 { delta } else { -delta };
+            } else if flag & same_or_positive == 0 {
+                value += u16_at(data, offset)? as i16 as i32;
+                offset += 2;
+            }
+            coordinates.push(value as f32);
+        }
+        Some(coordinates)
+    };
+    let xs = read_coordinates(0x02, 0x10)?;
+    let ys = read_coordinates(0x04, 0x20)?;
+
+    let mut start = 0;
+    let mut contours = Vec::with_capacity(contour_count);
+    for end in end_points {
+        let points = (start..=end.min(point_count - 1))
+            .map(|index| OutlinePoint { x: xs[index], y: ys[index], on_curve: flags[index] & 0x01 != 0 })
+            .collect();
+        contours.push(points);
+        start = end + 1;
+    }
+    Some(contours)
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+/// Offset and format of the best Unicode subtable, preferring full
+/// Unicode (format 12) over the Basic Multilingual Plane (format 4)
+fn unicode_cmap(data: &[u8], cmap: usize) -> Option<(usize, u16)> {
+    let count = u16_at(data, cmap + 2)?;
+    let mut best = None;
+    for index in 0..count as usize {
+        let record = cmap + 4 + index * 8;
+        let (platform, encoding) = (u16_at(data, record)?, u16_at(data, record + 2)?);
+        let offset = cmap + u32_at(data, record + 4)? as usize;
+        let unicode = platform == 0 || (platform == 3 && matches!(encoding, 1 | 10));
+        match u16_at(data, offset) {
+            Some(12) if unicode => return Some((offset, 12)),
+            Some(4) if unicode => best = best.or(Some((offset, 4))),
+            _ => {}
+        }
+    }
+    best
+}
+
+/// Segment mapping to delta values
+fn cmap4_lookup(data: &[u8], subtable: usize, code: u32) -> Option<u16> {
+    let code = u16::try_from(code).ok()?;
+    let segments = u16_at(data, subtable + 6)? as usize / 2;
+    let end_codes = subtable + 14;
+    let start_codes = end_codes + segments * 2 + 2;
+    let deltas = start_codes + segments * 2;
+    let range_offsets = deltas + segments * 2;
+    let segment = (0..segments).find(|&segment| u16_at(data, end_codes + segment * 2).is_some_and(|end| end >= code))?;
+    let start = u16_at(data, start_codes + segment * 2)?;
+    if start > code {
+        return None;
+    }
+    let delta = u16_at(data, deltas + segment * 2)?;
+    let range_offset = u16_at(data, range_offsets + segment * 2)? as usize;
+    if range_offset == 0 {
+        return Some(code.wrapping_add(delta));
+    }
+    // The offset is relative to where it is stored
+    let glyph = u16_at(data, range_offsets + segment * 2 + range_offset + (code - start) as usize * 2)?;
+    (glyph != 0).then(|| glyph.wrapping_add(delta))
+}
+
+/// Segmented coverage of all of Unicode
+fn cmap12_lookup(data: &[u8], subtable: usize, code: u32) -> Option<u16> {
+    let groups = u32_at(data, subtable + 12)? as usize;
+    (0..groups).find_map(|group| {
+        let record = subtable + 16 + group * 12;
+        let (start, end) = (u32_at(data, record)?, u32_at(data, record + 4)?);
+        (start..=end).contains(&code).then(|| u32_at(data, record + 8).map(|glyph| (glyph + code - start) as u16))?
+    })
+}
+
+/// Pairs of the horizontal format 0 subtables of a version 0 kern table
+fn kerning_pairs(data: &[u8], kern: usize) -> HashMap<(u16, u16), i16> {
+    let mut pairs = HashMap::new();
+    if u16_at(data, kern) != Some(0) {
+        return pairs;
+    }
+    let mut subtable = kern + 4;
+    for _ in 0..u16_at(data, kern + 2).unwrap_or(0) {
+        let (Some(length), Some(coverage)) = (u16_at(data, subtable + 2), u16_at(data, subtable + 4)) else {
+            break;
+        };
+        // Horizontal, not minimum values, not cross-stream, format 0
+        if coverage & 0xFF07 == 0x0001 {
+            for pair in 0..u16_at(data, subtable + 6).unwrap_or(0) as usize {
+                let record = subtable + 14 + pair * 6;
+                let (Some(left), Some(right), Some(value)) =
+                    (u16_at(data, record), u16_at(data, record + 2), u16_at(data, record + 4))
+                else {
+                    break;
+                };
+                pairs.insert((left, right), value as i16);
+            }
+        }
+        subtable += length as usize;
+    }
+    pairs
+}
@@ -0,0 +1,225 @@
+// Text labels for compositor-drawn UI
+//
+// Dialogs and overlays the compositor draws itself show short single-line
+// labels. A label is shaped with the font's metrics, and its glyph outlines
+// are flattened to line segments and rasterized on the CPU by accumulating
+// signed area coverage per pixel, then drawn by the UI pass as an image.
+
+use crate::font::{Font, OutlinePoint};
+use crate::shaping::{FontShaper, ShapedLine};
+use std::sync::Arc;
+use vulkan_renderer::{UiFilter, UiImage};
+
+/// Transparent border around the glyphs, so antialiased edges are not cut off
+const PADDING: f32 = 1.0;
+
+/// A label rasterized at one size and color
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub image: Arc<UiImage>,
+    /// Distance from the top of the image to the baseline, in pixels
+    pub baseline: f32,
+}
+
+impl Label {
+    /// Rasterize a line of text `font_size` pixels high in a straight-alpha color
+    ///
+    /// Returns `None` for text without any glyph to draw at that size.
+    pub fn new(text: &str, font: &Arc<Font>, font_size: f32, color: [f32; 4]) -> Option<Self> {
+        let line = ShapedLine::new(text, None, font_size, &FontShaper::new(font.clone()));
+        let scale = font_size / font.units_per_em() as f32;
+        let ascent = (font.ascender() * scale).ceil();
+        let width = (line.width.ceil() + PADDING * 2.0) as usize;
+        let height = (ascent - (font.descender() * scale).floor() + PADDING * 2.0) as usize;
+        if line.glyphs.is_empty() || width == 0 || height == 0 {
+            return None;
+        }
+
+        let baseline = PADDING + ascent;
+        let mut raster = Raster::new(width, height);
+        for glyph in &line.glyphs {
+            let origin = PADDING + glyph.x;
+            for contour in font.outline(glyph.glyph as u16) {
+                let points: Vec<OutlinePoint> = contour
+                    .into_iter()
+                    .map(|point| OutlinePoint { x: origin + point.x * scale, y: baseline - point.y * scale, ..point })
+                    .collect();
+                raster.draw_contour(&points);
+            }
+        }
+
+        let [r, g, b, a] = color.map(|channel| channel.clamp(0.0, 1.0));
+        let rgb = [r, g, b].map(|channel| (channel * 255.0).round() as u8);
+        let pixels = raster
+            .coverage()
+            .flat_map(|coverage| [rgb[0], rgb[1], rgb[2], (coverage * a * 255.0).round() as u8])
+            .collect();
+        let image = UiImage::new(width as u32, height as u32, pixels, UiFilter::Linear).ok()?;
+        Some(Self { image, baseline })
+    }
+
+    /// Size of the label's image in pixels
+    pub fn size(&self) -> (f32, f32) {
+        (self.image.width() as f32, self.image.height() as f32)
+    }
+}
+
+/// Signed area accumulation buffer, one cell per pixel plus slack for
+/// contributions right of the last column
+struct Raster {
+    width: usize,
+    height: usize,
+    cells: Vec<f32>,
+}
+
+impl Raster {
+    fn new(width: usize, height: usize) -> Self {
+        Self { width, height, cells: vec![0.0; width * height + 4] }
+    }
+
+    /// Add a closed contour of quadratic curves, resolving implied on-curve
+    /// points between consecutive control points
+    fn draw_contour(&mut self, points: &[OutlinePoint]) {
+        let Some(start) = points.iter().position(|point| point.on_curve) else {
+            return;
+        };
+        let first = (points[start].x, points[start].y);
+        let mut current = first;
+        let mut control: Option<(f32, f32)> = None;
+        for offset in 1..=points.len() {
+            let point = points[(start + offset) % points.len()];
+            let position = (point.x, point.y);
+            match (point.on_curve, control) {
+                (true, None) => {
+                    self.draw_line(current, position);
+                    current = position;
+                }
+                (true, Some(ctrl)) => {
+                    self.draw_quadratic(current, ctrl, position);
+                    current = position;
+                    control = None;
+                }
+                (false, None) => control = Some(position),
+                (false, Some(ctrl)) => {
+                    let middle = ((ctrl.0 + position.0) / 2.0, (ctrl.1 + position.1) / 2.0);
+                    self.draw_quadratic(current, ctrl, middle);
+                    current = middle;
+                    control = Some(position);
+                }
+            }
+        }
+        match control {
+            Some(ctrl) => self.draw_quadratic(current, ctrl, first),
+            None => self.draw_line(current, first),
+        }
+    }
+
+    /// Flatten a quadratic curve into segments within a tenth of a pixel
+    fn draw_quadratic(&mut self, from: (f32, f32), control: (f32, f32), to: (f32, f32)) {
+        let deviation = ((from.0 - 2.0 * control.0 + to.0).powi(2) + (from.1 - 2.0 * control.1 + to.1).powi(2)).sqrt();
+        let segments = (deviation / 0.8).sqrt().ceil().clamp(1.0, 32.0) as usize;
+        let mut previous = from;
+        for step in 1..=segments {
+            let t = step as f32 / segments as f32;
+            let u = 1.0 - t;
+            let point = (
+                u * u * from.0 + 2.0 * u * t * control.0 + t * t * to.0,
+                u * u * from.1 + 2.0 * u * t * control.1 + t * t * to.1,
+            );
+            self.draw_line(previous, point);
+            previous = point;
+        }
+    }
+
+    /// Accumulate the signed area a line covers in each cell it crosses
+    fn draw_line(&mut self, p0: (f32, f32), p1: (f32, f32)) {
+        if (p0.1 - p1.1).abs() <= f32::EPSILON {
+            return;
+        }
+        let (direction, p0, p1) = if p0.1 < p1.1 { (1.0, p0, p1) } else { (-1.0, p1, p0) };
+        let dxdy = (p1.0 - p0.0) / (p1.1 - p0.1);
+        let max_x = (self.width - 1) as f32;
+        let mut x = p0.0;
+        if p0.1 < 0.0 {
+            x -= p0.1 * dxdy;
+        }
+        for y in p0.1.max(0.0) as usize..self.height.min(p1.1.ceil() as usize) {
+            let row = y * self.width;
+            let dy = ((y + 1) as f32).min(p1.1) - (y as f32).max(p0.1);
+            let x_next = x + dxdy * dy;
+            let area = dy * direction;
+            let (x0, x1) = if x < x_next { (x, x_next) } else { (x_next, x) };
+            let (x0, x1) = (x0.clamp(0.0, max_x), x1.clamp(0.0, max_x));
+            let x0_floor = x0.floor();
+            let x0_index = x0_floor as usize;
+            let x1_ceil = x1.ceil();
+            let x1_index = x1_ceil as usize;
+            if x1_index <= x0_index + 1 {
+                // Within one cell: split by the mean x inside it
+                let middle = 0.5 * (x0 + x1) - x0_floor;
+                self.cells[row + x0_index] += area - area * middle;
+                self.cells[row + x0_index + 1] += area * middle;
+            } else {
+                let slope = (x1 - x0).recip();
+                let x0_fraction = x0 - x0_floor;
+                let first = 0.5 * slope * (1.0 - x0_fraction) * (1.0 - x0_fraction);
+                let x1_fraction = x1 - x1_ceil + 1.0;
+                let last = 0.5 * slope * x1_fraction * x1_fraction;
+                self.cells[row + x0_index] += area * first;
+                if x1_index == x0_index + 2 {
+                    self.cells[row + x0_index + 1] += area * (1.0 - first - last);
+                } else {
+                    let second = slope * (1.5 - x0_fraction);
+                    self.cells[row + x0_index + 1] += area * (second - first);
+                    for index in x0_index + 2..x1_index - 1 {
+                        self.cells[row + index] += area * slope;
+                    }
+                    let before_last = second + (x1_index - x0_index - 3) as f32 * slope;
+                    self.cells[row + x1_index - 1] += area * (1.0 - before_last - last);
+                }
+                self.cells[row + x1_index] += area * last;
+            }
+            x = x_next;
+        }
+    }
+
+    /// Coverage of every pixel, 0.0 to 1.0, row by row
+    fn coverage(&self) -> impl Iterator<Item = f32> + '_ {
+        self.cells[..self.width * self.height].iter().scan(0.0, |sum, &cell| {
+            *sum += cell;
+            Some(sum.abs().min(1.0))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn covers_the_inside_of_a_contour() {
+        let mut raster = Raster::new(6, 4);
+        // Square from (1, 1) to (4, 3), clockwise on screen like TrueType outlines
+        let corners = [(1.0, 3.0), (1.0, 1.0), (4.0, 1.0), (4.0, 3.0)];
+        let points: Vec<OutlinePoint> = corners.iter().map(|&(x, y)| OutlinePoint { x, y, on_curve: true }).collect();
+        raster.draw_contour(&points);
+        let coverage: Vec<f32> = raster.coverage().collect();
+        for (index, value) in coverage.iter().enumerate() {
+            let (x, y) = (index % 6, index / 6);
+            let inside = (1..4).contains(&x) && (1..3).contains(&y);
+            assert!((value - if inside { 1.0 } else { 0.0 }).abs() < 1e-5, "pixel ({}, {}) has coverage {}", x, y, value);
+        }
+    }
+
+    #[test]
+    fn half_covers_pixels_cut_by_an_edge() {
+        let mut raster = Raster::new(4, 2);
+        let corners = [(0.0, 2.0), (0.0, 0.0), (2.5, 0.0), (2.5, 2.0)];
+        let points: Vec<OutlinePoint> = corners.iter().map(|&(x, y)| OutlinePoint { x, y, on_curve: true }).collect();
+        raster.draw_contour(&points);
+        let coverage: Vec<f32> = raster.coverage().take(4).collect();
+        assert!((coverage[1] - 1.0).abs() < 1e-5);
+        assert!((coverage[2] - 0.5).abs() < 1e-5);
+        assert!(coverage[3].abs() < 1e-5);
+    }
+}
@@ -10,6 +10,15 @@ pub mod layout;
 pub mod styling;
 pub mod animation;
 pub mod effects;
+pub mod annotation;
+pub mod focus;
+pub mod font;
+pub mod label;
+pub mod shaping;
+pub mod calendar;
+pub mod timezone;
+pub mod region_select;
+pub mod damage;
 
 /// UI Framework main context
 pub struct UIFramework {
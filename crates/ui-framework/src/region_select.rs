@@ -0,0 +1,484 @@
+// Region selection overlay for screenshots and recordings
+//
+// Dims the screen and lets the user drag out a rectangle, move it, or resize
+// it from its edges and corners, with a readout of its size. Dragged edges
+// snap to nearby window and screen edges, and clicking without dragging
+// selects the window under the pointer. Arrow keys nudge the selection by a
+// pixel for fine adjustment. The overlay only produces primitives; the
+// renderer draws them above all surfaces.
+
+use crate::focus::{FocusAction, NavigationKey};
+use compositor_utils::math::Rect;
+use glam::Vec2;
+
+/// Distance within which a dragged edge snaps to a window or screen edge
+const SNAP_DISTANCE: f32 = 8.0;
+
+/// Pointer travel below which a press and release count as a click
+const CLICK_DISTANCE: f32 = 3.0;
+
+/// Pixels a coarse keyboard adjustment moves the selection
+const COARSE_STEP: f32 = 10.0;
+
+/// Part of the selection under the pointer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionHandle {
+    TopLeft,
+    Top,
+    TopRight,
+    Right,
+    BottomRight,
+    Bottom,
+    BottomLeft,
+    Left,
+    /// Inside the selection, dragging moves it
+    Inside,
+}
+
+impl SelectionHandle {
+    const CORNERS_AND_EDGES: [SelectionHandle; 8] = [
+        SelectionHandle::TopLeft,
+        SelectionHandle::Top,
+        SelectionHandle::TopRight,
+        SelectionHandle::Right,
+        SelectionHandle::BottomRight,
+        SelectionHandle::Bottom,
+        SelectionHandle::BottomLeft,
+        SelectionHandle::Left,
+    ];
+
+    /// Position of the handle on a rectangle, as fractions of its size
+    fn anchor(self) -> Vec2 {
+        match self {
+            SelectionHandle::TopLeft => Vec2::new(0.0, 0.0),
+            SelectionHandle::Top => Vec2::new(0.5, 0.0),
+            SelectionHandle::TopRight => Vec2::new(1.0, 0.0),
+            SelectionHandle::Right => Vec2::new(1.0, 0.5),
+            SelectionHandle::BottomRight => Vec2::new(1.0, 1.0),
+            SelectionHandle::Bottom => Vec2::new(0.5, 1.0),
+            SelectionHandle::BottomLeft => Vec2::new(0.0, 1.0),
+            SelectionHandle::Left => Vec2::new(0.0, 0.5),
+            SelectionHandle::Inside => Vec2::new(0.5, 0.5),
+        }
+    }
+
+    fn moves_left(self) -> bool {
+        matches!(self, SelectionHandle::TopLeft | SelectionHandle::Left | SelectionHandle::BottomLeft)
+    }
+
+    fn moves_right(self) -> bool {
+        matches!(self, SelectionHandle::TopRight | SelectionHandle::Right | SelectionHandle::BottomRight)
+    }
+
+    fn moves_top(self) -> bool {
+        matches!(self, SelectionHandle::TopLeft | SelectionHandle::Top | SelectionHandle::TopRight)
+    }
+
+    fn moves_bottom(self) -> bool {
+        matches!(self, SelectionHandle::BottomLeft | SelectionHandle::Bottom | SelectionHandle::BottomRight)
+    }
+}
+
+/// Colors and sizes of the overlay
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionSelectStyle {
+    /// Color laid over everything outside the selection
+    pub dim_color: [f32; 4], // RGBA
+    pub border_color: [f32; 4],
+    pub border_width: f32,
+    /// Side length of the square resize handles
+    pub handle_size: f32,
+    pub handle_color: [f32; 4],
+    pub readout_color: [f32; 4],
+    /// Gap between the selection and its size readout
+    pub readout_margin: f32,
+    /// Height reserved for the readout label
+    pub readout_height: f32,
+}
+
+impl Default for RegionSelectStyle {
+    fn default() -> Self {
+        Self {
+            dim_color: [0.0, 0.0, 0.0, 0.5],
+            border_color: [1.0, 1.0, 1.0, 0.9],
+            border_width: 1.0,
+            handle_size: 8.0,
+            handle_color: [1.0, 1.0, 1.0, 1.0],
+            readout_color: [1.0, 1.0, 1.0, 1.0],
+            readout_margin: 6.0,
+            readout_height: 16.0,
+        }
+    }
+}
+
+/// Shape for the renderer to draw
+#[derive(Debug, Clone, PartialEq)]
+pub enum OverlayPrimitive {
+    Fill { rect: Rect, color: [f32; 4] },
+    Outline { rect: Rect, width: f32, color: [f32; 4] },
+    /// Text with its top left corner at `position`
+    Label { text: String, position: Vec2, color: [f32; 4] },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    handle: Option<SelectionHandle>,
+    start: Vec2,
+    /// Selection when the drag started
+    initial: Option<Rect>,
+    moved: bool,
+}
+
+/// Region selection overlay state
+#[derive(Debug, Clone)]
+pub struct RegionSelector {
+    active: bool,
+    /// Area that can be selected, the output or the whole layout
+    screen: Rect,
+    /// Window rectangles to snap to, topmost first
+    windows: Vec<Rect>,
+    selection: Option<Rect>,
+    drag: Option<Drag>,
+    pointer: Vec2,
+    snapping: bool,
+}
+
+impl RegionSelector {
+    /// Create an inactive selector covering `screen`
+    pub fn new(screen: Rect) -> Self {
+        Self {
+            active: false,
+            screen,
+            windows: Vec::new(),
+            selection: None,
+            drag: None,
+            pointer: Vec2::ZERO,
+            snapping: true,
+        }
+    }
+
+    /// Whether the overlay is shown and takes input
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Show the overlay with no selection; `windows` are the rectangles of
+    /// visible windows, topmost first
+    pub fn start(&mut self, screen: Rect, windows: Vec<Rect>) {
+        self.active = true;
+        self.screen = screen;
+        self.windows = windows;
+        self.selection = None;
+        self.drag = None;
+    }
+
+    /// Hide the overlay, discarding the selection
+    pub fn cancel(&mut self) {
+        self.active = false;
+        self.selection = None;
+        self.drag = None;
+    }
+
+    /// Enable or disable snapping to window and screen edges, e.g. while a
+    /// modifier is held
+    pub fn set_snapping(&mut self, snapping: bool) {
+        self.snapping = snapping;
+    }
+
+    /// Current selection, in whole pixels
+    pub fn selection(&self) -> Option<Rect> {
+        self.selection.map(round)
+    }
+
+    /// Window that a click at the pointer would select
+    pub fn hovered_window(&self) -> Option<Rect> {
+        if !self.active || self.selection.is_some() && self.drag.is_none() {
+            return None;
+        }
+        self.window_at(self.pointer)
+    }
+
+    /// Part of the selection at `position`, for the cursor shape
+    pub fn handle_at(&self, position: Vec2, handle_size: f32) -> Option<SelectionHandle> {
+        let selection = self.selection?;
+        SelectionHandle::CORNERS_AND_EDGES
+            .into_iter()
+            .find(|handle| {
+                let anchor = handle_position(selection, *handle);
+                (position - anchor).abs().max_element() <= handle_size
+            })
+            .or_else(|| selection.contains(position).then_some(SelectionHandle::Inside))
+    }
+
+    /// Start dragging: a handle or the inside of the selection adjusts it,
+    /// anywhere else starts a new selection
+    pub fn pointer_pressed(&mut self, position: Vec2, handle_size: f32) {
+        if !self.active {
+            return;
+        }
+        self.pointer = position;
+        self.drag = Some(Drag {
+            handle: self.handle_at(position, handle_size),
+            start: position,
+            initial: self.selection,
+            moved: false,
+        });
+    }
+
+    pub fn pointer_moved(&mut self, position: Vec2) {
+        if !self.active {
+            return;
+        }
+        self.pointer = position;
+        let Some(drag) = &mut self.drag else { return };
+        drag.moved |= (position - drag.start).length() > CLICK_DISTANCE;
+        if !drag.moved {
+            return;
+        }
+        let drag = *drag;
+        let selection = match (drag.handle, drag.initial) {
+            (Some(SelectionHandle::Inside), Some(initial)) => self.moved(initial, position - drag.start),
+            (Some(handle), Some(initial)) => self.resized(initial, handle, position),
+            _ => {
+                let corner = self.clamp_point(self.snap_point(position));
+                let start = self.clamp_point(drag.start);
+                Rect::new(
+                    start.x.min(corner.x),
+                    start.y.min(corner.y),
+                    (start.x - corner.x).abs(),
+                    (start.y - corner.y).abs(),
+                )
+            }
+        };
+        self.selection = Some(selection);
+    }
+
+    /// End dragging; a click without a drag selects the window under the
+    /// pointer
+    pub fn pointer_released(&mut self, position: Vec2) {
+        self.pointer_moved(position);
+        let Some(drag) = self.drag.take() else { return };
+        if !drag.moved && drag.handle.is_none() {
+            self.selection = self.window_at(position).map(|window| self.clamp_rect(window));
+        }
+        if self.selection.is_some_and(|selection| selection.width < 1.0 || selection.height < 1.0) {
+            self.selection = None;
+        }
+    }
+
+    /// Adjust the selection with the keyboard
+    ///
+    /// Arrows move the selection by a pixel, or by ten with `coarse`; with
+    /// `resize` they move its right or bottom edge instead. Activate confirms
+    /// the selection and Cancel dismisses the overlay.
+    pub fn navigate(&mut self, key: NavigationKey, resize: bool, coarse: bool) -> FocusAction<Rect> {
+        if !self.active {
+            return FocusAction::Ignored;
+        }
+        let Some(selection) = self.selection else {
+            return match key {
+                NavigationKey::Cancel => {
+                    self.cancel();
+                    FocusAction::Dismiss
+                }
+                _ => FocusAction::Ignored,
+            };
+        };
+        let step = if coarse { COARSE_STEP } else { 1.0 };
+        let delta = match key {
+            NavigationKey::Left => Vec2::new(-step, 0.0),
+            NavigationKey::Right => Vec2::new(step, 0.0),
+            NavigationKey::Up => Vec2::new(0.0, -step),
+            NavigationKey::Down => Vec2::new(0.0, step),
+            NavigationKey::Activate => {
+                let selection = round(selection);
+                self.cancel();
+                return FocusAction::Activate(selection);
+            }
+            NavigationKey::Cancel => {
+                self.cancel();
+                return FocusAction::Dismiss;
+            }
+            _ => return FocusAction::Ignored,
+        };
+        let adjusted = if resize {
+            let mut adjusted = selection;
+            adjusted.width = (selection.width + delta.x).clamp(1.0, self.screen.x + self.screen.width - selection.x);
+            adjusted.height = (selection.height + delta.y).clamp(1.0, self.screen.y + self.screen.height - selection.y);
+            adjusted
+        } else {
+            self.clamp_rect(Rect::new(selection.x + delta.x, selection.y + delta.y, selection.width, selection.height))
+        };
+        if adjusted == selection {
+            return FocusAction::Ignored;
+        }
+        self.selection = Some(adjusted);
+        FocusAction::Moved(round(adjusted))
+    }
+
+    /// Size readout of the selection and where to show it: below the
+    /// selection, or above it at the bottom of the screen
+    pub fn readout(&self, style: &RegionSelectStyle) -> Option<(String, Vec2)> {
+        let selection = self.selection.or_else(|| self.hovered_window())?;
+        let selection = round(selection);
+        let text = format!("{} × {}", selection.width as u32, selection.height as u32);
+        let below = selection.y + selection.height + style.readout_margin;
+        let y = if below + style.readout_height <= self.screen.y + self.screen.height {
+            below
+        } else {
+            (selection.y - style.readout_margin - style.readout_height).max(self.screen.y)
+        };
+        Some((text, Vec2::new(selection.x, y)))
+    }
+
+    /// Shapes to draw for the current state, back to front
+    pub fn primitives(&self, style: &RegionSelectStyle) -> Vec<OverlayPrimitive> {
+        if !self.active {
+            return Vec::new();
+        }
+        let Some(highlight) = self.selection.or_else(|| self.hovered_window()) else {
+            return vec![OverlayPrimitive::Fill { rect: self.screen, color: style.dim_color }];
+        };
+        let highlight = round(highlight);
+
+        let mut primitives: Vec<OverlayPrimitive> = dim_around(self.screen, highlight)
+            .into_iter()
+            .map(|rect| OverlayPrimitive::Fill { rect, color: style.dim_color })
+            .collect();
+        primitives.push(OverlayPrimitive::Outline {
+            rect: highlight,
+            width: style.border_width,
+            color: style.border_color,
+        });
+        // Handles only on a selection, not on a window a click would select
+        if self.selection.is_some() {
+            let size = style.handle_size;
+            primitives.extend(SelectionHandle::CORNERS_AND_EDGES.into_iter().map(|handle| {
+                let center = handle_position(highlight, handle);
+                OverlayPrimitive::Fill {
+                    rect: Rect::new(center.x - size * 0.5, center.y - size * 0.5, size, size),
+                    color: style.handle_color,
+                }
+            }));
+        }
+        if let Some((text, position)) = self.readout(style) {
+            primitives.push(OverlayPrimitive::Label { text, position, color: style.readout_color });
+        }
+        primitives
+    }
+
+    fn window_at(&self, position: Vec2) -> Option<Rect> {
+        self.windows.iter().copied().find(|window| window.contains(position))
+    }
+
+    fn moved(&self, initial: Rect, delta: Vec2) -> Rect {
+        let mut moved = Rect::new(initial.x + delta.x, initial.y + delta.y, initial.width, initial.height);
+        if self.snapping {
+            let origin = Vec2::new(moved.x, moved.y);
+            let far = Vec2::new(moved.x + moved.width, moved.y + moved.height);
+            let snapped_origin = self.snap_point(origin);
+            let snapped_far = self.snap_point(far);
+            // Snap whichever edge is closer on each axis
+            moved.x += nearest(snapped_origin.x - origin.x, snapped_far.x - far.x);
+            moved.y += nearest(snapped_origin.y - origin.y, snapped_far.y - far.y);
+        }
+        self.clamp_rect(moved)
+    }
+
+    fn resized(&self, initial: Rect, handle: SelectionHandle, position: Vec2) -> Rect {
+        let position = self.clamp_point(self.snap_point(position));
+        let (mut left, mut top) = (initial.x, initial.y);
+        let (mut right, mut bottom) = (initial.x + initial.width, initial.y + initial.height);
+        if handle.moves_left() {
+            left = position.x;
+        } else if handle.moves_right() {
+            right = position.x;
+        }
+        if handle.moves_top() {
+            top = position.y;
+        } else if handle.moves_bottom() {
+            bottom = position.y;
+        }
+        // Dragging an edge past the opposite one flips the selection
+        Rect::new(left.min(right), top.min(bottom), (right - left).abs(), (bottom - top).abs())
+    }
+
+    /// Move a point onto window and screen edges within the snap distance
+    fn snap_point(&self, point: Vec2) -> Vec2 {
+        if !self.snapping {
+            return point;
+        }
+        let edges = self.windows.iter().chain(std::iter::once(&self.screen));
+        let snap = |value: f32, candidates: &mut dyn Iterator<Item = f32>| {
+            candidates
+                .filter(|edge| (edge - value).abs() <= SNAP_DISTANCE)
+                .min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()))
+                .unwrap_or(value)
+        };
+        Vec2::new(
+            snap(point.x, &mut edges.clone().flat_map(|rect| [rect.x, rect.x + rect.width])),
+            snap(point.y, &mut edges.flat_map(|rect| [rect.y, rect.y + rect.height])),
+        )
+    }
+
+    fn clamp_point(&self, point: Vec2) -> Vec2 {
+        Vec2::new(
+            point.x.clamp(self.screen.x, self.screen.x + self.screen.width),
+            point.y.clamp(self.screen.y, self.screen.y + self.screen.height),
+        )
+    }
+
+    /// Keep a rectangle on screen, moving it rather than shrinking it where
+    /// it fits
+    fn clamp_rect(&self, rect: Rect) -> Rect {
+        let width = rect.width.min(self.screen.width);
+        let height = rect.height.min(self.screen.height);
+        Rect::new(
+            rect.x.clamp(self.screen.x, self.screen.x + self.screen.width - width),
+            rect.y.clamp(self.screen.y, self.screen.y + self.screen.height - height),
+            width,
+            height,
+        )
+    }
+}
+
+impl Default for RegionSelector {
+    fn default() -> Self {
+        Self::new(Rect::from_size(0.0, 0.0))
+    }
+}
+
+/// Position of a handle on a rectangle
+fn handle_position(rect: Rect, handle: SelectionHandle) -> Vec2 {
+    Vec2::new(rect.x, rect.y) + Vec2::new(rect.width, rect.height) * handle.anchor()
+}
+
+/// The smaller of two snap offsets, ignoring edges that did not snap
+fn nearest(a: f32, b: f32) -> f32 {
+    match (a != 0.0, b != 0.0) {
+        (true, true) if b.abs() < a.abs() => b,
+        (true, _) => a,
+        _ => b,
+    }
+}
+
+fn round(rect: Rect) -> Rect {
+    Rect::new(rect.x.round(), rect.y.round(), rect.width.round(), rect.height.round())
+}
+
+/// Strips of `screen` outside `hole`: above, below, left and right of it
+fn dim_around(screen: Rect, hole: Rect) -> Vec<Rect> {
+    let screen_right = screen.x + screen.width;
+    let screen_bottom = screen.y + screen.height;
+    let hole_right = hole.x + hole.width;
+    let hole_bottom = hole.y + hole.height;
+    [
+        Rect::new(screen.x, screen.y, screen.width, hole.y - screen.y),
+        Rect::new(screen.x, hole_bottom, screen.width, screen_bottom - hole_bottom),
+        Rect::new(screen.x, hole.y, hole.x - screen.x, hole.height),
+        Rect::new(hole_right, hole.y, screen_right - hole_right, hole.height),
+    ]
+    .into_iter()
+    .filter(|rect| rect.width > 0.0 && rect.height > 0.0)
+    .collect()
+}
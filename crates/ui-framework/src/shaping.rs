@@ -0,0 +1,659 @@
+// Text shaping for compositor UI
+//
+// Window titles and launcher entries can mix scripts and directions. A line is
+// first split into runs of one script and one bidi embedding level (a
+// single-paragraph subset of the Unicode Bidirectional Algorithm, UAX #9),
+// then each run is shaped and the runs are laid out in visual order, so
+// Arabic and Hebrew read right-to-left while embedded numbers and Latin text
+// keep their own order. With a font loaded, runs take the font's glyphs,
+// advances and kerning, and Arabic letters their joining forms; without one,
+// advances are estimated.
+
+use crate::font::Font;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Writing system of a run, used to pick fonts and shaping rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Script {
+    /// Spaces, punctuation and digits shared by all scripts
+    Common,
+    Latin,
+    Greek,
+    Cyrillic,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Bengali,
+    Tamil,
+    Thai,
+    Hangul,
+    Hiragana,
+    Katakana,
+    Han,
+    Other,
+}
+
+impl Script {
+    /// Script of a character
+    pub fn of(c: char) -> Self {
+        match c as u32 {
+            0x0041..=0x005A | 0x0061..=0x007A | 0x00C0..=0x024F | 0x1E00..=0x1EFF => Self::Latin,
+            0x0000..=0x00BF => Self::Common,
+            0x0300..=0x036F => Self::Common,
+            0x0370..=0x03FF => Self::Greek,
+            0x0400..=0x052F => Self::Cyrillic,
+            0x0590..=0x05FF | 0xFB1D..=0xFB4F => Self::Hebrew,
+            0x0600..=0x06FF | 0x0750..=0x077F | 0x08A0..=0x08FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => {
+                Self::Arabic
+            }
+            0x0900..=0x097F => Self::Devanagari,
+            0x0980..=0x09FF => Self::Bengali,
+            0x0B80..=0x0BFF => Self::Tamil,
+            0x0E00..=0x0E7F => Self::Thai,
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Self::Hangul,
+            0x3040..=0x309F => Self::Hiragana,
+            0x30A0..=0x30FF | 0x31F0..=0x31FF => Self::Katakana,
+            0x2E80..=0x2FDF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x3134F => {
+                Self::Han
+            }
+            0x2000..=0x2BFF | 0x3000..=0x303F | 0xFE00..=0xFE6F | 0xFF00..=0xFFEF | 0x1F000..=0x1FAFF => {
+                Self::Common
+            }
+            _ => Self::Other,
+        }
+    }
+
+    /// Whether characters are typically drawn full-width (one em)
+    pub fn is_wide(self) -> bool {
+        matches!(self, Self::Han | Self::Hiragana | Self::Katakana | Self::Hangul)
+    }
+}
+
+/// Base direction of a paragraph or run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    LeftToRight,
+    RightToLeft,
+}
+
+impl Direction {
+    fn of_level(level: u8) -> Self {
+        if level.is_multiple_of(2) { Self::LeftToRight } else { Self::RightToLeft }
+    }
+}
+
+/// Bidi character type, reduced to the classes UI strings need
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BidiClass {
+    /// Strong left-to-right
+    Left,
+    /// Strong right-to-left (Hebrew)
+    Right,
+    /// Strong right-to-left (Arabic letter)
+    ArabicLetter,
+    /// European number
+    EuropeanNumber,
+    /// Arabic number
+    ArabicNumber,
+    /// Non-spacing mark, takes the class of its base
+    NonSpacingMark,
+    /// Whitespace
+    Whitespace,
+    /// Other neutral
+    OtherNeutral,
+}
+
+/// Whether a character is a combining mark drawn on its base character
+pub fn is_combining_mark(c: char) -> bool {
+    let code = c as u32;
+    match code {
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F => true,
+        0x0591..=0x05BD | 0x05BF | 0x05C1..=0x05C2 | 0x05C4..=0x05C5 | 0x05C7 => true,
+        0x0610..=0x061A | 0x064B..=0x065F | 0x0670 | 0x06D6..=0x06DC | 0x06DF..=0x06E4 => true,
+        0x06E7..=0x06E8 | 0x06EA..=0x06ED | 0x08D3..=0x08FF => true,
+        0x0E31 | 0x0E34..=0x0E3A | 0x0E47..=0x0E4E => true,
+        // Indic blocks share a layout: signs at the start, vowel signs and virama mid-block
+        0x0900..=0x0DFF => matches!(code & 0x7F, 0x00..=0x03 | 0x3A..=0x4F | 0x51..=0x57 | 0x62..=0x63),
+        _ => false,
+    }
+}
+
+fn bidi_class(c: char) -> BidiClass {
+    if is_combining_mark(c) {
+        return BidiClass::NonSpacingMark;
+    }
+    match c as u32 {
+        0x0030..=0x0039 | 0x06F0..=0x06F9 => BidiClass::EuropeanNumber,
+        0x0660..=0x0669 => BidiClass::ArabicNumber,
+        0x0590..=0x05FF | 0x07C0..=0x085F | 0xFB1D..=0xFB4F => BidiClass::Right,
+        0x0600..=0x07BF | 0x0860..=0x08FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => BidiClass::ArabicLetter,
+        _ if c.is_whitespace() => BidiClass::Whitespace,
+        _ if c.is_alphabetic() => BidiClass::Left,
+        _ if c.is_ascii() || matches!(c as u32, 0x2000..=0x2BFF | 0x3000..=0x303F) => BidiClass::OtherNeutral,
+        // Remaining symbols and letters outside the ranges above read left-to-right
+        _ => BidiClass::Left,
+    }
+}
+
+/// A run of text with one script and one embedding level
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextRun {
+    /// Byte range in the source string
+    pub range: Range<usize>,
+    pub script: Script,
+    /// Bidi embedding level; odd levels are right-to-left
+    pub level: u8,
+}
+
+impl TextRun {
+    /// Direction glyphs in this run advance in
+    pub fn direction(&self) -> Direction {
+        Direction::of_level(self.level)
+    }
+}
+
+/// A line of text split into runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paragraph {
+    pub direction: Direction,
+    /// Runs in logical (memory) order
+    pub runs: Vec<TextRun>,
+}
+
+impl Paragraph {
+    /// Split a line into script and direction runs
+    ///
+    /// The base direction is taken from the first strong character unless
+    /// given, so a title starting in Hebrew is right-aligned and right-to-left.
+    pub fn new(text: &str, direction: Option<Direction>) -> Self {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut classes: Vec<BidiClass> = chars.iter().map(|&(_, c)| bidi_class(c)).collect();
+
+        let direction = direction.unwrap_or_else(|| {
+            let first_strong = classes
+                .iter()
+                .find(|class| matches!(class, BidiClass::Left | BidiClass::Right | BidiClass::ArabicLetter));
+            match first_strong {
+                Some(BidiClass::Right | BidiClass::ArabicLetter) => Direction::RightToLeft,
+                _ => Direction::LeftToRight,
+            }
+        });
+        let base_level: u8 = match direction {
+            Direction::LeftToRight => 0,
+            Direction::RightToLeft => 1,
+        };
+        let base_class = match direction {
+            Direction::LeftToRight => BidiClass::Left,
+            Direction::RightToLeft => BidiClass::Right,
+        };
+
+        resolve_weak_types(&mut classes, base_class);
+        resolve_neutral_types(&mut classes, base_class);
+
+        let mut levels: Vec<u8> = classes
+            .iter()
+            .map(|class| match (base_level.is_multiple_of(2), class) {
+                (true, BidiClass::Right) => base_level + 1,
+                (true, BidiClass::ArabicNumber | BidiClass::EuropeanNumber) => base_level + 2,
+                (false, BidiClass::Left | BidiClass::EuropeanNumber | BidiClass::ArabicNumber) => base_level + 1,
+                _ => base_level,
+            })
+            .collect();
+
+        // Trailing whitespace takes the paragraph level (rule L1)
+        for (index, &(_, c)) in chars.iter().enumerate().rev() {
+            if !c.is_whitespace() {
+                break;
+            }
+            levels[index] = base_level;
+        }
+
+        Self {
+            direction,
+            runs: split_runs(text, &chars, &levels),
+        }
+    }
+
+    /// Runs in visual order, left to right on screen (rule L2)
+    pub fn visual_runs(&self) -> Vec<&TextRun> {
+        let mut runs: Vec<&TextRun> = self.runs.iter().collect();
+        let highest = runs.iter().map(|run| run.level).max().unwrap_or(0);
+        let lowest_odd = runs.iter().map(|run| run.level).filter(|level| level % 2 == 1).min();
+
+        let Some(lowest_odd) = lowest_odd else {
+            return runs;
+        };
+        for level in (lowest_odd..=highest).rev() {
+            let mut start = 0;
+            while start < runs.len() {
+                if runs[start].level < level {
+                    start += 1;
+                    continue;
+                }
+                let end = runs[start..]
+                    .iter()
+                    .position(|run| run.level < level)
+                    .map_or(runs.len(), |offset| start + offset);
+                runs[start..end].reverse();
+                start = end;
+            }
+        }
+        runs
+    }
+}
+
+/// Rules W1-W7 for a single isolating run sequence
+fn resolve_weak_types(classes: &mut [BidiClass], base_class: BidiClass) {
+    let mut previous = base_class;
+    let mut last_strong = base_class;
+    for class in classes.iter_mut() {
+        if *class == BidiClass::NonSpacingMark {
+            *class = previous;
+        }
+        match *class {
+            BidiClass::Left | BidiClass::Right | BidiClass::ArabicLetter => last_strong = *class,
+            BidiClass::EuropeanNumber if last_strong == BidiClass::ArabicLetter => *class = BidiClass::ArabicNumber,
+            BidiClass::EuropeanNumber if last_strong == BidiClass::Left => *class = BidiClass::Left,
+            _ => {}
+        }
+        previous = *class;
+        if *class == BidiClass::ArabicLetter {
+            *class = BidiClass::Right;
+        }
+    }
+}
+
+/// Rules N1 and N2: neutrals between text of one direction take that direction
+fn resolve_neutral_types(classes: &mut [BidiClass], base_class: BidiClass) {
+    let strong = |class: BidiClass| match class {
+        BidiClass::Left => Some(BidiClass::Left),
+        BidiClass::Right | BidiClass::EuropeanNumber | BidiClass::ArabicNumber => Some(BidiClass::Right),
+        _ => None,
+    };
+
+    let mut index = 0;
+    while index < classes.len() {
+        if strong(classes[index]).is_some() {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        while index < classes.len() && strong(classes[index]).is_none() {
+            index += 1;
+        }
+        let before = if start == 0 { base_class } else { strong(classes[start - 1]).unwrap_or(base_class) };
+        let after = classes.get(index).copied().and_then(strong).unwrap_or(base_class);
+        let resolved = if before == after { before } else { base_class };
+        classes[start..index].fill(resolved);
+    }
+}
+
+/// Group characters into runs of equal level and script
+///
+/// Common characters join the run they are in rather than starting a new one.
+fn split_runs(text: &str, chars: &[(usize, char)], levels: &[u8]) -> Vec<TextRun> {
+    let mut runs: Vec<TextRun> = Vec::new();
+    for (index, &(offset, c)) in chars.iter().enumerate() {
+        let end = chars.get(index + 1).map_or(text.len(), |&(next, _)| next);
+        let script = Script::of(c);
+        match runs.last_mut() {
+            Some(run) if run.level == levels[index] && (script == Script::Common || script == run.script) => {
+                run.range.end = end;
+            }
+            Some(run) if run.level == levels[index] && run.script == Script::Common => {
+                run.range.end = end;
+                run.script = script;
+            }
+            _ => runs.push(TextRun { range: offset..end, script, level: levels[index] }),
+        }
+    }
+    runs
+}
+
+/// A positioned glyph
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapedGlyph {
+    /// Glyph ID in the run's font; the character itself for the fallback shaper
+    pub glyph: u32,
+    /// Byte offset of the character cluster the glyph belongs to
+    pub cluster: usize,
+    /// Horizontal pen position of the glyph from the start of the line
+    pub x: f32,
+    pub advance: f32,
+}
+
+/// Shapes a single run into glyphs
+pub trait TextShaper {
+    /// Glyphs of `text[run.range]` in the order they are drawn along the run's direction
+    fn shape(&self, text: &str, run: &TextRun, font_size: f32) -> Vec<ShapedGlyph>;
+}
+
+/// Shaper that estimates advances without font data
+///
+/// Good enough for layout before fonts are loaded: wide scripts take a full
+/// em, combining marks take no space.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FallbackShaper;
+
+impl TextShaper for FallbackShaper {
+    fn shape(&self, text: &str, run: &TextRun, font_size: f32) -> Vec<ShapedGlyph> {
+        let mut glyphs: Vec<ShapedGlyph> = text[run.range.clone()]
+            .char_indices()
+            .map(|(offset, c)| {
+                let advance = if is_combining_mark(c) {
+                    0.0
+                } else if Script::of(c).is_wide() {
+                    font_size
+                } else {
+                    font_size * 0.6
+                };
+                ShapedGlyph { glyph: c as u32, cluster: run.range.start + offset, x: 0.0, advance }
+            })
+            .collect();
+        if run.direction() == Direction::RightToLeft {
+            glyphs.reverse();
+        }
+        glyphs
+    }
+}
+
+/// Shaper that positions a font's glyphs with its advances and kerning
+///
+/// Arabic letters take their initial, medial and final forms where the font
+/// maps the Arabic presentation forms; characters the font lacks are drawn
+/// as its missing glyph. Indic reordering and ligatures are not applied.
+#[derive(Debug, Clone)]
+pub struct FontShaper {
+    font: Arc<Font>,
+}
+
+impl FontShaper {
+    pub fn new(font: Arc<Font>) -> Self {
+        Self { font }
+    }
+}
+
+impl TextShaper for FontShaper {
+    fn shape(&self, text: &str, run: &TextRun, font_size: f32) -> Vec<ShapedGlyph> {
+        let scale = font_size / self.font.units_per_em() as f32;
+        let chars: Vec<(usize, char)> = text[run.range.clone()].char_indices().collect();
+        let forms = match run.script {
+            Script::Arabic => arabic_forms(&chars),
+            _ => chars.iter().map(|&(_, c)| c).collect(),
+        };
+        let mut glyphs: Vec<ShapedGlyph> = chars
+            .iter()
+            .zip(forms)
+            .map(|(&(offset, c), form)| {
+                let glyph = self.font.glyph_index(form).or_else(|| self.font.glyph_index(c)).unwrap_or(0);
+                ShapedGlyph {
+                    glyph: glyph as u32,
+                    cluster: run.range.start + offset,
+                    x: 0.0,
+                    advance: self.font.advance(glyph) as f32 * scale,
+                }
+            })
+            .collect();
+        if run.direction() == Direction::RightToLeft {
+            glyphs.reverse();
+        }
+        // Kerning pairs are in visual order, adjusting the left glyph
+        for index in 1..glyphs.len() {
+            let kerning = self.font.kerning(glyphs[index - 1].glyph as u16, glyphs[index].glyph as u16);
+            glyphs[index - 1].advance += kerning as f32 * scale;
+        }
+        glyphs
+    }
+}
+
+/// How an Arabic character connects to its neighbours
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Joining {
+    /// Joins on both sides
+    Dual,
+    /// Joins only to the preceding letter
+    Right,
+    /// Joins on both sides without changing shape, e.g. tatweel
+    Causing,
+    /// Skipped when finding neighbours, e.g. vowel marks
+    Transparent,
+    /// Never joins, e.g. hamza and characters of other scripts
+    Never,
+}
+
+/// Arabic letters with their isolated presentation form and whether they
+/// join on both sides; final, initial and medial forms follow the isolated one
+const ARABIC_FORMS: [(char, u32, bool); 36] = [
+    ('\u{0621}', 0xFE80, false),
+    ('\u{0622}', 0xFE81, false),
+    ('\u{0623}', 0xFE83, false),
+    ('\u{0624}', 0xFE85, false),
+    ('\u{0625}', 0xFE87, false),
+    ('\u{0626}', 0xFE89, true),
+    ('\u{0627}', 0xFE8D, false),
+    ('\u{0628}', 0xFE8F, true),
+    ('\u{0629}', 0xFE93, false),
+    ('\u{062A}', 0xFE95, true),
+    ('\u{062B}', 0xFE99, true),
+    ('\u{062C}', 0xFE9D, true),
+    ('\u{062D}', 0xFEA1, true),
+    ('\u{062E}', 0xFEA5, true),
+    ('\u{062F}', 0xFEA9, false),
+    ('\u{0630}', 0xFEAB, false),
+    ('\u{0631}', 0xFEAD, false),
+    ('\u{0632}', 0xFEAF, false),
+    ('\u{0633}', 0xFEB1, true),
+    ('\u{0634}', 0xFEB5, true),
+    ('\u{0635}', 0xFEB9, true),
+    ('\u{0636}', 0xFEBD, true),
+    ('\u{0637}', 0xFEC1, true),
+    ('\u{0638}', 0xFEC5, true),
+    ('\u{0639}', 0xFEC9, true),
+    ('\u{063A}', 0xFECD, true),
+    ('\u{0641}', 0xFED1, true),
+    ('\u{0642}', 0xFED5, true),
+    ('\u{0643}', 0xFED9, true),
+    ('\u{0644}', 0xFEDD, true),
+    ('\u{0645}', 0xFEE1, true),
+    ('\u{0646}', 0xFEE5, true),
+    ('\u{0647}', 0xFEE9, true),
+    ('\u{0648}', 0xFEED, false),
+    ('\u{0649}', 0xFEEF, false),
+    ('\u{064A}', 0xFEF1, true),
+];
+
+fn arabic_joining(c: char) -> Joining {
+    match ARABIC_FORMS.iter().find(|&&(letter, ..)| letter == c) {
+        // Hamza stands alone
+        Some(('\u{0621}', ..)) => Joining::Never,
+        Some(&(_, _, true)) => Joining::Dual,
+        Some(&(_, _, false)) => Joining::Right,
+        None if c == '\u{0640}' => Joining::Causing,
+        None if is_combining_mark(c) => Joining::Transparent,
+        None => Joining::Never,
+    }
+}
+
+/// Contextual forms of the characters of an Arabic run, in logical order
+fn arabic_forms(chars: &[(usize, char)]) -> Vec<char> {
+    fn neighbour<'a>(mut joinings: impl Iterator<Item = &'a Joining>) -> Joining {
+        joinings.find(|&&joining| joining != Joining::Transparent).copied().unwrap_or(Joining::Never)
+    }
+    let joinings: Vec<Joining> = chars.iter().map(|&(_, c)| arabic_joining(c)).collect();
+    chars
+        .iter()
+        .enumerate()
+        .map(|(index, &(_, c))| {
+            let Some(&(_, isolated, _)) = ARABIC_FORMS.iter().find(|&&(letter, ..)| letter == c) else {
+                return c;
+            };
+            let previous = neighbour(joinings[..index].iter().rev());
+            let next = neighbour(joinings[index + 1..].iter());
+            let joining = joinings[index];
+            let joins_previous = joining != Joining::Never && matches!(previous, Joining::Dual | Joining::Causing);
+            let joins_next = joining == Joining::Dual && matches!(next, Joining::Dual | Joining::Right | Joining::Causing);
+            let form = match (joins_previous, joins_next) {
+                (false, false) => 0,
+                (true, false) => 1,
+                (false, true) => 2,
+                (true, true) => 3,
+            };
+            char::from_u32(isolated + form).unwrap_or(c)
+        })
+        .collect()
+}
+
+/// A shaped line of glyphs in visual order
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapedLine {
+    pub direction: Direction,
+    pub glyphs: Vec<ShapedGlyph>,
+    pub width: f32,
+}
+
+impl ShapedLine {
+    /// Itemize and shape a single line of text
+    pub fn new(text: &str, direction: Option<Direction>, font_size: f32, shaper: &dyn TextShaper) -> Self {
+        let paragraph = Paragraph::new(text, direction);
+        let mut glyphs = Vec::new();
+        let mut x = 0.0;
+        for run in paragraph.visual_runs() {
+            for mut glyph in shaper.shape(text, run, font_size) {
+                glyph.x = x;
+                x += glyph.advance;
+                glyphs.push(glyph);
+            }
+        }
+        Self { direction: paragraph.direction, glyphs, width: x }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visual_ranges(text: &str, direction: Option<Direction>) -> Vec<(&str, u8)> {
+        let paragraph = Paragraph::new(text, direction);
+        paragraph.visual_runs().into_iter().map(|run| (&text[run.range.clone()], run.level)).collect()
+    }
+
+    #[test]
+    fn keeps_left_to_right_text_in_one_run() {
+        let paragraph = Paragraph::new("Terminal - ~/src", None);
+        assert_eq!(paragraph.direction, Direction::LeftToRight);
+        assert_eq!(paragraph.runs, [TextRun { range: 0..16, script: Script::Latin, level: 0 }]);
+    }
+
+    #[test]
+    fn embeds_hebrew_in_left_to_right_text() {
+        assert_eq!(
+            visual_ranges("abc אבג def", None),
+            [("abc ", 0), ("אבג", 1), (" def", 0)],
+        );
+    }
+
+    #[test]
+    fn detects_right_to_left_paragraph_and_reverses_embedded_latin() {
+        let text = "שלום abc";
+        assert_eq!(Paragraph::new(text, None).direction, Direction::RightToLeft);
+        assert_eq!(visual_ranges(text, None), [("abc", 2), ("שלום ", 1)]);
+    }
+
+    #[test]
+    fn keeps_numbers_left_to_right_in_right_to_left_text() {
+        let text = "אבג 123";
+        let line = ShapedLine::new(text, None, 10.0, &FallbackShaper);
+        let visual: String = line.glyphs.iter().filter_map(|glyph| char::from_u32(glyph.glyph)).collect();
+        assert_eq!(visual, "123 גבא");
+        // Pen positions advance left to right in visual order
+        assert!(line.glyphs.windows(2).all(|pair| pair[0].x < pair[1].x));
+    }
+
+    #[test]
+    fn numbers_after_arabic_letters_are_arabic_numbers() {
+        let mut classes = vec![BidiClass::ArabicLetter, BidiClass::Whitespace, BidiClass::EuropeanNumber];
+        resolve_weak_types(&mut classes, BidiClass::Left);
+        assert_eq!(classes, [BidiClass::Right, BidiClass::Whitespace, BidiClass::ArabicNumber]);
+    }
+
+    #[test]
+    fn forced_direction_overrides_detection() {
+        // The space between the two scripts takes the paragraph direction
+        assert_eq!(visual_ranges("abc אבג", Some(Direction::RightToLeft)), [(" אבג", 1), ("abc", 2)]);
+    }
+
+    #[test]
+    fn trailing_whitespace_takes_the_paragraph_level() {
+        let paragraph = Paragraph::new("abc אבג ", None);
+        assert_eq!(paragraph.runs.last().map(|run| run.level), Some(0));
+    }
+
+    #[test]
+    fn joins_arabic_letters() {
+        let chars: Vec<(usize, char)> = "ببب با".char_indices().collect();
+        let forms: String = arabic_forms(&chars).into_iter().collect();
+        // Beh initial, medial and final, then beh initial and alef final
+        assert_eq!(forms, "\u{FE91}\u{FE92}\u{FE90} \u{FE91}\u{FE8E}");
+    }
+
+    /// Font with glyphs for 'A', 'V' and the four forms of beh, and 'A'
+    /// kerned against 'V'
+    fn test_font() -> Arc<Font> {
+        fn be16(out: &mut Vec<u8>, values: &[u16]) {
+            values.iter().for_each(|value| out.extend_from_slice(&value.to_be_bytes()));
+        }
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        let mut hhea = vec![0; 36];
+        hhea[34..36].copy_from_slice(&7u16.to_be_bytes());
+        let mut hmtx = Vec::new();
+        for advance in [500, 600, 700, 400, 300, 350, 250] {
+            be16(&mut hmtx, &[advance, 0]);
+        }
+        // Segments for 'A', 'V', beh isolated to medial and the final 0xFFFF
+        let (starts, ends) = ([0x41, 0x56, 0xFE8F, 0xFFFF], [0x41, 0x56, 0xFE92, 0xFFFF]);
+        let deltas = [1u16.wrapping_sub(0x41), 2u16.wrapping_sub(0x56), 3u16.wrapping_sub(0xFE8F), 1];
+        let mut cmap = Vec::new();
+        be16(&mut cmap, &[0, 1, 3, 1, 0, 12, 4, 16 + 4 * 8, 0, 8, 0, 0, 0]);
+        be16(&mut cmap, &ends);
+        be16(&mut cmap, &[0]);
+        be16(&mut cmap, &starts);
+        be16(&mut cmap, &deltas);
+        be16(&mut cmap, &[0; 4]);
+        let mut kern = Vec::new();
+        be16(&mut kern, &[0, 1, 0, 20, 0x0001, 1, 0, 0, 0, 1, 2, (-80i16) as u16]);
+
+        let tables: [(&[u8; 4], Vec<u8>); 5] = [(b"cmap", cmap), (b"head", head), (b"hhea", hhea), (b"hmtx", hmtx), (b"kern", kern)];
+        let mut font = Vec::new();
+        be16(&mut font, &[1, 0, tables.len() as u16, 0, 0, 0]);
+        let mut offset = 12 + tables.len() * 16;
+        for (tag, data) in &tables {
+            font.extend_from_slice(*tag);
+            font.extend_from_slice(&[0; 4]);
+            font.extend_from_slice(&(offset as u32).to_be_bytes());
+            font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            offset += data.len();
+        }
+        tables.iter().for_each(|(_, data)| font.extend_from_slice(data));
+        Arc::new(Font::from_bytes(font).unwrap())
+    }
+
+    #[test]
+    fn shapes_with_font_advances_and_kerning() {
+        let line = ShapedLine::new("AVZ", None, 10.0, &FontShaper::new(test_font()));
+        let glyphs: Vec<(u32, f32)> = line.glyphs.iter().map(|glyph| (glyph.glyph, glyph.x)).collect();
+        // 'Z' is missing and drawn as glyph 0
+        assert_eq!(glyphs, [(1, 0.0), (2, 5.2), (0, 12.2)]);
+        assert!((line.width - 17.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn shapes_arabic_with_joining_forms_right_to_left() {
+        let text = "ببب";
+        let line = ShapedLine::new(text, None, 10.0, &FontShaper::new(test_font()));
+        let glyphs: Vec<(u32, usize)> = line.glyphs.iter().map(|glyph| (glyph.glyph, glyph.cluster)).collect();
+        // Final, medial and initial forms from left to right
+        assert_eq!(glyphs, [(4, 4), (6, 2), (5, 0)]);
+    }
+}
@@ -1,6 +1,8 @@
 // Styling and themes for glassmorphism/neomorphism
 use glam::Vec4;
 
+pub use compositor_utils::theme_tokens::{ColorToken, InteractionState, Palette, StateColors};
+
 pub struct Style {
     pub background_color: Vec4,
     pub blur_radius: f32,
@@ -10,3 +12,56 @@ pub struct Style {
 pub struct Theme {
     pub name: String,
 }
+
+/// Appearance of the keyboard focus indicator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FocusIndicatorStyle {
+    pub color: [f32; 4],
+    /// Outline width in pixels
+    pub width: f32,
+    /// Gap between the element and the outline in pixels
+    pub offset: f32,
+}
+
+impl Default for FocusIndicatorStyle {
+    fn default() -> Self {
+        Self {
+            color: [0.0, 0.5, 1.0, 1.0],
+            width: 2.0,
+            offset: 2.0,
+        }
+    }
+}
+
+/// Appearance of popovers such as the clock's calendar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PopoverStyle {
+    pub background: [f32; 4],
+    pub text: [f32; 4],
+    /// Text of secondary lines, e.g. weekday headers and other time zones
+    pub secondary_text: [f32; 4],
+    /// Selected day and today's marker
+    pub accent: [f32; 4],
+    pub corner_radius: f32,
+    pub focus: FocusIndicatorStyle,
+}
+
+impl PopoverStyle {
+    /// Style from the theme's semantic colors
+    pub fn from_palette(palette: &Palette, corner_radius: f32, focus: FocusIndicatorStyle) -> Self {
+        Self {
+            background: palette.color(ColorToken::Surface),
+            text: palette.color(ColorToken::OnSurface),
+            secondary_text: palette.color(ColorToken::OnSurfaceVariant),
+            accent: palette.color(ColorToken::Accent),
+            corner_radius,
+            focus,
+        }
+    }
+}
+
+impl Default for PopoverStyle {
+    fn default() -> Self {
+        Self::from_palette(&Palette::default(), 12.0, FocusIndicatorStyle::default())
+    }
+}
@@ -0,0 +1,545 @@
+// Time zones for clocks
+//
+// Zones are read from the system tz database (TZif files under
+// /usr/share/zoneinfo, or $TZDIR). Offsets come from the file's transition
+// table, and times past its last transition follow the POSIX TZ rule in the
+// file's footer, as slim TZif files carry only a few years of transitions.
+
+use crate::calendar::{days_in_month, Date};
+use compositor_utils::prelude::*;
+use std::path::PathBuf;
+
+/// Offset, DST flag and abbreviation in effect at some time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalTimeType {
+    /// Seconds east of UTC
+    pub utc_offset: i32,
+    pub is_dst: bool,
+    /// e.g. "CEST"
+    pub abbreviation: String,
+}
+
+impl LocalTimeType {
+    fn utc() -> Self {
+        Self { utc_offset: 0, is_dst: false, abbreviation: "UTC".to_string() }
+    }
+}
+
+/// Wall clock time in some time zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalTime {
+    pub date: Date,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl LocalTime {
+    /// Wall clock time of a Unix timestamp at a UTC offset in seconds
+    pub fn from_unix(timestamp: i64, utc_offset: i32) -> Self {
+        let seconds = (timestamp + i64::from(utc_offset)).rem_euclid(86_400) as u32;
+        Self {
+            date: Date::from_unix(timestamp, utc_offset),
+            hour: seconds / 3600,
+            minute: seconds / 60 % 60,
+            second: seconds % 60,
+        }
+    }
+}
+
+/// A zone of the tz database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeZone {
+    name: String,
+    /// Transition times and the index of the type in effect from then on
+    transitions: Vec<(i64, usize)>,
+    types: Vec<LocalTimeType>,
+    /// Rule for times after the last transition
+    rule: Option<PosixRule>,
+}
+
+impl TimeZone {
+    /// Coordinated Universal Time
+    pub fn utc() -> Self {
+        Self {
+            name: "UTC".to_string(),
+            transitions: Vec::new(),
+            types: vec![LocalTimeType::utc()],
+            rule: None,
+        }
+    }
+
+    /// Load a zone by its tz database name, e.g. "America/New_York"
+    pub fn load(name: &str) -> Result<Self> {
+        if !is_valid_name(name) {
+            return Err(CompositorError::configuration(format!("Invalid time zone name: {:?}", name)));
+        }
+        let path = zoneinfo_dir().join(name);
+        let data = std::fs::read(&path)
+            .map_err(|e| CompositorError::configuration(format!("Unknown time zone {}: {}", name, e)))?;
+        Self::parse(name, &data)
+    }
+
+    /// Zone of the system clock, from $TZ or /etc/localtime, falling back to UTC
+    pub fn local() -> Self {
+        let from_env = std::env::var("TZ")
+            .ok()
+            .map(|tz| tz.trim_start_matches(':').to_string())
+            .filter(|tz| !tz.is_empty())
+            .and_then(|tz| Self::load(&tz).ok());
+        from_env
+            .or_else(|| {
+                let data = std::fs::read("/etc/localtime").ok()?;
+                // The link target names the zone, e.g. /usr/share/zoneinfo/Europe/Berlin
+                let name = std::fs::read_link("/etc/localtime")
+                    .ok()
+                    .and_then(|target| {
+                        let target = target.to_string_lossy().into_owned();
+                        target.split_once("zoneinfo/").map(|(_, name)| name.to_string())
+                    })
+                    .unwrap_or_else(|| "localtime".to_string());
+                Self::parse(&name, &data).ok()
+            })
+            .unwrap_or_else(Self::utc)
+    }
+
+    /// Parse TZif data
+    pub fn parse(name: &str, data: &[u8]) -> Result<Self> {
+        let invalid = || CompositorError::configuration(format!("Invalid time zone data for {}", name));
+        let header = TzifHeader::parse(data).ok_or_else(invalid)?;
+
+        // Version 2+ files repeat the data with 64-bit times after the 32-bit block
+        let (header, body, time_size) = if header.version >= b'2' {
+            let rest = data.get(HEADER_SIZE + header.block_size(4)..).ok_or_else(invalid)?;
+            (TzifHeader::parse(rest).ok_or_else(invalid)?, &rest[HEADER_SIZE..], 8)
+        } else {
+            (header, &data[HEADER_SIZE..], 4)
+        };
+        if body.len() < header.block_size(time_size) {
+            return Err(invalid());
+        }
+
+        let (times, body) = body.split_at(header.time_count * time_size);
+        let (indices, body) = body.split_at(header.time_count);
+        let (infos, body) = body.split_at(header.type_count * 6);
+        let (chars, body) = body.split_at(header.char_count);
+
+        let types = infos
+            .chunks_exact(6)
+            .map(|info| {
+                let start = usize::from(info[5]).min(chars.len());
+                let end = chars[start..].iter().position(|&c| c == 0).map_or(chars.len(), |len| start + len);
+                LocalTimeType {
+                    utc_offset: i32::from_be_bytes([info[0], info[1], info[2], info[3]]),
+                    is_dst: info[4] != 0,
+                    abbreviation: String::from_utf8_lossy(&chars[start..end]).into_owned(),
+                }
+            })
+            .collect::<Vec<_>>();
+        if types.is_empty() {
+            return Err(invalid());
+        }
+
+        let transitions = times
+            .chunks_exact(time_size)
+            .zip(indices)
+            .map(|(time, &index)| {
+                let time = match *time {
+                    [a, b, c, d] => i64::from(i32::from_be_bytes([a, b, c, d])),
+                    _ => i64::from_be_bytes(time.try_into().unwrap_or_default()),
+                };
+                (time, usize::from(index).min(types.len() - 1))
+            })
+            .collect();
+
+        // The footer follows the leap second and indicator tables: "\n<rule>\n"
+        let rule = if time_size == 8 {
+            body.get(header.leap_count * 12 + header.std_count + header.ut_count..)
+                .and_then(|footer| std::str::from_utf8(footer).ok())
+                .and_then(|footer| footer.strip_prefix('\n')?.split('\n').next())
+                .and_then(PosixRule::parse)
+        } else {
+            None
+        };
+
+        Ok(Self { name: name.to_string(), transitions, types, rule })
+    }
+
+    /// tz database name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Offset and abbreviation in effect at a Unix timestamp
+    pub fn local_time_type(&self, timestamp: i64) -> LocalTimeType {
+        let count = self.transitions.partition_point(|&(time, _)| time <= timestamp);
+        if count == self.transitions.len() {
+            if let Some(rule) = &self.rule {
+                return rule.local_time_type(timestamp);
+            }
+        }
+        match count {
+            // Before the first transition the first standard time type applies
+            0 => self.types.iter().find(|t| !t.is_dst).unwrap_or(&self.types[0]).clone(),
+            count => self.types[self.transitions[count - 1].1].clone(),
+        }
+    }
+
+    /// Seconds east of UTC at a Unix timestamp
+    pub fn utc_offset(&self, timestamp: i64) -> i32 {
+        self.local_time_type(timestamp).utc_offset
+    }
+
+    /// Wall clock time at a Unix timestamp
+    pub fn local_time(&self, timestamp: i64) -> LocalTime {
+        LocalTime::from_unix(timestamp, self.utc_offset(timestamp))
+    }
+}
+
+/// Directory of the tz database, $TZDIR or /usr/share/zoneinfo
+pub fn zoneinfo_dir() -> PathBuf {
+    std::env::var_os("TZDIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/usr/share/zoneinfo"))
+}
+
+/// Whether a zone name stays inside the tz database directory
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('/')
+        && name.split('/').all(|part| !part.is_empty() && part != "." && part != "..")
+}
+
+/// Size of a TZif header
+const HEADER_SIZE: usize = 44;
+
+/// Counts from a TZif header
+struct TzifHeader {
+    version: u8,
+    ut_count: usize,
+    std_count: usize,
+    leap_count: usize,
+    time_count: usize,
+    type_count: usize,
+    char_count: usize,
+}
+
+impl TzifHeader {
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < HEADER_SIZE || &data[..4] != b"TZif" {
+            return None;
+        }
+        let count = |index: usize| {
+            let offset = 20 + index * 4;
+            u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]) as usize
+        };
+        Some(Self {
+            version: data[4],
+            ut_count: count(0),
+            std_count: count(1),
+            leap_count: count(2),
+            time_count: count(3),
+            type_count: count(4),
+            char_count: count(5),
+        })
+    }
+
+    /// Size of the data block following the header
+    fn block_size(&self, time_size: usize) -> usize {
+        self.time_count * (time_size + 1)
+            + self.type_count * 6
+            + self.char_count
+            + self.leap_count * (time_size + 4)
+            + self.std_count
+            + self.ut_count
+    }
+}
+
+/// Day a DST transition falls on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RuleDay {
+    /// Jn: day 1 - 365, February 29th never counted
+    Julian(u32),
+    /// n: day 0 - 365, February 29th counted
+    ZeroBased(u32),
+    /// Mm.w.d: weekday d (0 = Sunday) of week w (5 = last) of month m
+    MonthWeek { month: u32, week: u32, weekday: u32 },
+}
+
+impl RuleDay {
+    fn date(self, year: i32) -> Date {
+        let january_first = Date { year, month: 1, day: 1 };
+        match self {
+            Self::Julian(day) => {
+                let leap_day = u32::from(day >= 60 && days_in_month(year, 2) == 29);
+                january_first.add_days(i64::from(day + leap_day) - 1)
+            }
+            Self::ZeroBased(day) => january_first.add_days(i64::from(day)),
+            Self::MonthWeek { month, week, weekday } => {
+                let first = Date { year, month, day: 1 };
+                // Date::weekday counts from Monday
+                let target = (weekday + 6) % 7;
+                let mut day = 1 + (target + 7 - first.weekday()) % 7 + (week - 1) * 7;
+                while day > days_in_month(year, month) {
+                    day -= 7;
+                }
+                Date { year, month, day }
+            }
+        }
+    }
+}
+
+/// Day and local time in seconds of a DST transition
+type Transition = (RuleDay, i32);
+
+/// POSIX TZ string, e.g. "CET-1CEST,M3.5.0,M10.5.0/3"
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PosixRule {
+    standard: LocalTimeType,
+    /// Daylight saving type with its start and end
+    dst: Option<(LocalTimeType, Transition, Transition)>,
+}
+
+impl PosixRule {
+    fn parse(rule: &str) -> Option<Self> {
+        let mut parser = RuleParser { rest: rule };
+        let standard_name = parser.name()?;
+        // POSIX offsets count west of UTC
+        let standard_offset = -parser.time()?;
+        let standard = LocalTimeType { utc_offset: standard_offset, is_dst: false, abbreviation: standard_name };
+        if parser.rest.is_empty() {
+            return Some(Self { standard, dst: None });
+        }
+
+        let dst_name = parser.name()?;
+        let dst_offset = if parser.rest.starts_with(',') { standard_offset + 3600 } else { -parser.time()? };
+        let start = parser.transition()?;
+        let end = parser.transition()?;
+        if !parser.rest.is_empty() {
+            return None;
+        }
+        let dst = LocalTimeType { utc_offset: dst_offset, is_dst: true, abbreviation: dst_name };
+        Some(Self { standard, dst: Some((dst, start, end)) })
+    }
+
+    fn local_time_type(&self, timestamp: i64) -> LocalTimeType {
+        let Some((dst, start, end)) = &self.dst else {
+            return self.standard.clone();
+        };
+        let year = Date::from_unix(timestamp, self.standard.utc_offset).year;
+        let transition = |(day, time): Transition, offset: i32| {
+            day.date(year).to_days() * 86_400 + i64::from(time) - i64::from(offset)
+        };
+        // DST starts in standard time and ends in daylight saving time
+        let start = transition(*start, self.standard.utc_offset);
+        let end = transition(*end, dst.utc_offset);
+        let in_dst = if start < end {
+            (start..end).contains(&timestamp)
+        } else {
+            // Southern hemisphere: DST spans the turn of the year
+            !(end..start).contains(&timestamp)
+        };
+        if in_dst { dst.clone() } else { self.standard.clone() }
+    }
+}
+
+/// Cursor over a POSIX TZ string
+struct RuleParser<'a> {
+    rest: &'a str,
+}
+
+impl RuleParser<'_> {
+    /// Zone abbreviation, alphabetic or quoted in angle brackets like "<+03>"
+    fn name(&mut self) -> Option<String> {
+        let (name, rest) = if let Some(quoted) = self.rest.strip_prefix('<') {
+            let (name, rest) = quoted.split_once('>')?;
+            (name, rest)
+        } else {
+            let len = self.rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(self.rest.len());
+            self.rest.split_at(len)
+        };
+        if name.len() < 3 {
+            return None;
+        }
+        self.rest = rest;
+        Some(name.to_string())
+    }
+
+    /// Signed [+-]hh[:mm[:ss]] in seconds
+    fn time(&mut self) -> Option<i32> {
+        let sign = match self.rest.as_bytes().first() {
+            Some(b'-') => -1,
+            Some(b'+') => 1,
+            _ => 0,
+        };
+        if sign != 0 {
+            self.rest = &self.rest[1..];
+        }
+        let mut seconds = 0;
+        for (index, unit) in [3600, 60, 1].into_iter().enumerate() {
+            if index > 0 {
+                let Some(rest) = self.rest.strip_prefix(':') else { break };
+                self.rest = rest;
+            }
+            seconds += self.number()? as i32 * unit;
+        }
+        Some(if sign < 0 { -seconds } else { seconds })
+    }
+
+    /// ",day[/time]" with the time defaulting to 02:00
+    fn transition(&mut self) -> Option<Transition> {
+        self.rest = self.rest.strip_prefix(',')?;
+        let day = if let Some(rest) = self.rest.strip_prefix('M') {
+            self.rest = rest;
+            let month = self.number()?;
+            self.rest = self.rest.strip_prefix('.')?;
+            let week = self.number()?;
+            self.rest = self.rest.strip_prefix('.')?;
+            let weekday = self.number()?;
+            ((1..=12).contains(&month) && (1..=5).contains(&week) && weekday <= 6)
+                .then_some(RuleDay::MonthWeek { month, week, weekday })?
+        } else if let Some(rest) = self.rest.strip_prefix('J') {
+            self.rest = rest;
+            let day = self.number()?;
+            (1..=365).contains(&day).then_some(RuleDay::Julian(day))?
+        } else {
+            let day = self.number()?;
+            (day <= 365).then_some(RuleDay::ZeroBased(day))?
+        };
+        let time = match self.rest.strip_prefix('/') {
+            Some(rest) => {
+                self.rest = rest;
+                self.time()?
+            }
+            None => 2 * 3600,
+        };
+        Some((day, time))
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let len = self.rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(self.rest.len());
+        let (digits, rest) = self.rest.split_at(len);
+        let number = digits.parse().ok()?;
+        self.rest = rest;
+        Some(number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Version 2 TZif data with an empty 32-bit block
+    fn tzif(transitions: &[(i64, u8)], types: &[(i32, bool, &str)], footer: &str) -> Vec<u8> {
+        let header = |times: usize, types: usize, chars: usize| {
+            let mut header = b"TZif2".to_vec();
+            header.resize(20, 0);
+            for count in [0, 0, 0, times, types, chars] {
+                header.extend((count as u32).to_be_bytes());
+            }
+            header
+        };
+        let mut chars = Vec::new();
+        let mut infos = Vec::new();
+        for &(offset, is_dst, abbreviation) in types {
+            infos.extend(offset.to_be_bytes());
+            infos.extend([u8::from(is_dst), chars.len() as u8]);
+            chars.extend(abbreviation.bytes().chain([0]));
+        }
+
+        let mut data = header(0, 0, 0);
+        data.extend(header(transitions.len(), types.len(), chars.len()));
+        data.extend(transitions.iter().flat_map(|(time, _)| time.to_be_bytes()));
+        data.extend(transitions.iter().map(|(_, index)| index));
+        data.extend(infos);
+        data.extend(chars);
+        data.extend(format!("\n{}\n", footer).bytes());
+        data
+    }
+
+    fn rule_type(rule: &str, timestamp: i64) -> LocalTimeType {
+        PosixRule::parse(rule).unwrap().local_time_type(timestamp)
+    }
+
+    #[test]
+    fn follows_transitions_then_footer_rule() {
+        // Berlin from 1980, switching to summer time on 1980-04-06 01:00 UTC
+        let data = tzif(
+            &[(315_532_800, 0), (323_830_800, 1)],
+            &[(3600, false, "CET"), (7200, true, "CEST")],
+            "CET-1CEST,M3.5.0,M10.5.0/3",
+        );
+        let zone = TimeZone::parse("Europe/Berlin", &data).unwrap();
+        assert_eq!(zone.name(), "Europe/Berlin");
+
+        // Before the first transition standard time applies
+        assert_eq!(zone.utc_offset(0), 3600);
+        assert_eq!(zone.utc_offset(320_000_000), 3600);
+        let summer = zone.local_time_type(323_830_800);
+        assert_eq!((summer.utc_offset, summer.is_dst, summer.abbreviation.as_str()), (7200, true, "CEST"));
+
+        // Past the table, 2024-01-15 and 2024-07-01 12:00 UTC
+        assert_eq!(zone.local_time_type(1_705_320_000).abbreviation, "CET");
+        assert_eq!(zone.local_time_type(1_719_835_200).abbreviation, "CEST");
+        let noon = zone.local_time(1_719_835_200);
+        assert_eq!((noon.date, noon.hour, noon.minute), (Date { year: 2024, month: 7, day: 1 }, 14, 0));
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        assert!(TimeZone::parse("Broken", b"not a tz file").is_err());
+        let mut data = tzif(&[(0, 0)], &[(0, false, "UTC")], "UTC0");
+        data.truncate(100);
+        assert!(TimeZone::parse("Broken", &data).is_err());
+    }
+
+    #[test]
+    fn switches_dst_at_rule_transitions() {
+        // Europe switches at 01:00 UTC on the last Sundays of March and
+        // October, 2024-03-31 and 2024-10-27
+        let rule = "CET-1CEST,M3.5.0,M10.5.0/3";
+        assert_eq!(rule_type(rule, 1_711_846_799).utc_offset, 3600);
+        assert_eq!(rule_type(rule, 1_711_846_800).utc_offset, 7200);
+        assert_eq!(rule_type(rule, 1_729_990_799).utc_offset, 7200);
+        assert_eq!(rule_type(rule, 1_729_990_800).utc_offset, 3600);
+
+        // The second Sunday of March 2024 is the 10th, 02:00 EST
+        let rule = "EST5EDT,M3.2.0,M11.1.0";
+        assert_eq!(rule_type(rule, 1_710_053_999).abbreviation, "EST");
+        let dst = rule_type(rule, 1_710_054_000);
+        assert_eq!((dst.utc_offset, dst.is_dst, dst.abbreviation.as_str()), (-4 * 3600, true, "EDT"));
+    }
+
+    #[test]
+    fn southern_dst_spans_turn_of_year() {
+        // Sydney leaves DST on 2024-04-07 03:00 AEDT and enters it on
+        // 2024-10-06 02:00 AEST
+        let rule = "AEST-10AEDT,M10.1.0,M4.1.0/3";
+        assert_eq!(rule_type(rule, 1_705_320_000).utc_offset, 11 * 3600);
+        assert_eq!(rule_type(rule, 1_712_419_199).utc_offset, 11 * 3600);
+        assert_eq!(rule_type(rule, 1_712_419_200).utc_offset, 10 * 3600);
+        assert_eq!(rule_type(rule, 1_728_143_999).utc_offset, 10 * 3600);
+        assert_eq!(rule_type(rule, 1_728_144_000).utc_offset, 11 * 3600);
+    }
+
+    #[test]
+    fn parses_rules_without_dst_and_quoted_names() {
+        let rule = PosixRule::parse("<+0530>-5:30").unwrap();
+        assert_eq!(rule.standard.utc_offset, 5 * 3600 + 30 * 60);
+        assert_eq!(rule.standard.abbreviation, "+0530");
+        assert!(rule.dst.is_none());
+        assert_eq!(rule_type("UTC0", 0), LocalTimeType::utc());
+
+        assert!(PosixRule::parse("CET-1CEST,M13.5.0,M10.5.0").is_none());
+        assert!(PosixRule::parse("X0").is_none());
+    }
+
+    #[test]
+    fn zone_names_stay_in_database() {
+        assert!(is_valid_name("America/New_York"));
+        assert!(!is_valid_name("../etc/passwd"));
+        assert!(!is_valid_name("/etc/localtime"));
+        assert!(!is_valid_name("Europe//Berlin"));
+    }
+}
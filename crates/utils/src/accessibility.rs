@@ -0,0 +1,291 @@
+// Accessibility tree for compositor UI
+//
+// Compositor-drawn UI (bar widgets, launcher entries) and client windows are
+// described as a tree of accessible nodes. Producers register nodes and report
+// name, state and focus changes here; the AT-SPI bridge subscribes to the
+// resulting events and forwards them to screen readers such as Orca.
+
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::broadcast;
+
+/// Identifier of an accessible node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AccessibleId(u64);
+
+impl AccessibleId {
+    /// Root of the tree, representing the compositor itself
+    pub const ROOT: Self = Self(0);
+
+    /// Numeric value, used to build object paths
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// ID from its numeric value, e.g. parsed from an object path
+    pub fn from_u64(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+/// Role of an accessible node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessibleRole {
+    Application,
+    Frame,
+    Panel,
+    ToolBar,
+    PushButton,
+    ToggleButton,
+    List,
+    ListItem,
+    Label,
+    Slider,
+}
+
+impl AccessibleRole {
+    /// AT-SPI role number (AtspiRole)
+    pub fn atspi_role(self) -> u32 {
+        match self {
+            Self::Application => 75,
+            Self::Frame => 23,
+            Self::Panel => 39,
+            Self::ToolBar => 63,
+            Self::PushButton => 43,
+            Self::ToggleButton => 62,
+            Self::List => 31,
+            Self::ListItem => 32,
+            Self::Label => 29,
+            Self::Slider => 51,
+        }
+    }
+}
+
+/// State that can change on an accessible node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccessibleState {
+    Focusable,
+    Focused,
+    Selected,
+    Pressed,
+    Showing,
+    Sensitive,
+}
+
+impl AccessibleState {
+    /// AT-SPI state name used in StateChanged events
+    pub fn atspi_name(self) -> &'static str {
+        match self {
+            Self::Focusable => "focusable",
+            Self::Focused => "focused",
+            Self::Selected => "selected",
+            Self::Pressed => "pressed",
+            Self::Showing => "showing",
+            Self::Sensitive => "sensitive",
+        }
+    }
+}
+
+/// How urgently an announcement interrupts the screen reader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Politeness {
+    /// Spoken after current speech
+    Polite,
+    /// Interrupts current speech
+    Assertive,
+}
+
+/// A node in the accessibility tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibleNode {
+    pub id: AccessibleId,
+    pub parent: Option<AccessibleId>,
+    pub role: AccessibleRole,
+    pub name: String,
+    pub description: String,
+    pub states: Vec<AccessibleState>,
+    pub children: Vec<AccessibleId>,
+}
+
+impl AccessibleNode {
+    /// Whether the node currently has a state
+    pub fn has_state(&self, state: AccessibleState) -> bool {
+        self.states.contains(&state)
+    }
+}
+
+/// Change to the accessibility tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccessibilityEvent {
+    NodeAdded { id: AccessibleId, parent: AccessibleId },
+    NodeRemoved { id: AccessibleId, parent: AccessibleId },
+    NameChanged { id: AccessibleId, name: String },
+    StateChanged { id: AccessibleId, state: AccessibleState, enabled: bool },
+    FocusChanged { id: AccessibleId },
+    Announcement { text: String, politeness: Politeness },
+}
+
+/// Thread-safe accessibility tree shared by all producers
+pub struct AccessibilityTree {
+    nodes: RwLock<HashMap<AccessibleId, AccessibleNode>>,
+    focused: RwLock<Option<AccessibleId>>,
+    next_id: AtomicU64,
+    event_sender: broadcast::Sender<AccessibilityEvent>,
+}
+
+impl AccessibilityTree {
+    /// Create a tree containing only the root node
+    pub fn new(name: impl Into<String>) -> Self {
+        let (event_sender, _) = broadcast::channel(256);
+        let root = AccessibleNode {
+            id: AccessibleId::ROOT,
+            parent: None,
+            role: AccessibleRole::Application,
+            name: name.into(),
+            description: String::new(),
+            states: vec![AccessibleState::Showing],
+            children: Vec::new(),
+        };
+        Self {
+            nodes: RwLock::new(HashMap::from([(AccessibleId::ROOT, root)])),
+            focused: RwLock::new(None),
+            next_id: AtomicU64::new(1),
+            event_sender,
+        }
+    }
+
+    /// Subscribe to tree changes
+    pub fn subscribe(&self) -> broadcast::Receiver<AccessibilityEvent> {
+        self.event_sender.subscribe()
+    }
+
+    /// Add a node under `parent`, returning its ID
+    ///
+    /// Unknown parents fall back to the root so a node is never orphaned.
+    pub fn add(&self, parent: AccessibleId, role: AccessibleRole, name: impl Into<String>) -> AccessibleId {
+        let id = AccessibleId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let mut nodes = self.nodes.write();
+        let parent = if nodes.contains_key(&parent) { parent } else { AccessibleId::ROOT };
+        nodes.insert(id, AccessibleNode {
+            id,
+            parent: Some(parent),
+            role,
+            name: name.into(),
+            description: String::new(),
+            states: vec![AccessibleState::Showing, AccessibleState::Sensitive],
+            children: Vec::new(),
+        });
+        if let Some(parent_node) = nodes.get_mut(&parent) {
+            parent_node.children.push(id);
+        }
+        drop(nodes);
+
+        self.emit(AccessibilityEvent::NodeAdded { id, parent });
+        id
+    }
+
+    /// Remove a node and its descendants
+    pub fn remove(&self, id: AccessibleId) {
+        if id == AccessibleId::ROOT {
+            return;
+        }
+        let mut nodes = self.nodes.write();
+        let Some(node) = nodes.remove(&id) else {
+            return;
+        };
+        let parent = node.parent.unwrap_or(AccessibleId::ROOT);
+        if let Some(parent_node) = nodes.get_mut(&parent) {
+            parent_node.children.retain(|&child| child != id);
+        }
+        let mut pending = node.children;
+        while let Some(child) = pending.pop() {
+            if let Some(child_node) = nodes.remove(&child) {
+                pending.extend(child_node.children);
+            }
+        }
+        drop(nodes);
+
+        {
+            let mut focused = self.focused.write();
+            if focused.is_some_and(|focused| !self.nodes.read().contains_key(&focused)) {
+                *focused = None;
+            }
+        }
+        self.emit(AccessibilityEvent::NodeRemoved { id, parent });
+    }
+
+    /// Rename a node, reporting only actual changes
+    pub fn set_name(&self, id: AccessibleId, name: &str) {
+        let changed = self.nodes.write().get_mut(&id).is_some_and(|node| {
+            if node.name == name {
+                return false;
+            }
+            node.name = name.to_string();
+            true
+        });
+        if changed {
+            self.emit(AccessibilityEvent::NameChanged { id, name: name.to_string() });
+        }
+    }
+
+    /// Set a node's description
+    pub fn set_description(&self, id: AccessibleId, description: &str) {
+        if let Some(node) = self.nodes.write().get_mut(&id) {
+            node.description = description.to_string();
+        }
+    }
+
+    /// Turn a state on or off, reporting only actual changes
+    pub fn set_state(&self, id: AccessibleId, state: AccessibleState, enabled: bool) {
+        let changed = self.nodes.write().get_mut(&id).is_some_and(|node| {
+            if node.has_state(state) == enabled {
+                return false;
+            }
+            if enabled {
+                node.states.push(state);
+            } else {
+                node.states.retain(|&existing| existing != state);
+            }
+            true
+        });
+        if changed {
+            self.emit(AccessibilityEvent::StateChanged { id, state, enabled });
+        }
+    }
+
+    /// Move accessibility focus to a node, or clear it
+    pub fn focus(&self, id: Option<AccessibleId>) {
+        let previous = std::mem::replace(&mut *self.focused.write(), id);
+        if previous == id {
+            return;
+        }
+        if let Some(previous) = previous {
+            self.set_state(previous, AccessibleState::Focused, false);
+        }
+        if let Some(id) = id {
+            self.set_state(id, AccessibleState::Focused, true);
+            self.emit(AccessibilityEvent::FocusChanged { id });
+        }
+    }
+
+    /// Node that has accessibility focus
+    pub fn focused(&self) -> Option<AccessibleId> {
+        *self.focused.read()
+    }
+
+    /// Ask screen readers to speak a message
+    pub fn announce(&self, text: impl Into<String>, politeness: Politeness) {
+        self.emit(AccessibilityEvent::Announcement { text: text.into(), politeness });
+    }
+
+    /// Snapshot of a node
+    pub fn node(&self, id: AccessibleId) -> Option<AccessibleNode> {
+        self.nodes.read().get(&id).cloned()
+    }
+
+    fn emit(&self, event: AccessibilityEvent) {
+        // No subscribers simply means no screen reader is listening
+        let _ = self.event_sender.send(event);
+    }
+}
@@ -0,0 +1,128 @@
+// Animation ticks decoupled from the display refresh
+//
+// Cosmetic animations can step at a lower rate than the display refreshes,
+// e.g. 60 times a second on a 144Hz monitor, to save CPU time. Frames drawn
+// between two steps interpolate between the last two values the animation
+// stepped to, so motion stays smooth at the cost of one step of latency.
+
+use std::time::{Duration, Instant};
+
+/// Decides when an animation steps
+#[derive(Debug, Clone)]
+pub struct AnimationTicker {
+    /// Time between steps; `None` steps on every frame
+    interval: Option<Duration>,
+    /// When the animation last stepped
+    last_tick: Option<Instant>,
+}
+
+impl AnimationTicker {
+    /// Create a ticker stepping `rate` times a second; 0 steps on every frame
+    pub fn new(rate: u32) -> Self {
+        Self {
+            interval: interval(rate),
+            last_tick: None,
+        }
+    }
+
+    /// Change the step rate
+    pub fn set_rate(&mut self, rate: u32) {
+        self.interval = interval(rate);
+    }
+
+    /// Whether the animation steps on a frame drawn at `now`
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let due = match (self.interval, self.last_tick) {
+            (Some(interval), Some(last)) => now.saturating_duration_since(last) >= interval,
+            _ => true,
+        };
+        if !due {
+            return false;
+        }
+        // Keep steps evenly spaced unless a whole interval was missed
+        self.last_tick = Some(match (self.interval, self.last_tick) {
+            (Some(interval), Some(last)) if now.saturating_duration_since(last) < interval * 2 => last + interval,
+            _ => now,
+        });
+        true
+    }
+
+    /// How far a frame drawn at `now` is from the last step towards the
+    /// next, from 0.0 to 1.0; 1.0 when stepping on every frame
+    pub fn alpha(&self, now: Instant) -> f32 {
+        match (self.interval, self.last_tick) {
+            (Some(interval), Some(last)) => {
+                (now.saturating_duration_since(last).as_secs_f32() / interval.as_secs_f32()).min(1.0)
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+impl Default for AnimationTicker {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Values that can be interpolated
+pub trait Lerp: Copy {
+    /// Value `t` of the way from `self` to `to`
+    fn lerp(self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl<const N: usize> Lerp for [f32; N] {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        std::array::from_fn(|i| self[i].lerp(to[i], t))
+    }
+}
+
+/// The last two values an animation stepped to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interpolated<T> {
+    previous: T,
+    current: T,
+}
+
+impl<T: Lerp + PartialEq> Interpolated<T> {
+    /// Start at rest at `value`
+    pub fn new(value: T) -> Self {
+        Self { previous: value, current: value }
+    }
+
+    /// Record the value of a new step
+    pub fn step(&mut self, value: T) {
+        self.previous = std::mem::replace(&mut self.current, value);
+    }
+
+    /// Jump to a value without interpolating from the previous one
+    pub fn set(&mut self, value: T) {
+        *self = Self::new(value);
+    }
+
+    /// Value to draw, `alpha` of the way from the previous step to the last
+    pub fn value(&self, alpha: f32) -> T {
+        self.previous.lerp(self.current, alpha)
+    }
+
+    /// Value of the last step
+    pub fn current(&self) -> T {
+        self.current
+    }
+
+    /// Whether the last two steps had the same value, so frames until the
+    /// next step draw nothing new
+    pub fn is_settled(&self) -> bool {
+        self.previous == self.current
+    }
+}
+
+fn interval(rate: u32) -> Option<Duration> {
+    (rate > 0).then(|| Duration::from_secs(1) / rate)
+}
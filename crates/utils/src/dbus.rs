@@ -0,0 +1,1046 @@
+// Minimal D-Bus client
+//
+// Speaks the D-Bus wire protocol on the bus's Unix socket, enough for the
+// compositor and its shell to own a name, call methods, answer calls, and
+// emit and receive signals. Only the little-endian encoding and EXTERNAL
+// authentication are supported, which is what the bus daemons on Linux use;
+// passing file descriptors is not.
+//
+// A connection hands incoming method calls and signals to a channel, and
+// matches method returns and errors to the calls waiting for them.
+
+use crate::error::{CompositorError, Result};
+use std::collections::HashMap;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
+
+/// Name, path and interface of the bus daemon itself
+pub const BUS_NAME: &str = "org.freedesktop.DBus";
+pub const BUS_PATH: &str = "/org/freedesktop/DBus";
+pub const BUS_INTERFACE: &str = "org.freedesktop.DBus";
+
+/// Standard interface for reading and watching object properties
+pub const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+/// Standard interface describing an object's interfaces as XML
+pub const INTROSPECTABLE_INTERFACE: &str = "org.freedesktop.DBus.Introspectable";
+
+/// Error returned for calls to methods an object does not have
+pub const UNKNOWN_METHOD_ERROR: &str = "org.freedesktop.DBus.Error.UnknownMethod";
+
+/// Error returned for calls that failed
+pub const FAILED_ERROR: &str = "org.freedesktop.DBus.Error.Failed";
+
+/// Header flag of calls that want no reply
+const NO_REPLY_EXPECTED: u8 = 0x1;
+
+/// Largest message accepted from the bus; the spec allows 128 MiB, but
+/// nothing the compositor talks to sends more than a few kilobytes
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Containers nest at most this deep, as in the spec
+const MAX_DEPTH: usize = 64;
+
+/// How long a method call waits for its reply
+const CALL_TIMEOUT: Duration = Duration::from_secs(25);
+
+/// Value in a message body, typed after the D-Bus type system
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Byte(u8),
+    Bool(bool),
+    Int16(i16),
+    UInt16(u16),
+    Int32(i32),
+    UInt32(u32),
+    Int64(i64),
+    UInt64(u64),
+    Double(f64),
+    String(String),
+    ObjectPath(String),
+    Signature(String),
+    Variant(Box<Value>),
+    /// Array with the signature of its elements, so empty arrays keep their type
+    Array(String, Vec<Value>),
+    Struct(Vec<Value>),
+    DictEntry(Box<Value>, Box<Value>),
+}
+
+impl Value {
+    /// Signature of the value's type, e.g. "a{sv}"
+    pub fn signature(&self) -> String {
+        match self {
+            Self::Byte(_) => "y".to_string(),
+            Self::Bool(_) => "b".to_string(),
+            Self::Int16(_) => "n".to_string(),
+            Self::UInt16(_) => "q".to_string(),
+            Self::Int32(_) => "i".to_string(),
+            Self::UInt32(_) => "u".to_string(),
+            Self::Int64(_) => "x".to_string(),
+            Self::UInt64(_) => "t".to_string(),
+            Self::Double(_) => "d".to_string(),
+            Self::String(_) => "s".to_string(),
+            Self::ObjectPath(_) => "o".to_string(),
+            Self::Signature(_) => "g".to_string(),
+            Self::Variant(_) => "v".to_string(),
+            Self::Array(element, _) => format!("a{}", element),
+            Self::Struct(fields) => format!("({})", fields.iter().map(Value::signature).collect::<String>()),
+            Self::DictEntry(key, value) => format!("{{{}{}}}", key.signature(), value.signature()),
+        }
+    }
+
+    /// Dictionary of string keys to variants, "a{sv}"
+    pub fn dict(entries: impl IntoIterator<Item = (String, Value)>) -> Self {
+        Self::Array(
+            "{sv}".to_string(),
+            entries
+                .into_iter()
+                .map(|(key, value)| Self::DictEntry(Box::new(Self::String(key)), Box::new(Self::Variant(Box::new(value)))))
+                .collect(),
+        )
+    }
+
+    /// The value inside any number of variants
+    pub fn unwrap_variant(&self) -> &Value {
+        match self {
+            Self::Variant(inner) => inner.unwrap_variant(),
+            value => value,
+        }
+    }
+
+    /// String, object path or signature
+    pub fn as_str(&self) -> Option<&str> {
+        match self.unwrap_variant() {
+            Self::String(s) | Self::ObjectPath(s) | Self::Signature(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Boolean
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.unwrap_variant() {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Integer of any width that fits an i64
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self.unwrap_variant() {
+            Self::Byte(v) => Some(v.into()),
+            Self::Int16(v) => Some(v.into()),
+            Self::UInt16(v) => Some(v.into()),
+            Self::Int32(v) => Some(v.into()),
+            Self::UInt32(v) => Some(v.into()),
+            Self::Int64(v) => Some(v),
+            Self::UInt64(v) => i64::try_from(v).ok(),
+            _ => None,
+        }
+    }
+
+    /// Double, or an integer converted to one
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.unwrap_variant() {
+            Self::Double(v) => Some(*v),
+            value => value.as_i64().map(|v| v as f64),
+        }
+    }
+
+    /// Elements of an array
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self.unwrap_variant() {
+            Self::Array(_, items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Value of a string key in a dictionary, inside its variant
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_array()?.iter().find_map(|entry| match entry {
+            Self::DictEntry(k, v) if k.as_str() == Some(key) => Some(v.unwrap_variant()),
+            _ => None,
+        })
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Self::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::String(s)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(v: i32) -> Self {
+        Self::Int32(v)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(v: u32) -> Self {
+        Self::UInt32(v)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Self::Double(v)
+    }
+}
+
+/// Kind of message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    MethodCall = 1,
+    MethodReturn = 2,
+    Error = 3,
+    Signal = 4,
+}
+
+/// Message sent or received on the bus
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub kind: MessageType,
+    pub flags: u8,
+    /// Assigned by the connection when sending
+    pub serial: u32,
+    pub path: Option<String>,
+    pub interface: Option<String>,
+    pub member: Option<String>,
+    pub error_name: Option<String>,
+    pub reply_serial: Option<u32>,
+    pub destination: Option<String>,
+    pub sender: Option<String>,
+    pub body: Vec<Value>,
+}
+
+impl Message {
+    fn new(kind: MessageType) -> Self {
+        Self {
+            kind,
+            flags: 0,
+            serial: 0,
+            path: None,
+            interface: None,
+            member: None,
+            error_name: None,
+            reply_serial: None,
+            destination: None,
+            sender: None,
+            body: Vec::new(),
+        }
+    }
+
+    /// Call of a method on an object of a peer
+    pub fn method_call(destination: &str, path: &str, interface: &str, member: &str) -> Self {
+        Self {
+            destination: Some(destination.to_string()),
+            path: Some(path.to_string()),
+            interface: Some(interface.to_string()),
+            member: Some(member.to_string()),
+            ..Self::new(MessageType::MethodCall)
+        }
+    }
+
+    /// Signal broadcast from an object
+    pub fn signal(path: &str, interface: &str, member: &str) -> Self {
+        Self {
+            path: Some(path.to_string()),
+            interface: Some(interface.to_string()),
+            member: Some(member.to_string()),
+            ..Self::new(MessageType::Signal)
+        }
+    }
+
+    /// Successful reply to a method call
+    pub fn method_return(call: &Message) -> Self {
+        Self {
+            reply_serial: Some(call.serial),
+            destination: call.sender.clone(),
+            ..Self::new(MessageType::MethodReturn)
+        }
+    }
+
+    /// Error reply to a method call
+    pub fn error(call: &Message, name: &str, text: &str) -> Self {
+        Self {
+            error_name: Some(name.to_string()),
+            reply_serial: Some(call.serial),
+            destination: call.sender.clone(),
+            body: vec![Value::from(text)],
+            ..Self::new(MessageType::Error)
+        }
+    }
+
+    /// Set the body
+    pub fn with_body(mut self, body: Vec<Value>) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Signature of the body
+    pub fn signature(&self) -> String {
+        self.body.iter().map(Value::signature).collect()
+    }
+
+    /// Whether this is the call of `member` on `interface`
+    pub fn is_call(&self, interface: &str, member: &str) -> bool {
+        self.kind == MessageType::MethodCall
+            && self.interface.as_deref().is_none_or(|i| i == interface)
+            && self.member.as_deref() == Some(member)
+    }
+
+    /// Whether this is the signal `member` of `interface`
+    pub fn is_signal(&self, interface: &str, member: &str) -> bool {
+        self.kind == MessageType::Signal
+            && self.interface.as_deref() == Some(interface)
+            && self.member.as_deref() == Some(member)
+    }
+
+    /// Encode the message with the given serial
+    pub fn encode(&self, serial: u32) -> Result<Vec<u8>> {
+        let mut body = Encoder::default();
+        for value in &self.body {
+            body.write(value)?;
+        }
+
+        let mut fields = Vec::new();
+        let mut field = |code: u8, value: Value| {
+            fields.push(Value::Struct(vec![Value::Byte(code), Value::Variant(Box::new(value))]));
+        };
+        if let Some(path) = &self.path {
+            field(1, Value::ObjectPath(path.clone()));
+        }
+        if let Some(interface) = &self.interface {
+            field(2, Value::from(interface.as_str()));
+        }
+        if let Some(member) = &self.member {
+            field(3, Value::from(member.as_str()));
+        }
+        if let Some(error_name) = &self.error_name {
+            field(4, Value::from(error_name.as_str()));
+        }
+        if let Some(reply_serial) = self.reply_serial {
+            field(5, Value::UInt32(reply_serial));
+        }
+        if let Some(destination) = &self.destination {
+            field(6, Value::from(destination.as_str()));
+        }
+        if let Some(sender) = &self.sender {
+            field(7, Value::from(sender.as_str()));
+        }
+        if !self.body.is_empty() {
+            field(8, Value::Signature(self.signature()));
+        }
+
+        let body_len = u32::try_from(body.buf.len()).map_err(|_| CompositorError::ipc("D-Bus message body too large"))?;
+        let mut message = Encoder::default();
+        message.buf.extend_from_slice(&[b'l', self.kind as u8, self.flags, 1]);
+        message.buf.extend_from_slice(&body_len.to_le_bytes());
+        message.buf.extend_from_slice(&serial.to_le_bytes());
+        message.write(&Value::Array("(yv)".to_string(), fields))?;
+        message.pad(8);
+        message.buf.extend_from_slice(&body.buf);
+        Ok(message.buf)
+    }
+
+    /// Decode a message from its header and body bytes
+    ///
+    /// `header` holds everything up to the body, including the padding after
+    /// the header fields.
+    pub fn decode(header: &[u8], body: &[u8]) -> Result<Self> {
+        if header.len() < 16 {
+            return Err(CompositorError::ipc("Truncated D-Bus message header"));
+        }
+        if header[0] != b'l' {
+            return Err(CompositorError::ipc("Big-endian D-Bus messages are not supported"));
+        }
+        let kind = match header[1] {
+            1 => MessageType::MethodCall,
+            2 => MessageType::MethodReturn,
+            3 => MessageType::Error,
+            4 => MessageType::Signal,
+            kind => return Err(CompositorError::ipc(format!("Unknown D-Bus message type {}", kind))),
+        };
+        let mut message = Self::new(kind);
+        message.flags = header[2];
+        message.serial = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+
+        let mut decoder = Decoder { buf: header, pos: 12 };
+        let fields = decoder.read(b"a(yv)", 0)?;
+        let mut signature = String::new();
+        for field in fields.as_array().unwrap_or_default() {
+            let Value::Struct(parts) = field else { continue };
+            let [Value::Byte(code), value] = parts.as_slice() else { continue };
+            let value = value.unwrap_variant();
+            match code {
+                1 => message.path = value.as_str().map(str::to_string),
+                2 => message.interface = value.as_str().map(str::to_string),
+                3 => message.member = value.as_str().map(str::to_string),
+                4 => message.error_name = value.as_str().map(str::to_string),
+                5 => message.reply_serial = value.as_i64().and_then(|v| u32::try_from(v).ok()),
+                6 => message.destination = value.as_str().map(str::to_string),
+                7 => message.sender = value.as_str().map(str::to_string),
+                8 => signature = value.as_str().unwrap_or_default().to_string(),
+                _ => {}
+            }
+        }
+
+        let mut decoder = Decoder { buf: body, pos: 0 };
+        let mut remaining = signature.as_bytes();
+        while !remaining.is_empty() {
+            let len = single_type_len(remaining)?;
+            message.body.push(decoder.read(&remaining[..len], 0)?);
+            remaining = &remaining[len..];
+        }
+        Ok(message)
+    }
+}
+
+/// Alignment of a type by the first character of its signature
+fn alignment(code: u8) -> usize {
+    match code {
+        b'y' | b'g' | b'v' => 1,
+        b'n' | b'q' => 2,
+        b'x' | b't' | b'd' | b'(' | b'{' => 8,
+        _ => 4,
+    }
+}
+
+/// Length of the first complete type in a signature
+fn single_type_len(signature: &[u8]) -> Result<usize> {
+    let invalid = || CompositorError::ipc(format!("Invalid D-Bus signature {:?}", String::from_utf8_lossy(signature)));
+    match signature.first() {
+        None => Err(invalid()),
+        Some(b'a') => Ok(1 + single_type_len(&signature[1..])?),
+        Some(&open @ (b'(' | b'{')) => {
+            let close = if open == b'(' { b')' } else { b'}' };
+            let mut len = 1;
+            loop {
+                match signature.get(len) {
+                    None => return Err(invalid()),
+                    Some(&c) if c == close => return Ok(len + 1),
+                    Some(_) => len += single_type_len(&signature[len..])?,
+                }
+            }
+        }
+        Some(b'y' | b'b' | b'n' | b'q' | b'i' | b'u' | b'x' | b't' | b'd' | b'h' | b's' | b'o' | b'g' | b'v') => Ok(1),
+        Some(_) => Err(invalid()),
+    }
+}
+
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn pad(&mut self, alignment: usize) {
+        while !self.buf.len().is_multiple_of(alignment) {
+            self.buf.push(0);
+        }
+    }
+
+    fn write_str(&mut self, s: &str) -> Result<()> {
+        self.pad(4);
+        let len = u32::try_from(s.len()).map_err(|_| CompositorError::ipc("D-Bus string too long"))?;
+        self.buf.extend_from_slice(&len.to_le_bytes());
+        self.buf.extend_from_slice(s.as_bytes());
+        self.buf.push(0);
+        Ok(())
+    }
+
+    fn write_signature(&mut self, signature: &str) -> Result<()> {
+        let len = u8::try_from(signature.len()).map_err(|_| CompositorError::ipc("D-Bus signature too long"))?;
+        self.buf.push(len);
+        self.buf.extend_from_slice(signature.as_bytes());
+        self.buf.push(0);
+        Ok(())
+    }
+
+    fn write(&mut self, value: &Value) -> Result<()> {
+        match value {
+            Value::Byte(v) => self.buf.push(*v),
+            Value::Bool(v) => {
+                self.pad(4);
+                self.buf.extend_from_slice(&u32::from(*v).to_le_bytes());
+            }
+            Value::Int16(v) => {
+                self.pad(2);
+                self.buf.extend_from_slice(&v.to_le_bytes());
+            }
+            Value::UInt16(v) => {
+                self.pad(2);
+                self.buf.extend_from_slice(&v.to_le_bytes());
+            }
+            Value::Int32(v) => {
+                self.pad(4);
+                self.buf.extend_from_slice(&v.to_le_bytes());
+            }
+            Value::UInt32(v) => {
+                self.pad(4);
+                self.buf.extend_from_slice(&v.to_le_bytes());
+            }
+            Value::Int64(v) => {
+                self.pad(8);
+                self.buf.extend_from_slice(&v.to_le_bytes());
+            }
+            Value::UInt64(v) => {
+                self.pad(8);
+                self.buf.extend_from_slice(&v.to_le_bytes());
+            }
+            Value::Double(v) => {
+                self.pad(8);
+                self.buf.extend_from_slice(&v.to_le_bytes());
+            }
+            Value::String(s) | Value::ObjectPath(s) => self.write_str(s)?,
+            Value::Signature(s) => self.write_signature(s)?,
+            Value::Variant(inner) => {
+                self.write_signature(&inner.signature())?;
+                self.write(inner)?;
+            }
+            Value::Array(element, items) => {
+                self.pad(4);
+                let len_at = self.buf.len();
+                self.buf.extend_from_slice(&[0; 4]);
+                self.pad(alignment(element.as_bytes().first().copied().unwrap_or(b'y')));
+                let start = self.buf.len();
+                for item in items {
+                    if item.signature() != *element {
+                        return Err(CompositorError::ipc(format!(
+                            "D-Bus array of {} holds a {}",
+                            element,
+                            item.signature()
+                        )));
+                    }
+                    self.write(item)?;
+                }
+                let len = u32::try_from(self.buf.len() - start).map_err(|_| CompositorError::ipc("D-Bus array too long"))?;
+                self.buf[len_at..len_at + 4].copy_from_slice(&len.to_le_bytes());
+            }
+            Value::Struct(fields) => {
+                self.pad(8);
+                for field in fields {
+                    self.write(field)?;
+                }
+            }
+            Value::DictEntry(key, value) => {
+                self.pad(8);
+                self.write(key)?;
+                self.write(value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl Decoder<'_> {
+    fn truncated() -> CompositorError {
+        CompositorError::ipc("Truncated D-Bus message")
+    }
+
+    fn align(&mut self, alignment: usize) -> Result<()> {
+        let pos = self.pos.next_multiple_of(alignment);
+        if pos > self.buf.len() {
+            return Err(Self::truncated());
+        }
+        self.pos = pos;
+        Ok(())
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        self.align(N)?;
+        let bytes = self.buf.get(self.pos..self.pos + N).ok_or_else(Self::truncated)?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap_or([0; N]))
+    }
+
+    fn take_str(&mut self, len: usize) -> Result<String> {
+        let bytes = self.buf.get(self.pos..self.pos + len).ok_or_else(Self::truncated)?;
+        // Strings are followed by a nul byte
+        self.pos += len + 1;
+        if self.pos > self.buf.len() {
+            return Err(Self::truncated());
+        }
+        String::from_utf8(bytes.to_vec()).map_err(|_| CompositorError::ipc("D-Bus string is not UTF-8"))
+    }
+
+    fn read_signature(&mut self) -> Result<String> {
+        let [len] = self.take::<1>()?;
+        self.take_str(len.into())
+    }
+
+    /// Read one value of the complete type `signature`
+    fn read(&mut self, signature: &[u8], depth: usize) -> Result<Value> {
+        if depth > MAX_DEPTH {
+            return Err(CompositorError::ipc("D-Bus value nested too deep"));
+        }
+        let Some(&code) = signature.first() else {
+            return Err(CompositorError::ipc("Empty D-Bus signature"));
+        };
+        Ok(match code {
+            b'y' => Value::Byte(self.take::<1>()?[0]),
+            b'b' => Value::Bool(u32::from_le_bytes(self.take()?) != 0),
+            b'n' => Value::Int16(i16::from_le_bytes(self.take()?)),
+            b'q' => Value::UInt16(u16::from_le_bytes(self.take()?)),
+            b'i' => Value::Int32(i32::from_le_bytes(self.take()?)),
+            // File descriptor indices are read as plain integers
+            b'u' | b'h' => Value::UInt32(u32::from_le_bytes(self.take()?)),
+            b'x' => Value::Int64(i64::from_le_bytes(self.take()?)),
+            b't' => Value::UInt64(u64::from_le_bytes(self.take()?)),
+            b'd' => Value::Double(f64::from_le_bytes(self.take()?)),
+            b's' | b'o' => {
+                let len = u32::from_le_bytes(self.take()?) as usize;
+                let s = self.take_str(len)?;
+                if code == b's' { Value::String(s) } else { Value::ObjectPath(s) }
+            }
+            b'g' => Value::Signature(self.read_signature()?),
+            b'v' => {
+                let signature = self.read_signature()?;
+                if single_type_len(signature.as_bytes())? != signature.len() {
+                    return Err(CompositorError::ipc(format!("Invalid D-Bus variant signature {:?}", signature)));
+                }
+                Value::Variant(Box::new(self.read(signature.as_bytes(), depth + 1)?))
+            }
+            b'a' => {
+                let element = &signature[1..1 + single_type_len(&signature[1..])?];
+                let len = u32::from_le_bytes(self.take()?) as usize;
+                self.align(alignment(element[0]))?;
+                let end = self.pos.checked_add(len).filter(|&end| end <= self.buf.len()).ok_or_else(Self::truncated)?;
+                let mut items = Vec::new();
+                while self.pos < end {
+                    items.push(self.read(element, depth + 1)?);
+                }
+                Value::Array(String::from_utf8_lossy(element).into_owned(), items)
+            }
+            b'(' => {
+                self.align(8)?;
+                let len = single_type_len(signature)?;
+                let mut inner = &signature[1..len - 1];
+                let mut fields = Vec::new();
+                while !inner.is_empty() {
+                    let field_len = single_type_len(inner)?;
+                    fields.push(self.read(&inner[..field_len], depth + 1)?);
+                    inner = &inner[field_len..];
+                }
+                Value::Struct(fields)
+            }
+            b'{' => {
+                self.align(8)?;
+                let key_len = single_type_len(&signature[1..])?;
+                let key = self.read(&signature[1..1 + key_len], depth + 1)?;
+                let value_len = single_type_len(&signature[1 + key_len..])?;
+                let value = self.read(&signature[1 + key_len..1 + key_len + value_len], depth + 1)?;
+                Value::DictEntry(Box::new(key), Box::new(value))
+            }
+            _ => return Err(CompositorError::ipc(format!("Unsupported D-Bus type {:?}", code as char))),
+        })
+    }
+}
+
+/// Connection to a message bus
+///
+/// Cheap to clone; all clones share the socket.
+#[derive(Clone)]
+pub struct Connection {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    writer: tokio::sync::Mutex<OwnedWriteHalf>,
+    serial: AtomicU32,
+    pending: std::sync::Mutex<HashMap<u32, oneshot::Sender<Message>>>,
+    unique_name: std::sync::OnceLock<String>,
+}
+
+impl Connection {
+    /// Connect to the session bus of the user
+    pub async fn session() -> Result<(Self, mpsc::UnboundedReceiver<Message>)> {
+        let address = std::env::var("DBUS_SESSION_BUS_ADDRESS").or_else(|_| {
+            std::env::var("XDG_RUNTIME_DIR")
+                .map(|dir| format!("unix:path={}/bus", dir))
+                .map_err(|_| CompositorError::ipc("No session bus address"))
+        })?;
+        Self::connect(&address).await
+    }
+
+    /// Connect to the system bus
+    pub async fn system() -> Result<(Self, mpsc::UnboundedReceiver<Message>)> {
+        let address = std::env::var("DBUS_SYSTEM_BUS_ADDRESS")
+            .unwrap_or_else(|_| "unix:path=/var/run/dbus/system_bus_socket".to_string());
+        Self::connect(&address).await
+    }
+
+    /// Connect to the bus at a D-Bus address, e.g. "unix:path=/run/user/1000/bus"
+    ///
+    /// Returns the connection and the method calls and signals it receives;
+    /// signals only arrive for rules added with `add_match`.
+    pub async fn connect(address: &str) -> Result<(Self, mpsc::UnboundedReceiver<Message>)> {
+        let stream = Self::open(address)?;
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+
+        // EXTERNAL authentication with our UID, hex-encoded as a decimal string
+        let uid = nix::unistd::getuid().as_raw().to_string();
+        let hex_uid: String = uid.bytes().map(|b| format!("{:02x}", b)).collect();
+        writer.write_all(format!("\0AUTH EXTERNAL {}\r\n", hex_uid).as_bytes()).await?;
+        let mut line = String::new();
+        reader.read_line(&mut line).await?;
+        if !line.starts_with("OK ") {
+            return Err(CompositorError::ipc(format!("D-Bus authentication failed: {}", line.trim())));
+        }
+        writer.write_all(b"BEGIN\r\n").await?;
+
+        let (incoming_sender, incoming) = mpsc::unbounded_channel();
+        let connection = Self {
+            inner: Arc::new(Inner {
+                writer: tokio::sync::Mutex::new(writer),
+                serial: AtomicU32::new(1),
+                pending: std::sync::Mutex::new(HashMap::new()),
+                unique_name: std::sync::OnceLock::new(),
+            }),
+        };
+        tokio::spawn(Self::read_messages(reader, Arc::downgrade(&connection.inner), incoming_sender));
+
+        let reply = connection.call(Message::method_call(BUS_NAME, BUS_PATH, BUS_INTERFACE, "Hello")).await?;
+        let name = reply.body.first().and_then(Value::as_str).unwrap_or_default().to_string();
+        debug!("Connected to D-Bus as {}", name);
+        let _ = connection.inner.unique_name.set(name);
+        Ok((connection, incoming))
+    }
+
+    /// Open the first Unix socket address of a D-Bus address list
+    fn open(address: &str) -> Result<UnixStream> {
+        for entry in address.split(';') {
+            let Some(params) = entry.strip_prefix("unix:") else { continue };
+            let params: HashMap<&str, &str> = params.split(',').filter_map(|param| param.split_once('=')).collect();
+            let addr = if let Some(path) = params.get("path") {
+                SocketAddr::from_pathname(std::ffi::OsStr::from_bytes(&unescape(path)))?
+            } else if let Some(name) = params.get("abstract") {
+                use std::os::linux::net::SocketAddrExt;
+                SocketAddr::from_abstract_name(unescape(name))?
+            } else {
+                continue;
+            };
+            let stream = std::os::unix::net::UnixStream::connect_addr(&addr)?;
+            stream.set_nonblocking(true)?;
+            return Ok(UnixStream::from_std(stream)?);
+        }
+        Err(CompositorError::ipc(format!("No supported D-Bus address in {:?}", address)))
+    }
+
+    /// Deliver received messages until the bus closes the connection
+    async fn read_messages(
+        mut reader: BufReader<OwnedReadHalf>,
+        inner: std::sync::Weak<Inner>,
+        incoming: mpsc::UnboundedSender<Message>,
+    ) {
+        loop {
+            let message = match Self::read_message(&mut reader).await {
+                Ok(message) => message,
+                Err(e) => {
+                    debug!("D-Bus connection closed: {}", e);
+                    break;
+                }
+            };
+            let Some(inner) = inner.upgrade() else { break };
+            match (message.kind, message.reply_serial) {
+                (MessageType::MethodReturn | MessageType::Error, Some(serial)) => {
+                    let waiter = inner.pending.lock().ok().and_then(|mut pending| pending.remove(&serial));
+                    if let Some(waiter) = waiter {
+                        let _ = waiter.send(message);
+                    }
+                }
+                _ => {
+                    let _ = incoming.send(message);
+                }
+            }
+        }
+        // Fail the calls still waiting
+        if let Some(inner) = inner.upgrade() {
+            if let Ok(mut pending) = inner.pending.lock() {
+                pending.clear();
+            }
+        }
+    }
+
+    async fn read_message(reader: &mut BufReader<OwnedReadHalf>) -> Result<Message> {
+        let mut fixed = [0u8; 16];
+        reader.read_exact(&mut fixed).await?;
+        let body_len = u32::from_le_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]) as usize;
+        let fields_len = u32::from_le_bytes([fixed[12], fixed[13], fixed[14], fixed[15]]) as usize;
+        let header_len = (16 + fields_len).next_multiple_of(8);
+        if header_len + body_len > MAX_MESSAGE_SIZE {
+            return Err(CompositorError::ipc("D-Bus message too large"));
+        }
+        let mut header = vec![0u8; header_len];
+        header[..16].copy_from_slice(&fixed);
+        reader.read_exact(&mut header[16..]).await?;
+        let mut body = vec![0u8; body_len];
+        reader.read_exact(&mut body).await?;
+        Message::decode(&header, &body)
+    }
+
+    /// Unique name the bus assigned to this connection, e.g. ":1.42"
+    pub fn unique_name(&self) -> &str {
+        self.inner.unique_name.get().map_or("", String::as_str)
+    }
+
+    /// Send a message without waiting for a reply; returns its serial
+    pub async fn send(&self, message: &Message) -> Result<u32> {
+        let serial = self.inner.serial.fetch_add(1, Ordering::Relaxed);
+        let bytes = message.encode(serial)?;
+        self.inner.writer.lock().await.write_all(&bytes).await?;
+        Ok(serial)
+    }
+
+    /// Call a method and wait for its reply
+    ///
+    /// Error replies are returned as errors carrying the error name and text.
+    pub async fn call(&self, message: Message) -> Result<Message> {
+        let serial = self.inner.serial.fetch_add(1, Ordering::Relaxed);
+        let bytes = message.encode(serial)?;
+        let (sender, reply) = oneshot::channel();
+        if let Ok(mut pending) = self.inner.pending.lock() {
+            pending.insert(serial, sender);
+        }
+        self.inner.writer.lock().await.write_all(&bytes).await?;
+
+        let member = message.member.as_deref().unwrap_or_default();
+        let reply = match tokio::time::timeout(CALL_TIMEOUT, reply).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(_)) => return Err(CompositorError::ipc(format!("D-Bus connection closed during {}", member))),
+            Err(_) => {
+                if let Ok(mut pending) = self.inner.pending.lock() {
+                    pending.remove(&serial);
+                }
+                return Err(CompositorError::ipc(format!("D-Bus call {} timed out", member)));
+            }
+        };
+        if reply.kind == MessageType::Error {
+            let text = reply.body.first().and_then(Value::as_str).unwrap_or_default();
+            return Err(CompositorError::ipc(format!(
+                "{} failed: {} {}",
+                member,
+                reply.error_name.as_deref().unwrap_or_default(),
+                text
+            )));
+        }
+        Ok(reply)
+    }
+
+    /// Call a method and return the body of its reply
+    pub async fn call_method(
+        &self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        body: Vec<Value>,
+    ) -> Result<Vec<Value>> {
+        let message = Message::method_call(destination, path, interface, member).with_body(body);
+        Ok(self.call(message).await?.body)
+    }
+
+    /// Answer a method call, unless the caller asked for no reply
+    pub async fn reply(&self, call: &Message, body: Vec<Value>) -> Result<()> {
+        if call.flags & NO_REPLY_EXPECTED == 0 {
+            self.send(&Message::method_return(call).with_body(body)).await?;
+        }
+        Ok(())
+    }
+
+    /// Answer a method call with an error, unless the caller asked for no reply
+    pub async fn reply_error(&self, call: &Message, name: &str, text: &str) -> Result<()> {
+        if call.flags & NO_REPLY_EXPECTED == 0 {
+            self.send(&Message::error(call, name, text)).await?;
+        }
+        Ok(())
+    }
+
+    /// Emit a signal from an object
+    pub async fn emit_signal(&self, path: &str, interface: &str, member: &str, body: Vec<Value>) -> Result<()> {
+        self.send(&Message::signal(path, interface, member).with_body(body)).await?;
+        Ok(())
+    }
+
+    /// Own a well-known name; fails if another connection owns it
+    pub async fn request_name(&self, name: &str) -> Result<()> {
+        // DBUS_NAME_FLAG_DO_NOT_QUEUE
+        let reply = self
+            .call_method(BUS_NAME, BUS_PATH, BUS_INTERFACE, "RequestName", vec![Value::from(name), Value::UInt32(4)])
+            .await?;
+        // Primary owner, or already the owner
+        match reply.first().and_then(Value::as_i64) {
+            Some(1) | Some(4) => Ok(()),
+            _ => Err(CompositorError::ipc(format!("D-Bus name {} is owned by another process", name))),
+        }
+    }
+
+    /// Receive the signals matching a rule, e.g.
+    /// "type='signal',interface='org.freedesktop.DBus.Properties'"
+    pub async fn add_match(&self, rule: &str) -> Result<()> {
+        self.call_method(BUS_NAME, BUS_PATH, BUS_INTERFACE, "AddMatch", vec![Value::from(rule)]).await?;
+        Ok(())
+    }
+
+    /// Names currently owned on the bus
+    pub async fn list_names(&self) -> Result<Vec<String>> {
+        let reply = self.call_method(BUS_NAME, BUS_PATH, BUS_INTERFACE, "ListNames", Vec::new()).await?;
+        Ok(reply
+            .first()
+            .and_then(Value::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|name| name.as_str().map(str::to_string))
+            .collect())
+    }
+
+    /// Read a property of an object
+    pub async fn get_property(&self, destination: &str, path: &str, interface: &str, name: &str) -> Result<Value> {
+        let mut reply = self
+            .call_method(destination, path, PROPERTIES_INTERFACE, "Get", vec![Value::from(interface), Value::from(name)])
+            .await?;
+        match reply.pop() {
+            Some(Value::Variant(value)) => Ok(*value),
+            _ => Err(CompositorError::ipc(format!("No value for D-Bus property {}", name))),
+        }
+    }
+
+    /// Read all properties of an interface of an object, as "a{sv}"
+    pub async fn get_all_properties(&self, destination: &str, path: &str, interface: &str) -> Result<Value> {
+        let mut reply = self
+            .call_method(destination, path, PROPERTIES_INTERFACE, "GetAll", vec![Value::from(interface)])
+            .await?;
+        reply.pop().ok_or_else(|| CompositorError::ipc(format!("No properties for {}", interface)))
+    }
+
+    /// Change a property of an object
+    pub async fn set_property(&self, destination: &str, path: &str, interface: &str, name: &str, value: Value) -> Result<()> {
+        self.call_method(
+            destination,
+            path,
+            PROPERTIES_INTERFACE,
+            "Set",
+            vec![Value::from(interface), Value::from(name), Value::Variant(Box::new(value))],
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Connection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Connection").field("unique_name", &self.unique_name()).finish()
+    }
+}
+
+/// Undo the %XX escaping of D-Bus address values
+fn unescape(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(message: &Message) -> Message {
+        let bytes = message.encode(7).unwrap();
+        let fields_len = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+        let header_len = (16 + fields_len).next_multiple_of(8);
+        Message::decode(&bytes[..header_len], &bytes[header_len..]).unwrap()
+    }
+
+    #[test]
+    fn method_call_round_trips() {
+        let message = Message::method_call("org.example.Service", "/org/example", "org.example.Iface", "Method").with_body(vec![
+            Value::from("text"),
+            Value::Byte(3),
+            Value::Int64(-5),
+            Value::Struct(vec![Value::Bool(true), Value::Double(0.5)]),
+            Value::dict([("volume".to_string(), Value::UInt32(40))]),
+            Value::Array("s".to_string(), Vec::new()),
+        ]);
+        let decoded = round_trip(&message);
+        assert_eq!(decoded.serial, 7);
+        assert_eq!(decoded.signature(), "syx(bd)a{sv}as");
+        assert_eq!(Message { serial: 0, ..decoded }, message);
+    }
+
+    #[test]
+    fn dictionary_lookup_unwraps_variants() {
+        let dict = Value::dict([
+            ("Count".to_string(), Value::Int64(3)),
+            ("Name".to_string(), Value::from("player")),
+        ]);
+        assert_eq!(dict.get("Count").and_then(Value::as_i64), Some(3));
+        assert_eq!(dict.get("Name").and_then(Value::as_str), Some("player"));
+        assert_eq!(dict.get("Missing"), None);
+    }
+
+    #[test]
+    fn truncated_body_is_rejected() {
+        let message = Message::signal("/a", "org.example.Iface", "Changed").with_body(vec![Value::from("long enough")]);
+        let bytes = message.encode(1).unwrap();
+        let fields_len = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+        let header_len = (16 + fields_len).next_multiple_of(8);
+        assert!(Message::decode(&bytes[..header_len], &bytes[header_len..bytes.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn signatures_split_into_complete_types() {
+        assert_eq!(single_type_len(b"a{sv}u").unwrap(), 5);
+        assert_eq!(single_type_len(b"(ia(ss))").unwrap(), 8);
+        assert!(single_type_len(b"(ii").is_err());
+        assert!(single_type_len(b"z").is_err());
+    }
+
+    #[test]
+    fn address_values_are_unescaped() {
+        assert_eq!(unescape("/run/user/1000/bus"), b"/run/user/1000/bus");
+        assert_eq!(unescape("/tmp/a%20b"), b"/tmp/a b");
+    }
+}
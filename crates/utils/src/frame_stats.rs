@@ -0,0 +1,156 @@
+// Per-surface frame statistics
+//
+// An opt-in debugging aid for application developers: the compositor records
+// when each surface commits and when the frame containing that commit reaches
+// the screen, and reports delivered frame rate, missed deadlines and average
+// commit-to-present latency over IPC.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Time span the frame rate and average latency are computed over
+pub const FRAME_STATS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Frame statistics of one surface
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurfaceFrameStats {
+    /// Protocol ID of the surface
+    pub surface_id: u32,
+    pub app_id: String,
+    /// Frames presented per second over the last window
+    pub fps: f32,
+    /// Frames presented since tracking started
+    pub presented_frames: u64,
+    /// Frames presented more than one refresh interval after their commit
+    pub missed_deadlines: u64,
+    /// Average time from commit to present over the last window, in milliseconds
+    pub average_latency_ms: f32,
+}
+
+#[derive(Debug, Default)]
+struct SurfaceTimeline {
+    app_id: String,
+    /// Latest commit not yet on screen
+    pending_commit: Option<Instant>,
+    /// Present time and latency of frames within the window
+    recent: VecDeque<(Instant, Duration)>,
+    presented_frames: u64,
+    missed_deadlines: u64,
+}
+
+/// Thread-safe frame statistics, fed by the commit and present paths
+pub struct FrameStatistics {
+    enabled: AtomicBool,
+    surfaces: Mutex<HashMap<u32, SurfaceTimeline>>,
+}
+
+impl FrameStatistics {
+    /// Create a disabled recorder
+    pub fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            surfaces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start or stop recording; stopping discards collected statistics
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.surfaces.lock().clear();
+        }
+    }
+
+    /// Whether statistics are being recorded
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Record a surface commit
+    pub fn surface_committed(&self, surface_id: u32, app_id: Option<&str>, now: Instant) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut surfaces = self.surfaces.lock();
+        let timeline = surfaces.entry(surface_id).or_default();
+        if let Some(app_id) = app_id {
+            if timeline.app_id != app_id {
+                timeline.app_id = app_id.to_string();
+            }
+        }
+        timeline.pending_commit = Some(now);
+    }
+
+    /// Record a presented frame containing every commit made before it
+    ///
+    /// A frame counts as missing its deadline when it reached the screen more
+    /// than one refresh interval after the commit.
+    pub fn frame_presented(&self, presented: Instant, refresh_interval: Duration) {
+        if !self.is_enabled() {
+            return;
+        }
+        for timeline in self.surfaces.lock().values_mut() {
+            let Some(committed) = timeline.pending_commit.take_if(|committed| *committed <= presented) else {
+                continue;
+            };
+            let latency = presented.duration_since(committed);
+            timeline.presented_frames += 1;
+            if latency > refresh_interval {
+                timeline.missed_deadlines += 1;
+            }
+            timeline.recent.push_back((presented, latency));
+            while timeline
+                .recent
+                .front()
+                .is_some_and(|&(time, _)| presented.duration_since(time) > FRAME_STATS_WINDOW)
+            {
+                timeline.recent.pop_front();
+            }
+        }
+    }
+
+    /// Forget a destroyed surface
+    pub fn surface_destroyed(&self, surface_id: u32) {
+        self.surfaces.lock().remove(&surface_id);
+    }
+
+    /// Statistics of all tracked surfaces, ordered by surface ID
+    pub fn snapshot(&self, now: Instant) -> Vec<SurfaceFrameStats> {
+        let surfaces = self.surfaces.lock();
+        let mut stats: Vec<SurfaceFrameStats> = surfaces
+            .iter()
+            .map(|(&surface_id, timeline)| {
+                let recent: Vec<Duration> = timeline
+                    .recent
+                    .iter()
+                    .filter(|&&(time, _)| now.saturating_duration_since(time) <= FRAME_STATS_WINDOW)
+                    .map(|&(_, latency)| latency)
+                    .collect();
+                let average_latency_ms = if recent.is_empty() {
+                    0.0
+                } else {
+                    recent.iter().sum::<Duration>().as_secs_f32() * 1000.0 / recent.len() as f32
+                };
+                SurfaceFrameStats {
+                    surface_id,
+                    app_id: timeline.app_id.clone(),
+                    fps: recent.len() as f32 / FRAME_STATS_WINDOW.as_secs_f32(),
+                    presented_frames: timeline.presented_frames,
+                    missed_deadlines: timeline.missed_deadlines,
+                    average_latency_ms,
+                }
+            })
+            .collect();
+        stats.sort_by_key(|stats| stats.surface_id);
+        stats
+    }
+}
+
+impl Default for FrameStatistics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
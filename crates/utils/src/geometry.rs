@@ -0,0 +1,240 @@
+// Geometry in typed coordinate spaces
+//
+// Window management works in logical coordinates, the renderer in physical
+// pixels and client buffers in buffer coordinates. Points, sizes and
+// rectangles carry their space in their type, so values from different spaces
+// cannot be mixed by accident and converting between them always goes
+// through the output scale. They convert to and from glam vectors for the
+// renderer and UI code, and compositor-core converts them to and from
+// smithay's geometry types.
+
+use crate::math;
+use glam::Vec2;
+use std::marker::PhantomData;
+use std::ops::{Add, Sub};
+
+/// Compositor space shared by all outputs, independent of their scale
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Logical;
+
+/// Pixels of an output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Physical;
+
+/// Pixels of a client buffer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Buffer;
+
+/// A point in coordinate space `S`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Point<S> {
+    pub x: f32,
+    pub y: f32,
+    _space: PhantomData<S>,
+}
+
+impl<S> Point<S> {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y, _space: PhantomData }
+    }
+
+    fn scaled<T>(self, scale: f32) -> Point<T> {
+        Point::new(self.x * scale, self.y * scale)
+    }
+}
+
+impl Point<Logical> {
+    /// Position on an output with the given scale
+    pub fn to_physical(self, scale: f32) -> Point<Physical> {
+        self.scaled(scale)
+    }
+
+    /// Position in a buffer with the given buffer scale
+    pub fn to_buffer(self, scale: f32) -> Point<Buffer> {
+        self.scaled(scale)
+    }
+}
+
+impl Point<Physical> {
+    /// Logical position of a pixel on an output with the given scale
+    pub fn to_logical(self, scale: f32) -> Point<Logical> {
+        self.scaled(scale.recip())
+    }
+}
+
+impl Point<Buffer> {
+    /// Logical position of a pixel in a buffer with the given buffer scale
+    pub fn to_logical(self, scale: f32) -> Point<Logical> {
+        self.scaled(scale.recip())
+    }
+}
+
+impl<S> Add for Point<S> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl<S> Sub for Point<S> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl<S> From<Vec2> for Point<S> {
+    fn from(vec: Vec2) -> Self {
+        Self::new(vec.x, vec.y)
+    }
+}
+
+impl<S> From<Point<S>> for Vec2 {
+    fn from(point: Point<S>) -> Self {
+        Vec2::new(point.x, point.y)
+    }
+}
+
+/// A size in coordinate space `S`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Size<S> {
+    pub w: f32,
+    pub h: f32,
+    _space: PhantomData<S>,
+}
+
+impl<S> Size<S> {
+    pub fn new(w: f32, h: f32) -> Self {
+        Self { w, h, _space: PhantomData }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.w <= 0.0 || self.h <= 0.0
+    }
+
+    fn scaled<T>(self, scale: f32) -> Size<T> {
+        Size::new(self.w * scale, self.h * scale)
+    }
+}
+
+impl Size<Logical> {
+    /// Size on an output with the given scale
+    pub fn to_physical(self, scale: f32) -> Size<Physical> {
+        self.scaled(scale)
+    }
+
+    /// Size in a buffer with the given buffer scale
+    pub fn to_buffer(self, scale: f32) -> Size<Buffer> {
+        self.scaled(scale)
+    }
+}
+
+impl Size<Physical> {
+    /// Logical size of pixels on an output with the given scale
+    pub fn to_logical(self, scale: f32) -> Size<Logical> {
+        self.scaled(scale.recip())
+    }
+}
+
+impl Size<Buffer> {
+    /// Logical size of pixels in a buffer with the given buffer scale
+    pub fn to_logical(self, scale: f32) -> Size<Logical> {
+        self.scaled(scale.recip())
+    }
+}
+
+impl<S> From<Vec2> for Size<S> {
+    fn from(vec: Vec2) -> Self {
+        Self::new(vec.x, vec.y)
+    }
+}
+
+impl<S> From<Size<S>> for Vec2 {
+    fn from(size: Size<S>) -> Self {
+        Vec2::new(size.w, size.h)
+    }
+}
+
+/// A rectangle in coordinate space `S`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rect<S> {
+    pub loc: Point<S>,
+    pub size: Size<S>,
+}
+
+impl<S> Rect<S> {
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self::from_loc_and_size(Point::new(x, y), Size::new(w, h))
+    }
+
+    pub fn from_loc_and_size(loc: Point<S>, size: Size<S>) -> Self {
+        Self { loc, size }
+    }
+
+    /// Bottom right corner
+    pub fn max(&self) -> Point<S> {
+        Point::new(self.loc.x + self.size.w, self.loc.y + self.size.h)
+    }
+
+    pub fn center(&self) -> Point<S> {
+        Point::new(self.loc.x + self.size.w * 0.5, self.loc.y + self.size.h * 0.5)
+    }
+
+    /// Whether a point lies inside, the bottom and right edges excluded
+    pub fn contains(&self, point: Point<S>) -> bool {
+        let max = self.max();
+        point.x >= self.loc.x && point.x < max.x && point.y >= self.loc.y && point.y < max.y
+    }
+
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// Area covered by both rectangles, if any
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let (max, other_max) = (self.max(), other.max());
+        let loc = Point::new(self.loc.x.max(other.loc.x), self.loc.y.max(other.loc.y));
+        let size = Size::new(max.x.min(other_max.x) - loc.x, max.y.min(other_max.y) - loc.y);
+        (!size.is_empty()).then_some(Self { loc, size })
+    }
+}
+
+impl Rect<Logical> {
+    /// Area on an output with the given scale
+    pub fn to_physical(self, scale: f32) -> Rect<Physical> {
+        Rect::from_loc_and_size(self.loc.to_physical(scale), self.size.to_physical(scale))
+    }
+
+    /// Area in a buffer with the given buffer scale
+    pub fn to_buffer(self, scale: f32) -> Rect<Buffer> {
+        Rect::from_loc_and_size(self.loc.to_buffer(scale), self.size.to_buffer(scale))
+    }
+}
+
+impl Rect<Physical> {
+    /// Logical area of pixels on an output with the given scale
+    pub fn to_logical(self, scale: f32) -> Rect<Logical> {
+        Rect::from_loc_and_size(self.loc.to_logical(scale), self.size.to_logical(scale))
+    }
+}
+
+impl Rect<Buffer> {
+    /// Logical area of pixels in a buffer with the given buffer scale
+    pub fn to_logical(self, scale: f32) -> Rect<Logical> {
+        Rect::from_loc_and_size(self.loc.to_logical(scale), self.size.to_logical(scale))
+    }
+}
+
+impl<S> From<math::Rect> for Rect<S> {
+    fn from(rect: math::Rect) -> Self {
+        Self::new(rect.x, rect.y, rect.width, rect.height)
+    }
+}
+
+impl<S> From<Rect<S>> for math::Rect {
+    fn from(rect: Rect<S>) -> Self {
+        math::Rect::new(rect.loc.x, rect.loc.y, rect.size.w, rect.size.h)
+    }
+}
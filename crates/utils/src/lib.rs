@@ -6,8 +6,16 @@
 pub mod error;
 pub mod logging;
 pub mod math;
+pub mod geometry;
 pub mod memory;
 pub mod async_utils;
+pub mod params;
+pub mod accessibility;
+pub mod frame_stats;
+pub mod png;
+pub mod animation_tick;
+pub mod theme_tokens;
+pub mod dbus;
 
 // Re-export commonly used types
 pub use error::{CompositorError, Result};
@@ -0,0 +1,133 @@
+// Live-tunable parameter registry
+//
+// Parameters registered here can be adjusted while the compositor is running
+// (for example over IPC) and are picked up by their consumers on the next
+// frame, without editing or reloading configuration files.
+
+use crate::error::{CompositorError, Result};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tokio::sync::broadcast;
+
+/// Current value and allowed range of a tunable parameter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ParameterSpec {
+    pub value: f32,
+    pub default: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+/// Notification sent to subscribers whenever a parameter value changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterChange {
+    pub name: String,
+    pub value: f32,
+}
+
+/// Thread-safe registry of named, range-checked `f32` parameters
+pub struct ParameterRegistry {
+    parameters: RwLock<BTreeMap<String, ParameterSpec>>,
+    change_sender: broadcast::Sender<ParameterChange>,
+}
+
+impl ParameterRegistry {
+    /// Create an empty parameter registry
+    pub fn new() -> Self {
+        let (change_sender, _) = broadcast::channel(64);
+        Self {
+            parameters: RwLock::new(BTreeMap::new()),
+            change_sender,
+        }
+    }
+
+    /// Register a parameter with its default value and allowed range
+    ///
+    /// Registering a name that already exists keeps the current value (clamped
+    /// to the new range) so that re-registration after a plugin reload does not
+    /// discard values tuned at runtime.
+    pub fn register(&self, name: impl Into<String>, default: f32, min: f32, max: f32) {
+        let name = name.into();
+        let mut parameters = self.parameters.write();
+        let value = parameters
+            .get(&name)
+            .map(|spec| spec.value)
+            .unwrap_or(default)
+            .clamp(min, max);
+
+        parameters.insert(name, ParameterSpec { value, default, min, max });
+    }
+
+    /// Get the current value of a parameter
+    pub fn get(&self, name: &str) -> Option<f32> {
+        self.parameters.read().get(name).map(|spec| spec.value)
+    }
+
+    /// Get the current value of a parameter, falling back to `fallback` if unregistered
+    pub fn get_or(&self, name: &str, fallback: f32) -> f32 {
+        self.get(name).unwrap_or(fallback)
+    }
+
+    /// Get the full specification of a parameter
+    pub fn spec(&self, name: &str) -> Option<ParameterSpec> {
+        self.parameters.read().get(name).copied()
+    }
+
+    /// Set a parameter value, returning the value actually applied
+    ///
+    /// Values outside the registered range are clamped rather than rejected so
+    /// that coarse adjustments from tooling still land on a usable value.
+    pub fn set(&self, name: &str, value: f32) -> Result<f32> {
+        if !value.is_finite() {
+            return Err(CompositorError::configuration(format!(
+                "Parameter '{}' must be a finite number", name
+            )));
+        }
+
+        let applied = {
+            let mut parameters = self.parameters.write();
+            let spec = parameters.get_mut(name).ok_or_else(|| {
+                CompositorError::configuration(format!("Unknown parameter: {}", name))
+            })?;
+            spec.value = value.clamp(spec.min, spec.max);
+            spec.value
+        };
+
+        let _ = self.change_sender.send(ParameterChange {
+            name: name.to_string(),
+            value: applied,
+        });
+
+        Ok(applied)
+    }
+
+    /// Reset a parameter to its registered default
+    pub fn reset(&self, name: &str) -> Result<f32> {
+        let default = self
+            .spec(name)
+            .map(|spec| spec.default)
+            .ok_or_else(|| CompositorError::configuration(format!("Unknown parameter: {}", name)))?;
+        self.set(name, default)
+    }
+
+    /// Snapshot of all registered parameters, ordered by name
+    pub fn list(&self) -> Vec<(String, ParameterSpec)> {
+        self.parameters
+            .read()
+            .iter()
+            .map(|(name, spec)| (name.clone(), *spec))
+            .collect()
+    }
+
+    /// Subscribe to parameter changes
+    pub fn subscribe(&self) -> broadcast::Receiver<ParameterChange> {
+        self.change_sender.subscribe()
+    }
+}
+
+impl Default for ParameterRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,412 @@
+// PNG encoding and decoding
+//
+// Images the compositor writes itself, such as screenshots and exported
+// annotations, are encoded without an image library: uncompressed deflate
+// blocks keep the encoder small at the cost of file size. Images it reads,
+// such as wallpapers, are decoded with a small inflater; interlaced images
+// are not supported.
+
+use crate::error::{CompositorError, Result};
+
+/// Encode an RGBA8 image as a PNG using uncompressed (stored) deflate blocks
+pub fn encode_rgba(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    // Each scanline is prefixed with filter type 0 (none)
+    let row_len = width as usize * 4;
+    let mut raw = Vec::with_capacity((row_len + 1) * height as usize);
+    for row in rgba.chunks(row_len.max(1)).take(height as usize) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    // zlib stream of stored blocks (max 65535 bytes each)
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[0x01, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(last as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // 8-bit depth, color type 6 (RGBA), default compression/filter, no interlace
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_png_chunk(&mut png, b"IHDR", &ihdr);
+    write_png_chunk(&mut png, b"IDAT", &zlib);
+    write_png_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Decoded RGBA8 image with straight alpha
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    /// Pixels row by row
+    pub rgba: Vec<u8>,
+}
+
+/// Decode a non-interlaced PNG of any color type and bit depth to RGBA8
+pub fn decode_rgba(png: &[u8]) -> Result<DecodedImage> {
+    let invalid = |what: &str| CompositorError::graphics(format!("Invalid PNG: {}", what));
+    let body = png.strip_prefix(b"\x89PNG\r\n\x1a\n".as_slice()).ok_or_else(|| invalid("missing signature"))?;
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut zlib = Vec::new();
+    let mut rest = body;
+    while rest.len() >= 12 {
+        let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let kind = &rest[4..8];
+        let data = rest.get(8..8 + len).ok_or_else(|| invalid("truncated chunk"))?;
+        match kind {
+            b"IHDR" if data.len() == 13 => header = Some(data),
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = data,
+            b"IDAT" => zlib.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+        rest = rest.get(12 + len..).ok_or_else(|| invalid("truncated chunk"))?;
+    }
+    let header = header.ok_or_else(|| invalid("missing header"))?;
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+    let (depth, color_type, interlace) = (header[8] as usize, header[9], header[12]);
+    let channels = match color_type {
+        0 | 3 => 1,
+        4 => 2,
+        2 => 3,
+        6 => 4,
+        _ => return Err(invalid("unknown color type")),
+    };
+    if width == 0 || height == 0 || ![1, 2, 4, 8, 16].contains(&depth) || interlace != 0 {
+        return Err(invalid("unsupported header"));
+    }
+
+    // Undo the scanline filters, each relative to the pixel to the left and
+    // the row above
+    let raw = inflate(zlib.get(2..).ok_or_else(|| invalid("missing image data"))?)?;
+    let bits_per_pixel = channels * depth;
+    let stride = (width as usize * bits_per_pixel).div_ceil(8);
+    let left = bits_per_pixel.div_ceil(8);
+    if raw.len() < (stride + 1) * height as usize {
+        return Err(invalid("truncated image data"));
+    }
+    let mut rows = vec![0u8; stride * height as usize];
+    for y in 0..height as usize {
+        let filter = raw[y * (stride + 1)];
+        let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        let (done, current) = rows.split_at_mut(y * stride);
+        let above = done.get(done.len().saturating_sub(stride)..).filter(|_| y > 0);
+        let current = &mut current[..stride];
+        for x in 0..stride {
+            let a = if x >= left { current[x - left] } else { 0 };
+            let b = above.map_or(0, |above| above[x]);
+            let c = if x >= left { above.map_or(0, |above| above[x - left]) } else { 0 };
+            let predicted = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(invalid("unknown filter")),
+            };
+            current[x] = line[x].wrapping_add(predicted);
+        }
+    }
+
+    // Expand every pixel to 8-bit RGBA
+    let max = (1u32 << depth.min(8)) - 1;
+    let sample = |row: &[u8], index: usize| -> u8 {
+        match depth {
+            16 => row[index * 2],
+            8 => row[index],
+            _ => {
+                let bit = index * depth;
+                let value = (row[bit / 8] >> (8 - depth - bit % 8)) as u32 & max;
+                if color_type == 3 { value as u8 } else { (value * 255 / max) as u8 }
+            }
+        }
+    };
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for row in rows.chunks(stride) {
+        for x in 0..width as usize {
+            let pixel = |channel| sample(row, x * channels + channel);
+            let color = match color_type {
+                0 => [pixel(0), pixel(0), pixel(0), 255],
+                2 => [pixel(0), pixel(1), pixel(2), 255],
+                3 => {
+                    let index = pixel(0) as usize;
+                    let entry = palette.get(index * 3..index * 3 + 3).ok_or_else(|| invalid("palette index out of range"))?;
+                    [entry[0], entry[1], entry[2], transparency.get(index).copied().unwrap_or(255)]
+                }
+                4 => [pixel(0), pixel(0), pixel(0), pixel(1)],
+                _ => [pixel(0), pixel(1), pixel(2), pixel(3)],
+            };
+            rgba.extend_from_slice(&color);
+        }
+    }
+    Ok(DecodedImage { width, height, rgba })
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// Order code length code lengths are stored in
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Reads a deflate stream least significant bit first
+struct BitReader<'a> {
+    data: &'a [u8],
+    bit: usize,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = *self.data.get(self.bit / 8).ok_or_else(|| CompositorError::graphics("Truncated deflate stream"))?;
+            value |= ((byte >> (self.bit % 8)) as u32 & 1) << i;
+            self.bit += 1;
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        self.bit = self.bit.div_ceil(8) * 8;
+    }
+}
+
+/// Canonical Huffman code, as symbol counts per code length and symbols in
+/// code order
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16).filter(|&symbol| lengths[symbol as usize] != 0).collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(CompositorError::graphics("Invalid Huffman code in deflate stream"))
+    }
+}
+
+/// Decompress a raw deflate stream
+fn inflate(data: &[u8]) -> Result<Vec<u8>> {
+    let invalid = |what: &str| CompositorError::graphics(format!("Invalid deflate stream: {}", what));
+    let mut reader = BitReader { data, bit: 0 };
+    let mut out = Vec::new();
+    loop {
+        let last = reader.bits(1)? == 1;
+        let (literals, distances) = match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let start = reader.bit / 8;
+                let header = data.get(start..start + 4).ok_or_else(|| invalid("truncated stored block"))?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let block = data.get(start + 4..start + 4 + len).ok_or_else(|| invalid("truncated stored block"))?;
+                out.extend_from_slice(block);
+                reader.bit = (start + 4 + len) * 8;
+                if last {
+                    return Ok(out);
+                }
+                continue;
+            }
+            1 => {
+                let mut lengths = [8u8; 288];
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+            }
+            2 => {
+                let literal_count = reader.bits(5)? as usize + 257;
+                let distance_count = reader.bits(5)? as usize + 1;
+                let code_length_count = reader.bits(4)? as usize + 4;
+                let mut code_lengths = [0u8; 19];
+                for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+                    code_lengths[index] = reader.bits(3)? as u8;
+                }
+                let code_lengths = Huffman::new(&code_lengths);
+                let mut lengths = Vec::with_capacity(literal_count + distance_count);
+                while lengths.len() < literal_count + distance_count {
+                    let (value, repeat) = match code_lengths.decode(&mut reader)? {
+                        symbol @ 0..=15 => (symbol as u8, 1),
+                        16 => (*lengths.last().ok_or_else(|| invalid("repeat without a length"))?, 3 + reader.bits(2)?),
+                        17 => (0, 3 + reader.bits(3)?),
+                        _ => (0, 11 + reader.bits(7)?),
+                    };
+                    lengths.extend(std::iter::repeat_n(value, repeat as usize));
+                }
+                if lengths.len() != literal_count + distance_count {
+                    return Err(invalid("code lengths overrun"));
+                }
+                (Huffman::new(&lengths[..literal_count]), Huffman::new(&lengths[literal_count..]))
+            }
+            _ => return Err(invalid("reserved block type")),
+        };
+        loop {
+            let symbol = literals.decode(&mut reader)? as usize;
+            if symbol < 256 {
+                out.push(symbol as u8);
+                continue;
+            }
+            if symbol == 256 {
+                break;
+            }
+            let index = symbol - 257;
+            let (&base, &extra) = LENGTH_BASE.get(index).zip(LENGTH_EXTRA.get(index)).ok_or_else(|| invalid("bad length"))?;
+            let length = base as usize + reader.bits(extra as u32)? as usize;
+            let index = distances.decode(&mut reader)? as usize;
+            let (&base, &extra) = DISTANCE_BASE.get(index).zip(DISTANCE_EXTRA.get(index)).ok_or_else(|| invalid("bad distance"))?;
+            let distance = base as usize + reader.bits(extra as u32)? as usize;
+            if distance > out.len() {
+                return Err(invalid("distance before the start"));
+            }
+            let start = out.len() - distance;
+            for i in 0..length {
+                out.push(out[start + i]);
+            }
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+fn write_png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(kind.iter().chain(data.iter()).copied());
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(bytes: impl Iterator<Item = u8>) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_encoded_image() {
+        let rgba: Vec<u8> = (0..3 * 2 * 4).map(|i| (i * 11) as u8).collect();
+        let decoded = decode_rgba(&encode_rgba(&rgba, 3, 2)).unwrap();
+        assert_eq!(decoded, DecodedImage { width: 3, height: 2, rgba });
+    }
+
+    #[test]
+    fn reverses_scanline_filters() {
+        // 2x2 RGB: the first row Sub filtered, the second Paeth filtered
+        let raw = [1, 10, 20, 30, 5, 5, 5, 4, 1, 1, 1, 2, 2, 2];
+        let mut zlib = vec![0x78, 0x01, 0x01, raw.len() as u8, 0x00, !(raw.len() as u8), 0xFF];
+        zlib.extend_from_slice(&raw);
+        zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_png_chunk(&mut png, b"IHDR", &[0, 0, 0, 2, 0, 0, 0, 2, 8, 2, 0, 0, 0]);
+        write_png_chunk(&mut png, b"IDAT", &zlib);
+        write_png_chunk(&mut png, b"IEND", &[]);
+        let decoded = decode_rgba(&png).unwrap();
+        assert_eq!(decoded.rgba, [10, 20, 30, 255, 15, 25, 35, 255, 11, 21, 31, 255, 17, 27, 37, 255]);
+    }
+
+    #[test]
+    fn inflates_fixed_huffman_blocks() {
+        // "abcabcabc" with fixed codes and a back reference
+        let deflated = [0x4b, 0x4c, 0x4a, 0x4e, 0x04, 0x23, 0x00];
+        assert_eq!(inflate(&deflated).unwrap(), b"abcabcabc");
+    }
+
+    #[test]
+    fn inflates_dynamic_huffman_blocks() {
+        // 80 letters drawn by a linear congruential generator, skewed
+        // towards 'a' so the encoder picks its own codes
+        let letters = b"aaaaaaaabbbbccd";
+        let mut x = 1u32;
+        let expected: Vec<u8> = (0..80)
+            .map(|_| {
+                x = x.wrapping_mul(1103515245).wrapping_add(12345) & 0x7fff_ffff;
+                letters[(x >> 16) as usize % letters.len()]
+            })
+            .collect();
+        let deflated = [
+            0x2d, 0x8c, 0x89, 0x09, 0x00, 0x30, 0x10, 0xc2, 0x66, 0xf5, 0xd9, 0x7f, 0x86, 0x36, 0xb6, 0x70, 0x08, 0xc6,
+            0x70, 0x8e, 0x2c, 0xc9, 0x21, 0xee, 0x59, 0x59, 0xc9, 0x68, 0x55, 0x68, 0x36, 0x20, 0xf8, 0xdb, 0x01, 0xa1,
+            0xc2, 0xfb, 0x66, 0x3e, 0xf9, 0x00,
+        ];
+        assert_eq!(inflate(&deflated).unwrap(), expected);
+    }
+}
@@ -0,0 +1,163 @@
+// Semantic theme colors
+//
+// Compositor UI and decorations pick colors by role rather than by raw RGBA
+// value: a surface, the text on it, the accent, a warning. A palette derives
+// every role from the theme's few base colors, so a theme only overrides the
+// roles it cares about, and derives the hover, pressed and disabled variants
+// of each role for interactive elements.
+
+use serde::{Deserialize, Serialize};
+
+/// Role of a color in the theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorToken {
+    /// Behind everything, e.g. outputs without a wallpaper
+    Background,
+    /// Panels, popovers and server-side decorations
+    Surface,
+    /// Elements set apart from the surface, e.g. cards and inactive title bars
+    SurfaceVariant,
+    /// Text and icons on surfaces
+    OnSurface,
+    /// Secondary text and icons on surfaces
+    OnSurfaceVariant,
+    /// Focus, selection and highlights
+    Accent,
+    /// Text and icons on the accent
+    OnAccent,
+    /// Borders and separators
+    Outline,
+    Warning,
+    Error,
+    Success,
+}
+
+impl ColorToken {
+    /// Every token
+    pub const ALL: [ColorToken; 11] = [
+        ColorToken::Background,
+        ColorToken::Surface,
+        ColorToken::SurfaceVariant,
+        ColorToken::OnSurface,
+        ColorToken::OnSurfaceVariant,
+        ColorToken::Accent,
+        ColorToken::OnAccent,
+        ColorToken::Outline,
+        ColorToken::Warning,
+        ColorToken::Error,
+        ColorToken::Success,
+    ];
+}
+
+/// State of an interactive element
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InteractionState {
+    #[default]
+    Normal,
+    Hover,
+    Pressed,
+    Disabled,
+}
+
+/// A color and its variants for each interaction state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StateColors {
+    pub normal: [f32; 4],
+    pub hover: [f32; 4],
+    pub pressed: [f32; 4],
+    pub disabled: [f32; 4],
+}
+
+impl StateColors {
+    /// Derive the variants of a color: hovering and pressing move it towards
+    /// the contrasting text color, disabling greys it out and fades it
+    pub fn derive(color: [f32; 4]) -> Self {
+        let contrast = contrasting(color);
+        let grey = luminance(color);
+        Self {
+            normal: color,
+            hover: mix(color, contrast, 0.08),
+            pressed: mix(color, contrast, 0.16),
+            disabled: [grey, grey, grey, color[3] * 0.38],
+        }
+    }
+
+    /// Color in a state
+    pub fn get(&self, state: InteractionState) -> [f32; 4] {
+        match state {
+            InteractionState::Normal => self.normal,
+            InteractionState::Hover => self.hover,
+            InteractionState::Pressed => self.pressed,
+            InteractionState::Disabled => self.disabled,
+        }
+    }
+}
+
+/// Color of every role in a theme
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    colors: [[f32; 4]; ColorToken::ALL.len()],
+}
+
+impl Palette {
+    /// Derive every role from the theme's base colors
+    pub fn new(background: [f32; 4], surface: [f32; 4], surface_variant: [f32; 4], accent: [f32; 4]) -> Self {
+        let on_surface = contrasting(surface);
+        let mut palette = Self { colors: [[0.0; 4]; ColorToken::ALL.len()] };
+        palette.set(ColorToken::Background, background);
+        palette.set(ColorToken::Surface, surface);
+        palette.set(ColorToken::SurfaceVariant, surface_variant);
+        palette.set(ColorToken::OnSurface, on_surface);
+        palette.set(ColorToken::OnSurfaceVariant, [on_surface[0], on_surface[1], on_surface[2], 0.6]);
+        palette.set(ColorToken::Accent, accent);
+        palette.set(ColorToken::OnAccent, contrasting(accent));
+        palette.set(ColorToken::Outline, surface_variant);
+        palette.set(ColorToken::Warning, [1.0, 0.7, 0.0, 1.0]);
+        palette.set(ColorToken::Error, [0.9, 0.25, 0.2, 1.0]);
+        palette.set(ColorToken::Success, [0.2, 0.75, 0.35, 1.0]);
+        palette
+    }
+
+    /// Override the color of a role
+    pub fn set(&mut self, token: ColorToken, color: [f32; 4]) {
+        self.colors[token as usize] = color;
+    }
+
+    /// Color of a role
+    pub fn color(&self, token: ColorToken) -> [f32; 4] {
+        self.colors[token as usize]
+    }
+
+    /// Color of a role in each interaction state
+    pub fn states(&self, token: ColorToken) -> StateColors {
+        StateColors::derive(self.color(token))
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new([0.05, 0.05, 0.05, 0.9], [0.2, 0.2, 0.2, 0.8], [0.3, 0.3, 0.3, 0.6], [0.0, 0.5, 1.0, 1.0])
+    }
+}
+
+/// Relative luminance of a color, ignoring alpha
+fn luminance(color: [f32; 4]) -> f32 {
+    0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2]
+}
+
+/// Opaque text color readable on a color: black on light colors, white on
+/// dark ones
+fn contrasting(color: [f32; 4]) -> [f32; 4] {
+    if luminance(color) > 0.6 {
+        [0.0, 0.0, 0.0, 1.0]
+    } else {
+        [1.0, 1.0, 1.0, 1.0]
+    }
+}
+
+/// Move a color's RGB `amount` of the way to another's, keeping its alpha
+fn mix(color: [f32; 4], towards: [f32; 4], amount: f32) -> [f32; 4] {
+    let channel = |i: usize| color[i] + (towards[i] - color[i]) * amount;
+    [channel(0), channel(1), channel(2), color[3]]
+}
@@ -16,6 +16,10 @@ fn main() {
     // Compile shaders
     compile_shader(shader_dir, &output_dir, "surface.vert");
     compile_shader(shader_dir, &output_dir, "surface.frag");
+    compile_shader(shader_dir, &output_dir, "ui.vert");
+    compile_shader(shader_dir, &output_dir, "ui.frag");
+    compile_shader(shader_dir, &output_dir, "blur.vert");
+    compile_shader(shader_dir, &output_dir, "blur.frag");
     
     println!("Shaders compiled successfully");
 }
@@ -0,0 +1,458 @@
+// Antialiasing for compositor-drawn UI
+//
+// Curved edges drawn by the compositor (rounded window corners, decorations,
+// vector widgets) alias visibly at 4K. Shaders smooth them analytically by
+// ramping coverage over about a pixel of the signed distance to the edge, and
+// the UI pass can additionally be multisampled where the device supports it.
+
+use ash::vk;
+use compositor_utils::prelude::*;
+use crate::ui_pipeline::UiPipeline;
+use crate::{VulkanDevice, VulkanInstance};
+
+/// Antialiasing quality for compositor-drawn UI
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UiAntialiasing {
+    /// Hard edges
+    Off,
+    /// Coverage computed in the fragment shader
+    #[default]
+    Analytic,
+    /// Multisampled UI pass on top of analytic coverage
+    Msaa { samples: u32 },
+}
+
+impl UiAntialiasing {
+    /// Width in pixels of the analytic coverage ramp; 0.0 disables it
+    pub fn edge_softness(self) -> f32 {
+        match self {
+            Self::Off => 0.0,
+            Self::Analytic | Self::Msaa { .. } => 1.0,
+        }
+    }
+
+    /// Requested sample count
+    pub fn samples(self) -> u32 {
+        match self {
+            Self::Off | Self::Analytic => 1,
+            Self::Msaa { samples } => samples,
+        }
+    }
+}
+
+/// Highest sample count the device supports for color attachments, up to `requested`
+pub fn supported_sample_count(
+    instance: &VulkanInstance,
+    device: &VulkanDevice,
+    requested: u32,
+) -> vk::SampleCountFlags {
+    let properties = unsafe {
+        instance.handle().get_physical_device_properties(device.physical_device())
+    };
+    let supported = properties.limits.framebuffer_color_sample_counts;
+
+    [
+        vk::SampleCountFlags::TYPE_64,
+        vk::SampleCountFlags::TYPE_32,
+        vk::SampleCountFlags::TYPE_16,
+        vk::SampleCountFlags::TYPE_8,
+        vk::SampleCountFlags::TYPE_4,
+        vk::SampleCountFlags::TYPE_2,
+    ]
+    .into_iter()
+    .find(|&count| count.as_raw() <= requested && supported.contains(count))
+    .unwrap_or(vk::SampleCountFlags::TYPE_1)
+}
+
+/// Multisampled layer compositor UI is drawn into when MSAA is enabled
+///
+/// UI is drawn into a transparent multisampled image with its own pipeline,
+/// resolved into a single-sampled image, and that image is then blended
+/// over the scene. The resolved layer holds premultiplied color.
+pub struct UiMsaaTarget {
+    device: VulkanDevice,
+    render_pass: vk::RenderPass,
+    pipeline: UiPipeline,
+    multisampled: TargetImage,
+    resolved: TargetImage,
+    framebuffer: vk::Framebuffer,
+    descriptor_set: vk::DescriptorSet,
+    extent: vk::Extent2D,
+    samples: vk::SampleCountFlags,
+}
+
+/// Image backing one attachment of the UI layer
+struct TargetImage {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    image_view: vk::ImageView,
+}
+
+impl UiMsaaTarget {
+    /// Create a layer of `extent` with `samples` per pixel, and a set from
+    /// `pool` sampling the resolved layer through `sampler`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        instance: &VulkanInstance,
+        device: &VulkanDevice,
+        pipeline_cache: vk::PipelineCache,
+        pool: vk::DescriptorPool,
+        layout: vk::DescriptorSetLayout,
+        sampler: vk::Sampler,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        samples: vk::SampleCountFlags,
+    ) -> Result<Self> {
+        let render_pass = Self::create_render_pass(device, format, samples)?;
+        let pipeline = match UiPipeline::new(device.clone(), render_pass, pipeline_cache, samples) {
+            Ok(pipeline) => pipeline,
+            Err(e) => {
+                unsafe { device.handle().destroy_render_pass(render_pass, None) };
+                return Err(e);
+            }
+        };
+        let mut target = Self {
+            device: device.clone(),
+            render_pass,
+            pipeline,
+            multisampled: TargetImage::null(),
+            resolved: TargetImage::null(),
+            framebuffer: vk::Framebuffer::null(),
+            descriptor_set: vk::DescriptorSet::null(),
+            extent,
+            samples,
+        };
+        // Dropping the partly created target releases what was created so far
+        target.multisampled = TargetImage::new(
+            instance,
+            device,
+            extent,
+            format,
+            samples,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+        )?;
+        target.resolved = TargetImage::new(
+            instance,
+            device,
+            extent,
+            format,
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+        )?;
+
+        let attachments = [target.multisampled.image_view, target.resolved.image_view];
+        let framebuffer_info = vk::FramebufferCreateInfo {
+            render_pass,
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            width: extent.width,
+            height: extent.height,
+            layers: 1,
+            ..Default::default()
+        };
+        target.framebuffer = unsafe { device.handle().create_framebuffer(&framebuffer_info, None)? };
+
+        let layouts = [layout];
+        let set_info = vk::DescriptorSetAllocateInfo {
+            descriptor_pool: pool,
+            descriptor_set_count: layouts.len() as u32,
+            p_set_layouts: layouts.as_ptr(),
+            ..Default::default()
+        };
+        target.descriptor_set = unsafe {
+            device.handle().allocate_descriptor_sets(&set_info)
+                .map_err(|e| CompositorError::graphics(format!("Failed to allocate UI layer descriptor set: {}", e)))?[0]
+        };
+        let descriptor_image_info = vk::DescriptorImageInfo {
+            sampler,
+            image_view: target.resolved.image_view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        let write = vk::WriteDescriptorSet {
+            dst_set: target.descriptor_set,
+            dst_binding: 0,
+            descriptor_count: 1,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            p_image_info: &descriptor_image_info,
+            ..Default::default()
+        };
+        unsafe {
+            device.handle().update_descriptor_sets(&[write], &[]);
+        }
+
+        debug!(
+            "Created {}x{} UI layer with {}x MSAA",
+            extent.width,
+            extent.height,
+            samples.as_raw(),
+        );
+        Ok(target)
+    }
+
+    /// Size of the layer
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// Samples per pixel UI is drawn with
+    pub fn samples(&self) -> vk::SampleCountFlags {
+        self.samples
+    }
+
+    /// Pipeline drawing UI primitives into the layer
+    pub fn pipeline(&self) -> &UiPipeline {
+        &self.pipeline
+    }
+
+    /// Set sampling the resolved layer
+    pub fn descriptor_set(&self) -> vk::DescriptorSet {
+        self.descriptor_set
+    }
+
+    /// Begin the render pass clearing the layer, with viewport and scissor
+    /// covering it; ending the pass resolves the layer for sampling
+    pub fn begin(&self, command_buffer: vk::CommandBuffer) {
+        let clear_values = [vk::ClearValue {
+            color: vk::ClearColorValue { float32: [0.0; 4] },
+        }];
+        let render_area = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: self.extent,
+        };
+        let render_pass_info = vk::RenderPassBeginInfo {
+            render_pass: self.render_pass,
+            framebuffer: self.framebuffer,
+            render_area,
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+            ..Default::default()
+        };
+        let viewport = vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: self.extent.width as f32,
+            height: self.extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        };
+
+        let device = self.device.handle();
+        unsafe {
+            device.cmd_begin_render_pass(command_buffer, &render_pass_info, vk::SubpassContents::INLINE);
+            device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+            device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+        }
+    }
+
+    /// Free the layer's descriptor set back to `pool`; the rest is released on drop
+    pub fn free_descriptor_set(&mut self, pool: vk::DescriptorPool) {
+        if self.descriptor_set == vk::DescriptorSet::null() {
+            return;
+        }
+        unsafe {
+            if let Err(e) = self.device.handle().free_descriptor_sets(pool, &[self.descriptor_set]) {
+                warn!("Failed to free UI layer descriptor set: {}", e);
+            }
+        }
+        self.descriptor_set = vk::DescriptorSet::null();
+    }
+
+    /// Create the render pass clearing the multisampled image and resolving
+    /// it into the single-sampled one, left ready to be sampled
+    fn create_render_pass(
+        device: &VulkanDevice,
+        format: vk::Format,
+        samples: vk::SampleCountFlags,
+    ) -> Result<vk::RenderPass> {
+        let attachments = [
+            vk::AttachmentDescription {
+                format,
+                samples,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::DONT_CARE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                ..Default::default()
+            },
+            vk::AttachmentDescription {
+                format,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::DONT_CARE,
+                store_op: vk::AttachmentStoreOp::STORE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                ..Default::default()
+            },
+        ];
+
+        let color_attachment_ref = vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        };
+        let resolve_attachment_ref = vk::AttachmentReference {
+            attachment: 1,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        };
+
+        let subpass = vk::SubpassDescription {
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            color_attachment_count: 1,
+            p_color_attachments: &color_attachment_ref,
+            p_resolve_attachments: &resolve_attachment_ref,
+            ..Default::default()
+        };
+
+        // The previous frame may still sample the layer; the scene samples the result
+        let dependencies = [
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                ..Default::default()
+            },
+            vk::SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: vk::SUBPASS_EXTERNAL,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_access_mask: vk::AccessFlags::SHADER_READ,
+                ..Default::default()
+            },
+        ];
+
+        let render_pass_info = vk::RenderPassCreateInfo {
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            subpass_count: 1,
+            p_subpasses: &subpass,
+            dependency_count: dependencies.len() as u32,
+            p_dependencies: dependencies.as_ptr(),
+            ..Default::default()
+        };
+
+        unsafe {
+            device.handle().create_render_pass(&render_pass_info, None)
+                .map_err(|e| CompositorError::graphics(format!("Failed to create UI layer render pass: {}", e)))
+        }
+    }
+}
+
+impl Drop for UiMsaaTarget {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.handle().destroy_framebuffer(self.framebuffer, None);
+        }
+        self.multisampled.destroy(&self.device);
+        self.resolved.destroy(&self.device);
+        unsafe {
+            self.device.handle().destroy_render_pass(self.render_pass, None);
+        }
+    }
+}
+
+impl TargetImage {
+    fn null() -> Self {
+        Self {
+            image: vk::Image::null(),
+            memory: vk::DeviceMemory::null(),
+            image_view: vk::ImageView::null(),
+        }
+    }
+
+    fn new(
+        instance: &VulkanInstance,
+        device: &VulkanDevice,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        samples: vk::SampleCountFlags,
+        usage: vk::ImageUsageFlags,
+    ) -> Result<Self> {
+        let image_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            extent: vk::Extent3D { width: extent.width, height: extent.height, depth: 1 },
+            mip_levels: 1,
+            array_layers: 1,
+            format,
+            tiling: vk::ImageTiling::OPTIMAL,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            usage,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            samples,
+            ..Default::default()
+        };
+
+        let mut target = Self::null();
+        target.image = unsafe { device.handle().create_image(&image_info, None)? };
+        let memory_requirements = unsafe { device.handle().get_image_memory_requirements(target.image) };
+
+        let memory_properties = unsafe {
+            instance.handle().get_physical_device_memory_properties(device.physical_device())
+        };
+        let memory_type_index = (0..memory_properties.memory_type_count)
+            .find(|&i| {
+                (memory_requirements.memory_type_bits & (1 << i)) != 0
+                    && memory_properties.memory_types[i as usize]
+                        .property_flags
+                        .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+            });
+        let Some(memory_type_index) = memory_type_index else {
+            target.destroy(device);
+            return Err(CompositorError::graphics("Failed to find memory type for UI layer"));
+        };
+
+        let alloc_info = vk::MemoryAllocateInfo {
+            allocation_size: memory_requirements.size,
+            memory_type_index,
+            ..Default::default()
+        };
+        let bound = unsafe {
+            device.handle().allocate_memory(&alloc_info, None).and_then(|memory| {
+                target.memory = memory;
+                device.handle().bind_image_memory(target.image, memory, 0)
+            })
+        };
+        if let Err(e) = bound {
+            target.destroy(device);
+            return Err(e.into());
+        }
+
+        let image_view_info = vk::ImageViewCreateInfo {
+            image: target.image,
+            view_type: vk::ImageViewType::TYPE_2D,
+            format,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            ..Default::default()
+        };
+        match unsafe { device.handle().create_image_view(&image_view_info, None) } {
+            Ok(image_view) => target.image_view = image_view,
+            Err(e) => {
+                target.destroy(device);
+                return Err(e.into());
+            }
+        }
+        Ok(target)
+    }
+
+    /// Destroy whichever objects were created; null handles are ignored
+    fn destroy(&self, device: &VulkanDevice) {
+        unsafe {
+            device.handle().destroy_image_view(self.image_view, None);
+            device.handle().destroy_image(self.image, None);
+            device.handle().free_memory(self.memory, None);
+        }
+    }
+}
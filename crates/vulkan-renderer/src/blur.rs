@@ -0,0 +1,266 @@
+// Background blur behind client surfaces
+//
+// Holds per-surface blur requests (radius plus the surface-local regions to
+// blur) and the separable Gaussian kernel used by the blur pass. The pass
+// samples the already-composited scene behind a surface and is masked to the
+// requested regions, so opaque parts of the surface are never blurred.
+//
+// The quality setting trades fidelity for frame time: the backdrop is
+// downsampled before blurring, the kernel is folded into a limited number of
+// samples, and backdrops are refreshed only every few frames.
+
+use std::collections::HashMap;
+
+/// Largest kernel half-width used by the blur pass
+pub const MAX_KERNEL_RADIUS: usize = 64;
+
+/// Most samples the blur shader takes on each side of the center
+pub const MAX_BLUR_TAPS: usize = 24;
+
+/// Blur requested behind a surface
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceBlurRequest {
+    /// Blur radius in pixels; the default radius when unset
+    pub radius: Option<f32>,
+    /// Regions to blur as (x, y, width, height) in pixels relative to the
+    /// surface's geometry
+    pub regions: Vec<[i32; 4]>,
+}
+
+/// Cost settings of the blur pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlurQuality {
+    /// Texture samples per blur direction
+    pub samples: u32,
+    /// Times the backdrop is halved in size before blurring
+    pub downsample_levels: u32,
+    /// Frames between refreshes of a blurred backdrop
+    pub backdrop_update_interval: u32,
+}
+
+impl Default for BlurQuality {
+    fn default() -> Self {
+        Self {
+            samples: 8,
+            downsample_levels: 2,
+            backdrop_update_interval: 2,
+        }
+    }
+}
+
+/// Blur pass variant for one radius at one quality
+///
+/// The blur pipeline is specialized on the sample count, so surfaces with
+/// the same radius share a pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlurVariant {
+    /// Times the backdrop is halved before blurring, lowered for small radii
+    pub downsample_levels: u32,
+    /// Sample offsets in downsampled texels, center first
+    pub offsets: Vec<f32>,
+    /// Weight of the sample at each offset, applied at `+offset` and `-offset`
+    pub weights: Vec<f32>,
+}
+
+impl BlurVariant {
+    /// Variant blurring with `radius` full-resolution pixels within the quality budget
+    pub fn new(radius: f32, quality: BlurQuality) -> Self {
+        // Downsampling further than this leaves too few texels to blur
+        let mut downsample_levels = quality.downsample_levels;
+        while downsample_levels > 0 && radius / (1u32 << downsample_levels) as f32 <= 2.0 {
+            downsample_levels -= 1;
+        }
+        let kernel = gaussian_kernel(radius / (1u32 << downsample_levels) as f32);
+
+        // Fold neighbouring texels into one sample each; with linear
+        // filtering a sample at their weighted center reads them together.
+        // Sample counts beyond what the blur shader takes are lowered.
+        let samples_per_side = ((quality.samples / 2).max(1) as usize).min(MAX_BLUR_TAPS);
+        let half_width = kernel.len() - 1;
+        let group = half_width.div_ceil(samples_per_side).max(1);
+
+        let mut offsets = vec![0.0];
+        let mut weights = vec![kernel[0]];
+        for start in (1..=half_width).step_by(group) {
+            let texels = start..(start + group).min(half_width + 1);
+            let weight: f32 = kernel[texels.clone()].iter().sum();
+            let center = texels.map(|i| i as f32 * kernel[i]).sum::<f32>() / weight;
+            offsets.push(center);
+            weights.push(weight);
+        }
+
+        Self {
+            downsample_levels,
+            offsets,
+            weights,
+        }
+    }
+
+    /// Texture samples taken per blur direction
+    pub fn sample_count(&self) -> u32 {
+        (self.offsets.len() * 2 - 1) as u32
+    }
+}
+
+/// Blur requested behind one surface and its backdrop refresh state
+#[derive(Debug)]
+struct SurfaceBlur {
+    request: SurfaceBlurRequest,
+    variant: BlurVariant,
+    /// Frame the backdrop was last blurred in
+    refreshed: Option<u64>,
+}
+
+/// Blur radius of requests without their own until one is set
+pub const DEFAULT_BLUR_RADIUS: f32 = 20.0;
+
+/// Blur requests for all surfaces
+#[derive(Debug)]
+pub struct BlurState {
+    surfaces: HashMap<u32, SurfaceBlur>,
+    quality: BlurQuality,
+    default_radius: f32,
+    frame: u64,
+}
+
+impl Default for BlurState {
+    fn default() -> Self {
+        Self {
+            surfaces: HashMap::new(),
+            quality: BlurQuality::default(),
+            default_radius: DEFAULT_BLUR_RADIUS,
+            frame: 0,
+        }
+    }
+}
+
+impl BlurState {
+    /// Create an empty blur state
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set or clear the blur request for a surface
+    pub fn set_surface_blur(&mut self, surface_id: u32, request: Option<SurfaceBlurRequest>) {
+        // Requests using the default radius are kept while it is zero
+        match request {
+            Some(request) if request.radius.is_none_or(|radius| radius > 0.0) && !request.regions.is_empty() => {
+                let variant = BlurVariant::new(request.radius.unwrap_or(self.default_radius), self.quality);
+                self.surfaces.insert(surface_id, SurfaceBlur {
+                    request,
+                    variant,
+                    refreshed: None,
+                });
+            }
+            _ => {
+                self.surfaces.remove(&surface_id);
+            }
+        }
+    }
+
+    /// Replace the blur requests of all surfaces
+    ///
+    /// Surfaces whose request is unchanged keep their blurred backdrop.
+    pub fn set_surfaces(&mut self, requests: impl IntoIterator<Item = (u32, SurfaceBlurRequest)>) {
+        let requests: HashMap<u32, SurfaceBlurRequest> = requests.into_iter().collect();
+        self.surfaces.retain(|surface_id, _| requests.contains_key(surface_id));
+        for (surface_id, request) in requests {
+            if self.get(surface_id) != Some(&request) {
+                self.set_surface_blur(surface_id, Some(request));
+            }
+        }
+    }
+
+    /// Change the blur quality; every backdrop is blurred again next frame
+    pub fn set_quality(&mut self, quality: BlurQuality) {
+        self.quality = quality;
+        for blur in self.surfaces.values_mut() {
+            blur.variant = BlurVariant::new(blur.request.radius.unwrap_or(self.default_radius), quality);
+            blur.refreshed = None;
+        }
+    }
+    
+    /// Change the radius of requests without their own; their backdrops
+    /// are blurred again next frame
+    pub fn set_default_radius(&mut self, radius: f32) {
+        self.default_radius = radius.max(0.0);
+        for blur in self.surfaces.values_mut().filter(|blur| blur.request.radius.is_none()) {
+            blur.variant = BlurVariant::new(self.default_radius, self.quality);
+            blur.refreshed = None;
+        }
+    }
+
+    /// Current blur quality
+    pub fn quality(&self) -> BlurQuality {
+        self.quality
+    }
+
+    /// Blur request for a surface, if any
+    pub fn get(&self, surface_id: u32) -> Option<&SurfaceBlurRequest> {
+        self.surfaces.get(&surface_id).map(|blur| &blur.request)
+    }
+
+    /// Radius a surface is blurred with, if it is blurred
+    ///
+    /// Surfaces using a default radius of zero are not blurred.
+    pub fn radius(&self, surface_id: u32) -> Option<f32> {
+        self.get(surface_id)
+            .map(|request| request.radius.unwrap_or(self.default_radius))
+            .filter(|&radius| radius > 0.0)
+    }
+
+    /// Blur pass variant for a surface, if it is blurred
+    pub fn variant(&self, surface_id: u32) -> Option<&BlurVariant> {
+        self.surfaces.get(&surface_id).map(|blur| &blur.variant)
+    }
+
+    /// Start a frame, marking the backdrops due for a refresh as refreshed
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+        let interval = u64::from(self.quality.backdrop_update_interval.max(1));
+        for blur in self.surfaces.values_mut() {
+            if blur.refreshed.is_none_or(|refreshed| self.frame - refreshed >= interval) {
+                blur.refreshed = Some(self.frame);
+            }
+        }
+    }
+
+    /// Whether a surface's backdrop is blurred again this frame
+    ///
+    /// Otherwise the blurred backdrop of an earlier frame is reused.
+    pub fn refresh_due(&self, surface_id: u32) -> bool {
+        self.surfaces
+            .get(&surface_id)
+            .is_some_and(|blur| blur.refreshed == Some(self.frame))
+    }
+
+    /// Whether any surface needs the blur pass this frame
+    pub fn is_active(&self) -> bool {
+        !self.surfaces.is_empty()
+    }
+
+    /// Forget a removed surface
+    pub fn remove_surface(&mut self, surface_id: u32) {
+        self.surfaces.remove(&surface_id);
+    }
+}
+
+/// One-sided normalized Gaussian weights for a separable blur of the given radius
+///
+/// `weights[0]` is the center tap; the pass applies `weights[i]` at offsets
+/// `+i` and `-i` in each direction.
+pub fn gaussian_kernel(radius: f32) -> Vec<f32> {
+    let half_width = (radius.ceil().max(0.0) as usize).min(MAX_KERNEL_RADIUS);
+    if half_width == 0 {
+        return vec![1.0];
+    }
+
+    // Radius covers roughly three standard deviations
+    let sigma = (radius / 3.0).max(0.5);
+    let weights: Vec<f32> = (0..=half_width)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+
+    weights.into_iter().map(|weight| weight / total).collect()
+}
@@ -0,0 +1,759 @@
+// Vulkan pipeline for background blur
+//
+// The scene behind a blurred surface is blitted, downsampled, into the first
+// of two backdrop images, blurred horizontally into the second and then
+// vertically back into the first by drawing a fullscreen triangle. The
+// offsets and weights of the blur variant are passed as push constants, so a
+// single pipeline serves every radius and quality.
+
+use ash::vk;
+use compositor_utils::prelude::*;
+use crate::blur::{BlurVariant, MAX_BLUR_TAPS};
+use crate::{VulkanDevice, VulkanInstance};
+
+/// Graphics pipeline running one direction of the separable blur
+pub struct BlurPipeline {
+    device: VulkanDevice,
+    render_pass: vk::RenderPass,
+    pipeline: vk::Pipeline,
+    pipeline_layout: vk::PipelineLayout,
+    vertex_shader: vk::ShaderModule,
+    fragment_shader: vk::ShaderModule,
+}
+
+/// Push constants for one blur direction
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlurPushConstants {
+    pub texel_step: [f32; 2],           // One source texel along the blur direction, in texture coordinates
+    pub tap_count: u32,                 // Taps on each side of the center
+    pub center_weight: f32,             // Weight of the center sample
+    pub taps: [u32; MAX_BLUR_TAPS],     // Offset and weight of each tap as two packed halves
+}
+
+impl BlurPushConstants {
+    /// Push constants blurring with `variant` along `texel_step`
+    pub fn new(variant: &BlurVariant, texel_step: [f32; 2]) -> Self {
+        let mut taps = [0; MAX_BLUR_TAPS];
+        let sides = variant.offsets.iter().zip(&variant.weights).skip(1).take(MAX_BLUR_TAPS);
+        for (tap, (&offset, &weight)) in taps.iter_mut().zip(sides) {
+            *tap = u32::from(f16_bits(offset)) | (u32::from(f16_bits(weight)) << 16);
+        }
+        Self {
+            texel_step,
+            tap_count: (variant.offsets.len() - 1).min(MAX_BLUR_TAPS) as u32,
+            center_weight: variant.weights[0],
+            taps,
+        }
+    }
+}
+
+/// Bits of the half-precision float nearest to `value`, as read by
+/// `unpackHalf2x16` in the shader
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent >= 31 {
+        // Too large, or not a number; neither occurs in blur kernels
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // Subnormal, or too small to represent
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = (mantissa | 0x80_0000) >> (1 - exponent);
+        return sign | ((mantissa + 0x1000) >> 13) as u16;
+    }
+    // Rounding up may carry into the exponent, which is still correct
+    sign | ((((exponent as u32) << 23 | mantissa) + 0x1000) >> 13) as u16
+}
+
+impl BlurPipeline {
+    /// Create the blur pipeline for backdrops of `format`, sampling through
+    /// sets of `descriptor_set_layout` (one combined image sampler)
+    pub fn new(
+        device: VulkanDevice,
+        format: vk::Format,
+        pipeline_cache: vk::PipelineCache,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<Self> {
+        info!("Creating background blur pipeline");
+
+        let render_pass = Self::create_render_pass(&device, format)?;
+        let vertex_shader = Self::create_shader_module(&device, "blur.vert.spv")?;
+        let fragment_shader = Self::create_shader_module(&device, "blur.frag.spv")?;
+        let pipeline_layout = Self::create_pipeline_layout(&device, descriptor_set_layout)?;
+        let pipeline = Self::create_graphics_pipeline(
+            &device,
+            vertex_shader,
+            fragment_shader,
+            pipeline_layout,
+            render_pass,
+            pipeline_cache,
+        )?;
+
+        Ok(Self {
+            device,
+            render_pass,
+            pipeline,
+            pipeline_layout,
+            vertex_shader,
+            fragment_shader,
+        })
+    }
+
+    /// Render pass writing one blur direction into a backdrop image
+    pub fn render_pass(&self) -> vk::RenderPass {
+        self.render_pass
+    }
+
+    /// Record one blur direction from the image sampled by `source` into `framebuffer`
+    ///
+    /// The source must be in SHADER_READ_ONLY_OPTIMAL; the destination is
+    /// left in that layout.
+    pub fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        framebuffer: vk::Framebuffer,
+        extent: vk::Extent2D,
+        source: vk::DescriptorSet,
+        push_constants: &BlurPushConstants,
+    ) {
+        let render_pass_info = vk::RenderPassBeginInfo {
+            render_pass: self.render_pass,
+            framebuffer,
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent,
+            },
+            ..Default::default()
+        };
+        let viewport = vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: extent.width as f32,
+            height: extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        };
+        let scissor = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent,
+        };
+
+        let device = self.device.handle();
+        unsafe {
+            device.cmd_begin_render_pass(command_buffer, &render_pass_info, vk::SubpassContents::INLINE);
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
+            device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+            device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout,
+                0,
+                &[source],
+                &[],
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                std::slice::from_raw_parts(
+                    push_constants as *const BlurPushConstants as *const u8,
+                    std::mem::size_of::<BlurPushConstants>(),
+                ),
+            );
+            device.cmd_draw(command_buffer, 3, 1, 0, 0);
+            device.cmd_end_render_pass(command_buffer);
+        }
+    }
+
+    /// Create the render pass, which discards the previous contents and
+    /// leaves the image ready to be sampled
+    fn create_render_pass(device: &VulkanDevice, format: vk::Format) -> Result<vk::RenderPass> {
+        let color_attachment = vk::AttachmentDescription {
+            format,
+            samples: vk::SampleCountFlags::TYPE_1,
+            load_op: vk::AttachmentLoadOp::DONT_CARE,
+            store_op: vk::AttachmentStoreOp::STORE,
+            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            final_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ..Default::default()
+        };
+
+        let color_attachment_ref = vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        };
+
+        let subpass = vk::SubpassDescription {
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            color_attachment_count: 1,
+            p_color_attachments: &color_attachment_ref,
+            ..Default::default()
+        };
+
+        // Earlier passes may still sample the image; later ones sample the result
+        let dependencies = [
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                ..Default::default()
+            },
+            vk::SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: vk::SUBPASS_EXTERNAL,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_access_mask: vk::AccessFlags::SHADER_READ,
+                ..Default::default()
+            },
+        ];
+
+        let render_pass_info = vk::RenderPassCreateInfo {
+            attachment_count: 1,
+            p_attachments: &color_attachment,
+            subpass_count: 1,
+            p_subpasses: &subpass,
+            dependency_count: dependencies.len() as u32,
+            p_dependencies: dependencies.as_ptr(),
+            ..Default::default()
+        };
+
+        unsafe {
+            device.handle().create_render_pass(&render_pass_info, None)
+                .map_err(|e| CompositorError::graphics(format!("Failed to create blur render pass: {}", e)))
+        }
+    }
+
+    /// Create shader module from SPIR-V bytecode
+    fn create_shader_module(device: &VulkanDevice, filename: &str) -> Result<vk::ShaderModule> {
+        let spirv_bytes: &[u8] = match filename {
+            "blur.vert.spv" => include_bytes!(concat!(env!("OUT_DIR"), "/shaders/blur.vert.spv")),
+            "blur.frag.spv" => include_bytes!(concat!(env!("OUT_DIR"), "/shaders/blur.frag.spv")),
+            _ => return Err(CompositorError::graphics(format!("Unknown shader: {}", filename))),
+        };
+
+        let spirv_words: Vec<u32> = spirv_bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+
+        if spirv_words.is_empty() {
+            return Err(CompositorError::graphics(format!("Empty SPIR-V file: {}", filename)));
+        }
+
+        let create_info = vk::ShaderModuleCreateInfo {
+            code_size: spirv_bytes.len(),
+            p_code: spirv_words.as_ptr(),
+            ..Default::default()
+        };
+
+        unsafe {
+            device.handle().create_shader_module(&create_info, None)
+                .map_err(|e| CompositorError::graphics(format!("Failed to create shader module {}: {}", filename, e)))
+        }
+    }
+
+    /// Create pipeline layout with push constants
+    fn create_pipeline_layout(
+        device: &VulkanDevice,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<vk::PipelineLayout> {
+        let set_layouts = [descriptor_set_layout];
+        let push_constant_ranges = [
+            vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                offset: 0,
+                size: std::mem::size_of::<BlurPushConstants>() as u32,
+            },
+        ];
+
+        let pipeline_layout_info = vk::PipelineLayoutCreateInfo {
+            set_layout_count: set_layouts.len() as u32,
+            p_set_layouts: set_layouts.as_ptr(),
+            push_constant_range_count: push_constant_ranges.len() as u32,
+            p_push_constant_ranges: push_constant_ranges.as_ptr(),
+            ..Default::default()
+        };
+
+        unsafe {
+            device.handle().create_pipeline_layout(&pipeline_layout_info, None)
+                .map_err(|e| CompositorError::graphics(format!("Failed to create blur pipeline layout: {}", e)))
+        }
+    }
+
+    /// Create the graphics pipeline
+    fn create_graphics_pipeline(
+        device: &VulkanDevice,
+        vertex_shader: vk::ShaderModule,
+        fragment_shader: vk::ShaderModule,
+        pipeline_layout: vk::PipelineLayout,
+        render_pass: vk::RenderPass,
+        pipeline_cache: vk::PipelineCache,
+    ) -> Result<vk::Pipeline> {
+        let main_function_name = std::ffi::CString::new("main").unwrap();
+
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo {
+                stage: vk::ShaderStageFlags::VERTEX,
+                module: vertex_shader,
+                p_name: main_function_name.as_ptr(),
+                ..Default::default()
+            },
+            vk::PipelineShaderStageCreateInfo {
+                stage: vk::ShaderStageFlags::FRAGMENT,
+                module: fragment_shader,
+                p_name: main_function_name.as_ptr(),
+                ..Default::default()
+            },
+        ];
+
+        // The fullscreen triangle comes from gl_VertexIndex
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default();
+
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo {
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            primitive_restart_enable: vk::FALSE,
+            ..Default::default()
+        };
+
+        let viewport_state = vk::PipelineViewportStateCreateInfo {
+            viewport_count: 1,
+            scissor_count: 1,
+            ..Default::default()
+        };
+
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo {
+            depth_clamp_enable: vk::FALSE,
+            rasterizer_discard_enable: vk::FALSE,
+            polygon_mode: vk::PolygonMode::FILL,
+            line_width: 1.0,
+            cull_mode: vk::CullModeFlags::NONE,
+            front_face: vk::FrontFace::CLOCKWISE,
+            depth_bias_enable: vk::FALSE,
+            ..Default::default()
+        };
+
+        let multisampling = vk::PipelineMultisampleStateCreateInfo {
+            sample_shading_enable: vk::FALSE,
+            rasterization_samples: vk::SampleCountFlags::TYPE_1,
+            ..Default::default()
+        };
+
+        // Every texel is overwritten
+        let color_blend_attachment = vk::PipelineColorBlendAttachmentState {
+            color_write_mask: vk::ColorComponentFlags::RGBA,
+            blend_enable: vk::FALSE,
+            ..Default::default()
+        };
+
+        let color_blending = vk::PipelineColorBlendStateCreateInfo {
+            logic_op_enable: vk::FALSE,
+            logic_op: vk::LogicOp::COPY,
+            attachment_count: 1,
+            p_attachments: &color_blend_attachment,
+            blend_constants: [0.0, 0.0, 0.0, 0.0],
+            ..Default::default()
+        };
+
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo {
+            dynamic_state_count: dynamic_states.len() as u32,
+            p_dynamic_states: dynamic_states.as_ptr(),
+            ..Default::default()
+        };
+
+        let pipeline_info = vk::GraphicsPipelineCreateInfo {
+            stage_count: shader_stages.len() as u32,
+            p_stages: shader_stages.as_ptr(),
+            p_vertex_input_state: &vertex_input_info,
+            p_input_assembly_state: &input_assembly,
+            p_viewport_state: &viewport_state,
+            p_rasterization_state: &rasterizer,
+            p_multisample_state: &multisampling,
+            p_color_blend_state: &color_blending,
+            p_dynamic_state: &dynamic_state,
+            layout: pipeline_layout,
+            render_pass,
+            subpass: 0,
+            base_pipeline_handle: vk::Pipeline::null(),
+            base_pipeline_index: -1,
+            ..Default::default()
+        };
+
+        let pipelines = unsafe {
+            device.handle().create_graphics_pipelines(
+                pipeline_cache,
+                &[pipeline_info],
+                None,
+            ).map_err(|e| CompositorError::graphics(format!("Failed to create blur pipeline: {:?}", e)))?
+        };
+
+        Ok(pipelines[0])
+    }
+}
+
+impl Drop for BlurPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.handle().destroy_pipeline(self.pipeline, None);
+            self.device.handle().destroy_pipeline_layout(self.pipeline_layout, None);
+            self.device.handle().destroy_render_pass(self.render_pass, None);
+            self.device.handle().destroy_shader_module(self.vertex_shader, None);
+            self.device.handle().destroy_shader_module(self.fragment_shader, None);
+        }
+        debug!("Blur pipeline cleanup complete");
+    }
+}
+
+/// One of the two images a backdrop is blurred between
+struct BackdropImage {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    image_view: vk::ImageView,
+    framebuffer: vk::Framebuffer,
+    descriptor_set: vk::DescriptorSet,
+}
+
+/// Downsampled, blurred copy of the scene behind one surface
+///
+/// Kept between frames, so the blur can be refreshed less often than the
+/// scene is drawn.
+pub struct BlurBackdrop {
+    images: [BackdropImage; 2],
+    extent: vk::Extent2D,
+}
+
+impl BlurBackdrop {
+    /// Create the images of a backdrop, with descriptor sets from `pool`
+    /// sampling each through `sampler`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        instance: &VulkanInstance,
+        device: &VulkanDevice,
+        pipeline: &BlurPipeline,
+        pool: vk::DescriptorPool,
+        layout: vk::DescriptorSetLayout,
+        sampler: vk::Sampler,
+        extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> Result<Self> {
+        let first = BackdropImage::new(instance, device, pipeline, pool, layout, sampler, extent, format)?;
+        let second = match BackdropImage::new(instance, device, pipeline, pool, layout, sampler, extent, format) {
+            Ok(second) => second,
+            Err(e) => {
+                first.destroy(device, pool);
+                return Err(e);
+            }
+        };
+        debug!("Created {}x{} blur backdrop", extent.width, extent.height);
+        Ok(Self {
+            images: [first, second],
+            extent,
+        })
+    }
+
+    /// Size of the downsampled backdrop
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// Set sampling the blurred backdrop
+    pub fn descriptor_set(&self) -> vk::DescriptorSet {
+        self.images[0].descriptor_set
+    }
+
+    /// Record copying `source` of the scene image into the backdrop and blurring it
+    ///
+    /// The scene must be in COLOR_ATTACHMENT_OPTIMAL outside a render pass
+    /// and is left that way; `source` is the pixel rectangle as
+    /// (x0, y0, x1, y1). The blurred backdrop is left ready to be sampled.
+    pub fn record_refresh(
+        &self,
+        device: &VulkanDevice,
+        pipeline: &BlurPipeline,
+        command_buffer: vk::CommandBuffer,
+        scene: vk::Image,
+        source: [i32; 4],
+        variant: &BlurVariant,
+    ) {
+        let handle = device.handle();
+        let blurred = &self.images[0];
+        let scratch = &self.images[1];
+
+        let to_transfer = [
+            image_barrier(
+                scene,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                vk::AccessFlags::TRANSFER_READ,
+            ),
+            // The previous contents were sampled by earlier frames and are discarded
+            image_barrier(
+                blurred.image,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::AccessFlags::empty(),
+                vk::AccessFlags::TRANSFER_WRITE,
+            ),
+        ];
+        let blit = vk::ImageBlit {
+            src_subresource: color_subresource_layers(),
+            src_offsets: [
+                vk::Offset3D { x: source[0], y: source[1], z: 0 },
+                vk::Offset3D { x: source[2], y: source[3], z: 1 },
+            ],
+            dst_subresource: color_subresource_layers(),
+            dst_offsets: [
+                vk::Offset3D::default(),
+                vk::Offset3D { x: self.extent.width as i32, y: self.extent.height as i32, z: 1 },
+            ],
+        };
+        let from_transfer = [
+            image_barrier(
+                scene,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                vk::AccessFlags::TRANSFER_READ,
+                vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            ),
+            image_barrier(
+                blurred.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::SHADER_READ,
+            ),
+        ];
+
+        unsafe {
+            handle.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &to_transfer,
+            );
+            handle.cmd_blit_image(
+                command_buffer,
+                scene,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                blurred.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[blit],
+                vk::Filter::LINEAR,
+            );
+            handle.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &from_transfer,
+            );
+        }
+
+        let texel = [1.0 / self.extent.width as f32, 1.0 / self.extent.height as f32];
+        pipeline.record(
+            command_buffer,
+            scratch.framebuffer,
+            self.extent,
+            blurred.descriptor_set,
+            &BlurPushConstants::new(variant, [texel[0], 0.0]),
+        );
+        pipeline.record(
+            command_buffer,
+            blurred.framebuffer,
+            self.extent,
+            scratch.descriptor_set,
+            &BlurPushConstants::new(variant, [0.0, texel[1]]),
+        );
+    }
+
+    /// Destroy the backdrop's Vulkan objects, returning its sets to `pool`
+    pub fn destroy(&self, device: &VulkanDevice, pool: vk::DescriptorPool) {
+        for image in &self.images {
+            image.destroy(device, pool);
+        }
+    }
+}
+
+impl BackdropImage {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        instance: &VulkanInstance,
+        device: &VulkanDevice,
+        pipeline: &BlurPipeline,
+        pool: vk::DescriptorPool,
+        layout: vk::DescriptorSetLayout,
+        sampler: vk::Sampler,
+        extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> Result<Self> {
+        let image_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            extent: vk::Extent3D { width: extent.width, height: extent.height, depth: 1 },
+            mip_levels: 1,
+            array_layers: 1,
+            format,
+            tiling: vk::ImageTiling::OPTIMAL,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            usage: vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::TRANSFER_DST
+                | vk::ImageUsageFlags::SAMPLED,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            samples: vk::SampleCountFlags::TYPE_1,
+            ..Default::default()
+        };
+
+        let image = unsafe { device.handle().create_image(&image_info, None)? };
+        let memory_requirements = unsafe { device.handle().get_image_memory_requirements(image) };
+
+        let memory_properties = unsafe {
+            instance.handle().get_physical_device_memory_properties(device.physical_device())
+        };
+        let memory_type_index = (0..memory_properties.memory_type_count)
+            .find(|&i| {
+                (memory_requirements.memory_type_bits & (1 << i)) != 0
+                    && memory_properties.memory_types[i as usize]
+                        .property_flags
+                        .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+            })
+            .ok_or_else(|| CompositorError::graphics("Failed to find memory type for blur backdrop"))?;
+
+        let alloc_info = vk::MemoryAllocateInfo {
+            allocation_size: memory_requirements.size,
+            memory_type_index,
+            ..Default::default()
+        };
+
+        let memory = unsafe { device.handle().allocate_memory(&alloc_info, None)? };
+        unsafe { device.handle().bind_image_memory(image, memory, 0)? };
+
+        let image_view_info = vk::ImageViewCreateInfo {
+            image,
+            view_type: vk::ImageViewType::TYPE_2D,
+            format,
+            subresource_range: color_subresource_range(),
+            ..Default::default()
+        };
+        let image_view = unsafe { device.handle().create_image_view(&image_view_info, None)? };
+
+        let attachments = [image_view];
+        let framebuffer_info = vk::FramebufferCreateInfo {
+            render_pass: pipeline.render_pass(),
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            width: extent.width,
+            height: extent.height,
+            layers: 1,
+            ..Default::default()
+        };
+        let framebuffer = unsafe { device.handle().create_framebuffer(&framebuffer_info, None)? };
+
+        let layouts = [layout];
+        let set_info = vk::DescriptorSetAllocateInfo {
+            descriptor_pool: pool,
+            descriptor_set_count: layouts.len() as u32,
+            p_set_layouts: layouts.as_ptr(),
+            ..Default::default()
+        };
+        let descriptor_set = unsafe {
+            device.handle().allocate_descriptor_sets(&set_info)
+                .map_err(|e| CompositorError::graphics(format!("Failed to allocate blur descriptor set: {}", e)))?[0]
+        };
+        let descriptor_image_info = vk::DescriptorImageInfo {
+            sampler,
+            image_view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        let write = vk::WriteDescriptorSet {
+            dst_set: descriptor_set,
+            dst_binding: 0,
+            descriptor_count: 1,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            p_image_info: &descriptor_image_info,
+            ..Default::default()
+        };
+        unsafe {
+            device.handle().update_descriptor_sets(&[write], &[]);
+        }
+
+        Ok(Self {
+            image,
+            memory,
+            image_view,
+            framebuffer,
+            descriptor_set,
+        })
+    }
+
+    fn destroy(&self, device: &VulkanDevice, pool: vk::DescriptorPool) {
+        unsafe {
+            if let Err(e) = device.handle().free_descriptor_sets(pool, &[self.descriptor_set]) {
+                warn!("Failed to free blur descriptor set: {}", e);
+            }
+            device.handle().destroy_framebuffer(self.framebuffer, None);
+            device.handle().destroy_image_view(self.image_view, None);
+            device.handle().destroy_image(self.image, None);
+            device.handle().free_memory(self.memory, None);
+        }
+    }
+}
+
+fn image_barrier(
+    image: vk::Image,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+    src_access_mask: vk::AccessFlags,
+    dst_access_mask: vk::AccessFlags,
+) -> vk::ImageMemoryBarrier {
+    vk::ImageMemoryBarrier {
+        old_layout,
+        new_layout,
+        src_access_mask,
+        dst_access_mask,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        image,
+        subresource_range: color_subresource_range(),
+        ..Default::default()
+    }
+}
+
+fn color_subresource_range() -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+fn color_subresource_layers() -> vk::ImageSubresourceLayers {
+    vk::ImageSubresourceLayers {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        mip_level: 0,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
@@ -7,10 +7,52 @@ use ash::vk;
 use compositor_utils::prelude::*;
 use crate::{VulkanDevice, VulkanInstance, SurfaceRenderer, SurfacePipeline, SurfaceTexture, SurfacePushConstants};
 use crate::surface_renderer::{SurfaceBuffer, ShmFormat};
-use std::collections::HashMap;
+use crate::dimming::{DimmingSettings, FocusDimmer};
+use crate::blur::{BlurQuality, BlurState, BlurVariant, SurfaceBlurRequest};
+use crate::blur_pipeline::{BlurBackdrop, BlurPipeline};
+use crate::neomorphism::{NeomorphicEffect, NeomorphicParams};
+use crate::render_scale::{clamp_render_scale, scaled_extent, ScaledTarget};
+use crate::frame_graph::{FrameGraph, FrameResource, PassDesc, PassKind, ResourceUsage};
+use crate::sync::{CompletionFence, TimelineSemaphore};
+use crate::memory::{MemoryBudgetMonitor, MemoryPressure, MemoryUsage};
+use crate::mipmap::MIPMAP_SCALE_THRESHOLD;
+use crate::antialiasing::{supported_sample_count, UiAntialiasing, UiMsaaTarget};
+use crate::pipeline_cache::{default_cache_path, PipelineCache};
+use crate::readback::{PixelReadback, ReadbackPixels, ReadbackRegion};
+use crate::upload_queue::{QueuedUpload, UploadPriority, UploadQueue};
+use crate::ui::{UiFilter, UiPrimitive};
+use crate::ui_pipeline::{UiPipeline, UiPushConstants};
+use compositor_utils::math::Rect;
+use std::collections::{HashMap, HashSet};
+use std::os::fd::OwnedFd;
+use std::time::{Duration, Instant};
+
+/// Time without frames after which idle maintenance runs
+pub const IDLE_MAINTENANCE_DELAY: Duration = Duration::from_secs(5);
+
+/// Key of the white texture bound while drawing UI shapes; image IDs start at 1
+const UI_WHITE_IMAGE: u32 = 0;
+
+/// Part of the frame a blurred backdrop was captured from
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BlurCapture {
+    /// Frame pixels as (x0, y0, x1, y1)
+    source: [i32; 4],
+    /// The same area in global compositor coordinates
+    rect: Rect,
+}
+
+/// Image the surfaces are composited into this frame
+#[derive(Debug, Clone, Copy)]
+struct SceneTarget {
+    image: vk::Image,
+    framebuffer: vk::Framebuffer,
+    extent: vk::Extent2D,
+}
 
 /// Main compositor renderer that coordinates all rendering operations
 pub struct CompositorRenderer {
+    instance: VulkanInstance,
     device: VulkanDevice,
     surface_renderer: SurfaceRenderer,
     surface_pipeline: Option<SurfacePipeline>,
@@ -19,16 +61,123 @@ pub struct CompositorRenderer {
     command_buffers: Vec<vk::CommandBuffer>,
     command_pool: vk::CommandPool,
     
+    // Frame ordering: each submission signals the next timeline value
+    timeline: TimelineSemaphore,
+    timeline_value: u64,
+    /// Timeline value signalled by the last submission of each command buffer
+    command_buffer_values: Vec<u64>,
+    /// Exported after each submission where the driver supports it
+    completion_fence: Option<CompletionFence>,
+    /// Sync file of the last submission, until taken by the render loop
+    frame_completion: Option<OwnedFd>,
+    
     // Rendering state
     swapchain_extent: vk::Extent2D,
     swapchain_images: Vec<vk::Image>,
     swapchain_image_views: Vec<vk::ImageView>,
+    swapchain_format: vk::Format,
+    
+    // Reduced-resolution rendering, upscaled onto the swapchain image
+    render_scale: f32,
+    scaled_target: Option<ScaledTarget>,
+    /// Whether the swapchain images can be copied from, which blur needs
+    /// when rendering straight to them
+    swapchain_readable: bool,
     
     // Per-frame rendering resources
     vertex_buffers: HashMap<u32, vk::Buffer>,
     vertex_buffer_memories: HashMap<u32, vk::DeviceMemory>,
     descriptor_pool: Option<vk::DescriptorPool>,
     descriptor_sets: HashMap<u32, vk::DescriptorSet>,
+    // Sets of removed surfaces, freed once no frame in flight uses them
+    stale_descriptor_sets: Vec<vk::DescriptorSet>,
+    
+    // Pipelines compiled in earlier sessions, saved while idle
+    pipeline_cache: PipelineCache,
+    
+    // Idle maintenance runs once per idle period
+    last_frame: Instant,
+    idle_maintained: bool,
+    
+    // Surface IDs from bottom to top
+    stacking_order: Vec<u32>,
+    // Surfaces not shown right now, e.g. on another workspace
+    hidden_surfaces: HashSet<u32>,
+    // Surfaces rendered at another scale than their output's, from per-app overrides
+    rescaled_surfaces: HashSet<u32>,
+    // Surfaces the output being drawn does not show, e.g. windows on other outputs
+    offscreen_surfaces: HashSet<u32>,
+    
+    // Committed surface content waiting for the next frame
+    uploads: UploadQueue,
+    // Content of hidden surfaces moved to host memory under memory pressure,
+    // uploaded again once they are shown
+    evicted_textures: HashMap<u32, QueuedUpload>,
+    frame_started: Option<Instant>,
+    
+    // Active-window highlight
+    dimmer: FocusDimmer,
+    
+    // Background blur behind surfaces
+    blur: BlurState,
+    blur_pipeline: Option<BlurPipeline>,
+    blur_backdrops: HashMap<u32, BlurBackdrop>,
+    /// Where each backdrop was last captured from
+    blur_captures: HashMap<u32, BlurCapture>,
+    /// Surfaces whose backdrop is captured and blurred again this frame
+    blur_refreshes: HashSet<u32>,
+    /// Backdrops no longer drawn, destroyed once the timeline passes the value
+    stale_blur_backdrops: Vec<(BlurBackdrop, u64)>,
+    
+    // Shadows and highlights of the neomorphic rendering mode
+    neomorphism: NeomorphicEffect,
+    
+    // GPU memory budget tracking; expensive effects are skipped under pressure
+    memory_monitor: MemoryBudgetMonitor,
+    effects_degraded: bool,
+    
+    // Antialiasing of compositor-drawn edges
+    ui_antialiasing: UiAntialiasing,
+    ui_samples: vk::SampleCountFlags,
+    /// Layer UI is drawn into with MSAA, and the pipeline blending it over the scene
+    ui_msaa: Option<UiMsaaTarget>,
+    ui_layer_pipeline: Option<UiPipeline>,
+    /// Layers no longer drawn, destroyed once the timeline passes the value
+    stale_ui_msaa: Vec<(UiMsaaTarget, u64)>,
+    corner_radius: f32,
+    /// Corner radius of surfaces whose class overrides `corner_radius`
+    surface_corner_radii: HashMap<u32, f32>,
+    /// Width and color of borders drawn around surfaces, e.g. urgent windows
+    surface_borders: HashMap<u32, (f32, [f32; 4])>,
+    /// Drop shadow intensity of surfaces that cast one
+    surface_shadows: HashMap<u32, f32>,
+    /// Factor every drop shadow's intensity is scaled by
+    shadow_scale: f32,
+    /// Where surfaces are in global compositor coordinates
+    surface_geometry: HashMap<u32, Rect>,
+    
+    // Opaque color the frame is cleared to, showing where no wallpaper covers it
+    background_color: [f32; 4],
+    /// Wallpapers drawn over the background color below every surface, as
+    /// UI primitives sharing the UI images
+    wallpaper: Vec<UiPrimitive>,
+    
+    // Regions of frames copied back to the CPU, e.g. for the color picker
+    readback: PixelReadback,
+    
+    // Compositor-drawn UI, drawn over the surfaces by the UI pass
+    ui_pipeline: Option<UiPipeline>,
+    /// Resumes drawing into the scene, keeping its pixels: for the UI pass,
+    /// and for surfaces after blurring what is behind them
+    load_render_pass: Option<vk::RenderPass>,
+    ui: Vec<UiPrimitive>,
+    /// Images of UI primitives by image ID, with their descriptor sets
+    ui_textures: SurfaceRenderer,
+    ui_descriptor_sets: HashMap<u32, vk::DescriptorSet>,
+    /// Images no longer drawn, freed once the timeline passes the value
+    stale_ui_images: Vec<(u32, u64)>,
+    /// Part of the compositor space the output being drawn shows
+    output_region: Option<Rect>,
 }
 
 impl CompositorRenderer {
@@ -39,13 +188,33 @@ impl CompositorRenderer {
     ) -> Result<Self> {
         info!("Creating compositor renderer");
         
+        // Track device memory against the driver-reported budget
+        let memory_monitor = MemoryBudgetMonitor::new(instance.clone(), &device);
+        
         // Create surface renderer for texture management
         let surface_renderer = SurfaceRenderer::new(instance.clone(), device.clone())?;
         
         // Create command pool for rendering operations
         let command_pool = Self::create_command_pool(&device)?;
         
+        // Timeline semaphore ordering frames on the GPU
+        let timeline = TimelineSemaphore::new(&device, 0)?;
+        
+        // Sync files let the render loop wake up when frames finish
+        let completion_fence = if device.supports_fence_fd_export() {
+            Some(CompletionFence::new(&instance, &device)?)
+        } else {
+            None
+        };
+        
+        let pipeline_cache = PipelineCache::new(device.clone(), default_cache_path())?;
+        
+        let readback = PixelReadback::new(instance.clone(), device.clone());
+        
+        let ui_textures = SurfaceRenderer::new(instance.clone(), device.clone())?;
+        
         Ok(Self {
+            instance,
             device,
             surface_renderer,
             surface_pipeline: None,
@@ -53,13 +222,64 @@ impl CompositorRenderer {
             framebuffers: Vec::new(),
             command_buffers: Vec::new(),
             command_pool,
+            timeline,
+            timeline_value: 0,
+            command_buffer_values: Vec::new(),
+            completion_fence,
+            frame_completion: None,
             swapchain_extent: vk::Extent2D { width: 0, height: 0 },
             swapchain_images: Vec::new(),
             swapchain_image_views: Vec::new(),
+            swapchain_format: vk::Format::UNDEFINED,
+            render_scale: 1.0,
+            scaled_target: None,
+            swapchain_readable: false,
             vertex_buffers: HashMap::new(),
             vertex_buffer_memories: HashMap::new(),
             descriptor_pool: None,
             descriptor_sets: HashMap::new(),
+            stale_descriptor_sets: Vec::new(),
+            pipeline_cache,
+            last_frame: Instant::now(),
+            idle_maintained: false,
+            stacking_order: Vec::new(),
+            hidden_surfaces: HashSet::new(),
+            rescaled_surfaces: HashSet::new(),
+            offscreen_surfaces: HashSet::new(),
+            uploads: UploadQueue::new(),
+            evicted_textures: HashMap::new(),
+            frame_started: None,
+            dimmer: FocusDimmer::default(),
+            blur: BlurState::new(),
+            blur_pipeline: None,
+            blur_backdrops: HashMap::new(),
+            blur_captures: HashMap::new(),
+            blur_refreshes: HashSet::new(),
+            stale_blur_backdrops: Vec::new(),
+            neomorphism: NeomorphicEffect::new(),
+            memory_monitor,
+            effects_degraded: false,
+            ui_antialiasing: UiAntialiasing::default(),
+            ui_samples: vk::SampleCountFlags::TYPE_1,
+            ui_msaa: None,
+            ui_layer_pipeline: None,
+            stale_ui_msaa: Vec::new(),
+            corner_radius: 0.0,
+            surface_corner_radii: HashMap::new(),
+            surface_borders: HashMap::new(),
+            surface_shadows: HashMap::new(),
+            shadow_scale: 1.0,
+            surface_geometry: HashMap::new(),
+            background_color: [0.0, 0.0, 0.0, 1.0],
+            wallpaper: Vec::new(),
+            readback,
+            ui_pipeline: None,
+            load_render_pass: None,
+            ui: Vec::new(),
+            ui_textures,
+            ui_descriptor_sets: HashMap::new(),
+            stale_ui_images: Vec::new(),
+            output_region: None,
         })
     }
     
@@ -77,16 +297,55 @@ impl CompositorRenderer {
         self.swapchain_images = swapchain_images;
         self.swapchain_image_views = swapchain_image_views;
         self.swapchain_extent = swapchain_extent;
+        self.swapchain_format = swapchain_format;
         
-        // Create render pass
-        let render_pass = Self::create_render_pass(&self.device, swapchain_format)?;
+        // Create render pass; the frame graph moves the scene on to
+        // presentation or the upscale blit afterwards
+        let render_pass = Self::create_render_pass(
+            &self.device,
+            swapchain_format,
+            vk::AttachmentLoadOp::CLEAR,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        )?;
         self.render_pass = Some(render_pass);
         
+        // UI is drawn over the composited surfaces, keeping their pixels
+        let load_render_pass = Self::create_render_pass(
+            &self.device,
+            swapchain_format,
+            vk::AttachmentLoadOp::LOAD,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        )?;
+        self.load_render_pass = Some(load_render_pass);
+        let ui_pipeline = UiPipeline::new(
+            self.device.clone(),
+            load_render_pass,
+            self.pipeline_cache.handle(),
+            vk::SampleCountFlags::TYPE_1,
+        )?;
+        
+        // Blurred backdrops are sampled through the UI pipeline's set layout
+        self.blur_pipeline = Some(BlurPipeline::new(
+            self.device.clone(),
+            swapchain_format,
+            self.pipeline_cache.handle(),
+            ui_pipeline.descriptor_set_layout(),
+        )?);
+        self.ui_pipeline = Some(ui_pipeline);
+        self.ui_layer_pipeline = Some(UiPipeline::premultiplied(
+            self.device.clone(),
+            load_render_pass,
+            self.pipeline_cache.handle(),
+        )?);
+        
         // Create surface pipeline
         let surface_pipeline = SurfacePipeline::new(
-            &VulkanInstance::new()?, // TODO: Store instance reference
+            &self.instance,
             self.device.clone(),
             render_pass,
+            self.pipeline_cache.handle(),
         )?;
         self.surface_pipeline = Some(surface_pipeline);
         
@@ -99,10 +358,48 @@ impl CompositorRenderer {
         // Create descriptor pool
         self.create_descriptor_pool()?;
         
+        // Recreate the reduced-resolution target for the new extent
+        self.recreate_scaled_target()?;
+        
         info!("Compositor renderer initialized successfully");
         Ok(())
     }
     
+    /// Switch to a recreated swapchain of the same format, e.g. after a
+    /// present mode change
+    ///
+    /// No frame may be in flight. Framebuffers are rebuilt for the new
+    /// images; the render pass and pipelines are kept.
+    pub fn recreate_swapchain(
+        &mut self,
+        swapchain_images: Vec<vk::Image>,
+        swapchain_image_views: Vec<vk::ImageView>,
+        swapchain_extent: vk::Extent2D,
+    ) -> Result<()> {
+        for framebuffer in self.framebuffers.drain(..) {
+            unsafe {
+                self.device.handle().destroy_framebuffer(framebuffer, None);
+            }
+        }
+        
+        self.swapchain_images = swapchain_images;
+        self.swapchain_image_views = swapchain_image_views;
+        self.swapchain_extent = swapchain_extent;
+        self.create_framebuffers()?;
+        
+        // One command buffer per image
+        if self.command_buffers.len() != self.framebuffers.len() {
+            unsafe {
+                self.device.handle().free_command_buffers(self.command_pool, &self.command_buffers);
+            }
+            self.create_command_buffers()?;
+        }
+        
+        self.recreate_scaled_target()?;
+        debug!("Compositor renderer switched to recreated swapchain");
+        Ok(())
+    }
+    
     /// Render a frame with all visible surfaces
     pub fn render_frame(
         &mut self,
@@ -110,6 +407,17 @@ impl CompositorRenderer {
         image_index: u32,
     ) -> Result<vk::CommandBuffer> {
         let command_buffer = self.command_buffers[frame_index];
+        self.frame_started = Some(Instant::now());
+        
+        // Wait until the GPU has finished the last submission of this command buffer
+        if let Some(&value) = self.command_buffer_values.get(frame_index) {
+            self.timeline.wait(value, u64::MAX)?;
+        }
+        
+        // Upload content committed since the last frame, limited when over budget
+        self.flush_uploads()?;
+        self.prepare_ui_images()?;
+        self.prepare_ui_msaa()?;
         
         // Begin command buffer recording
         let begin_info = vk::CommandBufferBeginInfo {
@@ -121,22 +429,403 @@ impl CompositorRenderer {
             self.device.handle().begin_command_buffer(command_buffer, &begin_info)?;
         }
         
-        // Begin render pass
-        self.begin_render_pass(command_buffer, image_index)?;
+        // Advance focus dimming transitions
+        let surface_ids: Vec<u32> = self.surface_renderer.get_all_textures().map(|(id, _)| id).collect();
+        self.dimmer.advance(surface_ids.into_iter(), std::time::Instant::now());
+        
+        // React to memory pressure before allocating for this frame
+        self.check_memory_budget(std::time::Instant::now());
+        
+        self.blur.begin_frame();
+        self.prepare_blur_backdrops()?;
+        
+        // Record all passes with the barriers between them
+        let frame_graph = self.build_frame_graph();
+        frame_graph.record(
+            &self.device,
+            command_buffer,
+            |resource| self.frame_image(resource, image_index),
+            |pass, command_buffer| self.record_pass(pass, command_buffer, image_index),
+        )?;
         
-        // Render all surfaces
-        self.render_surfaces(command_buffer)?;
+        // Copy a requested region of the finished frame for readback
+        self.readback.record(
+            command_buffer,
+            self.swapchain_images[image_index as usize],
+            self.swapchain_extent,
+            self.swapchain_format,
+        )?;
         
-        // End render pass and command buffer
         unsafe {
-            self.device.handle().cmd_end_render_pass(command_buffer);
             self.device.handle().end_command_buffer(command_buffer)?;
         }
         
         Ok(command_buffer)
     }
     
-    /// Update surface texture from Wayland client
+    /// Submit a recorded frame to the graphics queue
+    ///
+    /// Waits on `wait_semaphore` (e.g. image acquired) and signals
+    /// `signal_semaphore` (e.g. ready to present); null handles are skipped.
+    /// Returns the timeline value signalled when the frame completes.
+    pub fn submit_frame(
+        &mut self,
+        frame_index: usize,
+        wait_semaphore: vk::Semaphore,
+        signal_semaphore: vk::Semaphore,
+    ) -> Result<u64> {
+        let signal_value = self.timeline_value + 1;
+        
+        let mut wait_semaphores = Vec::new();
+        let mut wait_stages = Vec::new();
+        if wait_semaphore != vk::Semaphore::null() {
+            wait_semaphores.push(wait_semaphore);
+            wait_stages.push(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT);
+        }
+        let wait_values = vec![0; wait_semaphores.len()];
+        
+        // Binary semaphores ignore their value
+        let mut signal_semaphores = vec![self.timeline.handle()];
+        if signal_semaphore != vk::Semaphore::null() {
+            signal_semaphores.push(signal_semaphore);
+        }
+        let mut signal_values = vec![0; signal_semaphores.len()];
+        signal_values[0] = signal_value;
+        
+        let timeline_info = vk::TimelineSemaphoreSubmitInfo {
+            wait_semaphore_value_count: wait_values.len() as u32,
+            p_wait_semaphore_values: wait_values.as_ptr(),
+            signal_semaphore_value_count: signal_values.len() as u32,
+            p_signal_semaphore_values: signal_values.as_ptr(),
+            ..Default::default()
+        };
+        
+        let command_buffers = [self.command_buffers[frame_index]];
+        let submit_info = vk::SubmitInfo {
+            p_next: &timeline_info as *const _ as *const std::ffi::c_void,
+            wait_semaphore_count: wait_semaphores.len() as u32,
+            p_wait_semaphores: wait_semaphores.as_ptr(),
+            p_wait_dst_stage_mask: wait_stages.as_ptr(),
+            command_buffer_count: command_buffers.len() as u32,
+            p_command_buffers: command_buffers.as_ptr(),
+            signal_semaphore_count: signal_semaphores.len() as u32,
+            p_signal_semaphores: signal_semaphores.as_ptr(),
+            ..Default::default()
+        };
+        
+        let fence = self.completion_fence.as_ref().map_or(vk::Fence::null(), |fence| fence.handle());
+        unsafe {
+            self.device.handle().queue_submit(self.device.graphics_queue(), &[submit_info], fence)
+                .map_err(|e| CompositorError::graphics(format!("Failed to submit frame: {}", e)))?;
+        }
+        
+        self.frame_completion = match self.completion_fence.as_ref().map(|fence| fence.export()) {
+            Some(Ok(fd)) => fd,
+            Some(Err(e)) => {
+                warn!("{}", e);
+                None
+            }
+            None => None,
+        };
+        self.timeline_value = signal_value;
+        self.command_buffer_values[frame_index] = signal_value;
+        self.readback.submitted(signal_value);
+        self.last_frame = Instant::now();
+        self.idle_maintained = false;
+        if let Some(started) = self.frame_started.take() {
+            self.uploads.frame_finished(started.elapsed());
+        }
+        Ok(signal_value)
+    }
+    
+    /// Compact GPU memory once no frame was submitted for `IDLE_MAINTENANCE_DELAY`
+    ///
+    /// Call while no output has a frame scheduled. Frees staging memory and
+    /// cached mip chains, returns unused command and descriptor pool memory
+    /// and saves the pipeline cache, so long sessions do not accumulate GPU
+    /// memory. Runs at most once per idle period; returns whether it ran.
+    pub fn maintain_if_idle(&mut self, now: Instant) -> Result<bool> {
+        if self.idle_maintained || now.duration_since(self.last_frame) < IDLE_MAINTENANCE_DELAY {
+            return Ok(false);
+        }
+        self.idle_maintained = true;
+        
+        // Frames in flight may still use the resources being freed
+        self.timeline.wait(self.timeline_value, u64::MAX)?;
+        
+        self.surface_renderer.trim()?;
+        self.compact_descriptor_pool()?;
+        unsafe {
+            self.device.handle().trim_command_pool(self.command_pool, vk::CommandPoolTrimFlags::empty());
+        }
+        if let Err(e) = self.pipeline_cache.save() {
+            warn!("{}", e);
+        }
+        debug!("Idle GPU maintenance complete");
+        Ok(true)
+    }
+    
+    /// Free descriptor sets of removed surfaces, resetting the pool when none are left
+    ///
+    /// Resetting returns the pool to a single free block, undoing
+    /// fragmentation from surfaces coming and going.
+    fn compact_descriptor_pool(&mut self) -> Result<()> {
+        let Some(pool) = self.descriptor_pool else {
+            return Ok(());
+        };
+        if self.descriptor_sets.is_empty()
+            && self.ui_descriptor_sets.is_empty()
+            && self.blur_backdrops.is_empty()
+            && self.stale_blur_backdrops.is_empty()
+            && self.ui_msaa.is_none()
+            && self.stale_ui_msaa.is_empty()
+        {
+            self.stale_descriptor_sets.clear();
+            unsafe {
+                self.device.handle().reset_descriptor_pool(pool, vk::DescriptorPoolResetFlags::empty())?;
+            }
+        } else if !self.stale_descriptor_sets.is_empty() {
+            unsafe {
+                self.device.handle().free_descriptor_sets(pool, &self.stale_descriptor_sets)?;
+            }
+            self.stale_descriptor_sets.clear();
+        }
+        Ok(())
+    }
+    
+    /// Re-arm frame synchronization after the system resumed from suspend
+    ///
+    /// Waits for the GPU to go idle, which fails if the device was lost
+    /// while suspended. The frame timeline is recreated at the last
+    /// submitted value, so work the driver dropped cannot leave a value
+    /// unsignalled that the next frame would wait on forever.
+    pub fn resume(&mut self) -> Result<()> {
+        unsafe { self.device.handle().device_wait_idle() }
+            .map_err(|e| CompositorError::graphics(format!("GPU did not survive suspend: {}", e)))?;
+        self.timeline = TimelineSemaphore::new(&self.device, self.timeline_value)?;
+        self.frame_completion = None;
+        self.frame_started = None;
+        self.last_frame = Instant::now();
+        Ok(())
+    }
+    
+    /// Timeline value of the most recently submitted frame
+    pub fn last_submitted_value(&self) -> u64 {
+        self.timeline_value
+    }
+    
+    /// Sync file that becomes readable once the last submitted frame
+    /// completes, or `None` if it already completed or fences cannot be
+    /// exported
+    pub fn take_frame_completion(&mut self) -> Option<OwnedFd> {
+        self.frame_completion.take()
+    }
+    
+    /// Most recently queried GPU memory usage, for metrics and the HUD
+    pub fn memory_usage(&self) -> &MemoryUsage {
+        self.memory_monitor.usage()
+    }
+    
+    /// Poll the memory budget and shed memory when nearing it
+    fn check_memory_budget(&mut self, now: std::time::Instant) {
+        let Some(pressure) = self.memory_monitor.poll(now) else {
+            return;
+        };
+        
+        let usage = self.memory_monitor.usage();
+        info!(
+            "GPU memory pressure now {:?} ({} / {} MiB device-local)",
+            pressure,
+            usage.device_local_usage() / (1024 * 1024),
+            usage.device_local_budget() / (1024 * 1024),
+        );
+        
+        match pressure {
+            MemoryPressure::Normal => {
+                self.effects_degraded = false;
+            }
+            MemoryPressure::High | MemoryPressure::Critical => {
+                // Staging memory is recreated on the next upload
+                if let Err(e) = self.surface_renderer.release_staging_buffer() {
+                    warn!("Failed to release staging buffer: {}", e);
+                }
+                if let Err(e) = self.surface_renderer.release_mipmaps() {
+                    warn!("Failed to release preview mip chains: {}", e);
+                }
+                self.effects_degraded = true;
+                if let Err(e) = self.evict_hidden_textures() {
+                    warn!("Failed to evict textures of hidden surfaces: {}", e);
+                }
+            }
+        }
+    }
+    
+    /// Move the textures of surfaces no output shows to host memory
+    ///
+    /// Their content is uploaded again when they are shown, see `flush_uploads`.
+    fn evict_hidden_textures(&mut self) -> Result<()> {
+        let hidden: Vec<u32> = self.hidden_surfaces
+            .iter()
+            .copied()
+            .filter(|&surface_id| self.surface_renderer.get_surface_texture(surface_id).is_some())
+            .collect();
+        if hidden.is_empty() {
+            return Ok(());
+        }
+        
+        // Frames in flight may still sample the textures
+        self.timeline.wait(self.timeline_value, u64::MAX)?;
+        for &surface_id in &hidden {
+            if let Some(texture) = self.surface_renderer.evict_surface_texture(surface_id)? {
+                let upload = QueuedUpload::new(texture.data, texture.width, texture.height, texture.format, Instant::now());
+                self.evicted_textures.insert(surface_id, upload);
+            }
+            if let Some(descriptor_set) = self.descriptor_sets.remove(&surface_id) {
+                self.stale_descriptor_sets.push(descriptor_set);
+            }
+        }
+        info!("Evicted textures of {} hidden surfaces", hidden.len());
+        Ok(())
+    }
+    
+    /// Declare the passes of the next frame
+    fn build_frame_graph(&self) -> FrameGraph {
+        let mut frame_graph = FrameGraph::new();
+        
+        // Surfaces render straight to the swapchain image, or offscreen when
+        // rendering at reduced resolution. Shadows and blurred backdrops are
+        // drawn in the same pass, each right before the surface over it.
+        if self.scaled_target.is_some() {
+            frame_graph.add_pass(
+                PassDesc::new(PassKind::Surface)
+                    .render_target(FrameResource::SceneColor, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            );
+            frame_graph.add_pass(
+                PassDesc::new(PassKind::Post)
+                    .uses(FrameResource::SceneColor, ResourceUsage::TransferSrc)
+                    .uses(FrameResource::Swapchain, ResourceUsage::TransferDst),
+            );
+        } else {
+            frame_graph.add_pass(
+                PassDesc::new(PassKind::Surface)
+                    .render_target(FrameResource::Swapchain, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            );
+        }
+        
+        // UI is drawn over the surfaces where the output shows any; with
+        // MSAA it goes through a layer whose render pass orders its own use
+        if self.ui_pipeline.is_some() && self.visible_ui().next().is_some() {
+            let target = if self.scaled_target.is_some() { FrameResource::SceneColor } else { FrameResource::Swapchain };
+            frame_graph.add_pass(
+                PassDesc::new(PassKind::Ui).uses(target, ResourceUsage::ColorAttachment),
+            );
+        }
+        
+        frame_graph
+    }
+    
+    /// Image backing a frame graph resource this frame
+    fn frame_image(&self, resource: FrameResource, image_index: u32) -> Option<vk::Image> {
+        match resource {
+            FrameResource::Swapchain => self.swapchain_images.get(image_index as usize).copied(),
+            FrameResource::SceneColor => self.scaled_target.as_ref().map(ScaledTarget::image),
+        }
+    }
+    
+    /// Record the work of a single pass
+    fn record_pass(&self, pass: PassKind, command_buffer: vk::CommandBuffer, image_index: u32) -> Result<()> {
+        match pass {
+            PassKind::Surface => {
+                let render_pass = self.render_pass
+                    .ok_or_else(|| CompositorError::runtime("Render pass not initialized"))?;
+                let scene = self.scene_target(image_index);
+                self.begin_render_pass(command_buffer, render_pass, scene.framebuffer, scene.extent)?;
+                
+                self.render_surfaces(command_buffer, scene)?;
+                
+                unsafe {
+                    self.device.handle().cmd_end_render_pass(command_buffer);
+                }
+            }
+            PassKind::Post => {
+                // Upscale the reduced-resolution composition onto the swapchain image
+                if let Some(ref target) = self.scaled_target {
+                    target.record_upscale(
+                        &self.device,
+                        command_buffer,
+                        self.swapchain_images[image_index as usize],
+                        self.swapchain_extent,
+                    );
+                }
+            }
+            PassKind::Ui => {
+                let render_pass = self.load_render_pass
+                    .ok_or_else(|| CompositorError::runtime("UI render pass not initialized"))?;
+                let scene = self.scene_target(image_index);
+                
+                // Multisampled UI is resolved into its layer first
+                if let Some(ref target) = self.ui_msaa {
+                    target.begin(command_buffer);
+                    self.render_ui(command_buffer, target.pipeline())?;
+                    unsafe {
+                        self.device.handle().cmd_end_render_pass(command_buffer);
+                    }
+                }
+                
+                self.begin_render_pass(command_buffer, render_pass, scene.framebuffer, scene.extent)?;
+                match (&self.ui_msaa, &self.ui_layer_pipeline) {
+                    (Some(target), Some(layer_pipeline)) => {
+                        self.draw_ui_layer(command_buffer, layer_pipeline, target.descriptor_set());
+                    }
+                    _ => {
+                        let pipeline = self.ui_pipeline.as_ref()
+                            .ok_or_else(|| CompositorError::runtime("UI pipeline not initialized"))?;
+                        self.render_ui(command_buffer, pipeline)?;
+                    }
+                }
+                
+                unsafe {
+                    self.device.handle().cmd_end_render_pass(command_buffer);
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Image the surfaces are composited into: the reduced-resolution
+    /// target if one is active, otherwise the swapchain image
+    fn scene_target(&self, image_index: u32) -> SceneTarget {
+        match &self.scaled_target {
+            Some(target) => SceneTarget {
+                image: target.image(),
+                framebuffer: target.framebuffer(),
+                extent: target.extent(),
+            },
+            None => SceneTarget {
+                image: self.swapchain_images[image_index as usize],
+                framebuffer: self.framebuffers[image_index as usize],
+                extent: self.swapchain_extent,
+            },
+        }
+    }
+    
+    /// Copy a region of the next frame back to the CPU
+    ///
+    /// The swapchain images must allow it, see `Swapchain::supports_readback`.
+    pub fn request_readback(&mut self, region: ReadbackRegion) {
+        self.readback.request(region);
+    }
+    
+    /// Pixels of the last requested region, once its frame has completed
+    pub fn take_readback(&mut self) -> Result<Option<ReadbackPixels>> {
+        let completed = self.timeline.value()?;
+        Ok(self.readback.take(completed))
+    }
+    
+    /// Queue new content of a surface from a Wayland client
+    ///
+    /// The content is uploaded when the next frame starts, replacing any
+    /// content queued earlier that was not uploaded yet.
     pub fn update_surface_texture(
         &mut self,
         surface_id: u32,
@@ -144,6 +833,104 @@ impl CompositorRenderer {
         width: u32,
         height: u32,
         format: vk::Format,
+    ) -> Result<()> {
+        debug!("Queueing surface {} texture: {}x{}", surface_id, width, height);
+        self.evicted_textures.remove(&surface_id);
+        let upload = QueuedUpload::new(buffer_data.to_vec(), width, height, format, Instant::now());
+        self.uploads.queue(surface_id, upload);
+        Ok(())
+    }
+    
+    /// Upload queued surface content, most urgent first
+    ///
+    /// While frames run over budget only part of the queue is uploaded, see
+    /// `UploadQueue::take_batch`.
+    pub fn flush_uploads(&mut self) -> Result<()> {
+        // Evicted content of surfaces shown again, unless newer content is waiting
+        let shown: Vec<u32> = self.evicted_textures
+            .keys()
+            .filter(|surface_id| !self.hidden_surfaces.contains(surface_id))
+            .copied()
+            .collect();
+        for surface_id in shown {
+            if let Some(upload) = self.evicted_textures.remove(&surface_id) {
+                if !self.uploads.contains(surface_id) {
+                    self.uploads.queue(surface_id, upload);
+                }
+            }
+        }
+        
+        if self.uploads.is_empty() {
+            return Ok(());
+        }
+        let focused = self.dimmer.focused();
+        let stacking_order = &self.stacking_order;
+        let hidden_surfaces = &self.hidden_surfaces;
+        let batch = self.uploads.take_batch(Instant::now(), |surface_id| {
+            if focused == Some(surface_id) {
+                UploadPriority::Focused
+            } else if hidden_surfaces.contains(&surface_id) {
+                UploadPriority::Hidden
+            } else {
+                // Surfaces missing from the stacking order, such as popups, are on top
+                let depth = stacking_order
+                    .iter()
+                    .rev()
+                    .position(|&id| id == surface_id)
+                    .map_or(0, |depth| depth + 1);
+                UploadPriority::Visible(depth)
+            }
+        });
+        let under_pressure = self.memory_monitor.pressure() != MemoryPressure::Normal;
+        for (surface_id, upload) in batch {
+            // Evicted surfaces stay on the host while still hidden under pressure
+            let evicted = self.surface_renderer.get_surface_texture(surface_id).is_none();
+            if under_pressure && evicted && self.hidden_surfaces.contains(&surface_id) {
+                self.evicted_textures.insert(surface_id, upload);
+                continue;
+            }
+            self.upload_surface_texture(surface_id, upload.data, upload.width, upload.height, upload.format)?;
+        }
+        Ok(())
+    }
+    
+    /// Set the time a frame may take before uploads are limited, e.g. the
+    /// refresh interval of the output being drawn; `None` never limits them
+    pub fn set_frame_budget(&mut self, budget: Option<Duration>) {
+        self.uploads.set_frame_budget(budget);
+    }
+    
+    /// Set the surfaces not shown right now, whose uploads wait while over budget
+    pub fn set_hidden_surfaces(&mut self, surface_ids: impl IntoIterator<Item = u32>) {
+        self.hidden_surfaces = surface_ids.into_iter().collect();
+    }
+    
+    /// Set the surfaces whose apps render at another scale than their output's
+    ///
+    /// Their buffers are sampled from a mip chain whenever they are drawn
+    /// scaled down at all, not only below `MIPMAP_SCALE_THRESHOLD`.
+    pub fn set_rescaled_surfaces(&mut self, surface_ids: impl IntoIterator<Item = u32>) {
+        self.rescaled_surfaces = surface_ids.into_iter().collect();
+    }
+    
+    /// Set the surfaces the next frame leaves out, as its output does not show them
+    pub fn set_offscreen_surfaces(&mut self, surface_ids: impl IntoIterator<Item = u32>) {
+        self.offscreen_surfaces = surface_ids.into_iter().collect();
+    }
+    
+    /// Queued contents replaced by newer commits before being uploaded
+    pub fn coalesced_upload_count(&self) -> u64 {
+        self.uploads.coalesced_count()
+    }
+    
+    /// Upload surface content to its texture
+    fn upload_surface_texture(
+        &mut self,
+        surface_id: u32,
+        buffer_data: Vec<u8>,
+        width: u32,
+        height: u32,
+        format: vk::Format,
     ) -> Result<()> {
         debug!("Updating surface {} texture: {}x{}", surface_id, width, height);
         
@@ -158,7 +945,7 @@ impl CompositorRenderer {
         
         // Create SurfaceBuffer
         let surface_buffer = SurfaceBuffer::Shm {
-            data: buffer_data.to_vec(),
+            data: buffer_data,
             width,
             height,
             stride: width * 4, // Assuming 4 bytes per pixel
@@ -177,11 +964,39 @@ impl CompositorRenderer {
         Ok(())
     }
     
+    /// Image view and sampler for drawing a surface at `scale`
+    ///
+    /// Thumbnails and the overview draw surfaces scaled down; those sample a
+    /// mip chain with trilinear filtering to avoid aliasing. The chain is
+    /// generated the first time a surface is drawn scaled. Rescaled surfaces
+    /// use the chain at any scale below 1.
+    pub fn surface_view_for_scale(&mut self, surface_id: u32, scale: f32) -> Result<Option<(vk::ImageView, vk::Sampler)>> {
+        let threshold = if self.rescaled_surfaces.contains(&surface_id) { 1.0 } else { MIPMAP_SCALE_THRESHOLD };
+        if scale < threshold && !self.effects_degraded {
+            if let Some(image_view) = self.surface_renderer.mipmapped_view(surface_id)? {
+                return Ok(Some((image_view, self.surface_renderer.preview_sampler())));
+            }
+        }
+        
+        // The trilinear sampler filters a single-level view bilinearly
+        Ok(self
+            .surface_renderer
+            .get_surface_texture(surface_id)
+            .map(|texture| (texture.image_view, self.surface_renderer.preview_sampler())))
+    }
+    
     /// Remove a surface and its associated resources
     pub fn remove_surface(&mut self, surface_id: u32) -> Result<()> {
         debug!("Removing surface {}", surface_id);
         
         // Remove from surface renderer
+        self.uploads.remove(surface_id);
+        self.evicted_textures.remove(&surface_id);
+        self.hidden_surfaces.remove(&surface_id);
+        self.rescaled_surfaces.remove(&surface_id);
+        self.offscreen_surfaces.remove(&surface_id);
+        self.surface_geometry.remove(&surface_id);
+        self.surface_shadows.remove(&surface_id);
         self.surface_renderer.remove_surface_texture(surface_id)?;
         
         // Clean up vertex buffer
@@ -195,9 +1010,522 @@ impl CompositorRenderer {
             }
         }
         
-        // Remove descriptor set
-        self.descriptor_sets.remove(&surface_id);
+        // Frames in flight may still bind the descriptor set
+        if let Some(descriptor_set) = self.descriptor_sets.remove(&surface_id) {
+            self.stale_descriptor_sets.push(descriptor_set);
+        }
+        
+        self.dimmer.remove_surface(surface_id);
+        self.blur.remove_surface(surface_id);
+        self.neomorphism.remove_surface(surface_id);
+        
+        Ok(())
+    }
+    
+    /// Set the order surfaces are drawn in, from bottom to top
+    pub fn set_stacking_order(&mut self, surface_ids: Vec<u32>) {
+        self.stacking_order = surface_ids;
+    }
+    
+    /// Surfaces with textures from bottom to top, leaving out those the
+    /// output does not show
+    ///
+    /// Surfaces missing from the stacking order, such as popups, are drawn
+    /// above the stacked ones.
+    fn stacked_textures(&self) -> Vec<(u32, &SurfaceTexture)> {
+        let mut textures: Vec<(u32, &SurfaceTexture)> = self
+            .surface_renderer
+            .get_all_textures()
+            .filter(|(surface_id, _)| !self.offscreen_surfaces.contains(surface_id))
+            .collect();
+        let rank = |surface_id: u32| {
+            self.stacking_order
+                .iter()
+                .position(|&id| id == surface_id)
+                .unwrap_or(self.stacking_order.len())
+        };
+        textures.sort_by_key(|&(surface_id, _)| (rank(surface_id), surface_id));
+        textures
+    }
+    
+    /// Set the focused surface used for the active-window highlight
+    pub fn set_focused_surface(&mut self, surface_id: Option<u32>) {
+        self.dimmer.set_focused(surface_id);
+    }
+    
+    /// Configure dimming of unfocused surfaces
+    pub fn set_dimming(&mut self, settings: DimmingSettings) {
+        self.dimmer.set_settings(settings);
+    }
+    
+    /// Set or clear background blur behind a surface
+    pub fn set_surface_blur(&mut self, surface_id: u32, request: Option<SurfaceBlurRequest>) {
+        self.blur.set_surface_blur(surface_id, request);
+    }
+    
+    /// Set the blur behind every surface, clearing it for surfaces left out
+    ///
+    /// Surfaces whose request is unchanged keep their blurred backdrop.
+    pub fn set_surface_blurs(&mut self, requests: impl IntoIterator<Item = (u32, SurfaceBlurRequest)>) {
+        self.blur.set_surfaces(requests);
+    }
+    
+    /// Set the blur radius of surfaces whose blur rule has none
+    pub fn set_default_blur_radius(&mut self, radius: f32) {
+        self.blur.set_default_radius(radius);
+    }
+    
+    /// Set whether the swapchain images can be copied from, see
+    /// `Swapchain::supports_readback`; blur is skipped while frames are
+    /// rendered straight to images that cannot
+    pub fn set_swapchain_readable(&mut self, readable: bool) {
+        self.swapchain_readable = readable;
+    }
+    
+    /// Set the cost of background blur
+    pub fn set_blur_quality(&mut self, quality: BlurQuality) {
+        self.blur.set_quality(quality);
+        info!("Blur quality set to {:?}", quality);
+    }
+    
+    /// Switch between glassmorphic and neomorphic rendering; the light angle
+    /// is in degrees counter-clockwise from the right
+    pub fn set_neomorphism(&mut self, enabled: bool, light_angle: f32, surface_color: [f32; 4]) {
+        self.neomorphism.set_mode(enabled, light_angle, surface_color);
+        info!("Neomorphic rendering {}", if enabled { "enabled" } else { "disabled" });
+    }
+    
+    /// Set how far each surface is extruded in the neomorphic mode
+    pub fn set_neomorphic_surfaces(&mut self, surfaces: impl IntoIterator<Item = (u32, NeomorphicParams)>) {
+        self.neomorphism.set_surfaces(surfaces);
+    }
+    
+    /// Set antialiasing quality for compositor-drawn edges
+    ///
+    /// MSAA sample counts the device does not support are lowered to the
+    /// highest supported count.
+    pub fn set_ui_antialiasing(&mut self, antialiasing: UiAntialiasing) {
+        self.ui_antialiasing = antialiasing;
+        self.ui_samples = supported_sample_count(&self.instance, &self.device, antialiasing.samples());
+        if self.ui_samples.as_raw() < antialiasing.samples() {
+            warn!(
+                "{}x MSAA not supported for UI, using {}x",
+                antialiasing.samples(),
+                self.ui_samples.as_raw(),
+            );
+        }
+        info!("UI antialiasing set to {:?}", antialiasing);
+    }
+    
+    /// Sample count for pipelines drawing compositor UI
+    pub fn ui_sample_count(&self) -> vk::SampleCountFlags {
+        self.ui_samples
+    }
+    
+    /// Set the radius of rounded window corners in pixels
+    pub fn set_corner_radius(&mut self, radius: f32) {
+        self.corner_radius = radius.max(0.0);
+    }
+    
+    /// Set the corner radius of surfaces that differ from the default, e.g.
+    /// popups and panels; other surfaces use `set_corner_radius`
+    pub fn set_surface_corner_radii(&mut self, radii: impl IntoIterator<Item = (u32, f32)>) {
+        self.surface_corner_radii = radii.into_iter().map(|(surface_id, radius)| (surface_id, radius.max(0.0))).collect();
+    }
+    
+    /// Set the surfaces drawn with a border along the inside of their edge,
+    /// with its width in pixels and color; other surfaces have none
+    pub fn set_surface_borders(&mut self, borders: impl IntoIterator<Item = (u32, f32, [f32; 4])>) {
+        self.surface_borders = borders
+            .into_iter()
+            .filter(|&(_, width, _)| width > 0.0)
+            .map(|(surface_id, width, color)| (surface_id, (width, color)))
+            .collect();
+    }
+    
+    /// Set the surfaces casting a drop shadow, with its intensity (0.0 - 1.0);
+    /// neomorphic surfaces are extruded instead
+    pub fn set_surface_shadows(&mut self, shadows: impl IntoIterator<Item = (u32, f32)>) {
+        self.surface_shadows = shadows
+            .into_iter()
+            .filter(|&(_, intensity)| intensity > 0.0)
+            .collect();
+    }
+    
+    /// Scale the intensity of every drop shadow, e.g. as shadows are tuned
+    /// over IPC; 1.0 draws them as set with `set_surface_shadows`
+    pub fn set_shadow_scale(&mut self, scale: f32) {
+        self.shadow_scale = scale.max(0.0);
+    }
+    
+    /// Set where surfaces are in global compositor coordinates, for the
+    /// effects drawn around them
+    pub fn set_surface_geometry(&mut self, geometry: impl IntoIterator<Item = (u32, Rect)>) {
+        self.surface_geometry = geometry.into_iter().collect();
+    }
+    
+    /// Set the color shown where no surface or wallpaper covers the output
+    ///
+    /// Alpha is ignored; the background is always opaque.
+    pub fn set_background_color(&mut self, color: [f32; 4]) {
+        self.background_color = [color[0], color[1], color[2], 1.0];
+    }
+    
+    /// Set the UI drawn over the surfaces, back to front, in global
+    /// compositor coordinates
+    pub fn set_ui(&mut self, primitives: Vec<UiPrimitive>) {
+        self.ui = primitives;
+    }
+    
+    /// Set the wallpapers drawn below the surfaces, back to front, in
+    /// global compositor coordinates
+    pub fn set_wallpaper(&mut self, primitives: Vec<UiPrimitive>) {
+        self.wallpaper = primitives;
+    }
+    
+    /// Set the part of the compositor space the next frame shows, i.e. the
+    /// geometry of the output being drawn
+    ///
+    /// Until set, the frame shows the swapchain extent from the origin.
+    pub fn set_output_region(&mut self, region: Rect) {
+        self.output_region = Some(region);
+    }
+    
+    /// Pixels of the presented image showing `rect` of compositor space,
+    /// as (x, y, width, height); `None` if a change there may also show
+    /// elsewhere, as blurred backdrops sample what is behind their surfaces
+    pub fn damage_rect(&self, rect: Rect) -> Option<[i32; 4]> {
+        if !self.blur_backdrops.is_empty() {
+            return None;
+        }
+        let region = self.output_region();
+        let scale_x = self.swapchain_extent.width as f32 / region.width;
+        let scale_y = self.swapchain_extent.height as f32 / region.height;
+        let x0 = ((rect.x - region.x) * scale_x).floor() as i32;
+        let y0 = ((rect.y - region.y) * scale_y).floor() as i32;
+        let x1 = ((rect.x + rect.width - region.x) * scale_x).ceil() as i32;
+        let y1 = ((rect.y + rect.height - region.y) * scale_y).ceil() as i32;
+        Some([x0, y0, x1 - x0, y1 - y0])
+    }
+    
+    /// Part of the compositor space the next frame shows
+    fn output_region(&self) -> Rect {
+        self.output_region.unwrap_or_else(|| {
+            Rect::from_size(self.swapchain_extent.width as f32, self.swapchain_extent.height as f32)
+        })
+    }
+    
+    /// UI primitives overlapping the output being drawn
+    fn visible_ui(&self) -> impl Iterator<Item = &UiPrimitive> {
+        let region = self.output_region();
+        self.ui.iter().filter(move |primitive| primitive.bounds().intersects(&region))
+    }
+    
+    /// Upload images of the UI that are new and free those no longer drawn
+    fn prepare_ui_images(&mut self) -> Result<()> {
+        let (Some(pipeline), Some(pool)) = (self.ui_pipeline.as_ref(), self.descriptor_pool) else {
+            return Ok(());
+        };
+        let layout = pipeline.descriptor_set_layout();
+        let samplers = [pipeline.sampler(UiFilter::Linear), pipeline.sampler(UiFilter::Nearest)];
+        let sampler = |filter| if filter == UiFilter::Linear { samplers[0] } else { samplers[1] };
+        
+        // Frames up to the last submitted one may still sample dropped images
+        let drawn: HashSet<u32> = self.wallpaper.iter()
+            .chain(&self.ui)
+            .filter_map(UiPrimitive::image)
+            .map(|image| image.id())
+            .collect();
+        for &image_id in self.ui_descriptor_sets.keys() {
+            if image_id != UI_WHITE_IMAGE && !drawn.contains(&image_id)
+                && !self.stale_ui_images.iter().any(|&(id, _)| id == image_id)
+            {
+                self.stale_ui_images.push((image_id, self.timeline_value));
+            }
+        }
+        let completed = self.timeline.value()?;
+        let mut freed = Vec::new();
+        self.stale_ui_images.retain(|&(image_id, value)| {
+            if drawn.contains(&image_id) {
+                return false;
+            }
+            if value <= completed {
+                freed.push(image_id);
+                return false;
+            }
+            true
+        });
+        for image_id in freed {
+            self.ui_textures.remove_surface_texture(image_id)?;
+            if let Some(descriptor_set) = self.ui_descriptor_sets.remove(&image_id) {
+                unsafe {
+                    self.device.handle().free_descriptor_sets(pool, &[descriptor_set])?;
+                }
+            }
+        }
+        
+        // Shapes sample a white pixel, leaving their color as is
+        if !self.ui_descriptor_sets.contains_key(&UI_WHITE_IMAGE) {
+            let set = self.upload_ui_image(pool, layout, sampler(UiFilter::Nearest), UI_WHITE_IMAGE, 1, 1, vec![255; 4])?;
+            self.ui_descriptor_sets.insert(UI_WHITE_IMAGE, set);
+        }
+        
+        let new_images: Vec<_> = self.wallpaper.iter()
+            .chain(&self.ui)
+            .filter_map(UiPrimitive::image)
+            .filter(|image| !self.ui_descriptor_sets.contains_key(&image.id()))
+            .cloned()
+            .collect();
+        for image in new_images {
+            if self.ui_descriptor_sets.contains_key(&image.id()) {
+                continue;
+            }
+            let set = self.upload_ui_image(
+                pool,
+                layout,
+                sampler(image.filter()),
+                image.id(),
+                image.width(),
+                image.height(),
+                image.pixels().to_vec(),
+            )?;
+            self.ui_descriptor_sets.insert(image.id(), set);
+        }
+        Ok(())
+    }
+    
+    /// Upload straight-alpha RGBA pixels and allocate the set sampling them
+    #[allow(clippy::too_many_arguments)]
+    fn upload_ui_image(
+        &mut self,
+        pool: vk::DescriptorPool,
+        layout: vk::DescriptorSetLayout,
+        sampler: vk::Sampler,
+        image_id: u32,
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+    ) -> Result<vk::DescriptorSet> {
+        self.ui_textures.update_surface_texture(image_id, SurfaceBuffer::Shm {
+            data: pixels,
+            width,
+            height,
+            stride: width * 4,
+            format: ShmFormat::Rgba8888,
+        })?;
+        let image_view = self.ui_textures.get_surface_texture(image_id)
+            .map(|texture| texture.image_view)
+            .ok_or_else(|| CompositorError::graphics(format!("UI image {} was not uploaded", image_id)))?;
+        
+        let layouts = [layout];
+        let alloc_info = vk::DescriptorSetAllocateInfo {
+            descriptor_pool: pool,
+            descriptor_set_count: layouts.len() as u32,
+            p_set_layouts: layouts.as_ptr(),
+            ..Default::default()
+        };
+        let descriptor_set = unsafe {
+            self.device.handle().allocate_descriptor_sets(&alloc_info)
+                .map_err(|e| CompositorError::graphics(format!("Failed to allocate UI descriptor set: {}", e)))?[0]
+        };
+        
+        let image_info = vk::DescriptorImageInfo {
+            sampler,
+            image_view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        let write = vk::WriteDescriptorSet {
+            dst_set: descriptor_set,
+            dst_binding: 0,
+            descriptor_count: 1,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            p_image_info: &image_info,
+            ..Default::default()
+        };
+        unsafe {
+            self.device.handle().update_descriptor_sets(&[write], &[]);
+        }
+        Ok(descriptor_set)
+    }
+    
+    /// Create, resize and free the backdrops of blurred surfaces on the
+    /// output being drawn, and pick those captured again this frame
+    ///
+    /// A backdrop is captured again when its blur is due for a refresh, or
+    /// when what is behind the surface moved, e.g. the window was dragged.
+    fn prepare_blur_backdrops(&mut self) -> Result<()> {
+        self.blur_refreshes.clear();
+        let Some(pool) = self.descriptor_pool else {
+            return Ok(());
+        };
+        
+        // Frames up to the last submitted one may still sample dropped backdrops
+        let completed = self.timeline.value()?;
+        let device = &self.device;
+        self.stale_blur_backdrops.retain(|(backdrop, value)| {
+            if *value <= completed {
+                backdrop.destroy(device, pool);
+                return false;
+            }
+            true
+        });
+        
+        // Blur is the first effect dropped under memory pressure, and needs
+        // to read back the scene it is drawn into
+        let scene_readable = self.scaled_target.is_some() || self.swapchain_readable;
+        let enabled = scene_readable && !self.effects_degraded && !self.neomorphism.is_enabled();
+        let extent = self.scaled_target.as_ref().map_or(self.swapchain_extent, ScaledTarget::extent);
+        let region = self.output_region();
+        let mut wanted: HashMap<u32, (BlurCapture, vk::Extent2D)> = HashMap::new();
+        if enabled {
+            for (surface_id, _) in self.stacked_textures() {
+                let (Some(radius), Some(variant), Some(&geometry)) = (
+                    self.blur.radius(surface_id),
+                    self.blur.variant(surface_id),
+                    self.surface_geometry.get(&surface_id),
+                ) else {
+                    continue;
+                };
+                if let Some(capture) = blur_capture(geometry, radius, region, extent) {
+                    wanted.insert(surface_id, (capture, backdrop_extent(&capture, variant)));
+                }
+            }
+        }
+        
+        let dropped: Vec<u32> = self.blur_backdrops
+            .iter()
+            .filter(|(surface_id, backdrop)| {
+                wanted.get(surface_id).is_none_or(|&(_, extent)| extent != backdrop.extent())
+            })
+            .map(|(&surface_id, _)| surface_id)
+            .collect();
+        for surface_id in dropped {
+            if let Some(backdrop) = self.blur_backdrops.remove(&surface_id) {
+                self.stale_blur_backdrops.push((backdrop, self.timeline_value));
+            }
+            self.blur_captures.remove(&surface_id);
+        }
+        
+        let (Some(blur_pipeline), Some(ui_pipeline)) = (self.blur_pipeline.as_ref(), self.ui_pipeline.as_ref()) else {
+            return Ok(());
+        };
+        for (surface_id, (capture, backdrop_extent)) in wanted {
+            if !self.blur_backdrops.contains_key(&surface_id) {
+                let backdrop = BlurBackdrop::new(
+                    &self.instance,
+                    &self.device,
+                    blur_pipeline,
+                    pool,
+                    ui_pipeline.descriptor_set_layout(),
+                    ui_pipeline.sampler(UiFilter::Linear),
+                    backdrop_extent,
+                    self.swapchain_format,
+                )?;
+                self.blur_backdrops.insert(surface_id, backdrop);
+            }
+            if self.blur.refresh_due(surface_id) || self.blur_captures.get(&surface_id) != Some(&capture) {
+                self.blur_refreshes.insert(surface_id);
+                self.blur_captures.insert(surface_id, capture);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Create, resize or drop the layer multisampled UI is drawn into
+    ///
+    /// The layer matches the scene and is kept while MSAA is enabled, also
+    /// on frames without UI, so it is not reallocated as overlays come and go.
+    fn prepare_ui_msaa(&mut self) -> Result<()> {
+        let Some(pool) = self.descriptor_pool else {
+            return Ok(());
+        };
+        
+        // Frames up to the last submitted one may still draw into dropped layers
+        let completed = self.timeline.value()?;
+        self.stale_ui_msaa.retain_mut(|(target, value)| {
+            if *value <= completed {
+                target.free_descriptor_set(pool);
+                return false;
+            }
+            true
+        });
+        
+        let extent = self.scaled_target.as_ref().map_or(self.swapchain_extent, ScaledTarget::extent);
+        let wanted = self.ui_samples != vk::SampleCountFlags::TYPE_1;
+        let current = self.ui_msaa.as_ref().map(|target| (target.samples(), target.extent()));
+        if wanted && current == Some((self.ui_samples, extent)) {
+            return Ok(());
+        }
+        if let Some(target) = self.ui_msaa.take() {
+            self.stale_ui_msaa.push((target, self.timeline_value));
+        }
+        if !wanted {
+            return Ok(());
+        }
+        
+        let Some(layer_pipeline) = self.ui_layer_pipeline.as_ref() else {
+            return Ok(());
+        };
+        self.ui_msaa = Some(UiMsaaTarget::new(
+            &self.instance,
+            &self.device,
+            self.pipeline_cache.handle(),
+            pool,
+            layer_pipeline.descriptor_set_layout(),
+            layer_pipeline.sampler(UiFilter::Nearest),
+            extent,
+            self.swapchain_format,
+            self.ui_samples,
+        )?);
+        Ok(())
+    }
+    
+    /// Set the internal render scale (1.0 = native resolution)
+    ///
+    /// Below 1.0 the composition is rendered at reduced resolution and
+    /// upscaled on scanout, trading sharpness for GPU time.
+    pub fn set_render_scale(&mut self, scale: f32) -> Result<()> {
+        let scale = clamp_render_scale(scale);
+        if scale == self.render_scale {
+            return Ok(());
+        }
+        
+        info!("Setting render scale to {:.2}", scale);
+        self.render_scale = scale;
+        
+        // The current target may still be in use by frames in flight
+        self.device.wait_idle()?;
+        self.recreate_scaled_target()
+    }
+    
+    /// Get the current render scale
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+    
+    /// Create, resize or drop the reduced-resolution target for the current scale
+    fn recreate_scaled_target(&mut self) -> Result<()> {
+        if let Some(target) = self.scaled_target.take() {
+            target.destroy(&self.device);
+        }
+        
+        if self.render_scale >= 1.0 || self.swapchain_images.is_empty() {
+            return Ok(());
+        }
+        
+        // The frame graph moves the target on to the upscale blit
+        let Some(render_pass) = self.render_pass else {
+            return Ok(());
+        };
         
+        let extent = scaled_extent(self.swapchain_extent, self.render_scale);
+        self.scaled_target = Some(ScaledTarget::new(
+            &self.instance,
+            &self.device,
+            render_pass,
+            extent,
+            self.swapchain_format,
+        )?);
         Ok(())
     }
     
@@ -216,16 +1544,24 @@ impl CompositorRenderer {
     }
     
     /// Create render pass for swapchain rendering
-    fn create_render_pass(device: &VulkanDevice, format: vk::Format) -> Result<vk::RenderPass> {
+    ///
+    /// Loading the attachment requires an `initial_layout` it is in already.
+    fn create_render_pass(
+        device: &VulkanDevice,
+        format: vk::Format,
+        load_op: vk::AttachmentLoadOp,
+        initial_layout: vk::ImageLayout,
+        final_layout: vk::ImageLayout,
+    ) -> Result<vk::RenderPass> {
         let color_attachment = vk::AttachmentDescription {
             format,
             samples: vk::SampleCountFlags::TYPE_1,
-            load_op: vk::AttachmentLoadOp::CLEAR,
+            load_op,
             store_op: vk::AttachmentStoreOp::STORE,
             stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
             stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
-            initial_layout: vk::ImageLayout::UNDEFINED,
-            final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+            initial_layout,
+            final_layout,
             ..Default::default()
         };
         
@@ -247,7 +1583,7 @@ impl CompositorRenderer {
             src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
             src_access_mask: vk::AccessFlags::empty(),
             dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-            dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
             ..Default::default()
         };
         
@@ -312,6 +1648,8 @@ impl CompositorRenderer {
             self.device.handle().allocate_command_buffers(&alloc_info)?
         };
         
+        self.command_buffer_values = vec![0; self.command_buffers.len()];
+        
         debug!("Created {} command buffers", self.command_buffers.len());
         Ok(())
     }
@@ -343,19 +1681,25 @@ impl CompositorRenderer {
     }
     
     /// Begin render pass
-    fn begin_render_pass(&self, command_buffer: vk::CommandBuffer, image_index: u32) -> Result<()> {
+    fn begin_render_pass(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        render_pass: vk::RenderPass,
+        framebuffer: vk::Framebuffer,
+        extent: vk::Extent2D,
+    ) -> Result<()> {
         let clear_values = [vk::ClearValue {
             color: vk::ClearColorValue {
-                float32: [0.0, 0.0, 0.0, 1.0], // Black background
+                float32: self.background_color,
             },
         }];
         
         let render_pass_info = vk::RenderPassBeginInfo {
-            render_pass: self.render_pass.unwrap(),
-            framebuffer: self.framebuffers[image_index as usize],
+            render_pass,
+            framebuffer,
             render_area: vk::Rect2D {
                 offset: vk::Offset2D { x: 0, y: 0 },
-                extent: self.swapchain_extent,
+                extent,
             },
             clear_value_count: clear_values.len() as u32,
             p_clear_values: clear_values.as_ptr(),
@@ -374,15 +1718,15 @@ impl CompositorRenderer {
         let viewport = vk::Viewport {
             x: 0.0,
             y: 0.0,
-            width: self.swapchain_extent.width as f32,
-            height: self.swapchain_extent.height as f32,
+            width: extent.width as f32,
+            height: extent.height as f32,
             min_depth: 0.0,
             max_depth: 1.0,
         };
         
         let scissor = vk::Rect2D {
             offset: vk::Offset2D { x: 0, y: 0 },
-            extent: self.swapchain_extent,
+            extent,
         };
         
         unsafe {
@@ -393,8 +1737,8 @@ impl CompositorRenderer {
         Ok(())
     }
     
-    /// Render all surfaces
-    fn render_surfaces(&self, command_buffer: vk::CommandBuffer) -> Result<()> {
+    /// Render all surfaces into the scene, in the render pass begun on it
+    fn render_surfaces(&self, command_buffer: vk::CommandBuffer, scene: SceneTarget) -> Result<()> {
         let surface_pipeline = self.surface_pipeline.as_ref()
             .ok_or_else(|| CompositorError::runtime("Surface pipeline not initialized"))?;
         
@@ -407,21 +1751,217 @@ impl CompositorRenderer {
             );
         }
         
-        // Render each surface
-        for (surface_id, texture) in self.surface_renderer.get_all_textures() {
+        // Wallpapers cover the background color below every surface
+        let region = self.output_region();
+        let wallpaper: Vec<UiPrimitive> = self.wallpaper.iter()
+            .filter(|primitive| primitive.bounds().intersects(&region))
+            .cloned()
+            .collect();
+        if !wallpaper.is_empty() {
+            let ui_pipeline = self.ui_pipeline.as_ref()
+                .ok_or_else(|| CompositorError::runtime("UI pipeline not initialized"))?;
+            self.draw_ui(command_buffer, ui_pipeline, &wallpaper)?;
+            unsafe {
+                self.device.handle().cmd_bind_pipeline(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    surface_pipeline.pipeline(),
+                );
+            }
+        }
+        
+        // Render each surface, bottom to top
+        for (surface_id, texture) in self.stacked_textures() {
+            // Neomorphic surfaces are matte, extruded by a shadow and a highlight;
+            // others may cast a drop shadow
+            let behind = match (self.surface_geometry.get(&surface_id), self.neomorphism.shadows(surface_id)) {
+                (Some(&rect), Some(shadows)) if !self.effects_degraded => {
+                    let corner_radius = self.surface_corner_radius(surface_id);
+                    vec![shadows.shadow.primitive(rect, corner_radius), shadows.highlight.primitive(rect, corner_radius)]
+                }
+                (Some(&rect), None) => self.surface_shadows
+                    .get(&surface_id)
+                    .map(|&intensity| {
+                        UiPrimitive::drop_shadow(rect, self.surface_corner_radius(surface_id), intensity * self.shadow_scale)
+                    })
+                    .into_iter()
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let blurred = self.draw_blurred_backdrop(command_buffer, scene, surface_id)?;
+            if !behind.is_empty() {
+                let ui_pipeline = self.ui_pipeline.as_ref()
+                    .ok_or_else(|| CompositorError::runtime("UI pipeline not initialized"))?;
+                self.draw_ui(command_buffer, ui_pipeline, &behind)?;
+            }
+            if blurred || !behind.is_empty() {
+                unsafe {
+                    self.device.handle().cmd_bind_pipeline(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        surface_pipeline.pipeline(),
+                    );
+                }
+            }
+            
             self.render_surface(command_buffer, surface_pipeline, surface_id, texture)?;
         }
         
         Ok(())
     }
     
+    /// Draw the blurred scene behind a surface, masked to its blur regions;
+    /// returns whether anything was drawn, leaving the UI pipeline bound
+    ///
+    /// When the backdrop is due for a refresh, the scene render pass is
+    /// ended to capture and blur what was drawn so far, then resumed.
+    fn draw_blurred_backdrop(&self, command_buffer: vk::CommandBuffer, scene: SceneTarget, surface_id: u32) -> Result<bool> {
+        let (Some(backdrop), Some(capture), Some(request), Some(variant), Some(&geometry)) = (
+            self.blur_backdrops.get(&surface_id),
+            self.blur_captures.get(&surface_id),
+            self.blur.get(surface_id),
+            self.blur.variant(surface_id),
+            self.surface_geometry.get(&surface_id),
+        ) else {
+            return Ok(false);
+        };
+        let (Some(blur_pipeline), Some(ui_pipeline), Some(load_render_pass)) =
+            (self.blur_pipeline.as_ref(), self.ui_pipeline.as_ref(), self.load_render_pass)
+        else {
+            return Ok(false);
+        };
+        
+        if self.blur_refreshes.contains(&surface_id) {
+            unsafe {
+                self.device.handle().cmd_end_render_pass(command_buffer);
+            }
+            backdrop.record_refresh(&self.device, blur_pipeline, command_buffer, scene.image, capture.source, variant);
+            self.begin_render_pass(command_buffer, load_render_pass, scene.framebuffer, scene.extent)?;
+        }
+        
+        unsafe {
+            self.device.handle().cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                ui_pipeline.pipeline(),
+            );
+            self.device.handle().cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                ui_pipeline.pipeline_layout(),
+                0,
+                &[backdrop.descriptor_set()],
+                &[],
+            );
+        }
+        
+        // Regions are relative to the surface and may not leave it
+        let region = self.output_region();
+        let corner_radius = self.surface_corner_radius(surface_id);
+        let captured = capture.rect;
+        for &[x, y, width, height] in &request.regions {
+            let left = (geometry.x + x as f32).max(geometry.x);
+            let top = (geometry.y + y as f32).max(geometry.y);
+            let right = (geometry.x + (x + width) as f32).min(geometry.x + geometry.width);
+            let bottom = (geometry.y + (y + height) as f32).min(geometry.y + geometry.height);
+            if right <= left || bottom <= top {
+                continue;
+            }
+            let rect = Rect::new(left, top, right - left, bottom - top);
+            let uv_rect = [
+                (rect.x - captured.x) / captured.width,
+                (rect.y - captured.y) / captured.height,
+                rect.width / captured.width,
+                rect.height / captured.height,
+            ];
+            ui_pipeline.draw(
+                command_buffer,
+                &UiPushConstants::for_backdrop(rect, uv_rect, geometry, corner_radius, region),
+            );
+        }
+        Ok(true)
+    }
+    
+    /// Draw the UI overlapping the output, back to front
+    fn render_ui(&self, command_buffer: vk::CommandBuffer, pipeline: &UiPipeline) -> Result<()> {
+        let primitives: Vec<UiPrimitive> = self.visible_ui().cloned().collect();
+        self.draw_ui(command_buffer, pipeline, &primitives)
+    }
+    
+    /// Blend the resolved multisampled UI layer over the whole scene
+    fn draw_ui_layer(&self, command_buffer: vk::CommandBuffer, pipeline: &UiPipeline, descriptor_set: vk::DescriptorSet) {
+        let region = self.output_region();
+        unsafe {
+            self.device.handle().cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.pipeline(),
+            );
+            self.device.handle().cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.pipeline_layout(),
+                0,
+                &[descriptor_set],
+                &[],
+            );
+        }
+        pipeline.draw(
+            command_buffer,
+            &UiPushConstants::for_backdrop(region, [0.0, 0.0, 1.0, 1.0], region, 0.0, region),
+        );
+    }
+    
+    /// Draw primitives with `pipeline` into the current render pass,
+    /// leaving that pipeline bound
+    fn draw_ui(&self, command_buffer: vk::CommandBuffer, pipeline: &UiPipeline, primitives: &[UiPrimitive]) -> Result<()> {
+        let region = self.output_region();
+        
+        unsafe {
+            self.device.handle().cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.pipeline(),
+            );
+        }
+        
+        let mut bound = None;
+        for primitive in primitives {
+            let image_id = primitive.image().map_or(UI_WHITE_IMAGE, |image| image.id());
+            let Some(&descriptor_set) = self.ui_descriptor_sets.get(&image_id) else {
+                continue;
+            };
+            if bound != Some(descriptor_set) {
+                unsafe {
+                    self.device.handle().cmd_bind_descriptor_sets(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        pipeline.pipeline_layout(),
+                        0,
+                        &[descriptor_set],
+                        &[],
+                    );
+                }
+                bound = Some(descriptor_set);
+            }
+            pipeline.draw(command_buffer, &UiPushConstants::for_primitive(primitive, region));
+        }
+        
+        Ok(())
+    }
+    
+    /// Corner radius a surface is drawn with
+    fn surface_corner_radius(&self, surface_id: u32) -> f32 {
+        self.surface_corner_radii.get(&surface_id).copied().unwrap_or(self.corner_radius)
+    }
+    
     /// Render a single surface
     fn render_surface(
         &self,
         command_buffer: vk::CommandBuffer,
         pipeline: &SurfacePipeline,
         surface_id: u32,
-        _texture: &SurfaceTexture,
+        texture: &SurfaceTexture,
     ) -> Result<()> {
         // Get vertex buffer for this surface
         let vertex_buffer = self.vertex_buffers.get(&surface_id)                .ok_or_else(|| CompositorError::runtime("Missing vertex buffer for surface"))?;
@@ -437,10 +1977,21 @@ impl CompositorRenderer {
             [0.0, 0.0, 0.0, 1.0],
         ];
         
+        let (dim, desaturation) = self.dimmer.factors(surface_id);
+        let (border_width, border_color) = self.surface_borders.get(&surface_id).copied().unwrap_or((0.0, [0.0; 4]));
+        
         let push_constants = SurfacePushConstants {
             transform,
             offset: [0.0, 0.0], // TODO: Get from surface position
             scale: [1.0, 1.0],  // TODO: Get from surface scale
+            dim,
+            desaturation,
+            size: [texture.width as f32, texture.height as f32],
+            corner_radius: self.surface_corner_radius(surface_id),
+            edge_softness: self.ui_antialiasing.edge_softness(),
+            border_width,
+            _padding: 0.0,
+            border_color,
         };
         
         unsafe {
@@ -458,7 +2009,7 @@ impl CompositorRenderer {
             self.device.handle().cmd_push_constants(
                 command_buffer,
                 pipeline.pipeline_layout(),
-                vk::ShaderStageFlags::VERTEX,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
                 0,
                 &std::mem::transmute::<_, [u8; std::mem::size_of::<SurfacePushConstants>()]>(push_constants),
             );
@@ -514,6 +2065,21 @@ impl Drop for CompositorRenderer {
             }
         }
         
+        // Clean up blurred backdrops and UI layers, returning their sets to the pool
+        if let Some(pool) = self.descriptor_pool {
+            for backdrop in self.blur_backdrops.values() {
+                backdrop.destroy(&self.device, pool);
+            }
+            for (backdrop, _) in &self.stale_blur_backdrops {
+                backdrop.destroy(&self.device, pool);
+            }
+            for target in self.ui_msaa.iter_mut().chain(self.stale_ui_msaa.iter_mut().map(|(target, _)| target)) {
+                target.free_descriptor_set(pool);
+            }
+        }
+        self.ui_msaa = None;
+        self.stale_ui_msaa.clear();
+        
         // Clean up descriptor pool
         if let Some(pool) = self.descriptor_pool {
             unsafe {
@@ -528,6 +2094,16 @@ impl Drop for CompositorRenderer {
             }
         }
         
+        // Clean up reduced-resolution target
+        if let Some(target) = self.scaled_target.take() {
+            target.destroy(&self.device);
+        }
+        if let Some(render_pass) = self.load_render_pass {
+            unsafe {
+                self.device.handle().destroy_render_pass(render_pass, None);
+            }
+        }
+        
         // Clean up render pass
         if let Some(render_pass) = self.render_pass {
             unsafe {
@@ -543,3 +2119,37 @@ impl Drop for CompositorRenderer {
         info!("Compositor renderer cleanup complete");
     }
 }
+
+/// Part of a frame of `extent` showing `region` that the blur behind a
+/// surface at `geometry` reads, extended by the blur radius so the edges
+/// blend with their surroundings; `None` if the surface is not in the frame
+fn blur_capture(geometry: Rect, radius: f32, region: Rect, extent: vk::Extent2D) -> Option<BlurCapture> {
+    let scale_x = extent.width as f32 / region.width;
+    let scale_y = extent.height as f32 / region.height;
+    let to_pixel = |value: f32, origin: f32, scale: f32, limit: u32| ((value - origin) * scale).clamp(0.0, limit as f32);
+    let source = [
+        to_pixel(geometry.x - radius, region.x, scale_x, extent.width).floor() as i32,
+        to_pixel(geometry.y - radius, region.y, scale_y, extent.height).floor() as i32,
+        to_pixel(geometry.x + geometry.width + radius, region.x, scale_x, extent.width).ceil() as i32,
+        to_pixel(geometry.y + geometry.height + radius, region.y, scale_y, extent.height).ceil() as i32,
+    ];
+    if source[2] <= source[0] || source[3] <= source[1] {
+        return None;
+    }
+    let rect = Rect::new(
+        region.x + source[0] as f32 / scale_x,
+        region.y + source[1] as f32 / scale_y,
+        (source[2] - source[0]) as f32 / scale_x,
+        (source[3] - source[1]) as f32 / scale_y,
+    );
+    Some(BlurCapture { source, rect })
+}
+
+/// Size of the backdrop a capture is downsampled into before blurring
+fn backdrop_extent(capture: &BlurCapture, variant: &BlurVariant) -> vk::Extent2D {
+    let divisor = 1u32 << variant.downsample_levels;
+    vk::Extent2D {
+        width: ((capture.source[2] - capture.source[0]) as u32).div_ceil(divisor),
+        height: ((capture.source[3] - capture.source[1]) as u32).div_ceil(divisor),
+    }
+}
@@ -15,6 +15,11 @@ pub struct VulkanDevice {
     #[allow(dead_code)] // Will be used for presentation and queue management
     present_queue_family: u32,
     device_properties: vk::PhysicalDeviceProperties,
+    present_wait_supported: bool,
+    memory_budget_supported: bool,
+    incremental_present_supported: bool,
+    drm_format_modifiers_supported: bool,
+    fence_fd_export_supported: bool,
 }
 
 impl VulkanDevice {
@@ -78,12 +83,56 @@ impl VulkanDevice {
                 .to_string_lossy()
         });
         
+        // Use VK_KHR_present_id/present_wait for present pacing where available
+        let present_wait_supported = Self::query_present_wait_support(instance, physical_device);
+        info!("Present wait support: {}", present_wait_supported);
+        
+        // Use VK_EXT_memory_budget to track usage against driver-reported budgets
+        let memory_budget_supported = Self::has_device_extension(
+            instance,
+            physical_device,
+            vk::ExtMemoryBudgetFn::name(),
+        );
+        info!("Memory budget support: {}", memory_budget_supported);
+        
+        // Use VK_KHR_incremental_present to pass damaged regions with each present
+        let incremental_present_supported = Self::has_device_extension(
+            instance,
+            physical_device,
+            vk::KhrIncrementalPresentFn::name(),
+        );
+        info!("Incremental present support: {}", incremental_present_supported);
+        
+        // Use VK_EXT_image_drm_format_modifier to list the dmabuf modifiers the GPU can sample
+        let drm_format_modifiers_supported = Self::has_device_extension(
+            instance,
+            physical_device,
+            vk::ExtImageDrmFormatModifierFn::name(),
+        );
+        info!("DRM format modifier support: {}", drm_format_modifiers_supported);
+        
+        // Use VK_KHR_external_fence_fd to wake the render loop when frames finish
+        let fence_fd_export_supported = Self::query_fence_fd_export_support(instance, physical_device);
+        info!("Fence sync fd export support: {}", fence_fd_export_supported);
+        
+        let optional_extensions = [
+            (memory_budget_supported, vk::ExtMemoryBudgetFn::name()),
+            (incremental_present_supported, vk::KhrIncrementalPresentFn::name()),
+            (fence_fd_export_supported, vk::KhrExternalFenceFdFn::name()),
+        ];
+        let optional_extensions: Vec<&CStr> = optional_extensions
+            .into_iter()
+            .filter_map(|(supported, name)| supported.then_some(name))
+            .collect();
+        
         // Create logical device
         let device = Self::create_logical_device(
             instance, 
             physical_device, 
             graphics_queue_family, 
-            present_queue_family
+            present_queue_family,
+            present_wait_supported,
+            &optional_extensions,
         )?;
         
         // Get queue handles
@@ -98,6 +147,11 @@ impl VulkanDevice {
             graphics_queue_family,
             present_queue_family,
             device_properties,
+            present_wait_supported,
+            memory_budget_supported,
+            incremental_present_supported,
+            drm_format_modifiers_supported,
+            fence_fd_export_supported,
         })
     }
     
@@ -186,11 +240,73 @@ impl VulkanDevice {
         Err(CompositorError::init("No suitable graphics device found"))
     }
     
+    /// Check whether a physical device supports a device extension
+    fn has_device_extension(instance: &VulkanInstance, physical_device: vk::PhysicalDevice, name: &CStr) -> bool {
+        let extensions = match unsafe {
+            instance.handle().enumerate_device_extension_properties(physical_device)
+        } {
+            Ok(extensions) => extensions,
+            Err(_) => return false,
+        };
+        extensions
+            .iter()
+            .any(|extension| unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) } == name)
+    }
+    
+    /// Check whether a physical device supports both VK_KHR_present_id and VK_KHR_present_wait
+    fn query_present_wait_support(instance: &VulkanInstance, physical_device: vk::PhysicalDevice) -> bool {
+        if !Self::has_device_extension(instance, physical_device, vk::KhrPresentIdFn::name())
+            || !Self::has_device_extension(instance, physical_device, vk::KhrPresentWaitFn::name())
+        {
+            return false;
+        }
+        
+        let mut present_wait_features = vk::PhysicalDevicePresentWaitFeaturesKHR::default();
+        let mut present_id_features = vk::PhysicalDevicePresentIdFeaturesKHR {
+            p_next: &mut present_wait_features as *mut _ as *mut std::ffi::c_void,
+            ..Default::default()
+        };
+        let mut features = vk::PhysicalDeviceFeatures2 {
+            p_next: &mut present_id_features as *mut _ as *mut std::ffi::c_void,
+            ..Default::default()
+        };
+        unsafe {
+            instance.handle().get_physical_device_features2(physical_device, &mut features);
+        }
+        
+        present_id_features.present_id == vk::TRUE && present_wait_features.present_wait == vk::TRUE
+    }
+    
+    /// Check whether fences can be exported as sync files, which become
+    /// readable once the fence signals
+    fn query_fence_fd_export_support(instance: &VulkanInstance, physical_device: vk::PhysicalDevice) -> bool {
+        if !Self::has_device_extension(instance, physical_device, vk::KhrExternalFenceFdFn::name()) {
+            return false;
+        }
+        
+        let external_info = vk::PhysicalDeviceExternalFenceInfo {
+            handle_type: vk::ExternalFenceHandleTypeFlags::SYNC_FD,
+            ..Default::default()
+        };
+        let mut properties = vk::ExternalFenceProperties::default();
+        unsafe {
+            instance.handle().get_physical_device_external_fence_properties(
+                physical_device,
+                &external_info,
+                &mut properties,
+            );
+        }
+        
+        properties.external_fence_features.contains(vk::ExternalFenceFeatureFlags::EXPORTABLE)
+    }
+    
     fn create_logical_device(
         instance: &VulkanInstance,
         physical_device: vk::PhysicalDevice,
         graphics_queue_family: u32,
         present_queue_family: u32,
+        enable_present_wait: bool,
+        optional_extensions: &[&CStr],
     ) -> Result<Device> {
         let queue_priorities = [1.0f32];
         
@@ -212,14 +328,42 @@ impl VulkanDevice {
             .collect();
         
         // Required device extensions
-        let device_extensions = [
+        let mut device_extensions = vec![
             ash::extensions::khr::Swapchain::name().as_ptr(),
         ];
+        if enable_present_wait {
+            device_extensions.push(vk::KhrPresentIdFn::name().as_ptr());
+            device_extensions.push(vk::KhrPresentWaitFn::name().as_ptr());
+        }
+        device_extensions.extend(optional_extensions.iter().map(|name| name.as_ptr()));
         
         // Device features
         let device_features = vk::PhysicalDeviceFeatures::default();
         
+        // Optional present pacing features, chained after the core features
+        let mut present_wait_features = vk::PhysicalDevicePresentWaitFeaturesKHR {
+            present_wait: vk::TRUE,
+            ..Default::default()
+        };
+        let mut present_id_features = vk::PhysicalDevicePresentIdFeaturesKHR {
+            p_next: &mut present_wait_features as *mut _ as *mut std::ffi::c_void,
+            present_id: vk::TRUE,
+            ..Default::default()
+        };
+        
+        // Timeline semaphores order frames in the compositor renderer
+        let vulkan12_features = vk::PhysicalDeviceVulkan12Features {
+            p_next: if enable_present_wait {
+                &mut present_id_features as *mut _ as *mut std::ffi::c_void
+            } else {
+                std::ptr::null_mut()
+            },
+            timeline_semaphore: vk::TRUE,
+            ..Default::default()
+        };
+        
         let device_create_info = vk::DeviceCreateInfo {
+            p_next: &vulkan12_features as *const _ as *const std::ffi::c_void,
             queue_create_info_count: queue_create_infos.len() as u32,
             p_queue_create_infos: queue_create_infos.as_ptr(),
             enabled_extension_count: device_extensions.len() as u32,
@@ -311,6 +455,48 @@ impl VulkanDevice {
         &self.device_properties
     }
     
+    /// Whether VK_KHR_present_id and VK_KHR_present_wait are enabled
+    /// 
+    /// When true, swapchains tag each present with an ID and can block until
+    /// it reaches the display, giving accurate present timestamps for pacing.
+    /// Otherwise callers fall back to timer-based frame scheduling.
+    pub fn supports_present_wait(&self) -> bool {
+        self.present_wait_supported
+    }
+    
+    /// Whether VK_EXT_memory_budget is enabled
+    /// 
+    /// When true, per-heap budgets and usage reported by the driver are
+    /// available through the physical device memory properties, letting the
+    /// renderer react before device memory runs out.
+    pub fn supports_memory_budget(&self) -> bool {
+        self.memory_budget_supported
+    }
+    
+    /// Whether VK_KHR_incremental_present is enabled
+    /// 
+    /// When true, presents can describe the regions that changed since the
+    /// previous present so the presentation engine only updates those.
+    pub fn supports_incremental_present(&self) -> bool {
+        self.incremental_present_supported
+    }
+    
+    /// Whether the device supports VK_EXT_image_drm_format_modifier
+    /// 
+    /// When true, the driver lists the DRM format modifiers, such as tiled
+    /// and compressed layouts, it supports for each format.
+    pub fn supports_drm_format_modifiers(&self) -> bool {
+        self.drm_format_modifiers_supported
+    }
+    
+    /// Whether fences can be exported as sync file descriptors
+    /// 
+    /// When true, the completion of submitted work can be polled alongside
+    /// other file descriptors instead of blocking on the fence.
+    pub fn supports_fence_fd_export(&self) -> bool {
+        self.fence_fd_export_supported
+    }
+    
     /// Wait for all GPU operations to complete
     /// 
     /// Blocks until the GPU has finished all pending operations on this device.
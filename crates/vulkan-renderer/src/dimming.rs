@@ -0,0 +1,141 @@
+// Active-window highlight by dimming unfocused surfaces
+//
+// Tracks a per-surface dim level that animates towards 0.0 for the focused
+// surface and 1.0 for all others. The level is scaled by the configured
+// strength and passed to the surface fragment shader as push constants.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Dimming settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DimmingSettings {
+    pub enabled: bool,
+    /// Amount of darkening applied to unfocused surfaces (0.0 - 1.0)
+    pub strength: f32,
+    /// Amount of desaturation applied to unfocused surfaces (0.0 - 1.0)
+    pub desaturation: f32,
+    /// Duration of the fade when focus changes
+    pub transition: Duration,
+}
+
+impl Default for DimmingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            strength: 0.25,
+            desaturation: 0.0,
+            transition: Duration::from_millis(200),
+        }
+    }
+}
+
+/// Animated per-surface dim levels driven by focus changes
+#[derive(Debug)]
+pub struct FocusDimmer {
+    settings: DimmingSettings,
+    focused: Option<u32>,
+    /// Current animation progress per surface: 0.0 = undimmed, 1.0 = fully dimmed
+    levels: HashMap<u32, f32>,
+    last_update: Option<Instant>,
+}
+
+impl FocusDimmer {
+    /// Create a dimmer with the given settings
+    pub fn new(settings: DimmingSettings) -> Self {
+        Self {
+            settings,
+            focused: None,
+            levels: HashMap::new(),
+            last_update: None,
+        }
+    }
+
+    /// Update the dimming settings
+    pub fn set_settings(&mut self, settings: DimmingSettings) {
+        self.settings = DimmingSettings {
+            strength: settings.strength.clamp(0.0, 1.0),
+            desaturation: settings.desaturation.clamp(0.0, 1.0),
+            ..settings
+        };
+    }
+
+    /// Get the current dimming settings
+    pub fn settings(&self) -> DimmingSettings {
+        self.settings
+    }
+
+    /// Set the focused surface; other surfaces fade towards dimmed
+    pub fn set_focused(&mut self, surface_id: Option<u32>) {
+        self.focused = surface_id;
+    }
+
+    /// The focused surface, if any
+    pub fn focused(&self) -> Option<u32> {
+        self.focused
+    }
+
+    /// Forget a removed surface
+    pub fn remove_surface(&mut self, surface_id: u32) {
+        self.levels.remove(&surface_id);
+        if self.focused == Some(surface_id) {
+            self.focused = None;
+        }
+    }
+
+    /// Advance all animations to `now` for the given set of surfaces
+    ///
+    /// Returns whether any surface is still animating, so the caller can keep
+    /// scheduling frames until transitions settle.
+    pub fn advance(&mut self, surfaces: impl Iterator<Item = u32>, now: Instant) -> bool {
+        let elapsed = self
+            .last_update
+            .map_or(Duration::ZERO, |last| now.saturating_duration_since(last));
+        self.last_update = Some(now);
+
+        let step = if self.settings.transition.is_zero() {
+            1.0
+        } else {
+            elapsed.as_secs_f32() / self.settings.transition.as_secs_f32()
+        };
+
+        let mut animating = false;
+        for surface_id in surfaces {
+            let target = self.target_level(surface_id);
+            // New surfaces start at their target so they don't fade in on map
+            let level = self.levels.entry(surface_id).or_insert(target);
+            if *level < target {
+                *level = (*level + step).min(target);
+            } else if *level > target {
+                *level = (*level - step).max(target);
+            }
+            animating |= *level != target;
+        }
+        animating
+    }
+
+    /// Brightness reduction and desaturation for a surface, as passed to the shader
+    pub fn factors(&self, surface_id: u32) -> (f32, f32) {
+        if !self.settings.enabled {
+            return (0.0, 0.0);
+        }
+
+        // Smoothstep easing for the fade
+        let t = self.levels.get(&surface_id).copied().unwrap_or(0.0);
+        let eased = t * t * (3.0 - 2.0 * t);
+        (eased * self.settings.strength, eased * self.settings.desaturation)
+    }
+
+    fn target_level(&self, surface_id: u32) -> f32 {
+        match self.focused {
+            Some(focused) if focused != surface_id => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+impl Default for FocusDimmer {
+    fn default() -> Self {
+        Self::new(DimmingSettings::default())
+    }
+}
@@ -0,0 +1,106 @@
+// DMA-BUF formats and modifiers the GPU can sample
+//
+// Clients allocate their dmabuf buffers in one of the formats and modifiers
+// the compositor advertises over linux-dmabuf. Tiled and compressed
+// modifiers are usually much faster for the GPU than linear buffers, so the
+// list is queried from the device: with VK_EXT_image_drm_format_modifier
+// every modifier the driver can sample from is listed, otherwise only linear
+// buffers of formats that can be sampled with linear tiling.
+
+use crate::device::VulkanDevice;
+use crate::instance::VulkanInstance;
+use ash::vk;
+
+/// DRM format modifier of linear buffers
+pub const DRM_FORMAT_MOD_LINEAR: u64 = 0;
+
+/// A DRM format and modifier clients can allocate buffers with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DmabufFormat {
+    /// DRM fourcc code
+    pub fourcc: u32,
+    pub modifier: u64,
+}
+
+/// DRM fourcc code of four characters
+const fn fourcc(code: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*code)
+}
+
+/// DRM formats and the Vulkan formats sampling them; DRM names list
+/// components from the most significant bit, Vulkan from the lowest address.
+/// Formats without alpha sample their padding, which the shader ignores.
+const FORMATS: [(u32, vk::Format); 11] = [
+    (fourcc(b"AR24"), vk::Format::B8G8R8A8_UNORM),
+    (fourcc(b"XR24"), vk::Format::B8G8R8A8_UNORM),
+    (fourcc(b"AB24"), vk::Format::R8G8B8A8_UNORM),
+    (fourcc(b"XB24"), vk::Format::R8G8B8A8_UNORM),
+    (fourcc(b"AR30"), vk::Format::A2R10G10B10_UNORM_PACK32),
+    (fourcc(b"XR30"), vk::Format::A2R10G10B10_UNORM_PACK32),
+    (fourcc(b"AB30"), vk::Format::A2B10G10R10_UNORM_PACK32),
+    (fourcc(b"XB30"), vk::Format::A2B10G10R10_UNORM_PACK32),
+    (fourcc(b"AB4H"), vk::Format::R16G16B16A16_SFLOAT),
+    (fourcc(b"XB4H"), vk::Format::R16G16B16A16_SFLOAT),
+    (fourcc(b"RG16"), vk::Format::R5G6B5_UNORM_PACK16),
+];
+
+/// Formats and modifiers of dmabuf buffers the device can sample from
+pub fn query_dmabuf_formats(instance: &VulkanInstance, device: &VulkanDevice) -> Vec<DmabufFormat> {
+    let physical_device = device.physical_device();
+    let with_modifiers = device.supports_drm_format_modifiers();
+    FORMATS
+        .iter()
+        .flat_map(|&(fourcc, format)| {
+            let modifiers = if with_modifiers {
+                sampled_modifiers(instance, physical_device, format)
+            } else {
+                sampled_linear(instance, physical_device, format)
+            };
+            modifiers.into_iter().map(move |modifier| DmabufFormat { fourcc, modifier })
+        })
+        .collect()
+}
+
+/// Modifiers of a format whose images can be sampled, from
+/// VK_EXT_image_drm_format_modifier
+fn sampled_modifiers(instance: &VulkanInstance, physical_device: vk::PhysicalDevice, format: vk::Format) -> Vec<u64> {
+    // Ask for the number of modifiers first, then fill them in
+    let mut list = vk::DrmFormatModifierPropertiesListEXT::default();
+    let mut properties = vk::FormatProperties2::builder().push_next(&mut list);
+    unsafe {
+        instance
+            .handle()
+            .get_physical_device_format_properties2(physical_device, format, &mut properties)
+    };
+
+    let mut modifiers = vec![vk::DrmFormatModifierPropertiesEXT::default(); list.drm_format_modifier_count as usize];
+    let mut list = vk::DrmFormatModifierPropertiesListEXT::builder().drm_format_modifier_properties(&mut modifiers);
+    let mut properties = vk::FormatProperties2::builder().push_next(&mut list);
+    unsafe {
+        instance
+            .handle()
+            .get_physical_device_format_properties2(physical_device, format, &mut properties)
+    };
+    let count = list.drm_format_modifier_count as usize;
+
+    modifiers
+        .iter()
+        .take(count)
+        .filter(|modifier| {
+            modifier
+                .drm_format_modifier_tiling_features
+                .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE)
+        })
+        .map(|modifier| modifier.drm_format_modifier)
+        .collect()
+}
+
+/// The linear modifier, if images of a format can be sampled with linear tiling
+fn sampled_linear(instance: &VulkanInstance, physical_device: vk::PhysicalDevice, format: vk::Format) -> Vec<u64> {
+    let properties = instance.get_physical_device_format_properties(physical_device, format);
+    if properties.linear_tiling_features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE) {
+        vec![DRM_FORMAT_MOD_LINEAR]
+    } else {
+        Vec::new()
+    }
+}
@@ -0,0 +1,315 @@
+// Frame graph
+//
+// A frame is declared as a list of passes (surface, UI, post) together with
+// the images each pass reads and writes. The graph tracks the layout and last
+// access of every image and inserts the pipeline barriers needed between
+// passes, then leaves the swapchain image ready to present. New effects are
+// added by declaring a pass instead of hand-placing barriers; effects that
+// depend on what is below each surface, like drop shadows and background
+// blur, are drawn within the surface pass.
+
+use ash::vk;
+use compositor_utils::prelude::*;
+use crate::VulkanDevice;
+use std::collections::HashMap;
+
+/// Passes of a compositor frame, recorded in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PassKind {
+    /// Client surfaces, with their shadows and blurred backdrops
+    Surface,
+    /// Compositor UI (app bar, overlays)
+    Ui,
+    /// Post-processing and upscale onto the swapchain image
+    Post,
+}
+
+/// Images used by frame passes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrameResource {
+    /// Composited scene when rendered offscreen (e.g. at reduced resolution)
+    SceneColor,
+    /// Swapchain image being presented
+    Swapchain,
+}
+
+/// How a pass uses an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceUsage {
+    ColorAttachment,
+    Sampled,
+    TransferSrc,
+    TransferDst,
+    Present,
+}
+
+impl ResourceUsage {
+    fn state(self) -> ResourceState {
+        let (layout, stage, access) = match self {
+            Self::ColorAttachment => (
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            ),
+            Self::Sampled => (
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::AccessFlags::SHADER_READ,
+            ),
+            Self::TransferSrc => (
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::TRANSFER_READ,
+            ),
+            Self::TransferDst => (
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::TRANSFER_WRITE,
+            ),
+            Self::Present => (
+                vk::ImageLayout::PRESENT_SRC_KHR,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::AccessFlags::empty(),
+            ),
+        };
+        ResourceState { layout, stage, access }
+    }
+}
+
+/// Layout and last access of an image while the frame is recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceState {
+    pub layout: vk::ImageLayout,
+    pub stage: vk::PipelineStageFlags,
+    pub access: vk::AccessFlags,
+}
+
+impl ResourceState {
+    /// State of an image with undefined contents at the start of a frame
+    const UNDEFINED: Self = Self {
+        layout: vk::ImageLayout::UNDEFINED,
+        stage: vk::PipelineStageFlags::TOP_OF_PIPE,
+        access: vk::AccessFlags::empty(),
+    };
+
+    fn writes(&self) -> bool {
+        self.access.intersects(
+            vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags::TRANSFER_WRITE
+                | vk::AccessFlags::SHADER_WRITE,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AccessKind {
+    /// Attachment of a render pass that transitions it itself
+    RenderTarget { final_layout: vk::ImageLayout },
+    Use(ResourceUsage),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ResourceAccess {
+    resource: FrameResource,
+    kind: AccessKind,
+}
+
+/// A declared pass and the images it touches
+#[derive(Debug, Clone)]
+pub struct PassDesc {
+    kind: PassKind,
+    accesses: Vec<ResourceAccess>,
+}
+
+impl PassDesc {
+    /// Declare a pass
+    pub fn new(kind: PassKind) -> Self {
+        Self {
+            kind,
+            accesses: Vec::new(),
+        }
+    }
+
+    /// Render into an image through a render pass that leaves it in `final_layout`
+    pub fn render_target(mut self, resource: FrameResource, final_layout: vk::ImageLayout) -> Self {
+        self.accesses.push(ResourceAccess {
+            resource,
+            kind: AccessKind::RenderTarget { final_layout },
+        });
+        self
+    }
+
+    /// Use an image in the given way
+    pub fn uses(mut self, resource: FrameResource, usage: ResourceUsage) -> Self {
+        self.accesses.push(ResourceAccess {
+            resource,
+            kind: AccessKind::Use(usage),
+        });
+        self
+    }
+}
+
+/// Image barrier inserted by the graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierDesc {
+    pub resource: FrameResource,
+    pub from: ResourceState,
+    pub to: ResourceState,
+}
+
+/// A pass with the barriers to record before it
+#[derive(Debug, Clone)]
+pub struct CompiledPass {
+    pub kind: PassKind,
+    pub barriers: Vec<BarrierDesc>,
+}
+
+/// Passes in execution order plus the barriers that finish the frame
+#[derive(Debug, Clone)]
+pub struct CompiledGraph {
+    pub passes: Vec<CompiledPass>,
+    pub final_barriers: Vec<BarrierDesc>,
+}
+
+/// Declared passes of one frame
+#[derive(Debug, Clone, Default)]
+pub struct FrameGraph {
+    passes: Vec<PassDesc>,
+}
+
+impl FrameGraph {
+    /// Create an empty frame graph
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a pass; passes run in `PassKind` order regardless of declaration order
+    pub fn add_pass(&mut self, pass: PassDesc) {
+        self.passes.push(pass);
+    }
+
+    /// Order the passes and work out the barriers between them
+    pub fn compile(&self) -> CompiledGraph {
+        let mut passes = self.passes.clone();
+        passes.sort_by_key(|pass| pass.kind);
+
+        let mut states: HashMap<FrameResource, ResourceState> = HashMap::new();
+        let compiled = passes
+            .into_iter()
+            .map(|pass| {
+                let mut barriers = Vec::new();
+                for access in &pass.accesses {
+                    let current = states.get(&access.resource).copied().unwrap_or(ResourceState::UNDEFINED);
+                    let attachment = ResourceUsage::ColorAttachment.state();
+                    let (required, after) = match access.kind {
+                        // The render pass clears and transitions the attachment;
+                        // only earlier accesses need to finish first
+                        AccessKind::RenderTarget { final_layout } => {
+                            (attachment, ResourceState { layout: final_layout, ..attachment })
+                        }
+                        AccessKind::Use(usage) => (usage.state(), usage.state()),
+                    };
+                    let needs_barrier = match access.kind {
+                        AccessKind::RenderTarget { .. } => current != ResourceState::UNDEFINED,
+                        AccessKind::Use(_) => needs_barrier(current, required),
+                    };
+                    if needs_barrier {
+                        barriers.push(BarrierDesc { resource: access.resource, from: current, to: required });
+                    }
+                    states.insert(access.resource, after);
+                }
+                CompiledPass { kind: pass.kind, barriers }
+            })
+            .collect();
+
+        // Leave the swapchain image ready to present
+        let present = ResourceUsage::Present.state();
+        let final_barriers = states
+            .get(&FrameResource::Swapchain)
+            .filter(|state| state.layout != present.layout)
+            .map(|&from| BarrierDesc { resource: FrameResource::Swapchain, from, to: present })
+            .into_iter()
+            .collect();
+
+        CompiledGraph { passes: compiled, final_barriers }
+    }
+
+    /// Record the frame: barriers, then each pass via `record_pass`
+    ///
+    /// `image_for` maps resources to the images backing them this frame;
+    /// barriers for resources without an image are skipped.
+    pub fn record(
+        &self,
+        device: &VulkanDevice,
+        command_buffer: vk::CommandBuffer,
+        image_for: impl Fn(FrameResource) -> Option<vk::Image>,
+        mut record_pass: impl FnMut(PassKind, vk::CommandBuffer) -> Result<()>,
+    ) -> Result<()> {
+        let compiled = self.compile();
+        for pass in &compiled.passes {
+            record_barriers(device, command_buffer, &pass.barriers, &image_for);
+            record_pass(pass.kind, command_buffer)?;
+        }
+        record_barriers(device, command_buffer, &compiled.final_barriers, &image_for);
+        Ok(())
+    }
+}
+
+/// Whether moving from `from` to `to` needs a barrier
+///
+/// Layout changes and any hazard involving a write do; read-after-read in
+/// the same layout does not.
+fn needs_barrier(from: ResourceState, to: ResourceState) -> bool {
+    from.layout != to.layout || from.writes() || to.writes()
+}
+
+fn record_barriers(
+    device: &VulkanDevice,
+    command_buffer: vk::CommandBuffer,
+    barriers: &[BarrierDesc],
+    image_for: &impl Fn(FrameResource) -> Option<vk::Image>,
+) {
+    let mut src_stage = vk::PipelineStageFlags::empty();
+    let mut dst_stage = vk::PipelineStageFlags::empty();
+    let image_barriers: Vec<vk::ImageMemoryBarrier> = barriers
+        .iter()
+        .filter_map(|barrier| {
+            let image = image_for(barrier.resource)?;
+            src_stage |= barrier.from.stage;
+            dst_stage |= barrier.to.stage;
+            Some(vk::ImageMemoryBarrier {
+                src_access_mask: barrier.from.access,
+                dst_access_mask: barrier.to.access,
+                old_layout: barrier.from.layout,
+                new_layout: barrier.to.layout,
+                src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                image,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+                ..Default::default()
+            })
+        })
+        .collect();
+
+    if image_barriers.is_empty() {
+        return;
+    }
+
+    unsafe {
+        device.handle().cmd_pipeline_barrier(
+            command_buffer,
+            src_stage,
+            dst_stage,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &image_barriers,
+        );
+    }
+}
@@ -19,16 +19,49 @@ pub mod descriptor;
 pub mod surface_renderer;
 pub mod surface_pipeline;
 pub mod compositor_renderer;
+pub mod dimming;
+pub mod blur;
+pub mod blur_pipeline;
+pub mod neomorphism;
+pub mod render_scale;
+pub mod frame_graph;
+pub mod staging;
+pub mod mipmap;
+pub mod antialiasing;
+pub mod present_damage;
+pub mod pipeline_cache;
+pub mod readback;
+pub mod dmabuf_formats;
+pub mod upload_queue;
+pub mod ui;
+pub mod ui_pipeline;
 
 #[cfg(test)]
 mod tests;
 
 pub use instance::VulkanInstance;
 pub use device::VulkanDevice;
-pub use swapchain::Swapchain;
+pub use swapchain::{PresentMode, Swapchain};
 pub use surface_renderer::{SurfaceRenderer, SurfaceTexture, SurfaceBuffer};
 pub use surface_pipeline::{SurfacePipeline, SurfacePushConstants, SurfaceVertex};
 pub use compositor_renderer::CompositorRenderer;
+pub use dimming::{DimmingSettings, FocusDimmer};
+pub use blur::{BlurQuality, BlurState, BlurVariant, SurfaceBlurRequest};
+pub use blur_pipeline::{BlurBackdrop, BlurPipeline, BlurPushConstants};
+pub use neomorphism::{neomorphic_primitives, NeomorphicEffect, NeomorphicParams, NeomorphicShadows, ShadowLayer};
+pub use frame_graph::{FrameGraph, FrameResource, PassDesc, PassKind, ResourceUsage};
+pub use sync::{CompletionFence, TimelineSemaphore};
+pub use staging::StagingRing;
+pub use mipmap::MipChain;
+pub use antialiasing::{UiAntialiasing, UiMsaaTarget};
+pub use present_damage::PresentDamage;
+pub use pipeline_cache::PipelineCache;
+pub use readback::{PixelReadback, ReadbackPixels, ReadbackRegion};
+pub use dmabuf_formats::{DmabufFormat, DRM_FORMAT_MOD_LINEAR};
+pub use upload_queue::{UploadPriority, UploadQueue};
+pub use memory::{MemoryBudgetMonitor, MemoryPressure, MemoryUsage};
+pub use ui::{UiFilter, UiImage, UiPrimitive};
+pub use ui_pipeline::{UiPipeline, UiPushConstants};
 
 /// Main Vulkan renderer context
 pub struct VulkanRenderer {
@@ -36,6 +69,10 @@ pub struct VulkanRenderer {
     device: Option<VulkanDevice>,
     swapchain: Option<Swapchain>,
     compositor_renderer: Option<CompositorRenderer>,
+    /// Present ID of the last frame not yet known to be on screen
+    pending_present: Option<u64>,
+    /// Present mode requested for the swapchain
+    present_mode: PresentMode,
 }
 
 impl VulkanRenderer {
@@ -52,6 +89,8 @@ impl VulkanRenderer {
             device: Some(device),
             swapchain: None,
             compositor_renderer: Some(compositor_renderer),
+            pending_present: None,
+            present_mode: PresentMode::default(),
         })
     }
     
@@ -62,7 +101,7 @@ impl VulkanRenderer {
             _ => return Err(CompositorError::runtime("Vulkan instance or device not available")),
         };
         
-        let swapchain = Swapchain::new(instance, device, surface, width, height)?;
+        let swapchain = Swapchain::new(instance, device, surface, width, height, self.present_mode)?;
         
         // Initialize compositor renderer with swapchain details
         if let (Some(ref mut compositor_renderer), Some(ref swapchain)) = 
@@ -73,6 +112,7 @@ impl VulkanRenderer {
                 swapchain.extent(),
                 swapchain.format(),
             )?;
+            compositor_renderer.set_swapchain_readable(swapchain.supports_readback());
         }
         
         self.swapchain = Some(swapchain);
@@ -128,6 +168,38 @@ impl VulkanRenderer {
         Ok(())
     }
 
+    /// Upload queued surface content now instead of when the next frame starts
+    pub fn flush_surface_uploads(&mut self) -> Result<()> {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.flush_uploads()?;
+        }
+        Ok(())
+    }
+    
+    /// Set the time a frame may take before surface uploads are limited
+    ///
+    /// Frames over budget coalesce commits and upload the focused and
+    /// visible surfaces first; `None` never limits uploads.
+    pub fn set_frame_budget(&mut self, budget: Option<std::time::Duration>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_frame_budget(budget);
+        }
+    }
+    
+    /// Set the surfaces not shown right now, e.g. on other workspaces
+    pub fn set_hidden_surfaces(&mut self, surface_ids: Vec<u32>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_hidden_surfaces(surface_ids);
+        }
+    }
+    
+    /// Set the surfaces the next frame leaves out, e.g. windows on other outputs
+    pub fn set_offscreen_surfaces(&mut self, surface_ids: Vec<u32>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_offscreen_surfaces(surface_ids);
+        }
+    }
+    
     /// Remove a surface texture
     pub fn remove_surface(&mut self, surface_id: u32) -> Result<()> {
         if let Some(ref mut compositor_renderer) = self.compositor_renderer {
@@ -137,22 +209,350 @@ impl VulkanRenderer {
         Ok(())
     }
     
-    /// End frame and present
+    /// Set the focused surface used for the active-window highlight
+    pub fn set_focused_surface(&mut self, surface_id: Option<u32>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_focused_surface(surface_id);
+        }
+    }
+    
+    /// Configure dimming of unfocused surfaces
+    pub fn set_dimming(&mut self, settings: DimmingSettings) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_dimming(settings);
+        }
+    }
+    
+    /// Set the surfaces whose apps render at another scale than their output's
+    pub fn set_rescaled_surfaces(&mut self, surface_ids: Vec<u32>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_rescaled_surfaces(surface_ids);
+        }
+    }
+    
+    /// Set the order surfaces are drawn in, from bottom to top
+    pub fn set_stacking_order(&mut self, surface_ids: Vec<u32>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_stacking_order(surface_ids);
+        }
+    }
+    
+    /// Set or clear background blur behind a surface
+    pub fn set_surface_blur(&mut self, surface_id: u32, request: Option<SurfaceBlurRequest>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_surface_blur(surface_id, request);
+        }
+    }
+    
+    /// Set the blur behind every surface, clearing it for surfaces left out
+    pub fn set_surface_blurs(&mut self, requests: Vec<(u32, SurfaceBlurRequest)>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_surface_blurs(requests);
+        }
+    }
+    
+    /// Set the blur radius of surfaces whose blur rule has none
+    pub fn set_default_blur_radius(&mut self, radius: f32) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_default_blur_radius(radius);
+        }
+    }
+    
+    /// Set the cost of background blur
+    pub fn set_blur_quality(&mut self, quality: BlurQuality) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_blur_quality(quality);
+        }
+    }
+
+    /// Switch between glassmorphic and neomorphic rendering
+    pub fn set_neomorphism(&mut self, enabled: bool, light_angle: f32, surface_color: [f32; 4]) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_neomorphism(enabled, light_angle, surface_color);
+        }
+    }
+
+    /// Set how far each surface is extruded in the neomorphic mode, by surface ID
+    pub fn set_neomorphic_surfaces(&mut self, surfaces: Vec<(u32, NeomorphicParams)>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_neomorphic_surfaces(surfaces);
+        }
+    }
+    
+    /// Set antialiasing quality for compositor-drawn edges
+    pub fn set_ui_antialiasing(&mut self, antialiasing: UiAntialiasing) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_ui_antialiasing(antialiasing);
+        }
+    }
+    
+    /// Set the radius of rounded window corners in pixels
+    pub fn set_corner_radius(&mut self, radius: f32) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_corner_radius(radius);
+        }
+    }
+    
+    /// Scale the intensity of every drop shadow; 1.0 draws them as set
+    pub fn set_shadow_scale(&mut self, scale: f32) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_shadow_scale(scale);
+        }
+    }
+    
+    /// Set the corner radius of surfaces whose class differs from the
+    /// default, by surface ID
+    pub fn set_surface_corner_radii(&mut self, radii: Vec<(u32, f32)>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_surface_corner_radii(radii);
+        }
+    }
+    
+    /// Set the surfaces drawn with a border, by surface ID, with its width
+    /// in pixels and color
+    pub fn set_surface_borders(&mut self, borders: Vec<(u32, f32, [f32; 4])>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_surface_borders(borders);
+        }
+    }
+    
+    /// Set the color shown where no surface or wallpaper covers the output
+    pub fn set_background_color(&mut self, color: [f32; 4]) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_background_color(color);
+        }
+    }
+    
+    /// Set the surfaces casting a drop shadow, with its intensity
+    pub fn set_surface_shadows(&mut self, shadows: Vec<(u32, f32)>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_surface_shadows(shadows);
+        }
+    }
+    
+    /// Set where surfaces are in global compositor coordinates
+    pub fn set_surface_geometry(&mut self, geometry: Vec<(u32, compositor_utils::math::Rect)>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_surface_geometry(geometry);
+        }
+    }
+    
+    /// Set the UI drawn over the surfaces, in global compositor coordinates
+    pub fn set_ui(&mut self, primitives: Vec<UiPrimitive>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_ui(primitives);
+        }
+    }
+    
+    /// Set the wallpapers drawn below the surfaces, in global compositor coordinates
+    pub fn set_wallpaper(&mut self, primitives: Vec<UiPrimitive>) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_wallpaper(primitives);
+        }
+    }
+    
+    /// Set the geometry of the output the next frame is drawn for
+    pub fn set_output_region(&mut self, region: compositor_utils::math::Rect) {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_output_region(region);
+        }
+    }
+    
+    /// Switch the present mode, recreating the swapchain
+    ///
+    /// Waits for the GPU to go idle first, so expect one dropped frame.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) -> Result<()> {
+        if present_mode == self.present_mode {
+            return Ok(());
+        }
+        self.present_mode = present_mode;
+        if self.recreate_swapchain()? {
+            info!("Present mode set to {:?} (requested {:?})", self.present_mode(), present_mode);
+        }
+        Ok(())
+    }
+    
+    /// Recover after the system resumed from suspend
+    ///
+    /// Fails if the GPU was lost while suspended. Otherwise frame
+    /// synchronization is re-armed and the swapchain recreated, since its
+    /// images may have lost their contents or gone out of date, and the next
+    /// frame is drawn in full.
+    pub fn resume(&mut self) -> Result<()> {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.resume()?;
+        }
+        self.recreate_swapchain()?;
+        self.pending_present = None;
+        self.damage_all();
+        Ok(())
+    }
+    
+    /// Recreate the swapchain with the requested present mode; returns
+    /// whether there was one to recreate
+    ///
+    /// Waits for the GPU to go idle first.
+    fn recreate_swapchain(&mut self) -> Result<bool> {
+        let (Some(instance), Some(device), Some(old_swapchain)) = (&self.instance, &self.device, &self.swapchain) else {
+            return Ok(false);
+        };
+        
+        // Swapchain images may not change while a frame using them is in flight
+        unsafe { device.handle().device_wait_idle()? };
+        let swapchain = old_swapchain.recreate(instance, device, self.present_mode)?;
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.recreate_swapchain(
+                swapchain.images().to_vec(),
+                swapchain.image_views().to_vec(),
+                swapchain.extent(),
+            )?;
+            compositor_renderer.set_swapchain_readable(swapchain.supports_readback());
+        }
+        self.swapchain = Some(swapchain);
+        self.pending_present = None;
+        Ok(true)
+    }
+    
+    /// Copy a region of the next frame back to the CPU, e.g. for the color
+    /// picker's loupe; take the pixels with `take_readback`
+    pub fn request_readback(&mut self, region: ReadbackRegion) -> Result<()> {
+        if !self.swapchain.as_ref().is_some_and(Swapchain::supports_readback) {
+            return Err(CompositorError::graphics("Frames cannot be read back from this swapchain"));
+        }
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.request_readback(region);
+        }
+        Ok(())
+    }
+    
+    /// Pixels requested with `request_readback`, once their frame has completed
+    pub fn take_readback(&mut self) -> Result<Option<ReadbackPixels>> {
+        match self.compositor_renderer {
+            Some(ref mut compositor_renderer) => compositor_renderer.take_readback(),
+            None => Ok(None),
+        }
+    }
+    
+    /// Present mode of the swapchain, once created
+    pub fn present_mode(&self) -> Option<PresentMode> {
+        self.swapchain.as_ref().map(Swapchain::present_mode)
+    }
+    
+    /// Render at a fraction of native resolution and upscale on scanout
+    pub fn set_render_scale(&mut self, scale: f32) -> Result<()> {
+        if let Some(ref mut compositor_renderer) = self.compositor_renderer {
+            compositor_renderer.set_render_scale(scale)?;
+        }
+        Ok(())
+    }
+    
+    /// Mark a region of the output, in pixels, as changed since the last frame
+    ///
+    /// Passed to the presentation engine with the next present where
+    /// VK_KHR_incremental_present is supported.
+    pub fn add_damage(&mut self, rect: [i32; 4]) {
+        if let Some(ref mut swapchain) = self.swapchain {
+            swapchain.add_damage(rect);
+        }
+    }
+    
+    /// Mark a region of the output being drawn, in compositor coordinates,
+    /// as changed since the last frame
+    pub fn add_region_damage(&mut self, rect: compositor_utils::math::Rect) {
+        let damage = self.compositor_renderer.as_ref().map(|compositor_renderer| compositor_renderer.damage_rect(rect));
+        if let Some(ref mut swapchain) = self.swapchain {
+            match damage {
+                Some(Some(rect)) => swapchain.add_damage(rect),
+                _ => swapchain.damage_all(),
+            }
+        }
+    }
+    
+    /// Mark the whole output as changed since the last frame
+    pub fn damage_all(&mut self) {
+        if let Some(ref mut swapchain) = self.swapchain {
+            swapchain.damage_all();
+        }
+    }
+    
+    /// End frame and present the image acquired by `begin_frame`
     pub fn end_frame(&mut self) -> Result<()> {
-        // Note: In a real implementation, frame_index and image_index would be tracked properly
-        // For now, using placeholder values for compilation
+        // Note: In a real implementation, frame_index would be tracked properly
         if let Some(ref mut compositor_renderer) = self.compositor_renderer {
             if let Some(ref mut swapchain) = self.swapchain {
-                let image_index = swapchain.acquire_next_image()?;
-                let _command_buffer = compositor_renderer.render_frame(0, image_index)?;
+                let image_index = swapchain.current_image();
+                compositor_renderer.render_frame(0, image_index)?;
+                compositor_renderer.submit_frame(
+                    0,
+                    swapchain.image_available_semaphore(),
+                    swapchain.render_finished_semaphore(),
+                )?;
                 
                 // Present the frame
-                swapchain.present()?;
+                if let Some(present_id) = swapchain.present()? {
+                    self.pending_present = Some(present_id);
+                }
             }
         }
         Ok(())
     }
     
+    /// Wait for the last presented frame to reach the display
+    ///
+    /// Returns the time the frame became visible when VK_KHR_present_wait is
+    /// available, for the frame limiter and presentation-time feedback.
+    /// Returns `None` without blocking when it is not, or on timeout, so
+    /// callers fall back to timer-based pacing.
+    pub fn wait_for_present(&mut self, timeout: std::time::Duration) -> Result<Option<std::time::Instant>> {
+        let (Some(swapchain), Some(present_id)) = (&self.swapchain, self.pending_present) else {
+            return Ok(None);
+        };
+        
+        let timeout_ns = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+        if swapchain.wait_for_present(present_id, timeout_ns)? {
+            self.pending_present = None;
+            return Ok(Some(std::time::Instant::now()));
+        }
+        Ok(None)
+    }
+    
+    /// Sync file that becomes readable once the last frame finishes rendering
+    ///
+    /// Lets the render loop sleep until then instead of blocking on the GPU.
+    /// `None` when the frame already finished or the driver cannot export
+    /// fences; callers then go on without waiting.
+    pub fn take_frame_completion(&mut self) -> Option<std::os::fd::OwnedFd> {
+        self.compositor_renderer
+            .as_mut()
+            .and_then(|compositor_renderer| compositor_renderer.take_frame_completion())
+    }
+    
+    /// Compact GPU memory and save the pipeline cache after a while without frames
+    ///
+    /// Call while no output has a frame scheduled; returns whether
+    /// maintenance ran.
+    pub fn maintain_if_idle(&mut self, now: std::time::Instant) -> Result<bool> {
+        match self.compositor_renderer {
+            Some(ref mut compositor_renderer) => compositor_renderer.maintain_if_idle(now),
+            None => Ok(false),
+        }
+    }
+    
+    /// GPU memory usage against the driver-reported budget
+    pub fn memory_usage(&self) -> Option<MemoryUsage> {
+        self.compositor_renderer
+            .as_ref()
+            .map(|compositor_renderer| compositor_renderer.memory_usage().clone())
+    }
+    
+    /// DMA-BUF formats and modifiers the GPU can sample, to advertise to clients
+    pub fn dmabuf_formats(&self) -> Vec<DmabufFormat> {
+        match (&self.instance, &self.device) {
+            (Some(instance), Some(device)) => dmabuf_formats::query_dmabuf_formats(instance, device),
+            _ => Vec::new(),
+        }
+    }
+    
     /// Get renderer information for debugging
     pub fn get_info(&self) -> RendererInfo {
         let (instance, device) = match (&self.instance, &self.device) {
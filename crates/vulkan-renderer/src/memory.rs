@@ -1,2 +1,173 @@
-// Memory management placeholder - will implement gpu-allocator integration
-pub struct VulkanMemoryManager;
+// GPU memory budget monitoring
+//
+// Tracks device-local memory usage against the budget reported by the driver
+// through VK_EXT_memory_budget, so the renderer can shed memory (staging
+// buffers, expensive effects) before allocations start failing on 4K
+// multi-window sessions. Without the extension, heap sizes stand in for the
+// budget and usage is unknown.
+
+use ash::vk;
+use crate::{VulkanDevice, VulkanInstance};
+use std::time::{Duration, Instant};
+
+/// Fraction of the budget at which memory is considered under pressure
+pub const HIGH_PRESSURE_FRACTION: f32 = 0.85;
+
+/// Fraction of the budget at which memory is considered critical
+pub const CRITICAL_PRESSURE_FRACTION: f32 = 0.95;
+
+/// How often the budget is re-queried
+pub const BUDGET_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Budget and usage of one memory heap, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapBudget {
+    pub size: u64,
+    pub budget: u64,
+    pub usage: u64,
+    pub device_local: bool,
+}
+
+/// Memory usage across all heaps
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub heaps: Vec<HeapBudget>,
+    /// Whether budgets and usage come from VK_EXT_memory_budget
+    pub from_driver: bool,
+}
+
+impl MemoryUsage {
+    /// Total device-local usage in bytes
+    pub fn device_local_usage(&self) -> u64 {
+        self.heaps.iter().filter(|heap| heap.device_local).map(|heap| heap.usage).sum()
+    }
+
+    /// Total device-local budget in bytes
+    pub fn device_local_budget(&self) -> u64 {
+        self.heaps.iter().filter(|heap| heap.device_local).map(|heap| heap.budget).sum()
+    }
+
+    /// Pressure level of the most loaded device-local heap
+    pub fn pressure(&self) -> MemoryPressure {
+        let fraction = self
+            .heaps
+            .iter()
+            .filter(|heap| heap.device_local && heap.budget > 0)
+            .map(|heap| heap.usage as f32 / heap.budget as f32)
+            .fold(0.0, f32::max);
+
+        if fraction >= CRITICAL_PRESSURE_FRACTION {
+            MemoryPressure::Critical
+        } else if fraction >= HIGH_PRESSURE_FRACTION {
+            MemoryPressure::High
+        } else {
+            MemoryPressure::Normal
+        }
+    }
+}
+
+/// How close device-local memory is to its budget
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemoryPressure {
+    #[default]
+    Normal,
+    /// Release caches and degrade expensive effects
+    High,
+    /// Release everything that can be recreated on demand
+    Critical,
+}
+
+/// Periodically queries memory budgets and reports pressure changes
+pub struct MemoryBudgetMonitor {
+    instance: VulkanInstance,
+    physical_device: vk::PhysicalDevice,
+    budget_supported: bool,
+    usage: MemoryUsage,
+    pressure: MemoryPressure,
+    last_poll: Option<Instant>,
+}
+
+impl MemoryBudgetMonitor {
+    /// Create a monitor for a device
+    pub fn new(instance: VulkanInstance, device: &VulkanDevice) -> Self {
+        Self {
+            instance,
+            physical_device: device.physical_device(),
+            budget_supported: device.supports_memory_budget(),
+            usage: MemoryUsage::default(),
+            pressure: MemoryPressure::Normal,
+            last_poll: None,
+        }
+    }
+
+    /// Most recently queried usage
+    pub fn usage(&self) -> &MemoryUsage {
+        &self.usage
+    }
+
+    /// Current pressure level
+    pub fn pressure(&self) -> MemoryPressure {
+        self.pressure
+    }
+
+    /// Re-query budgets if the poll interval has passed
+    ///
+    /// Returns the new pressure level when it changed.
+    pub fn poll(&mut self, now: Instant) -> Option<MemoryPressure> {
+        if self
+            .last_poll
+            .is_some_and(|last| now.saturating_duration_since(last) < BUDGET_POLL_INTERVAL)
+        {
+            return None;
+        }
+        self.last_poll = Some(now);
+
+        self.usage = self.query();
+        let pressure = self.usage.pressure();
+        if pressure == self.pressure {
+            return None;
+        }
+        self.pressure = pressure;
+        Some(pressure)
+    }
+
+    fn query(&self) -> MemoryUsage {
+        let mut budget_properties = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        let mut properties = vk::PhysicalDeviceMemoryProperties2 {
+            p_next: if self.budget_supported {
+                &mut budget_properties as *mut _ as *mut std::ffi::c_void
+            } else {
+                std::ptr::null_mut()
+            },
+            ..Default::default()
+        };
+        unsafe {
+            self.instance
+                .handle()
+                .get_physical_device_memory_properties2(self.physical_device, &mut properties);
+        }
+
+        let memory_properties = properties.memory_properties;
+        let heaps = (0..memory_properties.memory_heap_count as usize)
+            .map(|index| {
+                let heap = memory_properties.memory_heaps[index];
+                let (budget, usage) = if self.budget_supported {
+                    (budget_properties.heap_budget[index], budget_properties.heap_usage[index])
+                } else {
+                    (heap.size, 0)
+                };
+                HeapBudget {
+                    size: heap.size,
+                    budget,
+                    usage,
+                    device_local: heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+                }
+            })
+            .collect();
+
+        MemoryUsage {
+            heaps,
+            from_driver: self.budget_supported,
+        }
+    }
+}
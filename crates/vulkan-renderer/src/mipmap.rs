@@ -0,0 +1,342 @@
+// Mip chains for scaled surface previews
+//
+// Thumbnails and the overview draw windows far below their native size, which
+// aliases badly when sampling a single-level texture. The first time a surface
+// is drawn scaled down, its texture is blitted into a separate image with a
+// full mip chain that is sampled with trilinear filtering. The chain is
+// dropped with the texture, so surfaces never shown scaled pay nothing.
+
+use ash::vk;
+use compositor_utils::prelude::*;
+use crate::{VulkanDevice, VulkanInstance};
+
+/// Scale below which previews sample the mip chain
+pub const MIPMAP_SCALE_THRESHOLD: f32 = 0.9;
+
+/// Number of mip levels down to 1x1 for an extent
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Whether a texture of `format` can be downsampled with linear blits
+pub fn supports_mipmap_generation(instance: &VulkanInstance, device: &VulkanDevice, format: vk::Format) -> bool {
+    let properties = unsafe {
+        instance.handle().get_physical_device_format_properties(device.physical_device(), format)
+    };
+    properties.optimal_tiling_features.contains(
+        vk::FormatFeatureFlags::BLIT_SRC
+            | vk::FormatFeatureFlags::BLIT_DST
+            | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+    )
+}
+
+/// Create a sampler that filters between mip levels
+pub fn create_trilinear_sampler(device: &VulkanDevice) -> Result<vk::Sampler> {
+    let sampler_info = vk::SamplerCreateInfo {
+        mag_filter: vk::Filter::LINEAR,
+        min_filter: vk::Filter::LINEAR,
+        mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+        address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        max_anisotropy: 1.0,
+        border_color: vk::BorderColor::INT_OPAQUE_BLACK,
+        compare_op: vk::CompareOp::ALWAYS,
+        min_lod: 0.0,
+        max_lod: vk::LOD_CLAMP_NONE,
+        ..Default::default()
+    };
+
+    unsafe {
+        device.handle().create_sampler(&sampler_info, None)
+            .map_err(|e| CompositorError::graphics(format!("Failed to create trilinear sampler: {}", e)))
+    }
+}
+
+/// Mipmapped copy of a surface texture
+#[derive(Debug)]
+pub struct MipChain {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    image_view: vk::ImageView,
+    levels: u32,
+}
+
+impl MipChain {
+    /// Allocate a mipmapped image matching a texture
+    pub fn new(
+        instance: &VulkanInstance,
+        device: &VulkanDevice,
+        width: u32,
+        height: u32,
+        format: vk::Format,
+    ) -> Result<Self> {
+        let levels = mip_level_count(width, height);
+        let image_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            extent: vk::Extent3D { width, height, depth: 1 },
+            mip_levels: levels,
+            array_layers: 1,
+            format,
+            tiling: vk::ImageTiling::OPTIMAL,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            usage: vk::ImageUsageFlags::TRANSFER_SRC
+                | vk::ImageUsageFlags::TRANSFER_DST
+                | vk::ImageUsageFlags::SAMPLED,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            samples: vk::SampleCountFlags::TYPE_1,
+            ..Default::default()
+        };
+
+        let image = unsafe { device.handle().create_image(&image_info, None)? };
+        let memory_requirements = unsafe { device.handle().get_image_memory_requirements(image) };
+
+        let memory_properties = unsafe {
+            instance.handle().get_physical_device_memory_properties(device.physical_device())
+        };
+        let memory_type_index = (0..memory_properties.memory_type_count)
+            .find(|&i| {
+                (memory_requirements.memory_type_bits & (1 << i)) != 0
+                    && memory_properties.memory_types[i as usize]
+                        .property_flags
+                        .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+            })
+            .ok_or_else(|| CompositorError::graphics("Failed to find memory type for mip chain"))?;
+
+        let alloc_info = vk::MemoryAllocateInfo {
+            allocation_size: memory_requirements.size,
+            memory_type_index,
+            ..Default::default()
+        };
+
+        let memory = unsafe { device.handle().allocate_memory(&alloc_info, None)? };
+        unsafe { device.handle().bind_image_memory(image, memory, 0)? };
+
+        let image_view_info = vk::ImageViewCreateInfo {
+            image,
+            view_type: vk::ImageViewType::TYPE_2D,
+            format,
+            subresource_range: subresource_range(0, levels),
+            ..Default::default()
+        };
+        let image_view = unsafe { device.handle().create_image_view(&image_view_info, None)? };
+
+        debug!("Created {}x{} mip chain with {} levels", width, height, levels);
+
+        Ok(Self {
+            image,
+            memory,
+            image_view,
+            levels,
+        })
+    }
+
+    /// View over all levels, for trilinear sampling
+    pub fn image_view(&self) -> vk::ImageView {
+        self.image_view
+    }
+
+    /// Number of mip levels
+    pub fn levels(&self) -> u32 {
+        self.levels
+    }
+
+    /// Record copying `source` into level 0 and downsampling the rest
+    ///
+    /// Expects `source` in SHADER_READ_ONLY_OPTIMAL and leaves both images
+    /// ready for sampling.
+    pub fn record_generate(
+        &self,
+        device: &VulkanDevice,
+        command_buffer: vk::CommandBuffer,
+        source: vk::Image,
+        width: u32,
+        height: u32,
+    ) {
+        let handle = device.handle();
+        unsafe {
+            // Source to transfer source, whole chain to transfer destination
+            let barriers = [
+                image_barrier(
+                    source,
+                    subresource_range(0, 1),
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::AccessFlags::SHADER_READ,
+                    vk::AccessFlags::TRANSFER_READ,
+                ),
+                image_barrier(
+                    self.image,
+                    subresource_range(0, self.levels),
+                    vk::ImageLayout::UNDEFINED,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::TRANSFER_WRITE,
+                ),
+            ];
+            handle.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            );
+
+            // Level 0 is an exact copy of the texture
+            let (mut level_width, mut level_height) = (width, height);
+            let copy = vk::ImageBlit {
+                src_subresource: subresource_layers(0),
+                src_offsets: [vk::Offset3D::default(), extent_offset(width, height)],
+                dst_subresource: subresource_layers(0),
+                dst_offsets: [vk::Offset3D::default(), extent_offset(width, height)],
+            };
+            handle.cmd_blit_image(
+                command_buffer,
+                source,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[copy],
+                vk::Filter::NEAREST,
+            );
+
+            // Each level is downsampled from the one above it
+            for level in 1..self.levels {
+                handle.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[image_barrier(
+                        self.image,
+                        subresource_range(level - 1, 1),
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                        vk::AccessFlags::TRANSFER_READ,
+                    )],
+                );
+
+                let next_width = (level_width / 2).max(1);
+                let next_height = (level_height / 2).max(1);
+                let blit = vk::ImageBlit {
+                    src_subresource: subresource_layers(level - 1),
+                    src_offsets: [vk::Offset3D::default(), extent_offset(level_width, level_height)],
+                    dst_subresource: subresource_layers(level),
+                    dst_offsets: [vk::Offset3D::default(), extent_offset(next_width, next_height)],
+                };
+                handle.cmd_blit_image(
+                    command_buffer,
+                    self.image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    self.image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[blit],
+                    vk::Filter::LINEAR,
+                );
+                level_width = next_width;
+                level_height = next_height;
+            }
+
+            // Everything back to sampling
+            let last_level = self.levels - 1;
+            let barriers = [
+                image_barrier(
+                    source,
+                    subresource_range(0, 1),
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_READ,
+                    vk::AccessFlags::SHADER_READ,
+                ),
+                image_barrier(
+                    self.image,
+                    subresource_range(0, last_level),
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_READ,
+                    vk::AccessFlags::SHADER_READ,
+                ),
+                image_barrier(
+                    self.image,
+                    subresource_range(last_level, 1),
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::SHADER_READ,
+                ),
+            ];
+            // A single-level chain has no levels in TRANSFER_SRC_OPTIMAL
+            let barriers: Vec<_> = barriers
+                .into_iter()
+                .filter(|barrier| barrier.subresource_range.level_count > 0)
+                .collect();
+            handle.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            );
+        }
+    }
+
+    /// Destroy the chain's Vulkan objects
+    pub fn destroy(&self, device: &VulkanDevice) {
+        unsafe {
+            device.handle().destroy_image_view(self.image_view, None);
+            device.handle().destroy_image(self.image, None);
+            device.handle().free_memory(self.memory, None);
+        }
+    }
+}
+
+fn image_barrier(
+    image: vk::Image,
+    subresource_range: vk::ImageSubresourceRange,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+    src_access_mask: vk::AccessFlags,
+    dst_access_mask: vk::AccessFlags,
+) -> vk::ImageMemoryBarrier {
+    vk::ImageMemoryBarrier {
+        src_access_mask,
+        dst_access_mask,
+        old_layout,
+        new_layout,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        image,
+        subresource_range,
+        ..Default::default()
+    }
+}
+
+fn subresource_range(base_mip_level: u32, level_count: u32) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level,
+        level_count,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+fn subresource_layers(mip_level: u32) -> vk::ImageSubresourceLayers {
+    vk::ImageSubresourceLayers {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        mip_level,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+fn extent_offset(width: u32, height: u32) -> vk::Offset3D {
+    vk::Offset3D { x: width as i32, y: height as i32, z: 1 }
+}
@@ -0,0 +1,171 @@
+// Neomorphic surface effect
+//
+// In the neomorphism rendering mode surfaces are matte and extruded from a
+// background of the same color by two soft shadows: a dark one cast away
+// from the light and a highlight towards it. This module turns per-surface
+// extrusion settings into the two shadow layers drawn behind each surface,
+// as its rounded shape blurred with a Gaussian evaluated analytically by the
+// UI pipeline. Surfaces are not blurred behind in this mode.
+
+use crate::ui::UiPrimitive;
+use compositor_utils::math::Rect;
+use std::collections::HashMap;
+
+/// How far a surface is extruded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NeomorphicParams {
+    /// Shadow and highlight distance in pixels
+    pub distance: f32,
+    /// Shadow and highlight softness in pixels
+    pub blur: f32,
+    /// Strength of the shadow away from the light (0.0 - 1.0)
+    pub shadow_intensity: f32,
+    /// Strength of the highlight towards the light (0.0 - 1.0)
+    pub highlight_intensity: f32,
+}
+
+/// A blurred copy of a surface's shape drawn behind it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowLayer {
+    /// Offset from the surface in pixels
+    pub offset: [f32; 2],
+    /// Standard deviation of the blur in pixels
+    pub sigma: f32,
+    pub color: [f32; 4],
+}
+
+impl ShadowLayer {
+    /// The layer behind a rounded rectangle, for the UI pipeline to draw
+    pub fn primitive(&self, rect: Rect, corner_radius: f32) -> UiPrimitive {
+        UiPrimitive::Shadow {
+            rect: Rect::new(rect.x + self.offset[0], rect.y + self.offset[1], rect.width, rect.height),
+            corner_radius,
+            sigma: self.sigma,
+            color: self.color,
+        }
+    }
+}
+
+/// Shadow and highlight of one surface
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeomorphicShadows {
+    pub shadow: ShadowLayer,
+    pub highlight: ShadowLayer,
+}
+
+/// Neomorphic mode and the extrusion of each surface
+#[derive(Debug, Clone)]
+pub struct NeomorphicEffect {
+    enabled: bool,
+    /// Unit vector towards the light in screen coordinates, y down
+    light: [f32; 2],
+    surface_color: [f32; 4],
+    surfaces: HashMap<u32, NeomorphicParams>,
+}
+
+impl NeomorphicEffect {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            light: light_direction(135.0),
+            surface_color: [0.05, 0.05, 0.05, 1.0],
+            surfaces: HashMap::new(),
+        }
+    }
+
+    /// Turn the neomorphic mode on or off, with the direction the light comes
+    /// from in degrees counter-clockwise from the right and the matte color
+    /// surfaces are tinted from
+    pub fn set_mode(&mut self, enabled: bool, light_angle: f32, surface_color: [f32; 4]) {
+        self.enabled = enabled;
+        self.light = light_direction(light_angle);
+        self.surface_color = surface_color;
+    }
+
+    /// Whether surfaces are drawn neomorphic, so background blur is skipped
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Set the extrusion of every surface; others are drawn flat
+    pub fn set_surfaces(&mut self, surfaces: impl IntoIterator<Item = (u32, NeomorphicParams)>) {
+        self.surfaces = surfaces.into_iter().collect();
+    }
+
+    /// Forget a destroyed surface
+    pub fn remove_surface(&mut self, surface_id: u32) {
+        self.surfaces.remove(&surface_id);
+    }
+
+    /// Shadow and highlight to draw behind a surface, if it is extruded
+    pub fn shadows(&self, surface_id: u32) -> Option<NeomorphicShadows> {
+        if !self.enabled {
+            return None;
+        }
+        let params = self.surfaces.get(&surface_id)?;
+        if params.distance <= 0.0 {
+            return None;
+        }
+        Some(shadow_layers(self.light, params, self.surface_color))
+    }
+}
+
+impl Default for NeomorphicEffect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shadow and highlight of a rounded rectangle drawn by the UI pass, for
+/// compositor UI extruded like neomorphic surfaces
+///
+/// The light comes from `light_angle` degrees counter-clockwise from the
+/// right; `surface_color` is the matte color the shadows are tinted from.
+pub fn neomorphic_primitives(
+    rect: Rect,
+    corner_radius: f32,
+    params: &NeomorphicParams,
+    light_angle: f32,
+    surface_color: [f32; 4],
+) -> Vec<UiPrimitive> {
+    if params.distance <= 0.0 {
+        return Vec::new();
+    }
+    let shadows = shadow_layers(light_direction(light_angle), params, surface_color);
+    vec![shadows.shadow.primitive(rect, corner_radius), shadows.highlight.primitive(rect, corner_radius)]
+}
+
+/// Shadow cast away from the light and highlight towards it
+fn shadow_layers(light: [f32; 2], params: &NeomorphicParams, surface_color: [f32; 4]) -> NeomorphicShadows {
+    let [x, y] = light;
+    // Softness covers roughly three standard deviations, like a blur radius
+    let sigma = (params.blur / 3.0).max(0.5);
+    NeomorphicShadows {
+        shadow: ShadowLayer {
+            offset: [-x * params.distance, -y * params.distance],
+            sigma,
+            color: shade(surface_color, [0.0, 0.0, 0.0], params.shadow_intensity),
+        },
+        highlight: ShadowLayer {
+            offset: [x * params.distance, y * params.distance],
+            sigma,
+            color: shade(surface_color, [1.0, 1.0, 1.0], params.highlight_intensity),
+        },
+    }
+}
+
+/// Unit vector towards a light at `angle` degrees, with y pointing down
+fn light_direction(angle: f32) -> [f32; 2] {
+    let radians = angle.to_radians();
+    [radians.cos(), -radians.sin()]
+}
+
+/// Surface color moved halfway to `towards`, with `intensity` as alpha
+fn shade(color: [f32; 4], towards: [f32; 3], intensity: f32) -> [f32; 4] {
+    [
+        (color[0] + towards[0]) * 0.5,
+        (color[1] + towards[1]) * 0.5,
+        (color[2] + towards[2]) * 0.5,
+        intensity.clamp(0.0, 1.0),
+    ]
+}
@@ -0,0 +1,122 @@
+// Persistent pipeline cache
+//
+// Pipelines are created through one VkPipelineCache whose contents are
+// written to the user's cache directory while the compositor is idle and
+// loaded again at startup, so shaders are not recompiled on every login.
+// Cache data from another GPU or driver version is detected by its header
+// and discarded rather than handed to the driver.
+
+use ash::vk;
+use compositor_utils::prelude::*;
+use crate::VulkanDevice;
+use std::path::{Path, PathBuf};
+
+/// Size of VkPipelineCacheHeaderVersionOne
+const HEADER_SIZE: usize = 32;
+
+/// Pipeline cache shared by all pipelines of a device
+pub struct PipelineCache {
+    device: VulkanDevice,
+    cache: vk::PipelineCache,
+    /// File the cache is loaded from and saved to
+    path: Option<PathBuf>,
+    /// Size of the data last loaded or saved, to skip unchanged saves
+    saved_size: usize,
+}
+
+impl PipelineCache {
+    /// Create a cache, seeded from `path` when it holds data for this device
+    pub fn new(device: VulkanDevice, path: Option<PathBuf>) -> Result<Self> {
+        let initial_data = path
+            .as_deref()
+            .and_then(|path| std::fs::read(path).ok())
+            .filter(|data| {
+                let valid = is_compatible(data, device.properties());
+                if !valid {
+                    info!("Discarding pipeline cache from another GPU or driver");
+                }
+                valid
+            })
+            .unwrap_or_default();
+
+        let create_info = vk::PipelineCacheCreateInfo {
+            initial_data_size: initial_data.len(),
+            p_initial_data: initial_data.as_ptr().cast(),
+            ..Default::default()
+        };
+        let cache = unsafe { device.handle().create_pipeline_cache(&create_info, None)? };
+        debug!("Created pipeline cache with {} bytes of saved data", initial_data.len());
+
+        Ok(Self {
+            device,
+            cache,
+            path,
+            saved_size: initial_data.len(),
+        })
+    }
+
+    /// Handle to pass to pipeline creation
+    pub fn handle(&self) -> vk::PipelineCache {
+        self.cache
+    }
+
+    /// Write the cache to disk if pipelines were added since the last save
+    pub fn save(&mut self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let data = unsafe { self.device.handle().get_pipeline_cache_data(self.cache)? };
+        if data.len() == self.saved_size {
+            return Ok(());
+        }
+
+        write_atomically(path, &data)
+            .map_err(|e| CompositorError::graphics(format!("Failed to save pipeline cache: {}", e)))?;
+        debug!("Saved {} bytes of pipeline cache to {}", data.len(), path.display());
+        self.saved_size = data.len();
+        Ok(())
+    }
+}
+
+impl Drop for PipelineCache {
+    fn drop(&mut self) {
+        if let Err(e) = self.save() {
+            warn!("{}", e);
+        }
+        unsafe {
+            self.device.handle().destroy_pipeline_cache(self.cache, None);
+        }
+    }
+}
+
+/// Default cache file, under $XDG_CACHE_HOME or ~/.cache
+pub fn default_cache_path() -> Option<PathBuf> {
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache_home.join("custom-compositor").join("pipeline-cache.bin"))
+}
+
+/// Whether cache data was written by the same device and driver
+fn is_compatible(data: &[u8], properties: &vk::PhysicalDeviceProperties) -> bool {
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+    let word = |index: usize| u32::from_ne_bytes([data[index], data[index + 1], data[index + 2], data[index + 3]]);
+    word(0) as usize >= HEADER_SIZE
+        && word(4) == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+        && word(8) == properties.vendor_id
+        && word(12) == properties.device_id
+        && data[16..32] == properties.pipeline_cache_uuid
+}
+
+/// Replace `path` without leaving a truncated file behind on failure
+fn write_atomically(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let temporary = path.with_extension("tmp");
+    std::fs::write(&temporary, data)?;
+    std::fs::rename(&temporary, path)
+}
@@ -0,0 +1,199 @@
+// Output damage for incremental present
+//
+// Collects the regions of the output that changed since the last present so
+// they can be passed to VK_KHR_incremental_present. Display controllers and
+// remote presentation engines that honour the hint only scan out or transfer
+// the changed rectangles, which saves memory bandwidth on mostly idle 4K
+// desktops. The whole image counts as changed after swapchain creation, or
+// when the damage is too fragmented to be worth describing.
+
+use ash::vk;
+
+/// Rectangles beyond which damage is merged into its bounding box
+pub const MAX_DAMAGE_RECTS: usize = 16;
+
+/// Damage accumulated between two presents
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresentDamage {
+    /// Changed regions as (x, y, width, height) in image pixels
+    rects: Vec<[i32; 4]>,
+    /// The whole image changed
+    full: bool,
+}
+
+impl Default for PresentDamage {
+    fn default() -> Self {
+        // Nothing was presented yet
+        Self {
+            rects: Vec::new(),
+            full: true,
+        }
+    }
+}
+
+impl PresentDamage {
+    /// Create damage covering the whole image
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark a region as changed
+    pub fn add(&mut self, rect: [i32; 4]) {
+        if self.full || rect[2] <= 0 || rect[3] <= 0 {
+            return;
+        }
+        self.rects.push(rect);
+        if self.rects.len() > MAX_DAMAGE_RECTS {
+            self.rects = vec![bounding_box(&self.rects)];
+        }
+    }
+
+    /// Mark the whole image as changed
+    pub fn add_full(&mut self) {
+        self.full = true;
+        self.rects.clear();
+    }
+
+    /// Take the damage for a present of an image of size `extent`
+    ///
+    /// Returns `None` when the whole image changed, including when nothing
+    /// was reported: an empty region list means the same to the driver.
+    pub fn take(&mut self, extent: vk::Extent2D) -> Option<Vec<vk::RectLayerKHR>> {
+        let full = std::mem::replace(&mut self.full, false);
+        let rects = std::mem::take(&mut self.rects);
+        if full {
+            return None;
+        }
+
+        let regions: Vec<vk::RectLayerKHR> = rects
+            .iter()
+            .filter_map(|&rect| clip(rect, extent))
+            .collect();
+        (!regions.is_empty()).then_some(regions)
+    }
+}
+
+/// Smallest rectangle containing all of `rects`
+fn bounding_box(rects: &[[i32; 4]]) -> [i32; 4] {
+    let x0 = rects.iter().map(|r| r[0]).min().unwrap_or(0);
+    let y0 = rects.iter().map(|r| r[1]).min().unwrap_or(0);
+    let x1 = rects.iter().map(|r| r[0] + r[2]).max().unwrap_or(0);
+    let y1 = rects.iter().map(|r| r[1] + r[3]).max().unwrap_or(0);
+    [x0, y0, x1 - x0, y1 - y0]
+}
+
+/// Present region of `rect` within the image, if any part of it is inside
+fn clip(rect: [i32; 4], extent: vk::Extent2D) -> Option<vk::RectLayerKHR> {
+    let x0 = rect[0].max(0);
+    let y0 = rect[1].max(0);
+    let x1 = (rect[0] + rect[2]).min(extent.width as i32);
+    let y1 = (rect[1] + rect[3]).min(extent.height as i32);
+    if x1 <= x0 || y1 <= y0 {
+        return None;
+    }
+    Some(vk::RectLayerKHR {
+        offset: vk::Offset2D { x: x0, y: y0 },
+        extent: vk::Extent2D {
+            width: (x1 - x0) as u32,
+            height: (y1 - y0) as u32,
+        },
+        layer: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTENT: vk::Extent2D = vk::Extent2D { width: 1920, height: 1080 };
+
+    /// Damage taken for a present, as (x, y, width, height) rects
+    fn take(damage: &mut PresentDamage) -> Option<Vec<[i32; 4]>> {
+        damage.take(EXTENT).map(|rects| {
+            rects
+                .iter()
+                .map(|rect| {
+                    assert_eq!(rect.layer, 0);
+                    [rect.offset.x, rect.offset.y, rect.extent.width as i32, rect.extent.height as i32]
+                })
+                .collect()
+        })
+    }
+
+    /// Damage past the initial full present
+    fn presented() -> PresentDamage {
+        let mut damage = PresentDamage::new();
+        damage.take(EXTENT);
+        damage
+    }
+
+    #[test]
+    fn first_present_is_full() {
+        let mut damage = PresentDamage::new();
+        damage.add([0, 0, 10, 10]);
+        assert_eq!(take(&mut damage), None);
+    }
+
+    #[test]
+    fn reports_added_rects() {
+        let mut damage = presented();
+        damage.add([10, 20, 30, 40]);
+        damage.add([100, 100, 5, 5]);
+        assert_eq!(take(&mut damage), Some(vec![[10, 20, 30, 40], [100, 100, 5, 5]]));
+        // With no damage left the whole image counts as changed
+        assert_eq!(take(&mut damage), None);
+    }
+
+    #[test]
+    fn ignores_empty_rects() {
+        let mut damage = presented();
+        damage.add([10, 10, 0, 5]);
+        damage.add([10, 10, 5, -1]);
+        damage.add([1, 2, 3, 4]);
+        assert_eq!(take(&mut damage), Some(vec![[1, 2, 3, 4]]));
+    }
+
+    #[test]
+    fn merges_fragmented_damage_into_bounding_box() {
+        let mut damage = presented();
+        for i in 0..MAX_DAMAGE_RECTS as i32 {
+            damage.add([i * 10, i * 5, 4, 4]);
+        }
+        assert_eq!(take(&mut damage).map(|rects| rects.len()), Some(MAX_DAMAGE_RECTS));
+
+        for i in 0..=MAX_DAMAGE_RECTS as i32 {
+            damage.add([i * 10, i * 5, 4, 4]);
+        }
+        let last = MAX_DAMAGE_RECTS as i32;
+        let width = last * 10 + 4;
+        let height = last * 5 + 4;
+        assert_eq!(take(&mut damage), Some(vec![[0, 0, width, height]]));
+    }
+
+    #[test]
+    fn clamps_rects_to_the_image() {
+        let mut damage = presented();
+        damage.add([-10, -20, 30, 40]);
+        damage.add([1900, 1070, 50, 50]);
+        assert_eq!(take(&mut damage), Some(vec![[0, 0, 20, 20], [1900, 1070, 20, 10]]));
+    }
+
+    #[test]
+    fn damage_outside_the_image_is_full() {
+        let mut damage = presented();
+        damage.add([2000, 0, 10, 10]);
+        damage.add([0, -50, 10, 50]);
+        assert_eq!(take(&mut damage), None);
+    }
+
+    #[test]
+    fn full_damage_discards_rects() {
+        let mut damage = presented();
+        damage.add([1, 2, 3, 4]);
+        damage.add_full();
+        damage.add([5, 6, 7, 8]);
+        assert_eq!(take(&mut damage), None);
+        damage.add([5, 6, 7, 8]);
+        assert_eq!(take(&mut damage), Some(vec![[5, 6, 7, 8]]));
+    }
+}
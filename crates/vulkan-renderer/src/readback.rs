@@ -0,0 +1,323 @@
+// Reading composed frames back to the CPU
+//
+// Tools such as the color picker need the pixels the compositor showed. A
+// requested region of the swapchain image is copied into a persistently
+// mapped, host-visible buffer at the end of the next frame's command buffer,
+// and the pixels can be taken once that frame completes on the GPU. Nothing
+// stalls the frame loop; a region requested every frame arrives one or two
+// frames late.
+
+use ash::vk;
+use compositor_utils::geometry::{Physical, Rect};
+use compositor_utils::prelude::*;
+use crate::{VulkanDevice, VulkanInstance};
+
+/// Region of a frame to read back, in pixels of the swapchain image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadbackRegion {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ReadbackRegion {
+    /// The part of the region inside an image of `extent`
+    fn clamp(&self, extent: vk::Extent2D) -> Option<Self> {
+        let x = self.x.max(0);
+        let y = self.y.max(0);
+        let right = (self.x + self.width as i32).min(extent.width as i32);
+        let bottom = (self.y + self.height as i32).min(extent.height as i32);
+        (right > x && bottom > y).then(|| Self { x, y, width: (right - x) as u32, height: (bottom - y) as u32 })
+    }
+}
+
+impl From<Rect<Physical>> for ReadbackRegion {
+    /// Round an area of the output to whole pixels, at least one
+    fn from(rect: Rect<Physical>) -> Self {
+        Self {
+            x: rect.loc.x.round() as i32,
+            y: rect.loc.y.round() as i32,
+            width: rect.size.w.round().max(1.0) as u32,
+            height: rect.size.h.round().max(1.0) as u32,
+        }
+    }
+}
+
+/// Pixels read back from a frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadbackPixels {
+    /// Region as requested, telling apart the copies of different readers
+    pub requested: ReadbackRegion,
+    /// Region actually read, the requested one clipped to the frame
+    pub region: ReadbackRegion,
+    /// RGBA pixels, row by row
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl ReadbackPixels {
+    /// Pixel at a position of the frame, if it was read
+    pub fn pixel(&self, x: i32, y: i32) -> Option<[u8; 4]> {
+        let column = x.checked_sub(self.region.x)?;
+        let row = y.checked_sub(self.region.y)?;
+        if column < 0 || row < 0 || column >= self.region.width as i32 || row >= self.region.height as i32 {
+            return None;
+        }
+        self.pixels.get(row as usize * self.region.width as usize + column as usize).copied()
+    }
+}
+
+/// Mapped buffer the frame is copied into
+struct ReadbackBuffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    mapped: *const u8,
+    size: vk::DeviceSize,
+}
+
+/// Copy recorded into a frame, waiting for the frame to complete
+#[derive(Debug, Clone, Copy)]
+struct PendingCopy {
+    requested: ReadbackRegion,
+    region: ReadbackRegion,
+    format: vk::Format,
+    /// Timeline value of the frame, once submitted
+    value: Option<u64>,
+}
+
+/// Reads regions of composed frames back to the CPU
+pub struct PixelReadback {
+    instance: VulkanInstance,
+    device: VulkanDevice,
+    buffer: Option<ReadbackBuffer>,
+    requested: Option<ReadbackRegion>,
+    pending: Option<PendingCopy>,
+}
+
+impl PixelReadback {
+    /// Create a readback; its buffer is allocated on first use
+    pub fn new(instance: VulkanInstance, device: VulkanDevice) -> Self {
+        Self {
+            instance,
+            device,
+            buffer: None,
+            requested: None,
+            pending: None,
+        }
+    }
+
+    /// Read a region of the next frame, replacing an earlier request
+    pub fn request(&mut self, region: ReadbackRegion) {
+        self.requested = Some(region);
+    }
+
+    /// Record the copy of the requested region at the end of a frame
+    ///
+    /// `image` must be in PRESENT_SRC_KHR layout, as at the end of the frame
+    /// graph, and have been created with TRANSFER_SRC usage; it is returned
+    /// to PRESENT_SRC_KHR. Does nothing while an earlier copy is in flight.
+    pub fn record(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        image: vk::Image,
+        extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> Result<()> {
+        if self.pending.is_some() {
+            return Ok(());
+        }
+        let Some(requested) = self.requested.take() else {
+            return Ok(());
+        };
+        let Some(region) = requested.clamp(extent) else {
+            return Ok(());
+        };
+        if bytes_to_rgba(format).is_none() {
+            warn!("Cannot read back frames in format {:?}", format);
+            return Ok(());
+        }
+        let buffer = self.ensure_allocated(region.width as vk::DeviceSize * region.height as vk::DeviceSize * 4)?;
+
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let to_transfer = vk::ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::TRANSFER_WRITE,
+            dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+            old_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+            new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image,
+            subresource_range,
+            ..Default::default()
+        };
+        let to_present = vk::ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlags::TRANSFER_READ,
+            dst_access_mask: vk::AccessFlags::empty(),
+            old_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            new_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+            ..to_transfer
+        };
+        let to_host = vk::BufferMemoryBarrier {
+            src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            dst_access_mask: vk::AccessFlags::HOST_READ,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            buffer,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+            ..Default::default()
+        };
+        let copy = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: vk::Offset3D { x: region.x, y: region.y, z: 0 },
+            image_extent: vk::Extent3D { width: region.width, height: region.height, depth: 1 },
+        };
+
+        let device = self.device.handle();
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer],
+            );
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer,
+                &[copy],
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST | vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[to_host],
+                &[to_present],
+            );
+        }
+
+        self.pending = Some(PendingCopy { requested, region, format, value: None });
+        Ok(())
+    }
+
+    /// Note the timeline value of the frame a copy was recorded into
+    pub fn submitted(&mut self, value: u64) {
+        if let Some(pending) = self.pending.as_mut().filter(|pending| pending.value.is_none()) {
+            pending.value = Some(value);
+        }
+    }
+
+    /// Take the pixels of the last copy once its frame has completed, i.e.
+    /// the timeline reached `completed`
+    pub fn take(&mut self, completed: u64) -> Option<ReadbackPixels> {
+        let pending = self.pending.filter(|pending| pending.value.is_some_and(|value| value <= completed))?;
+        self.pending = None;
+        let buffer = self.buffer.as_ref()?;
+        let to_rgba = bytes_to_rgba(pending.format)?;
+
+        let count = pending.region.width as usize * pending.region.height as usize;
+        // The copy completed and the buffer is host coherent
+        let bytes = unsafe { std::slice::from_raw_parts(buffer.mapped, count * 4) };
+        let pixels = bytes.chunks_exact(4).map(|pixel| to_rgba([pixel[0], pixel[1], pixel[2], pixel[3]])).collect();
+        Some(ReadbackPixels { requested: pending.requested, region: pending.region, pixels })
+    }
+
+    /// Free the buffer, e.g. when no tool reads frames anymore
+    ///
+    /// Only possible while no copy is in flight.
+    pub fn release(&mut self) {
+        if self.pending.is_some() {
+            return;
+        }
+        if let Some(buffer) = self.buffer.take() {
+            unsafe {
+                self.device.handle().unmap_memory(buffer.memory);
+                self.device.handle().destroy_buffer(buffer.buffer, None);
+                self.device.handle().free_memory(buffer.memory, None);
+            }
+        }
+    }
+
+    fn ensure_allocated(&mut self, size: vk::DeviceSize) -> Result<vk::Buffer> {
+        if let Some(buffer) = self.buffer.as_ref().filter(|buffer| buffer.size >= size) {
+            return Ok(buffer.buffer);
+        }
+        self.release();
+
+        let buffer_info = vk::BufferCreateInfo {
+            size,
+            usage: vk::BufferUsageFlags::TRANSFER_DST,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = unsafe { self.device.handle().create_buffer(&buffer_info, None)? };
+
+        let memory_requirements = unsafe { self.device.handle().get_buffer_memory_requirements(buffer) };
+        let memory_properties = unsafe {
+            self.instance.handle().get_physical_device_memory_properties(self.device.physical_device())
+        };
+        let required = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+        let memory_type_index = (0..memory_properties.memory_type_count)
+            .find(|&i| {
+                (memory_requirements.memory_type_bits & (1 << i)) != 0
+                    && memory_properties.memory_types[i as usize].property_flags.contains(required)
+            })
+            .ok_or_else(|| CompositorError::graphics("Failed to find memory type for readback buffer"))?;
+
+        let alloc_info = vk::MemoryAllocateInfo {
+            allocation_size: memory_requirements.size,
+            memory_type_index,
+            ..Default::default()
+        };
+        let memory = unsafe { self.device.handle().allocate_memory(&alloc_info, None)? };
+
+        let mapped = unsafe {
+            self.device.handle().bind_buffer_memory(buffer, memory, 0)?;
+            self.device.handle().map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())? as *const u8
+        };
+
+        debug!("Created readback buffer with size: {} bytes", size);
+        self.buffer = Some(ReadbackBuffer { buffer, memory, mapped, size });
+        Ok(buffer)
+    }
+}
+
+// The mapped pointer is only read through `&mut self`
+unsafe impl Send for PixelReadback {}
+
+impl Drop for PixelReadback {
+    fn drop(&mut self) {
+        // Owners wait for their frames before dropping the readback
+        self.pending = None;
+        self.release();
+    }
+}
+
+/// Conversion of a texel of a swapchain format to RGBA
+fn bytes_to_rgba(format: vk::Format) -> Option<fn([u8; 4]) -> [u8; 4]> {
+    match format {
+        vk::Format::B8G8R8A8_SRGB | vk::Format::B8G8R8A8_UNORM => Some(|[b, g, r, a]| [r, g, b, a]),
+        vk::Format::R8G8B8A8_SRGB | vk::Format::R8G8B8A8_UNORM => Some(|rgba| rgba),
+        _ => None,
+    }
+}
@@ -0,0 +1,201 @@
+// Reduced-resolution rendering with upscale on scanout
+//
+// As an emergency performance mode for weak GPUs, an output's composition can
+// be rendered into an offscreen image at a fraction of its native resolution
+// (e.g. 0.75x of 4K) and blitted with linear filtering onto the swapchain
+// image before presentation.
+
+use ash::vk;
+use compositor_utils::prelude::*;
+use crate::{VulkanDevice, VulkanInstance};
+
+/// Smallest supported render scale
+pub const MIN_RENDER_SCALE: f32 = 0.25;
+
+/// Clamp a requested render scale to the supported range
+///
+/// Scales above 1.0 are not supported; non-finite values fall back to native.
+pub fn clamp_render_scale(scale: f32) -> f32 {
+    if scale.is_finite() {
+        scale.clamp(MIN_RENDER_SCALE, 1.0)
+    } else {
+        1.0
+    }
+}
+
+/// Extent of the offscreen target for a native extent and render scale
+pub fn scaled_extent(extent: vk::Extent2D, scale: f32) -> vk::Extent2D {
+    let scale = clamp_render_scale(scale);
+    vk::Extent2D {
+        width: ((extent.width as f32 * scale).round() as u32).max(1),
+        height: ((extent.height as f32 * scale).round() as u32).max(1),
+    }
+}
+
+/// Offscreen render target at reduced resolution
+pub struct ScaledTarget {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    image_view: vk::ImageView,
+    framebuffer: vk::Framebuffer,
+    extent: vk::Extent2D,
+}
+
+impl ScaledTarget {
+    /// Create a target usable as a color attachment and blit source
+    pub fn new(
+        instance: &VulkanInstance,
+        device: &VulkanDevice,
+        render_pass: vk::RenderPass,
+        extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> Result<Self> {
+        let image_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            extent: vk::Extent3D { width: extent.width, height: extent.height, depth: 1 },
+            mip_levels: 1,
+            array_layers: 1,
+            format,
+            tiling: vk::ImageTiling::OPTIMAL,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            usage: vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            samples: vk::SampleCountFlags::TYPE_1,
+            ..Default::default()
+        };
+
+        let image = unsafe { device.handle().create_image(&image_info, None)? };
+        let memory_requirements = unsafe { device.handle().get_image_memory_requirements(image) };
+
+        let memory_properties = unsafe {
+            instance.handle().get_physical_device_memory_properties(device.physical_device())
+        };
+        let memory_type_index = (0..memory_properties.memory_type_count)
+            .find(|&i| {
+                (memory_requirements.memory_type_bits & (1 << i)) != 0
+                    && memory_properties.memory_types[i as usize]
+                        .property_flags
+                        .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+            })
+            .ok_or_else(|| CompositorError::graphics("Failed to find memory type for scaled render target"))?;
+
+        let alloc_info = vk::MemoryAllocateInfo {
+            allocation_size: memory_requirements.size,
+            memory_type_index,
+            ..Default::default()
+        };
+
+        let memory = unsafe { device.handle().allocate_memory(&alloc_info, None)? };
+        unsafe { device.handle().bind_image_memory(image, memory, 0)? };
+
+        let image_view_info = vk::ImageViewCreateInfo {
+            image,
+            view_type: vk::ImageViewType::TYPE_2D,
+            format,
+            subresource_range: color_subresource_range(),
+            ..Default::default()
+        };
+        let image_view = unsafe { device.handle().create_image_view(&image_view_info, None)? };
+
+        let attachments = [image_view];
+        let framebuffer_info = vk::FramebufferCreateInfo {
+            render_pass,
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            width: extent.width,
+            height: extent.height,
+            layers: 1,
+            ..Default::default()
+        };
+        let framebuffer = unsafe { device.handle().create_framebuffer(&framebuffer_info, None)? };
+
+        debug!("Created {}x{} scaled render target", extent.width, extent.height);
+
+        Ok(Self {
+            image,
+            memory,
+            image_view,
+            framebuffer,
+            extent,
+        })
+    }
+
+    /// Framebuffer to render the composition into
+    pub fn framebuffer(&self) -> vk::Framebuffer {
+        self.framebuffer
+    }
+
+    /// Reduced render extent
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// Image the composition is rendered into
+    pub fn image(&self) -> vk::Image {
+        self.image
+    }
+
+    /// Record the upscale blit onto a swapchain image
+    ///
+    /// Expects the target in TRANSFER_SRC_OPTIMAL and the swapchain image in
+    /// TRANSFER_DST_OPTIMAL; the frame graph inserts those transitions.
+    pub fn record_upscale(
+        &self,
+        device: &VulkanDevice,
+        command_buffer: vk::CommandBuffer,
+        swapchain_image: vk::Image,
+        swapchain_extent: vk::Extent2D,
+    ) {
+        let blit = vk::ImageBlit {
+            src_subresource: color_subresource_layers(),
+            src_offsets: [vk::Offset3D::default(), extent_offset(self.extent)],
+            dst_subresource: color_subresource_layers(),
+            dst_offsets: [vk::Offset3D::default(), extent_offset(swapchain_extent)],
+        };
+
+        unsafe {
+            device.handle().cmd_blit_image(
+                command_buffer,
+                self.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                swapchain_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[blit],
+                vk::Filter::LINEAR,
+            );
+        }
+    }
+
+    /// Destroy the target's Vulkan objects
+    pub fn destroy(&self, device: &VulkanDevice) {
+        unsafe {
+            device.handle().destroy_framebuffer(self.framebuffer, None);
+            device.handle().destroy_image_view(self.image_view, None);
+            device.handle().destroy_image(self.image, None);
+            device.handle().free_memory(self.memory, None);
+        }
+    }
+}
+
+fn color_subresource_range() -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+fn color_subresource_layers() -> vk::ImageSubresourceLayers {
+    vk::ImageSubresourceLayers {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        mip_level: 0,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+fn extent_offset(extent: vk::Extent2D) -> vk::Offset3D {
+    vk::Offset3D { x: extent.width as i32, y: extent.height as i32, z: 1 }
+}
@@ -0,0 +1,28 @@
+#version 450
+
+layout(location = 0) in vec2 fragTexCoord;
+
+layout(location = 0) out vec4 outColor;
+
+layout(set = 0, binding = 0) uniform sampler2D backdrop;
+
+layout(push_constant) uniform PushConstants {
+    vec2 texelStep;      // One source texel along the blur direction
+    uint tapCount;       // Taps on each side of the center
+    float centerWeight;
+    uvec4 taps[6];       // Offset and weight of each tap as packed halves, four per vector
+} pushConstants;
+
+void main() {
+    vec4 color = texture(backdrop, fragTexCoord) * pushConstants.centerWeight;
+    
+    // Each tap sits between two texels, so linear filtering reads both
+    for (uint i = 0u; i < pushConstants.tapCount; i++) {
+        vec2 tap = unpackHalf2x16(pushConstants.taps[i / 4u][i % 4u]);
+        vec2 offset = pushConstants.texelStep * tap.x;
+        color += texture(backdrop, fragTexCoord + offset) * tap.y;
+        color += texture(backdrop, fragTexCoord - offset) * tap.y;
+    }
+    
+    outColor = color;
+}
@@ -0,0 +1,10 @@
+#version 450
+
+layout(location = 0) out vec2 fragTexCoord;
+
+void main() {
+    // One triangle covering the whole target: (0, 0), (2, 0), (0, 2) in texture space
+    vec2 corner = vec2(float((gl_VertexIndex << 1) & 2), float(gl_VertexIndex & 2));
+    gl_Position = vec4(corner * 2.0 - 1.0, 0.0, 1.0);
+    fragTexCoord = corner;
+}
@@ -6,12 +6,59 @@ layout(location = 0) out vec4 outColor;
 
 layout(set = 0, binding = 0) uniform sampler2D texSampler;
 
+layout(push_constant) uniform PushConstants {
+    mat4 transform;
+    vec2 offset;
+    vec2 scale;
+    float dim;
+    float desaturation;
+    vec2 size;
+    float cornerRadius;
+    float edgeSoftness;
+    float borderWidth;
+    vec4 borderColor;
+} pushConstants;
+
 void main() {
     // Simple texture sampling - will be enhanced in Phase 2 with AI-generated effects
     outColor = texture(texSampler, fragTexCoord);
     
+    // Signed distance to the rounded rectangle, negative inside
+    vec2 halfSize = pushConstants.size * 0.5;
+    vec2 p = abs(fragTexCoord * pushConstants.size - halfSize) - halfSize + pushConstants.cornerRadius;
+    float dist = length(max(p, 0.0)) + min(max(p.x, p.y), 0.0) - pushConstants.cornerRadius;
+    
+    // Rounded corners with analytic antialiasing: ramp coverage over
+    // edgeSoftness pixels of the signed distance to the rounded rectangle
+    float coverage = 1.0;
+    if (pushConstants.cornerRadius > 0.0) {
+        coverage = pushConstants.edgeSoftness > 0.0
+            ? clamp(0.5 - dist / pushConstants.edgeSoftness, 0.0, 1.0)
+            : step(dist, 0.0);
+        outColor.a *= coverage;
+    }
+    
+    // Border along the inside of the edge, following the rounded corners
+    float border = 0.0;
+    if (pushConstants.borderWidth > 0.0) {
+        float inner = dist + pushConstants.borderWidth;
+        border = pushConstants.edgeSoftness > 0.0
+            ? clamp(0.5 + inner / pushConstants.edgeSoftness, 0.0, 1.0)
+            : step(0.0, inner);
+        border *= pushConstants.borderColor.a;
+    }
+    
     // Basic alpha handling for client windows
-    if (outColor.a < 0.01) {
+    if (outColor.a < 0.01 && border * coverage < 0.01) {
         discard;
     }
+    
+    // Active-window highlight: desaturate and darken unfocused windows
+    float luminance = dot(outColor.rgb, vec3(0.2126, 0.7152, 0.0722));
+    outColor.rgb = mix(outColor.rgb, vec3(luminance), pushConstants.desaturation);
+    outColor.rgb *= 1.0 - pushConstants.dim;
+    
+    // The border is not dimmed, so it stands out on unfocused windows
+    outColor.rgb = mix(outColor.rgb, pushConstants.borderColor.rgb, border);
+    outColor.a = mix(outColor.a, coverage, border);
 }
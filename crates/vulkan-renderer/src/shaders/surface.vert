@@ -9,6 +9,13 @@ layout(push_constant) uniform PushConstants {
     mat4 transform;
     vec2 offset;
     vec2 scale;
+    float dim;
+    float desaturation;
+    vec2 size;
+    float cornerRadius;
+    float edgeSoftness;
+    float borderWidth;
+    vec4 borderColor;
 } pushConstants;
 
 void main() {
@@ -0,0 +1,116 @@
+#version 450
+
+layout(location = 0) in vec2 fragPosition;
+layout(location = 1) in vec2 fragTexCoord;
+
+layout(location = 0) out vec4 outColor;
+
+layout(set = 0, binding = 0) uniform sampler2D image;
+
+layout(push_constant) uniform PushConstants {
+    vec4 rect;
+    vec4 region;
+    vec4 color;
+    vec4 shapeRect;
+    vec4 uvRect;
+    float cornerRadius;
+    float borderWidth;
+    float edgeSoftness;
+    float sigma;
+    uint shape;
+    float progress;
+    float thickness;
+    float _padding;
+} pushConstants;
+
+const uint SHAPE_RECT = 0u;
+const uint SHAPE_RING = 1u;
+const uint SHAPE_SHADOW = 2u;
+const uint SHAPE_IMAGE = 3u;
+
+const float PI = 3.14159265;
+
+// Signed distance to a rounded rectangle, negative inside
+float roundedRectDistance(vec2 p, vec4 rect, float radius) {
+    vec2 halfSize = rect.zw * 0.5;
+    vec2 q = abs(p - rect.xy - halfSize) - halfSize + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+// Coverage of a pixel at a signed distance, ramped over edgeSoftness pixels
+float coverage(float dist) {
+    return pushConstants.edgeSoftness > 0.0
+        ? clamp(0.5 - dist / pushConstants.edgeSoftness, 0.0, 1.0)
+        : step(dist, 0.0);
+}
+
+vec2 erf(vec2 x) {
+    vec2 s = sign(x);
+    vec2 a = abs(x);
+    x = 1.0 + (0.278393 + (0.230389 + 0.078108 * (a * a)) * a) * a;
+    x *= x;
+    return s - s / (x * x);
+}
+
+float gaussian(float x, float sigma) {
+    return exp(-(x * x) / (2.0 * sigma * sigma)) / (sqrt(2.0 * PI) * sigma);
+}
+
+// Horizontal slice of a blurred rounded rectangle, integrated analytically
+float shadowSlice(float x, float y, float sigma, float corner, vec2 halfSize) {
+    float delta = min(halfSize.y - corner - abs(y), 0.0);
+    float curved = halfSize.x - corner + sqrt(max(0.0, corner * corner - delta * delta));
+    vec2 integral = 0.5 + 0.5 * erf((x + vec2(-curved, curved)) * (sqrt(0.5) / sigma));
+    return integral.y - integral.x;
+}
+
+// Rounded rectangle convolved with a Gaussian: exact across, sampled along
+float roundedRectShadow(vec2 p, vec4 rect, float corner, float sigma) {
+    vec2 halfSize = rect.zw * 0.5;
+    p -= rect.xy + halfSize;
+    float low = p.y - halfSize.y;
+    float high = p.y + halfSize.y;
+    float start = clamp(-3.0 * sigma, low, high);
+    float end = clamp(3.0 * sigma, low, high);
+    float stepSize = (end - start) / 4.0;
+    float y = start + stepSize * 0.5;
+    float value = 0.0;
+    for (int i = 0; i < 4; i++) {
+        value += shadowSlice(p.x, p.y - y, sigma, corner, halfSize) * gaussian(y, sigma) * stepSize;
+        y += stepSize;
+    }
+    return value;
+}
+
+void main() {
+    vec4 color = pushConstants.color;
+    float alpha = 1.0;
+    
+    if (pushConstants.shape == SHAPE_RECT) {
+        // Filled, or only the border along the inside of the edge
+        float dist = roundedRectDistance(fragPosition, pushConstants.shapeRect, pushConstants.cornerRadius);
+        alpha = coverage(dist);
+        if (pushConstants.borderWidth > 0.0) {
+            alpha *= 1.0 - coverage(dist + pushConstants.borderWidth);
+        }
+    } else if (pushConstants.shape == SHAPE_RING) {
+        // Arc clockwise from the top, progress of the way round
+        vec2 halfSize = pushConstants.shapeRect.zw * 0.5;
+        vec2 offset = fragPosition - pushConstants.shapeRect.xy - halfSize;
+        float middle = halfSize.x - pushConstants.thickness * 0.5;
+        alpha = coverage(abs(length(offset) - middle) - pushConstants.thickness * 0.5);
+        float turn = fract(atan(offset.x, -offset.y) / (2.0 * PI) + 1.0);
+        alpha *= step(turn, pushConstants.progress);
+    } else if (pushConstants.shape == SHAPE_SHADOW) {
+        alpha = roundedRectShadow(fragPosition, pushConstants.shapeRect, pushConstants.cornerRadius, max(pushConstants.sigma, 0.5));
+    } else if (pushConstants.shape == SHAPE_IMAGE) {
+        // Clipped to the rounded rectangle, e.g. a blurred backdrop behind a window
+        color *= texture(image, fragTexCoord);
+        alpha = coverage(roundedRectDistance(fragPosition, pushConstants.shapeRect, pushConstants.cornerRadius));
+    }
+    
+    outColor = vec4(color.rgb, color.a * alpha);
+    if (outColor.a < 0.002) {
+        discard;
+    }
+}
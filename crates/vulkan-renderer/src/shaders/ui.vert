@@ -0,0 +1,32 @@
+#version 450
+
+layout(location = 0) out vec2 fragPosition;
+layout(location = 1) out vec2 fragTexCoord;
+
+layout(push_constant) uniform PushConstants {
+    vec4 rect;
+    vec4 region;
+    vec4 color;
+    vec4 shapeRect;
+    vec4 uvRect;
+    float cornerRadius;
+    float borderWidth;
+    float edgeSoftness;
+    float sigma;
+    uint shape;
+    float progress;
+    float thickness;
+    float _padding;
+} pushConstants;
+
+void main() {
+    // Corners of the quad as a triangle strip: (0, 0), (1, 0), (0, 1), (1, 1)
+    vec2 corner = vec2(float(gl_VertexIndex & 1), float(gl_VertexIndex >> 1));
+    vec2 position = pushConstants.rect.xy + corner * pushConstants.rect.zw;
+    
+    // Compositor coordinates of the output region map onto the whole frame
+    vec2 ndc = (position - pushConstants.region.xy) / pushConstants.region.zw * 2.0 - 1.0;
+    gl_Position = vec4(ndc, 0.0, 1.0);
+    fragPosition = position;
+    fragTexCoord = pushConstants.uvRect.xy + corner * pushConstants.uvRect.zw;
+}
@@ -0,0 +1,237 @@
+// Shared staging ring buffer
+//
+// All texture uploads copy their pixels into one persistently mapped,
+// host-coherent buffer and suballocate from it in ring order. Each allocation
+// is tagged with the serial of the upload that reads it and is reclaimed once
+// that upload has completed on the GPU, so steady-state uploads neither
+// allocate memory nor map/unmap it.
+
+use ash::vk;
+use compositor_utils::prelude::*;
+use crate::{VulkanDevice, VulkanInstance};
+use std::collections::VecDeque;
+
+/// Initial ring size, enough for a 4K RGBA frame
+pub const DEFAULT_STAGING_SIZE: vk::DeviceSize = 64 * 1024 * 1024;
+
+/// Alignment of suballocations
+///
+/// Covers texel size and the optimal buffer copy offset alignment reported
+/// by common drivers.
+pub const STAGING_ALIGNMENT: vk::DeviceSize = 256;
+
+/// Region of the ring still read by an upload
+#[derive(Debug, Clone, Copy)]
+struct InFlightRegion {
+    start: vk::DeviceSize,
+    serial: u64,
+}
+
+/// Mapped buffer backing the ring
+struct RingBuffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    mapped: *mut u8,
+}
+
+/// Persistently mapped staging buffer shared by all uploads
+pub struct StagingRing {
+    instance: VulkanInstance,
+    device: VulkanDevice,
+    ring: Option<RingBuffer>,
+    capacity: vk::DeviceSize,
+    head: vk::DeviceSize,
+    /// Oldest first
+    in_flight: VecDeque<InFlightRegion>,
+}
+
+impl StagingRing {
+    /// Create a ring of `capacity` bytes; memory is allocated on first use
+    pub fn new(instance: VulkanInstance, device: VulkanDevice, capacity: vk::DeviceSize) -> Self {
+        Self {
+            instance,
+            device,
+            ring: None,
+            capacity,
+            head: 0,
+            in_flight: VecDeque::new(),
+        }
+    }
+
+    /// Buffer to copy from
+    pub fn buffer(&self) -> vk::Buffer {
+        self.ring.as_ref().map_or(vk::Buffer::null(), |ring| ring.buffer)
+    }
+
+    /// Ring size in bytes
+    pub fn capacity(&self) -> vk::DeviceSize {
+        self.capacity
+    }
+
+    /// Whether any upload still reads from the ring
+    pub fn is_idle(&self) -> bool {
+        self.in_flight.is_empty()
+    }
+
+    /// Copy `data` into the ring for the upload with `serial`
+    ///
+    /// Returns the offset of the copy, or `None` when the ring is too full
+    /// and the caller must wait for earlier uploads to complete.
+    pub fn write(&mut self, data: &[u8], serial: u64) -> Result<Option<vk::DeviceSize>> {
+        let mapped = self.ensure_allocated()?;
+        let Some(offset) = self.allocate(data.len() as vk::DeviceSize, serial) else {
+            return Ok(None);
+        };
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), mapped.add(offset as usize), data.len());
+        }
+        Ok(Some(offset))
+    }
+
+    /// Reclaim regions read by uploads up to and including `completed`
+    pub fn retire(&mut self, completed: u64) {
+        while self.in_flight.front().is_some_and(|region| region.serial <= completed) {
+            self.in_flight.pop_front();
+        }
+        if self.in_flight.is_empty() {
+            self.head = 0;
+        }
+    }
+
+    /// Grow the ring so a single upload of `size` bytes fits
+    ///
+    /// Only possible while no upload reads from the ring.
+    pub fn grow(&mut self, size: vk::DeviceSize) -> Result<()> {
+        if !self.is_idle() {
+            return Err(CompositorError::graphics("Cannot grow staging ring while uploads are in flight"));
+        }
+        let capacity = size.next_power_of_two().max(self.capacity);
+        if capacity != self.capacity {
+            self.release();
+            self.capacity = capacity;
+            debug!("Grew staging ring to {} bytes", capacity);
+        }
+        Ok(())
+    }
+
+    /// Free the ring's memory; it is reallocated by the next write
+    ///
+    /// Only possible while no upload reads from the ring.
+    pub fn release(&mut self) {
+        if !self.is_idle() {
+            warn!("Not releasing staging ring with uploads in flight");
+            return;
+        }
+        if let Some(ring) = self.ring.take() {
+            unsafe {
+                self.device.handle().unmap_memory(ring.memory);
+                self.device.handle().destroy_buffer(ring.buffer, None);
+                self.device.handle().free_memory(ring.memory, None);
+            }
+            debug!("Released staging ring");
+        }
+        self.head = 0;
+    }
+
+    /// Free the ring's memory and return to `capacity`, undoing growth for past large uploads
+    pub fn shrink(&mut self, capacity: vk::DeviceSize) {
+        self.release();
+        if self.ring.is_none() && self.capacity != capacity {
+            self.capacity = capacity;
+            debug!("Shrank staging ring to {} bytes", capacity);
+        }
+    }
+
+    /// Reserve `size` bytes, wrapping to the start of the ring when needed
+    fn allocate(&mut self, size: vk::DeviceSize, serial: u64) -> Option<vk::DeviceSize> {
+        if size > self.capacity {
+            return None;
+        }
+
+        let offset = align_up(self.head, STAGING_ALIGNMENT);
+        let offset = match self.in_flight.front() {
+            None if offset + size <= self.capacity => offset,
+            None => 0,
+            Some(oldest) if self.head > oldest.start => {
+                // Free space runs from the head to the end, then from the start to the oldest region
+                if offset + size <= self.capacity {
+                    offset
+                } else if size <= oldest.start {
+                    0
+                } else {
+                    return None;
+                }
+            }
+            Some(oldest) => {
+                // Wrapped: free space runs from the head to the oldest region
+                if offset + size <= oldest.start {
+                    offset
+                } else {
+                    return None;
+                }
+            }
+        };
+
+        self.in_flight.push_back(InFlightRegion { start: offset, serial });
+        self.head = offset + size;
+        Some(offset)
+    }
+
+    fn ensure_allocated(&mut self) -> Result<*mut u8> {
+        if let Some(ring) = &self.ring {
+            return Ok(ring.mapped);
+        }
+
+        let buffer_info = vk::BufferCreateInfo {
+            size: self.capacity,
+            usage: vk::BufferUsageFlags::TRANSFER_SRC,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = unsafe { self.device.handle().create_buffer(&buffer_info, None)? };
+
+        let memory_requirements = unsafe { self.device.handle().get_buffer_memory_requirements(buffer) };
+        let memory_properties = unsafe {
+            self.instance.handle().get_physical_device_memory_properties(self.device.physical_device())
+        };
+        let required = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+        let memory_type_index = (0..memory_properties.memory_type_count)
+            .find(|&i| {
+                (memory_requirements.memory_type_bits & (1 << i)) != 0
+                    && memory_properties.memory_types[i as usize].property_flags.contains(required)
+            })
+            .ok_or_else(|| CompositorError::graphics("Failed to find memory type for staging ring"))?;
+
+        let alloc_info = vk::MemoryAllocateInfo {
+            allocation_size: memory_requirements.size,
+            memory_type_index,
+            ..Default::default()
+        };
+        let memory = unsafe { self.device.handle().allocate_memory(&alloc_info, None)? };
+
+        let mapped = unsafe {
+            self.device.handle().bind_buffer_memory(buffer, memory, 0)?;
+            self.device.handle().map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())? as *mut u8
+        };
+
+        debug!("Created staging ring with size: {} bytes", self.capacity);
+        self.ring = Some(RingBuffer { buffer, memory, mapped });
+        Ok(mapped)
+    }
+}
+
+// The mapped pointer is only written through `&mut self`
+unsafe impl Send for StagingRing {}
+
+impl Drop for StagingRing {
+    fn drop(&mut self) {
+        // Owners wait for their uploads before dropping the ring
+        self.in_flight.clear();
+        self.release();
+    }
+}
+
+fn align_up(value: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    value.div_ceil(alignment) * alignment
+}
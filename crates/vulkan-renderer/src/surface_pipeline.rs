@@ -24,6 +24,14 @@ pub struct SurfacePushConstants {
     pub transform: [[f32; 4]; 4],  // MVP matrix
     pub offset: [f32; 2],          // Surface position offset
     pub scale: [f32; 2],           // Surface scale factor
+    pub dim: f32,                  // Brightness reduction (0.0 - 1.0)
+    pub desaturation: f32,         // Saturation reduction (0.0 - 1.0)
+    pub size: [f32; 2],            // Surface size in pixels
+    pub corner_radius: f32,        // Rounded corner radius in pixels (0.0 = square)
+    pub edge_softness: f32,        // Analytic antialiasing width in pixels (0.0 = hard edge)
+    pub border_width: f32,         // Border inside the surface edge in pixels (0.0 = none)
+    pub _padding: f32,             // Aligns border_color like the shader's vec4
+    pub border_color: [f32; 4],    // Border color, alpha scales its strength
 }
 
 /// Vertex data for surface quads
@@ -40,6 +48,7 @@ impl SurfacePipeline {
         _instance: &VulkanInstance,
         device: VulkanDevice,
         render_pass: vk::RenderPass,
+        pipeline_cache: vk::PipelineCache,
     ) -> Result<Self> {
         info!("Creating surface rendering pipeline");
         
@@ -60,6 +69,7 @@ impl SurfacePipeline {
             fragment_shader,
             pipeline_layout,
             render_pass,
+            pipeline_cache,
         )?;
         
         info!("Surface pipeline created successfully");
@@ -156,7 +166,7 @@ impl SurfacePipeline {
         
         let push_constant_ranges = [
             vk::PushConstantRange {
-                stage_flags: vk::ShaderStageFlags::VERTEX,
+                stage_flags: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
                 offset: 0,
                 size: std::mem::size_of::<SurfacePushConstants>() as u32,
             },
@@ -183,6 +193,7 @@ impl SurfacePipeline {
         fragment_shader: vk::ShaderModule,
         pipeline_layout: vk::PipelineLayout,
         render_pass: vk::RenderPass,
+        pipeline_cache: vk::PipelineCache,
     ) -> Result<vk::Pipeline> {
         let main_function_name = std::ffi::CString::new("main").unwrap();
         
@@ -309,7 +320,7 @@ impl SurfacePipeline {
         
         let pipelines = unsafe {
             device.handle().create_graphics_pipelines(
-                vk::PipelineCache::null(),
+                pipeline_cache,
                 &[pipeline_info],
                 None,
             ).map_err(|e| CompositorError::graphics(&format!("Failed to create graphics pipeline: {:?}", e)))?
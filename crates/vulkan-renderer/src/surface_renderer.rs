@@ -6,7 +6,9 @@
 use ash::vk;
 use compositor_utils::prelude::*;
 use crate::{VulkanInstance, VulkanDevice};
-use std::collections::HashMap;
+use crate::staging::{StagingRing, DEFAULT_STAGING_SIZE};
+use crate::mipmap::{create_trilinear_sampler, supports_mipmap_generation, MipChain};
+use std::collections::{HashMap, VecDeque};
 
 /// Surface rendering context for converting client buffers to textures
 pub struct SurfaceRenderer {
@@ -16,9 +18,21 @@ pub struct SurfaceRenderer {
     surface_textures: HashMap<u32, SurfaceTexture>,
     /// Command pool for texture operations
     command_pool: vk::CommandPool,
-    /// Staging buffer for SHM buffer uploads
-    staging_buffer: Option<vk::Buffer>,
-    staging_memory: Option<vk::DeviceMemory>,
+    /// Staging ring shared by all uploads
+    staging: StagingRing,
+    /// Submitted uploads not yet known to be complete, oldest first
+    pending_uploads: VecDeque<PendingUpload>,
+    /// Serial of the most recent upload
+    upload_serial: u64,
+    /// Trilinear sampler for scaled previews
+    preview_sampler: vk::Sampler,
+}
+
+/// Upload submitted to the GPU that still reads from the staging ring
+struct PendingUpload {
+    serial: u64,
+    fence: vk::Fence,
+    command_buffer: vk::CommandBuffer,
 }
 
 /// Vulkan texture representation of a Wayland surface buffer
@@ -30,6 +44,16 @@ pub struct SurfaceTexture {
     pub width: u32,
     pub height: u32,
     pub format: vk::Format,
+    /// Mip chain for scaled previews, generated on first scaled use
+    pub mipmaps: Option<MipChain>,
+}
+
+/// Pixels of a texture copied back to host memory, tightly packed
+pub struct EvictedTexture {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub format: vk::Format,
 }
 
 /// Surface buffer data received from Wayland clients
@@ -82,13 +106,18 @@ impl SurfaceRenderer {
         
         info!("Surface renderer initialized with command pool");
         
+        let staging = StagingRing::new(instance.clone(), device.clone(), DEFAULT_STAGING_SIZE);
+        let preview_sampler = create_trilinear_sampler(&device)?;
+        
         Ok(Self {
             instance,
             device,
             surface_textures: HashMap::new(),
             command_pool,
-            staging_buffer: None,
-            staging_memory: None,
+            staging,
+            pending_uploads: VecDeque::new(),
+            upload_serial: 0,
+            preview_sampler,
         })
     }
     
@@ -112,6 +141,60 @@ impl SurfaceRenderer {
         self.surface_textures.get(&surface_id)
     }
     
+    /// Sampler to use with mipmapped preview views
+    pub fn preview_sampler(&self) -> vk::Sampler {
+        self.preview_sampler
+    }
+    
+    /// View of a surface's mip chain, generating it on first use
+    ///
+    /// Returns `None` for unknown surfaces and formats that cannot be
+    /// downsampled with linear blits; callers then sample the base texture.
+    pub fn mipmapped_view(&mut self, surface_id: u32) -> Result<Option<vk::ImageView>> {
+        let Some(texture) = self.surface_textures.get(&surface_id) else {
+            return Ok(None);
+        };
+        if let Some(mipmaps) = &texture.mipmaps {
+            return Ok(Some(mipmaps.image_view()));
+        }
+        if !supports_mipmap_generation(&self.instance, &self.device, texture.format) {
+            return Ok(None);
+        }
+        
+        let mipmaps = MipChain::new(&self.instance, &self.device, texture.width, texture.height, texture.format)?;
+        let (image, width, height) = (texture.image, texture.width, texture.height);
+        if let Err(e) = self.submit_and_wait(|device, command_buffer| {
+            mipmaps.record_generate(device, command_buffer, image, width, height);
+        }) {
+            mipmaps.destroy(&self.device);
+            return Err(e);
+        }
+        
+        debug!("Generated {} mip levels for surface {}", mipmaps.levels(), surface_id);
+        let image_view = mipmaps.image_view();
+        if let Some(texture) = self.surface_textures.get_mut(&surface_id) {
+            texture.mipmaps = Some(mipmaps);
+        }
+        Ok(Some(image_view))
+    }
+    
+    /// Free all mip chains; they are regenerated on next scaled use
+    pub fn release_mipmaps(&mut self) -> Result<()> {
+        if self.surface_textures.values().all(|texture| texture.mipmaps.is_none()) {
+            return Ok(());
+        }
+        
+        // Frames in flight may still sample the chains
+        self.device.wait_idle()?;
+        for texture in self.surface_textures.values_mut() {
+            if let Some(mipmaps) = texture.mipmaps.take() {
+                mipmaps.destroy(&self.device);
+            }
+        }
+        debug!("Released surface mip chains");
+        Ok(())
+    }
+    
     /// Remove a surface texture
     pub fn remove_surface_texture(&mut self, surface_id: u32) -> Result<()> {
         if let Some(texture) = self.surface_textures.remove(&surface_id) {
@@ -121,6 +204,145 @@ impl SurfaceRenderer {
         Ok(())
     }
     
+    /// Copy a surface texture back to host memory and free it
+    ///
+    /// Returns the pixels to upload again once the surface is shown, or
+    /// `None` if it has no texture. No frame in flight may sample the texture.
+    pub fn evict_surface_texture(&mut self, surface_id: u32) -> Result<Option<EvictedTexture>> {
+        let Some(texture) = self.surface_textures.remove(&surface_id) else {
+            return Ok(None);
+        };
+        
+        // An upload may still be copying into the image
+        let pixels = self.wait_for_uploads().and_then(|_| self.read_texture(&texture));
+        let (width, height, format) = (texture.width, texture.height, texture.format);
+        self.cleanup_surface_texture(texture)?;
+        let data = pixels?;
+        debug!("Evicted texture for surface {} ({} bytes)", surface_id, data.len());
+        Ok(Some(EvictedTexture { data, width, height, format }))
+    }
+    
+    /// Read the pixels of a texture through a temporary host-visible buffer
+    fn read_texture(&self, texture: &SurfaceTexture) -> Result<Vec<u8>> {
+        let size = texture.width as vk::DeviceSize * texture.height as vk::DeviceSize * 4;
+        let buffer_info = vk::BufferCreateInfo {
+            size,
+            usage: vk::BufferUsageFlags::TRANSFER_DST,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = unsafe { self.device.handle().create_buffer(&buffer_info, None)? };
+        let memory_requirements = unsafe { self.device.handle().get_buffer_memory_requirements(buffer) };
+        let memory = self.find_memory_type(
+            memory_requirements.memory_type_bits,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        ).and_then(|memory_type_index| {
+            let alloc_info = vk::MemoryAllocateInfo {
+                allocation_size: memory_requirements.size,
+                memory_type_index,
+                ..Default::default()
+            };
+            Ok(unsafe { self.device.handle().allocate_memory(&alloc_info, None)? })
+        });
+        let memory = match memory {
+            Ok(memory) => memory,
+            Err(e) => {
+                unsafe { self.device.handle().destroy_buffer(buffer, None) };
+                return Err(e);
+            }
+        };
+        
+        let result = unsafe { self.device.handle().bind_buffer_memory(buffer, memory, 0) }
+            .map_err(CompositorError::from)
+            .and_then(|_| self.submit_and_wait(|device, command_buffer| {
+                let subresource_range = vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                };
+                let barrier = vk::ImageMemoryBarrier {
+                    old_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    image: texture.image,
+                    subresource_range,
+                    src_access_mask: vk::AccessFlags::SHADER_READ,
+                    dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+                    ..Default::default()
+                };
+                let region = vk::BufferImageCopy {
+                    buffer_offset: 0,
+                    buffer_row_length: 0,
+                    buffer_image_height: 0,
+                    image_subresource: vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    },
+                    image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+                    image_extent: vk::Extent3D {
+                        width: texture.width,
+                        height: texture.height,
+                        depth: 1,
+                    },
+                };
+                // Make the copy visible to the host mapping
+                let host_barrier = vk::BufferMemoryBarrier {
+                    src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                    dst_access_mask: vk::AccessFlags::HOST_READ,
+                    src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    buffer,
+                    offset: 0,
+                    size,
+                    ..Default::default()
+                };
+                unsafe {
+                    device.handle().cmd_pipeline_barrier(
+                        command_buffer,
+                        vk::PipelineStageFlags::FRAGMENT_SHADER,
+                        vk::PipelineStageFlags::TRANSFER,
+                        vk::DependencyFlags::empty(),
+                        &[],
+                        &[],
+                        &[barrier],
+                    );
+                    device.handle().cmd_copy_image_to_buffer(
+                        command_buffer,
+                        texture.image,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        buffer,
+                        &[region],
+                    );
+                    device.handle().cmd_pipeline_barrier(
+                        command_buffer,
+                        vk::PipelineStageFlags::TRANSFER,
+                        vk::PipelineStageFlags::HOST,
+                        vk::DependencyFlags::empty(),
+                        &[],
+                        &[host_barrier],
+                        &[],
+                    );
+                }
+            }))
+            .and_then(|_| unsafe {
+                let mapped = self.device.handle().map_memory(memory, 0, size, vk::MemoryMapFlags::empty())?;
+                let data = std::slice::from_raw_parts(mapped as *const u8, size as usize).to_vec();
+                self.device.handle().unmap_memory(memory);
+                Ok(data)
+            });
+        
+        unsafe {
+            self.device.handle().destroy_buffer(buffer, None);
+            self.device.handle().free_memory(memory, None);
+        }
+        result
+    }
+    
     /// Update SHM buffer texture
     fn update_shm_texture(&mut self, surface_id: u32, data: Vec<u8>, width: u32, height: u32, format: ShmFormat) -> Result<()> {
         // Remove existing texture if it exists
@@ -185,7 +407,10 @@ impl SurfaceRenderer {
             format,
             tiling: vk::ImageTiling::OPTIMAL,
             initial_layout: vk::ImageLayout::UNDEFINED,
-            usage: vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED,
+            // Transfer source for generating preview mip chains
+            usage: vk::ImageUsageFlags::TRANSFER_SRC
+                | vk::ImageUsageFlags::TRANSFER_DST
+                | vk::ImageUsageFlags::SAMPLED,
             sharing_mode: vk::SharingMode::EXCLUSIVE,
             samples: vk::SampleCountFlags::TYPE_1,
             ..Default::default()
@@ -247,115 +472,109 @@ impl SurfaceRenderer {
             width,
             height,
             format,
+            mipmaps: None,
         })
     }
     
-    /// Upload data to texture using staging buffer and command buffer
+    /// Upload data to texture through the staging ring
     fn upload_texture_data(&mut self, texture: &SurfaceTexture, data: &[u8]) -> Result<()> {
         debug!("Uploading {}x{} texture data ({} bytes)", 
                texture.width, texture.height, data.len());
         
-        let data_size = data.len() as vk::DeviceSize;
+        self.reclaim_uploads()?;
+        let serial = self.upload_serial + 1;
         
-        // Create or resize staging buffer if needed
-        self.ensure_staging_buffer(data_size)?;
+        // Copy data into the ring, waiting for earlier uploads when it is full
+        let offset = loop {
+            if let Some(offset) = self.staging.write(data, serial)? {
+                break offset;
+            }
+            if self.pending_uploads.is_empty() {
+                // Nothing left to wait for, so the upload is larger than the ring
+                self.staging.grow(data.len() as vk::DeviceSize)?;
+            } else {
+                self.wait_oldest_upload()?;
+            }
+        };
         
-        // Copy data to staging buffer
-        let staging_buffer = self.staging_buffer.unwrap();
-        let staging_memory = self.staging_memory.unwrap();
+        // Record and submit copy command
+        self.copy_buffer_to_image(self.staging.buffer(), offset, texture, serial)?;
+        self.upload_serial = serial;
         
+        Ok(())
+    }
+    
+    /// Free the staging ring; it is recreated by the next upload
+    pub fn release_staging_buffer(&mut self) -> Result<()> {
+        self.wait_for_uploads()?;
+        self.staging.release();
+        Ok(())
+    }
+    
+    /// Free memory that is recreated on demand, while the compositor is idle
+    ///
+    /// Releases the staging ring at its initial size, all mip chains and the
+    /// command pool's unused memory.
+    pub fn trim(&mut self) -> Result<()> {
+        self.wait_for_uploads()?;
+        self.staging.shrink(DEFAULT_STAGING_SIZE);
+        self.release_mipmaps()?;
         unsafe {
-            let mapped_ptr = self.device.handle().map_memory(
-                staging_memory,
-                0,
-                data_size,
-                vk::MemoryMapFlags::empty(),
-            )?;
-            
-            std::ptr::copy_nonoverlapping(
-                data.as_ptr(),
-                mapped_ptr as *mut u8,
-                data.len(),
-            );
-            
-            self.device.handle().unmap_memory(staging_memory);
+            self.device.handle().trim_command_pool(self.command_pool, vk::CommandPoolTrimFlags::empty());
         }
-        
-        // Record and submit copy command
-        self.copy_buffer_to_image(staging_buffer, texture)?;
-        
         Ok(())
     }
     
-    /// Ensure staging buffer exists and is large enough
-    fn ensure_staging_buffer(&mut self, required_size: vk::DeviceSize) -> Result<()> {
-        // Check if we need to create or resize the staging buffer
-        let needs_creation = match (self.staging_buffer, self.staging_memory) {
-            (Some(_), Some(_)) => {
-                // TODO: Check if current buffer is large enough
-                // For now, assume it's adequate
-                false
+    /// Reclaim staging space of uploads that have completed
+    fn reclaim_uploads(&mut self) -> Result<()> {
+        while let Some(upload) = self.pending_uploads.front() {
+            let complete = unsafe { self.device.handle().get_fence_status(upload.fence)? };
+            if !complete {
+                break;
             }
-            _ => true,
-        };
-        
-        if needs_creation {
-            // Clean up existing staging buffer if any
-            if let (Some(buffer), Some(memory)) = (self.staging_buffer, self.staging_memory) {
-                unsafe {
-                    self.device.handle().destroy_buffer(buffer, None);
-                    self.device.handle().free_memory(memory, None);
-                }
-            }
-            
-            // Create new staging buffer
-            let buffer_info = vk::BufferCreateInfo {
-                size: required_size,
-                usage: vk::BufferUsageFlags::TRANSFER_SRC,
-                sharing_mode: vk::SharingMode::EXCLUSIVE,
-                ..Default::default()
-            };
-            
-            let buffer = unsafe {
-                self.device.handle().create_buffer(&buffer_info, None)?
-            };
-            
-            // Allocate memory for staging buffer
-            let memory_requirements = unsafe {
-                self.device.handle().get_buffer_memory_requirements(buffer)
-            };
-            
-            let memory_type_index = self.find_memory_type(
-                memory_requirements.memory_type_bits,
-                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-            )?;
-            
-            let alloc_info = vk::MemoryAllocateInfo {
-                allocation_size: memory_requirements.size,
-                memory_type_index,
-                ..Default::default()
-            };
-            
-            let memory = unsafe {
-                self.device.handle().allocate_memory(&alloc_info, None)?
-            };
-            
-            // Bind buffer to memory
+            self.finish_oldest_upload();
+        }
+        Ok(())
+    }
+    
+    /// Block until the oldest pending upload completes
+    fn wait_oldest_upload(&mut self) -> Result<()> {
+        if let Some(upload) = self.pending_uploads.front() {
             unsafe {
-                self.device.handle().bind_buffer_memory(buffer, memory, 0)?;
+                self.device.handle().wait_for_fences(&[upload.fence], true, u64::MAX)?;
             }
-            
-            self.staging_buffer = Some(buffer);
-            self.staging_memory = Some(memory);
-            
-            debug!("Created staging buffer with size: {} bytes", required_size);
+            self.finish_oldest_upload();
         }
-        
         Ok(())
     }
     
+    /// Block until all pending uploads complete
+    fn wait_for_uploads(&mut self) -> Result<()> {
+        while !self.pending_uploads.is_empty() {
+            self.wait_oldest_upload()?;
+        }
+        Ok(())
+    }
+    
+    /// Free a completed upload's resources and its staging space
+    fn finish_oldest_upload(&mut self) {
+        if let Some(upload) = self.pending_uploads.pop_front() {
+            unsafe {
+                self.device.handle().destroy_fence(upload.fence, None);
+                self.device.handle().free_command_buffers(self.command_pool, &[upload.command_buffer]);
+            }
+            self.staging.retire(upload.serial);
+        }
+    }
+    
     /// Copy data from staging buffer to image using command buffer
-    fn copy_buffer_to_image(&self, buffer: vk::Buffer, texture: &SurfaceTexture) -> Result<()> {
+    fn copy_buffer_to_image(
+        &mut self,
+        buffer: vk::Buffer,
+        offset: vk::DeviceSize,
+        texture: &SurfaceTexture,
+        serial: u64,
+    ) -> Result<()> {
         // Allocate command buffer
         let command_buffer_info = vk::CommandBufferAllocateInfo {
             command_pool: self.command_pool,
@@ -409,7 +628,7 @@ impl SurfaceRenderer {
             
             // Copy buffer to image
             let region = vk::BufferImageCopy {
-                buffer_offset: 0,
+                buffer_offset: offset,
                 buffer_row_length: 0,
                 buffer_image_height: 0,
                 image_subresource: vk::ImageSubresourceLayers {
@@ -474,24 +693,68 @@ impl SurfaceRenderer {
             ..Default::default()
         };
         
+        // The fence tells when the staging space can be reused
+        let fence = unsafe {
+            self.device.handle().create_fence(&vk::FenceCreateInfo::default(), None)?
+        };
+        
         unsafe {
             self.device.handle().queue_submit(
                 self.device.graphics_queue(),
                 &[submit_info],
-                vk::Fence::null(),
+                fence,
             )?;
-            
-            // Wait for completion (in production, use fences for async)
-            self.device.handle().queue_wait_idle(self.device.graphics_queue())?;
-            
-            // Free command buffer
-            self.device.handle().free_command_buffers(self.command_pool, &[command_buffer]);
         }
         
-        debug!("Successfully uploaded texture data to GPU");
+        self.pending_uploads.push_back(PendingUpload { serial, fence, command_buffer });
+        
+        debug!("Submitted texture upload {}", serial);
         Ok(())
     }
     
+    /// Record a one-off command buffer, submit it and wait for it to finish
+    fn submit_and_wait(&self, record: impl FnOnce(&VulkanDevice, vk::CommandBuffer)) -> Result<()> {
+        let command_buffer_info = vk::CommandBufferAllocateInfo {
+            command_pool: self.command_pool,
+            level: vk::CommandBufferLevel::PRIMARY,
+            command_buffer_count: 1,
+            ..Default::default()
+        };
+        let command_buffer = unsafe {
+            self.device.handle().allocate_command_buffers(&command_buffer_info)?[0]
+        };
+        
+        let begin_info = vk::CommandBufferBeginInfo {
+            flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+            ..Default::default()
+        };
+        let result = unsafe {
+            self.device.handle().begin_command_buffer(command_buffer, &begin_info)
+                .and_then(|_| {
+                    record(&self.device, command_buffer);
+                    self.device.handle().end_command_buffer(command_buffer)
+                })
+                .and_then(|_| self.device.handle().create_fence(&vk::FenceCreateInfo::default(), None))
+                .and_then(|fence| {
+                    let submit_info = vk::SubmitInfo {
+                        command_buffer_count: 1,
+                        p_command_buffers: &command_buffer,
+                        ..Default::default()
+                    };
+                    let result = self.device.handle()
+                        .queue_submit(self.device.graphics_queue(), &[submit_info], fence)
+                        .and_then(|_| self.device.handle().wait_for_fences(&[fence], true, u64::MAX));
+                    self.device.handle().destroy_fence(fence, None);
+                    result
+                })
+        };
+        
+        unsafe {
+            self.device.handle().free_command_buffers(self.command_pool, &[command_buffer]);
+        }
+        result.map_err(|e| CompositorError::graphics(format!("Failed to submit one-off commands: {}", e)))
+    }
+    
     /// Find suitable memory type for allocation
     fn find_memory_type(&self, type_filter: u32, properties: vk::MemoryPropertyFlags) -> Result<u32> {
         let memory_properties = unsafe {
@@ -510,7 +773,14 @@ impl SurfaceRenderer {
     }
     
     /// Clean up a surface texture and its resources
-    fn cleanup_surface_texture(&self, texture: SurfaceTexture) -> Result<()> {
+    fn cleanup_surface_texture(&mut self, texture: SurfaceTexture) -> Result<()> {
+        // An upload may still be copying into the image
+        self.wait_for_uploads()?;
+        
+        if let Some(mipmaps) = &texture.mipmaps {
+            mipmaps.destroy(&self.device);
+        }
+        
         unsafe {
             self.device.handle().destroy_image_view(texture.image_view, None);
             self.device.handle().destroy_image(texture.image, None);
@@ -535,18 +805,18 @@ impl Drop for SurfaceRenderer {
             }
         }
         
-        // Clean up command pool
+        // Wait for outstanding uploads before freeing their command buffers
+        if let Err(e) = self.wait_for_uploads() {
+            error!("Failed to wait for texture uploads: {}", e);
+        }
+        
+        // Clean up command pool and sampler
         unsafe {
             self.device.handle().destroy_command_pool(self.command_pool, None);
+            self.device.handle().destroy_sampler(self.preview_sampler, None);
         }
         
-        // Clean up staging buffer if allocated
-        if let (Some(buffer), Some(memory)) = (self.staging_buffer, self.staging_memory) {
-            unsafe {
-                self.device.handle().destroy_buffer(buffer, None);
-                self.device.handle().free_memory(memory, None);
-            }
-        }
+        // The staging ring frees itself when dropped
         
         info!("Surface renderer cleanup complete");
     }
@@ -1,11 +1,56 @@
 use ash::vk;
 use compositor_utils::prelude::*;
 use crate::{instance::VulkanInstance, device::VulkanDevice};
+use crate::present_damage::PresentDamage;
+
+/// How presented images are synchronized with the display refresh
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Wait for vblank; no tearing, up to a frame of extra latency
+    Fifo,
+    /// Replace the queued image at vblank; no tearing, lower latency
+    #[default]
+    Mailbox,
+    /// Present right away; lowest latency, may tear
+    Immediate,
+}
+
+impl PresentMode {
+    fn to_vk(self) -> vk::PresentModeKHR {
+        match self {
+            Self::Fifo => vk::PresentModeKHR::FIFO,
+            Self::Mailbox => vk::PresentModeKHR::MAILBOX,
+            Self::Immediate => vk::PresentModeKHR::IMMEDIATE,
+        }
+    }
+    
+    fn from_vk(mode: vk::PresentModeKHR) -> Self {
+        match mode {
+            vk::PresentModeKHR::MAILBOX => Self::Mailbox,
+            vk::PresentModeKHR::IMMEDIATE => Self::Immediate,
+            _ => Self::Fifo,
+        }
+    }
+}
 
 /// Vulkan swapchain wrapper for presenting rendered frames
 pub struct Swapchain {
+    device: ash::Device,
     swapchain_loader: ash::extensions::khr::Swapchain,
     swapchain: vk::SwapchainKHR,
+    surface: vk::SurfaceKHR,
+    present_mode: PresentMode,
+    present_queue: vk::Queue,
+    /// Loaded when VK_KHR_present_id/present_wait are enabled on the device
+    present_wait: Option<ash::extensions::khr::PresentWait>,
+    /// ID attached to the most recent present, if present IDs are in use
+    last_present_id: Option<u64>,
+    /// Whether VK_KHR_incremental_present is enabled on the device
+    incremental_present: bool,
+    /// Regions changed since the last present
+    damage: PresentDamage,
+    image_available: vk::Semaphore,
+    render_finished: vk::Semaphore,
     #[allow(dead_code)] // Will be used for render pass operations and resource binding
     images: Vec<vk::Image>,
     #[allow(dead_code)] // Will be used for framebuffer creation and rendering
@@ -13,16 +58,49 @@ pub struct Swapchain {
     format: vk::Format,
     extent: vk::Extent2D,
     current_image: u32,
+    /// Whether the images can be copied from, for frame readback
+    readable: bool,
 }
 
 impl Swapchain {
     /// Create a new swapchain
+    ///
+    /// Falls back to the closest supported mode when `present_mode` is not
+    /// supported by the surface.
     pub fn new(
         instance: &VulkanInstance,
         device: &VulkanDevice,
         surface: vk::SurfaceKHR,
         width: u32,
         height: u32,
+        present_mode: PresentMode,
+    ) -> Result<Self> {
+        Self::create(instance, device, surface, width, height, present_mode, vk::SwapchainKHR::null())
+    }
+    
+    /// Create a swapchain replacing this one, e.g. with another present mode
+    ///
+    /// No frame may be in flight; drop this swapchain once the new one is in use.
+    pub fn recreate(&self, instance: &VulkanInstance, device: &VulkanDevice, present_mode: PresentMode) -> Result<Self> {
+        Self::create(
+            instance,
+            device,
+            self.surface,
+            self.extent.width,
+            self.extent.height,
+            present_mode,
+            self.swapchain,
+        )
+    }
+    
+    fn create(
+        instance: &VulkanInstance,
+        device: &VulkanDevice,
+        surface: vk::SurfaceKHR,
+        width: u32,
+        height: u32,
+        present_mode: PresentMode,
+        old_swapchain: vk::SwapchainKHR,
     ) -> Result<Self> {
         let swapchain_loader = ash::extensions::khr::Swapchain::new(instance.handle(), device.handle());
         
@@ -39,12 +117,12 @@ impl Swapchain {
         
         let format = Self::choose_surface_format(&formats);
         
-        // Choose present mode (prefer mailbox for low latency)
+        // Choose present mode
         let present_modes = unsafe {
             surface_loader.get_physical_device_surface_present_modes(device.physical_device(), surface)?
         };
         
-        let present_mode = Self::choose_present_mode(&present_modes);
+        let present_mode = Self::choose_present_mode(&present_modes, present_mode);
         
         // Choose extent
         let extent = Self::choose_extent(&capabilities, width, height);
@@ -55,6 +133,13 @@ impl Swapchain {
             image_count = capabilities.max_image_count;
         }
         
+        // Frames can be read back where the surface allows copying from its images
+        let readable = capabilities.supported_usage_flags.contains(vk::ImageUsageFlags::TRANSFER_SRC);
+        let mut image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_DST;
+        if readable {
+            image_usage |= vk::ImageUsageFlags::TRANSFER_SRC;
+        }
+        
         // Create swapchain
         let swapchain_create_info = vk::SwapchainCreateInfoKHR {
             surface,
@@ -63,12 +148,13 @@ impl Swapchain {
             image_color_space: format.color_space,
             image_extent: extent,
             image_array_layers: 1,
-            image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            image_usage,
             image_sharing_mode: vk::SharingMode::EXCLUSIVE,
             pre_transform: capabilities.current_transform,
             composite_alpha: vk::CompositeAlphaFlagsKHR::OPAQUE,
             present_mode,
             clipped: vk::TRUE,
+            old_swapchain,
             ..Default::default()
         };
         
@@ -82,16 +168,40 @@ impl Swapchain {
         // Create image views
         let image_views = Self::create_image_views(device, &images, format.format)?;
         
-        info!("Swapchain created: {}x{}, {} images", extent.width, extent.height, images.len());
+        info!("Swapchain created: {}x{}, {} images, {:?}", extent.width, extent.height, images.len(), present_mode);
+        
+        // Semaphores ordering acquire -> render -> present
+        let semaphore_info = vk::SemaphoreCreateInfo::default();
+        let (image_available, render_finished) = unsafe {
+            (
+                device.handle().create_semaphore(&semaphore_info, None)?,
+                device.handle().create_semaphore(&semaphore_info, None)?,
+            )
+        };
+        
+        let present_wait = device
+            .supports_present_wait()
+            .then(|| ash::extensions::khr::PresentWait::new(instance.handle(), device.handle()));
         
         Ok(Self {
+            device: device.handle().clone(),
             swapchain_loader,
             swapchain,
+            surface,
+            present_mode: PresentMode::from_vk(present_mode),
+            present_queue: device.present_queue(),
+            present_wait,
+            last_present_id: None,
+            incremental_present: device.supports_incremental_present(),
+            damage: PresentDamage::new(),
+            image_available,
+            render_finished,
             images,
             image_views,
             format: format.format,
             extent,
             current_image: 0,
+            readable,
         })
     }
     
@@ -109,16 +219,19 @@ impl Swapchain {
         formats[0]
     }
     
-    fn choose_present_mode(present_modes: &[vk::PresentModeKHR]) -> vk::PresentModeKHR {
-        // Prefer mailbox for low latency and smooth rendering
-        for &mode in present_modes {
-            if mode == vk::PresentModeKHR::MAILBOX {
-                return mode;
-            }
-        }
-        
-        // Fallback to FIFO (always available)
-        vk::PresentModeKHR::FIFO
+    fn choose_present_mode(present_modes: &[vk::PresentModeKHR], preferred: PresentMode) -> vk::PresentModeKHR {
+        // Never fall back to a mode that tears when it was not asked for;
+        // FIFO is always available
+        let fallbacks: &[PresentMode] = match preferred {
+            PresentMode::Immediate => &[PresentMode::Immediate, PresentMode::Mailbox],
+            PresentMode::Mailbox => &[PresentMode::Mailbox],
+            PresentMode::Fifo => &[],
+        };
+        fallbacks
+            .iter()
+            .map(|mode| mode.to_vk())
+            .find(|mode| present_modes.contains(mode))
+            .unwrap_or(vk::PresentModeKHR::FIFO)
     }
     
     fn choose_extent(capabilities: &vk::SurfaceCapabilitiesKHR, width: u32, height: u32) -> vk::Extent2D {
@@ -162,13 +275,14 @@ impl Swapchain {
     }
     
     /// Acquire the next image for rendering
+    ///
+    /// Signals `image_available_semaphore()` once the image can be rendered to.
     pub fn acquire_next_image(&mut self) -> Result<u32> {
-        // Simplified - in real implementation would use semaphores
         let (image_index, _) = unsafe {
             self.swapchain_loader.acquire_next_image(
                 self.swapchain,
                 u64::MAX,
-                vk::Semaphore::null(),
+                self.image_available,
                 vk::Fence::null(),
             )?
         };
@@ -177,23 +291,122 @@ impl Swapchain {
         Ok(image_index)
     }
     
-    /// Present the current image
-    pub fn present(&self) -> Result<()> {
-        // Simplified - in real implementation would use proper queue submission
+    /// Present the current image once `render_finished_semaphore()` is signalled
+    ///
+    /// Returns the present ID when present IDs are in use.
+    pub fn present(&mut self) -> Result<Option<u64>> {
         let swapchains = [self.swapchain];
         let image_indices = [self.current_image];
+        let wait_semaphores = [self.render_finished];
+        
+        // Present IDs must increase monotonically per swapchain
+        let present_id = self
+            .present_wait
+            .as_ref()
+            .map(|_| self.last_present_id.map_or(1, |id| id + 1));
+        let present_ids = [present_id.unwrap_or(0)];
+        let present_id_info = vk::PresentIdKHR {
+            swapchain_count: 1,
+            p_present_ids: present_ids.as_ptr(),
+            ..Default::default()
+        };
         
-        let _present_info = vk::PresentInfoKHR {
+        let present_id_next = if present_id.is_some() {
+            &present_id_info as *const _ as *const std::ffi::c_void
+        } else {
+            std::ptr::null()
+        };
+        
+        // Changed regions, chained before the present ID
+        let damage = self.damage.take(self.extent).filter(|_| self.incremental_present);
+        let present_region = damage.as_ref().map(|rects| vk::PresentRegionKHR {
+            rectangle_count: rects.len() as u32,
+            p_rectangles: rects.as_ptr(),
+        });
+        let present_regions_info = present_region.as_ref().map(|region| vk::PresentRegionsKHR {
+            p_next: present_id_next,
+            swapchain_count: 1,
+            p_regions: region,
+            ..Default::default()
+        });
+        
+        let present_info = vk::PresentInfoKHR {
+            p_next: match &present_regions_info {
+                Some(info) => info as *const _ as *const std::ffi::c_void,
+                None => present_id_next,
+            },
+            wait_semaphore_count: wait_semaphores.len() as u32,
+            p_wait_semaphores: wait_semaphores.as_ptr(),
             swapchain_count: 1,
             p_swapchains: swapchains.as_ptr(),
             p_image_indices: image_indices.as_ptr(),
             ..Default::default()
         };
         
-        // Note: This should use the present queue from device
-        // For now, using a simplified approach
+        unsafe {
+            self.swapchain_loader.queue_present(self.present_queue, &present_info)?;
+        }
+        
+        if present_id.is_some() {
+            self.last_present_id = present_id;
+        }
+        Ok(present_id)
+    }
+    
+    /// Mark a region of the output, in image pixels, as changed for the next present
+    pub fn add_damage(&mut self, rect: [i32; 4]) {
+        self.damage.add(rect);
+    }
+    
+    /// Mark the whole output as changed for the next present
+    pub fn damage_all(&mut self) {
+        self.damage.add_full();
+    }
+    
+    /// Block until the present with the given ID is visible on the display
+    ///
+    /// Returns `Ok(false)` if `timeout` (nanoseconds) expires first or present
+    /// wait is not supported.
+    pub fn wait_for_present(&self, present_id: u64, timeout: u64) -> Result<bool> {
+        let Some(ref present_wait) = self.present_wait else {
+            return Ok(false);
+        };
         
-        Ok(())
+        match unsafe { present_wait.wait_for_present(self.swapchain, present_id, timeout) } {
+            Ok(()) => Ok(true),
+            Err(vk::Result::TIMEOUT) => Ok(false),
+            Err(e) => Err(CompositorError::graphics(format!("Failed to wait for present {}: {}", present_id, e))),
+        }
+    }
+    
+    /// ID of the most recent present, if present IDs are in use
+    pub fn last_present_id(&self) -> Option<u64> {
+        self.last_present_id
+    }
+    
+    /// Whether presents can be waited on for accurate timing
+    pub fn supports_present_wait(&self) -> bool {
+        self.present_wait.is_some()
+    }
+    
+    /// Whether frames can be read back from the images
+    pub fn supports_readback(&self) -> bool {
+        self.readable
+    }
+    
+    /// Index of the most recently acquired image
+    pub fn current_image(&self) -> u32 {
+        self.current_image
+    }
+    
+    /// Semaphore signalled when the acquired image is ready for rendering
+    pub fn image_available_semaphore(&self) -> vk::Semaphore {
+        self.image_available
+    }
+    
+    /// Semaphore the present waits on; signal it when rendering completes
+    pub fn render_finished_semaphore(&self) -> vk::Semaphore {
+        self.render_finished
     }
     
     /// Get current extent
@@ -215,11 +428,24 @@ impl Swapchain {
     pub fn format(&self) -> vk::Format {
         self.format
     }
+    
+    /// Present mode in use, which may differ from the requested one
+    pub fn present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
 }
 
 impl Drop for Swapchain {
     fn drop(&mut self) {
         // Note: In a real implementation, device should be passed for cleanup
+        unsafe {
+            self.device.destroy_semaphore(self.image_available, None);
+            self.device.destroy_semaphore(self.render_finished, None);
+            for &image_view in &self.image_views {
+                self.device.destroy_image_view(image_view, None);
+            }
+            self.swapchain_loader.destroy_swapchain(self.swapchain, None);
+        }
         info!("Swapchain destroyed");
     }
 }
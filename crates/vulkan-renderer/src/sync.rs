@@ -1,2 +1,156 @@
-// Synchronization primitives placeholder
-pub struct VulkanSync;
+// Synchronization primitives
+//
+// Timeline semaphores order GPU work across frames with a single
+// monotonically increasing counter instead of a fence per frame in flight.
+// Where the driver can export fences as sync files, a completion fence lets
+// event loops wait for submitted work alongside their other file descriptors.
+
+use ash::extensions::khr::ExternalFenceFd;
+use ash::vk;
+use compositor_utils::prelude::*;
+use crate::{VulkanDevice, VulkanInstance};
+use std::ffi::c_void;
+use std::os::fd::{FromRawFd, OwnedFd};
+
+/// Vulkan timeline semaphore
+pub struct TimelineSemaphore {
+    device: VulkanDevice,
+    semaphore: vk::Semaphore,
+}
+
+impl TimelineSemaphore {
+    /// Create a timeline semaphore starting at `initial_value`
+    pub fn new(device: &VulkanDevice, initial_value: u64) -> Result<Self> {
+        let type_info = vk::SemaphoreTypeCreateInfo {
+            semaphore_type: vk::SemaphoreType::TIMELINE,
+            initial_value,
+            ..Default::default()
+        };
+        let create_info = vk::SemaphoreCreateInfo {
+            p_next: &type_info as *const _ as *const c_void,
+            ..Default::default()
+        };
+
+        let semaphore = unsafe {
+            device.handle().create_semaphore(&create_info, None)
+                .map_err(|e| CompositorError::graphics(format!("Failed to create timeline semaphore: {}", e)))?
+        };
+
+        Ok(Self {
+            device: device.clone(),
+            semaphore,
+        })
+    }
+
+    /// Raw semaphore handle for queue submission
+    pub fn handle(&self) -> vk::Semaphore {
+        self.semaphore
+    }
+
+    /// Last value signalled by the GPU
+    pub fn value(&self) -> Result<u64> {
+        unsafe {
+            self.device.handle().get_semaphore_counter_value(self.semaphore)
+                .map_err(|e| CompositorError::graphics(format!("Failed to query timeline semaphore: {}", e)))
+        }
+    }
+
+    /// Block until the semaphore reaches `value` or `timeout` nanoseconds pass
+    pub fn wait(&self, value: u64, timeout: u64) -> Result<()> {
+        let semaphores = [self.semaphore];
+        let values = [value];
+        let wait_info = vk::SemaphoreWaitInfo {
+            semaphore_count: semaphores.len() as u32,
+            p_semaphores: semaphores.as_ptr(),
+            p_values: values.as_ptr(),
+            ..Default::default()
+        };
+
+        unsafe {
+            self.device.handle().wait_semaphores(&wait_info, timeout)
+                .map_err(|e| CompositorError::graphics(format!("Failed to wait for timeline value {}: {}", value, e)))
+        }
+    }
+}
+
+impl Drop for TimelineSemaphore {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.handle().destroy_semaphore(self.semaphore, None);
+        }
+    }
+}
+
+/// Fence signalled by a submission and exported as a sync file
+///
+/// The sync file becomes readable once the submission completes. Exporting
+/// resets the fence, so it can be passed to the next submission right away.
+pub struct CompletionFence {
+    device: VulkanDevice,
+    fence: vk::Fence,
+    external_fence_fd: ExternalFenceFd,
+}
+
+impl CompletionFence {
+    /// Create a fence exportable as a sync file; the device must support
+    /// fence fd export
+    pub fn new(instance: &VulkanInstance, device: &VulkanDevice) -> Result<Self> {
+        let export_info = vk::ExportFenceCreateInfo {
+            handle_types: vk::ExternalFenceHandleTypeFlags::SYNC_FD,
+            ..Default::default()
+        };
+        let create_info = vk::FenceCreateInfo {
+            p_next: &export_info as *const _ as *const c_void,
+            ..Default::default()
+        };
+
+        let fence = unsafe {
+            device.handle().create_fence(&create_info, None)
+                .map_err(|e| CompositorError::graphics(format!("Failed to create completion fence: {}", e)))?
+        };
+
+        Ok(Self {
+            device: device.clone(),
+            fence,
+            external_fence_fd: ExternalFenceFd::new(instance.handle(), device.handle()),
+        })
+    }
+
+    /// Raw fence handle for queue submission
+    pub fn handle(&self) -> vk::Fence {
+        self.fence
+    }
+
+    /// Export the signal of the submission the fence was passed to
+    ///
+    /// Returns `None` when the submission already completed. On failure the
+    /// fence is waited on and reset, so it can still be submitted again.
+    pub fn export(&self) -> Result<Option<OwnedFd>> {
+        let get_info = vk::FenceGetFdInfoKHR {
+            fence: self.fence,
+            handle_type: vk::ExternalFenceHandleTypeFlags::SYNC_FD,
+            ..Default::default()
+        };
+
+        match unsafe { self.external_fence_fd.get_fence_fd(&get_info) } {
+            // A signalled fence may be exported as -1 instead of a sync file
+            Ok(fd) if fd < 0 => Ok(None),
+            Ok(fd) => Ok(Some(unsafe { OwnedFd::from_raw_fd(fd) })),
+            Err(e) => {
+                unsafe {
+                    self.device.handle().wait_for_fences(&[self.fence], true, u64::MAX)?;
+                    self.device.handle().reset_fences(&[self.fence])?;
+                }
+                Err(CompositorError::graphics(format!("Failed to export completion fence: {}", e)))
+            }
+        }
+    }
+}
+
+impl Drop for CompletionFence {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.handle().destroy_fence(self.fence, None);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// Compositor-drawn UI
+//
+// Overlays the compositor draws itself, such as the dwell click countdown,
+// dialogs and the color picker loupe, are handed to the renderer as a list
+// of primitives in global compositor coordinates, back to front. The UI pass
+// draws those overlapping the output being rendered on top of its surfaces.
+// Shapes are evaluated analytically in the fragment shader; text and other
+// content is rasterized on the CPU and drawn as images.
+
+use compositor_utils::math::Rect;
+use compositor_utils::prelude::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Softness of drop shadows, as the standard deviation of their blur in pixels
+pub const DROP_SHADOW_SIGMA: f32 = 12.0;
+
+/// How far drop shadows fall below what casts them, in pixels
+pub const DROP_SHADOW_OFFSET: f32 = 6.0;
+
+/// How an image is sampled when drawn at another size than its own
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UiFilter {
+    #[default]
+    Linear,
+    /// Keeps pixels sharp when magnified, e.g. in the color picker loupe
+    Nearest,
+}
+
+/// Image rasterized on the CPU for the UI pass, e.g. a text label
+///
+/// Images are uploaded once and identified by an ID assigned on creation,
+/// so a new image is needed when the content changes.
+#[derive(Debug)]
+pub struct UiImage {
+    id: u32,
+    width: u32,
+    height: u32,
+    /// Straight-alpha RGBA8 pixels, row by row
+    pixels: Vec<u8>,
+    filter: UiFilter,
+}
+
+impl UiImage {
+    /// Wrap RGBA8 pixels with straight alpha
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>, filter: UiFilter) -> Result<Arc<Self>> {
+        if width == 0 || height == 0 || pixels.len() != width as usize * height as usize * 4 {
+            return Err(CompositorError::graphics(format!(
+                "{} bytes are not a {}x{} RGBA image",
+                pixels.len(),
+                width,
+                height,
+            )));
+        }
+        static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+        Ok(Arc::new(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            width,
+            height,
+            pixels,
+            filter,
+        }))
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn filter(&self) -> UiFilter {
+        self.filter
+    }
+}
+
+impl PartialEq for UiImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+/// Shape drawn by the UI pass, in global compositor coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub enum UiPrimitive {
+    /// Rounded rectangle filled with a color
+    Rect { rect: Rect, color: [f32; 4], corner_radius: f32 },
+    /// Border along the inside of a rounded rectangle
+    Outline { rect: Rect, width: f32, color: [f32; 4], corner_radius: f32 },
+    /// Arc of a ring from the top clockwise, `progress` (0.0-1.0) of the way round
+    Ring { center: Vec2, radius: f32, thickness: f32, progress: f32, color: [f32; 4] },
+    /// Soft shadow of a rounded rectangle, blurred with standard deviation `sigma`
+    Shadow { rect: Rect, corner_radius: f32, sigma: f32, color: [f32; 4] },
+    /// Image stretched over a rectangle, its colors multiplied by `tint`
+    Image { rect: Rect, image: Arc<UiImage>, tint: [f32; 4] },
+}
+
+impl UiPrimitive {
+    /// Drop shadow below a rounded rectangle, `intensity` (0.0-1.0) being its opacity
+    pub fn drop_shadow(rect: Rect, corner_radius: f32, intensity: f32) -> Self {
+        Self::Shadow {
+            rect: Rect::new(rect.x, rect.y + DROP_SHADOW_OFFSET, rect.width, rect.height),
+            corner_radius,
+            sigma: DROP_SHADOW_SIGMA,
+            color: [0.0, 0.0, 0.0, intensity.clamp(0.0, 1.0)],
+        }
+    }
+
+    /// Area the primitive draws into
+    pub fn bounds(&self) -> Rect {
+        match self {
+            Self::Rect { rect, .. } | Self::Outline { rect, .. } | Self::Image { rect, .. } => *rect,
+            Self::Ring { center, radius, .. } => {
+                Rect::new(center.x - radius, center.y - radius, radius * 2.0, radius * 2.0)
+            }
+            // A Gaussian is negligible beyond three standard deviations
+            Self::Shadow { rect, sigma, .. } => {
+                let spread = sigma * 3.0;
+                Rect::new(rect.x - spread, rect.y - spread, rect.width + spread * 2.0, rect.height + spread * 2.0)
+            }
+        }
+    }
+
+    /// Image the primitive samples, if any
+    pub fn image(&self) -> Option<&Arc<UiImage>> {
+        match self {
+            Self::Image { image, .. } => Some(image),
+            _ => None,
+        }
+    }
+}
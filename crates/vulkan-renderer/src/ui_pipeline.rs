@@ -0,0 +1,464 @@
+// Vulkan rendering pipeline for compositor-drawn UI
+//
+// Every UI primitive is drawn as one quad generated in the vertex shader, so
+// the pipeline has no vertex input. The fragment shader evaluates the shape
+// selected by the push constants, or samples the bound image.
+
+use ash::vk;
+use compositor_utils::math::Rect;
+use compositor_utils::prelude::*;
+use crate::ui::{UiFilter, UiPrimitive};
+use crate::VulkanDevice;
+
+/// Shape selectors matching the constants in ui.frag
+pub const SHAPE_RECT: u32 = 0;
+pub const SHAPE_RING: u32 = 1;
+pub const SHAPE_SHADOW: u32 = 2;
+pub const SHAPE_IMAGE: u32 = 3;
+
+/// Width in pixels over which shape edges are antialiased
+const EDGE_SOFTNESS: f32 = 1.0;
+
+/// Graphics pipeline for drawing UI primitives
+pub struct UiPipeline {
+    device: VulkanDevice,
+    pipeline: vk::Pipeline,
+    pipeline_layout: vk::PipelineLayout,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    linear_sampler: vk::Sampler,
+    nearest_sampler: vk::Sampler,
+    vertex_shader: vk::ShaderModule,
+    fragment_shader: vk::ShaderModule,
+}
+
+/// Push constants for one UI primitive
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UiPushConstants {
+    pub rect: [f32; 4],          // Quad drawn, x/y/width/height in compositor coordinates
+    pub region: [f32; 4],        // Output region the frame covers
+    pub color: [f32; 4],         // Color, or image tint
+    pub shape_rect: [f32; 4],    // Rectangle the shape is evaluated against
+    pub uv_rect: [f32; 4],       // Part of the image sampled, in texture coordinates
+    pub corner_radius: f32,      // Rounded corner radius in pixels
+    pub border_width: f32,       // Draws only a border this wide when non-zero
+    pub edge_softness: f32,      // Analytic antialiasing width in pixels
+    pub sigma: f32,              // Shadow blur standard deviation
+    pub shape: u32,              // One of the SHAPE_* selectors
+    pub progress: f32,           // Filled fraction of a ring
+    pub thickness: f32,          // Ring thickness in pixels
+    pub _padding: f32,           // Rounds the block up to a multiple of 16 bytes
+}
+
+impl UiPushConstants {
+    /// Push constants drawing `primitive` into a frame covering `region`
+    pub fn for_primitive(primitive: &UiPrimitive, region: Rect) -> Self {
+        let base = Self {
+            region: rect_array(region),
+            uv_rect: [0.0, 0.0, 1.0, 1.0],
+            edge_softness: EDGE_SOFTNESS,
+            ..Default::default()
+        };
+        match primitive {
+            UiPrimitive::Rect { rect, color, corner_radius } => Self {
+                rect: rect_array(*rect),
+                shape_rect: rect_array(*rect),
+                color: *color,
+                corner_radius: *corner_radius,
+                shape: SHAPE_RECT,
+                ..base
+            },
+            UiPrimitive::Outline { rect, width, color, corner_radius } => Self {
+                rect: rect_array(*rect),
+                shape_rect: rect_array(*rect),
+                color: *color,
+                corner_radius: *corner_radius,
+                border_width: width.max(0.0),
+                shape: SHAPE_RECT,
+                ..base
+            },
+            UiPrimitive::Ring { thickness, progress, color, .. } => Self {
+                rect: rect_array(primitive.bounds()),
+                shape_rect: rect_array(primitive.bounds()),
+                color: *color,
+                thickness: *thickness,
+                progress: progress.clamp(0.0, 1.0),
+                shape: SHAPE_RING,
+                ..base
+            },
+            UiPrimitive::Shadow { rect, corner_radius, sigma, color } => Self {
+                rect: rect_array(primitive.bounds()),
+                shape_rect: rect_array(*rect),
+                color: *color,
+                corner_radius: corner_radius.min(rect.width * 0.5).min(rect.height * 0.5),
+                sigma: *sigma,
+                shape: SHAPE_SHADOW,
+                ..base
+            },
+            UiPrimitive::Image { rect, tint, .. } => Self {
+                rect: rect_array(*rect),
+                shape_rect: rect_array(*rect),
+                color: *tint,
+                shape: SHAPE_IMAGE,
+                ..base
+            },
+        }
+    }
+
+    /// Push constants drawing part of a blurred backdrop over `rect`, clipped
+    /// to the rounded rectangle of the surface in front of it
+    ///
+    /// `uv_rect` is the part of the backdrop image under `rect`.
+    pub fn for_backdrop(rect: Rect, uv_rect: [f32; 4], clip: Rect, corner_radius: f32, region: Rect) -> Self {
+        Self {
+            rect: rect_array(rect),
+            region: rect_array(region),
+            color: [1.0; 4],
+            shape_rect: rect_array(clip),
+            uv_rect,
+            corner_radius,
+            edge_softness: EDGE_SOFTNESS,
+            shape: SHAPE_IMAGE,
+            ..Default::default()
+        }
+    }
+}
+
+fn rect_array(rect: Rect) -> [f32; 4] {
+    [rect.x, rect.y, rect.width, rect.height]
+}
+
+impl UiPipeline {
+    /// Create the UI pipeline for a render pass with `samples` per pixel
+    pub fn new(
+        device: VulkanDevice,
+        render_pass: vk::RenderPass,
+        pipeline_cache: vk::PipelineCache,
+        samples: vk::SampleCountFlags,
+    ) -> Result<Self> {
+        Self::with_blend(device, render_pass, pipeline_cache, samples, false)
+    }
+
+    /// Create a pipeline blending images of premultiplied color, such as UI
+    /// drawn into a transparent layer, over a single-sampled render pass
+    pub fn premultiplied(
+        device: VulkanDevice,
+        render_pass: vk::RenderPass,
+        pipeline_cache: vk::PipelineCache,
+    ) -> Result<Self> {
+        Self::with_blend(device, render_pass, pipeline_cache, vk::SampleCountFlags::TYPE_1, true)
+    }
+
+    fn with_blend(
+        device: VulkanDevice,
+        render_pass: vk::RenderPass,
+        pipeline_cache: vk::PipelineCache,
+        samples: vk::SampleCountFlags,
+        premultiplied: bool,
+    ) -> Result<Self> {
+        info!("Creating UI rendering pipeline ({:?} samples)", samples);
+
+        let vertex_shader = Self::create_shader_module(&device, "ui.vert.spv")?;
+        let fragment_shader = Self::create_shader_module(&device, "ui.frag.spv")?;
+        let descriptor_set_layout = Self::create_descriptor_set_layout(&device)?;
+        let pipeline_layout = Self::create_pipeline_layout(&device, descriptor_set_layout)?;
+        let pipeline = Self::create_graphics_pipeline(
+            &device,
+            vertex_shader,
+            fragment_shader,
+            pipeline_layout,
+            render_pass,
+            pipeline_cache,
+            samples,
+            premultiplied,
+        )?;
+        let linear_sampler = Self::create_sampler(&device, vk::Filter::LINEAR)?;
+        let nearest_sampler = Self::create_sampler(&device, vk::Filter::NEAREST)?;
+
+        Ok(Self {
+            device,
+            pipeline,
+            pipeline_layout,
+            descriptor_set_layout,
+            linear_sampler,
+            nearest_sampler,
+            vertex_shader,
+            fragment_shader,
+        })
+    }
+
+    /// Get the pipeline handle
+    pub fn pipeline(&self) -> vk::Pipeline {
+        self.pipeline
+    }
+
+    /// Get the pipeline layout
+    pub fn pipeline_layout(&self) -> vk::PipelineLayout {
+        self.pipeline_layout
+    }
+
+    /// Get the descriptor set layout
+    pub fn descriptor_set_layout(&self) -> vk::DescriptorSetLayout {
+        self.descriptor_set_layout
+    }
+
+    /// Sampler for images drawn with `filter`
+    pub fn sampler(&self, filter: UiFilter) -> vk::Sampler {
+        match filter {
+            UiFilter::Linear => self.linear_sampler,
+            UiFilter::Nearest => self.nearest_sampler,
+        }
+    }
+
+    /// Record the draw of one primitive; the image descriptor set must be bound
+    pub fn draw(&self, command_buffer: vk::CommandBuffer, push_constants: &UiPushConstants) {
+        unsafe {
+            self.device.handle().cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                0,
+                std::slice::from_raw_parts(
+                    push_constants as *const UiPushConstants as *const u8,
+                    std::mem::size_of::<UiPushConstants>(),
+                ),
+            );
+            self.device.handle().cmd_draw(command_buffer, 4, 1, 0, 0);
+        }
+    }
+
+    /// Create shader module from SPIR-V bytecode
+    fn create_shader_module(device: &VulkanDevice, filename: &str) -> Result<vk::ShaderModule> {
+        let spirv_bytes: &[u8] = match filename {
+            "ui.vert.spv" => include_bytes!(concat!(env!("OUT_DIR"), "/shaders/ui.vert.spv")),
+            "ui.frag.spv" => include_bytes!(concat!(env!("OUT_DIR"), "/shaders/ui.frag.spv")),
+            _ => return Err(CompositorError::graphics(format!("Unknown shader: {}", filename))),
+        };
+
+        let spirv_words: Vec<u32> = spirv_bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+
+        if spirv_words.is_empty() {
+            return Err(CompositorError::graphics(format!("Empty SPIR-V file: {}", filename)));
+        }
+
+        let create_info = vk::ShaderModuleCreateInfo {
+            code_size: spirv_bytes.len(),
+            p_code: spirv_words.as_ptr(),
+            ..Default::default()
+        };
+
+        unsafe {
+            device.handle().create_shader_module(&create_info, None)
+                .map_err(|e| CompositorError::graphics(format!("Failed to create shader module {}: {}", filename, e)))
+        }
+    }
+
+    /// Create descriptor set layout for the sampled image
+    fn create_descriptor_set_layout(device: &VulkanDevice) -> Result<vk::DescriptorSetLayout> {
+        let bindings = [
+            vk::DescriptorSetLayoutBinding {
+                binding: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                p_immutable_samplers: std::ptr::null(),
+            },
+        ];
+
+        let layout_info = vk::DescriptorSetLayoutCreateInfo {
+            binding_count: bindings.len() as u32,
+            p_bindings: bindings.as_ptr(),
+            ..Default::default()
+        };
+
+        unsafe {
+            device.handle().create_descriptor_set_layout(&layout_info, None)
+                .map_err(|e| CompositorError::graphics(format!("Failed to create UI descriptor set layout: {}", e)))
+        }
+    }
+
+    /// Create pipeline layout with push constants
+    fn create_pipeline_layout(
+        device: &VulkanDevice,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+    ) -> Result<vk::PipelineLayout> {
+        let set_layouts = [descriptor_set_layout];
+        let push_constant_ranges = [
+            vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                offset: 0,
+                size: std::mem::size_of::<UiPushConstants>() as u32,
+            },
+        ];
+
+        let pipeline_layout_info = vk::PipelineLayoutCreateInfo {
+            set_layout_count: set_layouts.len() as u32,
+            p_set_layouts: set_layouts.as_ptr(),
+            push_constant_range_count: push_constant_ranges.len() as u32,
+            p_push_constant_ranges: push_constant_ranges.as_ptr(),
+            ..Default::default()
+        };
+
+        unsafe {
+            device.handle().create_pipeline_layout(&pipeline_layout_info, None)
+                .map_err(|e| CompositorError::graphics(format!("Failed to create UI pipeline layout: {}", e)))
+        }
+    }
+
+    /// Create a clamped sampler filtering with `filter`
+    fn create_sampler(device: &VulkanDevice, filter: vk::Filter) -> Result<vk::Sampler> {
+        let sampler_info = vk::SamplerCreateInfo {
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+            address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            max_lod: 0.0,
+            ..Default::default()
+        };
+
+        unsafe {
+            device.handle().create_sampler(&sampler_info, None)
+                .map_err(|e| CompositorError::graphics(format!("Failed to create UI sampler: {}", e)))
+        }
+    }
+
+    /// Create the graphics pipeline
+    #[allow(clippy::too_many_arguments)]
+    fn create_graphics_pipeline(
+        device: &VulkanDevice,
+        vertex_shader: vk::ShaderModule,
+        fragment_shader: vk::ShaderModule,
+        pipeline_layout: vk::PipelineLayout,
+        render_pass: vk::RenderPass,
+        pipeline_cache: vk::PipelineCache,
+        samples: vk::SampleCountFlags,
+        premultiplied: bool,
+    ) -> Result<vk::Pipeline> {
+        let main_function_name = std::ffi::CString::new("main").unwrap();
+
+        let shader_stages = [
+            vk::PipelineShaderStageCreateInfo {
+                stage: vk::ShaderStageFlags::VERTEX,
+                module: vertex_shader,
+                p_name: main_function_name.as_ptr(),
+                ..Default::default()
+            },
+            vk::PipelineShaderStageCreateInfo {
+                stage: vk::ShaderStageFlags::FRAGMENT,
+                module: fragment_shader,
+                p_name: main_function_name.as_ptr(),
+                ..Default::default()
+            },
+        ];
+
+        // Quad corners come from gl_VertexIndex
+        let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default();
+
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo {
+            topology: vk::PrimitiveTopology::TRIANGLE_STRIP,
+            primitive_restart_enable: vk::FALSE,
+            ..Default::default()
+        };
+
+        let viewport_state = vk::PipelineViewportStateCreateInfo {
+            viewport_count: 1,
+            scissor_count: 1,
+            ..Default::default()
+        };
+
+        // The strip's winding alternates, so nothing is culled
+        let rasterizer = vk::PipelineRasterizationStateCreateInfo {
+            depth_clamp_enable: vk::FALSE,
+            rasterizer_discard_enable: vk::FALSE,
+            polygon_mode: vk::PolygonMode::FILL,
+            line_width: 1.0,
+            cull_mode: vk::CullModeFlags::NONE,
+            front_face: vk::FrontFace::CLOCKWISE,
+            depth_bias_enable: vk::FALSE,
+            ..Default::default()
+        };
+
+        let multisampling = vk::PipelineMultisampleStateCreateInfo {
+            sample_shading_enable: vk::FALSE,
+            rasterization_samples: samples,
+            ..Default::default()
+        };
+
+        // Drawing into a transparent target leaves its color premultiplied
+        let src_color_blend_factor = if premultiplied { vk::BlendFactor::ONE } else { vk::BlendFactor::SRC_ALPHA };
+        let color_blend_attachment = vk::PipelineColorBlendAttachmentState {
+            color_write_mask: vk::ColorComponentFlags::RGBA,
+            blend_enable: vk::TRUE,
+            src_color_blend_factor,
+            dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ONE,
+            dst_alpha_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            alpha_blend_op: vk::BlendOp::ADD,
+        };
+
+        let color_blending = vk::PipelineColorBlendStateCreateInfo {
+            logic_op_enable: vk::FALSE,
+            logic_op: vk::LogicOp::COPY,
+            attachment_count: 1,
+            p_attachments: &color_blend_attachment,
+            blend_constants: [0.0, 0.0, 0.0, 0.0],
+            ..Default::default()
+        };
+
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo {
+            dynamic_state_count: dynamic_states.len() as u32,
+            p_dynamic_states: dynamic_states.as_ptr(),
+            ..Default::default()
+        };
+
+        let pipeline_info = vk::GraphicsPipelineCreateInfo {
+            stage_count: shader_stages.len() as u32,
+            p_stages: shader_stages.as_ptr(),
+            p_vertex_input_state: &vertex_input_info,
+            p_input_assembly_state: &input_assembly,
+            p_viewport_state: &viewport_state,
+            p_rasterization_state: &rasterizer,
+            p_multisample_state: &multisampling,
+            p_color_blend_state: &color_blending,
+            p_dynamic_state: &dynamic_state,
+            layout: pipeline_layout,
+            render_pass,
+            subpass: 0,
+            base_pipeline_handle: vk::Pipeline::null(),
+            base_pipeline_index: -1,
+            ..Default::default()
+        };
+
+        let pipelines = unsafe {
+            device.handle().create_graphics_pipelines(
+                pipeline_cache,
+                &[pipeline_info],
+                None,
+            ).map_err(|e| CompositorError::graphics(format!("Failed to create UI pipeline: {:?}", e)))?
+        };
+
+        Ok(pipelines[0])
+    }
+}
+
+impl Drop for UiPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.handle().destroy_pipeline(self.pipeline, None);
+            self.device.handle().destroy_pipeline_layout(self.pipeline_layout, None);
+            self.device.handle().destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            self.device.handle().destroy_sampler(self.linear_sampler, None);
+            self.device.handle().destroy_sampler(self.nearest_sampler, None);
+            self.device.handle().destroy_shader_module(self.vertex_shader, None);
+            self.device.handle().destroy_shader_module(self.fragment_shader, None);
+        }
+        debug!("UI pipeline cleanup complete");
+    }
+}
@@ -0,0 +1,177 @@
+// Coalescing of surface uploads under load
+//
+// Clients can commit buffers faster than frames are drawn. Committed content
+// is queued per surface and uploaded at the start of the next frame, so a
+// newer commit replaces content that was never uploaded instead of adding to
+// the work; the queue never holds more than one buffer per surface.
+//
+// While frames run over their budget, uploads are ordered by how much they
+// matter: the focused surface first, then visible surfaces from the top of
+// the stack down. Only a limited number of bytes is uploaded per frame and
+// hidden surfaces wait until the load eases, so a burst of commits costs
+// background windows a few frames of latency rather than dropping frames.
+
+use ash::vk;
+use compositor_utils::prelude::*;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Bytes uploaded per frame while over budget; the first upload of a frame
+/// always goes through, however large
+pub const UPLOAD_BYTES_UNDER_LOAD: usize = 32 * 1024 * 1024;
+
+/// Consecutive frames within budget before uploads stop being limited
+pub const RECOVERY_FRAMES: u32 = 30;
+
+/// Longest a hidden surface's content waits while over budget
+pub const MAX_HIDDEN_DELAY: Duration = Duration::from_millis(500);
+
+/// Surface content waiting to be uploaded
+#[derive(Debug)]
+pub struct QueuedUpload {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub format: vk::Format,
+    /// When the oldest content this upload replaced was queued
+    queued_at: Instant,
+}
+
+impl QueuedUpload {
+    pub fn new(data: Vec<u8>, width: u32, height: u32, format: vk::Format, now: Instant) -> Self {
+        Self { data, width, height, format, queued_at: now }
+    }
+}
+
+/// How urgently a surface's content is uploaded while over budget, most
+/// urgent first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UploadPriority {
+    Focused,
+    /// Visible, this many surfaces below the top of the stack
+    Visible(usize),
+    Hidden,
+}
+
+/// Surface uploads waiting for the next frame
+#[derive(Debug, Default)]
+pub struct UploadQueue {
+    pending: HashMap<u32, QueuedUpload>,
+    /// Time a frame may take; `None` never limits uploads
+    frame_budget: Option<Duration>,
+    under_load: bool,
+    /// Consecutive frames within budget while under load
+    frames_within_budget: u32,
+    /// Queued contents replaced by a newer commit before being uploaded
+    coalesced: u64,
+}
+
+impl UploadQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a surface's content, replacing content not uploaded yet
+    pub fn queue(&mut self, surface_id: u32, mut upload: QueuedUpload) {
+        if let Some(replaced) = self.pending.remove(&surface_id) {
+            upload.queued_at = replaced.queued_at;
+            self.coalesced += 1;
+        }
+        self.pending.insert(surface_id, upload);
+    }
+
+    /// Whether content of the surface is waiting
+    pub fn contains(&self, surface_id: u32) -> bool {
+        self.pending.contains_key(&surface_id)
+    }
+
+    /// Drop the content of a removed surface
+    pub fn remove(&mut self, surface_id: u32) {
+        self.pending.remove(&surface_id);
+    }
+
+    /// Set the time a frame may take, e.g. the output's refresh interval
+    pub fn set_frame_budget(&mut self, budget: Option<Duration>) {
+        self.frame_budget = budget;
+        if budget.is_none() {
+            self.under_load = false;
+        }
+    }
+
+    /// Record how long a frame took from starting to record to submission
+    pub fn frame_finished(&mut self, elapsed: Duration) {
+        let Some(budget) = self.frame_budget else {
+            return;
+        };
+        if elapsed > budget {
+            if !self.under_load {
+                debug!("Frame took {:?} of a {:?} budget, limiting surface uploads", elapsed, budget);
+            }
+            self.under_load = true;
+            self.frames_within_budget = 0;
+        } else if self.under_load {
+            self.frames_within_budget += 1;
+            if self.frames_within_budget >= RECOVERY_FRAMES {
+                debug!("Frames back within budget, no longer limiting surface uploads");
+                self.under_load = false;
+            }
+        }
+    }
+
+    /// Whether recent frames ran over budget
+    pub fn is_under_load(&self) -> bool {
+        self.under_load
+    }
+
+    /// Number of surfaces with content waiting
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Queued contents replaced before being uploaded so far
+    pub fn coalesced_count(&self) -> u64 {
+        self.coalesced
+    }
+
+    /// Take the uploads for a frame started at `now`, most urgent first
+    ///
+    /// Takes everything unless recent frames ran over budget; the rest stays
+    /// queued for later frames.
+    pub fn take_batch(&mut self, now: Instant, priority: impl Fn(u32) -> UploadPriority) -> Vec<(u32, QueuedUpload)> {
+        if !self.under_load {
+            return self.pending.drain().collect();
+        }
+
+        // Hidden content that waited too long goes after the visible surfaces
+        let mut order: Vec<(UploadPriority, u32)> = self
+            .pending
+            .iter()
+            .filter_map(|(&surface_id, upload)| match priority(surface_id) {
+                UploadPriority::Hidden if now.saturating_duration_since(upload.queued_at) < MAX_HIDDEN_DELAY => None,
+                UploadPriority::Hidden => Some((UploadPriority::Visible(usize::MAX), surface_id)),
+                urgency => Some((urgency, surface_id)),
+            })
+            .collect();
+        order.sort_unstable();
+
+        let mut batch = Vec::new();
+        let mut bytes = 0;
+        for (_, surface_id) in order {
+            if !batch.is_empty() && bytes >= UPLOAD_BYTES_UNDER_LOAD {
+                break;
+            }
+            if let Some(upload) = self.pending.remove(&surface_id) {
+                bytes += upload.data.len();
+                batch.push((surface_id, upload));
+            }
+        }
+        if !self.pending.is_empty() {
+            debug!("Deferred {} surface uploads to later frames", self.pending.len());
+        }
+        batch
+    }
+}
@@ -3,9 +3,18 @@
 
 use compositor_utils::prelude::*;
 use compositor_core::Compositor;
+use compositor_core::benchmark::BenchmarkScenario;
+use compositor_core::doctor::{self, CheckStatus};
+use config::ConfigManager;
+use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // `doctor` only reports on the system and never starts the compositor
+    if std::env::args().nth(1).as_deref() == Some("doctor") {
+        return run_doctor();
+    }
+    
     // Initialize logging system
     compositor_utils::setup_logging()?;
     
@@ -15,16 +24,53 @@ async fn main() -> anyhow::Result<()> {
     // Print system information
     print_system_info();
     
+    // Load the benchmark scenario before anything else so typos fail fast
+    let benchmark = match benchmark_scenario_path() {
+        Some(path) => Some(BenchmarkScenario::load_from_file(&path)
+            .with_context(|| format!("Failed to load benchmark scenario {}", path))?),
+        None => None,
+    };
+    
+    // Load the system and user configuration and environment overrides
+    let mut config_manager = ConfigManager::new(None).await
+        .context("Failed to load configuration")?;
+    if let Err(e) = config_manager.enable_hot_reload().await {
+        warn!("Configuration changes will not be applied until restart: {}", e);
+    }
+    let config = config_manager.get_config().await;
+    
     // Create and run compositor
-    let compositor = Compositor::new().await
+    let mut compositor = Compositor::new(&config).await
         .context("Failed to create compositor")?;
     
+    if let Some(scenario) = benchmark {
+        let report = compositor.run_benchmark(&scenario)?;
+        println!("{}", report.to_ron()?);
+        compositor.shutdown().await?;
+        return Ok(());
+    }
+    
     // Display connection information
     if let Some(socket_name) = compositor.wayland_socket_name() {
         info!("Wayland socket available: {}", socket_name);
         info!("Clients can connect with: WAYLAND_DISPLAY={}", socket_name);
     }
     
+    compositor.apply_config(&config);
+    
+    // Follow configuration changes, and reload when asked to over IPC
+    let (reload_requests, mut reloads) = tokio::sync::mpsc::unbounded_channel();
+    compositor.follow_config(config_manager.subscribe_to_changes(), reload_requests);
+    let config_manager = Arc::new(config_manager);
+    let reloader = config_manager.clone();
+    tokio::spawn(async move {
+        while reloads.recv().await.is_some() {
+            if let Err(e) = reloader.reload().await {
+                warn!("Failed to reload configuration: {}", e);
+            }
+        }
+    });
+    
     info!("Compositor created successfully, starting main loop");
     
     // Run the compositor (this consumes self and handles its own cleanup)
@@ -37,6 +83,31 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Print the result of every prerequisite check; fails if any check failed
+fn run_doctor() -> anyhow::Result<()> {
+    let diagnostics = doctor::run_all();
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+    
+    if doctor::has_failures(&diagnostics) {
+        anyhow::bail!("System prerequisites are not met");
+    }
+    println!("All required prerequisites are met");
+    Ok(())
+}
+
+/// Scenario file given with `--benchmark <scenario.ron>`
+fn benchmark_scenario_path() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--benchmark" {
+            return args.next();
+        }
+    }
+    None
+}
+
 fn print_system_info() {
     info!("System Information:");
     
@@ -69,21 +140,16 @@ fn print_system_info() {
 }
 
 fn check_permissions() {
-    // Check if we can access DRM devices
-    let drm_paths = ["/dev/dri/card0", "/dev/dri/card1"];
-    for path in &drm_paths {
-        match std::fs::metadata(path) {
-            Ok(_) => info!("  DRM device accessible: {}", path),
-            Err(_) => warn!("  DRM device not accessible: {}", path),
-        }
-    }
-    
-    // Check for input device access
-    match std::fs::read_dir("/dev/input") {
-        Ok(entries) => {
-            let count = entries.count();
-            info!("  Input devices found: {}", count);
+    // The full report, including Vulkan, is available with `doctor`
+    for diagnostic in doctor::run_permission_checks() {
+        match diagnostic.status {
+            CheckStatus::Pass => info!("  {}: {}", diagnostic.name, diagnostic.detail),
+            CheckStatus::Warn | CheckStatus::Fail => {
+                warn!("  {}: {}", diagnostic.name, diagnostic.detail);
+                if let Some(remediation) = &diagnostic.remediation {
+                    warn!("    {}", remediation);
+                }
+            }
         }
-        Err(e) => warn!("  Cannot access input devices: {}", e),
     }
 }